@@ -0,0 +1,151 @@
+//! Embeddable token agent.
+//!
+//! Runs the same refresh loops, sinks and HTTP server as the `token-agent` binary
+//! inside another tokio service:
+//!
+//! ```ignore
+//! let handle = TokenAgent::from_config(service_config).await?.start()?;
+//! let token = handle.get_token("metadata", "metadata_token").await;
+//! handle.shutdown().await?;
+//! ```
+
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info};
+
+use crate::cache::persistent_cache::PersistentCache;
+use crate::cache::token::Token;
+use crate::cache::token_cache::TokenCache;
+use crate::cache::token_event::TokenSubscription;
+use crate::config::proc_initiateor::initiate_default_values;
+use crate::config::proc_validator::{check_service_config, format_issues};
+use crate::config::sources::ServiceConfig;
+use crate::sinks::sink_check::CheckSummary;
+use crate::sinks::sink_dry_run::DryRunSummary;
+use crate::utils::app;
+use crate::utils::shutdown::{self, SHUTDOWN_GRACE_PERIOD_SECONDS_DEFAULT};
+
+pub struct TokenAgent {
+    service_config: ServiceConfig,
+    config_path: Option<String>,
+    shutdown: CancellationToken,
+}
+
+impl TokenAgent {
+    /// Inject config defaults and validate it, all validation errors are returned in one error
+    pub async fn from_config(service_config: ServiceConfig) -> Result<Self> {
+        let service_config = initiate_default_values(service_config);
+        check_service_config(&service_config)
+            .await
+            .map_err(|errors| anyhow!(format_issues(&errors)))?;
+        Ok(Self { service_config, config_path: None, shutdown: CancellationToken::new() })
+    }
+
+    /// Reload config from this file on SIGHUP or, with `settings.reload`, on file change
+    pub fn with_config_path(mut self, config_path: &str) -> Self {
+        self.config_path = Some(config_path.to_owned());
+        self
+    }
+
+    /// Stop agent when this token is cancelled, e.g. by signal handling of the embedding service
+    pub fn with_shutdown(mut self, shutdown: CancellationToken) -> Self {
+        self.shutdown = shutdown;
+        self
+    }
+
+    /// Fetch every source once and write file sinks, then return; nothing is left running.
+    /// Error lists sources failed after retries and file sinks not written
+    pub async fn run_once(self) -> Result<()> {
+        app::run_oneshot(&self.service_config).await
+    }
+
+    /// Fetch every source once and report what each sink would write; no sink is written,
+    /// HTTP server and loops are not started
+    pub async fn dry_run(self) -> Result<DryRunSummary> {
+        app::run_dry_run(&self.service_config).await
+    }
+
+    /// Fetch every source once and check that each sink has a live token and each file sink holds it;
+    /// nothing is written, HTTP server, metrics and loops are not started
+    pub async fn check(self) -> Result<CheckSummary> {
+        app::run_check(&self.service_config).await
+    }
+
+    /// Spawn agent tasks on current tokio runtime, cached tokens are warmed from persistent cache first
+    pub fn start(self) -> Result<TokenAgentHandle> {
+        let persist_path = self.service_config.settings.cache.as_ref().and_then(|cache| cache.persist_path.as_ref());
+        if let Some(persist_path) = persist_path.filter(|_| PersistentCache::instance().is_none()) {
+            PersistentCache::init(persist_path)?;
+        }
+        let grace_period = Duration::from_secs(
+            self.service_config
+                .settings
+                .shutdown_grace_period_seconds
+                .unwrap_or(SHUTDOWN_GRACE_PERIOD_SECONDS_DEFAULT),
+        );
+        let shutdown = self.shutdown.clone();
+        let task = tokio::spawn(async move {
+            if PersistentCache::instance().is_some() {
+                TokenCache::warm_from_persistent().await?;
+            }
+            match &self.config_path {
+                Some(config_path) => app::run_with_reload(config_path, self.service_config, self.shutdown).await,
+                None => app::run_app(&self.service_config, self.shutdown).await,
+            }
+        });
+        Ok(TokenAgentHandle { shutdown, grace_period, task })
+    }
+}
+
+/// Running agent
+pub struct TokenAgentHandle {
+    shutdown: CancellationToken,
+    grace_period: Duration,
+    task: JoinHandle<Result<()>>,
+}
+
+impl TokenAgentHandle {
+    /// Cached token until 1 second before its expiration; the safety margin is not applied,
+    /// so a token waiting for refresh is still returned
+    pub async fn get_token(&self, source_id: &str, token_id: &str) -> Option<Token> {
+        TokenCache::get(source_id, token_id)
+            .await
+            .filter(|token_context| !token_context.should_remove())
+            .map(|token_context| token_context.token)
+    }
+
+    /// Changes of a token, e.g. to rebuild a client when its credentials are refreshed
+    pub async fn subscribe(&self, source_id: &str, token_id: &str) -> TokenSubscription {
+        TokenCache::subscribe(source_id, token_id).await
+    }
+
+    /// Token cancelling the agent, same as `shutdown` without waiting
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.shutdown.clone()
+    }
+
+    /// Stop agent and wait for its tasks within `settings.shutdown_grace_period_seconds`
+    pub async fn shutdown(self) -> Result<()> {
+        self.shutdown.cancel();
+        self.wait().await
+    }
+
+    /// Wait until agent stops, on error or after shutdown is requested;
+    /// tasks still running after grace period are aborted
+    pub async fn wait(self) -> Result<()> {
+        let abort_handle = self.task.abort_handle();
+        let task = self.task;
+        let res = shutdown::run_with_grace_period(async { task.await? }, self.shutdown, self.grace_period).await;
+        abort_handle.abort();
+        if let Some(cache) = PersistentCache::instance() {
+            if let Err(e) = cache.flush().await {
+                error!("persistent cache: flush on shutdown failed: {}", e);
+            }
+        }
+        info!("token agent stopped");
+        res
+    }
+}
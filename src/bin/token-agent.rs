@@ -1,40 +1,109 @@
-use clap::arg;
-use clap::command;
 use clap::Parser;
-use reqwest::Client;
-use token_agent::observability::service_resources_metrics::collect_process_metrics;
-use token_agent::server;
-use token_agent::sinks::manager::SinkManager;
+use clap::Subcommand;
+use clap::ValueEnum;
+use token_agent::config::proc_dump::DumpFormat;
+use token_agent::config::proc_validator::ValidationIssue;
+use token_agent::observability::otel;
 use token_agent::sources::builder_in_order::SourceDag;
-use token_agent::utils::channel;
 use token_agent::utils::config_loader;
+use token_agent::TokenAgent;
 use token_agent::utils::logging;
+use token_agent::utils::shutdown;
+use tokio_util::sync::CancellationToken;
 use anyhow::Result;
 use token_agent::utils::logging::LogLevel;
-use tracing::info;
+use tracing::{error, info};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    #[arg(short, long, env = "CONFIG", default_value = "token-agent.yaml")]
+    #[arg(short, long, env = "CONFIG", default_value = "token-agent.yaml", global = true)]
     config: String,
     #[arg(long, env = "LOG_LEVEL" , value_enum)]
     log_level: Option<LogLevel>,
+    /// same as `validate` subcommand
+    #[arg(long)]
+    validate_only: bool,
+    /// Fetch every source once, write file sinks and exit: 0 if all sources succeeded, 1 if not.
+    /// HTTP server, UDS and other active sinks are not started
+    #[arg(long)]
+    oneshot: bool,
+    /// Fetch every source once and print what each sink would write (redacted), nothing is written:
+    /// 0 if all sources succeeded, 1 if not
+    #[arg(long)]
+    dry_run: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
     // #[arg(long)]
     // watch_config: bool,
 }
 
+#[derive(Subcommand)]
+enum Command {
+    /// Run until SIGINT/SIGTERM, SIGHUP reloads config; the default when no subcommand is given
+    Run,
+    /// Validate config and exit: 0 if valid, 1 if not
+    Validate(ValidateArgs),
+    /// Print resolved config (env vars expanded, defaults injected, secrets redacted) and exit
+    DumpConfig(DumpConfigArgs),
+    /// Print source dependency graph in Graphviz DOT format and exit, e.g. `token-agent dag | dot -Tsvg > dag.svg`
+    Dag,
+    /// Fetch every source once and check every sink has a live token, file sinks hold it at `path`;
+    /// prints a status table and exits: 0 if all sinks are OK, 1 if not. Nothing is written or served
+    Check,
+}
+
+#[derive(clap::Args)]
+struct DumpConfigArgs {
+    #[arg(long, value_enum, default_value = "yaml")]
+    output: DumpFormat,
+}
+
+#[derive(clap::Args, Default)]
+struct ValidateArgs {
+    /// `.env` file used for `${VAR}` placeholders not set in environment
+    #[arg(long)]
+    env_file: Option<String>,
+    #[arg(long, alias = "output", value_enum, default_value = "plain")]
+    format: ValidateOutput,
+}
+
+#[derive(Clone, Copy, Default, ValueEnum)]
+enum ValidateOutput {
+    #[default]
+    Plain,
+    Json,
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     // -------------------------------
     // 1. Make preparations
     // 
     // read env
-    // create channel
     // -------------------------------
     
     let args = Args::parse();
-    let sink_sender = channel::run();
+    let command = args.command.as_ref().unwrap_or(&Command::Run);
+
+    // validate config and exit
+    if let Command::Validate(validate_args) = command {
+        return validate(&args.config, validate_args).await;
+    }
+    if args.validate_only {
+        return validate(&args.config, &ValidateArgs::default()).await;
+    }
+    // print resolved config and exit, nothing is started
+    if let Command::DumpConfig(dump_config_args) = command {
+        print!("{}", config_loader::dump(&args.config, dump_config_args.output).await?);
+        return Ok(());
+    }
+    // print source dependency graph and exit, nothing is started
+    if let Command::Dag = command {
+        let service_config = config_loader::run(&args.config).await?;
+        print!("{}", SourceDag::build(&service_config.sources)?.to_dot());
+        return Ok(());
+    }
     
     // -------------------------------
     // 2. Load YAML config
@@ -43,74 +112,72 @@ async fn main() -> Result<()> {
     let service_config = config_loader::run(&args.config).await?;
     logging::run(&service_config, args.log_level.to_owned()).await?;
 
-    // -------------------------------
-    // 3. Prepare sources dependency graph
-    // -------------------------------
-
-    let dag = SourceDag::build(&service_config.sources)?;
-
-    // -------------------------------
-    // 4. Create request client
-    // -------------------------------
-
-    let client = Client::new();
-
-
-    // -------------------------------
-    // SOURCES
-    // -------------------------------
-
-
-    // -------------------------------
-    // 5. Fetch tokens for each source according to dependecy graph order
-    // -------------------------------
-
-    // -------------------------------
-    // 5.1. Prepare fetch tokens worker
-    // -------------------------------
-
-    let safety_margin_seconds = service_config.settings.safety_margin_seconds;
-    let retry = &service_config.settings.retry;
-    let receiver = dag.loop_refrech_tokens(&client, &retry, safety_margin_seconds, sink_sender.clone());
-
-    // -------------------------------
-    // 5.2. Prepare cleanup expired tokens worker
-    // -------------------------------
-
-    let cleaner = dag.loop_check_token_exp(&service_config.sources, &safety_margin_seconds, sink_sender.clone());
-
-
-    // -------------------------------
-    // SINKS
-    // -------------------------------
-
-
-    // -------------------------------
-    // 6. Start file, udp (actve) sinks
-    // -------------------------------
-
-    let sink_manager = SinkManager::new(service_config.sinks.to_owned());
-    let active_sinks = sink_manager.start_active_sinks(sink_sender.clone());
-
-    // -------------------------------
-    // 7. Start http server with http (pasive) sink
-    // -------------------------------    
-
-    let http_server = server::server::start(&service_config.settings, &service_config.sinks);
-
-
-    // -------------------------------
-    // METRICS
-    // -------------------------------
-
-
-    // -------------------------------
-    // 8. Start scraping system resources consupption metrics
-    // -------------------------------    
-
-    let service_metrics = collect_process_metrics(service_config.settings.metrics.is_enabled.to_owned());
-    info!("Service starting...");
-    tokio::try_join!(receiver, cleaner, active_sinks, http_server, service_metrics)?;
+    // fetch once, write file sinks and exit
+    if args.oneshot {
+        if let Err(err) = TokenAgent::from_config(service_config).await?.run_once().await {
+            error!("oneshot failed: {}", err);
+            std::process::exit(1);
+        }
+        info!("oneshot completed");
+        otel::shutdown().await;
+        return Ok(());
+    }
+
+    // fetch once, check tokens of every sink and exit
+    if let Command::Check = command {
+        let summary = TokenAgent::from_config(service_config).await?.check().await?;
+        print!("{}", summary);
+        std::process::exit(if summary.is_success() { 0 } else { 1 });
+    }
+
+    // fetch once, report planned sink writes and exit
+    if args.dry_run {
+        let summary = TokenAgent::from_config(service_config).await?.dry_run().await?;
+        print!("{}", summary);
+        std::process::exit(if summary.is_success() { 0 } else { 1 });
+    }
+
+    // -------------------------------
+    // 3. Run until SIGINT/SIGTERM, then wait for tasks within grace period,
+    //    SIGHUP reloads config; cached tokens are warmed from persistent cache first
+    // -------------------------------
+
+    let shutdown = CancellationToken::new();
+    tokio::spawn(shutdown::cancel_on_signal(shutdown.clone()));
+    let stopped = TokenAgent::from_config(service_config)
+        .await?
+        .with_config_path(&args.config)
+        .with_shutdown(shutdown)
+        .start()?
+        .wait()
+        .await;
+    // spans of the last refresh cycles are still queued
+    otel::shutdown().await;
+    stopped?;
+    info!("Service stopped");
 
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Validate config, print errors and exit with code 1 if there are any
+async fn validate(config_path: &str, validate_args: &ValidateArgs) -> Result<()> {
+    let errors = match config_loader::validate(config_path, validate_args.env_file.as_deref()).await {
+        Ok(errors) => errors,
+        // config can not be read or parsed, the error is not about a config path
+        Err(err) => vec![ValidationIssue::new("", err.to_string())],
+    };
+    match validate_args.format {
+        ValidateOutput::Plain if errors.is_empty() => println!("config '{}' is valid", config_path),
+        ValidateOutput::Plain => {
+            eprintln!("config '{}' is not valid, total errors: {}", config_path, errors.len());
+            errors.iter().for_each(|error| eprintln!("{}", error));
+        }
+        ValidateOutput::Json => {
+            println!(
+                "{}",
+                serde_json::json!({ "config": config_path, "valid": errors.is_empty(), "errors": errors })
+            )
+        }
+    }
+    std::process::exit(if errors.is_empty() { 0 } else { 1 });
+}
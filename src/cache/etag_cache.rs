@@ -0,0 +1,65 @@
+use std::collections::HashMap;
+
+use http::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use http::HeaderMap;
+use tokio::sync::{OnceCell, RwLock};
+use tracing::debug;
+
+// Declare the static OnceCell to hold the EtagCache.
+static ETAG_CACHE_INSTANCE: OnceCell<EtagCache> = OnceCell::const_new();
+
+/// Asynchronously initializes and gets a reference to the static `EtagCache`.
+async fn get_etag_cache() -> &'static EtagCache {
+    ETAG_CACHE_INSTANCE.get_or_init(|| async {
+        debug!("Initializing static EtagCache...");
+        EtagCache::default()
+    }).await
+}
+
+/// Validators of the last successful source response
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EtagEntry {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl EtagEntry {
+    /// `ETag` and `Last-Modified` of response, `None` when upstream sends neither
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let header = |name| headers.get(name).and_then(|v| v.to_str().ok()).map(str::to_owned);
+        let entry = Self { etag: header(ETAG), last_modified: header(LAST_MODIFIED) };
+        (entry.etag.is_some() || entry.last_modified.is_some()).then_some(entry)
+    }
+
+    /// `If-None-Match` / `If-Modified-Since` request headers
+    pub fn conditional_headers(&self) -> Vec<(http::HeaderName, String)> {
+        let mut headers = Vec::with_capacity(2);
+        if let Some(etag) = &self.etag {
+            headers.push((IF_NONE_MATCH, etag.to_owned()));
+        }
+        if let Some(last_modified) = &self.last_modified {
+            headers.push((IF_MODIFIED_SINCE, last_modified.to_owned()));
+        }
+        headers
+    }
+}
+
+/// Conditional GET cache: source_id -> validators of last parsed response
+#[derive(Debug, Default)]
+pub struct EtagCache {
+    inner: RwLock<HashMap<String, EtagEntry>>,
+}
+
+impl EtagCache {
+    pub async fn get_by_source_id(source_id: &str) -> Option<EtagEntry> {
+        get_etag_cache().await.inner.read().await.get(source_id).cloned()
+    }
+
+    pub async fn set(source_id: &str, entry: EtagEntry) {
+        get_etag_cache().await.inner.write().await.insert(source_id.to_owned(), entry);
+    }
+
+    pub async fn remove(source_id: &str) {
+        get_etag_cache().await.inner.write().await.remove(source_id);
+    }
+}
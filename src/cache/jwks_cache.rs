@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use jsonwebtoken::jwk::{Jwk, JwkSet};
+use jsonwebtoken::{decode, decode_header, DecodingKey, Validation};
+use reqwest::Client;
+use serde_json::Value;
+use tokio::sync::{OnceCell, RwLock};
+use tokio::time::Instant;
+use tracing::{debug, info};
+
+pub const JWKS_CACHE_TTL_SECONDS: u64 = 300;
+
+// Declare the static OnceCell to hold the JwksCache.
+static JWKS_CACHE_INSTANCE: OnceCell<JwksCache> = OnceCell::const_new();
+
+/// Asynchronously initializes and gets a reference to the static `JwksCache`.
+async fn get_jwks_cache() -> &'static JwksCache {
+    JWKS_CACHE_INSTANCE.get_or_init(|| async {
+        debug!("Initializing static JwksCache...");
+        JwksCache::new()
+    }).await
+}
+
+#[derive(Debug, Clone)]
+struct JwksEntry {
+    keys: JwkSet,
+    fetched_at: Instant,
+}
+
+/// JWKS cache: jwks_uri -> key set,
+/// refreshed on key id miss or when older than `JWKS_CACHE_TTL_SECONDS`
+#[derive(Debug)]
+pub struct JwksCache {
+    client: Client,
+    inner: RwLock<HashMap<String, JwksEntry>>,
+}
+
+impl JwksCache {
+    pub fn new() -> Self {
+        Self {
+            client: Client::new(),
+            inner: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Verify JWT signature and `exp` with the key from `jwks_uri` matching token `kid`
+    pub async fn verify(token_value: &str, jwks_uri: &str) -> Result<()> {
+        let header = decode_header(token_value).map_err(|e| anyhow!("invalid JWT header: {}", e))?;
+        let jwk = JwksCache::get_key(jwks_uri, header.kid.as_deref()).await?;
+        let key = DecodingKey::from_jwk(&jwk).map_err(|e| anyhow!("invalid JWK: {}", e))?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.validate_aud = false;
+        decode::<Value>(token_value, &key, &validation)
+            .map(|_| ())
+            .map_err(|e| anyhow!("JWT signature verification failed: {}", e))
+    }
+
+    /// Get key by kid, key set is re-fetched once on miss or ttl expiry
+    async fn get_key(jwks_uri: &str, kid: Option<&str>) -> Result<Jwk> {
+        let cache = get_jwks_cache().await;
+        let cached = cache.inner.read().await.get(jwks_uri).cloned();
+        if let Some(entry) = cached.filter(|entry| entry.fetched_at.elapsed() < Duration::from_secs(JWKS_CACHE_TTL_SECONDS)) {
+            if let Some(jwk) = find_key(&entry.keys, kid) {
+                return Ok(jwk);
+            }
+            debug!("jwks '{}': key id {:?} not found in cache", jwks_uri, kid);
+        }
+
+        let keys = cache.fetch(jwks_uri).await?;
+        let jwk = find_key(&keys, kid);
+        cache.inner.write().await.insert(jwks_uri.to_owned(), JwksEntry { keys, fetched_at: Instant::now() });
+        jwk.ok_or_else(|| anyhow!("jwks '{}': key id {:?} not found", jwks_uri, kid))
+    }
+
+    async fn fetch(&self, jwks_uri: &str) -> Result<JwkSet> {
+        info!("fetching jwks '{}'", jwks_uri);
+        let response = self.client.get(jwks_uri).send().await?;
+        if !response.status().is_success() {
+            return Err(anyhow!("jwks '{}' request failed: {}", jwks_uri, response.status()));
+        }
+        response
+            .json::<JwkSet>()
+            .await
+            .map_err(|e| anyhow!("jwks '{}' invalid key set: {}", jwks_uri, e))
+    }
+}
+
+impl Default for JwksCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Match by kid, token without kid is accepted only for single key set
+fn find_key(keys: &JwkSet, kid: Option<&str>) -> Option<Jwk> {
+    match kid {
+        Some(kid) => keys.find(kid).cloned(),
+        None if keys.keys.len() == 1 => keys.keys.first().cloned(),
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+    use httpmock::prelude::*;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use serde_json::json;
+
+    use super::*;
+    use crate::config::sources::{ParseConfig, TokenField, TokenType};
+    use crate::observability::metrics::get_metrics;
+    use crate::parser::parser::parse_tokens;
+
+    const EXP: u64 = 4070944800;
+
+    fn sign(kid: &str, secret: &[u8]) -> String {
+        let mut header = Header::new(jsonwebtoken::Algorithm::HS256);
+        header.kid = Some(kid.to_owned());
+        encode(&header, &json!({ "exp": EXP }), &EncodingKey::from_secret(secret)).unwrap()
+    }
+
+    fn jwks(kid: &str, secret: &[u8]) -> Value {
+        json!({ "keys": [{ "kty": "oct", "kid": kid, "alg": "HS256", "k": URL_SAFE_NO_PAD.encode(secret) }] })
+    }
+
+    #[tokio::test]
+    async fn verify_signature_with_cached_jwks() {
+        let server = MockServer::start_async().await;
+        let mock = server.mock(|when, then| {
+            when.method(GET).path("/jwks");
+            then.status(200).json_body(jwks("k1", b"secret-1"));
+        });
+        let uri = server.url("/jwks");
+
+        assert!(JwksCache::verify(&sign("k1", b"secret-1"), &uri).await.is_ok());
+        assert!(JwksCache::verify(&sign("k1", b"secret-1"), &uri).await.is_ok());
+        mock.assert_calls(1);
+
+        // forged signature
+        let err = JwksCache::verify(&sign("k1", b"forged"), &uri).await.unwrap_err();
+        assert!(err.to_string().contains("signature verification failed"), "{}", err);
+        mock.assert_calls(1);
+    }
+
+    #[tokio::test]
+    async fn refresh_jwks_on_key_id_miss() {
+        let server = MockServer::start_async().await;
+        let mut old_keys = server.mock(|when, then| {
+            when.method(GET).path("/jwks");
+            then.status(200).json_body(jwks("k1", b"secret-1"));
+        });
+        let uri = server.url("/jwks");
+        assert!(JwksCache::verify(&sign("k1", b"secret-1"), &uri).await.is_ok());
+        old_keys.delete();
+
+        // keys rotated
+        let new_keys = server.mock(|when, then| {
+            when.method(GET).path("/jwks");
+            then.status(200).json_body(jwks("k2", b"secret-2"));
+        });
+        assert!(JwksCache::verify(&sign("k2", b"secret-2"), &uri).await.is_ok());
+        new_keys.assert_calls(1);
+
+        let err = JwksCache::verify(&sign("k3", b"secret-3"), &uri).await.unwrap_err();
+        assert!(err.to_string().contains("not found"), "{}", err);
+        new_keys.assert_calls(2);
+    }
+
+    #[tokio::test]
+    async fn parse_tokens_rejects_forged_jwt() {
+        let server = MockServer::start_async().await;
+        server.mock(|when, then| {
+            when.method(GET).path("/jwks");
+            then.status(200).json_body(jwks("k1", b"secret-1"));
+        });
+        let config = ParseConfig {
+            expect: None,
+            body_format: None,
+            tokens: vec![
+                TokenField {
+                    id: "verified".into(),
+                    parent: "body".into(),
+                    pointer: "verified".into(),
+                    token_type: TokenType::Jwt,
+                    jwks_uri: Some(server.url("/jwks")),
+                    transforms: None,
+                    extract_claims: None,
+                    iterate: None,
+                    max_token_bytes: None,
+                    value_regex: None,
+                    expiration: None,
+                },
+                TokenField {
+                    id: "forged".into(),
+                    parent: "body".into(),
+                    pointer: "forged".into(),
+                    token_type: TokenType::Jwt,
+                    jwks_uri: Some(server.url("/jwks")),
+                    transforms: None,
+                    extract_claims: None,
+                    iterate: None,
+                    max_token_bytes: None,
+                    value_regex: None,
+                    expiration: None,
+                },
+            ],
+        };
+        let body = json!({ "verified": sign("k1", b"secret-1"), "forged": sign("k1", b"forged") }).to_string();
+
+        let failures = get_metrics().await.jwt_signature_failures.get();
+        let tokens = parse_tokens(http::HeaderMap::new(), body, config, None, None).await.unwrap();
+
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].id, "verified");
+        assert_eq!(tokens[0].token.exp_unix_ts, EXP);
+        assert!(get_metrics().await.jwt_signature_failures.get() > failures);
+    }
+}
@@ -1,3 +1,9 @@
 pub mod token_cache;
 pub mod token_context;
-pub mod token;
\ No newline at end of file
+pub mod token_event;
+pub mod token;
+pub mod jwks_cache;
+pub mod persistent_cache;
+pub mod etag_cache;
+pub mod ssm_cache;
+pub mod secret_cache;
\ No newline at end of file
@@ -0,0 +1,345 @@
+use std::sync::mpsc::{self, Sender};
+use std::thread::JoinHandle;
+
+use anyhow::{anyhow, Result};
+use tokio::sync::{oneshot, OnceCell};
+use tracing::{debug, error, info};
+
+use crate::cache::token_context::TokenContext;
+
+// Declare the static OnceCell to hold the PersistentCache, set once on startup when enabled.
+static PERSISTENT_CACHE_INSTANCE: OnceCell<PersistentCache> = OnceCell::const_new();
+
+// source_id and token_id separator in keys
+const KEY_SEPARATOR: u8 = 0;
+// tokens are stored in plaintext, only the agent user may read them
+#[cfg(unix)]
+const PERSISTENT_CACHE_DIR_MODE: u32 = 0o700;
+
+/// Persistent (L2) token cache over `sled`: source_id + token_id -> TokenContext (JSON)
+#[derive(Debug)]
+pub struct PersistentCache {
+    db: sled::Db,
+    // queued writes are applied one by one by the writer thread, in the order `TokenCache` changed
+    writes: Sender<PersistentWrite>,
+    writer: Option<JoinHandle<()>>,
+}
+
+/// Change of L1 to be repeated in L2
+#[derive(Debug)]
+pub enum PersistentWrite {
+    Set { source_id: String, token_contexts: Vec<TokenContext> },
+    Remove { source_id: String, token_ids: Vec<String> },
+    RemoveSource { source_id: String },
+    Flush(oneshot::Sender<Result<()>>),
+}
+
+impl PersistentCache {
+    /// Open or create cache directory, it is made accessible to the agent user only
+    pub fn open(path: &str) -> Result<Self> {
+        let db = sled::open(path).map_err(|e| anyhow!("open persistent cache '{}': {}", path, e))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(PERSISTENT_CACHE_DIR_MODE))
+                .map_err(|e| anyhow!("set persistent cache '{}' permissions: {}", path, e))?;
+        }
+        let (writes, queued) = mpsc::channel();
+        let writer_db = db.clone();
+        let writer = std::thread::Builder::new()
+            .name("persistent-cache-writer".into())
+            .spawn(move || queued.into_iter().for_each(|write| apply_write(&writer_db, write)))
+            .map_err(|e| anyhow!("start persistent cache writer: {}", e))?;
+        Ok(Self { db, writes, writer: Some(writer) })
+    }
+
+    /// Open persistent cache at `path` and make it available as L2 for `TokenCache`
+    pub fn init(path: &str) -> Result<&'static PersistentCache> {
+        let cache = PersistentCache::open(path)?;
+        info!("persistent token cache opened at '{}'", path);
+        PERSISTENT_CACHE_INSTANCE
+            .set(cache)
+            .map_err(|_| anyhow!("persistent cache is already initialized"))?;
+        Ok(PERSISTENT_CACHE_INSTANCE.get().unwrap())
+    }
+
+    /// Persistent cache if enabled
+    pub fn instance() -> Option<&'static PersistentCache> {
+        PERSISTENT_CACHE_INSTANCE.get()
+    }
+
+    /// Get token by source_id and token_id
+    pub fn get(&self, source_id: &str, token_id: &str) -> Option<TokenContext> {
+        self.db
+            .get(key(source_id, token_id))
+            .inspect_err(|e| error!("persistent cache read failed: {}", e))
+            .ok()
+            .flatten()
+            .and_then(|value| serde_json::from_slice(&value).ok())
+    }
+
+    /// Insert or update tokens of source
+    pub fn set(&self, source_id: &str, source_token_contexts: &[TokenContext]) -> Result<()> {
+        set_tokens(&self.db, source_id, source_token_contexts)
+    }
+
+    /// Queue write behind the ones queued before, it is applied without blocking the caller
+    pub fn queue(&self, write: PersistentWrite) {
+        if self.writes.send(write).is_err() {
+            error!("persistent cache: writer is stopped, write dropped");
+        }
+    }
+
+    /// Apply queued writes and write them to disk, called on shutdown.
+    /// Writes are not flushed one by one, `sled` flushes them in background in between
+    pub async fn flush(&self) -> Result<()> {
+        let (flushed, flush_result) = oneshot::channel();
+        self.queue(PersistentWrite::Flush(flushed));
+        flush_result.await.map_err(|_| anyhow!("persistent cache writer is stopped"))?
+    }
+
+    /// Remove expired tokens of source
+    pub fn invalidate_expired_tokens_by_source_id(&self, source_id: &str) -> Result<()> {
+        for (key, token_context) in self.scan_prefix(source_id)? {
+            if token_context.should_remove() {
+                self.db.remove(key)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove all tokens of source
+    pub fn remove_by_source_id(&self, source_id: &str) -> Result<()> {
+        remove_source(&self.db, source_id)
+    }
+
+    /// All entries as (source_id, token_context)
+    pub fn get_all(&self) -> Result<Vec<(String, TokenContext)>> {
+        let mut entries = Vec::new();
+        for item in self.db.iter() {
+            let (key, value) = item?;
+            let Some(source_id) = key.split(|b| *b == KEY_SEPARATOR).next() else {
+                continue;
+            };
+            match serde_json::from_slice::<TokenContext>(&value) {
+                Ok(token_context) => entries.push((String::from_utf8_lossy(source_id).into_owned(), token_context)),
+                Err(e) => error!("persistent cache: skip invalid entry: {}", e),
+            }
+        }
+        Ok(entries)
+    }
+
+    fn scan_prefix(&self, source_id: &str) -> Result<Vec<(sled::IVec, TokenContext)>> {
+        let mut entries = Vec::new();
+        for item in self.db.scan_prefix(source_prefix(source_id)) {
+            let (key, value) = item?;
+            if let Ok(token_context) = serde_json::from_slice::<TokenContext>(&value) {
+                entries.push((key, token_context));
+            }
+        }
+        Ok(entries)
+    }
+}
+
+impl Drop for PersistentCache {
+    /// Apply queued writes and release the database, so it can be opened again
+    fn drop(&mut self) {
+        // replaced sender closes the queue, the writer stops after the last queued write
+        drop(std::mem::replace(&mut self.writes, mpsc::channel().0));
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.join();
+        }
+    }
+}
+
+/// Writer thread step, a failed write is logged and the next one is applied
+fn apply_write(db: &sled::Db, write: PersistentWrite) {
+    let (source_id, result) = match write {
+        PersistentWrite::Set { source_id, token_contexts } => {
+            let result = set_tokens(db, &source_id, &token_contexts);
+            (source_id, result)
+        }
+        PersistentWrite::Remove { source_id, token_ids } => {
+            let result = remove_tokens(db, &source_id, &token_ids);
+            (source_id, result)
+        }
+        PersistentWrite::RemoveSource { source_id } => {
+            let result = remove_source(db, &source_id);
+            (source_id, result)
+        }
+        PersistentWrite::Flush(flushed) => {
+            let _ = flushed.send(db.flush().map(|_| ()).map_err(Into::into));
+            return;
+        }
+    };
+    if let Err(e) = result {
+        error!("persistent cache: writing tokens of source '{}' failed: {}", source_id, e);
+    }
+}
+
+fn set_tokens(db: &sled::Db, source_id: &str, source_token_contexts: &[TokenContext]) -> Result<()> {
+    let mut batch = sled::Batch::default();
+    for token_context in source_token_contexts {
+        batch.insert(key(source_id, &token_context.id), serde_json::to_vec(token_context)?);
+    }
+    db.apply_batch(batch)?;
+    debug!("persistent cache: stored {} tokens for source '{}'", source_token_contexts.len(), source_id);
+    Ok(())
+}
+
+fn remove_tokens(db: &sled::Db, source_id: &str, token_ids: &[String]) -> Result<()> {
+    let mut batch = sled::Batch::default();
+    for token_id in token_ids {
+        batch.remove(key(source_id, token_id));
+    }
+    db.apply_batch(batch)?;
+    debug!("persistent cache: removed {} tokens of source '{}'", token_ids.len(), source_id);
+    Ok(())
+}
+
+fn remove_source(db: &sled::Db, source_id: &str) -> Result<()> {
+    let mut batch = sled::Batch::default();
+    for item in db.scan_prefix(source_prefix(source_id)) {
+        let (key, _) = item?;
+        batch.remove(key);
+    }
+    db.apply_batch(batch)?;
+    debug!("persistent cache: removed tokens of source '{}'", source_id);
+    Ok(())
+}
+
+fn source_prefix(source_id: &str) -> Vec<u8> {
+    let mut prefix = source_id.as_bytes().to_vec();
+    prefix.push(KEY_SEPARATOR);
+    prefix
+}
+
+fn key(source_id: &str, token_id: &str) -> Vec<u8> {
+    let mut key = Vec::with_capacity(source_id.len() + token_id.len() + 1);
+    key.extend_from_slice(source_id.as_bytes());
+    key.push(KEY_SEPARATOR);
+    key.extend_from_slice(token_id.as_bytes());
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::cache::token::Token;
+    use crate::cache::token_cache::TokenCache;
+    use crate::tests::common::refresh_context;
+
+    fn token_context(id: &str, exp: u64) -> TokenContext {
+        TokenContext::new(id.to_owned(), Token::new(format!("value-{}", id), exp), 10)
+    }
+
+    #[test]
+    fn set_get_and_invalidate_expired() {
+        let dir = tempfile::tempdir().unwrap();
+        let now = Utc::now().timestamp() as u64;
+        {
+            let cache = PersistentCache::open(dir.path().to_str().unwrap()).unwrap();
+            cache.set("src", &[token_context("live", now + 3600), token_context("expired", now - 1)]).unwrap();
+            cache.set("src_other", &[token_context("live", now + 3600)]).unwrap();
+        }
+
+        // reopen: entries survive restart
+        let cache = PersistentCache::open(dir.path().to_str().unwrap()).unwrap();
+        let live = cache.get("src", "live").unwrap();
+        assert_eq!(live.token.value, "value-live");
+        assert_eq!(live.token.exp_unix_ts, now + 3600);
+        assert_eq!(live.fetched_at_unix_ts, now + 3590);
+        assert!(cache.get("src", "expired").is_some());
+
+        cache.invalidate_expired_tokens_by_source_id("src").unwrap();
+        assert!(cache.get("src", "expired").is_none());
+        assert!(cache.get("src", "live").is_some());
+
+        let mut all: Vec<(String, String)> = cache.get_all().unwrap().into_iter().map(|(s, t)| (s, t.id)).collect();
+        all.sort();
+        assert_eq!(all, vec![("src".to_owned(), "live".to_owned()), ("src_other".to_owned(), "live".to_owned())]);
+    }
+
+    #[tokio::test]
+    async fn queued_writes_are_applied_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let now = Utc::now().timestamp() as u64;
+        let cache = PersistentCache::open(dir.path().to_str().unwrap()).unwrap();
+
+        // invalidation queued after the store wins, a store queued after a removal is kept
+        cache.queue(PersistentWrite::Set { source_id: "src".into(), token_contexts: vec![token_context("a", now + 3600), token_context("b", now + 3600)] });
+        cache.queue(PersistentWrite::RemoveSource { source_id: "src".into() });
+        cache.queue(PersistentWrite::Set { source_id: "src".into(), token_contexts: vec![token_context("b", now + 7200)] });
+        cache.queue(PersistentWrite::Remove { source_id: "src".into(), token_ids: vec!["a".into()] });
+        cache.flush().await.unwrap();
+
+        assert!(cache.get("src", "a").is_none());
+        assert_eq!(cache.get("src", "b").unwrap().token.exp_unix_ts, now + 7200);
+    }
+
+    #[tokio::test]
+    async fn warm_token_cache_skips_expired_tokens() {
+        let dir = tempfile::tempdir().unwrap();
+        let now = Utc::now().timestamp() as u64;
+        let cache = PersistentCache::open(dir.path().to_str().unwrap()).unwrap();
+        cache.set("persist_warm", &[token_context("live", now + 3600), token_context("expired", now - 1)]).unwrap();
+
+        let source_ids = TokenCache::warm_from(&cache).await.unwrap();
+
+        assert_eq!(source_ids, vec!["persist_warm".to_owned()]);
+        assert_eq!(TokenCache::get("persist_warm", "live").await.unwrap().token.value, "value-live");
+        assert!(TokenCache::get("persist_warm", "expired").await.is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn cache_directory_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cache");
+
+        PersistentCache::open(path.to_str().unwrap()).unwrap();
+
+        assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o700);
+    }
+
+    #[tokio::test]
+    #[serial_test::serial]
+    async fn restored_valid_token_is_not_fetched() {
+        use std::collections::HashMap;
+
+        use crate::config::sources::SourceConfig;
+        use crate::sources::builder_in_order::SourceDag;
+
+        TokenCache::cleanup().await;
+        let server = httpmock::MockServer::start_async().await;
+        let token_mock = server.mock(|when, then| {
+            when.method(httpmock::Method::GET).path("/token");
+            then.status(200).json_body(serde_json::json!({ "token": "fetched" }));
+        });
+        let source: SourceConfig = serde_yaml::from_str(&format!(
+            "type: http\nrequest: {{ url: \"{}\", method: GET }}\nparse:\n  tokens:\n    - {{ id: live, parent: body, pointer: token, token_type: plain_text }}\n",
+            server.url("/token")
+        ))
+        .unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let cache = PersistentCache::open(dir.path().to_str().unwrap()).unwrap();
+            cache.set("persist_restored", &[token_context("live", Utc::now().timestamp() as u64 + 3600)]).unwrap();
+        }
+
+        // restart: warm from disk, then first refresh cycle
+        let cache = PersistentCache::open(dir.path().to_str().unwrap()).unwrap();
+        TokenCache::warm_from(&cache).await.unwrap();
+        let dag = SourceDag::build(&HashMap::from([("persist_restored".to_owned(), source)])).unwrap();
+        let layers: Vec<Vec<_>> = dag.layers().into_iter().map(|layer| layer.into_iter().cloned().collect()).collect();
+        let refresh_context = refresh_context();
+        SourceDag::refresh_layers(&layers, &refresh_context).await;
+
+        token_mock.assert_calls_async(0).await;
+        assert_eq!(TokenCache::get("persist_restored", "live").await.unwrap().token.value, "value-live");
+        TokenCache::cleanup().await;
+    }
+}
@@ -0,0 +1,695 @@
+//! AWS SSM Parameter Store values for `parameter_name` request values
+//!
+//! Values are read with a SigV4 signed `GetParameter` call. Credentials are looked up like the AWS SDK
+//! default chain: `AWS_ACCESS_KEY_ID`, `AWS_SECRET_ACCESS_KEY` and optional `AWS_SESSION_TOKEN`, then
+//! web identity (IRSA) with `AWS_WEB_IDENTITY_TOKEN_FILE` and `AWS_ROLE_ARN`, then the EC2 instance profile
+//! through IMDSv2. Temporary credentials are reused until shortly before they expire. Region comes from
+//! `AWS_REGION` or `AWS_DEFAULT_REGION`, `AWS_ENDPOINT_URL_SSM` overrides the regional endpoint. Values are
+//! cached by parameter name and `with_decryption` for `settings.ssm_cache_ttl_seconds`, so refresh cycles
+//! don't call SSM each time.
+
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Error, Result};
+use chrono::Utc;
+use reqwest::{Client, Url};
+use ring::{digest, hmac};
+use serde_json::{json, Value};
+use tokio::sync::{OnceCell, RwLock};
+use tokio::time::Instant;
+use tracing::{debug, info};
+
+use crate::cache::token::fingerprint;
+use crate::config::sources::RequestConfig;
+use crate::helpers::time::now_i64;
+use crate::observability::metrics::get_metrics;
+use crate::sources::aws_sts::{
+    parse_credentials, AWS_ACCESS_KEY_ID_TOKEN_ID, AWS_SECRET_ACCESS_KEY_TOKEN_ID, AWS_SESSION_TOKEN_TOKEN_ID, AWS_STS_ACTION,
+    AWS_STS_API_VERSION, AWS_STS_DURATION_SECONDS_DEFAULT,
+};
+use crate::sources::error::FetchError;
+use crate::sources::metadata::{fetch_imdsv2_session_token, IMDSV2_SESSION_TOKEN_HEADER};
+
+pub const SSM_CACHE_TTL_SECONDS_DEFAULT: u64 = 300;
+
+const SSM_SERVICE: &str = "ssm";
+const SSM_GET_PARAMETER_TARGET: &str = "AmazonSSM.GetParameter";
+const SSM_CONTENT_TYPE: &str = "application/x-amz-json-1.1";
+
+const AWS_ROLE_SESSION_NAME_DEFAULT: &str = "token-agent";
+const AWS_EC2_METADATA_ENDPOINT_DEFAULT: &str = "http://169.254.169.254";
+const AWS_EC2_CREDENTIALS_PATH: &str = "/latest/meta-data/iam/security-credentials/";
+/// instance metadata is only reachable on EC2, elsewhere the lookup must fail fast
+const AWS_EC2_METADATA_TIMEOUT: Duration = Duration::from_secs(2);
+/// whole request timeout of STS and SSM calls, a hung endpoint must not block the source fetch
+const SSM_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const CREDENTIALS_RENEW_MARGIN_SECONDS: u64 = 300;
+
+// Declare the static OnceCell to hold the SsmCache.
+static SSM_CACHE_INSTANCE: OnceCell<SsmCache> = OnceCell::const_new();
+
+/// Asynchronously initializes and gets a reference to the static `SsmCache`.
+async fn get_ssm_cache() -> &'static SsmCache {
+    SSM_CACHE_INSTANCE.get_or_init(|| async {
+        debug!("Initializing static SsmCache...");
+        SsmCache::new()
+    }).await
+}
+
+#[derive(Clone)]
+struct SsmEntry {
+    value: String,
+    fetched_at: Instant,
+}
+
+impl std::fmt::Debug for SsmEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SsmEntry")
+            .field("value", &format_args!("{}", fingerprint(&self.value)))
+            .field("fetched_at", &self.fetched_at)
+            .finish()
+    }
+}
+
+/// SSM cache: (parameter name, with_decryption) -> value, re-fetched when older than ttl
+#[derive(Debug)]
+pub struct SsmCache {
+    client: Client,
+    ttl_seconds: AtomicU64,
+    inner: RwLock<HashMap<(String, bool), SsmEntry>>,
+    /// temporary credentials of web identity or instance profile
+    credentials: RwLock<Option<AwsCredentials>>,
+}
+
+#[derive(Clone)]
+struct AwsCredentials {
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: Option<String>,
+    /// None for static credentials from environment
+    renew_at: Option<Instant>,
+}
+
+impl std::fmt::Debug for AwsCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AwsCredentials")
+            .field("access_key_id", &self.access_key_id)
+            .field("secret_access_key", &format_args!("{}", fingerprint(&self.secret_access_key)))
+            .field("renew_at", &self.renew_at)
+            .finish()
+    }
+}
+
+impl AwsCredentials {
+    /// Temporary credentials are renewed `CREDENTIALS_RENEW_MARGIN_SECONDS` before `exp_unix_ts`
+    fn temporary(access_key_id: String, secret_access_key: String, session_token: String, exp_unix_ts: i64) -> Self {
+        let valid_seconds = u64::try_from(exp_unix_ts - now_i64()).unwrap_or_default();
+        Self {
+            access_key_id,
+            secret_access_key,
+            session_token: Some(session_token),
+            renew_at: Some(Instant::now() + Duration::from_secs(valid_seconds.saturating_sub(CREDENTIALS_RENEW_MARGIN_SECONDS))),
+        }
+    }
+}
+
+impl SsmCache {
+    fn new() -> Self {
+        Self {
+            // same panic as `Client::new` when TLS backend can not be initialized
+            client: Client::builder().timeout(SSM_REQUEST_TIMEOUT).build().expect("TLS backend cannot be initialized"),
+            ttl_seconds: AtomicU64::new(SSM_CACHE_TTL_SECONDS_DEFAULT),
+            inner: RwLock::new(HashMap::new()),
+            credentials: RwLock::new(None),
+        }
+    }
+
+    /// Apply `settings.ssm_cache_ttl_seconds`, default is used when not set
+    pub async fn set_ttl_seconds(ttl_seconds: Option<u64>) {
+        let ttl_seconds = ttl_seconds.unwrap_or(SSM_CACHE_TTL_SECONDS_DEFAULT);
+        get_ssm_cache().await.ttl_seconds.store(ttl_seconds, Ordering::Relaxed);
+    }
+
+    /// Parameter value, from cache while younger than ttl; decrypted and raw values are cached apart
+    pub async fn get(parameter_name: &str, with_decryption: bool) -> Result<String> {
+        let cache = get_ssm_cache().await;
+        let ttl = Duration::from_secs(cache.ttl_seconds.load(Ordering::Relaxed));
+        let key = (parameter_name.to_owned(), with_decryption);
+        let cached = cache.inner.read().await.get(&key).cloned();
+        if let Some(entry) = cached.filter(|entry| entry.fetched_at.elapsed() < ttl) {
+            return Ok(entry.value);
+        }
+
+        let metrics = get_metrics().await;
+        metrics.ssm_fetch_requests.with_label_values(&[parameter_name]).inc();
+        let value = cache.fetch(parameter_name, with_decryption).await.inspect_err(|_| {
+            metrics.ssm_fetch_failures.with_label_values(&[parameter_name]).inc();
+        })?;
+        cache.inner.write().await.insert(key, SsmEntry { value: value.clone(), fetched_at: Instant::now() });
+        Ok(value)
+    }
+
+    async fn fetch(&self, parameter_name: &str, with_decryption: bool) -> Result<String> {
+        info!("fetching ssm parameter '{}'", parameter_name);
+        let region = env::var("AWS_REGION")
+            .or_else(|_| env::var("AWS_DEFAULT_REGION"))
+            .map_err(|_| anyhow!("ssm parameter '{}': AWS_REGION is not set", parameter_name))?;
+        let credentials = self
+            .get_credentials(&region)
+            .await
+            .with_context(|| format!("ssm parameter '{}': aws credentials", parameter_name))?;
+        let endpoint = env::var("AWS_ENDPOINT_URL_SSM").unwrap_or_else(|_| format!("https://ssm.{}.amazonaws.com/", region));
+        let url = Url::parse(&endpoint).with_context(|| format!("invalid ssm endpoint '{}'", endpoint))?;
+        let body = json!({ "Name": parameter_name, "WithDecryption": with_decryption }).to_string();
+
+        let amz_date = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+        let headers = get_signed_headers(&credentials, &region, &url, &amz_date, &body);
+        let mut request = self.client.post(url).body(body);
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        let response = request.send().await.map_err(FetchError::from)?;
+        let status = response.status();
+        let fetch_error = (!status.is_success()).then(|| FetchError::from_response(status, response.headers()));
+        let payload: Value = response.json().await.unwrap_or(Value::Null);
+        if let Some(fetch_error) = fetch_error {
+            // e.g. {"__type":"ParameterNotFound","message":"..."}
+            let error_type = payload.get("__type").and_then(Value::as_str).unwrap_or_default();
+            let message = payload.get("message").or_else(|| payload.get("Message")).and_then(Value::as_str).unwrap_or_default();
+            return Err(Error::from(fetch_error)
+                .context(format!("ssm parameter '{}': GetParameter failed: {} {}", parameter_name, error_type, message)));
+        }
+
+        payload
+            .pointer("/Parameter/Value")
+            .and_then(Value::as_str)
+            .map(str::to_owned)
+            .ok_or_else(|| anyhow!("ssm parameter '{}': response has no Parameter.Value", parameter_name))
+    }
+
+    /// Credentials from environment, web identity or instance profile; temporary ones are reused until renew time
+    async fn get_credentials(&self, region: &str) -> Result<AwsCredentials> {
+        if let Some(credentials) = get_env_credentials() {
+            return Ok(credentials);
+        }
+        let cached = self.credentials.read().await.clone();
+        if let Some(credentials) = cached.filter(|credentials| credentials.renew_at.is_some_and(|renew_at| renew_at > Instant::now())) {
+            return Ok(credentials);
+        }
+
+        let web_identity_token_file = env::var("AWS_WEB_IDENTITY_TOKEN_FILE").ok().filter(|path| !path.is_empty());
+        let credentials = match (web_identity_token_file, env::var("AWS_ROLE_ARN")) {
+            (Some(token_file), Ok(role_arn)) => self.fetch_web_identity_credentials(region, &token_file, &role_arn).await?,
+            _ => self.fetch_instance_profile_credentials().await?,
+        };
+        *self.credentials.write().await = Some(credentials.clone());
+        Ok(credentials)
+    }
+
+    /// IRSA: `AssumeRoleWithWebIdentity` with the projected service account token, the call is not signed
+    async fn fetch_web_identity_credentials(&self, region: &str, token_file: &str, role_arn: &str) -> Result<AwsCredentials> {
+        info!("fetching aws credentials for role '{}' with web identity token", role_arn);
+        let web_identity_token = fs::read_to_string(token_file)
+            .map_err(|e| anyhow!("AWS_WEB_IDENTITY_TOKEN_FILE '{}' is not readable: {}", token_file, e))?;
+        let endpoint = env::var("AWS_ENDPOINT_URL_STS").unwrap_or_else(|_| format!("https://sts.{}.amazonaws.com/", region));
+        let form = [
+            ("Action", AWS_STS_ACTION.to_owned()),
+            ("Version", AWS_STS_API_VERSION.to_owned()),
+            ("RoleArn", role_arn.to_owned()),
+            ("RoleSessionName", env::var("AWS_ROLE_SESSION_NAME").unwrap_or_else(|_| AWS_ROLE_SESSION_NAME_DEFAULT.to_owned())),
+            ("WebIdentityToken", web_identity_token.trim().to_owned()),
+            ("DurationSeconds", AWS_STS_DURATION_SECONDS_DEFAULT.to_string()),
+        ];
+        let response = self.client.post(&endpoint).form(&form).send().await.map_err(FetchError::from)?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(Error::from(FetchError::from_response(status, response.headers())).context(format!("aws sts {} failed", AWS_STS_ACTION)));
+        }
+        let body = response.text().await.map_err(FetchError::from)?;
+        let tokens = parse_credentials(body, Some(0), None).await?;
+        let credential = |token_id: &str| {
+            tokens
+                .iter()
+                .find(|token_context| token_context.id == token_id)
+                .map(|token_context| token_context.token.clone())
+                .ok_or_else(|| anyhow!("aws sts response has no {}", token_id))
+        };
+        let session_token = credential(AWS_SESSION_TOKEN_TOKEN_ID)?;
+        Ok(AwsCredentials::temporary(
+            credential(AWS_ACCESS_KEY_ID_TOKEN_ID)?.value,
+            credential(AWS_SECRET_ACCESS_KEY_TOKEN_ID)?.value,
+            session_token.value,
+            session_token.exp_unix_ts as i64,
+        ))
+    }
+
+    /// EC2 instance profile: role name and its credentials are read from IMDSv2
+    async fn fetch_instance_profile_credentials(&self) -> Result<AwsCredentials> {
+        if env::var("AWS_EC2_METADATA_DISABLED").is_ok_and(|disabled| disabled.eq_ignore_ascii_case("true")) {
+            return Err(anyhow!(
+                "no credentials: AWS_ACCESS_KEY_ID, AWS_WEB_IDENTITY_TOKEN_FILE with AWS_ROLE_ARN are not set and instance metadata is disabled"
+            ));
+        }
+        let endpoint = env::var("AWS_EC2_METADATA_SERVICE_ENDPOINT").unwrap_or_else(|_| AWS_EC2_METADATA_ENDPOINT_DEFAULT.to_owned());
+        let credentials_url = format!("{}{}", endpoint.trim_end_matches('/'), AWS_EC2_CREDENTIALS_PATH);
+        let client = Client::builder().timeout(AWS_EC2_METADATA_TIMEOUT).build()?;
+        let request = RequestConfig { url: credentials_url.clone(), ..Default::default() };
+        let session_token = fetch_imdsv2_session_token(&client, &request)
+            .await
+            .with_context(|| format!("instance metadata at '{}' is not reachable", endpoint))?;
+        let get_metadata = |url: String| {
+            let request = client.get(url).header(IMDSV2_SESSION_TOKEN_HEADER, &session_token);
+            async move {
+                let response = request.send().await.map_err(FetchError::from)?;
+                let status = response.status();
+                if !status.is_success() {
+                    return Err(Error::from(FetchError::from_response(status, response.headers())));
+                }
+                Ok(response.text().await.map_err(FetchError::from)?)
+            }
+        };
+
+        let role_name = get_metadata(credentials_url.clone()).await.context("instance profile role")?;
+        let role_name = role_name
+            .lines()
+            .next()
+            .map(str::trim)
+            .filter(|role_name| !role_name.is_empty())
+            .ok_or_else(|| anyhow!("no instance profile is attached to the instance"))?
+            .to_owned();
+        info!("fetching aws credentials of instance profile role '{}'", role_name);
+        let payload: Value = serde_json::from_str(&get_metadata(format!("{}{}", credentials_url, role_name)).await?)
+            .map_err(|e| anyhow!("instance profile credentials are not JSON: {}", e))?;
+        let field = |name: &str| {
+            payload
+                .get(name)
+                .and_then(Value::as_str)
+                .map(str::to_owned)
+                .ok_or_else(|| anyhow!("instance profile credentials have no {}", name))
+        };
+        let expiration = field("Expiration")?;
+        let exp_unix_ts = chrono::DateTime::parse_from_rfc3339(&expiration)
+            .map_err(|_| anyhow!("instance profile credentials Expiration '{}' is not RFC3339", expiration))?
+            .timestamp();
+        Ok(AwsCredentials::temporary(field("AccessKeyId")?, field("SecretAccessKey")?, field("Token")?, exp_unix_ts))
+    }
+}
+
+/// Static credentials, None when `AWS_ACCESS_KEY_ID` or `AWS_SECRET_ACCESS_KEY` is not set
+fn get_env_credentials() -> Option<AwsCredentials> {
+    let access_key_id = env::var("AWS_ACCESS_KEY_ID").ok().filter(|value| !value.is_empty())?;
+    let secret_access_key = env::var("AWS_SECRET_ACCESS_KEY").ok().filter(|value| !value.is_empty())?;
+    let session_token = env::var("AWS_SESSION_TOKEN").ok().filter(|token| !token.is_empty());
+    Some(AwsCredentials { access_key_id, secret_access_key, session_token, renew_at: None })
+}
+
+/// Request headers of `GetParameter` call with SigV4 `Authorization`, `host` is set by the client
+fn get_signed_headers(credentials: &AwsCredentials, region: &str, url: &Url, amz_date: &str, body: &str) -> Vec<(String, String)> {
+    let host = match url.port() {
+        Some(port) => format!("{}:{}", url.host_str().unwrap_or_default(), port),
+        None => url.host_str().unwrap_or_default().to_owned(),
+    };
+    // sorted by name, as required for the canonical request
+    let mut headers = vec![
+        ("content-type".to_owned(), SSM_CONTENT_TYPE.to_owned()),
+        ("host".to_owned(), host),
+        ("x-amz-date".to_owned(), amz_date.to_owned()),
+    ];
+    if let Some(session_token) = &credentials.session_token {
+        headers.push(("x-amz-security-token".to_owned(), session_token.clone()));
+    }
+    headers.push(("x-amz-target".to_owned(), SSM_GET_PARAMETER_TARGET.to_owned()));
+
+    let request = SignedRequest { method: "POST", url, headers: &headers, amz_date, body };
+    let authorization = get_authorization(credentials, region, SSM_SERVICE, &request);
+    headers.retain(|(name, _)| name != "host");
+    headers.push(("authorization".to_owned(), authorization));
+    headers
+}
+
+/// Request parts covered by the SigV4 signature; `headers` are lowercase, sorted by name and include `host` and `x-amz-date`
+struct SignedRequest<'a> {
+    method: &'a str,
+    url: &'a Url,
+    headers: &'a [(String, String)],
+    amz_date: &'a str,
+    body: &'a str,
+}
+
+/// SigV4 `Authorization` header value
+fn get_authorization(credentials: &AwsCredentials, region: &str, service: &str, request: &SignedRequest) -> String {
+    let SignedRequest { method, url, headers, amz_date, body } = *request;
+    let signed_headers = headers.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>().join(";");
+    let canonical_request = get_canonical_request(method, url, headers, body);
+    let date = &amz_date[..8];
+    let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        to_hex(digest::digest(&digest::SHA256, canonical_request.as_bytes()).as_ref())
+    );
+    let signing_key = get_signing_key(&credentials.secret_access_key, date, region, service);
+    let signature = to_hex(hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, &signing_key), string_to_sign.as_bytes()).as_ref());
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        credentials.access_key_id, scope, signed_headers, signature
+    )
+}
+
+/// Method, path, query (none for SSM), headers, signed header names and hex SHA-256 of the body, one per line
+fn get_canonical_request(method: &str, url: &Url, headers: &[(String, String)], body: &str) -> String {
+    let signed_headers = headers.iter().map(|(name, _)| name.as_str()).collect::<Vec<_>>().join(";");
+    let canonical_headers = headers.iter().map(|(name, value)| format!("{}:{}\n", name, value)).collect::<String>();
+    format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method,
+        url.path(),
+        url.query().unwrap_or_default(),
+        canonical_headers,
+        signed_headers,
+        to_hex(digest::digest(&digest::SHA256, body.as_bytes()).as_ref())
+    )
+}
+
+/// SigV4 signing key: secret -> date -> region -> service -> `aws4_request`
+fn get_signing_key(secret_access_key: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    [date, region, service, "aws4_request"]
+        .iter()
+        .fold(format!("AWS4{}", secret_access_key).into_bytes(), |key, part| {
+            hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, &key), part.as_bytes()).as_ref().to_vec()
+        })
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use httpmock::prelude::*;
+    use serial_test::serial;
+
+    use super::*;
+
+    const EXAMPLE_AMZ_DATE: &str = "20150830T123600Z";
+    const EMPTY_BODY_SHA256: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    fn example_credentials() -> AwsCredentials {
+        AwsCredentials {
+            access_key_id: "AKIDEXAMPLE".to_owned(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_owned(),
+            session_token: None,
+            renew_at: None,
+        }
+    }
+
+    fn example_headers(extra: &[(&str, &str)]) -> Vec<(String, String)> {
+        let mut headers = vec![
+            ("host".to_owned(), "example.amazonaws.com".to_owned()),
+            ("x-amz-date".to_owned(), EXAMPLE_AMZ_DATE.to_owned()),
+        ];
+        headers.extend(extra.iter().map(|(name, value)| (name.to_string(), value.to_string())));
+        headers.sort();
+        headers
+    }
+
+    /// Env credentials off, cached temporary credentials dropped, SSM and STS served by `server`
+    async fn set_aws_env_without_credentials(server: &MockServer) {
+        for name in ["AWS_ACCESS_KEY_ID", "AWS_SECRET_ACCESS_KEY", "AWS_SESSION_TOKEN", "AWS_WEB_IDENTITY_TOKEN_FILE", "AWS_ROLE_ARN"] {
+            env::remove_var(name);
+        }
+        env::set_var("AWS_REGION", "us-east-1");
+        env::set_var("AWS_ENDPOINT_URL_SSM", server.url("/ssm"));
+        env::set_var("AWS_ENDPOINT_URL_STS", server.url("/sts"));
+        *get_ssm_cache().await.credentials.write().await = None;
+    }
+
+    async fn mock_ssm_parameter<'a>(server: &'a MockServer, name: &str, key_id: &str, value: &str) -> httpmock::Mock<'a> {
+        let key_id = key_id.to_owned();
+        let (name, value) = (name.to_owned(), value.to_owned());
+        server
+            .mock_async(|when, then| {
+                when.method(POST)
+                    .path("/ssm")
+                    .header_matches("^authorization$", format!("^AWS4-HMAC-SHA256 Credential={}/", key_id))
+                    .json_body(json!({ "Name": name, "WithDecryption": true }));
+                then.status(200).json_body(json!({ "Parameter": { "Name": name, "Value": value } }));
+            })
+            .await
+    }
+
+    fn set_aws_env(endpoint: &str) {
+        env::remove_var("AWS_WEB_IDENTITY_TOKEN_FILE");
+        env::set_var("AWS_ACCESS_KEY_ID", "AKIDEXAMPLE");
+        env::set_var("AWS_SECRET_ACCESS_KEY", "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY");
+        env::set_var("AWS_SESSION_TOKEN", "session-token");
+        env::set_var("AWS_REGION", "us-east-1");
+        env::set_var("AWS_ENDPOINT_URL_SSM", endpoint);
+    }
+
+    #[test]
+    fn signing_key_matches_aws_example() {
+        // AWS Signature Version 4 documentation example
+        let key = get_signing_key("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY", "20150830", "us-east-1", "iam");
+        assert_eq!(to_hex(&key), "c4afb1cc5771d871763a393e44b703571b55cc28424d1a5e86da6ed3c154a4b9");
+    }
+
+    #[test]
+    fn get_vanilla_matches_aws_test_suite() {
+        // aws-sig-v4-test-suite get-vanilla
+        let url = Url::parse("https://example.amazonaws.com/").unwrap();
+        let headers = example_headers(&[]);
+        assert_eq!(
+            get_canonical_request("GET", &url, &headers, ""),
+            format!("GET\n/\n\nhost:example.amazonaws.com\nx-amz-date:20150830T123600Z\n\nhost;x-amz-date\n{}", EMPTY_BODY_SHA256)
+        );
+        assert_eq!(
+            get_authorization(
+                &example_credentials(),
+                "us-east-1",
+                "service",
+                &SignedRequest { method: "GET", url: &url, headers: &headers, amz_date: EXAMPLE_AMZ_DATE, body: "" }
+            ),
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, SignedHeaders=host;x-amz-date, \
+             Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+    }
+
+    #[test]
+    fn post_vanilla_matches_aws_test_suite() {
+        // aws-sig-v4-test-suite post-vanilla
+        let url = Url::parse("https://example.amazonaws.com/").unwrap();
+        let headers = example_headers(&[]);
+        assert_eq!(
+            get_canonical_request("POST", &url, &headers, ""),
+            format!("POST\n/\n\nhost:example.amazonaws.com\nx-amz-date:20150830T123600Z\n\nhost;x-amz-date\n{}", EMPTY_BODY_SHA256)
+        );
+        assert_eq!(
+            get_authorization(
+                &example_credentials(),
+                "us-east-1",
+                "service",
+                &SignedRequest { method: "POST", url: &url, headers: &headers, amz_date: EXAMPLE_AMZ_DATE, body: "" }
+            ),
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, SignedHeaders=host;x-amz-date, \
+             Signature=5da7c1a2acd57cee7505fc6676e4e544621c30862966e37dddb68e92efbe5d6b"
+        );
+    }
+
+    #[test]
+    fn post_with_body_matches_aws_test_suite() {
+        // aws-sig-v4-test-suite post-x-www-form-urlencoded
+        let url = Url::parse("https://example.amazonaws.com/").unwrap();
+        let headers = example_headers(&[("content-type", "application/x-www-form-urlencoded")]);
+        let canonical_request = get_canonical_request("POST", &url, &headers, "Param1=value1");
+        assert_eq!(
+            canonical_request,
+            "POST\n/\n\ncontent-type:application/x-www-form-urlencoded\nhost:example.amazonaws.com\nx-amz-date:20150830T123600Z\n\n\
+             content-type;host;x-amz-date\n9095672bbd1f56dfc5b65f3e153adc8731a4a654192329106275f4c7b24d0b6e"
+        );
+        assert_eq!(
+            to_hex(digest::digest(&digest::SHA256, canonical_request.as_bytes()).as_ref()),
+            "42a5e5bb34198acb3e84da4f085bb7927f2bc277ca766e6d19c73c2154021281"
+        );
+        assert_eq!(
+            get_authorization(
+                &example_credentials(),
+                "us-east-1",
+                "service",
+                &SignedRequest { method: "POST", url: &url, headers: &headers, amz_date: EXAMPLE_AMZ_DATE, body: "Param1=value1" }
+            ),
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, SignedHeaders=content-type;host;x-amz-date, \
+             Signature=ff11897932ad3f4e8b18135d722051e5ac45fc38421b1da7b9d196a0fe09473a"
+        );
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn parameter_is_fetched_once_within_ttl() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(POST)
+                    .path("/")
+                    .header("x-amz-target", SSM_GET_PARAMETER_TARGET)
+                    .header("x-amz-security-token", "session-token")
+                    .header_matches("^authorization$", r"^AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/\d{8}/us-east-1/ssm/aws4_request, SignedHeaders=content-type;host;x-amz-date;x-amz-security-token;x-amz-target, Signature=[0-9a-f]{64}$")
+                    .json_body(json!({ "Name": "/ssm-cache-test/client-id", "WithDecryption": true }));
+                then.status(200).json_body(json!({
+                    "Parameter": { "Name": "/ssm-cache-test/client-id", "Type": "SecureString", "Value": "client-id-value" }
+                }));
+            })
+            .await;
+        set_aws_env(&server.url("/"));
+
+        assert_eq!(SsmCache::get("/ssm-cache-test/client-id", true).await.unwrap(), "client-id-value");
+        assert_eq!(SsmCache::get("/ssm-cache-test/client-id", true).await.unwrap(), "client-id-value");
+        mock.assert_calls_async(1).await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn decrypted_and_raw_values_are_cached_apart() {
+        let server = MockServer::start_async().await;
+        let decrypted = server
+            .mock_async(|when, then| {
+                when.method(POST).path("/").json_body(json!({ "Name": "/ssm-cache-test/secret", "WithDecryption": true }));
+                then.status(200).json_body(json!({ "Parameter": { "Name": "/ssm-cache-test/secret", "Value": "plain-secret" } }));
+            })
+            .await;
+        let raw = server
+            .mock_async(|when, then| {
+                when.method(POST).path("/").json_body(json!({ "Name": "/ssm-cache-test/secret", "WithDecryption": false }));
+                then.status(200).json_body(json!({ "Parameter": { "Name": "/ssm-cache-test/secret", "Value": "AQICAHencrypted" } }));
+            })
+            .await;
+        set_aws_env(&server.url("/"));
+
+        assert_eq!(SsmCache::get("/ssm-cache-test/secret", true).await.unwrap(), "plain-secret");
+        assert_eq!(SsmCache::get("/ssm-cache-test/secret", false).await.unwrap(), "AQICAHencrypted");
+        assert_eq!(SsmCache::get("/ssm-cache-test/secret", true).await.unwrap(), "plain-secret");
+        decrypted.assert_calls_async(1).await;
+        raw.assert_calls_async(1).await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn web_identity_credentials_are_fetched_once_and_reused() {
+        let server = MockServer::start_async().await;
+        set_aws_env_without_credentials(&server).await;
+        let token_file = tempfile::NamedTempFile::new().unwrap();
+        fs::write(token_file.path(), "projected-sa-token\n").unwrap();
+        env::set_var("AWS_WEB_IDENTITY_TOKEN_FILE", token_file.path());
+        env::set_var("AWS_ROLE_ARN", "arn:aws:iam::123456789012:role/token-agent");
+        let expiration = (chrono::Utc::now() + chrono::Duration::hours(1)).to_rfc3339();
+        let sts = server
+            .mock_async(|when, then| {
+                when.method(POST)
+                    .path("/sts")
+                    .form_urlencoded_tuple("Action", AWS_STS_ACTION)
+                    .form_urlencoded_tuple("RoleArn", "arn:aws:iam::123456789012:role/token-agent")
+                    .form_urlencoded_tuple("WebIdentityToken", "projected-sa-token");
+                then.status(200).body(format!(
+                    "<AssumeRoleWithWebIdentityResponse><AssumeRoleWithWebIdentityResult><Credentials>\
+                     <AccessKeyId>ASIAIRSA</AccessKeyId><SecretAccessKey>irsa-secret</SecretAccessKey>\
+                     <SessionToken>irsa-session</SessionToken><Expiration>{}</Expiration>\
+                     </Credentials></AssumeRoleWithWebIdentityResult></AssumeRoleWithWebIdentityResponse>",
+                    expiration
+                ));
+            })
+            .await;
+        let first = mock_ssm_parameter(&server, "/ssm-cache-test/irsa-a", "ASIAIRSA", "a").await;
+        let second = mock_ssm_parameter(&server, "/ssm-cache-test/irsa-b", "ASIAIRSA", "b").await;
+
+        assert_eq!(SsmCache::get("/ssm-cache-test/irsa-a", true).await.unwrap(), "a");
+        assert_eq!(SsmCache::get("/ssm-cache-test/irsa-b", true).await.unwrap(), "b");
+        sts.assert_calls_async(1).await;
+        first.assert_calls_async(1).await;
+        second.assert_calls_async(1).await;
+
+        env::remove_var("AWS_WEB_IDENTITY_TOKEN_FILE");
+        env::remove_var("AWS_ROLE_ARN");
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn instance_profile_credentials_are_read_from_imdsv2() {
+        let server = MockServer::start_async().await;
+        set_aws_env_without_credentials(&server).await;
+        env::set_var("AWS_EC2_METADATA_SERVICE_ENDPOINT", server.base_url());
+        let expiration = (chrono::Utc::now() + chrono::Duration::hours(6)).to_rfc3339();
+        let session = server
+            .mock_async(|when, then| {
+                when.method(PUT).path("/latest/api/token");
+                then.status(200).body("imds-session");
+            })
+            .await;
+        let role = server
+            .mock_async(|when, then| {
+                when.method(GET).path(AWS_EC2_CREDENTIALS_PATH).header(IMDSV2_SESSION_TOKEN_HEADER, "imds-session");
+                then.status(200).body("instance-role\n");
+            })
+            .await;
+        let credentials = server
+            .mock_async(|when, then| {
+                when.method(GET)
+                    .path(format!("{}instance-role", AWS_EC2_CREDENTIALS_PATH))
+                    .header(IMDSV2_SESSION_TOKEN_HEADER, "imds-session");
+                then.status(200).json_body(json!({
+                    "Code": "Success",
+                    "AccessKeyId": "ASIAEC2",
+                    "SecretAccessKey": "ec2-secret",
+                    "Token": "ec2-session",
+                    "Expiration": expiration
+                }));
+            })
+            .await;
+        let parameter = mock_ssm_parameter(&server, "/ssm-cache-test/ec2", "ASIAEC2", "from-ec2").await;
+
+        assert_eq!(SsmCache::get("/ssm-cache-test/ec2", true).await.unwrap(), "from-ec2");
+        session.assert_calls_async(1).await;
+        role.assert_calls_async(1).await;
+        credentials.assert_calls_async(1).await;
+        parameter.assert_calls_async(1).await;
+
+        env::remove_var("AWS_EC2_METADATA_SERVICE_ENDPOINT");
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn no_credentials_is_error() {
+        let server = MockServer::start_async().await;
+        set_aws_env_without_credentials(&server).await;
+        env::set_var("AWS_EC2_METADATA_DISABLED", "true");
+
+        let err = SsmCache::get("/ssm-cache-test/no-credentials", true).await.unwrap_err();
+        assert!(format!("{:#}", err).contains("instance metadata is disabled"), "{:#}", err);
+
+        env::remove_var("AWS_EC2_METADATA_DISABLED");
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn missing_parameter_is_error_and_not_cached() {
+        let server = MockServer::start_async().await;
+        let mock = server
+            .mock_async(|when, then| {
+                when.method(POST).path("/");
+                then.status(400).json_body(json!({ "__type": "ParameterNotFound", "message": "not found" }));
+            })
+            .await;
+        set_aws_env(&server.url("/"));
+
+        let err = SsmCache::get("/ssm-cache-test/missing", false).await.unwrap_err();
+        assert!(format!("{:#}", err).contains("ParameterNotFound"), "{:#}", err);
+        assert!(SsmCache::get("/ssm-cache-test/missing", false).await.is_err());
+        mock.assert_calls_async(2).await;
+        let failures = get_metrics().await.ssm_fetch_failures.with_label_values(&["/ssm-cache-test/missing"]).get();
+        assert_eq!(failures, 2);
+    }
+}
@@ -1,7 +1,12 @@
+use std::fmt;
+
+use ring::digest;
+use serde::{Deserialize, Serialize};
 
 pub const TOKEN_VALUE_STUB: &'static str  = "";
 
-#[derive(Debug, Clone)]
+/// Debug and Display print `fingerprint()` instead of the value
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Token {
     pub value: String,
     pub exp_unix_ts: u64, // UNIX TIMESTAMP
@@ -12,4 +17,44 @@ impl Token {
     pub fn new(value: String, exp_unix_ts: u64) -> Self {
         Self {value, exp_unix_ts}
     }
-}
\ No newline at end of file
+
+    /// First 8 hex chars of the value sha256 and its length, e.g. `sha256:9f86d081 len=4`
+    pub fn fingerprint(&self) -> String {
+        fingerprint(&self.value)
+    }
+}
+
+/// Loggable stand-in for a secret value
+pub fn fingerprint(value: &str) -> String {
+    let hash = digest::digest(&digest::SHA256, value.as_bytes());
+    let prefix: String = hash.as_ref()[..4].iter().map(|b| format!("{:02x}", b)).collect();
+    format!("sha256:{} len={}", prefix, value.len())
+}
+
+impl fmt::Debug for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Token")
+            .field("value", &format_args!("{}", self.fingerprint()))
+            .field("exp_unix_ts", &self.exp_unix_ts)
+            .finish()
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.fingerprint())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_formatting_prints_fingerprint_only() {
+        let token = Token::new("test".to_string(), 42);
+        assert_eq!(token.fingerprint(), "sha256:9f86d081 len=4");
+        assert_eq!(token.to_string(), "sha256:9f86d081 len=4");
+        assert_eq!(format!("{:?}", token), "Token { value: sha256:9f86d081 len=4, exp_unix_ts: 42 }");
+    }
+}
@@ -1,9 +1,17 @@
 use anyhow::Result;
-use tracing::{debug};
+use tracing::{debug, info};
 use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::broadcast::{self, Receiver, Sender};
 use tokio::sync::{OnceCell, RwLock};
 
-use crate::{cache::token_context::TokenContext, observability::metrics::get_metrics};
+use crate::{cache::{persistent_cache::{PersistentCache, PersistentWrite}, token_context::TokenContext}, observability::metrics::get_metrics};
+use crate::cache::token_event::{TokenEvent, TokenEventKind, TokenSubscription};
+use crate::helpers::time::now_i64;
+use crate::observability::audit::{self, AuditEvent, AuditEventType};
+use crate::resilience::fallback::{keep_as_stale, StaleFallback};
+
+const TOKEN_EVENTS_BUFFER_SIZE: usize = 256;
 
 
 // Declare the static OnceCell to hold the TokenCache.
@@ -20,24 +28,75 @@ async fn get_token_cache() -> &'static TokenCache {
 
 
 /// Token cache: source_name -> token_id -> TokenContext
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct TokenCache {
     inner: RwLock<HashMap<String, HashMap<String, TokenContext>>>,
+    /// replaced tokens kept for `rotation_overlap_seconds`: `{source_id}:{token_id}:prev` -> TokenContext
+    previous: RwLock<HashMap<String, TokenContext>>,
+    events: Sender<TokenEvent>,
+}
+
+impl Default for TokenCache {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl TokenCache {
     pub fn new() -> Self {
+        let (events, _) = broadcast::channel(TOKEN_EVENTS_BUFFER_SIZE);
         Self {
             inner: RwLock::new(HashMap::new()),
+            previous: RwLock::new(HashMap::new()),
+            events,
+        }
+    }
+
+    /// Events of a single token: `Updated` when it is stored with new value or expiration, `Removed` when it is dropped
+    pub async fn subscribe(source_id: &str, token_id: &str) -> TokenSubscription {
+        TokenSubscription::new(source_id, token_id, TokenCache::subscribe_all().await)
+    }
+
+    /// Events of all tokens
+    pub async fn subscribe_all() -> Receiver<TokenEvent> {
+        get_token_cache().await.events.subscribe()
+    }
+
+    fn emit(&self, events: Vec<TokenEvent>) {
+        for event in events {
+            debug!("token event {:?}: source_id {} token_id {}", event.kind, event.source_id, event.token_id);
+            if event.kind == TokenEventKind::Removed {
+                audit::emit(
+                    AuditEvent::new(AuditEventType::Invalidation, &event.source_id)
+                        .token(&event.token_id, Some(event.token_context.token.exp_unix_ts)),
+                );
+            }
+            // no subscribers is not an error
+            let _ = self.events.send(event);
         }
     }
 
-    /// Insert or update a token
+    /// Insert or update a token, `Updated` event is emitted for new tokens and tokens with changed value or expiration
     pub async fn set(source_id: String, source_token_contexts: Vec<TokenContext>) -> Result<Vec<String>> {
-        let mut guard = get_token_cache().await.inner.write().await;
+        TokenCache::set_with_overlap(source_id, source_token_contexts, None).await
+    }
+
+    /// Same as `set`, a token replaced by a new value is kept as previous for `rotation_overlap_seconds`
+    #[tracing::instrument(name = "cache.set", skip_all, fields(source.id = %source_id, tokens = source_token_contexts.len()))]
+    pub async fn set_with_overlap(
+        source_id: String,
+        source_token_contexts: Vec<TokenContext>,
+        rotation_overlap_seconds: Option<u64>,
+    ) -> Result<Vec<String>> {
+        // L2 write is queued under the L1 lock, so L2 is changed in the same order as L1
+        let persist = PersistentCache::instance().map(|cache| (cache, source_token_contexts.clone()));
+        let token_cache = get_token_cache().await;
+        let mut guard = token_cache.inner.write().await;
         let source_map = guard.entry(source_id.to_owned()).or_default();
         
         let mut updated_tokens: Vec<String> = Vec::new();
+        let mut events: Vec<TokenEvent> = Vec::new();
+        let mut replaced: Vec<TokenContext> = Vec::new();
         
         source_token_contexts.into_iter()
 
@@ -46,49 +105,240 @@ impl TokenCache {
 
                 match source_map.get_mut(&token_context.id){
                     Some(existing_token_context) => {                
+                        if existing_token_context.token.value != token_context.token.value
+                            || existing_token_context.token.exp_unix_ts != token_context.token.exp_unix_ts
+                        {
+                            events.push(TokenEvent::updated(&source_id, token_context.clone()));
+                            if existing_token_context.token.value != token_context.token.value {
+                                replaced.push(existing_token_context.clone());
+                            }
+                        }
                         *existing_token_context = token_context;
                     },
                     None => {
                         debug!("inserted token token_id {} exp {} fetched_at_unix_ts: {}", &token_context.id, &token_context.token.exp_unix_ts, &token_context.fetched_at_unix_ts);
+                        events.push(TokenEvent::updated(&source_id, token_context.clone()));
                         source_map.insert(token_context.id.to_owned(), token_context);
                     },
                 }
         });
         get_metrics().await.cached_tokens.with_label_values(&[&source_id.as_str()]).set(source_map.values().len() as i64);
+        get_metrics().await.cached_stale_tokens.with_label_values(&[&source_id.as_str()]).set(count_stale(source_map));
+        if let Some((cache, token_contexts)) = persist {
+            cache.queue(PersistentWrite::Set { source_id: source_id.to_owned(), token_contexts });
+        }
+        drop(guard);
+        if let Some(overlap_seconds) = rotation_overlap_seconds.filter(|overlap_seconds| *overlap_seconds > 0) {
+            token_cache.keep_previous(&source_id, replaced, overlap_seconds).await;
+        }
+        token_cache.emit(events);
         Ok(updated_tokens)
     }
 
-    /// Get token by source_id and token_id
+    /// Store replaced tokens under shadow keys, each one is removed once the overlap elapses
+    async fn keep_previous(&self, source_id: &str, replaced: Vec<TokenContext>, overlap_seconds: u64) {
+        let mut guard = self.previous.write().await;
+        for token_context in replaced {
+            let key = previous_key(source_id, &token_context.id);
+            debug!("keeping previous token {} for {} seconds", key, overlap_seconds);
+            guard.insert(key.to_owned(), token_context.clone());
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_secs(overlap_seconds)).await;
+                let mut guard = get_token_cache().await.previous.write().await;
+                // a later rotation may have replaced the entry, its own timer removes it
+                if guard.get(&key).is_some_and(|previous| previous.token.value == token_context.token.value) {
+                    guard.remove(&key);
+                    debug!("previous token {} removed, rotation overlap elapsed", key);
+                }
+            });
+        }
+    }
+
+    /// Token replaced or invalidated last for `source_id.token_id` while its overlap lasts. It may be expired already:
+    /// the overlap window, not the token expiration, bounds how long it is served
+    pub async fn get_previous(source_id: &str, token_id: &str) -> Option<TokenContext> {
+        let guard = get_token_cache().await.previous.read().await;
+        guard.get(&previous_key(source_id, token_id)).cloned()
+    }
+
+    /// Load non-expired tokens from persistent cache, returns source ids of loaded tokens
+    pub async fn warm_from_persistent() -> Result<Vec<String>> {
+        let Some(cache) = PersistentCache::instance() else {
+            return Ok(Vec::with_capacity(0));
+        };
+        TokenCache::warm_from(cache).await
+    }
+
+    pub(crate) async fn warm_from(cache: &PersistentCache) -> Result<Vec<String>> {
+        let entries = cache.get_all()?;
+
+        let mut guard = get_token_cache().await.inner.write().await;
+        let mut source_ids: Vec<String> = Vec::new();
+        for (source_id, token_context) in entries.into_iter().filter(|(_, token_context)| !token_context.should_remove()) {
+            debug!("warm token cache: source_id {} token_id {}", source_id, token_context.id);
+            if !source_ids.contains(&source_id) {
+                source_ids.push(source_id.to_owned());
+            }
+            guard.entry(source_id).or_default().insert(token_context.id.to_owned(), token_context);
+        }
+        info!("token cache warmed from persistent cache, sources: {:?}", source_ids);
+        Ok(source_ids)
+    }
+
+    /// Get token by source_id and token_id; persistent cache is not read, it is loaded into L1 on start
     pub async fn get(source_id: &str, token_id: &str) -> Option<TokenContext> {
         let guard = get_token_cache().await.inner.read().await;
         guard.get(source_id).and_then(|m| m.get(token_id).cloned())
     }
 
+    /// Snapshot of all tokens as (source_id, token_context)
+    pub async fn get_all() -> Vec<(String, TokenContext)> {
+        let guard = get_token_cache().await.inner.read().await;
+        guard
+            .iter()
+            .flat_map(|(source_id, source_map)| {
+                source_map.values().map(move |token_context| (source_id.to_owned(), token_context.clone()))
+            })
+            .collect()
+    }
+
+    /// All tokens of source ordered by token id, empty if source is unknown
+    pub async fn get_all_by_source_id(source_id: &str) -> Vec<TokenContext> {
+        let guard = get_token_cache().await.inner.read().await;
+        let mut token_contexts: Vec<TokenContext> =
+            guard.get(source_id).map(|source_map| source_map.values().cloned().collect()).unwrap_or_default();
+        token_contexts.sort_by(|a, b| a.id.cmp(&b.id));
+        token_contexts
+    }
+
+    /// Ids of sources with cached tokens, sorted
+    pub async fn list_source_ids() -> Vec<String> {
+        let guard = get_token_cache().await.inner.read().await;
+        let mut source_ids: Vec<String> = guard.keys().cloned().collect();
+        source_ids.sort();
+        source_ids
+    }
+
+    /// Mark all tokens of source for immediate re-fetch,
+    /// current tokens are served until replaced
+    pub async fn force_refresh_by_source_id(source_id: &str) -> bool {
+        let mut guard = get_token_cache().await.inner.write().await;
+        match guard.get_mut(source_id) {
+            Some(source_map) => {
+                source_map.values_mut().for_each(|token_context| token_context.fetched_at_unix_ts = 0);
+                true
+            }
+            None => false,
+        }
+    }
+
     /// Check if source_id exists
     pub async fn contains_source_id(source_id: &str) -> bool {
         let guard = get_token_cache().await.inner.read().await;
         guard.contains_key(source_id)
     }
 
-    /// Invalidate token by source_id
+    /// Remove all tokens of source, e.g. source removed from config, `Removed` event is emitted for each token
+    pub async fn remove_by_source_id(source_id: &str) -> bool {
+        let removed = TokenCache::take_by_source_id(source_id).await;
+        get_token_cache().await.emit(
+            removed
+                .iter()
+                .flatten()
+                .map(|token_context| TokenEvent::removed(source_id, token_context.clone()))
+                .collect(),
+        );
+        removed.is_some()
+    }
+
+    /// Remove all tokens of source without emitting events, the caller notifies sinks.
+    /// None if the source has no cached tokens
+    pub(crate) async fn take_by_source_id(source_id: &str) -> Option<Vec<TokenContext>> {
+        let token_cache = get_token_cache().await;
+        let mut guard = token_cache.inner.write().await;
+        let removed = guard.remove(source_id);
+        if let Some(cache) = PersistentCache::instance() {
+            cache.queue(PersistentWrite::RemoveSource { source_id: source_id.to_owned() });
+        }
+        drop(guard);
+        let source_prefix = format!("{}:", source_id);
+        token_cache.previous.write().await.retain(|key, _| !key.starts_with(&source_prefix));
+        let metrics = get_metrics().await;
+        metrics.cached_tokens.with_label_values(&[source_id]).set(0);
+        metrics.cached_stale_tokens.with_label_values(&[source_id]).set(0);
+        removed.map(|source_map| source_map.into_values().collect())
+    }
+
+    /// Invalidate token by source_id, `Removed` event is emitted for each expired token
     pub async fn invalidate_expired_tokens_by_source_id(source_id: &str) -> bool {
-        let mut guard = get_token_cache().await.inner.write().await;
+        TokenCache::invalidate_expired_tokens_with_stale(source_id, None, None).await
+    }
+
+    /// Same as `invalidate_expired_tokens_by_source_id`, while the last fetch of the source failed an expired token
+    /// is kept as stale for `stale_token_ttl_seconds` more and `Updated` event is emitted for it.
+    /// A removed token is kept as previous for `rotation_overlap_seconds`, sinks serve it until the refetch stores the new one
+    pub async fn invalidate_expired_tokens_with_stale(
+        source_id: &str,
+        stale_token_ttl_seconds: Option<u64>,
+        rotation_overlap_seconds: Option<u64>,
+    ) -> bool {
+        let last_fetch_failed = StaleFallback::last_fetch_failed(source_id).await;
+        let token_cache = get_token_cache().await;
+        let mut guard = token_cache.inner.write().await;
         if !guard.contains_key(source_id) {
             return false;
         }
         let source_map = guard.get_mut(source_id).unwrap();
-        source_map.retain(|_, token_context| !token_context.should_remove());
+        let mut events: Vec<TokenEvent> = Vec::new();
+        let mut stale_tokens: Vec<TokenContext> = Vec::new();
+        let mut removed: Vec<TokenContext> = Vec::new();
+        source_map.retain(|_, token_context| {
+            if !token_context.should_remove() {
+                return true;
+            }
+            match keep_as_stale(source_id, token_context, stale_token_ttl_seconds, last_fetch_failed) {
+                Some(stale) => {
+                    events.push(TokenEvent::updated(source_id, stale.clone()));
+                    stale_tokens.push(stale.clone());
+                    *token_context = stale;
+                    true
+                }
+                None => {
+                    events.push(TokenEvent::removed(source_id, token_context.clone()));
+                    removed.push(token_context.clone());
+                    false
+                }
+            }
+        });
+        get_metrics().await.cached_stale_tokens.with_label_values(&[source_id]).set(count_stale(source_map));
+        if let Some(cache) = PersistentCache::instance() {
+            let token_ids = removed.iter().map(|token_context| token_context.id.to_owned()).collect();
+            cache.queue(PersistentWrite::Remove { source_id: source_id.to_owned(), token_ids });
+            // stale tokens survive a restart during the outage
+            if !stale_tokens.is_empty() {
+                cache.queue(PersistentWrite::Set { source_id: source_id.to_owned(), token_contexts: stale_tokens });
+            }
+        }
+        drop(guard);
+        if let Some(overlap_seconds) = rotation_overlap_seconds.filter(|overlap_seconds| *overlap_seconds > 0) {
+            token_cache.keep_previous(source_id, removed, overlap_seconds).await;
+        }
+        token_cache.emit(events);
         true
     }
     
     pub async fn process_metrics() -> () {
         let metrics = get_metrics().await;
         let guard = get_token_cache().await.inner.read().await;
+        let now = now_i64();
         guard.iter().for_each(|(source_id, source_map)| {
             metrics.cached_tokens.with_label_values(&[&source_id.as_str()]).set(source_map.values().len() as i64);
+            metrics.cached_stale_tokens.with_label_values(&[&source_id.as_str()]).set(count_stale(source_map));
             source_map.iter().for_each(|(token_id, token_context)|{
                 metrics.token_expiry_unix.with_label_values(&[&source_id.as_str(), &token_id.as_str()])
                 .set(token_context.token.exp_unix_ts as i64);
+                metrics.token_seconds_until_expiry.with_label_values(&[&source_id.as_str(), &token_id.as_str()])
+                .set((token_context.token.exp_unix_ts as i64 - now).max(0));
             });
         });        
     }
@@ -96,6 +346,7 @@ impl TokenCache {
     pub async fn cleanup() -> () {
         let mut guard = get_token_cache().await.inner.write().await;
         guard.clear();
+        get_token_cache().await.previous.write().await.clear();
     }
 
     pub async fn println() -> () {
@@ -104,3 +355,94 @@ impl TokenCache {
     }
 
 }
+
+/// Tokens of a source served as stale
+fn count_stale(source_map: &HashMap<String, TokenContext>) -> i64 {
+    source_map.values().filter(|token_context| token_context.is_stale).count() as i64
+}
+
+/// Shadow key of the previous token, `{source_id}:{token_id}:prev`
+pub fn previous_key(source_id: &str, token_id: &str) -> String {
+    format!("{}:{}:prev", source_id, token_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serial_test::serial;
+
+    use crate::cache::token::Token;
+    use crate::cache::token_cache::TokenCache;
+    use crate::cache::token_context::TokenContext;
+
+    fn token(id: &str, value: &str) -> TokenContext {
+        TokenContext::new(id.into(), Token::new(value.into(), 4_102_444_800), 10)
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn unknown_source_and_cleared_cache_give_nothing() {
+        TokenCache::set("list_cleared".into(), vec![token("a", "1")]).await.unwrap();
+        TokenCache::cleanup().await;
+
+        assert!(TokenCache::get_all_by_source_id("list_cleared").await.is_empty());
+        assert!(TokenCache::get_all_by_source_id("list_unknown").await.is_empty());
+        assert!(!TokenCache::list_source_ids().await.iter().any(|id| id == "list_cleared"));
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn single_source_tokens_listed_by_token_id() {
+        TokenCache::cleanup().await;
+        TokenCache::set("list_single".into(), vec![token("b", "2"), token("c", "3"), token("a", "1")]).await.unwrap();
+
+        let tokens = TokenCache::get_all_by_source_id("list_single").await;
+
+        let tokens: Vec<(&str, &str)> = tokens.iter().map(|t| (t.id.as_str(), t.token.value.as_str())).collect();
+        assert_eq!(tokens, [("a", "1"), ("b", "2"), ("c", "3")]);
+        assert!(TokenCache::list_source_ids().await.contains(&"list_single".to_string()));
+        TokenCache::cleanup().await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn multiple_sources_listed_separately() {
+        TokenCache::cleanup().await;
+        TokenCache::set("list_second".into(), vec![token("a", "second")]).await.unwrap();
+        TokenCache::set("list_first".into(), vec![token("a", "first"), token("b", "first")]).await.unwrap();
+
+        let source_ids: Vec<String> =
+            TokenCache::list_source_ids().await.into_iter().filter(|id| id.starts_with("list_")).collect();
+        assert_eq!(source_ids, ["list_first", "list_second"]);
+        assert_eq!(TokenCache::get_all_by_source_id("list_first").await.len(), 2);
+        let second = TokenCache::get_all_by_source_id("list_second").await;
+        assert_eq!((second.len(), second[0].token.value.as_str()), (1, "second"));
+
+        TokenCache::remove_by_source_id("list_first").await;
+        assert!(!TokenCache::list_source_ids().await.contains(&"list_first".to_string()));
+        assert!(TokenCache::get_all_by_source_id("list_first").await.is_empty());
+        TokenCache::cleanup().await;
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn replaced_token_kept_as_previous_for_overlap() {
+        TokenCache::cleanup().await;
+        TokenCache::set("overlap".into(), vec![token("a", "old")]).await.unwrap();
+        TokenCache::set("overlap".into(), vec![token("a", "new")]).await.unwrap();
+        // no overlap configured
+        assert!(TokenCache::get_previous("overlap", "a").await.is_none());
+
+        TokenCache::set_with_overlap("overlap".into(), vec![token("a", "newer")], Some(1)).await.unwrap();
+        assert_eq!(TokenCache::get("overlap", "a").await.unwrap().token.value, "newer");
+        assert_eq!(TokenCache::get_previous("overlap", "a").await.unwrap().token.value, "new");
+        // same value stored again is not a rotation
+        TokenCache::set_with_overlap("overlap".into(), vec![token("a", "newer")], Some(1)).await.unwrap();
+        assert_eq!(TokenCache::get_previous("overlap", "a").await.unwrap().token.value, "new");
+
+        tokio::time::sleep(Duration::from_millis(1200)).await;
+        assert!(TokenCache::get_previous("overlap", "a").await.is_none());
+        TokenCache::cleanup().await;
+    }
+}
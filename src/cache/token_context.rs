@@ -1,13 +1,30 @@
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
 use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use crate::cache::token::Token;
 
 /// Token structure
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TokenContext {
     pub id: String,                     // unique token id per source
     pub token: Token,                   // token
-    /// token fetching start at
+    /// token refresh starts at: expiration minus safety margin
     pub fetched_at_unix_ts: u64,        // unix seconds
+    /// token was received from its source at
+    #[serde(default)]
+    pub received_at_unix_ts: u64,       // unix seconds
+    /// JWT claims listed in `extract_claims` of the token field
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub claims: Option<HashMap<String, Value>>,
+    /// JWT `nbf` minus clock skew, set when the token is not yet valid at parse time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub not_before_unix_ts: Option<u64>,
+    /// expired token kept by `stale_token_ttl_seconds` while its source fails, see `resilience::fallback`
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub is_stale: bool,
 }
 
 impl TokenContext {
@@ -24,13 +41,72 @@ impl TokenContext {
             id,
             token,
             fetched_at_unix_ts: fetched_at_unix_ts as u64,
+            received_at_unix_ts: Utc::now().timestamp() as u64,
+            claims: None,
+            not_before_unix_ts: None,
+            is_stale: false,
         }
     }
+
+    /// Recompute update time for a margin known only after the token is parsed
+    pub fn with_safety_margin(mut self, safety_margin_seconds: u64) -> Self {
+        self.fetched_at_unix_ts = self.token.exp_unix_ts.saturating_sub(safety_margin_seconds);
+        self
+    }
+
+    pub fn with_claims(mut self, claims: Option<HashMap<String, Value>>) -> Self {
+        self.claims = claims;
+        self
+    }
+
+    pub fn with_not_before(mut self, not_before_unix_ts: Option<u64>) -> Self {
+        self.not_before_unix_ts = not_before_unix_ts;
+        self
+    }
+
+    /// Check if token is already valid, false for a JWT with `nbf` in the future
+    pub fn is_active(&self) -> bool {
+        self.not_before_unix_ts
+            .is_none_or(|not_before| Utc::now().timestamp() as u64 >= not_before)
+    }
     
+    /// Unix ts when token should be updated: expiration minus safety margin
+    pub fn should_update_at(&self) -> i64 {
+        self.fetched_at_unix_ts as i64
+    }
+
     /// Check if token should be udtated
     pub fn should_update(&self) -> bool {
-        Utc::now().timestamp() as u64 >=  self.fetched_at_unix_ts
+        Utc::now().timestamp() >= self.should_update_at()
+    }
+    /// Check if token stays out of its refresh window for `duration` from now
+    pub fn is_valid_for(&self, duration: Duration) -> bool {
+        self.is_valid_for_at(duration, SystemTime::now())
     }
+
+    pub fn is_valid_for_at(&self, duration: Duration, now: SystemTime) -> bool {
+        self.fetched_at_unix_ts > unix_secs(now).saturating_add(duration.as_secs())
+    }
+
+    /// Time left until `should_update()` becomes true, zero once it is
+    pub fn time_until_refresh(&self) -> Duration {
+        self.time_until_refresh_at(SystemTime::now())
+    }
+
+    pub fn time_until_refresh_at(&self, now: SystemTime) -> Duration {
+        Duration::from_secs(self.fetched_at_unix_ts.saturating_sub(unix_secs(now)))
+    }
+
+    /// Unix ts when background pre-fetch should start
+    pub fn should_prefetch_at(&self, prefetch_margin_seconds: u64) -> u64 {
+        (self.should_update_at() as u64).saturating_sub(prefetch_margin_seconds)
+    }
+
+    /// Check if token should be pre-fetched while still live
+    pub fn should_prefetch(&self, prefetch_margin_seconds: u64) -> bool {
+        Utc::now().timestamp() as u64 >= self.should_prefetch_at(prefetch_margin_seconds)
+    }
+
     /// Check if token should be removed
     pub fn should_remove_at(&self) -> i64 {
         // invalidate token 1 second before expiration
@@ -42,4 +118,109 @@ impl TokenContext {
     pub fn should_remove(&self) -> bool {
         Utc::now().timestamp() as u64 >= self.should_remove_at() as u64
     }
-}
\ No newline at end of file
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map_or(0, |since_epoch| since_epoch.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(unix_ts: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(unix_ts)
+    }
+
+    #[test]
+    fn is_valid_for_excludes_safety_margin() {
+        // exp 10_000, margin 600: refresh window starts at 9_400
+        let ctx = TokenContext::new("t".to_string(), Token::new("v".to_string(), 10_000), 600);
+        assert!(ctx.is_valid_for_at(Duration::ZERO, at(9_000)));
+        assert!(ctx.is_valid_for_at(Duration::from_secs(399), at(9_000)));
+        assert!(!ctx.is_valid_for_at(Duration::from_secs(400), at(9_000)));
+        assert!(!ctx.is_valid_for_at(Duration::ZERO, at(9_400)));
+        assert!(!ctx.is_valid_for_at(Duration::ZERO, at(20_000)));
+        assert!(!ctx.is_valid_for_at(Duration::MAX, at(0)));
+        // margin longer than lifetime: never valid
+        let ctx = TokenContext::new("t".to_string(), Token::new("v".to_string(), 100), 600);
+        assert!(!ctx.is_valid_for_at(Duration::ZERO, at(0)));
+    }
+
+    #[test]
+    fn time_until_refresh_counts_down_to_should_update() {
+        let ctx = TokenContext::new("t".to_string(), Token::new("v".to_string(), 10_000), 600);
+        assert_eq!(ctx.time_until_refresh_at(at(9_000)), Duration::from_secs(400));
+        assert_eq!(ctx.time_until_refresh_at(at(9_399)), Duration::from_secs(1));
+        assert_eq!(ctx.time_until_refresh_at(at(9_400)), Duration::ZERO);
+        assert_eq!(ctx.time_until_refresh_at(at(12_000)), Duration::ZERO);
+        // clock before epoch is treated as 0
+        assert_eq!(ctx.time_until_refresh_at(UNIX_EPOCH - Duration::from_secs(5)), Duration::from_secs(9_400));
+
+        // consistent with wall clock helpers
+        let now = Utc::now().timestamp() as u64;
+        let ctx = TokenContext::new("t".to_string(), Token::new("v".to_string(), now + 3_600), 600);
+        assert!(!ctx.should_update());
+        assert!(ctx.is_valid_for(Duration::ZERO));
+        assert!(!ctx.is_valid_for(Duration::from_secs(3_600)));
+        assert!(ctx.time_until_refresh() <= Duration::from_secs(3_000));
+        assert!(ctx.time_until_refresh() >= Duration::from_secs(2_990));
+    }
+
+    #[test]
+    fn debug_output_redacts_token_value() {
+        let secret = "s3cr3t-token-value";
+        let ctx = TokenContext::new("t".to_string(), Token::new(secret.to_string(), 10_000), 600);
+        let debug = format!("{:?}", ctx);
+        assert!(!debug.contains(secret), "{}", debug);
+        assert!(debug.contains(&ctx.token.fingerprint()), "{}", debug);
+        assert!(!format!("{:#?}", ctx).contains(secret));
+    }
+
+    #[test]
+    fn should_prefetch_before_safety_margin_window() {
+        let now = Utc::now().timestamp() as u64;
+        // refetch at now + 20
+        let ctx = TokenContext::new("t".to_string(), Token::new("v".to_string(), now + 30), 10);
+
+        assert!(!ctx.should_update());
+        assert!(!ctx.should_prefetch(0));
+        assert!(!ctx.should_prefetch(5));
+        assert!(ctx.should_prefetch(20));
+        assert_eq!(ctx.should_prefetch_at(u64::MAX), 0);
+    }
+
+    #[test]
+    fn should_update_at_is_expiration_minus_safety_margin() {
+        let ctx = TokenContext::new("t".to_string(), Token::new("v".to_string(), 10_000), 600);
+        assert_eq!(ctx.should_update_at(), 9_400);
+        assert_eq!(ctx.should_remove_at(), 9_999);
+        assert_eq!(ctx.should_prefetch_at(400), 9_000);
+        // safety margin longer than token lifetime: update right away
+        let ctx = TokenContext::new("t".to_string(), Token::new("v".to_string(), 100), 600);
+        assert_eq!(ctx.should_update_at(), 0);
+        assert!(ctx.should_update());
+    }
+
+    #[test]
+    fn with_safety_margin_moves_update_time() {
+        let ctx = TokenContext::new("t".to_string(), Token::new("v".to_string(), 10_000), 600).with_safety_margin(2_500);
+        assert_eq!(ctx.should_update_at(), 7_500);
+        let ctx = ctx.with_safety_margin(20_000);
+        assert_eq!(ctx.should_update_at(), 0);
+    }
+
+    #[test]
+    fn received_at_is_creation_time_not_refresh_time() {
+        let before = Utc::now().timestamp() as u64;
+        let ctx = TokenContext::new("t".to_string(), Token::new("v".to_string(), 10_000), 600).with_safety_margin(2_500);
+        assert!(ctx.received_at_unix_ts >= before);
+        assert!(ctx.received_at_unix_ts <= Utc::now().timestamp() as u64);
+        // persisted before the field existed
+        let ctx: TokenContext = serde_json::from_value(serde_json::json!({
+            "id": "t", "token": { "value": "v", "exp_unix_ts": 10_000 }, "fetched_at_unix_ts": 9_400
+        }))
+        .unwrap();
+        assert_eq!(ctx.received_at_unix_ts, 0);
+    }
+}
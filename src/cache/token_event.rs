@@ -0,0 +1,74 @@
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+use tracing::warn;
+
+use crate::cache::token_context::TokenContext;
+use crate::observability::otel::current_traceparent;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenEventKind {
+    /// token is new or its value or expiration changed
+    Updated,
+    /// token expired or its source was removed
+    Removed,
+}
+
+/// Token change emitted by `TokenCache`, used by sinks and library subscribers
+#[derive(Debug, Clone)]
+pub struct TokenEvent {
+    pub source_id: String,
+    pub token_id: String,
+    pub kind: TokenEventKind,
+    /// new token for `Updated`, removed token for `Removed`
+    pub token_context: TokenContext,
+    /// trace context of the span that emitted the event, sink spans join its trace
+    pub traceparent: Option<String>,
+}
+
+impl TokenEvent {
+    pub fn updated(source_id: &str, token_context: TokenContext) -> Self {
+        Self::new(source_id, token_context, TokenEventKind::Updated)
+    }
+
+    pub fn removed(source_id: &str, token_context: TokenContext) -> Self {
+        Self::new(source_id, token_context, TokenEventKind::Removed)
+    }
+
+    fn new(source_id: &str, token_context: TokenContext, kind: TokenEventKind) -> Self {
+        Self {
+            source_id: source_id.to_owned(),
+            token_id: token_context.id.to_owned(),
+            kind,
+            token_context,
+            traceparent: current_traceparent(),
+        }
+    }
+}
+
+/// Events of a single token, see `TokenCache::subscribe`
+pub struct TokenSubscription {
+    source_id: String,
+    token_id: String,
+    rx: Receiver<TokenEvent>,
+}
+
+impl TokenSubscription {
+    pub(crate) fn new(source_id: &str, token_id: &str, rx: Receiver<TokenEvent>) -> Self {
+        Self { source_id: source_id.to_owned(), token_id: token_id.to_owned(), rx }
+    }
+
+    /// Wait for next event of the token; events missed by a slow subscriber are skipped,
+    /// current token is always available with `TokenCache::get`
+    pub async fn recv(&mut self) -> Option<TokenEvent> {
+        loop {
+            match self.rx.recv().await {
+                Ok(event) if event.source_id == self.source_id && event.token_id == self.token_id => return Some(event),
+                Ok(_) => continue,
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("token '{}.{}' subscriber lagged, {} events skipped", self.source_id, self.token_id, skipped);
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+}
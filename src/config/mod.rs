@@ -5,3 +5,5 @@ pub mod sinks;
 pub mod proc_loader;
 pub mod proc_initiateor;
 pub mod proc_validator;
+pub mod proc_reload;
+pub mod proc_dump;
@@ -0,0 +1,160 @@
+//! Resolved config dump: env vars expanded, defaults injected, secrets redacted.
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde_json::Value;
+
+use crate::config::sinks::{SinkAuthConfig, SinkAuthSecret};
+use crate::config::sources::{GenericSourceValue, ServiceConfig};
+
+pub const REDACTED_VALUE: &str = "***";
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum DumpFormat {
+    #[default]
+    Yaml,
+    Json,
+}
+
+/// Serialize config with `from_env`/`from_file` values, admin bearer token and sink auth secrets redacted,
+/// keys are sorted and unset fields omitted so dumps can be diffed
+pub fn dump_config(service_config: &ServiceConfig, format: DumpFormat) -> Result<String> {
+    let mut service_config = service_config.to_owned();
+    redact_secrets(&mut service_config);
+    let mut value = serde_json::to_value(&service_config)?;
+    remove_nulls(&mut value);
+    match format {
+        DumpFormat::Yaml => Ok(serde_yaml::to_string(&value)?),
+        DumpFormat::Json => Ok(serde_json::to_string_pretty(&value)?),
+    }
+}
+
+fn redact_secrets(service_config: &mut ServiceConfig) {
+    if let Some(bearer_token) = service_config.settings.admin.as_mut().and_then(|admin| admin.bearer_token.as_mut()) {
+        *bearer_token = REDACTED_VALUE.to_string();
+    }
+    for source in service_config.sources.values_mut() {
+        let request = &mut source.request;
+        [&mut request.headers, &mut request.query, &mut request.body]
+            .into_iter()
+            .flatten()
+            .flat_map(|values| values.values_mut())
+            .for_each(redact_value);
+        if let Some(form) = &mut request.form {
+            [&mut form.client_id, &mut form.scope].into_iter().for_each(redact_value);
+            [&mut form.client_secret, &mut form.client_assertion].into_iter().flatten().for_each(redact_value);
+        }
+        if let Some(vault) = &mut source.vault {
+            [&mut vault.role_id, &mut vault.secret_id].into_iter().for_each(redact_value);
+        }
+        if let Some(aws) = &mut source.aws_sts_web_identity {
+            redact_value(&mut aws.web_identity_token);
+        }
+    }
+    for redis in service_config.sinks.values_mut().filter_map(|sink| sink.redis.as_mut()) {
+        redact_value(&mut redis.redis_url);
+    }
+    for auth in service_config.sinks.values_mut().filter_map(|sink| sink.auth.as_mut()) {
+        match auth {
+            SinkAuthConfig::Bearer { secret } | SinkAuthConfig::MtlsHeader { secret, .. } => match secret {
+                SinkAuthSecret::Literal { value } => *value = REDACTED_VALUE.to_string(),
+                SinkAuthSecret::FromEnv { from_env } => *from_env = REDACTED_VALUE.to_string(),
+            },
+        }
+    }
+}
+
+fn redact_value(value: &mut GenericSourceValue) {
+    match value {
+        GenericSourceValue::FromEnv { from_env } => *from_env = REDACTED_VALUE.to_string(),
+        GenericSourceValue::FromFile { path } => *path = REDACTED_VALUE.to_string(),
+        _ => {}
+    }
+}
+
+fn remove_nulls(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.retain(|_, v| !v.is_null());
+            map.values_mut().for_each(remove_nulls);
+        }
+        Value::Array(values) => values.iter_mut().for_each(remove_nulls),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::proc_loader::parse_config;
+
+    const CONFIG: &str = r#"
+settings:
+  server:
+    host: 127.0.0.1
+    port: "8080"
+  metrics:
+    path: "/metrics"
+  admin:
+    enabled: true
+    bearer_token: admin-secret
+sources:
+  oauth:
+    type: http
+    request:
+      url: "http://localhost/token"
+      method: POST
+      headers:
+        Authorization:
+          from_env: OAUTH_AUTHORIZATION
+      body:
+        client_secret:
+          path: /run/secrets/client_secret
+        grant_type:
+          value: client_credentials
+    parse:
+      tokens:
+        - id: access_token
+          parent: body
+          pointer: /access_token
+          token_type: jwt
+sinks:
+  token_file:
+    type: file
+    source_id: oauth
+    token_id: access_token
+    path: /tmp/token-agent-dump
+  token_http:
+    type: http
+    source_id: oauth
+    token_id: access_token
+    path: /token
+    auth:
+      type: bearer
+      value: sink-secret
+"#;
+
+    #[tokio::test]
+    async fn dump_config_redacts_secrets_and_injects_defaults() {
+        let service_config = parse_config(CONFIG.to_string()).await.unwrap();
+
+        let yaml = dump_config(&service_config, DumpFormat::Yaml).unwrap();
+        assert!(!yaml.contains("OAUTH_AUTHORIZATION") && !yaml.contains("/run/secrets") && !yaml.contains("admin-secret") && !yaml.contains("sink-secret"), "{}", yaml);
+        assert!(yaml.contains("client_credentials"), "{}", yaml);
+        assert!(!yaml.contains("null"), "{}", yaml);
+
+        let json: Value = serde_json::from_str(&dump_config(&service_config, DumpFormat::Json).unwrap()).unwrap();
+        let request = &json["sources"]["oauth"]["request"];
+        assert_eq!(request["method"], "POST");
+        assert_eq!(request["headers"]["Authorization"]["from_env"], REDACTED_VALUE);
+        assert_eq!(request["body"]["client_secret"]["path"], REDACTED_VALUE);
+        assert_eq!(json["settings"]["admin"]["bearer_token"], REDACTED_VALUE);
+        assert_eq!(json["sinks"]["token_http"]["auth"]["value"], REDACTED_VALUE);
+        // defaults injected by initiator
+        assert_eq!(json["sinks"]["token_file"]["sink_id"], "token_file");
+
+        // dump is a loadable config
+        let reloaded: ServiceConfig = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(reloaded.sources["oauth"].parse.tokens[0].id, "access_token");
+    }
+}
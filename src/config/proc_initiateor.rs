@@ -1,7 +1,87 @@
+use crate::config::settings::ReadinessConfig;
 use crate::config::sinks::{ResponseField};
+use crate::config::sources::SourceTypes;
+use crate::sources::aws_sts::get_aws_sts_request_and_parse;
+use crate::sources::exec::get_exec_source_request;
+use crate::sources::file::get_file_source_request_and_parse;
+use crate::sources::gcp_workload_identity::get_gcp_workload_identity_parse;
+use crate::sources::kube_service_account::get_kube_service_account_request_and_parse;
+use crate::sources::vault::get_vault_request_and_parse;
 use crate::ServiceConfig;
 
 pub fn initiate_default_values(mut config: ServiceConfig) -> ServiceConfig {
+    // derive request and parse blocks for vault, kube_service_account, file and aws_sts_web_identity sources,
+    // parse block for gcp_workload_identity sources, request block for exec sources
+    for source_config in config.sources.values_mut() {
+        if let (SourceTypes::VAULT, Some(vault_config)) = (source_config.source_type, &source_config.vault) {
+            let (request, parse) = get_vault_request_and_parse(vault_config);
+            source_config.request = request;
+            source_config.parse = parse;
+        }
+        if let (SourceTypes::KubeServiceAccount, Some(kube_config)) =
+            (source_config.source_type, &source_config.kube_service_account)
+        {
+            let (request, parse) = get_kube_service_account_request_and_parse(kube_config);
+            source_config.request = request;
+            source_config.parse = parse;
+        }
+        if let (SourceTypes::FILE, Some(file_config)) = (source_config.source_type, &source_config.file) {
+            let (request, parse) = get_file_source_request_and_parse(file_config);
+            source_config.request = request;
+            source_config.parse = parse;
+        }
+        if let (SourceTypes::EXEC, Some(exec_config)) = (source_config.source_type, &source_config.exec) {
+            source_config.request = get_exec_source_request(exec_config, &source_config.request);
+        }
+        if let (SourceTypes::GcpWorkloadIdentity, Some(gcp_config)) =
+            (source_config.source_type, &source_config.gcp_workload_identity)
+        {
+            source_config.parse = get_gcp_workload_identity_parse(gcp_config);
+        }
+        if let (SourceTypes::AwsStsWebIdentity, Some(aws_config)) =
+            (source_config.source_type, &source_config.aws_sts_web_identity)
+        {
+            let (request, parse) = get_aws_sts_request_and_parse(aws_config);
+            source_config.request = request;
+            source_config.parse = parse;
+        }
+    }
+
+    // settings level response body limit applies to sources without their own
+    if let Some(max_response_bytes) = config.settings.max_response_bytes {
+        for source_config in config.sources.values_mut() {
+            source_config.request.max_response_bytes.get_or_insert(max_response_bytes);
+        }
+    }
+
+    // settings level token size limit applies to token fields without their own
+    if let Some(max_token_bytes) = config.settings.max_token_bytes {
+        for source_config in config.sources.values_mut() {
+            for token_field in source_config.parse.tokens.iter_mut() {
+                token_field.max_token_bytes.get_or_insert(max_token_bytes);
+            }
+        }
+    }
+
+    // settings level safety margin percent applies to sources without their own
+    if let Some(safety_margin_percent) = config.settings.safety_margin_percent {
+        for source_config in config.sources.values_mut() {
+            source_config.safety_margin_percent.get_or_insert(safety_margin_percent);
+        }
+    }
+
+    // readiness waits for every enabled source unless configured otherwise
+    if config.settings.readiness.is_none() {
+        let mut required_sources: Vec<String> = config
+            .sources
+            .iter()
+            .filter(|(_, source_config)| source_config.is_enabled())
+            .map(|(source_id, _)| source_id.to_owned())
+            .collect();
+        required_sources.sort();
+        config.settings.readiness = Some(ReadinessConfig { required_sources });
+    }
+
     config.sinks = config
         .sinks
         .into_iter()
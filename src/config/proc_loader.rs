@@ -1,27 +1,114 @@
+use std::collections::HashMap;
 use std::{fs, path::Path};
 use crate::config::proc_initiateor::initiate_default_values;
 use crate::config::settings::{LogFormat, LoggingConfig};
 use crate::config::sources::ServiceConfig;
 use crate::observability::metrics::get_metrics;
-use anyhow::{Result};
+use anyhow::{anyhow, Result};
 use regex::Regex;
 use tracing::{debug, error};
-use crate::config::proc_validator;
+use crate::config::proc_validator::{self, ValidationIssue};
 
-/// Load and validate config from YAML file
+/// Config file format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Yaml,
+    Toml,
+    Json,
+}
+
+impl ConfigFormat {
+    /// Format by file extension: `.toml`, `.json`, YAML for `.yaml`, `.yml` and any other extension
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()).map(str::to_ascii_lowercase).as_deref() {
+            Some("toml") => ConfigFormat::Toml,
+            Some("json") => ConfigFormat::Json,
+            _ => ConfigFormat::Yaml,
+        }
+    }
+}
+
+/// Load and validate config from YAML, TOML or JSON file, format is detected by file extension
 pub async  fn file_to_config(path: &Path) -> Result<ServiceConfig> {
     let content= fs::read_to_string(path)?;
         
     let expanded = expand_env_vars(&content);
-     parse_config(expanded).await
+     parse_config_typed(expanded, ConfigFormat::from_path(path)).await
 }
+
+/// Parse and validate YAML config with expanded env vars
 pub async fn parse_config(content: String) -> Result<ServiceConfig> {
+    parse_config_typed(content, ConfigFormat::Yaml).await
+}
+
+/// Parse and validate config of given format with expanded env vars
+pub async fn parse_config_typed(content: String, format: ConfigFormat) -> Result<ServiceConfig> {
+    let service_config = parse_config_with_defaults(content, format).await?;
+    debug!("validation config ...");
+    let _ = proc_validator::validate_service_config(&service_config).await;
+    
+    Ok(service_config)
+}
+
+/// Load config from file, validation errors are returned instead of aborting
+pub async fn file_to_checked_config(path: &Path) -> Result<ServiceConfig> {
+    let content = fs::read_to_string(path)?;
+    let service_config = parse_config_with_defaults(expand_env_vars(&content), ConfigFormat::from_path(path)).await?;
+    proc_validator::check_service_config(&service_config)
+        .await
+        .map_err(|errors| anyhow!(proc_validator::format_issues(&errors)))?;
+    Ok(service_config)
+}
+
+/// Load config from file and collect all validation errors, empty when config is valid.
+/// Placeholders fall back to `env_file` values (`KEY=VALUE` lines) when env var is not set
+pub async fn file_to_validation_errors(path: &Path, env_file: Option<&Path>) -> Result<Vec<ValidationIssue>> {
+    let content = fs::read_to_string(path).map_err(|e| anyhow!("failed to read '{}': {}", path.display(), e))?;
+    let env_file_vars = match env_file {
+        Some(env_file) => parse_env_file(env_file)?,
+        None => HashMap::new(),
+    };
+    let service_config = parse_config_with_defaults(expand_env_vars_with(&content, &env_file_vars), ConfigFormat::from_path(path))
+        .await
+        .map_err(|e| anyhow!("invalid config format: {}", e))?;
+    Ok(proc_validator::check_service_config(&service_config).await.err().unwrap_or_default())
+}
+
+/// `.env` file: `KEY=VALUE` lines, optional `export ` prefix and quotes, `#` comments
+pub fn parse_env_file(path: &Path) -> Result<HashMap<String, String>> {
+    let content = fs::read_to_string(path).map_err(|e| anyhow!("failed to read env file '{}': {}", path.display(), e))?;
+    let mut vars = HashMap::new();
+    for (line_number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| anyhow!("env file '{}' line {}: expected KEY=VALUE", path.display(), line_number + 1))?;
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+            .unwrap_or(value);
+        vars.insert(key.trim().to_owned(), value.to_owned());
+    }
+    Ok(vars)
+}
+
+async fn parse_config_with_defaults(content: String, format: ConfigFormat) -> Result<ServiceConfig> {
     let metrics = get_metrics().await;
-    let mut service_config: ServiceConfig = serde_yaml::from_str(&content)
-        .inspect_err(|e| {
-            error!("parse config error: {}", e);
-            metrics.parse_failures.inc();
-        })?;
+    let parsed: Result<ServiceConfig> = match format {
+        ConfigFormat::Yaml => serde_yaml::from_str(&content).map_err(Into::into),
+        ConfigFormat::Toml => toml::from_str(&content).map_err(Into::into),
+        ConfigFormat::Json => serde_json::from_str(&content).map_err(Into::into),
+    };
+    let mut service_config = parsed.inspect_err(|e| {
+        error!("parse config error: {}", e);
+        metrics.parse_failures.inc();
+    })?;
 
     // Apply defaults
     if service_config.settings.logging.is_none() {
@@ -30,19 +117,23 @@ pub async fn parse_config(content: String) -> Result<ServiceConfig> {
     if service_config.settings.safety_margin_seconds.is_none() {
         service_config.settings.safety_margin_seconds = Some(60);
     }
-    service_config = initiate_default_values(service_config);
-    debug!("validation config ...");
-    let _ = proc_validator::validate_service_config(&service_config).await;
-    
-    Ok(service_config)
+    Ok(initiate_default_values(service_config))
 }
 
 fn expand_env_vars(input: &str) -> String {
+    expand_env_vars_with(input, &HashMap::new())
+}
+
+/// `${VAR}` / `${VAR:default}` from env, then `fallback` vars, then default
+fn expand_env_vars_with(input: &str, fallback: &HashMap<String, String>) -> String {
     let re = Regex::new(r"\$\{(\w+)(?::([^\}]+))?\}").unwrap();
     re.replace_all(input, |caps: &regex::Captures| {
         let var = &caps[1];
         let default = caps.get(2).map(|m| m.as_str()).unwrap_or("");
-        std::env::var(var).unwrap_or_else(|_| default.to_string())
+        std::env::var(var)
+            .ok()
+            .or_else(|| fallback.get(var).cloned())
+            .unwrap_or_else(|| default.to_string())
     })
     .to_string()
 }
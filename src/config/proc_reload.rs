@@ -0,0 +1,117 @@
+//! Config hot reload: difference between running and reloaded config
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use serde::Serialize;
+use tracing::info;
+
+use crate::cache::token_cache::TokenCache;
+use crate::config::sinks::SinkType;
+use crate::config::sources::ServiceConfig;
+use crate::resilience::backoff::SourceBackoff;
+use crate::resilience::fallback::StaleFallback;
+use crate::resilience::circuit_breaker::CircuitBreaker;
+use crate::sources::tls::SourceClient;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct ConfigDiff {
+    pub added_sources: Vec<String>,
+    pub removed_sources: Vec<String>,
+    pub changed_sources: Vec<String>,
+    pub added_sinks: Vec<String>,
+    pub removed_sinks: Vec<String>,
+    pub changed_sinks: Vec<String>,
+    pub settings_changed: bool,
+}
+
+impl ConfigDiff {
+    pub fn new(current: &ServiceConfig, reloaded: &ServiceConfig) -> Self {
+        let (added_sources, removed_sources, changed_sources) = diff_maps(&current.sources, &reloaded.sources);
+        let (added_sinks, removed_sinks, changed_sinks) = diff_maps(&current.sinks, &reloaded.sinks);
+        Self {
+            added_sources,
+            removed_sources,
+            changed_sources,
+            added_sinks,
+            removed_sinks,
+            changed_sinks,
+            settings_changed: differs(&current.settings, &reloaded.settings),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == ConfigDiff::default()
+    }
+
+    /// Drop state of removed and changed sources, cached tokens of surviving sources are kept
+    pub async fn apply(&self) {
+        for source_id in &self.removed_sources {
+            TokenCache::remove_by_source_id(source_id).await;
+            info!("config reload: tokens of removed source '{}' dropped", source_id);
+        }
+        for source_id in self.removed_sources.iter().chain(&self.changed_sources) {
+            SourceClient::remove_by_source_id(source_id).await;
+            CircuitBreaker::remove_by_source_id(source_id).await;
+            SourceBackoff::remove_by_source_id(source_id).await;
+            StaleFallback::remove_by_source_id(source_id).await;
+        }
+    }
+}
+
+impl fmt::Display for ConfigDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "sources added: {:?}, removed: {:?}, changed: {:?}; sinks added: {:?}, removed: {:?}, changed: {:?}; settings changed: {}",
+            self.added_sources,
+            self.removed_sources,
+            self.changed_sources,
+            self.added_sinks,
+            self.removed_sinks,
+            self.changed_sinks,
+            self.settings_changed
+        )
+    }
+}
+
+/// File sink paths present in both configs, their token files are kept while sinks restart
+pub fn retained_file_sink_paths(current: &ServiceConfig, reloaded: &ServiceConfig) -> HashSet<String> {
+    let file_paths = |config: &ServiceConfig| {
+        config
+            .sinks
+            .values()
+            .filter(|sink_config| sink_config.sink_type == SinkType::File)
+            .map(|sink_config| sink_config.path.to_owned())
+            .collect::<HashSet<String>>()
+    };
+    file_paths(current).intersection(&file_paths(reloaded)).cloned().collect()
+}
+
+/// (added, removed, changed) keys, sorted
+fn diff_maps<T: Serialize>(current: &HashMap<String, T>, reloaded: &HashMap<String, T>) -> (Vec<String>, Vec<String>, Vec<String>) {
+    let mut added: Vec<String> = reloaded.keys().filter(|key| !current.contains_key(*key)).cloned().collect();
+    let mut removed: Vec<String> = current.keys().filter(|key| !reloaded.contains_key(*key)).cloned().collect();
+    let mut changed: Vec<String> = current
+        .iter()
+        .filter(|(key, value)| {
+            reloaded
+                .get(*key)
+                .is_some_and(|reloaded_value| differs(*value, reloaded_value))
+        })
+        .map(|(key, _)| key.to_owned())
+        .collect();
+    added.sort();
+    removed.sort();
+    changed.sort();
+    (added, removed, changed)
+}
+
+/// Configs are compared as JSON values, maps inside (headers, env, ...) are compared regardless of order.
+/// Config that fails to serialize is reported as changed
+pub(crate) fn differs<T: Serialize>(current: &T, reloaded: &T) -> bool {
+    match (serde_json::to_value(current), serde_json::to_value(reloaded)) {
+        (Ok(current), Ok(reloaded)) => current != reloaded,
+        _ => true,
+    }
+}
@@ -1,49 +1,113 @@
 //! Comprehensive configuration validation with aggregated errors.
-//! - Aggregates all issues into Vec<String>
+//! - Aggregates all issues into Vec<ValidationIssue>, each one about a canonical config path
 //! - Validates invariants discussed across the design thread:
 //!   * token vs expiration semantics
 //!   * parent and pointer rules
 //!   * source/sink references
-//!   * template references (rudimentary check)
+//!   * `ref` and template references to source tokens
 //!   * path / HTTP method / logging / retry invariants
 //!   * uniqueness and collisions (duplicate HTTP sink path)
 //!
 //! Adjust `use` paths if your types are placed in a different module.
 
+use http::HeaderName;
 use regex::Regex;
+use serde::Serialize;
 use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::path::Path;
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
-use crate::config::settings::{RetryConfig, SettingsConfig};
-use crate::config::sinks::{HttpResponseBlock, ResponseField, SinkConfig, SinkType};
+use crate::config::settings::{CircuitBreakerConfig, RetryConfig, SettingsConfig, TimeoutConfig};
+use crate::config::sinks::{FileSinkFormat, HttpResponseBlock, KubernetesSecretSinkConfig, RedisSinkConfig, RedisTtlMode, ResponseField, SinkAuthConfig, SinkAuthSecret, SinkConfig, SinkType};
 use crate::config::sources::{
-    Expiration, ExpirationSource, GenericSourceValue, ServiceConfig, SourceConfig, SourceTypes,
-    TokenField, TokenType,
+    AwsStsWebIdentityConfig, BodyFormat, ExecSourceConfig, Expiration, ExpirationSource, ExpirationSourceFormat, GcpWorkloadIdentityConfig,
+    GenericSourceValue, ServiceConfig, SourceConfig, SourceTypes, TokenField, TokenIterate, TokenTransform, TokenType,
 };
+use crate::helpers::time::get_effective_safety_margin_seconds;
 use crate::observability::metrics::get_metrics;
+use crate::parser::parser::{is_ambiguous_json_pointer, is_valid_json_pointer, is_valid_xml_path, PLAIN_BODY_POINTER};
+use crate::server::health::{HEALTHZ_PATH, READYZ_PATH};
+use crate::sinks::sink_file::{is_valid_id_or_name, FILE_TEMPLATE_PLACEHOLDER};
+use crate::sinks::sink_redis::REDIS_URL_SCHEMES;
+use crate::sources::aws_sts::{AWS_STS_DURATION_SECONDS_MAX, AWS_STS_DURATION_SECONDS_MIN};
+use crate::sources::exec::EXEC_SOURCE_COMMAND_FORBIDDEN_CHARS;
+use crate::sources::fetch::SOURCE_TEMPLATE_PLACEHOLDER;
+use crate::sources::gcp_workload_identity::{GCP_LIFETIME_SECONDS_MAX, GCP_SUBJECT_TOKEN_TYPES};
+use crate::sources::metadata::IMDSV2_SESSION_TTL_SECONDS_MAX;
+use crate::sources::signed_jwt::{get_signing_algorithm, load_signing_key, SIGNED_JWT_RESERVED_CLAIMS};
 use anyhow::Result;
 
-/// Public entrypoint: returns Ok(()) or Err(Vec<String>) containing all issues.
-pub async fn validate_service_config(cfg: &ServiceConfig) -> Result<(), Vec<String>> {
-    let mut errors: Vec<String> = Vec::new();
+/// Last segment of the id pattern `<field_id>.*` of tokens generated by an `iterate` token field
+static ITERATE_ID_WILDCARD: &str = "*";
+
+/// Validation error split into the config path it is about and the message, e.g. for CI annotations.
+/// Path is the YAML path from the config root, list entries are addressed by id:
+/// `sources.s1.request.url`, `sources.s1.parse.tokens[access_token].expiration.pointer`, `sinks.file.mode`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValidationIssue {
+    pub path: String,
+    pub message: String,
+}
+
+impl ValidationIssue {
+    pub fn new(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self { path: path.into(), message: message.into() }
+    }
+}
+
+impl fmt::Display for ValidationIssue {
+    /// `path: message`, only the message for errors not about a config path, e.g. unreadable file
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.path.is_empty() {
+            true => write!(f, "{}", self.message),
+            false => write!(f, "{}: {}", self.path, self.message),
+        }
+    }
+}
+
+/// One issue per line, used in error messages and logs
+pub fn format_issues(issues: &[ValidationIssue]) -> String {
+    format!(
+        "config is not valid, total errors:{}, \n{}",
+        issues.len(),
+        issues.iter().map(ValidationIssue::to_string).collect::<Vec<_>>().join("\n")
+    )
+}
+
+/// Public entrypoint: returns Ok(()) or Err(Vec<ValidationIssue>) containing all issues.
+pub async fn validate_service_config(cfg: &ServiceConfig) -> Result<(), Vec<ValidationIssue>> {
+    if let Err(errors) = check_service_config(cfg).await {
+        panic!("{}", format_issues(&errors));
+    }
+    Ok(())
+}
+
+/// Same checks as `validate_service_config` without aborting, used on config reload
+pub async fn check_service_config(cfg: &ServiceConfig) -> Result<(), Vec<ValidationIssue>> {
+    let mut errors: Vec<ValidationIssue> = Vec::new();
 
     // Validate settings
     validate_settings(&cfg.settings, &mut errors);
 
     // Sources must not be empty
     if cfg.sources.is_empty() {
-        errors.push("config: 'sources' is empty; at least one source required".to_string());
+        errors.push(ValidationIssue::new("sources", "is empty; at least one source required"));
     }
 
     // Validate sources themselves and build helper maps
     let mut source_token_ids: HashMap<String, HashSet<String>> = HashMap::new();
     for (src_name, src_cfg) in &cfg.sources {
         validate_source_basics(src_name, src_cfg, &mut errors);
+        warn_large_safety_margin(src_name, src_cfg, cfg.settings.safety_margin_seconds);
         // collect token ids
         let mut set = HashSet::new();
         for t in &src_cfg.parse.tokens {
-            set.insert(t.id.clone());
+            // ids generated by `iterate` are only known at runtime, kept as `<id>.*` pattern
+            match t.iterate {
+                Some(_) => set.insert(format!("{}.{}", t.id, ITERATE_ID_WILDCARD)),
+                None => set.insert(t.id.clone()),
+            };
         }
         source_token_ids.insert(src_name.clone(), set);
     }
@@ -53,14 +117,19 @@ pub async fn validate_service_config(cfg: &ServiceConfig) -> Result<(), Vec<Stri
         if let Some(inputs) = &src_cfg.inputs {
             for dep in inputs {
                 if !cfg.sources.contains_key(dep) {
-                    errors.push(format!(
-                        "source['{}'].inputs references unknown source '{}'",
-                        src_name, dep
+                    errors.push(ValidationIssue::new(
+                        format!("sources.{}.inputs", src_name),
+                        format!("references unknown source '{}'", dep),
                     ));
                 } else if dep == src_name {
-                    errors.push(format!(
-                        "source['{}'].inputs must not reference itself",
-                        src_name
+                    errors.push(ValidationIssue::new(
+                        format!("sources.{}.inputs", src_name),
+                        "must not reference itself",
+                    ));
+                } else if src_cfg.is_enabled() && !cfg.sources[dep].is_enabled() {
+                    errors.push(ValidationIssue::new(
+                        format!("sources.{}.inputs", src_name),
+                        format!("references disabled source '{}'", dep),
                     ));
                 }
             }
@@ -69,71 +138,37 @@ pub async fn validate_service_config(cfg: &ServiceConfig) -> Result<(), Vec<Stri
 
     // Validate inputs block exists when source field exists in source
     for (src_name, src_cfg) in &cfg.sources {
-        let mut ref_sources: Vec<String> = Vec::new();
-        src_cfg
+        let ref_sources: HashSet<&str> = src_cfg
             .request
-            .headers
-            .iter()
-            .for_each(|h: &HashMap<String, GenericSourceValue>| {
-                h.values()
-                    .for_each(
-                        |generic_source_value: &GenericSourceValue| match generic_source_value {
-                            GenericSourceValue::Ref { source, id: _, prefix: _ } => {
-                                ref_sources.push(source.to_owned());
-                            }
-                            _ => {}
-                        },
-                    );
-            });
-
-        src_cfg
-            .request
-            .body
-            .iter()
-            .for_each(|h: &HashMap<String, GenericSourceValue>| {
-                h.values()
-                    .for_each(
-                        |generic_source_value: &GenericSourceValue| match generic_source_value {
-                            GenericSourceValue::Ref { source, id: _, prefix: _ } => {
-                                ref_sources.push(source.to_owned());
-                            }
-                            _ => {}
-                        },
-                    );
-            });
-
-        if !ref_sources.is_empty() {
-            ref_sources.iter().for_each(|source| {
-                if src_cfg
-                    .inputs
-                    .clone()
-                    .filter(|input_vec| input_vec.contains(source))
-                    .is_none()
-                {
-                    errors.push(format!(
-                        "source['{}'].inputs must be provided and contains '{}'",
-                        src_name, source
-                    ));
-                }
-            });
+            .generic_values()
+            .into_iter()
+            .chain(src_cfg.exec.iter().flat_map(ExecSourceConfig::generic_values))
+            .filter_map(|(_, generic_source_value)| match generic_source_value {
+                GenericSourceValue::Ref { source, .. } => Some(source.as_str()),
+                _ => None,
+            })
+            .collect();
+        for source in ref_sources {
+            if !src_cfg.inputs.as_ref().is_some_and(|inputs| inputs.iter().any(|input| input == source)) {
+                errors.push(ValidationIssue::new(
+                    format!("sources.{}.inputs", src_name),
+                    format!("must be provided and contains '{}'", source),
+                ));
+            }
         }
-        // .or_else(Vec::with_capacity(0));
-
-        // if &src_cfg.request.headers. {
-
-        //     // for dep in inputs {
-        //     //     if !cfg.sources.contains_key(dep) {
-        //     //         errors.push(format!("source['{}'].inputs references unknown source '{}'", src_name, dep));
-        //     //     } else if dep == src_name {
-        //     //         errors.push(format!("source['{}'].inputs must not reference itself", src_name));
-        //     //     }
-        //     // }
-        // }
+        validate_source_references(src_name, src_cfg, &cfg.sources, &source_token_ids, &mut errors);
     }
 
     // Validate sinks and HTTP path collisions
+    let server = &cfg.settings.server;
+    let probe_paths = [server.health_path.as_str(), server.ready_path.as_str(), HEALTHZ_PATH, READYZ_PATH];
     let mut http_paths: HashMap<String, String> = HashMap::new(); // path -> sink_name
     for (sink_name, sink_cfg) in &cfg.sinks {
+        // sinks of disabled sources are not started
+        if cfg.sources.get(&sink_cfg.source_id).is_some_and(|source| !source.is_enabled()) {
+            warn!("sinks.{}: source '{}' is disabled, sink is skipped", sink_name, sink_cfg.source_id);
+            continue;
+        }
         validate_sink_basics(
             sink_name,
             sink_cfg,
@@ -141,18 +176,42 @@ pub async fn validate_service_config(cfg: &ServiceConfig) -> Result<(), Vec<Stri
             &source_token_ids,
             &mut errors,
         );
+        validate_sink_references(sink_name, sink_cfg, &cfg.sources, &source_token_ids, &mut errors);
 
         // collision detection for HTTP sinks (single global server)
         if let SinkType::Http = sink_cfg.sink_type {
             if let Some(prev) = http_paths.insert(sink_cfg.path.clone(), sink_name.clone()) {
-                errors.push(format!(
-                    "sink['{}'] and sink['{}'] both define HTTP path '{}'; HTTP sink paths must be unique",
-                    prev, sink_name, sink_cfg.path
+                errors.push(ValidationIssue::new(
+                    format!("sinks.{}.path", sink_name),
+                    format!("'{}' is already defined by sinks.{}; HTTP sink paths must be unique", sink_cfg.path, prev),
+                ));
+            }
+            if probe_paths.contains(&sink_cfg.path.as_str()) {
+                errors.push(ValidationIssue::new(
+                    format!("sinks.{}.path", sink_name),
+                    format!("'{}' is reserved for probes", sink_cfg.path),
                 ));
             }
         }
     }
 
+    // readiness probe sources must exist
+    for source_id in cfg.settings.readiness.iter().flat_map(|readiness| readiness.required_sources.iter()) {
+        match cfg.sources.get(source_id) {
+            None => errors.push(ValidationIssue::new(
+                "settings.readiness.required_sources",
+                format!("references unknown source '{}'", source_id),
+            )),
+            Some(source) if !source.is_enabled() => {
+                errors.push(ValidationIssue::new(
+                    "settings.readiness.required_sources",
+                    format!("references disabled source '{}'", source_id),
+                ))
+            }
+            Some(_) => {}
+        }
+    }
+
     if errors.is_empty() {
         info!("config valid");
         Ok(())
@@ -162,27 +221,112 @@ pub async fn validate_service_config(cfg: &ServiceConfig) -> Result<(), Vec<Stri
             error!(" - {}", e);
         }
         get_metrics().await.config_validation_errors.inc();
-        panic!(
-            "config is not valid, total errors:{}, \n{}",
-            errors.len(),
-            errors.join("\n")
-        );
+        Err(errors)
     }
 }
 
 /// SETTINGS VALIDATION
-fn validate_settings(settings: &SettingsConfig, errors: &mut Vec<String>) {
+fn validate_settings(settings: &SettingsConfig, errors: &mut Vec<ValidationIssue>) {
     // retry invariants
     if let Some(retry) = &settings.retry {
         validate_retry("settings.retry", retry, errors);
     }
 
+    if settings.max_response_bytes == Some(0) {
+        errors.push(ValidationIssue::new("settings.max_response_bytes", "must be greater than 0"));
+    }
+    if settings.max_token_bytes == Some(0) {
+        errors.push(ValidationIssue::new("settings.max_token_bytes", "must be greater than 0"));
+    }
+
+    if let Some(audit) = &settings.audit {
+        if audit.path.trim().is_empty() {
+            errors.push(ValidationIssue::new("settings.audit.path", "cannot be empty"));
+        }
+        if audit.max_file_bytes == Some(0) {
+            errors.push(ValidationIssue::new("settings.audit.max_file_bytes", "must be greater than 0"));
+        }
+        if audit.max_files == Some(0) {
+            errors.push(ValidationIssue::new("settings.audit.max_files", "must be greater than 0"));
+        }
+    }
+    if settings.ssm_cache_ttl_seconds == Some(0) {
+        errors.push(ValidationIssue::new("settings.ssm_cache_ttl_seconds", "must be greater than 0"));
+    }
+    if settings.gcp_secret_cache_ttl_seconds == Some(0) {
+        errors.push(ValidationIssue::new("settings.gcp_secret_cache_ttl_seconds", "must be greater than 0"));
+    }
+    // zero permits would block every fetch
+    if settings.max_concurrent_fetches == Some(0) {
+        errors.push(ValidationIssue::new("settings.max_concurrent_fetches", "must be greater than 0"));
+    }
+
+    if let Some(sample_ratio) = settings.otel.as_ref().and_then(|otel| otel.sample_ratio) {
+        if !(0.0..=1.0).contains(&sample_ratio) {
+            errors.push(ValidationIssue::new(
+                "settings.otel.sample_ratio",
+                format!("must be between 0.0 and 1.0, got {}", sample_ratio),
+            ));
+        }
+    }
+
+    // persistent cache path must not be empty
+    if let Some(persist_path) = settings.cache.as_ref().and_then(|c| c.persist_path.as_ref()) {
+        if persist_path.trim().is_empty() {
+            errors.push(ValidationIssue::new("settings.cache.persist_path", "cannot be empty"));
+        }
+    }
+
+    // admin api invariants
+    if let Some(admin) = settings.admin.as_ref().filter(|admin| admin.enabled) {
+        if admin.bearer_token.as_ref().is_some_and(|t| t.trim().is_empty()) {
+            errors.push(ValidationIssue::new("settings.admin.bearer_token", "cannot be empty"));
+        }
+        if admin.bearer_token.is_none() {
+            warn!("settings.admin: enabled without bearer_token, admin routes are not protected");
+        }
+        if let Some(port) = &admin.port {
+            if port.parse::<u16>().is_err() {
+                errors.push(ValidationIssue::new("settings.admin.port", format!("'{}' is not a valid port", port)));
+            }
+            if Some(port) == Some(&settings.server.port) && admin.host.as_ref().is_none_or(|h| h == &settings.server.host) {
+                errors.push(ValidationIssue::new("settings.admin.port", "must differ from settings.server.port"));
+            }
+        }
+        if admin.host.is_some() && admin.port.is_none() {
+            errors.push(ValidationIssue::new("settings.admin.host", "requires settings.admin.port"));
+        }
+    }
+
+    // circuit breaker invariants
+    if let Some(circuit_breaker) = &settings.circuit_breaker {
+        validate_circuit_breaker("settings.circuit_breaker", circuit_breaker, errors);
+    }
+
+    // timeouts invariants
+    if let Some(timeouts) = &settings.timeouts {
+        validate_timeouts("settings.timeouts", timeouts, errors);
+    }
+
     // safety margin sane bounds
     if let Some(s) = settings.safety_margin_seconds {
         if s > 60 * 60 * 24 * 365 {
-            errors.push(format!(
-                "settings.safety_margin_seconds ({}) is unreasonably large",
-                s
+            errors.push(ValidationIssue::new(
+                "settings.safety_margin_seconds",
+                format!("({}) is unreasonably large", s),
+            ));
+        }
+    }
+
+    if let Some(percent) = settings.safety_margin_percent {
+        validate_safety_margin_percent("settings.safety_margin_percent", percent, errors);
+    }
+
+    if let Some(s) = settings.prefetch_margin_seconds {
+        if s > 60 * 60 * 24 * 365 {
+            errors.push(ValidationIssue::new(
+                "settings.prefetch_margin_seconds",
+                format!("({}) is unreasonably large", s),
             ));
         }
     }
@@ -192,24 +336,40 @@ fn validate_settings(settings: &SettingsConfig, errors: &mut Vec<String>) {
     //     errors.push(format!("settings.server.path '{}' must be an absolute path", p));
     // }
     if settings.server.host.is_empty() {
-        errors.push(format!(
-            "settings.server.host '{}' must be valid",
-            settings.server.host
-        ));
+        errors.push(ValidationIssue::new("settings.server.host", format!("'{}' must be valid", settings.server.host)));
     }
     if settings.server.port.is_empty() {
-        errors.push(format!(
-            "settings.server.poort '{}' must be valid",
-            settings.server.port
-        ));
+        errors.push(ValidationIssue::new("settings.server.port", format!("'{}' must be valid", settings.server.port)));
     }
 
     // metrics endpoint start with '/'
     let metrics = &settings.metrics;
     if !metrics.path.starts_with('/') {
-        errors.push(format!(
-            "settings.metrics.path '{}' must start with '/'",
-            metrics.path
+        errors.push(ValidationIssue::new("settings.metrics.path", format!("'{}' must start with '/'", metrics.path)));
+    }
+
+    // probe paths start with '/' and do not shadow each other or metrics
+    for (name, path) in [("health_path", &settings.server.health_path), ("ready_path", &settings.server.ready_path)] {
+        if !path.starts_with('/') {
+            errors.push(ValidationIssue::new(
+                format!("settings.server.{}", name),
+                format!("'{}' must start with '/'", path),
+            ));
+        }
+        if path == &metrics.path {
+            errors.push(ValidationIssue::new(
+                format!("settings.server.{}", name),
+                format!("'{}' conflicts with settings.metrics.path", path),
+            ));
+        }
+    }
+    if settings.server.health_path == settings.server.ready_path
+        || settings.server.health_path == READYZ_PATH
+        || settings.server.ready_path == HEALTHZ_PATH
+    {
+        errors.push(ValidationIssue::new(
+            "settings.server.ready_path",
+            "must differ from settings.server.health_path (or their /healthz, /readyz aliases)",
         ));
     }
     // let port =  metrics.port.parse::<u32>();
@@ -227,51 +387,480 @@ fn validate_settings(settings: &SettingsConfig, errors: &mut Vec<String>) {
     if let Some(logging) = &settings.logging {
         let valid = ["trace", "debug", "info", "warn", "error"];
         if !valid.contains(&logging.level.as_str()) {
-            errors.push(format!(
-                "settings.logging.log_level '{}' invalid; allowed: {:?}",
-                logging.level, valid
+            errors.push(ValidationIssue::new(
+                "settings.logging.level",
+                format!("'{}' invalid; allowed: {:?}", logging.level, valid),
             ));
         }
     }
 }
 
-fn validate_retry(path: &str, retry: &RetryConfig, errors: &mut Vec<String>) {
+fn validate_safety_margin_percent(path: &str, percent: f64, errors: &mut Vec<ValidationIssue>) {
+    if !(percent > 0.0 && percent < 100.0) {
+        errors.push(ValidationIssue::new(path, format!("({}) must be > 0 and < 100", percent)));
+    }
+}
+
+/// Token TTL is known upfront only for `manual_ttl_seconds`
+fn warn_large_safety_margin(src_name: &str, src_cfg: &SourceConfig, safety_margin_seconds_settings: Option<u64>) {
+    for token in &src_cfg.parse.tokens {
+        let Some(ttl) = token.expiration.as_ref().and_then(|exp| exp.manual_ttl_seconds) else {
+            continue;
+        };
+        let safety_margin = get_effective_safety_margin_seconds(
+            src_cfg.safety_margin_seconds.or(safety_margin_seconds_settings),
+            src_cfg.safety_margin_percent,
+            ttl,
+        );
+        if safety_margin > ttl / 2 {
+            warn!(
+                "sources.{}.parse.token[{}]: safety margin {}s exceeds half of manual_ttl_seconds {}s, token is refreshed early",
+                src_name, token.id, safety_margin, ttl
+            );
+        }
+    }
+}
+
+fn validate_circuit_breaker(path: &str, circuit_breaker: &CircuitBreakerConfig, errors: &mut Vec<ValidationIssue>) {
+    if circuit_breaker.failure_threshold == Some(0) {
+        errors.push(ValidationIssue::new(format!("{}.failure_threshold", path), "must be > 0"));
+    }
+    if circuit_breaker.open_duration_seconds == Some(0) {
+        errors.push(ValidationIssue::new(format!("{}.open_duration_seconds", path), "must be > 0"));
+    }
+}
+
+fn validate_timeouts(path: &str, timeouts: &TimeoutConfig, errors: &mut Vec<ValidationIssue>) {
+    if timeouts.connect_seconds == Some(0) {
+        errors.push(ValidationIssue::new(format!("{}.connect_seconds", path), "must be > 0"));
+    }
+    if timeouts.read_seconds == Some(0) {
+        errors.push(ValidationIssue::new(format!("{}.read_seconds", path), "must be > 0"));
+    }
+}
+
+fn validate_retry(path: &str, retry: &RetryConfig, errors: &mut Vec<ValidationIssue>) {
     if let Some(attempts) = retry.attempts {
         if attempts == 0 {
-            errors.push(format!("{}.attempts must be > 0", path));
+            errors.push(ValidationIssue::new(format!("{}.attempts", path), "must be > 0"));
         }
     }
     if let (Some(base), Some(max)) = (retry.base_delay_ms, retry.max_delay_ms) {
         if max < base {
-            errors.push(format!(
-                "{}.max_delay_ms ({}) must be >= base_delay_ms ({})",
-                path, max, base
+            errors.push(ValidationIssue::new(
+                format!("{}.max_delay_ms", path),
+                format!("({}) must be >= base_delay_ms ({})", max, base),
+            ));
+        }
+    }
+    if let Some(retry_on_status) = &retry.retry_on_status {
+        for status in retry_on_status {
+            if !(100..=599).contains(status) {
+                errors.push(ValidationIssue::new(
+                    format!("{}.retry_on_status", path),
+                    format!("'{}' is not a valid HTTP status", status),
+                ));
+            }
+        }
+    }
+}
+
+fn validate_gcp_workload_identity(src_name: &str, gcp: &GcpWorkloadIdentityConfig, errors: &mut Vec<ValidationIssue>) {
+    let path = format!("sources.{}.gcp_workload_identity", src_name);
+    if !gcp.audience.starts_with("//iam.googleapis.com/") {
+        errors.push(ValidationIssue::new(
+            format!("{}.audience", path),
+            format!("'{}' must be a workload identity pool provider, '//iam.googleapis.com/projects/.../providers/...'", gcp.audience),
+        ));
+    }
+    if let Some(subject_token_type) = gcp.subject_token_type.as_deref().filter(|t| !GCP_SUBJECT_TOKEN_TYPES.contains(t)) {
+        errors.push(ValidationIssue::new(
+            format!("{}.subject_token_type", path),
+            format!("'{}' is not supported, expected one of {:?}", subject_token_type, GCP_SUBJECT_TOKEN_TYPES),
+        ));
+    }
+    if let Some(pointer) = gcp.subject_token_pointer.as_deref().filter(|pointer| !is_valid_json_pointer(pointer)) {
+        errors.push(ValidationIssue::new(
+            format!("{}.subject_token_pointer", path),
+            format!("'{}' is not a valid JSON pointer", pointer),
+        ));
+    }
+    if gcp.scope.as_deref().is_some_and(|scope| scope.trim().is_empty()) {
+        errors.push(ValidationIssue::new(format!("{}.scope", path), "cannot be empty"));
+    }
+    if let Some(service_account) = gcp.service_account.as_deref().filter(|sa| !sa.contains('@') || sa.contains('/')) {
+        errors.push(ValidationIssue::new(
+            format!("{}.service_account", path),
+            format!("'{}' must be a service account email", service_account),
+        ));
+    }
+    if let Some(lifetime) = gcp.lifetime_seconds {
+        if gcp.service_account.is_none() {
+            errors.push(ValidationIssue::new(format!("{}.lifetime_seconds", path), "requires service_account"));
+        }
+        if lifetime == 0 || lifetime > GCP_LIFETIME_SECONDS_MAX {
+            errors.push(ValidationIssue::new(
+                format!("{}.lifetime_seconds", path),
+                format!("({}) must be in range 1-{}", lifetime, GCP_LIFETIME_SECONDS_MAX),
+            ));
+        }
+    }
+    for (field, url) in [("sts_url", &gcp.sts_url), ("iam_credentials_url", &gcp.iam_credentials_url)] {
+        if let Some(url) = url.as_deref().filter(|url| !(url.starts_with("http://") || url.starts_with("https://"))) {
+            errors.push(ValidationIssue::new(
+                format!("{}.{}", path, field),
+                format!("'{}' must be an http(s) URL", url),
+            ));
+        }
+    }
+}
+
+fn validate_aws_sts_web_identity(src_name: &str, aws: &AwsStsWebIdentityConfig, errors: &mut Vec<ValidationIssue>) {
+    let path = format!("sources.{}.aws_sts_web_identity", src_name);
+    if !Regex::new(r"^[a-z]{2}(-[a-z]+)+-[0-9]+$").unwrap().is_match(&aws.region) {
+        errors.push(ValidationIssue::new(
+            format!("{}.region", path),
+            format!("'{}' is not an AWS region, e.g. 'eu-west-1'", aws.region),
+        ));
+    }
+    if let GenericSourceValue::Literal { value } = &aws.role_arn {
+        if !(value.starts_with("arn:") && value.contains(":role/")) {
+            errors.push(ValidationIssue::new(
+                format!("{}.role_arn", path),
+                format!("'{}' must be an IAM role ARN, 'arn:aws:iam::<account>:role/<name>'", value),
+            ));
+        }
+    }
+    if let GenericSourceValue::Literal { value } = &aws.role_session_name {
+        if !Regex::new(r"^[\w+=,.@-]{2,64}$").unwrap().is_match(value) {
+            errors.push(ValidationIssue::new(
+                format!("{}.role_session_name", path),
+                format!("'{}' must be 2-64 characters of [\\w+=,.@-]", value),
+            ));
+        }
+    }
+    if let Some(duration) = aws.duration_seconds {
+        if !(AWS_STS_DURATION_SECONDS_MIN..=AWS_STS_DURATION_SECONDS_MAX).contains(&duration) {
+            errors.push(ValidationIssue::new(
+                format!("{}.duration_seconds", path),
+                format!("({}) must be in range {}-{}", duration, AWS_STS_DURATION_SECONDS_MIN, AWS_STS_DURATION_SECONDS_MAX),
             ));
         }
     }
+    if let Some(url) = aws.sts_endpoint.as_deref().filter(|url| !(url.starts_with("http://") || url.starts_with("https://"))) {
+        errors.push(ValidationIssue::new(
+            format!("{}.sts_endpoint", path),
+            format!("'{}' must be an http(s) URL", url),
+        ));
+    }
+}
+
+/// Command is a single executable without shell syntax, stdout has no headers to parse tokens from
+fn validate_exec_source(src_name: &str, exec: &ExecSourceConfig, tokens: &[TokenField], errors: &mut Vec<ValidationIssue>) {
+    let path = format!("sources.{}.exec", src_name);
+    if exec.command.trim().is_empty() {
+        errors.push(ValidationIssue::new(format!("{}.command", path), "cannot be empty"));
+    } else if exec.command.contains(EXEC_SOURCE_COMMAND_FORBIDDEN_CHARS) {
+        errors.push(ValidationIssue::new(
+            format!("{}.command", path),
+            format!("'{}' must be a single executable without shell syntax, pass arguments in args", exec.command),
+        ));
+    }
+    if exec.timeout_ms == Some(0) {
+        errors.push(ValidationIssue::new(format!("{}.timeout_ms", path), "must be greater than 0"));
+    }
+    for (field, value) in exec.generic_values() {
+        if field == "env." {
+            errors.push(ValidationIssue::new(format!("{}.env", path), "variable name cannot be empty"));
+        }
+        validate_generic_source_value(&format!("{}.{}", path, field), value, errors);
+    }
+    for token in tokens.iter().filter(|token| token.parent == "header") {
+        errors.push(ValidationIssue::new(
+            format!("sources.{}.parse.tokens[{}].parent", src_name, token.id),
+            "'header' is not available for type=exec, tokens are read from stdout",
+        ));
+    }
 }
 
 /// SOURCE BASICS & TOKEN INVARIANTS
-fn validate_source_basics(src_name: &str, src_cfg: &SourceConfig, errors: &mut Vec<String>) {
+fn validate_source_basics(src_name: &str, src_cfg: &SourceConfig, errors: &mut Vec<ValidationIssue>) {
     // source type allowed
     match src_cfg.source_type {
         SourceTypes::HTTP | SourceTypes::METADATA | SourceTypes::OAUTH2 => {} // (serde ensures value is valid; keeping match for clarity)
+        SourceTypes::VAULT => match &src_cfg.vault {
+            None => errors.push(ValidationIssue::new(
+                format!("sources.{}.vault", src_name),
+                "block is required for type=vault",
+            )),
+            Some(vault) => {
+                if !(vault.vault_addr.starts_with("http://") || vault.vault_addr.starts_with("https://")) {
+                    errors.push(ValidationIssue::new(
+                        format!("sources.{}.vault.vault_addr", src_name),
+                        format!("'{}' must be an http(s) URL", vault.vault_addr),
+                    ));
+                }
+            }
+        },
+        SourceTypes::KubeServiceAccount => match &src_cfg.kube_service_account {
+            None => errors.push(ValidationIssue::new(
+                format!("sources.{}.kube_service_account", src_name),
+                "block is required for type=kube_service_account",
+            )),
+            Some(kube) => {
+                if !Path::new(&kube.path).is_absolute() {
+                    errors.push(ValidationIssue::new(
+                        format!("sources.{}.kube_service_account.path", src_name),
+                        format!("'{}' must be an absolute path", kube.path),
+                    ));
+                }
+                if kube.audience.as_deref().is_some_and(|audience| audience.trim().is_empty()) {
+                    errors.push(ValidationIssue::new(
+                        format!("sources.{}.kube_service_account.audience", src_name),
+                        "cannot be empty",
+                    ));
+                }
+            }
+        },
+        SourceTypes::FILE => match &src_cfg.file {
+            None => errors.push(ValidationIssue::new(
+                format!("sources.{}.file", src_name),
+                "block is required for type=file",
+            )),
+            Some(file) => {
+                if !Path::new(&file.path).is_absolute() {
+                    errors.push(ValidationIssue::new(
+                        format!("sources.{}.file.path", src_name),
+                        format!("'{}' must be an absolute path", file.path),
+                    ));
+                }
+                match (file.token_type.clone().unwrap_or(TokenType::PlainText), file.ttl_seconds) {
+                    (TokenType::PlainText, None) => {
+                        errors.push(ValidationIssue::new(
+                            format!("sources.{}.file.ttl_seconds", src_name),
+                            "is required for token_type=plain_text",
+                        ))
+                    }
+                    (TokenType::Jwt, Some(_)) => errors.push(ValidationIssue::new(
+                        format!("sources.{}.file.ttl_seconds", src_name),
+                        "must not be set for token_type=jwt, expiration comes from the exp claim",
+                    )),
+                    _ => {}
+                }
+            }
+        },
+        SourceTypes::EXEC => match &src_cfg.exec {
+            None => errors.push(ValidationIssue::new(
+                format!("sources.{}.exec", src_name),
+                "block is required for type=exec",
+            )),
+            Some(exec) => validate_exec_source(src_name, exec, &src_cfg.parse.tokens, errors),
+        },
+        SourceTypes::GcpWorkloadIdentity => match &src_cfg.gcp_workload_identity {
+            None => errors.push(ValidationIssue::new(
+                format!("sources.{}.gcp_workload_identity", src_name),
+                "block is required for type=gcp_workload_identity",
+            )),
+            Some(gcp) => validate_gcp_workload_identity(src_name, gcp, errors),
+        },
+        SourceTypes::AwsStsWebIdentity => match &src_cfg.aws_sts_web_identity {
+            None => errors.push(ValidationIssue::new(
+                format!("sources.{}.aws_sts_web_identity", src_name),
+                "block is required for type=aws_sts_web_identity",
+            )),
+            Some(aws) => validate_aws_sts_web_identity(src_name, aws, errors),
+        },
+        SourceTypes::IMDSV2 => {
+            if let Some(ttl) = src_cfg.request.session_ttl_seconds {
+                if ttl == 0 || ttl > IMDSV2_SESSION_TTL_SECONDS_MAX {
+                    errors.push(ValidationIssue::new(
+                        format!("sources.{}.request.session_ttl_seconds", src_name),
+                        format!("({}) must be in range 1-{}", ttl, IMDSV2_SESSION_TTL_SECONDS_MAX),
+                    ));
+                }
+            }
+        }
+    }
+    if src_cfg.vault.is_some() && !matches!(src_cfg.source_type, SourceTypes::VAULT) {
+        errors.push(ValidationIssue::new(format!("sources.{}.vault", src_name), "block is only valid for type=vault"));
+    }
+    if src_cfg.kube_service_account.is_some() && !matches!(src_cfg.source_type, SourceTypes::KubeServiceAccount) {
+        errors.push(ValidationIssue::new(
+            format!("sources.{}.kube_service_account", src_name),
+            "block is only valid for type=kube_service_account",
+        ));
+    }
+    if src_cfg.file.is_some() && !matches!(src_cfg.source_type, SourceTypes::FILE) {
+        errors.push(ValidationIssue::new(format!("sources.{}.file", src_name), "block is only valid for type=file"));
+    }
+    if src_cfg.exec.is_some() && !matches!(src_cfg.source_type, SourceTypes::EXEC) {
+        errors.push(ValidationIssue::new(format!("sources.{}.exec", src_name), "block is only valid for type=exec"));
+    }
+    if src_cfg.gcp_workload_identity.is_some() && !matches!(src_cfg.source_type, SourceTypes::GcpWorkloadIdentity) {
+        errors.push(ValidationIssue::new(
+            format!("sources.{}.gcp_workload_identity", src_name),
+            "block is only valid for type=gcp_workload_identity",
+        ));
+    }
+    if src_cfg.aws_sts_web_identity.is_some() && !matches!(src_cfg.source_type, SourceTypes::AwsStsWebIdentity) {
+        errors.push(ValidationIssue::new(
+            format!("sources.{}.aws_sts_web_identity", src_name),
+            "block is only valid for type=aws_sts_web_identity",
+        ));
+    }
+    if src_cfg.request.max_response_bytes == Some(0) {
+        errors.push(ValidationIssue::new(
+            format!("sources.{}.request.max_response_bytes", src_name),
+            "must be greater than 0",
+        ));
+    }
+    if src_cfg.stale_token_ttl_seconds == Some(0) {
+        errors.push(ValidationIssue::new(
+            format!("sources.{}.stale_token_ttl_seconds", src_name),
+            "must be greater than 0",
+        ));
+    }
+    if src_cfg.refresh_on_input_change == Some(true) && src_cfg.inputs.as_ref().is_none_or(|inputs| inputs.is_empty()) {
+        errors.push(ValidationIssue::new(format!("sources.{}.refresh_on_input_change", src_name), "requires inputs"));
+    }
+    if src_cfg.request.session_ttl_seconds.is_some() && !matches!(src_cfg.source_type, SourceTypes::IMDSV2) {
+        errors.push(ValidationIssue::new(
+            format!("sources.{}.request.session_ttl_seconds", src_name),
+            "is only valid for type=imdsv2",
+        ));
+    }
+    if let Some(expect) = &src_cfg.parse.expect {
+        if !matches!(
+            src_cfg.source_type,
+            SourceTypes::HTTP | SourceTypes::METADATA | SourceTypes::OAUTH2 | SourceTypes::IMDSV2
+        ) {
+            errors.push(ValidationIssue::new(
+                format!("sources.{}.parse.expect", src_name),
+                "is only valid for type=http, metadata, oauth2 or imdsv2",
+            ));
+        }
+        match &expect.expected_status {
+            Some(statuses) if statuses.is_empty() => {
+                errors.push(ValidationIssue::new(
+                    format!("sources.{}.parse.expect.expected_status", src_name),
+                    "must not be empty",
+                ))
+            }
+            Some(statuses) => {
+                for status in statuses.iter().filter(|status| !(100..=599).contains(*status)) {
+                    errors.push(ValidationIssue::new(
+                        format!("sources.{}.parse.expect.expected_status", src_name),
+                        format!("{} is not a valid HTTP status", status),
+                    ));
+                }
+            }
+            None => {}
+        }
+        if expect.expected_content_type.as_ref().is_some_and(|content_type| content_type.trim().is_empty()) {
+            errors.push(ValidationIssue::new(
+                format!("sources.{}.parse.expect.expected_content_type", src_name),
+                "must not be empty",
+            ));
+        }
+    }
+
+    // source level retry invariants
+    if let Some(retry) = &src_cfg.retry {
+        validate_retry(&format!("sources.{}.retry", src_name), retry, errors);
+    }
+
+    // source level circuit breaker invariants
+    if let Some(circuit_breaker) = &src_cfg.circuit_breaker {
+        validate_circuit_breaker(&format!("sources.{}.circuit_breaker", src_name), circuit_breaker, errors);
+    }
+
+    // source level timeouts invariants
+    if let Some(timeouts) = &src_cfg.timeouts {
+        validate_timeouts(&format!("sources.{}.timeouts", src_name), timeouts, errors);
+    }
+
+    // tls files must be readable, client cert and key go together
+    if let Some(tls) = &src_cfg.tls {
+        if tls.client_cert_file.is_some() != tls.client_key_file.is_some() {
+            errors.push(ValidationIssue::new(
+                format!("sources.{}.tls", src_name),
+                "client_cert_file and client_key_file must be set together",
+            ));
+        }
+        for (field, file) in [
+            ("ca_file", &tls.ca_file),
+            ("client_cert_file", &tls.client_cert_file),
+            ("client_key_file", &tls.client_key_file),
+        ] {
+            match file {
+                Some(file) if file.trim().is_empty() => {
+                    errors.push(ValidationIssue::new(
+                        format!("sources.{}.tls.{}", src_name, field),
+                        "must not be empty",
+                    ));
+                }
+                Some(file) if !Path::new(file).is_file() => {
+                    errors.push(ValidationIssue::new(
+                        format!("sources.{}.tls.{}", src_name, field),
+                        format!("'{}' does not exist", file),
+                    ));
+                }
+                Some(file) => {
+                    if let Err(e) = std::fs::File::open(file) {
+                        errors.push(ValidationIssue::new(
+                            format!("sources.{}.tls.{}", src_name, field),
+                            format!("'{}' is not readable: {}", file, e),
+                        ));
+                    }
+                }
+                None => {}
+            }
+        }
+        if tls.insecure_skip_verify.unwrap_or(false) {
+            warn!("sources.{}: tls.insecure_skip_verify is enabled, server certificate is not verified", src_name);
+        }
     }
 
-    // request URL non-empty
-    if src_cfg.request.url.trim().is_empty() {
-        errors.push(format!("sources.{}: request.url cannot be empty", src_name));
+    // request URL non-empty, file and exec sources have no request
+    if src_cfg.request.url.trim().is_empty() && !matches!(src_cfg.source_type, SourceTypes::FILE | SourceTypes::EXEC) {
+        errors.push(ValidationIssue::new(format!("sources.{}.request.url", src_name), "cannot be empty"));
     }
 
-    // request method allowed (GET, POST)
+    // well known XML endpoints answer with XML only, JSON parsing would fail on every fetch: warn only
+    if matches!(src_cfg.source_type, SourceTypes::HTTP)
+        && src_cfg.parse.body_format != Some(BodyFormat::Xml)
+        && is_known_xml_endpoint(&src_cfg.request.url)
+    {
+        warn!(
+            "sources.{}: request.url '{}' returns XML, set parse.body_format: xml",
+            src_name, src_cfg.request.url
+        );
+    }
+
+    // request method allowed (GET, POST, PUT, PATCH, DELETE)
     match src_cfg.request.method.as_str() {
-        "GET" | "POST" => {}
-        m => errors.push(format!(
-            "sources.{}: request.method '{}' must be 'GET' or 'POST'",
-            src_name, m
+        "GET" | "POST" | "PUT" | "PATCH" | "DELETE" => {}
+        m => errors.push(ValidationIssue::new(
+            format!("sources.{}.request.method", src_name),
+            format!("'{}' must be one of 'GET', 'POST', 'PUT', 'PATCH', 'DELETE'", m),
         )),
     }
 
+    // body is ignored for GET and unusual for DELETE: warn only
+    if src_cfg.request.body.is_some() {
+        match src_cfg.request.method.as_str() {
+            "GET" => warn!(
+                "sources.{}: request.body is ignored for method 'GET'",
+                src_name
+            ),
+            "DELETE" => warn!(
+                "sources.{}: request.body with method 'DELETE' may be rejected by the server",
+                src_name
+            ),
+            _ => {}
+        }
+    }
+
     // headers & body: validate GenericSourceValue usage
     if let Some(headers) = &src_cfg.request.headers {
         for (k, v) in headers {
@@ -280,13 +869,21 @@ fn validate_source_basics(src_name: &str, src_cfg: &SourceConfig, errors: &mut V
                 v,
                 errors,
             );
-            // If Template variant -> check template primitive syntax
-            if let GenericSourceValue::Template { template, required } = v {
-                validate_template_placeholders(template, errors, src_name);
-                if *required {
-                    // required=true enforced here conceptually; no further validation needed
-                }
+        }
+    }
+    if let Some(query) = &src_cfg.request.query {
+        for (k, v) in query {
+            if k.trim().is_empty() {
+                errors.push(ValidationIssue::new(
+                    format!("sources.{}.request.query", src_name),
+                    "parameter name cannot be empty",
+                ));
             }
+            validate_generic_source_value(
+                &format!("sources.{}.request.query.{}", src_name, k),
+                v,
+                errors,
+            );
         }
     }
     if let Some(body) = &src_cfg.request.body {
@@ -296,27 +893,43 @@ fn validate_source_basics(src_name: &str, src_cfg: &SourceConfig, errors: &mut V
                 v,
                 errors,
             );
-            if let GenericSourceValue::Template {
-                template,
-                required: _,
-            } = v
-            {
-                validate_template_placeholders(template, errors, src_name);
-            }
         }
     }
     if let Some(form) = &src_cfg.request.form {
+        if src_cfg.request.body.is_some() {
+            errors.push(ValidationIssue::new(
+                format!("sources.{}.request.form", src_name),
+                "body and form are mutually exclusive",
+            ));
+        }
+        if form.grant_type.as_ref().is_some_and(|grant_type| grant_type.trim().is_empty()) {
+            errors.push(ValidationIssue::new(
+                format!("sources.{}.request.form.grant_type", src_name),
+                "cannot be empty",
+            ));
+        }
         // verify form fields are provided and valid
         validate_generic_source_value(
             &format!("sources.{}.request.form.client_id", src_name),
             &form.client_id,
             errors,
         );
-        validate_generic_source_value(
-            &format!("sources.{}.request.form.client_secret", src_name),
-            &form.client_secret,
-            errors,
-        );
+        match (&form.client_secret, &form.client_assertion) {
+            (Some(client_secret), None) => validate_generic_source_value(
+                &format!("sources.{}.request.form.client_secret", src_name),
+                client_secret,
+                errors,
+            ),
+            (None, Some(client_assertion)) => validate_generic_source_value(
+                &format!("sources.{}.request.form.client_assertion", src_name),
+                client_assertion,
+                errors,
+            ),
+            _ => errors.push(ValidationIssue::new(
+                format!("sources.{}.request.form", src_name),
+                "exactly one of client_secret and client_assertion is required",
+            )),
+        }
         validate_generic_source_value(
             &format!("sources.{}.request.form.scope", src_name),
             &form.scope,
@@ -326,35 +939,50 @@ fn validate_source_basics(src_name: &str, src_cfg: &SourceConfig, errors: &mut V
 
     // parse tokens must exist and be unique per source
     if src_cfg.parse.tokens.is_empty() {
-        errors.push(format!(
-            "sources.{}: parse.tokens must include at least one token field",
-            src_name
+        errors.push(ValidationIssue::new(
+            format!("sources.{}.parse.tokens", src_name),
+            "must include at least one token field",
         ));
     } else {
         let mut seen_ids = HashSet::new();
+        let body_format = src_cfg.parse.body_format.unwrap_or_default();
         for token in &src_cfg.parse.tokens {
             if token.id.trim().is_empty() {
-                errors.push(format!(
-                    "sources.{}.parse: token id cannot be empty",
-                    src_name
+                errors.push(ValidationIssue::new(
+                    format!("sources.{}.parse.tokens", src_name),
+                    "token id cannot be empty",
                 ));
             }
             if !seen_ids.insert(token.id.clone()) {
-                errors.push(format!(
-                    "sources.{}: duplicate token id '{}'",
-                    src_name, token.id
+                errors.push(ValidationIssue::new(
+                    format!("sources.{}.parse.tokens[{}]", src_name, token.id),
+                    "duplicate token id",
                 ));
             }
-            validate_token_field(src_name, token, errors);
+            validate_token_field(src_name, token, body_format, errors);
         }
+        validate_linked_token_ids(src_name, &src_cfg.parse.tokens, errors);
     }
 
     // safety margin bounds
     if let Some(s) = src_cfg.safety_margin_seconds {
         if s > 60 * 60 * 24 * 365 {
-            errors.push(format!(
-                "sources.{}.safety_margin_seconds ({}) is unreasonably large",
-                src_name, s
+            errors.push(ValidationIssue::new(
+                format!("sources.{}.safety_margin_seconds", src_name),
+                format!("({}) is unreasonably large", s),
+            ));
+        }
+    }
+
+    if let Some(percent) = src_cfg.safety_margin_percent {
+        validate_safety_margin_percent(&format!("sources.{}.safety_margin_percent", src_name), percent, errors);
+    }
+
+    if let Some(s) = src_cfg.prefetch_margin_seconds {
+        if s > 60 * 60 * 24 * 365 {
+            errors.push(ValidationIssue::new(
+                format!("sources.{}.prefetch_margin_seconds", src_name),
+                format!("({}) is unreasonably large", s),
             ));
         }
     }
@@ -362,64 +990,260 @@ fn validate_source_basics(src_name: &str, src_cfg: &SourceConfig, errors: &mut V
     // inputs checked at top-level later to ensure existence.
 }
 
-fn validate_generic_source_value(path: &str, v: &GenericSourceValue, errors: &mut Vec<String>) {
+/// Redis URL scheme, key template placeholders and fixed TTL
+fn validate_redis_sink(sink_name: &str, redis: &RedisSinkConfig, errors: &mut Vec<ValidationIssue>) {
+    let path = format!("sinks.{}.redis", sink_name);
+    validate_generic_source_value(&format!("{}.redis_url", path), &redis.redis_url, errors);
+    if let GenericSourceValue::Literal { value } = &redis.redis_url {
+        if !REDIS_URL_SCHEMES.iter().any(|scheme| value.starts_with(scheme)) {
+            errors.push(ValidationIssue::new(
+                format!("{}.redis_url", path),
+                format!("must start with one of {:?}", REDIS_URL_SCHEMES),
+            ));
+        }
+    }
+    if redis.key_template.trim().is_empty() {
+        errors.push(ValidationIssue::new(format!("{}.key_template", path), "cannot be empty"));
+    }
+    let placeholder = Regex::new(r"\{([^{}]*)\}").unwrap();
+    for captures in placeholder.captures_iter(&redis.key_template) {
+        if !matches!(&captures[1], "source_id" | "token_id") {
+            errors.push(ValidationIssue::new(
+                format!("{}.key_template", path),
+                format!("placeholder '{{{}}}' is unknown, expected {{source_id}} or {{token_id}}", &captures[1]),
+            ));
+        }
+    }
+    if redis.ttl_mode == Some(RedisTtlMode::FixedSeconds(0)) {
+        errors.push(ValidationIssue::new(format!("{}.ttl_mode", path), "fixed_seconds must be greater than 0"));
+    }
+}
+
+fn validate_kubernetes_secret_sink(sink_name: &str, secret: &KubernetesSecretSinkConfig, errors: &mut Vec<ValidationIssue>) {
+    let path = format!("sinks.{}.kubernetes_secret", sink_name);
+    // RFC 1123 label for namespaces, subdomain for object names
+    let label = Regex::new(r"^[a-z0-9]([-a-z0-9]*[a-z0-9])?$").unwrap();
+    if secret.namespace.len() > 63 || !label.is_match(&secret.namespace) {
+        errors.push(ValidationIssue::new(
+            format!("{}.namespace", path),
+            format!("'{}' is not a valid namespace name", secret.namespace),
+        ));
+    }
+    if secret.secret_name.len() > 253 || !secret.secret_name.split('.').all(|part| label.is_match(part)) {
+        errors.push(ValidationIssue::new(
+            format!("{}.secret_name", path),
+            format!("'{}' is not a valid Secret name", secret.secret_name),
+        ));
+    }
+    let data_key = Regex::new(r"^[-._a-zA-Z0-9]+$").unwrap();
+    if secret.key.len() > 253 || !data_key.is_match(&secret.key) {
+        errors.push(ValidationIssue::new(
+            format!("{}.key", path),
+            format!("'{}' must consist of alphanumeric characters, '-', '_' or '.'", secret.key),
+        ));
+    }
+    if secret.kubeconfig.as_ref().is_some_and(|kubeconfig| kubeconfig.trim().is_empty()) {
+        errors.push(ValidationIssue::new(format!("{}.kubeconfig", path), "path cannot be empty"));
+    }
+}
+
+fn validate_generic_source_value(path: &str, v: &GenericSourceValue, errors: &mut Vec<ValidationIssue>) {
     match v {
         GenericSourceValue::Literal { value } => {
             if value.trim().is_empty() {
-                errors.push(format!("{}: literal value cannot be empty", path));
+                errors.push(ValidationIssue::new(format!("{}.value", path), "cannot be empty"));
             }
         }
         GenericSourceValue::FromEnv { from_env } => {
             if from_env.trim().is_empty() {
-                errors.push(format!("{}: env name cannot be empty", path));
+                errors.push(ValidationIssue::new(format!("{}.from_env", path), "cannot be empty"));
             }
         }
         GenericSourceValue::FromFile { path: p } => {
             if p.trim().is_empty() {
-                errors.push(format!("{}: from_file path cannot be empty", path));
+                errors.push(ValidationIssue::new(format!("{}.path", path), "from_file path cannot be empty"));
             }
             // don't check file existence here; prechecks elsewhere may check FS permissions
         }
+        GenericSourceValue::FromAwsSsm { parameter_name, with_decryption: _ } => {
+            if parameter_name.trim().is_empty() {
+                errors.push(ValidationIssue::new(format!("{}.parameter_name", path), "cannot be empty"));
+            }
+        }
+        GenericSourceValue::FromGcpSecret { project, secret, version } => {
+            if project.trim().is_empty() || secret.trim().is_empty() {
+                errors.push(ValidationIssue::new(path, "project and secret cannot be empty"));
+            }
+            if version.as_ref().is_some_and(|version| version.trim().is_empty()) {
+                errors.push(ValidationIssue::new(format!("{}.version", path), "cannot be empty"));
+            }
+            // short name expected, `projects/p/secrets/s` is built from project and secret
+            if secret.contains('/') {
+                warn!("{}: secret '{}' contains '/', expected a secret name without 'projects/.../secrets/' prefix", path, secret);
+            }
+        }
         GenericSourceValue::Ref {
             source,
             id,
             prefix: _,
         } => {
             if source.trim().is_empty() || id.trim().is_empty() {
-                errors.push(format!(
-                    "{}: ref must include non-empty source and id",
-                    path
-                ));
+                errors.push(ValidationIssue::new(path, "ref must include non-empty source and id"));
+            }
+            // cross-reference check done in validate_source_references and validate_sink_references
+        }
+        GenericSourceValue::SignedJwt { key_file, algorithm, claims, ttl_seconds } => {
+            if key_file.trim().is_empty() {
+                errors.push(ValidationIssue::new(format!("{}.key_file", path), "cannot be empty"));
+            }
+            match get_signing_algorithm(algorithm.as_deref()) {
+                Err(err) => errors.push(ValidationIssue::new(path, err.to_string())),
+                // fail fast on unreadable or invalid key, it is cached on first signing
+                Ok(algorithm) if !key_file.trim().is_empty() => {
+                    if let Err(err) = load_signing_key(key_file, algorithm) {
+                        errors.push(ValidationIssue::new(path, err.to_string()));
+                    }
+                }
+                Ok(_) => {}
+            }
+            for claim in SIGNED_JWT_RESERVED_CLAIMS.iter().filter(|claim| claims.contains_key(**claim)) {
+                errors.push(ValidationIssue::new(format!("{}.claims.{}", path, claim), "is set from ttl_seconds"));
+            }
+            if *ttl_seconds == Some(0) {
+                errors.push(ValidationIssue::new(format!("{}.ttl_seconds", path), "must be greater than 0"));
             }
-            // cross-reference check done later when we have list of sources and tokens
         }
         GenericSourceValue::Template {
             template,
             required: _,
         } => {
             if template.trim().is_empty() {
-                errors.push(format!("{}: template cannot be empty", path));
+                errors.push(ValidationIssue::new(format!("{}.template", path), "cannot be empty"));
             }
-            // template placeholder validation done below at higher level (needs knowledge of sources)
+            // template placeholder validation done in validate_source_references and validate_sink_references
         }
     }
 }
 
+/// linked_token_id must reference another, not linked, token of the same source
+fn validate_linked_token_ids(src_name: &str, tokens: &[TokenField], errors: &mut Vec<ValidationIssue>) {
+    let tokens_by_id: HashMap<&str, &TokenField> = tokens.iter().map(|t| (t.id.as_str(), t)).collect();
+    for token in tokens {
+        let Some(linked) = token.expiration.as_ref().and_then(|exp| exp.linked_token_id.as_ref()) else {
+            continue;
+        };
+        if linked.trim().is_empty() {
+            continue;
+        }
+        match tokens_by_id.get(linked.as_str()) {
+            None => errors.push(ValidationIssue::new(
+                format!("sources.{}.parse.tokens[{}].expiration.linked_token_id", src_name, token.id),
+                format!("'{}' not found in source tokens", linked),
+            )),
+            Some(_) if linked == &token.id => errors.push(ValidationIssue::new(
+                format!("sources.{}.parse.tokens[{}].expiration.linked_token_id", src_name, token.id),
+                "must reference another token",
+            )),
+            Some(linked_token) if linked_token.expiration.as_ref().is_some_and(|exp| exp.linked_token_id.is_some()) => {
+                errors.push(ValidationIssue::new(
+                    format!("sources.{}.parse.tokens[{}].expiration.linked_token_id", src_name, token.id),
+                    format!("linked token '{}' must not be linked itself", linked),
+                ))
+            }
+            Some(_) => {}
+        }
+    }
+}
+
+/// Best-effort check of endpoints known to return XML: AWS STS and SOAP services
+fn is_known_xml_endpoint(url: &str) -> bool {
+    let url = url.to_ascii_lowercase();
+    let host = url.split("://").nth(1).unwrap_or(&url).split(['/', '?', ':']).next().unwrap_or_default();
+    (host.starts_with("sts.") && host.ends_with("amazonaws.com"))
+        || url.contains("?wsdl")
+        || url.contains("/soap")
+}
+
 /// Validate token-level invariants (token + expiration)
-fn validate_token_field(src_name: &str, token: &TokenField, errors: &mut Vec<String>) {
+fn validate_token_field(src_name: &str, token: &TokenField, body_format: BodyFormat, errors: &mut Vec<ValidationIssue>) {
     // parent must be "body" or "header"
     match token.parent.as_str() {
         "body" | "header" => {}
-        other => errors.push(format!(
-            "sources.{}.parse.token[{}].parent must be 'body' or 'header', got '{}'",
-            src_name, token.id, other
+        other => errors.push(ValidationIssue::new(
+            format!("sources.{}.parse.tokens[{}].parent", src_name, token.id),
+            format!("must be 'body' or 'header', got '{}'", other),
         )),
     }
 
     if token.pointer.trim().is_empty() {
-        errors.push(format!(
-            "sources.{}.parse.token[{}].pointer cannot be empty",
-            src_name, token.id
+        errors.push(ValidationIssue::new(
+            format!("sources.{}.parse.tokens[{}].pointer", src_name, token.id),
+            "cannot be empty",
+        ));
+    } else if token.parent == "body" && body_format == BodyFormat::Plain && token.pointer != PLAIN_BODY_POINTER {
+        errors.push(ValidationIssue::new(
+            format!("sources.{}.parse.tokens[{}].pointer", src_name, token.id),
+            format!("must be '{}' for body_format=plain", PLAIN_BODY_POINTER),
+        ));
+    } else if token.parent == "body" && body_format == BodyFormat::FormUrlencoded && token.pointer.starts_with('/') {
+        errors.push(ValidationIssue::new(
+            format!("sources.{}.parse.tokens[{}].pointer", src_name, token.id),
+            format!("'{}' must be a form key for body_format=form_urlencoded", token.pointer),
+        ));
+    } else if token.parent == "body" && body_format == BodyFormat::Xml && !is_valid_xml_path(&token.pointer) {
+        errors.push(ValidationIssue::new(
+            format!("sources.{}.parse.tokens[{}].pointer", src_name, token.id),
+            format!("'{}' is not a valid XML element path like '/Root/Element' or 'Root.Element'", token.pointer),
+        ));
+    } else if token.parent == "body" && body_format != BodyFormat::Json {
+        // plain, form and XML pointers are not JSON pointers
+    } else if token.parent == "body" && !is_valid_json_pointer(&token.pointer) {
+        errors.push(ValidationIssue::new(
+            format!("sources.{}.parse.tokens[{}].pointer", src_name, token.id),
+            format!("'{}' is not a valid JSON pointer", token.pointer),
+        ));
+    } else if token.parent == "body" && is_ambiguous_json_pointer(&token.pointer) {
+        warn!(
+            "sources.{}.parse.token[{}].pointer '{}' is read as top-level field if present, otherwise as dot path; use '/{}' for a nested path",
+            src_name,
+            token.id,
+            token.pointer,
+            token.pointer.replace('.', "/")
+        );
+    }
+
+    if let Some(iterate) = &token.iterate {
+        validate_token_iterate(src_name, token, iterate, body_format, errors);
+    }
+
+    for transform in token.transforms.iter().flatten() {
+        match transform {
+            TokenTransform::StripPrefix(affix) | TokenTransform::StripSuffix(affix) if affix.is_empty() => {
+                errors.push(ValidationIssue::new(
+                    format!("sources.{}.parse.tokens[{}].transforms", src_name, token.id),
+                    "strip prefix/suffix cannot be empty",
+                ));
+            }
+            TokenTransform::JsonExtract { pointer } if !is_valid_json_pointer(pointer) => {
+                errors.push(ValidationIssue::new(
+                    format!("sources.{}.parse.tokens[{}].transforms", src_name, token.id),
+                    format!("json_extract pointer '{}' is not a valid JSON pointer", pointer),
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    if token.max_token_bytes == Some(0) {
+        errors.push(ValidationIssue::new(
+            format!("sources.{}.parse.tokens[{}].max_token_bytes", src_name, token.id),
+            "must be greater than 0",
+        ));
+    }
+    if let Some(Err(e)) = token.value_regex.as_deref().map(Regex::new) {
+        errors.push(ValidationIssue::new(
+            format!("sources.{}.parse.tokens[{}].value_regex", src_name, token.id),
+            format!("is not a valid regex: {}", e),
         ));
     }
 
@@ -427,44 +1251,122 @@ fn validate_token_field(src_name: &str, token: &TokenField, errors: &mut Vec<Str
         TokenType::Jwt => {
             // For JWT tokens we expect no explicit expiration block (expiration must be None)
             if token.expiration.is_some() {
-                errors.push(format!("sources.{}.parse.token[{}]: token_type=jwt must not declare expiration block; expiry extracted from token", src_name, token.id));
+                errors.push(ValidationIssue::new(
+                    format!("sources.{}.parse.tokens[{}].expiration", src_name, token.id),
+                    "must not be declared for token_type=jwt; expiry extracted from token",
+                ));
+            }
+            if let Some(jwks_uri) = &token.jwks_uri {
+                if !(jwks_uri.starts_with("http://") || jwks_uri.starts_with("https://")) {
+                    errors.push(ValidationIssue::new(
+                        format!("sources.{}.parse.tokens[{}].jwks_uri", src_name, token.id),
+                        format!("'{}' must be an http(s) URL", jwks_uri),
+                    ));
+                }
+            }
+            let claim_name = Regex::new(r"^[a-zA-Z0-9_]+$").unwrap();
+            for claim in token.extract_claims.iter().flatten().filter(|claim| !claim_name.is_match(claim)) {
+                errors.push(ValidationIssue::new(
+                    format!("sources.{}.parse.tokens[{}].extract_claims", src_name, token.id),
+                    format!("claim '{}' must contain only letters, digits and '_'", claim),
+                ));
             }
         }
         TokenType::PlainText => {
-            // Plain text must have expiration block
-            if token.expiration.is_none() {
-                errors.push(format!(
-                    "sources.{}.parse.token[{}]: token_type=plain_text requires expiration block",
-                    src_name, token.id
+            if token.jwks_uri.is_some() {
+                errors.push(ValidationIssue::new(
+                    format!("sources.{}.parse.tokens[{}].jwks_uri", src_name, token.id),
+                    "is only valid for token_type=jwt",
+                ));
+            }
+            if token.extract_claims.is_some() {
+                errors.push(ValidationIssue::new(
+                    format!("sources.{}.parse.tokens[{}].extract_claims", src_name, token.id),
+                    "is only valid for token_type=jwt",
                 ));
-            } else if let Some(exp) = &token.expiration {
-                validate_expiration(src_name, token, exp, errors);
             }
+            // Plain text must have expiration block, unless it is read from every array element
+            let exp_from = token.iterate.as_ref().and_then(|iterate| iterate.exp_from.as_ref());
+            match (&token.expiration, exp_from) {
+                (None, None) => errors.push(ValidationIssue::new(
+                    format!("sources.{}.parse.tokens[{}].expiration", src_name, token.id),
+                    "is required for token_type=plain_text",
+                )),
+                (Some(_), Some(_)) => errors.push(ValidationIssue::new(
+                    format!("sources.{}.parse.tokens[{}].expiration", src_name, token.id),
+                    "must not be declared with iterate.exp_from",
+                )),
+                (Some(exp), None) => validate_expiration(src_name, token, exp, body_format, errors),
+                (None, Some(_)) => {}
+            }
+        }
+    }
+}
+
+fn validate_token_iterate(
+    src_name: &str,
+    token: &TokenField,
+    iterate: &TokenIterate,
+    body_format: BodyFormat,
+    errors: &mut Vec<ValidationIssue>,
+) {
+    if token.parent != "body" || body_format != BodyFormat::Json {
+        errors.push(ValidationIssue::new(
+            format!("sources.{}.parse.tokens[{}].iterate", src_name, token.id),
+            "is only valid for parent=body with body_format=json",
+        ));
+    }
+    let pointers = [("id_from", Some(&iterate.id_from)), ("value_from", Some(&iterate.value_from)), ("exp_from", iterate.exp_from.as_ref())];
+    for (field, pointer) in pointers.into_iter().filter_map(|(field, pointer)| Some((field, pointer?))) {
+        if !is_valid_json_pointer(pointer) {
+            errors.push(ValidationIssue::new(
+                format!("sources.{}.parse.tokens[{}].iterate.{}", src_name, token.id, field),
+                format!("'{}' is not a valid JSON pointer", pointer),
+            ));
         }
     }
+    if token.token_type == TokenType::Jwt && iterate.exp_from.is_some() {
+        errors.push(ValidationIssue::new(
+            format!("sources.{}.parse.tokens[{}].iterate.exp_from", src_name, token.id),
+            "is only valid for token_type=plain_text; expiry extracted from token",
+        ));
+    }
 }
 
 fn validate_expiration(
     src_name: &str,
     token: &TokenField,
     exp: &Expiration,
-    errors: &mut Vec<String>,
+    body_format: BodyFormat,
+    errors: &mut Vec<ValidationIssue>,
 ) {
     // format must be a valid enum (serde ensures it), but we check logical constraints:
     match exp.source {
         ExpirationSource::SelfField => {
             // self allowed only if token is JWT
             if token.token_type != TokenType::Jwt {
-                errors.push(format!("sources.{}.parse.token[{}].expiration: source='self' only valid for token_type=jwt", src_name, token.id));
+                errors.push(ValidationIssue::new(
+                    format!("sources.{}.parse.tokens[{}].expiration.source", src_name, token.id),
+                    "'self' is only valid for token_type=jwt",
+                ));
             }
             if exp.pointer.is_some() {
-                errors.push(format!("sources.{}.parse.token[{}].expiration: when source='self' pointer must not be provided", src_name, token.id));
+                errors.push(ValidationIssue::new(
+                    format!("sources.{}.parse.tokens[{}].expiration.pointer", src_name, token.id),
+                    "must not be provided when source='self'",
+                ));
             }
             if exp.manual_ttl_seconds.is_some() {
-                errors.push(format!("sources.{}.parse.token[{}].expiration: when source='self' manual_ttl_seconds must not be provided", src_name, token.id));
+                errors.push(ValidationIssue::new(
+                    format!("sources.{}.parse.tokens[{}].expiration.manual_ttl_seconds", src_name, token.id),
+                    "must not be provided when source='self'",
+                ));
             }
             if exp.linked_token_id.is_some() {
-                errors.push(format!("sources.{}.parse.token[{}].expiration: when source='self' linked_token_id must not be provided", src_name, token.id));
+                errors.push(ValidationIssue::new(
+                    format!("sources.{}.parse.tokens[{}].expiration.linked_token_id", src_name, token.id),
+                    "must not be provided when source='self'",
+                ));
             }
         }
         ExpirationSource::JsonBodyField | ExpirationSource::HeaderField => {
@@ -475,24 +1377,65 @@ fn validate_expiration(
                 .map(|s| s.trim().is_empty())
                 .unwrap_or(true)
             {
-                errors.push(format!("sources.{}.parse.token[{}].expiration: pointer required when source is json_body_field/header_field", src_name, token.id));
+                errors.push(ValidationIssue::new(
+                    format!("sources.{}.parse.tokens[{}].expiration.pointer", src_name, token.id),
+                    "is required when source is json_body_field/header_field",
+                ));
+            } else if let (ExpirationSource::JsonBodyField, Some(pointer)) = (exp.source, &exp.pointer) {
+                if body_format == BodyFormat::Plain {
+                    errors.push(ValidationIssue::new(
+                        format!("sources.{}.parse.tokens[{}].expiration.source", src_name, token.id),
+                        "json_body_field is not valid for body_format=plain, the body is the token",
+                    ));
+                } else if body_format == BodyFormat::FormUrlencoded && pointer.starts_with('/') {
+                    errors.push(ValidationIssue::new(
+                        format!("sources.{}.parse.tokens[{}].expiration.pointer", src_name, token.id),
+                        format!("'{}' must be a form key for body_format=form_urlencoded", pointer),
+                    ));
+                } else if body_format == BodyFormat::Xml && !is_valid_xml_path(pointer) {
+                    errors.push(ValidationIssue::new(
+                        format!("sources.{}.parse.tokens[{}].expiration.pointer", src_name, token.id),
+                        format!("'{}' is not a valid XML element path like '/Root/Element' or 'Root.Element'", pointer),
+                    ));
+                } else if body_format == BodyFormat::Json && !is_valid_json_pointer(pointer) {
+                    errors.push(ValidationIssue::new(
+                        format!("sources.{}.parse.tokens[{}].expiration.pointer", src_name, token.id),
+                        format!("'{}' is not a valid JSON pointer", pointer),
+                    ));
+                }
             }
-            // if linked_token_id present, ensure it's not empty
+            // if linked_token_id present, ensure it's not empty, reference checked with all source tokens
             if let Some(ref linked) = exp.linked_token_id {
                 if linked.trim().is_empty() {
-                    errors.push(format!("sources.{}.parse.token[{}].expiration: linked_token_id if present must be non-empty", src_name, token.id));
+                    errors.push(ValidationIssue::new(
+                        format!("sources.{}.parse.tokens[{}].expiration.linked_token_id", src_name, token.id),
+                        "if present must be non-empty",
+                    ));
                 }
-            } else {
-                // if expiration is separate field (not in same token) it's acceptable, but ensure the config supports it.
             }
         }
         ExpirationSource::Manual => {
+            if matches!(exp.format, ExpirationSourceFormat::Rfc3339) {
+                errors.push(ValidationIssue::new(
+                    format!("sources.{}.parse.tokens[{}].expiration.format", src_name, token.id),
+                    "rfc3339 is not valid when source=manual",
+                ));
+            }
+            if exp.linked_token_id.is_some() {
+                errors.push(ValidationIssue::new(
+                    format!("sources.{}.parse.tokens[{}].expiration.linked_token_id", src_name, token.id),
+                    "must not be provided when source=manual",
+                ));
+            }
             if exp.manual_ttl_seconds.is_none() {
-                errors.push(format!("sources.{}.parse.token[{}].expiration: manual_ttl_seconds required when source=manual", src_name, token.id));
+                errors.push(ValidationIssue::new(
+                    format!("sources.{}.parse.tokens[{}].expiration.manual_ttl_seconds", src_name, token.id),
+                    "is required when source=manual",
+                ));
             } else if exp.manual_ttl_seconds.unwrap() == 0 {
-                errors.push(format!(
-                    "sources.{}.parse.token[{}].expiration: manual_ttl_seconds must be > 0",
-                    src_name, token.id
+                errors.push(ValidationIssue::new(
+                    format!("sources.{}.parse.tokens[{}].expiration.manual_ttl_seconds", src_name, token.id),
+                    "must be > 0",
                 ));
             }
         }
@@ -505,14 +1448,14 @@ fn validate_sink_basics(
     sink: &SinkConfig,
     sources: &HashMap<String, SourceConfig>,
     source_token_ids: &HashMap<String, HashSet<String>>,
-    errors: &mut Vec<String>,
+    errors: &mut Vec<ValidationIssue>,
 ) {
     // sink type handled by serde; basic checks:
     // input must exist
     if !sources.contains_key(&sink.source_id) {
-        errors.push(format!(
-            "sinks.{}: input '{}' does not reference any source",
-            sink_name, sink.source_id
+        errors.push(ValidationIssue::new(
+            format!("sinks.{}.source_id", sink_name),
+            format!("'{}' does not reference any source", sink.source_id),
         ));
         // cannot continue other sink-specific checks if input missing
         return;
@@ -520,35 +1463,335 @@ fn validate_sink_basics(
 
     // token must exist in referenced source
     let token_set = &source_token_ids[&sink.source_id];
-    if !token_set.contains(&sink.token_id) {
-        errors.push(format!(
-            "sinks.{}: token_id '{}' not found in source '{}'",
-            sink_name, sink.token_id, sink.source_id
+    if !is_known_token_id(token_set, &sink.token_id) {
+        errors.push(ValidationIssue::new(
+            format!("sinks.{}.token_id", sink_name),
+            format!("'{}' not found in source '{}'", sink.token_id, sink.source_id),
+        ));
+    }
+    if let Some(tokens) = &sink.tokens {
+        for token_id in tokens.iter().filter(|token_id| !is_known_token_id(token_set, token_id)) {
+            errors.push(ValidationIssue::new(
+                format!("sinks.{}.tokens", sink_name),
+                format!("entry '{}' not found in source '{}'", token_id, sink.source_id),
+            ));
+        }
+        match sink.sink_type {
+            SinkType::Http | SinkType::HttpPush => {}
+            SinkType::File if sink.template.is_some() => {}
+            SinkType::File => errors.push(ValidationIssue::new(
+                format!("sinks.{}.tokens", sink_name),
+                "requires template for sink type file",
+            )),
+            _ => errors.push(ValidationIssue::new(
+                format!("sinks.{}.tokens", sink_name),
+                "is only supported for sink types file, http and http_push",
+            )),
+        }
+    }
+
+    if let Some(auth) = &sink.auth {
+        if sink.sink_type != SinkType::Http || sink.is_http_push() {
+            errors.push(ValidationIssue::new(
+                format!("sinks.{}.auth", sink_name),
+                "is only supported for HTTP sink method GET",
+            ));
+        }
+        validate_sink_auth(sink_name, auth, errors);
+    }
+
+    if let Some(rate_limit) = &sink.rate_limit {
+        if sink.sink_type != SinkType::Http || sink.is_http_push() {
+            errors.push(ValidationIssue::new(
+                format!("sinks.{}.rate_limit", sink_name),
+                "is only supported for HTTP sink method GET",
+            ));
+        }
+        if !(rate_limit.requests_per_second.is_finite() && rate_limit.requests_per_second > 0.0) {
+            errors.push(ValidationIssue::new(
+                format!("sinks.{}.rate_limit.requests_per_second", sink_name),
+                "must be greater than 0",
+            ));
+        }
+        if rate_limit.burst == 0 {
+            errors.push(ValidationIssue::new(
+                format!("sinks.{}.rate_limit.burst", sink_name),
+                "must be greater than 0",
+            ));
+        }
+    }
+
+    // file template placeholders must resolve to known tokens or expiration helpers
+    if let Some(template) = &sink.template {
+        if !matches!(sink.sink_type, SinkType::File | SinkType::HttpPush) {
+            errors.push(ValidationIssue::new(
+                format!("sinks.{}.template", sink_name),
+                "is only supported for sink types file and http_push",
+            ));
+        } else {
+            validate_file_sink_template(sink_name, template, source_token_ids, errors);
+        }
+    }
+
+    // file content format rules
+    if let Some(format) = sink.format {
+        if sink.sink_type != SinkType::File {
+            errors.push(ValidationIssue::new(
+                format!("sinks.{}.format", sink_name),
+                "is only supported for sink type file",
+            ));
+        } else if format == FileSinkFormat::JsonEnvelope && sink.template.is_some() {
+            errors.push(ValidationIssue::new(
+                format!("sinks.{}.format", sink_name),
+                "json_envelope cannot be combined with template",
+            ));
+        }
+    }
+    if sink.type_hint.is_some() && sink.format != Some(FileSinkFormat::JsonEnvelope) {
+        errors.push(ValidationIssue::new(format!("sinks.{}.type_hint", sink_name), "requires format json_envelope"));
+    }
+
+    if let Some(stub_value) = &sink.stub_value {
+        if !matches!(sink.sink_type, SinkType::File | SinkType::Uds) {
+            errors.push(ValidationIssue::new(
+                format!("sinks.{}.stub_value", sink_name),
+                "is only supported for sink types file and uds",
+            ));
+        } else if stub_value.starts_with("eyJ") {
+            warn!("sinks.{}: stub_value looks like a JWT, consumers may accept it as a valid token", sink_name);
+        }
+    }
+
+    // file mode and ownership rules
+    if let Some(mode) = sink.mode {
+        if sink.sink_type != SinkType::File {
+            errors.push(ValidationIssue::new(
+                format!("sinks.{}.mode", sink_name),
+                "is only supported for sink type file",
+            ));
+        } else if mode > 0o777 {
+            errors.push(ValidationIssue::new(
+                format!("sinks.{}.mode", sink_name),
+                format!("'{:o}' must be in range 0o000-0o777", mode),
+            ));
+        }
+    }
+    // names are resolved when the file is written, the validating host may not have the users of the target one
+    for (field, value) in [("owner", &sink.owner), ("group", &sink.group)] {
+        let Some(value) = value else { continue };
+        if sink.sink_type != SinkType::File {
+            errors.push(ValidationIssue::new(
+                format!("sinks.{}.{}", sink_name, field),
+                "is only supported for sink type file",
+            ));
+        } else if !is_valid_id_or_name(value) {
+            errors.push(ValidationIssue::new(
+                format!("sinks.{}.{}", sink_name, field),
+                format!("'{}' must be a numeric id or a valid {} name", value, if field == "owner" { "user" } else { "group" }),
+            ));
+        }
+    }
+    #[cfg(not(unix))]
+    if sink.mode.is_some() || sink.owner.is_some() || sink.group.is_some() {
+        errors.push(ValidationIssue::new(
+            format!("sinks.{}", sink_name),
+            "mode, owner and group are only supported on unix",
+        ));
+    }
+
+    if sink.sink_type != SinkType::File {
+        if sink.atomic.is_some() {
+            errors.push(ValidationIssue::new(
+                format!("sinks.{}.atomic", sink_name),
+                "is only supported for sink type file",
+            ));
+        }
+        if sink.create_dirs.is_some() {
+            errors.push(ValidationIssue::new(
+                format!("sinks.{}.create_dirs", sink_name),
+                "is only supported for sink type file",
+            ));
+        }
+    } else if sink.atomic.unwrap_or(false)
+        && Path::new(&sink.path).file_name().is_some_and(|name| name.to_string_lossy().starts_with(".."))
+    {
+        errors.push(ValidationIssue::new(
+            format!("sinks.{}.path", sink_name),
+            format!("'{}' file name must not start with '..' when atomic is enabled", sink.path),
+        ));
+    }
+
+    if !matches!(sink.sink_type, SinkType::Http | SinkType::HttpPush) && (sink.method.is_some() || sink.target_url.is_some()) {
+        errors.push(ValidationIssue::new(
+            format!("sinks.{}", sink_name),
+            "method and target_url are only supported for sink types http and http_push",
         ));
     }
 
+    // exec rules
+    match (&sink.exec, sink.sink_type) {
+        (Some(exec), SinkType::Exec) => {
+            if exec.command.trim().is_empty() {
+                errors.push(ValidationIssue::new(format!("sinks.{}.exec.command", sink_name), "cannot be empty"));
+            } else if !Path::new(&exec.command).is_absolute() {
+                errors.push(ValidationIssue::new(
+                    format!("sinks.{}.exec.command", sink_name),
+                    format!("'{}' must be an absolute path", exec.command),
+                ));
+            }
+            if exec.env_name.is_empty()
+                || exec.env_name.starts_with(|c: char| c.is_ascii_digit())
+                || !exec.env_name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+            {
+                errors.push(ValidationIssue::new(
+                    format!("sinks.{}.exec.env_name", sink_name),
+                    format!("'{}' is not a valid env var name", exec.env_name),
+                ));
+            }
+        }
+        (None, SinkType::Exec) => {
+            errors.push(ValidationIssue::new(
+                format!("sinks.{}.exec", sink_name),
+                "block is required for sink type exec",
+            ));
+        }
+        (Some(_), _) => {
+            errors.push(ValidationIssue::new(
+                format!("sinks.{}.exec", sink_name),
+                "block is only supported for sink type exec",
+            ));
+        }
+        (None, _) => {}
+    }
+
+    // nats rules
+    match (&sink.nats, sink.sink_type) {
+        (Some(nats), SinkType::Nats) => {
+            if !(nats.nats_url.starts_with("nats://") || nats.nats_url.starts_with("tls://")) {
+                errors.push(ValidationIssue::new(
+                    format!("sinks.{}.nats.nats_url", sink_name),
+                    format!("'{}' must start with nats:// or tls://", nats.nats_url),
+                ));
+            }
+            if nats.subject.is_empty()
+                || nats.subject.chars().any(|c| c.is_whitespace() || c == '*' || c == '>')
+                || nats.subject.split('.').any(str::is_empty)
+            {
+                errors.push(ValidationIssue::new(
+                    format!("sinks.{}.nats.subject", sink_name),
+                    format!("'{}' is not a valid publish subject", nats.subject),
+                ));
+            }
+            if let Some(credentials_path) = &nats.credentials_path {
+                validate_generic_source_value(&format!("sinks.{}.nats.credentials_path", sink_name), credentials_path, errors);
+            }
+        }
+        (None, SinkType::Nats) => {
+            errors.push(ValidationIssue::new(
+                format!("sinks.{}.nats", sink_name),
+                "block is required for sink type nats",
+            ));
+        }
+        (Some(_), _) => {
+            errors.push(ValidationIssue::new(
+                format!("sinks.{}.nats", sink_name),
+                "block is only supported for sink type nats",
+            ));
+        }
+        (None, _) => {}
+    }
+
+    // redis rules
+    match (&sink.redis, sink.sink_type) {
+        (Some(redis), SinkType::Redis) => validate_redis_sink(sink_name, redis, errors),
+        (None, SinkType::Redis) => {
+            errors.push(ValidationIssue::new(
+                format!("sinks.{}.redis", sink_name),
+                "block is required for sink type redis",
+            ));
+        }
+        (Some(_), _) => {
+            errors.push(ValidationIssue::new(
+                format!("sinks.{}.redis", sink_name),
+                "block is only supported for sink type redis",
+            ));
+        }
+        (None, _) => {}
+    }
+
+    // kubernetes_secret rules
+    match (&sink.kubernetes_secret, sink.sink_type) {
+        (Some(secret), SinkType::KubernetesSecret) => validate_kubernetes_secret_sink(sink_name, secret, errors),
+        (None, SinkType::KubernetesSecret) => {
+            errors.push(ValidationIssue::new(
+                format!("sinks.{}.kubernetes_secret", sink_name),
+                "block is required for sink type kubernetes_secret",
+            ));
+        }
+        (Some(_), _) => {
+            errors.push(ValidationIssue::new(
+                format!("sinks.{}.kubernetes_secret", sink_name),
+                "block is only supported for sink type kubernetes_secret",
+            ));
+        }
+        (None, _) => {}
+    }
+
     // path rules
     match sink.sink_type {
         SinkType::File | SinkType::Uds => {
             if !Path::new(&sink.path).is_absolute() {
-                errors.push(format!(
-                    "sinks.{}: path '{}' must be absolute for sink type {:?}",
-                    sink_name, sink.path, sink.sink_type
+                errors.push(ValidationIssue::new(
+                    format!("sinks.{}.path", sink_name),
+                    format!("'{}' must be absolute for sink type {:?}", sink.path, sink.sink_type),
                 ));
             }
         }
+        SinkType::HttpPush if !sink.push_method().is_push() => {
+            errors.push(ValidationIssue::new(
+                format!("sinks.{}.method", sink_name),
+                "must be POST or PUT for sink type http_push",
+            ));
+        }
+        SinkType::Http | SinkType::HttpPush if sink.is_http_push() => match &sink.target_url {
+            None => errors.push(ValidationIssue::new(
+                format!("sinks.{}.target_url", sink_name),
+                format!("is required for HTTP sink method {:?}", sink.push_method()),
+            )),
+            Some(target_url) => {
+                if !(target_url.starts_with("http://") || target_url.starts_with("https://")) {
+                    errors.push(ValidationIssue::new(
+                        format!("sinks.{}.target_url", sink_name),
+                        format!("'{}' must be an http(s) URL", target_url),
+                    ));
+                }
+                if sink.response.is_none() && sink.template.is_none() {
+                    errors.push(ValidationIssue::new(
+                        format!("sinks.{}.response", sink_name),
+                        "block or template is required for push HTTP sink",
+                    ));
+                }
+            }
+        },
         SinkType::Http => {
+            if sink.target_url.is_some() {
+                errors.push(ValidationIssue::new(
+                    format!("sinks.{}.target_url", sink_name),
+                    "is only valid for HTTP sink method POST or PUT",
+                ));
+            }
             if !sink.path.starts_with('/') {
-                errors.push(format!(
-                    "sinks.{}: path '{}' must start with '/' for HTTP sink",
-                    sink_name, sink.path
+                errors.push(ValidationIssue::new(
+                    format!("sinks.{}.path", sink_name),
+                    format!("'{}' must start with '/' for HTTP sink", sink.path),
                 ));
             }
         }
+        SinkType::Exec | SinkType::HttpPush | SinkType::Nats | SinkType::Redis | SinkType::KubernetesSecret => {}
     }
 
-    // if http sink, validate response block if present
-    if let SinkType::Http = sink.sink_type {
+    // if http or nats sink, validate response block if present
+    if let SinkType::Http | SinkType::HttpPush | SinkType::Nats = sink.sink_type {
         if let Some(resp) = &sink.response {
             validate_http_response_block(
                 sink_name,
@@ -559,16 +1802,16 @@ fn validate_sink_basics(
             );
         }
     }
-    let sink_token_id = &sink.token_id;
+    let sink_token_ids = sink.token_ids();
     if let Some(response_block) = &sink.response {
         if let Some(res_body) = &response_block.body {
             for (_field_type, response_field) in res_body {
                 match response_field {
                     ResponseField::Token { id } => {
-                        validate_sink_body_token_id(sink_name, &sink_token_id, id.as_str(), errors)
+                        validate_sink_body_token_id(sink_name, &sink_token_ids, id.as_str(), errors)
                     }
                     ResponseField::Expiration { format: _, id } => {
-                        validate_sink_body_token_id(sink_name, &sink_token_id, id.as_str(), errors)
+                        validate_sink_body_token_id(sink_name, &sink_token_ids, id.as_str(), errors)
                     }
                     ResponseField::String { value: _ } => {}
                 }
@@ -577,16 +1820,43 @@ fn validate_sink_basics(
     }
 }
 
+fn validate_sink_auth(sink_name: &str, auth: &SinkAuthConfig, errors: &mut Vec<ValidationIssue>) {
+    if let SinkAuthConfig::MtlsHeader { header, .. } = auth {
+        if HeaderName::from_bytes(header.as_bytes()).is_err() {
+            errors.push(ValidationIssue::new(
+                format!("sinks.{}.auth", sink_name),
+                format!("invalid header name '{}'", header),
+            ));
+        }
+    }
+    match auth.secret() {
+        SinkAuthSecret::Literal { value } if value.trim().is_empty() => {
+            errors.push(ValidationIssue::new(format!("sinks.{}.auth", sink_name), "secret cannot be empty"));
+        }
+        SinkAuthSecret::FromEnv { from_env } if from_env.trim().is_empty() => {
+            errors.push(ValidationIssue::new(format!("sinks.{}.auth", sink_name), "env name cannot be empty"));
+        }
+        secret => {
+            if secret.resolve().is_none_or(|value| value.trim().is_empty()) {
+                errors.push(ValidationIssue::new(
+                    format!("sinks.{}.auth", sink_name),
+                    "secret env var is not set or empty",
+                ));
+            }
+        }
+    }
+}
+
 fn validate_sink_body_token_id(
     sink_name: &str,
-    sink_token_id: &str,
+    sink_token_ids: &[&str],
     body_token_id: &str,
-    errors: &mut Vec<String>,
+    errors: &mut Vec<ValidationIssue>,
 ) {
-    if body_token_id != sink_token_id {
-        errors.push(format!(
-            "sinks.{}.response.content_type must be the same as response body {} token id",
-            sink_name, body_token_id
+    if !sink_token_ids.contains(&body_token_id) {
+        errors.push(ValidationIssue::new(
+            format!("sinks.{}.response.body", sink_name),
+            format!("token id '{}' must be one of the sink tokens {:?}", body_token_id, sink_token_ids),
         ));
     }
 }
@@ -597,14 +1867,11 @@ fn validate_http_response_block(
     resp: &HttpResponseBlock,
     input_source: &str,
     source_token_ids: &HashMap<String, HashSet<String>>,
-    errors: &mut Vec<String>,
+    errors: &mut Vec<ValidationIssue>,
 ) {
     // content_type basic check
     if resp.content_type.trim().is_empty() {
-        errors.push(format!(
-            "sinks.{}.response.content_type must not be empty",
-            sink_name
-        ));
+        errors.push(ValidationIssue::new(format!("sinks.{}.response.content_type", sink_name), "must not be empty"));
     }
 
     if let Some(headers) = &resp.headers {
@@ -643,74 +1910,201 @@ fn validate_response_field(
     field: &ResponseField,
     input_source: &str,
     source_token_ids: &HashMap<String, HashSet<String>>,
-    errors: &mut Vec<String>,
+    errors: &mut Vec<ValidationIssue>,
 ) {
     match field {
         ResponseField::Token { id } => {
             if !source_token_ids
                 .get(input_source)
-                .map_or(false, |s| s.contains(id))
+                .map_or(false, |s| is_known_token_id(s, id))
             {
-                errors.push(format!(
-                    "sinks.{}.response.{}.{}: Token id '{}' not found in source '{}'",
-                    sink_name, section, field_name, id, input_source
+                errors.push(ValidationIssue::new(
+                    format!("sinks.{}.response.{}.{}", sink_name, section, field_name),
+                    format!("Token id '{}' not found in source '{}'", id, input_source),
                 ));
             }
         }
         ResponseField::Expiration { id, format: _ } => {
             if !source_token_ids
                 .get(input_source)
-                .map_or(false, |s| s.contains(id))
+                .map_or(false, |s| is_known_token_id(s, id))
             {
-                errors.push(format!(
-                    "sinks.{}.response.{}.{}: Expiration id '{}' not found in source '{}'",
-                    sink_name, section, field_name, id, input_source
+                errors.push(ValidationIssue::new(
+                    format!("sinks.{}.response.{}.{}", sink_name, section, field_name),
+                    format!("Expiration id '{}' not found in source '{}'", id, input_source),
                 ));
             }
             // format validated by serde enum
         }
         ResponseField::String { value } => {
             if value.trim().is_empty() {
-                errors.push(format!(
-                    "sinks.{}.response.{}.{}: literal string value is empty",
-                    sink_name, section, field_name
+                errors.push(ValidationIssue::new(
+                    format!("sinks.{}.response.{}.{}", sink_name, section, field_name),
+                    "literal string value is empty",
                 ));
             }
         }
     }
 }
 
+/// `Ref` values and template placeholders of request values must point at a token id of an existing source,
+/// claim placeholders must point at a claim listed in the token `extract_claims`
+fn validate_source_references(
+    src_name: &str,
+    src_cfg: &SourceConfig,
+    sources: &HashMap<String, SourceConfig>,
+    source_token_ids: &HashMap<String, HashSet<String>>,
+    errors: &mut Vec<ValidationIssue>,
+) {
+    for (field, value) in src_cfg.request.generic_values() {
+        let path = format!("sources.{}.request.{}", src_name, field);
+        validate_value_references(&path, value, sources, source_token_ids, errors);
+    }
+    for (field, value) in src_cfg.exec.iter().flat_map(ExecSourceConfig::generic_values) {
+        let path = format!("sources.{}.exec.{}", src_name, field);
+        validate_value_references(&path, value, sources, source_token_ids, errors);
+    }
+}
+
+/// Same checks for values resolved by sinks, e.g. `redis.redis_url` rendered from a source token
+fn validate_sink_references(
+    sink_name: &str,
+    sink_cfg: &SinkConfig,
+    sources: &HashMap<String, SourceConfig>,
+    source_token_ids: &HashMap<String, HashSet<String>>,
+    errors: &mut Vec<ValidationIssue>,
+) {
+    for (field, value) in sink_cfg.generic_values() {
+        let path = format!("sinks.{}.{}", sink_name, field);
+        validate_value_references(&path, value, sources, source_token_ids, errors);
+    }
+}
+
+fn validate_value_references(
+    path: &str,
+    value: &GenericSourceValue,
+    sources: &HashMap<String, SourceConfig>,
+    source_token_ids: &HashMap<String, HashSet<String>>,
+    errors: &mut Vec<ValidationIssue>,
+) {
+    match value {
+        GenericSourceValue::Ref { source, id, .. } if !source.trim().is_empty() && !id.trim().is_empty() => {
+            validate_token_reference(path, "ref", source, id, source_token_ids, errors);
+        }
+        GenericSourceValue::Template { template, .. } => {
+            validate_template_placeholders(path, template, sources, source_token_ids, errors);
+        }
+        GenericSourceValue::SignedJwt { claims, .. } => {
+            for (claim, template) in claims {
+                let path = format!("{}.claims.{}", path, claim);
+                validate_template_placeholders(&path, template, sources, source_token_ids, errors);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn validate_template_placeholders(
+    path: &str,
+    template: &str,
+    sources: &HashMap<String, SourceConfig>,
+    source_token_ids: &HashMap<String, HashSet<String>>,
+    errors: &mut Vec<ValidationIssue>,
+) {
+    let placeholder = Regex::new(r"\{\{([^{}]*)\}\}").unwrap();
+    let source_placeholder = Regex::new(&format!("^{}$", SOURCE_TEMPLATE_PLACEHOLDER)).unwrap();
+    for caps in placeholder.captures_iter(template) {
+        let parts: Vec<&str> = caps[1].split('.').collect();
+        if !source_placeholder.is_match(&caps[0]) {
+            errors.push(ValidationIssue::new(
+                path,
+                format!("invalid template placeholder '{}', expected '{{{{source.token_id}}}}' or '{{{{source.token_id.claim}}}}'", &caps[0]),
+            ));
+            continue;
+        }
+        let what = format!("template placeholder '{}'", &caps[0]);
+        let (source, id) = (parts[0], parts[1]);
+        validate_token_reference(path, &what, source, id, source_token_ids, errors);
+        let Some(claim) = parts.get(2) else { continue };
+        let token = sources
+            .get(source)
+            .and_then(|src| src.parse.tokens.iter().find(|token| token.id == id));
+        if token.is_some_and(|token| !token.extract_claims.iter().flatten().any(|c| c == claim)) {
+            errors.push(ValidationIssue::new(
+                path,
+                format!("{} references claim '{}' not listed in extract_claims of '{}.{}'", what, claim, source, id),
+            ));
+        }
+    }
+}
+
+/// Token id declared in a source or generated by an `iterate` token field, e.g. `batch.svc-a` for field `batch`;
+/// generated ids can not be verified before the first fetch, so they are accepted with a warning
+fn is_known_token_id(token_ids: &HashSet<String>, id: &str) -> bool {
+    if token_ids.contains(id) {
+        return true;
+    }
+    let field_id = id
+        .match_indices('.')
+        .map(|(index, _)| &id[..index])
+        .find(|field_id| token_ids.contains(&format!("{}.{}", field_id, ITERATE_ID_WILDCARD)));
+    if let Some(field_id) = field_id {
+        warn!("token id '{}' is generated by iterate token field '{}', it is not verified until the source is fetched", id, field_id);
+    }
+    field_id.is_some()
+}
+
+fn validate_token_reference(
+    path: &str,
+    what: &str,
+    source: &str,
+    id: &str,
+    source_token_ids: &HashMap<String, HashSet<String>>,
+    errors: &mut Vec<ValidationIssue>,
+) {
+    match source_token_ids.get(source) {
+        None => errors.push(ValidationIssue::new(path, format!("{} references unknown source '{}'", what, source))),
+        Some(token_ids) if !is_known_token_id(token_ids, id) => errors.push(ValidationIssue::new(
+            path,
+            format!("{} references token id '{}' not found in source '{}'", what, id, source),
+        )),
+        Some(_) => {}
+    }
+}
+
 /// TEMPLATE VALIDATION (rudimentary)
 ///
-/// Validates placeholders like `{{source.parent.token_id}}` or `{{source.token_id}}`
-/// - Ensures referenced source exists
-/// - Ensures referenced token ID exists in that source
-///
-/// This is intentionally simple (not a full handlebars parser) but covers common cases.
-fn validate_template_placeholders(template: &str, errors: &mut Vec<String>, _context_source: &str) {
-    // regex to capture {{ ... }} tokens
-    let re = Regex::new(r"\{\{\s*([a-zA-Z0-9_\.:-]+)\s*\}\}").unwrap();
-
+fn validate_file_sink_template(
+    sink_name: &str,
+    template: &str,
+    source_token_ids: &HashMap<String, HashSet<String>>,
+    errors: &mut Vec<ValidationIssue>,
+) {
+    let re = Regex::new(FILE_TEMPLATE_PLACEHOLDER).unwrap();
     for caps in re.captures_iter(template) {
-        if let Some(tok) = caps.get(1) {
-            let content = tok.as_str();
-            // patterns supported:
-            // - source.token_id (e.g., metadata.metadata_token)
-            // - source.parent.token_id (e.g., metadata.body.metadata_token) — accept both; we'll parse tokens by id only
-            let parts: Vec<&str> = content.split('.').collect();
-            if parts.len() >= 2 {
-                let src = parts[0];
-                let token_id = parts.last().unwrap();
-                // we can't access sources map here; higher-level validation will ensure actual references are valid
-                if src.trim().is_empty() || token_id.trim().is_empty() {
-                    errors.push(format!(
-                        "template '{}' contains invalid placeholder '{{{{{}}}}}'",
-                        template, content
+        let content = &caps[1];
+        match (content.split_once(':'), content.split_once('.')) {
+            (Some(("expiration", format)), _) => {
+                if !matches!(format, "unix" | "rfc3339" | "seconds") {
+                    errors.push(ValidationIssue::new(
+                        format!("sinks.{}.template", sink_name),
+                        format!("'{{{{{}}}}}' format must be one of unix, rfc3339, seconds", content),
                     ));
                 }
-            } else {
-                errors.push(format!("template '{}' contains ambiguous placeholder '{{{{{}}}}}', expected 'source.token_id' or 'source.parent.token_id'", template, content));
             }
+            (None, Some((source, token_id))) => {
+                if !source_token_ids.get(source).is_some_and(|ids| is_known_token_id(ids, token_id)) {
+                    errors.push(ValidationIssue::new(
+                        format!("sinks.{}.template", sink_name),
+                        format!("placeholder '{{{{{}}}}}' does not reference a source token", content),
+                    ));
+                }
+            }
+            _ => errors.push(ValidationIssue::new(
+                format!("sinks.{}.template", sink_name),
+                format!("invalid placeholder '{{{{{}}}}}', expected 'source.token_id' or 'expiration:<format>'", content),
+            )),
         }
     }
 }
+
@@ -1,18 +1,108 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
 /// ================================
 /// Global service-wide settings
 /// ================================
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SettingsConfig {
     pub safety_margin_seconds: Option<u64>,
+    /// refresh when this percent of the token lifetime is left, exclusive range (0, 100)
+    pub safety_margin_percent: Option<f64>,
+    /// start fetching a new token this many seconds before `safety_margin_seconds` window,
+    /// current token remains live until replaced
+    pub prefetch_margin_seconds: Option<u64>,
     pub retry: Option<RetryConfig>,
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+    /// source request timeouts, no timeouts if not set
+    pub timeouts: Option<TimeoutConfig>,
+    /// source response body limit, 1 MiB by default, overridden by `request.max_response_bytes`
+    pub max_response_bytes: Option<usize>,
+    /// longest accepted token value, 64 KiB by default, overridden by token field `max_token_bytes`
+    pub max_token_bytes: Option<usize>,
+    /// max sources fetched at the same time within a dependency layer, unlimited if not set
+    pub max_concurrent_fetches: Option<usize>,
+    /// how long `parameter_name` values are reused before SSM is called again, default 300
+    pub ssm_cache_ttl_seconds: Option<u64>,
+    /// tolerated issuer clock drift in JWT `exp`, `nbf` and `iat` checks, default 30
+    pub clock_skew_seconds: Option<u64>,
+    /// how long GCP Secret Manager `secret` values are reused before they are accessed again, default 300
+    pub gcp_secret_cache_ttl_seconds: Option<u64>,
+    /// time for running tasks to finish after SIGINT/SIGTERM, default 10
+    pub shutdown_grace_period_seconds: Option<u64>,
+    pub cache: Option<CacheConfig>,
+    pub admin: Option<AdminConfig>,
+    pub readiness: Option<ReadinessConfig>,
+    pub reload: Option<ReloadConfig>,
+    /// `settings.tracing` is accepted as well
+    #[serde(alias = "tracing")]
+    pub otel: Option<OtelConfig>,
+    pub audit: Option<AuditConfig>,
     pub metrics: MetricsConfig,
     pub server: ServerConfig,
     pub logging: Option<LoggingConfig>
 }
 
-#[derive(Debug, Deserialize, Clone)]
+/// Admin API (`/admin/*`) settings
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AdminConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// if set, requests must send `Authorization: Bearer <bearer_token>`
+    #[serde(alias = "api_key")]
+    pub bearer_token: Option<String>,
+    /// separate listener for admin routes, main server is used if not set
+    pub host: Option<String>,
+    pub port: Option<String>,
+}
+
+/// Config hot reload settings, `SIGHUP` always triggers a reload
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ReloadConfig {
+    /// poll config file modification time with this interval, file is not watched if not set
+    pub watch_interval_seconds: Option<u64>,
+}
+
+/// Readiness probe settings
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ReadinessConfig {
+    /// sources that must hold at least one unexpired token before the agent reports ready,
+    /// all configured sources if `readiness` is not set
+    #[serde(default)]
+    pub required_sources: Vec<String>,
+}
+
+/// OpenTelemetry trace export settings
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct OtelConfig {
+    /// export is off when `false` even if endpoint is set, default true
+    pub enabled: Option<bool>,
+    /// OTLP/HTTP collector endpoint, e.g. `http://otel-collector:4318`; export is disabled when not set
+    pub endpoint: Option<String>,
+    /// `service.name` resource attribute, `token-agent` by default
+    pub service_name: Option<String>,
+    /// share of refresh cycle traces exported, `0.0` to `1.0`, default 1.0
+    pub sample_ratio: Option<f64>,
+}
+
+/// Token lifecycle audit log, JSON lines separate from the main log
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AuditConfig {
+    /// audit log file, `stderr` writes to standard error
+    pub path: String,
+    /// file is renamed to `<path>.1` before it grows over this size, 10 MiB by default
+    pub max_file_bytes: Option<u64>,
+    /// rotated files kept, `<path>.1` ... `<path>.<max_files>`, 5 by default
+    pub max_files: Option<usize>,
+}
+
+/// Token cache settings
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct CacheConfig {
+    /// directory of persistent (L2) token cache, tokens survive restarts when set
+    pub persist_path: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct RetryConfig {
     pub attempts: Option<u32>,
     /// will be mutiply by 2 on every attempt until max_delay_ms 
@@ -21,9 +111,47 @@ pub struct RetryConfig {
     /// invariant: >= base_delay_ms. 
     /// used for token expiration time
     pub max_delay_ms: Option<u64>,
+    /// randomization of backoff delay: none (default), full, equal
+    pub jitter: Option<JitterMode>,
+    /// retry only responses with these HTTP statuses (e.g. [429, 500, 502, 503, 504]),
+    /// other statuses fail immediately; network errors are always retried.
+    /// all statuses are retried if not set
+    pub retry_on_status: Option<Vec<u16>>,
+    /// wait for the full `Retry-After` delay even if it exceeds `max_delay_ms`
+    pub respect_retry_after: Option<bool>,
+}
+
+/// Backoff jitter strategy
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum JitterMode {
+    /// exact exponential delay
+    #[default]
+    None,
+    /// random delay in [0, delay]
+    Full,
+    /// delay / 2 + random delay in [0, delay / 2]
+    Equal,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TimeoutConfig {
+    /// TCP/TLS connect timeout of source requests
+    pub connect_seconds: Option<u64>,
+    /// time limit of one fetch attempt, every retry attempt gets a fresh timeout
+    pub read_seconds: Option<u64>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct CircuitBreakerConfig {
+    /// consecutive failed fetch cycles (after all retries) before circuit opens
+    pub failure_threshold: Option<u32>,
+    /// how long fetches short-circuit before a single half-open probe
+    #[serde(alias = "open_seconds")]
+    pub open_duration_seconds: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct MetricsConfig {
     #[serde(default = "default_metrics_path")]
     pub path: String,
@@ -31,17 +159,23 @@ pub struct MetricsConfig {
     pub is_enabled: bool,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ServerConfig {
     // pub path: Option<String>,
     pub host: String,
-    pub port: String
+    pub port: String,
+    /// liveness probe path, always `200 {"status":"ok"}` while the server runs
+    #[serde(default = "default_health_path")]
+    pub health_path: String,
+    /// readiness probe path, `200` once `settings.readiness.required_sources` hold unexpired tokens
+    #[serde(default = "default_ready_path")]
+    pub ready_path: String,
 }
 
 /// ================================
 /// Logging
 /// ================================
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct LoggingConfig {
     pub level: String, // allowed: trace, debug, info, warn, error
     pub format: LogFormat,
@@ -53,7 +187,7 @@ impl LoggingConfig {
     }
 }
 
-#[derive(Clone, Debug, Deserialize, PartialEq, Eq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum LogFormat {
     Json,
@@ -76,3 +210,11 @@ impl LogFormat {
 fn default_metrics_path() -> String {
     "/metics".to_string()
 }
+
+fn default_health_path() -> String {
+    "/health".to_string()
+}
+
+fn default_ready_path() -> String {
+    "/ready".to_string()
+}
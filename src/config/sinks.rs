@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use crate::cache::token_event::TokenEvent;
+use crate::config::sources::GenericSourceValue;
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "snake_case")]
 pub enum SinkType {
@@ -8,18 +11,103 @@ pub enum SinkType {
     /// not implemented yet
     Uds, 
     Http,
+    /// runs a command with the token in an env var
+    Exec,
+    /// pushes the token to a remote webhook on every refresh
+    HttpPush,
+    /// publishes the token to a NATS subject on every refresh
+    Nats,
+    /// stores the token in a Redis key on every refresh, the key is deleted when the token is removed
+    Redis,
+    /// patches the token into a Kubernetes Secret key on every refresh, the key is removed when the token is removed
+    KubernetesSecret,
+}
+
+/// HTTP sink method: pull (`GET`) or push (`POST`, `PUT`), `http_push` sinks default to `POST`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum HttpSinkMethod {
+    #[default]
+    Get,
+    Post,
+    Put,
+}
+
+impl HttpSinkMethod {
+    /// `POST` and `PUT` sinks push tokens to `target_url`
+    pub fn is_push(&self) -> bool {
+        !matches!(self, HttpSinkMethod::Get)
+    }
 }
 
-// used for passing event from sources to active sinks
-#[derive(Clone, Debug)]
-pub struct SinkMessage(pub String);
+impl SinkConfig {
+    /// HTTP sink pushing tokens to `target_url` instead of serving them
+    pub fn is_http_push(&self) -> bool {
+        match self.sink_type {
+            SinkType::HttpPush => true,
+            SinkType::Http => self.method.unwrap_or_default().is_push(),
+            _ => false,
+        }
+    }
+
+    /// `sink_type` label of sink metrics
+    pub fn metric_sink_type(&self) -> &'static str {
+        match self.sink_type {
+            _ if self.is_http_push() => "http_push",
+            SinkType::File => "file",
+            SinkType::Uds => "uds",
+            SinkType::Http => "http",
+            SinkType::Exec => "exec",
+            SinkType::HttpPush => "http_push",
+            SinkType::Nats => "nats",
+            SinkType::Redis => "redis",
+            SinkType::KubernetesSecret => "kubernetes_secret",
+        }
+    }
+
+    /// `token_id` followed by additional `tokens`, without duplicates
+    pub fn token_ids(&self) -> Vec<&str> {
+        let mut token_ids = vec![self.token_id.as_str()];
+        for token_id in self.tokens.iter().flatten() {
+            if !token_ids.contains(&token_id.as_str()) {
+                token_ids.push(token_id);
+            }
+        }
+        token_ids
+    }
+
+    /// Sink renders this token, so the event has to be propagated by it
+    pub fn is_subscribed(&self, event: &TokenEvent) -> bool {
+        self.source_id == event.source_id && self.token_ids().contains(&event.token_id.as_str())
+    }
+
+    /// All values resolved at propagation time with their field path relative to the sink, e.g. `redis.redis_url`
+    pub fn generic_values(&self) -> Vec<(String, &GenericSourceValue)> {
+        let mut values = Vec::new();
+        if let Some(redis) = &self.redis {
+            values.push(("redis.redis_url".to_string(), &redis.redis_url));
+        }
+        if let Some(credentials_path) = self.nats.as_ref().and_then(|nats| nats.credentials_path.as_ref()) {
+            values.push(("nats.credentials_path".to_string(), credentials_path));
+        }
+        values
+    }
+
+    /// Method used to push tokens to `target_url`
+    pub fn push_method(&self) -> HttpSinkMethod {
+        match (self.sink_type, self.method) {
+            (SinkType::HttpPush, None) => HttpSinkMethod::Post,
+            (_, method) => method.unwrap_or_default(),
+        }
+    }
+}
 
 /// The top-level sink configuration block.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SinkConfig {
     #[serde(default = "default_token_id")]
     pub sink_id: String,
-    /// Type of sink: "file", "uds", or "http".
+    /// Type of sink: "file", "uds", "http", "exec", "http_push", "nats", "redis" or "kubernetes_secret".
     #[serde(rename = "type")]
     pub sink_type: SinkType,
 
@@ -30,15 +118,232 @@ pub struct SinkConfig {
     /// Path or endpoint where the token will be propagated.
     /// - For `file`/`uds`: absolute filesystem path.
     /// - For `http`: relative URL path (e.g., `/tokens/client`).
+    /// - Not used for `exec`, `nats`, `redis` and `kubernetes_secret`.
+    #[serde(default)]
     pub path: String,
 
     /// The ID of the token (defined in source.parse.tokens).
     pub token_id: String,
 
-    /// Optional HTTP response definition (for type = "http").
+    /// Additional token IDs of the same source rendered by this sink (for type = "file", "http", "http_push"),
+    /// addressed by `ResponseField::Token { id }` and template placeholders.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tokens: Option<Vec<String>>,
+
+    /// Optional HTTP response definition (for type = "http"), headers and body of pushed requests
+    /// (for type = "http_push"), headers and body of published messages (for type = "nats").
     #[serde(skip_serializing_if = "Option::is_none")]
     pub response: Option<HttpResponseBlock>,
-    
+
+    /// HTTP sink method (for type = "http"): `GET` serves the response on `path` (default),
+    /// `POST`/`PUT` push the rendered response to `target_url` whenever the token changes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub method: Option<HttpSinkMethod>,
+
+    /// Consumer URL for push HTTP sinks (method = POST/PUT) and `http_push` sinks.
+    #[serde(alias = "url", skip_serializing_if = "Option::is_none")]
+    pub target_url: Option<String>,
+
+    /// File permissions (for type = "file"), octal string `"0600"` (default) or integer `0o600`.
+    #[serde(default, deserialize_with = "deserialize_file_mode", skip_serializing_if = "Option::is_none")]
+    pub mode: Option<u32>,
+
+    /// File owner (for type = "file"), user name or uid, applied when running as root.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+
+    /// File group (for type = "file"), group name or gid, applied when running as root.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+
+    /// File content template (for type = "file"): `{{source.token_id}}` placeholders and
+    /// `{{expiration:unix}}`/`{{expiration:rfc3339}}`/`{{expiration:seconds}}` of the sink token.
+    /// Raw token value is written when not set.
+    /// For type = "http_push" the rendered template is sent as request body instead of `response.body`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+
+    /// File content format (for type = "file"), raw token value if not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<FileSinkFormat>,
+
+    /// `type_hint` of JSON envelope (for type = "file" with format = "json_envelope"), e.g. `Bearer`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub type_hint: Option<String>,
+
+    /// Content written when the token is removed (for type = "file" and "uds"), `""` truncates the file.
+    /// File sinks write an empty file (`{"token":""}` for JSON envelope) and UDS sinks serve nothing if not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stub_value: Option<String>,
+
+    /// Kubernetes style `..data` symlink swap (for type = "file"), `path` becomes a symlink.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub atomic: Option<bool>,
+
+    /// Create missing parent directories of `path` (for type = "file").
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub create_dirs: Option<bool>,
+
+    /// Child process definition (for type = "exec").
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exec: Option<ExecSinkConfig>,
+
+    /// Request authentication (for type = "http" with method GET), unauthorized requests get 401.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auth: Option<SinkAuthConfig>,
+
+    /// NATS connection and subject (for type = "nats").
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nats: Option<NatsSinkConfig>,
+
+    /// Redis connection and key (for type = "redis").
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redis: Option<RedisSinkConfig>,
+
+    /// Secret and its data key (for type = "kubernetes_secret").
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kubernetes_secret: Option<KubernetesSecretSinkConfig>,
+
+    /// Per client IP request limit (for type = "http" with method GET), limited requests get 429.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimitConfig>,
+}
+
+/// Token bucket of HTTP sink requests per client IP.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RateLimitConfig {
+    /// Sustained rate, fractions allowed, e.g. `0.5` is one request every 2 seconds.
+    pub requests_per_second: f64,
+    /// Requests allowed at once before the rate applies.
+    pub burst: u32,
+}
+
+/// NATS subject the token is published to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NatsSinkConfig {
+    /// Server URL, e.g. `nats://localhost:4222`.
+    pub nats_url: String,
+    pub subject: String,
+    /// Path of `.creds` file (JWT + NKey seed), plain connection if not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub credentials_path: Option<GenericSourceValue>,
+}
+
+/// Redis key the token is stored in.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedisSinkConfig {
+    /// Server URL, e.g. `redis://localhost:6379/0` or `rediss://` for TLS, may carry credentials.
+    pub redis_url: GenericSourceValue,
+    /// Key with `{source_id}` and `{token_id}` placeholders, e.g. `tokens:{source_id}:{token_id}`.
+    pub key_template: String,
+    /// Key expiration, the key never expires if not set.
+    #[serde(default, with = "serde_yaml::with::singleton_map", skip_serializing_if = "Option::is_none")]
+    pub ttl_mode: Option<RedisTtlMode>,
+    #[serde(default)]
+    pub value_format: RedisValueFormat,
+}
+
+/// Kubernetes Secret data key the token is written to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KubernetesSecretSinkConfig {
+    pub namespace: String,
+    pub secret_name: String,
+    /// Data key, the token is base64 encoded as the Secret format requires.
+    pub key: String,
+    /// Path of kubeconfig, its current context is used; in-cluster config from `KUBERNETES_SERVICE_HOST`
+    /// and the pod service account if not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kubeconfig: Option<String>,
+}
+
+/// Expiration of Redis sink key.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RedisTtlMode {
+    /// Key expires together with the token.
+    MatchTokenExp,
+    /// Key expires after fixed number of seconds, e.g. `ttl_mode: { fixed_seconds: 300 }`.
+    FixedSeconds(u64),
+}
+
+/// Value stored in Redis sink key.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RedisValueFormat {
+    /// Raw token value.
+    #[default]
+    RawString,
+    /// `{"token": ..., "exp_unix_ts": ..., "source_id": ..., "token_id": ...}`.
+    JsonEnvelope,
+}
+
+/// Content of file sink.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FileSinkFormat {
+    /// Raw token value.
+    #[default]
+    Raw,
+    /// `{"token": ..., "exp_unix_ts": ..., "fetched_at_unix_ts": ..., "type_hint": ...}`.
+    JsonEnvelope,
+}
+
+/// Authentication of HTTP sink requests.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SinkAuthConfig {
+    /// Requests must send `Authorization: Bearer <secret>`.
+    Bearer {
+        #[serde(flatten)]
+        secret: SinkAuthSecret,
+    },
+    /// Requests must carry `header` set by a fronting proxy after client certificate verification,
+    /// e.g. `X-Client-Verify: SUCCESS`.
+    MtlsHeader {
+        header: String,
+        #[serde(flatten)]
+        secret: SinkAuthSecret,
+    },
+}
+
+impl SinkAuthConfig {
+    pub fn secret(&self) -> &SinkAuthSecret {
+        match self {
+            SinkAuthConfig::Bearer { secret } | SinkAuthConfig::MtlsHeader { secret, .. } => secret,
+        }
+    }
+}
+
+/// Expected secret of HTTP sink auth: literal `value` or `from_env` variable name.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SinkAuthSecret {
+    Literal { value: String },
+    FromEnv { from_env: String },
+}
+
+impl SinkAuthSecret {
+    /// Expected secret, `None` when the env variable is not set
+    pub fn resolve(&self) -> Option<String> {
+        match self {
+            SinkAuthSecret::Literal { value } => Some(value.clone()),
+            SinkAuthSecret::FromEnv { from_env } => std::env::var(from_env).ok(),
+        }
+    }
+}
+
+/// Command started with the token exported as env var.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecSinkConfig {
+    /// Absolute path of the executable.
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Env var name the token is exported as, e.g. `API_TOKEN`.
+    pub env_name: String,
+    /// Kill and start the command again when the token changes.
+    #[serde(default)]
+    pub restart_on_refresh: bool,
 }
 
 /// HTTP response structure for HTTP sinks.
@@ -109,7 +414,7 @@ pub enum ExpirationSinkFormat {
     Unix,
 }
 
-fn default_content_type() -> String {
+pub(crate) fn default_content_type() -> String {
     "application/json".to_string()
 }
 
@@ -117,4 +422,27 @@ fn default_token_id() -> String {
     "default_token_id".to_string()
 }
 
+/// File mode as integer or octal string: `"0600"`, `"0o600"`, `"600"`
+fn deserialize_file_mode<'de, D>(deserializer: D) -> Result<Option<u32>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum FileMode {
+        Int(u32),
+        Str(String),
+    }
+    match Option::<FileMode>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(FileMode::Int(mode)) => Ok(Some(mode)),
+        Some(FileMode::Str(mode)) => parse_file_mode(&mode).map(Some).map_err(serde::de::Error::custom),
+    }
+}
+
+pub fn parse_file_mode(mode: &str) -> Result<u32, String> {
+    let digits = mode.trim().trim_start_matches("0o");
+    u32::from_str_radix(digits, 8).map_err(|_| format!("mode '{}' is not an octal number", mode))
+}
+
 
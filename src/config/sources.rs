@@ -1,46 +1,256 @@
 use http::Method;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use crate::config::{settings::SettingsConfig, sinks::SinkConfig};
+use crate::config::{settings::{CircuitBreakerConfig, RetryConfig, SettingsConfig, TimeoutConfig}, sinks::SinkConfig};
 
 
 /// ================================
 /// Full service configuration
 /// ================================
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct ServiceConfig {
     pub settings: SettingsConfig,
     pub sources: HashMap<String, SourceConfig>,
     pub sinks: HashMap<String, SinkConfig>,
 }
 
+impl ServiceConfig {
+    /// Sinks of enabled sources, sinks of disabled sources are not started
+    pub fn enabled_sinks(&self) -> HashMap<String, SinkConfig> {
+        self.sinks
+            .iter()
+            .filter(|(_, sink_config)| self.sources.get(&sink_config.source_id).is_none_or(SourceConfig::is_enabled))
+            .map(|(sink_id, sink_config)| (sink_id.to_owned(), sink_config.to_owned()))
+            .collect()
+    }
+}
+
 /// ================================
 /// Sources
 /// ================================
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct SourceConfig {
     #[serde(rename = "type")]
     pub source_type: SourceTypes, // e.g., http, oauth2, metadata
-    pub request: RequestConfig,
-    pub parse: ParseConfig,
+    #[serde(default)]
+    pub request: RequestConfig, // derived from `vault`, `file`, `exec` and `aws_sts_web_identity` blocks, subject token request for type=gcp_workload_identity
+    #[serde(default)]
+    pub parse: ParseConfig,     // derived from `vault`, `file`, `gcp_workload_identity` and `aws_sts_web_identity` blocks
     pub inputs: Option<Vec<String>>,
+    /// `false` keeps the source in config without fetching it, its sinks are skipped; default true
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
     pub safety_margin_seconds: Option<u64>,
+    /// refresh when this percent of the token lifetime is left, the larger margin wins when
+    /// `safety_margin_seconds` is set too; falls back to `settings.safety_margin_percent`
+    pub safety_margin_percent: Option<f64>,
+    pub prefetch_margin_seconds: Option<u64>,
+    /// after a failed fetch wait at least this long before the next attempt, doubled on every
+    /// consecutive failure up to 5 minutes and reset on success; default 15, 0 disables
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_refresh_interval_seconds: Option<u64>,
+    /// keep a replaced or invalidated token for this many seconds, HTTP sinks serve it while the new one is missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotation_overlap_seconds: Option<u64>,
+    /// keep an expired token this many seconds longer while the last fetch of the source failed,
+    /// sinks serve it marked as stale; default unset, expired tokens are removed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stale_token_ttl_seconds: Option<u64>,
+    /// fetch the source again whenever tokens of any of its `inputs` change, default false
+    pub refresh_on_input_change: Option<bool>,
+    pub tls: Option<TlsConfig>,
+    /// overrides `settings.retry` for this source, unset fields fall back to settings
+    pub retry: Option<RetryConfig>,
+    /// overrides `settings.circuit_breaker` for this source, unset fields fall back to settings
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+    /// overrides `settings.timeouts` for this source, unset fields fall back to settings
+    pub timeouts: Option<TimeoutConfig>,
+    /// type=vault only: AppRole login settings
+    pub vault: Option<VaultConfig>,
+    /// type=kube_service_account only: projected service account token file
+    pub kube_service_account: Option<KubeServiceAccountConfig>,
+    /// type=gcp_workload_identity only: STS exchange of the subject token fetched with `request`
+    pub gcp_workload_identity: Option<GcpWorkloadIdentityConfig>,
+    /// type=aws_sts_web_identity only: `AssumeRoleWithWebIdentity` call settings
+    pub aws_sts_web_identity: Option<AwsStsWebIdentityConfig>,
+    /// type=file only: token file replacing `request`
+    pub file: Option<FileSourceConfig>,
+    /// type=exec only: credential helper command replacing `request`, its stdout is parsed as response body
+    pub exec: Option<ExecSourceConfig>,
+}
+
+impl SourceConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
+    }
+}
+
+/// HashiCorp Vault AppRole login
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VaultConfig {
+    /// Vault address, e.g. `https://vault.internal:8200`
+    pub vault_addr: String,
+    pub role_id: GenericSourceValue,
+    pub secret_id: GenericSourceValue,
+    /// AppRole auth mount path, `approle` by default
+    pub mount: Option<String>,
+    /// id of the emitted token, `client_token` by default
+    pub token_id: Option<String>,
+}
+
+/// Kubernetes projected service account token (`serviceAccountToken` volume projection)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct KubeServiceAccountConfig {
+    /// token file path, e.g. `/var/run/secrets/tokens/my-token`
+    pub path: String,
+    /// expected `aud` claim, token is rejected when it is not in the audience
+    pub audience: Option<String>,
+    /// id of the emitted token, `token` by default
+    pub token_id: Option<String>,
+}
+
+/// Token read from a local file, e.g. a token written by another agent or a mounted secret
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FileSourceConfig {
+    /// token file path, whole trimmed content is the token
+    pub path: String,
+    /// `plain_text` by default, `jwt` takes expiration from the `exp` claim
+    pub token_type: Option<TokenType>,
+    /// plain_text only: token lifetime counted from every read, required for plain_text
+    pub ttl_seconds: Option<u64>,
+    /// id of the emitted token, `token` by default
+    pub token_id: Option<String>,
+}
+
+/// External credential helper, e.g. `gcloud auth print-access-token`, run without a shell
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ExecSourceConfig {
+    /// executable name or path, resolved with `PATH`
+    pub command: String,
+    /// arguments passed as is, no shell expansion
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// environment variables added to the agent environment
+    pub env: Option<HashMap<String, GenericSourceValue>>,
+    /// the command is killed when it runs longer, `30000` by default
+    pub timeout_ms: Option<u64>,
+}
+
+impl ExecSourceConfig {
+    /// Env values with their field path relative to `exec`, e.g. `env.VAULT_TOKEN`
+    pub fn generic_values(&self) -> Vec<(String, &GenericSourceValue)> {
+        self.env
+            .iter()
+            .flatten()
+            .map(|(name, value)| (format!("env.{}", name), value))
+            .collect()
+    }
+}
+
+/// GCP Workload Identity Federation: subject token from `request` is exchanged at Google STS,
+/// the federated token is optionally exchanged for a service account access token
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GcpWorkloadIdentityConfig {
+    /// pool provider, e.g. `//iam.googleapis.com/projects/<number>/locations/global/workloadIdentityPools/<pool>/providers/<provider>`
+    pub audience: String,
+    /// `urn:ietf:params:oauth:token-type:jwt` by default
+    pub subject_token_type: Option<String>,
+    /// JSON pointer to the subject token in the `request` response, whole trimmed body when unset (e.g. SAML assertion)
+    pub subject_token_pointer: Option<String>,
+    /// `https://www.googleapis.com/auth/cloud-platform` by default
+    pub scope: Option<String>,
+    /// service account email to impersonate, the federated token is emitted when unset
+    pub service_account: Option<String>,
+    /// lifetime of the service account access token, `3600` by default
+    pub lifetime_seconds: Option<u64>,
+    /// `https://sts.googleapis.com/v1/token` by default
+    pub sts_url: Option<String>,
+    /// `https://iamcredentials.googleapis.com` by default
+    pub iam_credentials_url: Option<String>,
+    /// id of the emitted token, `access_token` by default
+    pub token_id: Option<String>,
+}
+
+/// AWS STS `AssumeRoleWithWebIdentity`: web identity token is exchanged for temporary credentials
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AwsStsWebIdentityConfig {
+    /// e.g. `arn:aws:iam::123456789012:role/token-agent`
+    pub role_arn: GenericSourceValue,
+    pub role_session_name: GenericSourceValue,
+    /// OIDC token, usually read from file, e.g. `/var/run/secrets/eks.amazonaws.com/serviceaccount/token`
+    pub web_identity_token: GenericSourceValue,
+    /// STS region, requests go to `https://sts.<region>.amazonaws.com/`
+    pub region: String,
+    /// session duration, `3600` by default
+    pub duration_seconds: Option<u64>,
+    /// STS endpoint override, regional endpoint by default
+    pub sts_endpoint: Option<String>,
+}
+
+/// TLS options for source requests, a dedicated client is built per source when present
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TlsConfig {
+    /// PEM encoded CA certificate added to trusted roots
+    #[serde(alias = "ca_bundle_path")]
+    pub ca_file: Option<String>,
+    /// PEM encoded client certificate (mTLS), requires `client_key_file`
+    #[serde(alias = "cert_path")]
+    pub client_cert_file: Option<String>,
+    /// PEM encoded client private key, PKCS#8 or SEC1/PKCS#1 (mTLS), requires `client_cert_file`
+    #[serde(alias = "key_path")]
+    pub client_key_file: Option<String>,
+    /// disable server certificate verification, for testing only
+    pub insecure_skip_verify: Option<bool>,
 }
 
 /// HTTP request details
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 #[serde(rename_all = "lowercase")]
 pub struct RequestConfig {
     pub url: String,
     #[serde(with = "http_serde::method")]
-    pub method: Method, // GET, POST
+    pub method: Method, // GET, POST, PUT, PATCH, DELETE
     pub headers: Option<HashMap<String, GenericSourceValue>>,
+    /// query string parameters, url-encoded and appended to `url`
+    pub query: Option<HashMap<String, GenericSourceValue>>,
     pub body: Option<HashMap<String, GenericSourceValue>>,
-    pub form: Option<FormValue>
+    pub form: Option<FormValue>,
+    /// IMDSv2 only: session token TTL sent as `X-aws-ec2-metadata-token-ttl-seconds`
+    pub session_ttl_seconds: Option<u64>,
+    /// response body limit, overrides `settings.max_response_bytes`
+    pub max_response_bytes: Option<usize>,
+}
+
+impl RequestConfig {
+    /// Check if request body is sent for configured method (GET and HEAD never carry a body)
+    pub fn allows_body(&self) -> bool {
+        !matches!(self.method, Method::GET | Method::HEAD)
+    }
+
+    /// All request values with their field path relative to `request`, e.g. `headers.Authorization`
+    pub fn generic_values(&self) -> Vec<(String, &GenericSourceValue)> {
+        let mut values = Vec::new();
+        for (group, map) in [("headers", &self.headers), ("query", &self.query), ("body", &self.body)] {
+            for (name, value) in map.iter().flatten() {
+                values.push((format!("{}.{}", group, name), value));
+            }
+        }
+        if let Some(form) = &self.form {
+            values.push(("form.client_id".to_string(), &form.client_id));
+            if let Some(client_secret) = &form.client_secret {
+                values.push(("form.client_secret".to_string(), client_secret));
+            }
+            if let Some(client_assertion) = &form.client_assertion {
+                values.push(("form.client_assertion".to_string(), client_assertion));
+            }
+            values.push(("form.scope".to_string(), &form.scope));
+        }
+        values
+    }
 }
 
 /// Header value sources
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(untagged)]
 pub enum GenericSourceValue {
     Literal {
@@ -52,11 +262,34 @@ pub enum GenericSourceValue {
     FromFile {
         path: String,
     },
+    /// AWS SSM Parameter Store `GetParameter`, cached for `settings.ssm_cache_ttl_seconds`
+    FromAwsSsm {
+        parameter_name: String,
+        #[serde(default)]
+        with_decryption: bool,
+    },
+    /// GCP Secret Manager secret version, `latest` by default, cached for `settings.gcp_secret_cache_ttl_seconds`
+    FromGcpSecret {
+        project: String,
+        secret: String,
+        version: Option<String>,
+    },
     Ref {
         source: String,
         id: String,
         prefix: Option<String>,
     },
+    /// JWT signed with `key_file` at request time, e.g. `client_assertion` of `private_key_jwt`
+    SignedJwt {
+        key_file: String,
+        /// `RS256` by default
+        algorithm: Option<String>,
+        /// string claims, e.g. `iss`, `sub`, `aud`; templates are rendered
+        #[serde(default)]
+        claims: HashMap<String, String>,
+        /// `exp` is `iat` plus this, 300 by default
+        ttl_seconds: Option<u64>,
+    },
     Template {
         template: String,
         #[serde(default)]
@@ -64,25 +297,66 @@ pub enum GenericSourceValue {
     },
 }
 
-/// Body value sources
-#[derive(Debug, Deserialize, Clone)]
+pub const OAUTH2_GRANT_TYPE_DEFAULT: &str = "client_credentials";
+pub const OAUTH2_CLIENT_ASSERTION_TYPE_JWT: &str = "urn:ietf:params:oauth:client-assertion-type:jwt-bearer";
+
+/// OAuth2 token request sent as `application/x-www-form-urlencoded`, mutually exclusive with `body`
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct  FormValue {
     pub client_id: GenericSourceValue,
-    pub client_secret: GenericSourceValue,
+    /// client authentication, exactly one of `client_secret` and `client_assertion`
+    pub client_secret: Option<GenericSourceValue>,
+    /// `private_key_jwt` client authentication, usually `key_file` signed JWT;
+    /// sent with `client_assertion_type` JWT bearer
+    pub client_assertion: Option<GenericSourceValue>,
     pub scope: GenericSourceValue,
+    /// `grant_type` form field, `client_credentials` by default
+    pub grant_type: Option<String>,
 }
 
 /// ================================
 /// Parsing - Tokens & Expirations
 /// ================================
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct ParseConfig {
+    /// response checks made before tokens are parsed, http sources only
+    pub expect: Option<ResponseExpect>,
+    /// how the body is decoded before body pointers are resolved, detected from `Content-Type` if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_format: Option<BodyFormat>,
     pub tokens: Vec<TokenField>,
 }
 
+/// Response body encoding
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BodyFormat {
+    #[default]
+    Json,
+    /// whole trimmed body is the token, addressed by pointer `.`
+    #[serde(alias = "plain_text")]
+    Plain,
+    /// `key=value&...` pairs, pointers are keys
+    #[serde(alias = "form_encoded")]
+    FormUrlencoded,
+    /// XML document, pointers are element paths from the root like `/Response/Result/Token`
+    Xml,
+}
+
+/// Response of an HTTP source that violates these checks fails the fetch with reason `unexpected_response`
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ResponseExpect {
+    /// acceptable statuses, any 2xx if not set
+    pub expected_status: Option<Vec<u16>>,
+    /// substring of `Content-Type` header, e.g. `application/json`
+    pub expected_content_type: Option<String>,
+}
+
 pub const SAFETY_MARGIN_SECONDS_SOURCE_DEFAULT: u64 = 10;
+pub const PREFETCH_MARGIN_SECONDS_DEFAULT: u64 = 0;
+pub const MIN_REFRESH_INTERVAL_SECONDS_DEFAULT: u64 = 15;
 /// Represents a token or expiration field
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TokenField {
     pub id: String,            // unique per source
     pub parent: String,        // one of: body, header, query
@@ -90,10 +364,57 @@ pub struct TokenField {
     pub token_type: TokenType, // allowed: jwt, plain_text, expiration
     pub expiration: Option<Expiration>, // None for JWT, Some for plain or manual
                                // invariants documented in YAML contract
+    pub jwks_uri: Option<String>, // jwt only: verify signature with key set from this URI
+    /// applied in order to the raw value before it is stored as token value
+    #[serde(default, with = "serde_yaml::with::singleton_map_recursive", skip_serializing_if = "Option::is_none")]
+    pub transforms: Option<Vec<TokenTransform>>,
+    /// jwt only: payload claims kept with the token, available in request templates as `{{source.token_id.claim}}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extract_claims: Option<Vec<String>>,
+    /// body only: `pointer` is an array, one token per element with id `<id>.<element name>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iterate: Option<TokenIterate>,
+    /// longest accepted token value, `settings.max_token_bytes` or 64 KiB by default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_token_bytes: Option<usize>,
+    /// token value must match, e.g. `^[A-Za-z0-9._-]+$`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value_regex: Option<String>,
+}
+
+/// Batch of tokens in a JSON array, element fields are read by pointers relative to the element,
+/// e.g. `{id_from: /name, value_from: /value, exp_from: /exp, exp_format: unix}`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TokenIterate {
+    pub id_from: String,
+    pub value_from: String,
+    /// plain_text only, replaces the `expiration` block of the token field
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exp_from: Option<String>,
+    #[serde(default)]
+    pub exp_format: ExpirationSourceFormat,
+}
+
+/// Token value transform, e.g. `- strip_prefix: "Bearer "`
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenTransform {
+    Trim,
+    StripPrefix(String),
+    StripSuffix(String),
+    /// standard alphabet, padding optional, result must be UTF-8
+    Base64Decode,
+    /// URL-safe alphabet, padding optional, result must be UTF-8
+    Base64UrlDecode,
+    UpperCase,
+    LowerCase,
+    /// value is a JSON document, e.g. credentials nested as a string by Vault,
+    /// `pointer` resolves like a body pointer, a non-string result is kept as JSON text
+    JsonExtract { pointer: String },
 }
 
 /// Expiration definition
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Expiration {
     pub source: ExpirationSource,        // self | field | manual
     pub pointer: Option<String>,         // required if source=field
@@ -102,7 +423,7 @@ pub struct Expiration {
     pub format: ExpirationSourceFormat
 }
 
-#[derive(Debug, Clone, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "snake_case")]
 pub enum ExpirationSourceFormat {
     /// Duration in seconds until expiration.
@@ -111,10 +432,13 @@ pub enum ExpirationSourceFormat {
 
     /// Unix timestamp (integer seconds since epoch)
     Unix,
+
+    /// RFC3339 / ISO8601 datetime string (`2025-10-07T10:00:00Z`)
+    Rfc3339,
 }
 
 /// Token types
-#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum TokenType {
     Jwt,
@@ -123,7 +447,7 @@ pub enum TokenType {
 }
 
 /// Expiration sources
-#[derive(Debug, Deserialize, Clone, Copy)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
 #[serde(rename_all = "snake_case")]
 pub enum ExpirationSource {
     #[serde(rename = "self")]
@@ -133,16 +457,64 @@ pub enum ExpirationSource {
     Manual, // user-defined TTL
 }
 
-#[derive(Debug, Deserialize, Clone, Copy, Serialize)]
+#[derive(Debug, Deserialize, Clone, Copy, Serialize, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum SourceTypes {
+    #[default]
     HTTP,
     METADATA,
     OAUTH2,
+    /// AWS EC2 metadata with IMDSv2 session token handshake
+    IMDSV2,
+    /// HashiCorp Vault AppRole login with token renewal
+    VAULT,
+    /// Kubernetes projected service account token read from file
+    #[serde(rename = "kube_service_account")]
+    KubeServiceAccount,
+    /// GCP Workload Identity Federation: STS token exchange with optional service account impersonation
+    #[serde(rename = "gcp_workload_identity")]
+    GcpWorkloadIdentity,
+    /// AWS STS `AssumeRoleWithWebIdentity`: temporary credentials for a web identity token
+    #[serde(rename = "aws_sts_web_identity")]
+    AwsStsWebIdentity,
+    /// Token read from a local file, re-read on schedule and when the file is modified
+    FILE,
+    /// Token printed to stdout by an external credential helper command
+    EXEC,
+}
+
+impl SourceTypes {
+    /// Name as written in config `type` field
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SourceTypes::HTTP => "http",
+            SourceTypes::METADATA => "metadata",
+            SourceTypes::OAUTH2 => "oauth2",
+            SourceTypes::IMDSV2 => "imdsv2",
+            SourceTypes::VAULT => "vault",
+            SourceTypes::KubeServiceAccount => "kube_service_account",
+            SourceTypes::GcpWorkloadIdentity => "gcp_workload_identity",
+            SourceTypes::AwsStsWebIdentity => "aws_sts_web_identity",
+            SourceTypes::FILE => "file",
+            SourceTypes::EXEC => "exec",
+        }
+    }
+
+    /// Fetched with the `request` block as a single HTTP request, method and `http.*` attributes apply
+    pub fn is_http(&self) -> bool {
+        matches!(self, SourceTypes::HTTP | SourceTypes::METADATA | SourceTypes::OAUTH2 | SourceTypes::IMDSV2)
+    }
 }
 
 // jwt oken
 #[derive(Debug, Deserialize)]
 pub struct JwtClaims {
     pub exp: u64,
+    #[serde(default)]
+    pub nbf: Option<u64>,
+    #[serde(default)]
+    pub iat: Option<u64>,
+    /// string or array of strings
+    #[serde(default)]
+    pub aud: Option<serde_json::Value>,
 }
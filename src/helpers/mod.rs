@@ -1 +1,2 @@
+pub mod secret;
 pub mod time;
\ No newline at end of file
@@ -0,0 +1,19 @@
+use subtle::ConstantTimeEq;
+
+/// Compare a provided secret with the expected one in constant time, only the length difference is observable
+pub fn secret_eq(provided: &str, expected: &str) -> bool {
+    provided.as_bytes().ct_eq(expected.as_bytes()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn secret_eq_matches_only_identical_values() {
+        assert!(secret_eq("s3cr3t", "s3cr3t"));
+        assert!(!secret_eq("s3cr3x", "s3cr3t"));
+        assert!(!secret_eq("s3cr3", "s3cr3t"));
+        assert!(!secret_eq("", "s3cr3t"));
+    }
+}
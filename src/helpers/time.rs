@@ -1,7 +1,8 @@
 use chrono::Utc;
 use tokio::time::Instant;
 
-use crate::config::sources::SAFETY_MARGIN_SECONDS_SOURCE_DEFAULT;
+use crate::cache::token_context::TokenContext;
+use crate::config::sources::{SourceConfig, PREFETCH_MARGIN_SECONDS_DEFAULT, SAFETY_MARGIN_SECONDS_SOURCE_DEFAULT};
 
 pub fn get_token_safety_margin_seconds(
     safety_margin_seconds_settings: Option<u64>,
@@ -15,6 +16,45 @@ pub fn get_token_safety_margin_seconds(
         .unwrap()
 }
 
+/// Larger of fixed and percent of `total_ttl_seconds` margins, whichever is configured
+pub fn get_effective_safety_margin_seconds(
+    safety_margin_seconds: Option<u64>,
+    safety_margin_percent: Option<f64>,
+    total_ttl_seconds: u64,
+) -> u64 {
+    match safety_margin_percent {
+        Some(percent) => {
+            let percent_margin = (total_ttl_seconds as f64 * percent / 100.0) as u64;
+            safety_margin_seconds.map_or(percent_margin, |seconds| seconds.max(percent_margin))
+        }
+        None => safety_margin_seconds.unwrap_or(SAFETY_MARGIN_SECONDS_SOURCE_DEFAULT),
+    }
+}
+
+/// Safety margin of a freshly fetched token, percent margin is taken of its remaining TTL
+pub fn get_safety_margin_from_context(
+    token_context: &TokenContext,
+    safety_margin_seconds_settings: Option<u64>,
+    source: &SourceConfig,
+) -> u64 {
+    get_effective_safety_margin_seconds(
+        source.safety_margin_seconds.or(safety_margin_seconds_settings),
+        source.safety_margin_percent,
+        token_context.token.exp_unix_ts.saturating_sub(now_u64()),
+    )
+}
+
+pub fn get_token_prefetch_margin_seconds(
+    prefetch_margin_seconds_settings: Option<u64>,
+    prefetch_margin_seconds_source: Option<u64>,
+) -> u64 {
+    // source level
+    prefetch_margin_seconds_source
+        // settings (global) level
+        .or(prefetch_margin_seconds_settings)
+        .unwrap_or(PREFETCH_MARGIN_SECONDS_DEFAULT)
+}
+
 pub fn now_u64() -> u64 {
     now_i64() as u64
 }
@@ -25,4 +65,19 @@ pub fn now_i64() -> i64 {
 
 pub fn get_instant() -> Instant {
     Instant::now()
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn effective_safety_margin_is_larger_of_seconds_and_percent() {
+        // 10% of 24h outweighs 60s, 10% of 60s does not
+        assert_eq!(get_effective_safety_margin_seconds(Some(60), Some(10.0), 86_400), 8_640);
+        assert_eq!(get_effective_safety_margin_seconds(Some(60), Some(10.0), 60), 60);
+        assert_eq!(get_effective_safety_margin_seconds(None, Some(25.0), 60), 15);
+        assert_eq!(get_effective_safety_margin_seconds(Some(30), None, 60), 30);
+        assert_eq!(get_effective_safety_margin_seconds(None, None, 60), SAFETY_MARGIN_SECONDS_SOURCE_DEFAULT);
+    }
+}
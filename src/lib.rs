@@ -9,6 +9,7 @@
 //! - `cache` — token cache implementation
 //! - `sources` — HTTP, Metadata, OAuth2 token sources
 //! - `parser` — parsing responses to extract tokens and expirations
+//! - `agent` — `TokenAgent` builder to embed the agent in other binaries
 
 pub mod config;
 pub mod cache;
@@ -21,7 +22,9 @@ pub mod server;
 pub mod sinks;
 pub mod helpers;
 pub mod utils;
+pub mod agent;
 
 
 pub use crate::config::sources::*;
 pub use crate::parser::parser::parse_tokens;
+pub use crate::agent::{TokenAgent, TokenAgentHandle};
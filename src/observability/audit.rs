@@ -0,0 +1,252 @@
+//! Token lifecycle audit log
+//!
+//! JSON lines written to `settings.audit.path` (or stderr) separately from the `tracing` output,
+//! one line per fetched, failed, served, invalidated or propagated token. Token values are never
+//! written, events only carry ids and expiry. The file is renamed to `<path>.1` (older files shifted
+//! up to `<path>.<max_files>`) before a line would grow it over `max_file_bytes`.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use serde::Serialize;
+use tokio::sync::OnceCell;
+use tracing::{error, info};
+
+use crate::config::settings::AuditConfig;
+
+pub const AUDIT_STDERR_PATH: &str = "stderr";
+pub const AUDIT_MAX_FILE_BYTES_DEFAULT: u64 = 10 * 1024 * 1024;
+pub const AUDIT_MAX_FILES_DEFAULT: usize = 5;
+// audit log may reveal which tokens exist, only the agent user may read it
+#[cfg(unix)]
+const AUDIT_FILE_MODE: u32 = 0o600;
+
+// Declare the static OnceCell to hold the AuditLog, set once on startup when enabled.
+static AUDIT_LOG_INSTANCE: OnceCell<AuditLog> = OnceCell::const_new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditEventType {
+    FetchSuccess,
+    FetchFailure,
+    /// token served from cache to a consumer, HTTP sink request
+    CacheHit,
+    Invalidation,
+    SinkPropagation,
+}
+
+/// Audit log line, fields not known for an event type are `null`
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditEvent {
+    pub event_type: AuditEventType,
+    pub source_id: String,
+    pub token_id: Option<String>,
+    pub timestamp_rfc3339: String,
+    pub exp_unix_ts: Option<u64>,
+    pub consumer_ip: Option<String>,
+    pub sink_id: Option<String>,
+    /// `FetchError` reason of `fetch_failure`
+    pub reason: Option<String>,
+}
+
+impl AuditEvent {
+    pub fn new(event_type: AuditEventType, source_id: &str) -> Self {
+        Self {
+            event_type,
+            source_id: source_id.to_owned(),
+            token_id: None,
+            timestamp_rfc3339: Utc::now().to_rfc3339(),
+            exp_unix_ts: None,
+            consumer_ip: None,
+            sink_id: None,
+            reason: None,
+        }
+    }
+
+    pub fn token(mut self, token_id: &str, exp_unix_ts: Option<u64>) -> Self {
+        self.token_id = Some(token_id.to_owned());
+        self.exp_unix_ts = exp_unix_ts;
+        self
+    }
+
+    pub fn sink(mut self, sink_id: &str) -> Self {
+        self.sink_id = Some(sink_id.to_owned());
+        self
+    }
+
+    pub fn consumer_ip(mut self, consumer_ip: Option<String>) -> Self {
+        self.consumer_ip = consumer_ip;
+        self
+    }
+
+    pub fn reason(mut self, reason: &str) -> Self {
+        self.reason = Some(reason.to_owned());
+        self
+    }
+}
+
+/// Write event to the audit log if enabled
+pub fn emit(event: AuditEvent) {
+    if let Some(audit_log) = AUDIT_LOG_INSTANCE.get() {
+        if let Err(e) = audit_log.write(&event) {
+            error!("audit log write failed: {}", e);
+        }
+    }
+}
+
+enum AuditWriter {
+    Stderr,
+    File { file: File, size: u64 },
+}
+
+pub struct AuditLog {
+    path: String,
+    max_file_bytes: u64,
+    max_files: usize,
+    writer: Mutex<AuditWriter>,
+}
+
+impl AuditLog {
+    /// Open audit log file for append, `stderr` path writes to standard error
+    pub fn open(config: &AuditConfig) -> Result<Self> {
+        let writer = match config.path.as_str() {
+            AUDIT_STDERR_PATH => AuditWriter::Stderr,
+            path => open_file(path)?,
+        };
+        Ok(Self {
+            path: config.path.to_owned(),
+            max_file_bytes: config.max_file_bytes.unwrap_or(AUDIT_MAX_FILE_BYTES_DEFAULT),
+            max_files: config.max_files.unwrap_or(AUDIT_MAX_FILES_DEFAULT),
+            writer: Mutex::new(writer),
+        })
+    }
+
+    /// Open audit log and make it the target of `emit`, kept for the process lifetime
+    pub fn init(config: &AuditConfig) -> Result<()> {
+        if AUDIT_LOG_INSTANCE.initialized() {
+            return Ok(());
+        }
+        let audit_log = AuditLog::open(config)?;
+        info!("audit log opened at '{}'", config.path);
+        AUDIT_LOG_INSTANCE.set(audit_log).map_err(|_| anyhow!("audit log is already initialized"))
+    }
+
+    pub fn write(&self, event: &AuditEvent) -> Result<()> {
+        let mut line = serde_json::to_vec(event)?;
+        line.push(b'\n');
+        let mut writer = self.writer.lock().map_err(|_| anyhow!("audit log lock poisoned"))?;
+        if let AuditWriter::File { size, .. } = &*writer {
+            if *size > 0 && *size + line.len() as u64 > self.max_file_bytes {
+                self.rotate()?;
+                *writer = open_file(&self.path)?;
+            }
+        }
+        match &mut *writer {
+            AuditWriter::Stderr => io::stderr().write_all(&line)?,
+            AuditWriter::File { file, size } => {
+                file.write_all(&line)?;
+                *size += line.len() as u64;
+            }
+        }
+        Ok(())
+    }
+
+    /// `<path>.N-1` -> `<path>.N` ... `<path>` -> `<path>.1`, the oldest file is overwritten
+    fn rotate(&self) -> Result<()> {
+        for index in (1..self.max_files).rev() {
+            let from = format!("{}.{}", self.path, index);
+            if fs::metadata(&from).is_ok() {
+                fs::rename(&from, format!("{}.{}", self.path, index + 1))?;
+            }
+        }
+        fs::rename(&self.path, format!("{}.1", self.path))?;
+        Ok(())
+    }
+}
+
+fn open_file(path: &str) -> Result<AuditWriter> {
+    let mut options = OpenOptions::new();
+    options.create(true).append(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(AUDIT_FILE_MODE);
+    }
+    let file = options.open(path).map_err(|e| anyhow!("open audit log '{}': {}", path, e))?;
+    let size = file.metadata()?.len();
+    Ok(AuditWriter::File { file, size })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+
+    const SCHEMA_FIELDS: [&str; 8] =
+        ["event_type", "source_id", "token_id", "timestamp_rfc3339", "exp_unix_ts", "consumer_ip", "sink_id", "reason"];
+
+    fn read_lines(path: &str) -> Vec<Value> {
+        fs::read_to_string(path).unwrap().lines().map(|line| serde_json::from_str(line).unwrap()).collect()
+    }
+
+    #[test]
+    fn each_event_type_has_audit_schema() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.log").to_str().unwrap().to_owned();
+        let audit_log = AuditLog::open(&AuditConfig { path: path.clone(), ..Default::default() }).unwrap();
+
+        let events = [
+            AuditEvent::new(AuditEventType::FetchSuccess, "idp").token("access_token", Some(1_900_000_000)),
+            AuditEvent::new(AuditEventType::FetchFailure, "idp").reason("http_5xx"),
+            AuditEvent::new(AuditEventType::CacheHit, "idp")
+                .token("access_token", Some(1_900_000_000))
+                .sink("http_sink")
+                .consumer_ip(Some("10.0.0.7".into())),
+            AuditEvent::new(AuditEventType::Invalidation, "idp").token("access_token", Some(1_900_000_000)),
+            AuditEvent::new(AuditEventType::SinkPropagation, "idp").token("access_token", None).sink("file_sink"),
+        ];
+        events.iter().for_each(|event| audit_log.write(event).unwrap());
+
+        let lines = read_lines(&path);
+        let event_types: Vec<&str> = lines.iter().map(|line| line["event_type"].as_str().unwrap()).collect();
+        assert_eq!(event_types, ["fetch_success", "fetch_failure", "cache_hit", "invalidation", "sink_propagation"]);
+        for line in &lines {
+            let mut keys: Vec<&str> = line.as_object().unwrap().keys().map(String::as_str).collect();
+            keys.sort();
+            let mut expected = SCHEMA_FIELDS.to_vec();
+            expected.sort();
+            assert_eq!(keys, expected);
+            assert_eq!(line["source_id"], "idp");
+            assert!(chrono::DateTime::parse_from_rfc3339(line["timestamp_rfc3339"].as_str().unwrap()).is_ok());
+        }
+        assert_eq!(lines[0]["token_id"], "access_token");
+        assert_eq!(lines[0]["exp_unix_ts"], 1_900_000_000);
+        assert_eq!(lines[1]["token_id"], Value::Null);
+        assert_eq!(lines[1]["reason"], "http_5xx");
+        assert_eq!(lines[2]["consumer_ip"], "10.0.0.7");
+        assert_eq!(lines[2]["sink_id"], "http_sink");
+        assert_eq!(lines[4]["exp_unix_ts"], Value::Null);
+    }
+
+    #[test]
+    fn file_is_rotated_before_max_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("audit.log").to_str().unwrap().to_owned();
+        let event = AuditEvent::new(AuditEventType::FetchSuccess, "idp").token("access_token", Some(1));
+        let line_len = serde_json::to_vec(&event).unwrap().len() as u64 + 1;
+        let config = AuditConfig { path: path.clone(), max_file_bytes: Some(line_len * 2), max_files: Some(2) };
+        let audit_log = AuditLog::open(&config).unwrap();
+
+        (0..7).for_each(|_| audit_log.write(&event).unwrap());
+
+        // 7 lines: 2 + 2 rotated twice, 2 rotated once, 1 current; the oldest 2 are dropped
+        assert_eq!(read_lines(&path).len(), 1);
+        assert_eq!(read_lines(&format!("{}.1", path)).len(), 2);
+        assert_eq!(read_lines(&format!("{}.2", path)).len(), 2);
+        assert!(fs::metadata(format!("{}.3", path)).is_err());
+    }
+}
@@ -10,6 +10,10 @@ use tokio::sync::OnceCell;
 // Declare the static OnceCell to hold the Metrics.
 static METRICS_INSTANCE: OnceCell<Arc<Metrics>> = OnceCell::const_new();
 
+/// `enabled` label of `sink_propagations_total`
+pub static SINK_ENABLED_LABEL: &str = "true";
+pub static SINK_DISABLED_LABEL: &str = "false";
+
 /// Asynchronously initializes and gets a reference to the static `TokenCache`.
 pub async fn get_metrics() -> &'static Arc<Metrics> {
     METRICS_INSTANCE.get_or_init(|| async { 
@@ -27,23 +31,50 @@ pub struct Metrics {
     pub source_fetch_requests: IntCounterVec,
     pub source_fetch_failures: IntCounterVec,
     pub source_fetch_duration: HistogramVec,
+    pub token_refresh_latency: HistogramVec,
+    pub token_issued_ttl: HistogramVec,
+    pub token_refresh: IntCounterVec,
+    pub source_circuit_state: IntGaugeVec,
+    pub source_backoff_seconds: IntGaugeVec,
+    pub source_prefetch_requests: IntCounterVec,
+    pub source_304_responses: IntCounterVec,
+    pub ssm_fetch_requests: IntCounterVec,
+    pub ssm_fetch_failures: IntCounterVec,
+    pub gcp_secret_fetch_requests: IntCounterVec,
+    pub gcp_secret_fetch_failures: IntCounterVec,
 
     // Parser metrics
     pub parse_failures: IntCounter,
+    pub jwt_signature_failures: IntCounter,
     // pub template_failures: IntCounterVec,
 
     // Cache metrics
     pub cached_tokens: IntGaugeVec,
+    pub cached_stale_tokens: IntGaugeVec,
     pub token_expiry_unix: IntGaugeVec,
+    pub token_seconds_until_expiry: IntGaugeVec,
+    pub token_last_refresh_unix: IntGaugeVec,
 
     // Sink metrics
     pub sink_propagations: IntCounterVec,
     pub sink_failures: IntCounterVec,
     pub sink_duration: HistogramVec,
+    pub sink_push_requests: IntCounterVec,
+    pub sink_push_failures: IntCounterVec,
+    pub sink_auth_failures: IntCounterVec,
+    pub sink_rate_limited_requests: IntCounterVec,
+    pub sink_nats_published: IntCounterVec,
+    pub sink_nats_failures: IntCounterVec,
+    pub sink_redis_commands: IntCounterVec,
+    pub sink_kubernetes_secret_patches: IntCounterVec,
+    pub sink_uds_connect_retries: IntCounterVec,
+    pub sink_http_304_responses: IntCounterVec,
+    pub sink_overlap_served: IntCounterVec,
 
     // Config/runtime
     pub config_validation_errors: IntCounter,
-    // pub config_reloads: IntCounterVec,
+    pub config_reloads: IntCounterVec,
+    pub config_reload_failures: IntCounter,
     pub up: IntGauge,
 
         // === Service resource metrics ===
@@ -65,20 +96,52 @@ impl Metrics {
             source_fetch_requests: IntCounterVec::new(Opts::new("source_fetch_requests_total","Total fetch attempts by source",),&["source", "source_type", "method"],).unwrap(),
             source_fetch_failures: IntCounterVec::new(Opts::new("source_fetch_failures_total", "Fetch failures by reason"),&["source", "reason"],).unwrap(),
             source_fetch_duration: HistogramVec::new(HistogramOpts::new("source_fetch_duration_seconds", "Fetch duration seconds").buckets(vec![0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0]),&["source"],).unwrap(),
+            token_refresh_latency: HistogramVec::new(HistogramOpts::new("token_refresh_latency_seconds", "Successful fetch duration per token, cycle: initial or refresh").buckets(vec![0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.0, 2.5, 5.0, 10.0]),&["source", "token_id", "cycle"],).unwrap(),
+            token_issued_ttl: HistogramVec::new(HistogramOpts::new("token_issued_ttl_seconds", "Remaining lifetime of newly issued tokens at fetch time").buckets(vec![60.0, 300.0, 900.0, 1800.0, 3600.0, 7200.0, 21600.0, 43200.0, 86400.0]),&["source", "token_id"],).unwrap(),
+            token_refresh: IntCounterVec::new(Opts::new("token_refresh_total", "Token refresh attempts by result: success or failure"),&["source", "token_id", "result"],).unwrap(),
+
+            source_circuit_state: IntGaugeVec::new(Opts::new("source_circuit_state", "Circuit breaker state by source: 0 closed, 1 open, 2 half-open"),&["source"],).unwrap(),
+            source_backoff_seconds: IntGaugeVec::new(Opts::new("source_backoff_seconds", "Current wait before the next fetch attempt of a failing source, 0 when healthy"),&["source"],).unwrap(),
+
+            source_prefetch_requests: IntCounterVec::new(Opts::new("source_prefetch_requests_total", "Pre-fetch attempts while current token is still live"),&["source"],).unwrap(),
+
+            source_304_responses: IntCounterVec::new(Opts::new("source_304_responses_total", "Conditional source requests answered with 304 Not Modified"),&["source"],).unwrap(),
+
+            ssm_fetch_requests: IntCounterVec::new(Opts::new("ssm_fetch_requests_total", "SSM GetParameter calls, cache hits not counted"),&["parameter"],).unwrap(),
+            ssm_fetch_failures: IntCounterVec::new(Opts::new("ssm_fetch_failures_total", "Failed SSM GetParameter calls"),&["parameter"],).unwrap(),
+            gcp_secret_fetch_requests: IntCounterVec::new(Opts::new("gcp_secret_fetch_requests_total", "GCP Secret Manager access calls, cache hits not counted"),&["secret"],).unwrap(),
+            gcp_secret_fetch_failures: IntCounterVec::new(Opts::new("gcp_secret_fetch_failures_total", "Failed GCP Secret Manager access calls"),&["secret"],).unwrap(),
 
             parse_failures: IntCounter::new("parse_extraction_failures_total","Parser/extraction failures",).unwrap(),
+            jwt_signature_failures: IntCounter::new("parse_jwt_signature_failures_total","JWT signature verification failures",).unwrap(),
 
             // Cache
             cached_tokens: IntGaugeVec::new(Opts::new("cached_tokens_total", "Cached tokens per source"),&["source"],).unwrap(),
+            cached_stale_tokens: IntGaugeVec::new(Opts::new("cached_stale_tokens_total", "Cached expired tokens served as stale per source, see stale_token_ttl_seconds"),&["source"],).unwrap(),
             token_expiry_unix: IntGaugeVec::new(Opts::new("token_expiry_unix_seconds", "Token expiry timestamp"),&["source", "token_id"],).unwrap(),
+            token_seconds_until_expiry: IntGaugeVec::new(Opts::new("token_seconds_until_expiry", "Seconds left until token expiry, 0 once expired"),&["source", "token_id"],).unwrap(),
+            token_last_refresh_unix: IntGaugeVec::new(Opts::new("token_last_refresh_unix", "Unix timestamp the token was last stored"),&["source", "token_id"],).unwrap(),
 
             // Sink
-            sink_propagations: IntCounterVec::new(Opts::new("sink_propagations_total", "Total propagations"),&["sink", "sink_type", "source", "token_id"],).unwrap(),
+            sink_propagations: IntCounterVec::new(Opts::new("sink_propagations_total", "Total propagations, `enabled=false` series are sinks of disabled sources"),&["sink", "sink_type", "source", "token_id", "enabled"],).unwrap(),
             sink_failures: IntCounterVec::new(Opts::new("sink_failures_total", "Sink failures"),&["sink", "reason"],).unwrap(),
             sink_duration: HistogramVec::new(HistogramOpts::new("sink_propagation_duration_seconds", "Sink propagation time").buckets(vec![0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0]),&["sink"],).unwrap(),
+            sink_push_requests: IntCounterVec::new(Opts::new("sink_push_requests_total", "HTTP sink push requests, retries included"),&["sink", "method"],).unwrap(),
+            sink_push_failures: IntCounterVec::new(Opts::new("sink_push_failures_total", "HTTP sink pushes failed after all retries"),&["sink", "reason"],).unwrap(),
+            sink_auth_failures: IntCounterVec::new(Opts::new("sink_auth_failures_total", "HTTP sink requests rejected as unauthorized"),&["sink", "reason"],).unwrap(),
+            sink_rate_limited_requests: IntCounterVec::new(Opts::new("sink_rate_limited_requests_total", "HTTP sink requests rejected with 429 by rate limit"),&["sink"],).unwrap(),
+            sink_nats_published: IntCounterVec::new(Opts::new("sink_nats_published_total", "Tokens published to NATS subjects"),&["sink"],).unwrap(),
+            sink_nats_failures: IntCounterVec::new(Opts::new("sink_nats_failures_total", "Failed NATS sink publishes"),&["sink", "reason"],).unwrap(),
+            sink_redis_commands: IntCounterVec::new(Opts::new("sink_redis_commands_total", "Commands sent by Redis sinks"),&["sink", "command", "status"],).unwrap(),
+            sink_kubernetes_secret_patches: IntCounterVec::new(Opts::new("sink_kubernetes_secret_patches_total", "Secret patches sent by Kubernetes Secret sinks, operation: set or remove"),&["sink", "operation", "status"],).unwrap(),
+            sink_uds_connect_retries: IntCounterVec::new(Opts::new("sink_uds_connect_retries_total", "UDS sink socket bind retries"),&["sink"],).unwrap(),
+            sink_http_304_responses: IntCounterVec::new(Opts::new("sink_http_304_responses_total", "HTTP sink conditional requests answered with 304 Not Modified"),&["sink"],).unwrap(),
+            sink_overlap_served: IntCounterVec::new(Opts::new("sink_overlap_served_total", "Previous token served while the current one is missing, within rotation_overlap_seconds"),&["sink"],).unwrap(),
 
             // Config/runtime
             config_validation_errors: IntCounter::new("config_validation_errors_total","Validation errors during startup/config reload",).unwrap(),
+            config_reloads: IntCounterVec::new(Opts::new("config_reloads_total", "Config reloads by result: success, failure"),&["result"],).unwrap(),
+            config_reload_failures: IntCounter::new("config_reload_failures_total", "Config reloads aborted because new config failed to load or validate").unwrap(),
             up: IntGauge::new("up", "1 if service is healthy").unwrap(),
             process_cpu_usage: Gauge::new("process_cpu_usage_percent", "CPU usage % of this process").unwrap(),
             process_memory_usage: IntGauge::new("process_memory_usage_bytes", "Resident memory used by this process").unwrap(),
@@ -96,13 +159,41 @@ impl Metrics {
         reg.register(Box::new(metrics.source_fetch_requests.clone())).unwrap();
         reg.register(Box::new(metrics.source_fetch_failures.clone())).unwrap();
         reg.register(Box::new(metrics.source_fetch_duration.clone())).unwrap();
+        reg.register(Box::new(metrics.token_refresh_latency.clone())).unwrap();
+        reg.register(Box::new(metrics.token_issued_ttl.clone())).unwrap();
+        reg.register(Box::new(metrics.token_refresh.clone())).unwrap();
+        reg.register(Box::new(metrics.source_circuit_state.clone())).unwrap();
+        reg.register(Box::new(metrics.source_backoff_seconds.clone())).unwrap();
+        reg.register(Box::new(metrics.source_prefetch_requests.clone())).unwrap();
+        reg.register(Box::new(metrics.source_304_responses.clone())).unwrap();
+        reg.register(Box::new(metrics.ssm_fetch_requests.clone())).unwrap();
+        reg.register(Box::new(metrics.ssm_fetch_failures.clone())).unwrap();
+        reg.register(Box::new(metrics.gcp_secret_fetch_requests.clone())).unwrap();
+        reg.register(Box::new(metrics.gcp_secret_fetch_failures.clone())).unwrap();
         reg.register(Box::new(metrics.parse_failures.clone())).unwrap();
+        reg.register(Box::new(metrics.jwt_signature_failures.clone())).unwrap();
         reg.register(Box::new(metrics.cached_tokens.clone())).unwrap();
+        reg.register(Box::new(metrics.cached_stale_tokens.clone())).unwrap();
         reg.register(Box::new(metrics.token_expiry_unix.clone())).unwrap();
+        reg.register(Box::new(metrics.token_seconds_until_expiry.clone())).unwrap();
+        reg.register(Box::new(metrics.token_last_refresh_unix.clone())).unwrap();
         reg.register(Box::new(metrics.sink_propagations.clone())).unwrap();
         reg.register(Box::new(metrics.sink_failures.clone())).unwrap();
         reg.register(Box::new(metrics.sink_duration.clone())).unwrap();
+        reg.register(Box::new(metrics.sink_push_requests.clone())).unwrap();
+        reg.register(Box::new(metrics.sink_push_failures.clone())).unwrap();
+        reg.register(Box::new(metrics.sink_auth_failures.clone())).unwrap();
+        reg.register(Box::new(metrics.sink_rate_limited_requests.clone())).unwrap();
+        reg.register(Box::new(metrics.sink_nats_published.clone())).unwrap();
+        reg.register(Box::new(metrics.sink_nats_failures.clone())).unwrap();
+        reg.register(Box::new(metrics.sink_redis_commands.clone())).unwrap();
+        reg.register(Box::new(metrics.sink_kubernetes_secret_patches.clone())).unwrap();
+        reg.register(Box::new(metrics.sink_uds_connect_retries.clone())).unwrap();
+        reg.register(Box::new(metrics.sink_http_304_responses.clone())).unwrap();
+        reg.register(Box::new(metrics.sink_overlap_served.clone())).unwrap();
         reg.register(Box::new(metrics.config_validation_errors.clone())).unwrap();
+        reg.register(Box::new(metrics.config_reloads.clone())).unwrap();
+        reg.register(Box::new(metrics.config_reload_failures.clone())).unwrap();
         reg.register(Box::new(metrics.up.clone())).unwrap();
 
         reg.register(Box::new(metrics.process_cpu_usage.clone())).unwrap();
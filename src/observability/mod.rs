@@ -1,3 +1,5 @@
+pub mod audit;
 pub mod metrics;
+pub mod otel;
 pub mod routes;
 pub mod service_resources_metrics;
\ No newline at end of file
@@ -0,0 +1,340 @@
+//! OpenTelemetry trace export, cargo feature `otel` (on by default)
+//!
+//! With `settings.otel.endpoint` set, spans of this crate are exported by a `tracing-opentelemetry` layer
+//! through a batching `opentelemetry-otlp` exporter, OTLP/HTTP protobuf to `settings.otel.endpoint`
+//! (`/v1/traces` is appended when no path is given). The export queue is bounded, spans are dropped
+//! when it is full; queued spans are flushed by `shutdown`.
+//! A W3C `traceparent` makes a span a child of the remote caller, so consumer requests and upstream fetches share one trace.
+//! Every refresh cycle is a `refresh.cycle` root span with `token.fetch`, `token.parse` and `cache.set`
+//! children; token events carry the `cache.set` context so `sink.propagate` spans join the same trace.
+//! Root spans are sampled with `sample_ratio`, children follow the decision of their parent.
+//! Without the feature no spans are exported and trace context is never propagated.
+
+#[cfg(feature = "otel")]
+use std::sync::OnceLock;
+#[cfg(feature = "otel")]
+use std::time::Duration;
+
+#[cfg(feature = "otel")]
+use opentelemetry::propagation::{Extractor, Injector, TextMapPropagator};
+#[cfg(feature = "otel")]
+use opentelemetry::trace::{TraceContextExt, TracerProvider};
+#[cfg(feature = "otel")]
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+#[cfg(feature = "otel")]
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+#[cfg(feature = "otel")]
+use opentelemetry_sdk::trace::{BatchConfigBuilder, BatchSpanProcessor, Sampler, SdkTracerProvider, TracerProviderBuilder};
+#[cfg(feature = "otel")]
+use opentelemetry_sdk::Resource;
+#[cfg(feature = "otel")]
+use tracing::warn;
+#[cfg(feature = "otel")]
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+#[cfg(feature = "otel")]
+use tracing_subscriber::filter::Targets;
+use tracing::{info_span, Span, Subscriber};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+use crate::cache::token_event::TokenEvent;
+use crate::config::settings::OtelConfig;
+
+pub const OTEL_SERVICE_NAME_DEFAULT: &str = "token-agent";
+pub const OTEL_TRACES_PATH: &str = "/v1/traces";
+pub const OTEL_EXPORT_BATCH_SIZE: usize = 512;
+pub const OTEL_EXPORT_QUEUE_SIZE: usize = 2048;
+pub const OTEL_EXPORT_INTERVAL_SECONDS: u64 = 5;
+pub const OTEL_EXPORT_TIMEOUT_SECONDS: u64 = 10;
+/// W3C trace context header name
+pub const TRACEPARENT_FIELD: &str = "traceparent";
+pub const OTEL_SAMPLE_RATIO_DEFAULT: f64 = 1.0;
+
+/// provider of the exporting layer, flushed by `shutdown`
+#[cfg(feature = "otel")]
+static TRACER_PROVIDER: OnceLock<SdkTracerProvider> = OnceLock::new();
+
+/// Layer exporting spans to the configured endpoint, None when disabled or endpoint is not set
+#[cfg(feature = "otel")]
+pub fn layer<S>(otel: Option<&OtelConfig>) -> Option<Box<dyn Layer<S> + Send + Sync>>
+where
+    S: Subscriber + for<'a> LookupSpan<'a> + Send + Sync,
+{
+    let otel = otel.filter(|otel| otel.enabled != Some(false))?;
+    let endpoint = otel.endpoint.as_ref()?;
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(get_traces_url(endpoint))
+        .with_timeout(Duration::from_secs(OTEL_EXPORT_TIMEOUT_SECONDS))
+        .build();
+    let exporter = match exporter {
+        Ok(exporter) => exporter,
+        Err(err) => {
+            // logging is not initialized yet
+            eprintln!("otel: trace export is off, exporter for '{}' can not be built: {}", endpoint, err);
+            return None;
+        }
+    };
+    let batch_config = BatchConfigBuilder::default()
+        .with_max_queue_size(OTEL_EXPORT_QUEUE_SIZE)
+        .with_max_export_batch_size(OTEL_EXPORT_BATCH_SIZE)
+        .with_scheduled_delay(Duration::from_secs(OTEL_EXPORT_INTERVAL_SECONDS))
+        .build();
+    let builder = SdkTracerProvider::builder().with_span_processor(BatchSpanProcessor::builder(exporter).with_batch_config(batch_config).build());
+    let provider = tracer_provider(builder, otel);
+    let layer = provider_layer(&provider);
+    let _ = TRACER_PROVIDER.set(provider);
+    Some(layer)
+}
+
+/// Without feature `otel` configured export only logs a warning
+#[cfg(not(feature = "otel"))]
+pub fn layer<S>(otel: Option<&OtelConfig>) -> Option<Box<dyn Layer<S> + Send + Sync>>
+where
+    S: Subscriber + for<'a> LookupSpan<'a> + Send + Sync,
+{
+    if otel.is_some_and(|otel| otel.enabled != Some(false) && otel.endpoint.is_some()) {
+        // logging is not initialized yet
+        eprintln!("otel: trace export is off, token-agent is built without feature 'otel'");
+    }
+    None
+}
+
+/// Provider with `service_name` resource and parent based `sample_ratio` sampling
+#[cfg(feature = "otel")]
+pub(crate) fn tracer_provider(builder: TracerProviderBuilder, otel: &OtelConfig) -> SdkTracerProvider {
+    let service_name = otel.service_name.clone().unwrap_or_else(|| OTEL_SERVICE_NAME_DEFAULT.to_owned());
+    let sample_ratio = otel.sample_ratio.unwrap_or(OTEL_SAMPLE_RATIO_DEFAULT).clamp(0.0, 1.0);
+    builder
+        .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(sample_ratio))))
+        .with_resource(Resource::builder().with_service_name(service_name).build())
+        .build()
+}
+
+/// Layer recording spans of this crate into `provider`, spans of dependencies (HTTP clients, exporter) are left out
+#[cfg(feature = "otel")]
+pub(crate) fn provider_layer<S>(provider: &SdkTracerProvider) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a> + Send + Sync,
+{
+    let tracer = provider.tracer(OTEL_SERVICE_NAME_DEFAULT);
+    let crate_spans = Targets::new().with_target(env!("CARGO_CRATE_NAME"), tracing::Level::TRACE);
+    Box::new(tracing_opentelemetry::layer().with_tracer(tracer).with_filter(crate_spans))
+}
+
+/// Export queued spans and stop the exporter, no-op when export is off
+pub async fn shutdown() {
+    #[cfg(feature = "otel")]
+    if let Some(provider) = TRACER_PROVIDER.get() {
+        // blocks until the export thread is done
+        let flushed = tokio::task::spawn_blocking(|| provider.shutdown()).await;
+        if let Ok(Err(err)) = flushed {
+            warn!("otel: flush on shutdown failed: {}", err);
+        }
+    }
+}
+
+/// W3C trace context of the current span, None when it is not traced
+pub fn current_traceparent() -> Option<String> {
+    #[cfg(feature = "otel")]
+    {
+        let context = Span::current().context();
+        if !context.span().span_context().is_valid() {
+            return None;
+        }
+        let mut carrier = TraceparentCarrier::default();
+        TraceContextPropagator::new().inject_context(&context, &mut carrier);
+        carrier.0
+    }
+    #[cfg(not(feature = "otel"))]
+    None
+}
+
+/// Make `span` a child of the remote `traceparent`, must be called before the span is entered.
+/// Invalid trace context is ignored
+pub fn set_remote_parent(span: &Span, traceparent: Option<&str>) {
+    #[cfg(feature = "otel")]
+    {
+        let Some(traceparent) = traceparent else {
+            return;
+        };
+        let carrier = TraceparentCarrier(Some(traceparent.trim().to_owned()));
+        let context = TraceContextPropagator::new().extract(&carrier);
+        if context.span().span_context().is_valid() {
+            let _ = span.set_parent(context);
+        }
+    }
+    #[cfg(not(feature = "otel"))]
+    let _ = (span, traceparent);
+}
+
+/// Span of a sink propagating the token of an event, child of the cache update that emitted the event
+pub fn sink_propagate_span(sink_type: &str, sink_id: &str, event: &TokenEvent) -> Span {
+    let span = info_span!(
+        "sink.propagate",
+        sink.id = sink_id,
+        sink.type = sink_type,
+        source.id = %event.source_id,
+        token.id = %event.token_id,
+    );
+    set_remote_parent(&span, event.traceparent.as_deref());
+    span
+}
+
+/// Span of a sink propagating the cached token after it lagged behind token events, there is no event to join
+pub fn sink_resync_span(sink_type: &str, sink_id: &str, source_id: &str, token_id: &str) -> Span {
+    info_span!("sink.resync", sink.id = sink_id, sink.type = sink_type, source.id = source_id, token.id = token_id)
+}
+
+/// `traceparent` header only, `tracestate` is not propagated
+#[cfg(feature = "otel")]
+#[derive(Default)]
+struct TraceparentCarrier(Option<String>);
+
+#[cfg(feature = "otel")]
+impl Injector for TraceparentCarrier {
+    fn set(&mut self, key: &str, value: String) {
+        if key == TRACEPARENT_FIELD {
+            self.0 = Some(value);
+        }
+    }
+}
+
+#[cfg(feature = "otel")]
+impl Extractor for TraceparentCarrier {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.as_deref().filter(|_| key == TRACEPARENT_FIELD)
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        vec![TRACEPARENT_FIELD]
+    }
+}
+
+/// Endpoint as is when it has a path, otherwise OTLP/HTTP traces path is appended
+pub fn get_traces_url(endpoint: &str) -> String {
+    let without_scheme = endpoint.split_once("://").map(|(_, rest)| rest).unwrap_or(endpoint);
+    if without_scheme.trim_end_matches('/').contains('/') {
+        endpoint.to_owned()
+    } else {
+        format!("{}{}", endpoint.trim_end_matches('/'), OTEL_TRACES_PATH)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn traces_url() {
+        assert_eq!(get_traces_url("http://collector:4318"), "http://collector:4318/v1/traces");
+        assert_eq!(get_traces_url("http://collector:4318/"), "http://collector:4318/v1/traces");
+        assert_eq!(get_traces_url("http://collector:4318/custom"), "http://collector:4318/custom");
+    }
+
+    #[cfg(feature = "otel")]
+    mod export {
+        use httpmock::prelude::*;
+        use opentelemetry::trace::SpanId;
+        use opentelemetry_sdk::trace::{InMemorySpanExporter, SpanData};
+        use tracing::info_span;
+        use tracing_subscriber::layer::SubscriberExt;
+
+        use super::super::*;
+
+        fn in_memory_provider(sample_ratio: f64) -> (SdkTracerProvider, InMemorySpanExporter) {
+            let exporter = InMemorySpanExporter::default();
+            let otel = OtelConfig { sample_ratio: Some(sample_ratio), ..Default::default() };
+            (tracer_provider(SdkTracerProvider::builder().with_simple_exporter(exporter.clone()), &otel), exporter)
+        }
+
+        fn find<'a>(spans: &'a [SpanData], name: &str) -> &'a SpanData {
+            spans.iter().find(|span| span.name == name).unwrap_or_else(|| panic!("span {} not found", name))
+        }
+
+        #[test]
+        fn spans_inherit_remote_and_local_parent() {
+            let (provider, exporter) = in_memory_provider(1.0);
+            let subscriber = tracing_subscriber::registry().with(provider_layer(&provider));
+            tracing::subscriber::with_default(subscriber, || {
+                let sink_span = info_span!("sink.http", sink.id = "sink");
+                set_remote_parent(&sink_span, Some("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"));
+                let _entered = sink_span.enter();
+                let fetch_span = info_span!("token.fetch", source.id = "source", http.method = "GET");
+                drop(fetch_span);
+            });
+
+            let spans = exporter.get_finished_spans().unwrap();
+            let (fetch, sink) = (find(&spans, "token.fetch"), find(&spans, "sink.http"));
+            assert_eq!(sink.span_context.trace_id().to_string(), "4bf92f3577b34da6a3ce929d0e0e4736");
+            assert_eq!(sink.parent_span_id.to_string(), "00f067aa0ba902b7");
+            assert_eq!(fetch.span_context.trace_id(), sink.span_context.trace_id());
+            assert_eq!(fetch.parent_span_id, sink.span_context.span_id());
+            assert!(fetch.attributes.iter().any(|kv| kv.key.as_str() == "source.id" && kv.value.as_str() == "source"));
+        }
+
+        #[test]
+        fn invalid_traceparent_starts_new_trace() {
+            let (provider, exporter) = in_memory_provider(1.0);
+            tracing::subscriber::with_default(tracing_subscriber::registry().with(provider_layer(&provider)), || {
+                let sink_span = info_span!("sink.http");
+                set_remote_parent(&sink_span, Some("00-00000000000000000000000000000000-00f067aa0ba902b7-01"));
+                drop(sink_span);
+            });
+            assert_eq!(find(&exporter.get_finished_spans().unwrap(), "sink.http").parent_span_id, SpanId::INVALID);
+        }
+
+        #[test]
+        fn unsampled_traces_are_not_exported() {
+            let (provider, exporter) = in_memory_provider(0.0);
+            tracing::subscriber::with_default(tracing_subscriber::registry().with(provider_layer(&provider)), || {
+                let cycle_span = info_span!("refresh.cycle");
+                let _entered = cycle_span.enter();
+                assert!(current_traceparent().unwrap().ends_with("-00"));
+                drop(info_span!("token.fetch", source.id = "source"));
+            });
+            assert!(exporter.get_finished_spans().unwrap().is_empty());
+
+            let (provider, exporter) = in_memory_provider(1.0);
+            tracing::subscriber::with_default(tracing_subscriber::registry().with(provider_layer(&provider)), || {
+                // remote parent decision is kept regardless of sample ratio
+                let sink_span = info_span!("sink.http");
+                set_remote_parent(&sink_span, Some("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00"));
+                drop(sink_span);
+                let cache_span = info_span!("cache.set");
+                let traceparent = cache_span.in_scope(current_traceparent).unwrap();
+                drop(cache_span);
+                let cache = exporter.get_finished_spans().unwrap().pop().unwrap();
+                assert_eq!(traceparent, format!("00-{}-{}-01", cache.span_context.trace_id(), cache.span_context.span_id()));
+            });
+            assert_eq!(exporter.get_finished_spans().unwrap().len(), 1);
+            assert!(current_traceparent().is_none());
+        }
+
+        #[test]
+        fn spans_of_other_crates_are_not_exported() {
+            let (provider, exporter) = in_memory_provider(1.0);
+            tracing::subscriber::with_default(tracing_subscriber::registry().with(provider_layer(&provider)), || {
+                drop(info_span!(target: "hyper::client", "connect"));
+            });
+            assert!(exporter.get_finished_spans().unwrap().is_empty());
+        }
+
+        #[tokio::test(flavor = "multi_thread")]
+        async fn layer_posts_otlp_on_shutdown() {
+            let server = MockServer::start_async().await;
+            let collector = server.mock(|when, then| {
+                when.method(POST).path(OTEL_TRACES_PATH).header("content-type", "application/x-protobuf");
+                then.status(200);
+            });
+            let otel = OtelConfig { endpoint: Some(server.base_url()), ..Default::default() };
+            let layer = layer(Some(&otel)).expect("export is on");
+            tracing::subscriber::with_default(tracing_subscriber::registry().with(layer), || {
+                drop(info_span!("token.fetch", source.id = "source"));
+            });
+            // batch is not due yet, shutdown flushes it
+            shutdown().await;
+            collector.assert_async().await;
+        }
+    }
+}
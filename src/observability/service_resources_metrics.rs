@@ -2,9 +2,11 @@ use crate::observability::metrics::get_metrics;
 use anyhow::Result;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
+use tokio::select;
 use tokio::time::sleep;
+use tokio_util::sync::CancellationToken;
 
-pub async fn collect_process_metrics(is_metrics_enabled: bool) -> Result<()> {
+pub async fn collect_process_metrics(is_metrics_enabled: bool, shutdown: CancellationToken) -> Result<()> {
     if !is_metrics_enabled {
         return Ok(());
     }
@@ -53,6 +55,9 @@ pub async fn collect_process_metrics(is_metrics_enabled: bool) -> Result<()> {
             metrics.process_uptime.set(uptime);
         }
 
-        sleep(Duration::from_secs(5)).await;
+        select! {
+            _ = shutdown.cancelled() => return Ok(()),
+            _ = sleep(Duration::from_secs(5)) => {}
+        }
     }
 }
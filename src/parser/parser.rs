@@ -1,21 +1,37 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+
 use crate::cache::token::Token;
 
-use crate::config::sources::{ExpirationSource, ExpirationSourceFormat, JwtClaims, ParseConfig, TokenField, TokenType};
+use crate::config::sources::{BodyFormat, ExpirationSource, ExpirationSourceFormat, JwtClaims, ParseConfig, TokenField, TokenIterate, TokenType};
+use crate::cache::jwks_cache::JwksCache;
 use crate::cache::token_context::TokenContext;
 use crate::helpers::time::get_token_safety_margin_seconds;
+use crate::observability::metrics::get_metrics;
+use crate::parser::transform::{apply_token_transforms, BASE64, BASE64_URL};
+use crate::sources::error::FetchError;
 use anyhow::{anyhow, Result};
 use base64::Engine;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use http::header::CONTENT_TYPE;
 use http::HeaderMap;
 use serde_json::Value;
 use tracing::{debug, error, warn};
 
 
 static HEADER_FIELD: &str = "header";
+/// Pointer of the whole body for `body_format: plain`
+pub const PLAIN_BODY_POINTER: &str = ".";
+pub const CLOCK_SKEW_SECONDS_DEFAULT: u64 = 30;
+pub const MAX_TOKEN_BYTES_DEFAULT: usize = 64 * 1024;
+
+static CLOCK_SKEW_SECONDS: AtomicU64 = AtomicU64::new(CLOCK_SKEW_SECONDS_DEFAULT);
 
 /// Parse both header and body tokens according to configuration.
 ///
 /// Returns all tokens (active + inactive stubs).
+#[tracing::instrument(name = "token.parse", skip_all, fields(tokens = parse_config.tokens.len()))]
 pub async fn parse_tokens(
     headers: HeaderMap,
     body: String,
@@ -24,26 +40,42 @@ pub async fn parse_tokens(
     safety_margin_source: Option<u64>,
 ) -> Result<Vec<TokenContext>> {
     let mut token_context_vec = Vec::with_capacity(parse_config.tokens.len());
+    let metrics = get_metrics().await;
 
-    let json_body: Option<Value> = match serde_json::from_str(&body) {
+    let body_format = get_body_format(&parse_config, &headers, &body);
+    let json_body: Option<Value> = match decode_body(&body, body_format) {
         Ok(v) => Some(v),
         Err(e) => {
-            warn!("Body is not valid JSON: {}", e);
+            warn!("Body can not be decoded: {}", e);
+            if parse_config.tokens.iter().all(|t| t.parent != HEADER_FIELD) {
+                return Err(FetchError::ParseBody { message: e.to_string() }.into());
+            }
             None
         }
     };
+    let mut first_error: Option<String> = None;
 
     // -------------------------------
     // 1. Parse HEADER tokens
     // -------------------------------
 
-    for token_field in parse_config.tokens.iter().filter(|t| t.parent == HEADER_FIELD) {
+    // tokens with linked expiration are parsed last, so linked tokens are already resolved
+    let mut token_fields: Vec<&TokenField> = parse_config.tokens.iter().collect();
+    token_fields.sort_by_key(|t| linked_token_id(t).is_some());
+
+    for token_field in token_fields.iter().copied().filter(|t| t.parent == HEADER_FIELD) {
         let safety_margin = get_token_safety_margin_seconds(safety_margin_settings, safety_margin_source);
 
-        match parse_header_token(token_field, &headers, json_body.as_ref(), safety_margin) {
-            Ok(ctx) => { token_context_vec.push(ctx); },
+        match parse_header_token(token_field, &headers, json_body.as_ref(), body_format, &token_context_vec, safety_margin) {
+            Ok(ctx) => {
+                if verify_jwt_signature(token_field, &ctx).await {
+                    token_context_vec.push(ctx);
+                }
+            },
             Err(e) => {
+                metrics.parse_failures.inc();
                 error!(id = %token_field.id, error = ?e, "header token parse failed");
+                first_error.get_or_insert_with(|| format!("{}: {}", token_field.id, e));
             }
         };
     }
@@ -52,90 +84,385 @@ pub async fn parse_tokens(
     // 2. Parse BODY tokens
     // -------------------------------
     
-    for token_field in parse_config.tokens.iter().filter(|t| t.parent == "body") {
+    for token_field in token_fields.iter().copied().filter(|t| t.parent == "body") {
         let safety_margin = get_token_safety_margin_seconds(safety_margin_settings, safety_margin_source);
 
-        match parse_body_token(token_field, json_body.as_ref(), &headers, safety_margin)
-        {
-            Ok(ctx) => {
-                token_context_vec.push(ctx);
-            },
-            Err(e) => { error!(id = %token_field.id, error = ?e, "body token parse failed"); }
+        let parsed = match &token_field.iterate {
+            Some(iterate) => {
+                parse_iterated_body_tokens(token_field, iterate, json_body.as_ref(), body_format, &headers, &token_context_vec, safety_margin)
+                    .unwrap_or_else(|e| vec![Err(e)])
+            }
+            None => vec![parse_body_token(token_field, json_body.as_ref(), body_format, &headers, &token_context_vec, safety_margin)],
         };
+        for result in parsed {
+            match result {
+                Ok(ctx) => {
+                    if verify_jwt_signature(token_field, &ctx).await {
+                        token_context_vec.push(ctx);
+                    }
+                },
+                Err(e) => {
+                    metrics.parse_failures.inc();
+                    error!(id = %token_field.id, error = ?e, "body token parse failed");
+                    first_error.get_or_insert_with(|| format!("{}: {}", token_field.id, e));
+                }
+            };
+        }
     }
 
+    // partially parsed response is kept, tokens that failed are fetched again on next refresh
+    if let (true, Some(message)) = (token_context_vec.is_empty(), first_error) {
+        return Err(FetchError::ParseToken { message }.into());
+    }
     Ok(token_context_vec)
 }
 
+/// `parse.body_format`, otherwise detected from `Content-Type`: `text/plain` bodies not starting like a JSON document
+/// are plain, `application/x-www-form-urlencoded` bodies are form, anything else is JSON
+fn get_body_format(parse_config: &ParseConfig, headers: &HeaderMap, body: &str) -> BodyFormat {
+    if let Some(body_format) = parse_config.body_format {
+        return body_format;
+    }
+    let content_type = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(|value| value.trim().to_ascii_lowercase());
+    match content_type.as_deref() {
+        // JSON served as text/plain by some issuers
+        Some("text/plain") if !body.trim_start().starts_with(['{', '[']) => BodyFormat::Plain,
+        Some("application/x-www-form-urlencoded") => BodyFormat::FormUrlencoded,
+        _ => BodyFormat::Json,
+    }
+}
+
+/// Body as JSON value: plain body is an object with the trimmed body under `.`,
+/// form body is an object of decoded string pairs, the last of repeated keys wins,
+/// XML body is an object of the root element, see `xml_element_to_json`
+fn decode_body(body: &str, body_format: BodyFormat) -> Result<Value> {
+    match body_format {
+        BodyFormat::Json => serde_json::from_str(body).map_err(|e| anyhow!("invalid JSON: {}", e)),
+        BodyFormat::Plain => {
+            let mut pairs = serde_json::Map::new();
+            // empty body has no token, reported as missing field
+            if !body.trim().is_empty() {
+                pairs.insert(PLAIN_BODY_POINTER.to_owned(), Value::String(body.trim().to_owned()));
+            }
+            Ok(Value::Object(pairs))
+        }
+        BodyFormat::FormUrlencoded => Ok(Value::Object(
+            form_urlencoded::parse(body.trim().as_bytes())
+                .map(|(key, value)| (key.into_owned(), Value::String(value.into_owned())))
+                .collect(),
+        )),
+        BodyFormat::Xml => {
+            let document = roxmltree::Document::parse(body.trim()).map_err(|e| anyhow!("invalid XML: {}", e))?;
+            let root = document.root_element();
+            let mut object = serde_json::Map::new();
+            object.insert(root.tag_name().name().to_owned(), xml_element_to_json(root));
+            Ok(Value::Object(object))
+        }
+    }
+}
+
+/// Element with child elements as object keyed by local names (namespaces dropped, first of repeated
+/// elements wins), element without children as its trimmed unescaped text
+fn xml_element_to_json(element: roxmltree::Node) -> Value {
+    let mut children = element.children().filter(|child| child.is_element()).peekable();
+    if children.peek().is_none() {
+        return Value::String(element.text().unwrap_or_default().trim().to_owned());
+    }
+    let mut object = serde_json::Map::new();
+    for child in children {
+        if !object.contains_key(child.tag_name().name()) {
+            object.insert(child.tag_name().name().to_owned(), xml_element_to_json(child));
+        }
+    }
+    Value::Object(object)
+}
+
+/// Verify JWT signature when `jwks_uri` is configured, tokens without it are accepted as is
+async fn verify_jwt_signature(token_field: &TokenField, token_context: &TokenContext) -> bool {
+    let Some(jwks_uri) = token_field.jwks_uri.as_ref().filter(|_| token_field.token_type == TokenType::Jwt) else {
+        return true;
+    };
+    match JwksCache::verify(&token_context.token.value, jwks_uri).await {
+        Ok(_) => true,
+        Err(e) => {
+            get_metrics().await.jwt_signature_failures.inc();
+            error!(id = %token_field.id, error = ?e, "jwt signature verification failed");
+            false
+        }
+    }
+}
+
 /// Handle a header-based token
 fn parse_header_token(
     token_field: &TokenField,
     headers: &HeaderMap,
     json_body: Option<&Value>,
+    body_format: BodyFormat,
+    parsed: &[TokenContext],
     safety_margin: u64,
 ) -> Result<TokenContext> {
     let token_value = get_header_value(headers, &token_field.pointer)?;
+    let token_value = apply_token_transforms(token_value, token_field.transforms.as_deref())?;
+    check_token_value(token_field, &token_value)?;
     let expiration = match token_field.token_type {
         TokenType::Jwt => get_jwt_token_expiration(&token_value)?,
-        TokenType::PlainText => {
-            let json = json_body.ok_or_else(|| anyhow!("body required for plain text token"))?;
-            get_plain_text_expiration(token_field, json, headers)?
-        }
+        TokenType::PlainText => match get_linked_token_expiration(token_field, parsed) {
+            Some(exp) => exp,
+            // manual and header expirations need no body, e.g. `204 No Content` listed in `parse.expect`
+            None => get_plain_text_expiration(token_field, json_body.unwrap_or(&Value::Null), body_format, headers)?,
+        },
     };
 
+    let claims = get_extracted_claims(token_field, &token_value)?;
+    let not_before = get_jwt_not_before(token_field, &token_value)?;
+
     Ok(TokenContext::new(
         token_field.id.clone(),
         Token::new(token_value, expiration),
         safety_margin,
-    ))
+    )
+    .with_claims(claims)
+    .with_not_before(not_before))
 }
 
 /// Handle a body-based token
 fn parse_body_token(
     token_field: &TokenField,
     json_body: Option<&Value>,
+    body_format: BodyFormat,
     headers: &HeaderMap,
+    parsed: &[TokenContext],
     safety_margin: u64,
 ) -> Result<TokenContext> {
     let json = json_body.ok_or_else(|| anyhow!("missing body for body token"))?;
-    let token_value = json[&token_field.pointer]
+    // whole plain body is the token, also for a body detected as plain with a field pointer
+    let pointer = match body_format {
+        BodyFormat::Plain => PLAIN_BODY_POINTER,
+        _ => token_field.pointer.as_str(),
+    };
+    let token_value = get_json_value(json, pointer)?
         .as_str()
-        .ok_or_else(|| anyhow!("body field '{}' not found or not a string", token_field.pointer))?
+        .ok_or_else(|| anyhow!("body field '{}' is not a string", token_field.pointer))?
         .to_owned();
+    let token_value = apply_token_transforms(token_value, token_field.transforms.as_deref())?;
+    check_token_value(token_field, &token_value)?;
 
     let expiration = match token_field.token_type {
         TokenType::Jwt => get_jwt_token_expiration(&token_value)?,
-        TokenType::PlainText => get_plain_text_expiration(token_field, json, headers)?,
+        TokenType::PlainText => match get_linked_token_expiration(token_field, parsed) {
+            Some(exp) => exp,
+            None => get_plain_text_expiration(token_field, json, body_format, headers)?,
+        },
     };
 
+    let claims = get_extracted_claims(token_field, &token_value)?;
+    let not_before = get_jwt_not_before(token_field, &token_value)?;
+
     Ok(TokenContext::new(
         token_field.id.clone(),
         Token::new(token_value, expiration),
         safety_margin,
-    ))
+    )
+    .with_claims(claims)
+    .with_not_before(not_before))
+}
+
+/// Reject values a misconfigured pointer could yield: empty, longer than `max_token_bytes`,
+/// not matching `value_regex`
+fn check_token_value(token_field: &TokenField, token_value: &str) -> Result<()> {
+    if token_value.trim().is_empty() {
+        return Err(anyhow!("token value is empty"));
+    }
+    let max_token_bytes = token_field.max_token_bytes.unwrap_or(MAX_TOKEN_BYTES_DEFAULT);
+    if token_value.len() > max_token_bytes {
+        return Err(anyhow!("token value is {} bytes, max_token_bytes is {}", token_value.len(), max_token_bytes));
+    }
+    if let Some(value_regex) = &token_field.value_regex {
+        let re = Regex::new(value_regex).map_err(|e| anyhow!("invalid value_regex: {}", e))?;
+        if !re.is_match(token_value) {
+            return Err(anyhow!("token value does not match value_regex '{}'", value_regex));
+        }
+    }
+    Ok(())
 }
 
-fn decode_jwt_from_string(token_string: &str) -> Result<JwtClaims> {
+/// Handle a body array of tokens, one result per element: elements without id or value fail alone,
+/// the first element wins for duplicate ids; empty array yields no tokens
+fn parse_iterated_body_tokens(
+    token_field: &TokenField,
+    iterate: &TokenIterate,
+    json_body: Option<&Value>,
+    body_format: BodyFormat,
+    headers: &HeaderMap,
+    parsed: &[TokenContext],
+    safety_margin: u64,
+) -> Result<Vec<Result<TokenContext>>> {
+    let json = json_body.ok_or_else(|| anyhow!("missing body for body token"))?;
+    let elements = get_json_value(json, &token_field.pointer)?
+        .as_array()
+        .ok_or_else(|| anyhow!("body field '{}' is not an array", token_field.pointer))?;
+    if elements.is_empty() {
+        warn!(id = %token_field.id, pointer = %token_field.pointer, "token array is empty");
+    }
+
+    // expiration block shared by all elements
+    let shared_expiration = match (&token_field.token_type, &iterate.exp_from) {
+        (TokenType::PlainText, None) => Some(match get_linked_token_expiration(token_field, parsed) {
+            Some(exp) => exp,
+            None => get_plain_text_expiration(token_field, json, body_format, headers)?,
+        }),
+        _ => None,
+    };
+
+    let mut ids = HashSet::with_capacity(elements.len());
+    Ok(elements
+        .iter()
+        .enumerate()
+        .map(|(index, element)| {
+            parse_iterated_element(token_field, iterate, element, &mut ids, shared_expiration, safety_margin)
+                .map_err(|e| anyhow!("element {}: {}", index, e))
+        })
+        .collect())
+}
+
+fn parse_iterated_element(
+    token_field: &TokenField,
+    iterate: &TokenIterate,
+    element: &Value,
+    ids: &mut HashSet<String>,
+    shared_expiration: Option<u64>,
+    safety_margin: u64,
+) -> Result<TokenContext> {
+    let name = match get_json_value(element, &iterate.id_from)? {
+        Value::String(name) if !name.trim().is_empty() => name.to_owned(),
+        Value::Number(name) => name.to_string(),
+        _ => return Err(anyhow!("body field '{}' is not a token name", iterate.id_from)),
+    };
+    let id = format!("{}.{}", token_field.id, name);
+    if !ids.insert(id.clone()) {
+        return Err(anyhow!("duplicate token id '{}', first element is kept", id));
+    }
+    let token_value = get_json_value(element, &iterate.value_from)?
+        .as_str()
+        .ok_or_else(|| anyhow!("body field '{}' is not a string", iterate.value_from))?
+        .to_owned();
+    let token_value = apply_token_transforms(token_value, token_field.transforms.as_deref())?;
+    check_token_value(token_field, &token_value)?;
+
+    let expiration = match (&token_field.token_type, &iterate.exp_from, shared_expiration) {
+        (TokenType::Jwt, _, _) => get_jwt_token_expiration(&token_value)?,
+        (TokenType::PlainText, Some(exp_from), _) => get_element_expiration(element, exp_from, &iterate.exp_format)?,
+        (TokenType::PlainText, None, exp) => exp.ok_or_else(|| anyhow!("expiration required for plain_text"))?,
+    };
+
+    let claims = get_extracted_claims(token_field, &token_value)?;
+    let not_before = get_jwt_not_before(token_field, &token_value)?;
+
+    Ok(TokenContext::new(id, Token::new(token_value, expiration), safety_margin)
+        .with_claims(claims)
+        .with_not_before(not_before))
+}
+
+/// Expiration read from an array element by `iterate.exp_from`
+fn get_element_expiration(element: &Value, exp_from: &str, format: &ExpirationSourceFormat) -> Result<u64> {
+    let value = get_json_value(element, exp_from)?;
+    match format {
+        ExpirationSourceFormat::Rfc3339 => value
+            .as_str()
+            .ok_or_else(|| anyhow!("body field '{}' is not a string", exp_from))
+            .and_then(parse_rfc3339_expiration),
+        ExpirationSourceFormat::Unix => value.as_u64().ok_or_else(|| anyhow!("body field '{}' is not u64", exp_from)),
+        ExpirationSourceFormat::Seconds => value
+            .as_u64()
+            .map(|seconds| Utc::now().timestamp() as u64 + seconds)
+            .ok_or_else(|| anyhow!("body field '{}' is not u64", exp_from)),
+    }
+}
+
+fn linked_token_id(token_field: &TokenField) -> Option<&str> {
+    token_field
+        .expiration
+        .as_ref()
+        .filter(|exp| matches!(exp.source, ExpirationSource::JsonBodyField | ExpirationSource::HeaderField))
+        .and_then(|exp| exp.linked_token_id.as_deref())
+}
+
+/// Expiration of linked token: the expiration pointer belongs to it, this token shares its `exp_unix_ts`.
+/// None if token is not linked or linked token was not parsed, then the pointer is read directly
+fn get_linked_token_expiration(token_field: &TokenField, parsed: &[TokenContext]) -> Option<u64> {
+    let linked_id = linked_token_id(token_field)?;
+    let linked = parsed.iter().find(|token_context| token_context.id == linked_id);
+    if linked.is_none() {
+        warn!(id = %token_field.id, linked_id = %linked_id, "linked token not parsed, reading expiration pointer");
+    }
+    linked.map(|token_context| token_context.token.exp_unix_ts)
+}
+
+/// Payload of a signed JWT: base64url as in RFC 7515, standard alphabet accepted too, padding optional
+fn decode_jwt_payload(token_string: &str) -> Result<Vec<u8>> {
     let parts: Vec<&str> = token_string.split('.').collect();
-    if parts.len() != 3 {
-        return Err(anyhow!("invalid JWT format"));
+    match parts.len() {
+        3 => {}
+        // JWE: header.encrypted_key.iv.ciphertext.tag, claims are not readable
+        n if n > 3 => {
+            return Err(anyhow!(
+                "encrypted token, cannot extract exp; configure token_type plain_text with a manual expiration"
+            ))
+        }
+        _ => return Err(anyhow!("invalid JWT format")),
     }
 
     let payload = parts[1];
-    let decoded = base64::engine::general_purpose::STANDARD_NO_PAD
+    BASE64_URL
         .decode(payload)
-        .map_err(|e| anyhow!("base64 decode error: {}", e))?;
+        .or_else(|_| BASE64.decode(payload))
+        .map_err(|e| anyhow!("base64 decode error: {}", e))
+}
 
-    serde_json::from_slice::<JwtClaims>(&decoded)
+pub(crate) fn decode_jwt_from_string(token_string: &str) -> Result<JwtClaims> {
+    serde_json::from_slice::<JwtClaims>(&decode_jwt_payload(token_string)?)
         .map_err(|e| anyhow!("invalid JWT payload: {}", e))
 }
 
-fn get_jwt_token_expiration(token_value: &str) -> Result<u64> {
+/// JWT payload claims listed in `extract_claims`, claims absent in the payload are skipped
+fn get_extracted_claims(token_field: &TokenField, token_value: &str) -> Result<Option<HashMap<String, Value>>> {
+    let Some(claim_names) = token_field.extract_claims.as_ref().filter(|_| token_field.token_type == TokenType::Jwt) else {
+        return Ok(None);
+    };
+    let mut payload: HashMap<String, Value> = serde_json::from_slice(&decode_jwt_payload(token_value)?)
+        .map_err(|e| anyhow!("invalid JWT payload: {}", e))?;
+    let mut claims = HashMap::with_capacity(claim_names.len());
+    for claim_name in claim_names {
+        match payload.remove(claim_name) {
+            Some(value) => {
+                claims.insert(claim_name.to_owned(), value);
+            }
+            None => warn!(id = %token_field.id, claim = %claim_name, "jwt claim is absent"),
+        }
+    }
+    Ok(Some(claims))
+}
+
+/// Apply `settings.clock_skew_seconds`, default is used when not set
+pub fn set_clock_skew_seconds(clock_skew_seconds: Option<u64>) {
+    CLOCK_SKEW_SECONDS.store(clock_skew_seconds.unwrap_or(CLOCK_SKEW_SECONDS_DEFAULT), Ordering::Relaxed);
+}
+
+/// JWT `exp`, accepted up to clock skew in the past; `iat` in the future is logged with the measured skew
+pub(crate) fn get_jwt_token_expiration(token_value: &str) -> Result<u64> {
     let claims = decode_jwt_from_string(token_value)?;
     let exp = claims.exp;
     let now = Utc::now().timestamp() as u64;
+    let clock_skew = CLOCK_SKEW_SECONDS.load(Ordering::Relaxed);
 
-    if exp <= now {
+    if let Some(iat) = claims.iat.filter(|iat| *iat > now) {
+        warn!(issued_at = iat, skew_seconds = iat - now, "jwt issued in the future, issuer clock is ahead");
+    }
+    if exp.saturating_add(clock_skew) <= now {
         Err(anyhow!("JWT expired at {}", exp))
     } else {
         debug!(expires_at = exp, "jwt parsed successfully");
@@ -143,9 +470,27 @@ fn get_jwt_token_expiration(token_value: &str) -> Result<u64> {
     }
 }
 
+/// Start of validity of a JWT with `nbf` beyond clock skew in the future, None for valid and non-JWT tokens
+fn get_jwt_not_before(token_field: &TokenField, token_value: &str) -> Result<Option<u64>> {
+    if token_field.token_type != TokenType::Jwt {
+        return Ok(None);
+    }
+    let now = Utc::now().timestamp() as u64;
+    let clock_skew = CLOCK_SKEW_SECONDS.load(Ordering::Relaxed);
+    let not_before = decode_jwt_from_string(token_value)?
+        .nbf
+        .map(|nbf| nbf.saturating_sub(clock_skew))
+        .filter(|not_before| *not_before > now);
+    if let Some(not_before) = not_before {
+        warn!(id = %token_field.id, not_before = not_before, "jwt is not valid yet, stored as not active");
+    }
+    Ok(not_before)
+}
+
 fn get_plain_text_expiration(
     token_field: &TokenField,
     json_body: &Value,
+    body_format: BodyFormat,
     headers: &HeaderMap,
 ) -> Result<u64> {
     let exp_cfg = token_field
@@ -161,17 +506,33 @@ fn get_plain_text_expiration(
             let pointer = exp_cfg
                 .pointer
                 .ok_or_else(|| anyhow!("expiration.pointer required"))?;
-            json_body[&pointer]
-                .as_u64()
-                .ok_or_else(|| anyhow!("body {} not found or not u64, ", &pointer))
+            let value = get_json_value(json_body, &pointer)?;
+            match exp_cfg.format {
+                ExpirationSourceFormat::Rfc3339 => value
+                    .as_str()
+                    .ok_or_else(|| anyhow!("body field '{}' is not a string", &pointer))
+                    .and_then(parse_rfc3339_expiration),
+                // form and XML values are always strings
+                _ if matches!(body_format, BodyFormat::FormUrlencoded | BodyFormat::Xml) => value
+                    .as_str()
+                    .and_then(|v| v.trim().parse::<u64>().ok())
+                    .ok_or_else(|| anyhow!("body field '{}' is not u64", &pointer)),
+                _ => value
+                    .as_u64()
+                    .ok_or_else(|| anyhow!("body field '{}' is not u64", &pointer)),
+            }
         }
         ExpirationSource::HeaderField => {
             let key = exp_cfg
                 .pointer
                 .ok_or_else(|| anyhow!("expiration.pointer required"))?;
             let val = get_header_value(headers, &key)?;
-            val.parse::<u64>()
-                .map_err(|e| anyhow!("invalid header value '{}': {}", key, e))
+            match exp_cfg.format {
+                ExpirationSourceFormat::Rfc3339 => parse_rfc3339_expiration(&val),
+                _ => val
+                    .parse::<u64>()
+                    .map_err(|e| anyhow!("invalid header value '{}': {}", key, e)),
+            }
         }
         ExpirationSource::Manual => {
              exp_cfg.manual_ttl_seconds.ok_or_else(|| {
@@ -186,11 +547,75 @@ fn get_plain_text_expiration(
         match exp_cfg.format {
             ExpirationSourceFormat::Seconds => Utc::now().timestamp() as u64 + exp_row_value,
             ExpirationSourceFormat::Unix => exp_row_value,
+            // already converted to unix timestamp
+            ExpirationSourceFormat::Rfc3339 => exp_row_value,
         }
     })
 
 }
 
+/// Convert RFC3339 datetime (fractional seconds and any offset allowed) to unix timestamp
+fn parse_rfc3339_expiration(value: &str) -> Result<u64> {
+    let exp = DateTime::parse_from_rfc3339(value.trim())
+        .map_err(|e| anyhow!("invalid rfc3339 expiration '{}': {}", value, e))?
+        .timestamp();
+    u64::try_from(exp).map_err(|_| anyhow!("rfc3339 expiration '{}' is before unix epoch", value))
+}
+
+/// Resolve body field by RFC 6901 JSON pointer (`/Credentials/SessionToken`, `/items/0/token`),
+/// by plain top-level field name (`access_token`) or by dot path (`credentials.access.token`, `items.0.token`);
+/// a top-level field named with dots wins over the dot path
+pub(crate) fn get_json_value<'a>(json: &'a Value, pointer: &str) -> Result<&'a Value> {
+    let value = if pointer.starts_with('/') {
+        json.pointer(pointer)
+    } else {
+        json.get(pointer).or_else(|| get_json_value_by_dot_path(json, pointer))
+    };
+    value.ok_or_else(|| anyhow!("body field '{}' not found", pointer))
+}
+
+/// Traverse `a.b.0.c`: object keys by name, array items by index
+fn get_json_value_by_dot_path<'a>(json: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(json, |value, key| match value {
+        Value::Array(items) => key.parse::<usize>().ok().and_then(|index| items.get(index)),
+        _ => value.get(key),
+    })
+}
+
+/// Dot path that may also be read as a top-level field name, e.g. `credentials.token`
+pub fn is_ambiguous_json_pointer(pointer: &str) -> bool {
+    !pointer.starts_with('/') && pointer.contains('.')
+}
+
+/// Check XML element path syntax: `/Root/Child/Leaf` or dotted `Root.Child.Leaf`, segments are element names
+/// without prefixes, predicates, attributes or wildcards; names containing '.' need the '/' form
+pub fn is_valid_xml_path(pointer: &str) -> bool {
+    let (segments, separator) = match pointer.strip_prefix('/') {
+        Some(path) => (path, '/'),
+        None => (pointer, '.'),
+    };
+    segments.split(separator).all(|segment| {
+        let mut chars = segment.chars();
+        chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+            && chars.all(|c| c.is_alphanumeric() || matches!(c, '_' | '-') || (c == '.' && separator == '/'))
+    })
+}
+
+/// Check JSON pointer syntax: plain field names are accepted as is,
+/// pointers starting with '/' may only use '~0' and '~1' escapes
+pub fn is_valid_json_pointer(pointer: &str) -> bool {
+    if !pointer.starts_with('/') {
+        return !pointer.trim().is_empty();
+    }
+    let mut chars = pointer.chars();
+    while let Some(c) = chars.next() {
+        if c == '~' && !matches!(chars.next(), Some('0') | Some('1')) {
+            return false;
+        }
+    }
+    true
+}
+
 fn get_header_value(headers: &HeaderMap, key: &str) -> Result<String> {
     headers
         .get(key)
@@ -208,8 +633,10 @@ mod tests {
     use base64::{engine::general_purpose::STANDARD_NO_PAD, Engine};
     use chrono::{Utc};
     use http::{HeaderMap, HeaderName, HeaderValue};
-    use serde_json::json;
-    use crate::parser::parser::{ParseConfig, parse_tokens};
+    use serde_json::{json, Value};
+    use crate::observability::metrics::get_metrics;
+    use crate::parser::parser::{ParseConfig, parse_tokens, CLOCK_SKEW_SECONDS_DEFAULT, HEADER_FIELD, MAX_TOKEN_BYTES_DEFAULT};
+    use crate::sources::error::FetchError;
 
     fn sample_jwt(exp: u64) -> String {
         // minimal unsigned JWT for tests: {"exp": exp}
@@ -232,6 +659,8 @@ mod tests {
     fn make_parse_config() -> ParseConfig {
         use crate::config::sources::*;
         ParseConfig {
+            expect: None,
+            body_format: None,
             tokens: vec![
                 // JWT from body
                 TokenField {
@@ -239,6 +668,12 @@ mod tests {
                     parent: "body".into(),
                     pointer: "jwt_token".into(),
                     token_type: TokenType::Jwt,
+                    jwks_uri: None,
+                    transforms: None,
+                    extract_claims: None,
+                    iterate: None,
+                    max_token_bytes: None,
+                    value_regex: None,
                     expiration: None,
                 },
                 // JWT from header
@@ -247,6 +682,12 @@ mod tests {
                     parent: "header".into(),
                     pointer: "x-jwt".into(),
                     token_type: TokenType::Jwt,
+                    jwks_uri: None,
+                    transforms: None,
+                    extract_claims: None,
+                    iterate: None,
+                    max_token_bytes: None,
+                    value_regex: None,
                     expiration: None,
                 },
                 // Plain text with manual TTL
@@ -255,6 +696,12 @@ mod tests {
                     parent: "body".into(),
                     pointer: "plain_token".into(),
                     token_type: TokenType::PlainText,
+                    jwks_uri: None,
+                    transforms: None,
+                    extract_claims: None,
+                    iterate: None,
+                    max_token_bytes: None,
+                    value_regex: None,
                     expiration: Some(Expiration {
                         source: ExpirationSource::Manual,
                         format: ExpirationSourceFormat::Seconds,
@@ -269,6 +716,12 @@ mod tests {
                     parent: "body".into(),
                     pointer: "plain_json_token".into(),
                     token_type: TokenType::PlainText,
+                    jwks_uri: None,
+                    transforms: None,
+                    extract_claims: None,
+                    iterate: None,
+                    max_token_bytes: None,
+                    value_regex: None,
                     expiration: Some(Expiration {
                         source: ExpirationSource::JsonBodyField,
                         format: ExpirationSourceFormat::Unix,
@@ -283,6 +736,12 @@ mod tests {
                     parent: "header".into(),
                     pointer: "x-plain".into(),
                     token_type: TokenType::PlainText,
+                    jwks_uri: None,
+                    transforms: None,
+                    extract_claims: None,
+                    iterate: None,
+                    max_token_bytes: None,
+                    value_regex: None,
                     expiration: Some(Expiration {
                         source: ExpirationSource::HeaderField,
                         format: ExpirationSourceFormat::Unix,
@@ -378,6 +837,122 @@ mod tests {
         assert_eq!(header_token_opt.is_none(), true);
     }
 
+    fn make_nested_parse_config() -> ParseConfig {
+        use crate::config::sources::*;
+        ParseConfig {
+            expect: None,
+            body_format: None,
+            tokens: vec![
+                TokenField {
+                    id: "session_token".into(),
+                    parent: "body".into(),
+                    pointer: "/Credentials/SessionToken".into(),
+                    token_type: TokenType::PlainText,
+                    jwks_uri: None,
+                    transforms: None,
+                    extract_claims: None,
+                    iterate: None,
+                    max_token_bytes: None,
+                    value_regex: None,
+                    expiration: Some(Expiration {
+                        source: ExpirationSource::JsonBodyField,
+                        format: ExpirationSourceFormat::Unix,
+                        manual_ttl_seconds: None,
+                        pointer: Some("/Credentials/Expiration".into()),
+                        linked_token_id: None
+                    }),
+                },
+                TokenField {
+                    id: "array_token".into(),
+                    parent: "body".into(),
+                    pointer: "/items/0/token".into(),
+                    token_type: TokenType::PlainText,
+                    jwks_uri: None,
+                    transforms: None,
+                    extract_claims: None,
+                    iterate: None,
+                    max_token_bytes: None,
+                    value_regex: None,
+                    expiration: Some(Expiration {
+                        source: ExpirationSource::Manual,
+                        format: ExpirationSourceFormat::Seconds,
+                        manual_ttl_seconds: Some(60),
+                        pointer: None,
+                        linked_token_id: None
+                    }),
+                },
+            ],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_json_pointer_nested_object_and_array() {
+        let now = Utc::now().timestamp() as u64;
+        let body = json!({
+            "Credentials": { "SessionToken": "nested-abc", "Expiration": now + 100 },
+            "items": [ { "token": "first" }, { "token": "second" } ]
+        })
+        .to_string();
+
+        let tokens = parse_tokens(HeaderMap::new(), body, make_nested_parse_config(), None, None).await.unwrap();
+
+        let nested = tokens.iter().find(|t| t.id == "session_token").unwrap();
+        assert_eq!(nested.token.value, "nested-abc");
+        assert_eq!(nested.token.exp_unix_ts, now + 100);
+        let array = tokens.iter().find(|t| t.id == "array_token").unwrap();
+        assert_eq!(array.token.value, "first");
+    }
+
+    #[tokio::test]
+    async fn test_json_pointer_missing_nested_path() {
+        let body = json!({ "Credentials": {} }).to_string();
+
+        let err = parse_tokens(HeaderMap::new(), body, make_nested_parse_config(), None, None).await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<FetchError>(), Some(FetchError::ParseToken { .. })), "{}", err);
+
+        let json = json!({ "Credentials": {} });
+        let err = super::get_json_value(&json, "/Credentials/SessionToken").unwrap_err();
+        assert_eq!(err.to_string(), "body field '/Credentials/SessionToken' not found");
+    }
+
+    #[test]
+    fn test_dot_path_nested_object_array_and_missing() {
+        use super::{get_json_value, is_ambiguous_json_pointer};
+        let json = json!({
+            "credentials": { "access": { "token": "deep" } },
+            "items": [ { "token": "first" }, { "token": "second" } ],
+            "dotted.key": "top-level",
+            "dotted": { "key": "nested" }
+        });
+        assert_eq!(get_json_value(&json, "credentials.access.token").unwrap(), "deep");
+        assert_eq!(get_json_value(&json, "items.1.token").unwrap(), "second");
+        // same paths as JSON pointer
+        assert_eq!(get_json_value(&json, "/credentials/access/token").unwrap(), "deep");
+        assert_eq!(get_json_value(&json, "/items/1/token").unwrap(), "second");
+        // top-level field with dots wins
+        assert_eq!(get_json_value(&json, "dotted.key").unwrap(), "top-level");
+        assert_eq!(get_json_value(&json, "/dotted/key").unwrap(), "nested");
+
+        for missing in ["credentials.refresh.token", "items.5.token", "items.first.token", "credentials.access.token.value"] {
+            let err = get_json_value(&json, missing).unwrap_err();
+            assert_eq!(err.to_string(), format!("body field '{}' not found", missing));
+        }
+        assert!(is_ambiguous_json_pointer("credentials.access.token"));
+        assert!(!is_ambiguous_json_pointer("/credentials/access.token"));
+        assert!(!is_ambiguous_json_pointer("access_token"));
+    }
+
+    #[test]
+    fn test_json_pointer_syntax() {
+        use super::is_valid_json_pointer;
+        assert!(is_valid_json_pointer("access_token"));
+        assert!(is_valid_json_pointer("/Credentials/SessionToken"));
+        assert!(is_valid_json_pointer("/a~1b/c~0d"));
+        assert!(!is_valid_json_pointer("/a~2b"));
+        assert!(!is_valid_json_pointer("/a~"));
+        assert!(!is_valid_json_pointer(" "));
+    }
+
     #[tokio::test]
     async fn test_invalid_json_body() {
         let headers = make_headers(&[("x-jwt", sample_jwt(Utc::now().timestamp() as u64 + 60).as_str())]);
@@ -388,5 +963,725 @@ mod tests {
         let jwt_header = tokens.iter().find(|t| t.id == "jwt_header").unwrap();
         assert_eq!(jwt_header.should_remove(), false);
     }
-}
 
+    fn make_rfc3339_parse_config() -> ParseConfig {
+        use crate::config::sources::*;
+        ParseConfig {
+            expect: None,
+            body_format: None,
+            tokens: vec![
+                TokenField {
+                    id: "rfc3339_body".into(),
+                    parent: "body".into(),
+                    pointer: "/Credentials/SessionToken".into(),
+                    token_type: TokenType::PlainText,
+                    jwks_uri: None,
+                    transforms: None,
+                    extract_claims: None,
+                    iterate: None,
+                    max_token_bytes: None,
+                    value_regex: None,
+                    expiration: Some(Expiration {
+                        source: ExpirationSource::JsonBodyField,
+                        format: ExpirationSourceFormat::Rfc3339,
+                        manual_ttl_seconds: None,
+                        pointer: Some("/Credentials/Expiration".into()),
+                        linked_token_id: None
+                    }),
+                },
+                TokenField {
+                    id: "rfc3339_header".into(),
+                    parent: "header".into(),
+                    pointer: "x-token".into(),
+                    token_type: TokenType::PlainText,
+                    jwks_uri: None,
+                    transforms: None,
+                    extract_claims: None,
+                    iterate: None,
+                    max_token_bytes: None,
+                    value_regex: None,
+                    expiration: Some(Expiration {
+                        source: ExpirationSource::HeaderField,
+                        format: ExpirationSourceFormat::Rfc3339,
+                        manual_ttl_seconds: None,
+                        pointer: Some("x-expires-on".into()),
+                        linked_token_id: None
+                    }),
+                },
+            ],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_rfc3339_body_and_header_expiration() {
+        let headers = make_headers(&[
+            ("x-token", "hdr"),
+            // offset other than Z
+            ("x-expires-on", "2099-01-01T12:00:00+02:00"),
+        ]);
+        let body = json!({
+            // fractional seconds
+            "Credentials": { "SessionToken": "sts", "Expiration": "2099-01-01T10:00:00.123456Z" }
+        })
+        .to_string();
+
+        let tokens = parse_tokens(headers, body, make_rfc3339_parse_config(), None, None).await.unwrap();
+
+        let body_token = tokens.iter().find(|t| t.id == "rfc3339_body").unwrap();
+        assert_eq!(body_token.token.exp_unix_ts, 4070944800);
+        let header_token = tokens.iter().find(|t| t.id == "rfc3339_header").unwrap();
+        assert_eq!(header_token.token.exp_unix_ts, 4070944800);
+    }
+
+    #[tokio::test]
+    async fn test_rfc3339_invalid_expiration() {
+        let headers = make_headers(&[("x-token", "hdr"), ("x-expires-on", "01/01/2099 10:00")]);
+        let body = json!({
+            "Credentials": { "SessionToken": "sts", "Expiration": 4070944800u64 }
+        })
+        .to_string();
+
+        let parse_failures = crate::observability::metrics::get_metrics().await.parse_failures.get();
+        let err = parse_tokens(headers, body, make_rfc3339_parse_config(), None, None).await.unwrap_err();
+
+        assert!(matches!(err.downcast_ref::<FetchError>(), Some(FetchError::ParseToken { .. })), "{}", err);
+        assert!(crate::observability::metrics::get_metrics().await.parse_failures.get() >= parse_failures + 2);
+        assert!(super::parse_rfc3339_expiration("2025-10-07T10:00:00").is_err());
+    }
+
+    fn linked_token_field(id: &str, pointer: &str, linked_token_id: &str) -> crate::config::sources::TokenField {
+        use crate::config::sources::*;
+        TokenField {
+            id: id.into(),
+            parent: "body".into(),
+            pointer: pointer.into(),
+            token_type: TokenType::PlainText,
+            jwks_uri: None,
+            transforms: None,
+            extract_claims: None,
+            iterate: None,
+            max_token_bytes: None,
+            value_regex: None,
+            expiration: Some(Expiration {
+                source: ExpirationSource::JsonBodyField,
+                format: ExpirationSourceFormat::Seconds,
+                manual_ttl_seconds: None,
+                pointer: Some("expires_in".into()),
+                linked_token_id: Some(linked_token_id.into()),
+            }),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_linked_token_inherits_expiration() {
+        use crate::config::sources::*;
+        let now = Utc::now().timestamp() as u64;
+        let config = ParseConfig {
+            expect: None,
+            body_format: None,
+            tokens: vec![
+                // linked token listed first, resolved after the token it links to
+                linked_token_field("refresh_token", "refresh_token", "access_token"),
+                TokenField {
+                    id: "access_token".into(),
+                    parent: "header".into(),
+                    pointer: "x-access-token".into(),
+                    token_type: TokenType::Jwt,
+                    jwks_uri: None,
+                    transforms: None,
+                    extract_claims: None,
+                    iterate: None,
+                    max_token_bytes: None,
+                    value_regex: None,
+                    expiration: None,
+                },
+            ],
+        };
+        let access_token = sample_jwt(now + 600);
+        let headers = make_headers(&[("x-access-token", access_token.as_str())]);
+        // expires_in belongs to access token, differs from jwt exp to show which one is used
+        let body = json!({ "refresh_token": "refresh", "expires_in": 60 }).to_string();
+
+        let tokens = parse_tokens(headers, body, config, None, None).await.unwrap();
+
+        let refresh = tokens.iter().find(|t| t.id == "refresh_token").unwrap();
+        assert_eq!(refresh.token.exp_unix_ts, now + 600);
+    }
+
+    #[tokio::test]
+    async fn test_linked_token_falls_back_to_pointer() {
+        let now = Utc::now().timestamp() as u64;
+        let config = ParseConfig { expect: None, body_format: None, tokens: vec![linked_token_field("refresh_token", "refresh_token", "missing")] };
+        let body = json!({ "refresh_token": "refresh", "expires_in": 60 }).to_string();
+
+        let tokens = parse_tokens(HeaderMap::new(), body, config, None, None).await.unwrap();
+
+        let refresh = tokens.iter().find(|t| t.id == "refresh_token").unwrap();
+        assert!(refresh.token.exp_unix_ts >= now + 60 && refresh.token.exp_unix_ts <= now + 61);
+    }
+
+    #[tokio::test]
+    async fn test_transforms_applied_before_jwt_expiration() {
+        use crate::config::sources::*;
+        let now = Utc::now().timestamp() as u64;
+        let jwt = sample_jwt(now + 600);
+        let config = ParseConfig {
+            expect: None,
+            body_format: None,
+            tokens: vec![TokenField {
+                id: "access_token".into(),
+                parent: "body".into(),
+                pointer: "access_token".into(),
+                token_type: TokenType::Jwt,
+                jwks_uri: None,
+                transforms: Some(vec![TokenTransform::Trim, TokenTransform::StripPrefix("Bearer ".into())]),
+                extract_claims: None,
+                iterate: None,
+                max_token_bytes: None,
+                value_regex: None,
+                expiration: None,
+            }],
+        };
+        let body = json!({ "access_token": format!(" Bearer {} ", jwt) }).to_string();
+
+        let tokens = parse_tokens(HeaderMap::new(), body, config, None, None).await.unwrap();
+
+        assert_eq!(tokens[0].token.value, jwt);
+        assert_eq!(tokens[0].token.exp_unix_ts, now + 600);
+    }
+
+    #[tokio::test]
+    async fn test_listed_jwt_claims_extracted() {
+        use crate::config::sources::*;
+        let now = Utc::now().timestamp() as u64;
+        let header = STANDARD_NO_PAD.encode(r#"{"alg":"none"}"#);
+        let payload = STANDARD_NO_PAD.encode(json!({ "exp": now + 600, "scope": "read write", "tenant": 42, "sub": "svc" }).to_string());
+        let jwt = format!("{}.{}.", header, payload);
+        let config = ParseConfig {
+            expect: None,
+            body_format: None,
+            tokens: vec![TokenField {
+                id: "access_token".into(),
+                parent: "body".into(),
+                pointer: "access_token".into(),
+                token_type: TokenType::Jwt,
+                jwks_uri: None,
+                transforms: None,
+                extract_claims: Some(vec!["scope".into(), "tenant".into(), "missing".into()]),
+                iterate: None,
+                max_token_bytes: None,
+                value_regex: None,
+                expiration: None,
+            }],
+        };
+        let body = json!({ "access_token": jwt }).to_string();
+
+        let tokens = parse_tokens(HeaderMap::new(), body, config, None, None).await.unwrap();
+
+        let claims = tokens[0].claims.as_ref().unwrap();
+        assert_eq!(claims.len(), 2);
+        assert_eq!(claims["scope"], json!("read write"));
+        assert_eq!(claims["tenant"], json!(42));
+        assert_eq!(tokens[0].token.exp_unix_ts, now + 600);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_json_body_without_header_tokens_is_parse_body_error() {
+        let err = parse_tokens(HeaderMap::new(), "<html>".to_string(), make_nested_parse_config(), None, None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err.downcast_ref::<FetchError>(), Some(FetchError::ParseBody { .. })), "{}", err);
+    }
+
+    fn body_format_token_field(pointer: &str, expiration: crate::config::sources::Expiration) -> crate::config::sources::TokenField {
+        use crate::config::sources::*;
+        TokenField {
+            id: "token".into(),
+            parent: "body".into(),
+            pointer: pointer.into(),
+            token_type: TokenType::PlainText,
+            jwks_uri: None,
+            transforms: None,
+            extract_claims: None,
+            iterate: None,
+            max_token_bytes: None,
+            value_regex: None,
+            expiration: Some(expiration),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_body_formats_json_plain_and_form() {
+        use crate::config::sources::*;
+        let now = Utc::now().timestamp() as u64;
+        let manual = Expiration {
+            source: ExpirationSource::Manual,
+            format: ExpirationSourceFormat::Seconds,
+            manual_ttl_seconds: Some(60),
+            pointer: None,
+            linked_token_id: None,
+        };
+        let expires_in = Expiration {
+            source: ExpirationSource::JsonBodyField,
+            format: ExpirationSourceFormat::Seconds,
+            manual_ttl_seconds: None,
+            pointer: Some("expires_in".into()),
+            linked_token_id: None,
+        };
+
+        // json stays the default
+        let config = ParseConfig { expect: None, body_format: Some(BodyFormat::Json), tokens: vec![body_format_token_field("access_token", expires_in.clone())] };
+        let body = json!({ "access_token": "json-token", "expires_in": 60 }).to_string();
+        let tokens = parse_tokens(HeaderMap::new(), body, config, None, None).await.unwrap();
+        assert_eq!(tokens[0].token.value, "json-token");
+        assert!(tokens[0].token.exp_unix_ts >= now + 60 && tokens[0].token.exp_unix_ts <= now + 61);
+
+        // whole body with trailing newline, e.g. IMDSv1
+        let config = ParseConfig { expect: None, body_format: Some(BodyFormat::Plain), tokens: vec![body_format_token_field(".", manual.clone())] };
+        let tokens = parse_tokens(HeaderMap::new(), "AQoDYXdzEJr...token\n".to_string(), config.clone(), None, None).await.unwrap();
+        assert_eq!(tokens[0].token.value, "AQoDYXdzEJr...token");
+        let err = parse_tokens(HeaderMap::new(), " \n".to_string(), config, None, None).await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<FetchError>(), Some(FetchError::ParseToken { .. })), "{}", err);
+
+        // url-escaped pairs, expiration read from a string value
+        let config = ParseConfig { expect: None, body_format: Some(BodyFormat::FormUrlencoded), tokens: vec![body_format_token_field("access_token", expires_in)] };
+        let body = "access_token=a%2Bb%2Fc%3D%3D+d&expires_in=60&scope=read%20write\n".to_string();
+        let tokens = parse_tokens(HeaderMap::new(), body, config.clone(), None, None).await.unwrap();
+        assert_eq!(tokens[0].token.value, "a+b/c== d");
+        assert!(tokens[0].token.exp_unix_ts >= now + 60 && tokens[0].token.exp_unix_ts <= now + 61);
+        let err = parse_tokens(HeaderMap::new(), "access_token=abc&expires_in=soon".to_string(), config, None, None).await.unwrap_err();
+        assert!(err.to_string().contains("body field 'expires_in' is not u64"), "{}", err);
+    }
+
+    /// AWS STS AssumeRoleWithWebIdentity response, with an escaped entity and a padded value
+    const STS_XML_RESPONSE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<AssumeRoleWithWebIdentityResponse xmlns="https://sts.amazonaws.com/doc/2011-06-15/">
+  <AssumeRoleWithWebIdentityResult>
+    <SubjectFromWebIdentityToken>system:serviceaccount:default:token-agent</SubjectFromWebIdentityToken>
+    <Credentials>
+      <AccessKeyId>ASIAEXAMPLE</AccessKeyId>
+      <SecretAccessKey>wJalr/K7MDENG+bPxRfiCY</SecretAccessKey>
+      <SessionToken>
+        FwoGZXIvYXdzE&amp;token
+      </SessionToken>
+      <Expiration>2099-01-01T10:00:00Z</Expiration>
+    </Credentials>
+  </AssumeRoleWithWebIdentityResult>
+  <ResponseMetadata><RequestId>ad4156e9-bce1-11e2-82e6-6b6efEXAMPLE</RequestId></ResponseMetadata>
+</AssumeRoleWithWebIdentityResponse>
+"#;
+
+    #[tokio::test]
+    async fn test_xml_body_sts_credentials() {
+        use crate::config::sources::*;
+        let credentials = "/AssumeRoleWithWebIdentityResponse/AssumeRoleWithWebIdentityResult/Credentials";
+        let expiration = Expiration {
+            source: ExpirationSource::JsonBodyField,
+            format: ExpirationSourceFormat::Rfc3339,
+            manual_ttl_seconds: None,
+            pointer: Some(format!("{}/Expiration", credentials)),
+            linked_token_id: None,
+        };
+        let field = |id: &str, name: &str| TokenField { id: id.into(), ..body_format_token_field(&format!("{}/{}", credentials, name), expiration.clone()) };
+        let config = ParseConfig {
+            expect: None,
+            body_format: Some(BodyFormat::Xml),
+            tokens: vec![field("session_token", "SessionToken"), field("secret_access_key", "SecretAccessKey")],
+        };
+
+        let tokens = parse_tokens(HeaderMap::new(), STS_XML_RESPONSE.to_string(), config.clone(), None, None).await.unwrap();
+
+        let session_token = tokens.iter().find(|t| t.id == "session_token").unwrap();
+        assert_eq!(session_token.token.value, "FwoGZXIvYXdzE&token");
+        assert_eq!(session_token.token.exp_unix_ts, 4070944800);
+        let secret = tokens.iter().find(|t| t.id == "secret_access_key").unwrap();
+        assert_eq!(secret.token.value, "wJalr/K7MDENG+bPxRfiCY");
+
+        let err = parse_tokens(HeaderMap::new(), "<AssumeRoleWithWebIdentityResponse>".to_string(), config, None, None).await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<FetchError>(), Some(FetchError::ParseBody { .. })), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_xml_body_dotted_paths() {
+        use crate::config::sources::*;
+        let credentials = "AssumeRoleWithWebIdentityResponse.AssumeRoleWithWebIdentityResult.Credentials";
+        let expiration = Expiration {
+            source: ExpirationSource::JsonBodyField,
+            format: ExpirationSourceFormat::Rfc3339,
+            manual_ttl_seconds: None,
+            pointer: Some(format!("{}.Expiration", credentials)),
+            linked_token_id: None,
+        };
+        let field = |id: &str, name: &str| TokenField { id: id.into(), ..body_format_token_field(&format!("{}.{}", credentials, name), expiration.clone()) };
+        let config = ParseConfig {
+            expect: None,
+            body_format: Some(BodyFormat::Xml),
+            tokens: vec![field("access_key_id", "AccessKeyId"), field("session_token", "SessionToken")],
+        };
+
+        let tokens = parse_tokens(HeaderMap::new(), STS_XML_RESPONSE.to_string(), config, None, None).await.unwrap();
+
+        let access_key_id = tokens.iter().find(|t| t.id == "access_key_id").unwrap();
+        assert_eq!(access_key_id.token.value, "ASIAEXAMPLE");
+        assert_eq!(access_key_id.token.exp_unix_ts, 4070944800);
+        let session_token = tokens.iter().find(|t| t.id == "session_token").unwrap();
+        assert_eq!(session_token.token.value, "FwoGZXIvYXdzE&token");
+    }
+
+    #[test]
+    fn test_xml_path_syntax() {
+        use super::is_valid_xml_path;
+        assert!(is_valid_xml_path("/AssumeRoleWithWebIdentityResponse/AssumeRoleWithWebIdentityResult/Credentials/SessionToken"));
+        assert!(is_valid_xml_path("/soap-env.Envelope/_Body/token_1"));
+        assert!(is_valid_xml_path("AssumeRoleWithWebIdentityResponse.AssumeRoleWithWebIdentityResult.Credentials.SessionToken"));
+        assert!(is_valid_xml_path("Token"));
+        assert!(!is_valid_xml_path("Credentials..SessionToken"));
+        assert!(!is_valid_xml_path("Credentials.0.SessionToken"));
+        assert!(!is_valid_xml_path("Credentials/SessionToken"));
+        assert!(!is_valid_xml_path("/Credentials//SessionToken"));
+        assert!(!is_valid_xml_path("/Credentials/"));
+        assert!(!is_valid_xml_path("/Credentials[1]/SessionToken"));
+        assert!(!is_valid_xml_path("/Credentials/@id"));
+        assert!(!is_valid_xml_path("/soap:Envelope/Body"));
+    }
+
+    #[tokio::test]
+    async fn test_base64_jwt_and_embedded_json_transforms() {
+        use crate::config::sources::*;
+        let now = Utc::now().timestamp() as u64;
+        let jwt = sample_jwt(now + 600);
+        let config = ParseConfig {
+            expect: None,
+            body_format: None,
+            tokens: vec![
+                TokenField {
+                    id: "access_token".into(),
+                    parent: "body".into(),
+                    pointer: "access_token".into(),
+                    token_type: TokenType::Jwt,
+                    jwks_uri: None,
+                    transforms: Some(vec![TokenTransform::Base64Decode]),
+                    extract_claims: None,
+                    iterate: None,
+                    max_token_bytes: None,
+                    value_regex: None,
+                    expiration: None,
+                },
+                TokenField {
+                    id: "secret_key".into(),
+                    transforms: Some(vec![TokenTransform::JsonExtract { pointer: "/secret_key".into() }]),
+                    ..body_format_token_field(
+                        "/data/credentials",
+                        Expiration {
+                            source: ExpirationSource::JsonBodyField,
+                            format: ExpirationSourceFormat::Seconds,
+                            manual_ttl_seconds: None,
+                            pointer: Some("lease_duration".into()),
+                            linked_token_id: None,
+                        },
+                    )
+                },
+            ],
+        };
+        let body = json!({
+            "access_token": base64::engine::general_purpose::STANDARD.encode(&jwt),
+            "data": { "credentials": json!({ "access_key": "AKIA", "secret_key": "inner-secret" }).to_string() },
+            "lease_duration": 60
+        })
+        .to_string();
+
+        let tokens = parse_tokens(HeaderMap::new(), body, config, None, None).await.unwrap();
+
+        let access_token = tokens.iter().find(|t| t.id == "access_token").unwrap();
+        assert_eq!(access_token.token.value, jwt);
+        assert_eq!(access_token.token.exp_unix_ts, now + 600);
+        let secret_key = tokens.iter().find(|t| t.id == "secret_key").unwrap();
+        assert_eq!(secret_key.token.value, "inner-secret");
+        assert!(secret_key.token.exp_unix_ts >= now + 60 && secret_key.token.exp_unix_ts <= now + 61);
+    }
+
+    fn iterate_token_field() -> crate::config::sources::TokenField {
+        use crate::config::sources::*;
+        TokenField {
+            id: "batch".into(),
+            parent: "body".into(),
+            pointer: "/tokens".into(),
+            token_type: TokenType::PlainText,
+            jwks_uri: None,
+            transforms: None,
+            extract_claims: None,
+            iterate: Some(TokenIterate {
+                id_from: "/name".into(),
+                value_from: "/value".into(),
+                exp_from: Some("/exp".into()),
+                exp_format: ExpirationSourceFormat::Unix,
+            }),
+            max_token_bytes: None,
+            value_regex: None,
+            expiration: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_iterate_token_array() {
+        let config = ParseConfig { expect: None, body_format: None, tokens: vec![iterate_token_field()] };
+        let body = json!({ "tokens": [
+            { "name": "svc-a", "value": "token-a", "exp": 4102444800u64 },
+            { "name": "svc-b", "value": "token-b", "exp": 4102444900u64 },
+        ]})
+        .to_string();
+
+        let tokens = parse_tokens(HeaderMap::new(), body, config, None, None).await.unwrap();
+
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens[0].id, "batch.svc-a");
+        assert_eq!(tokens[0].token.value, "token-a");
+        assert_eq!(tokens[0].token.exp_unix_ts, 4102444800);
+        assert_eq!(tokens[1].id, "batch.svc-b");
+        assert_eq!(tokens[1].token.exp_unix_ts, 4102444900);
+    }
+
+    #[tokio::test]
+    async fn test_iterate_empty_array_missing_ids_and_duplicates() {
+        let config = ParseConfig { expect: None, body_format: None, tokens: vec![iterate_token_field()] };
+
+        // empty batch is not a failure
+        let body = json!({ "tokens": [] }).to_string();
+        let tokens = parse_tokens(HeaderMap::new(), body, config.clone(), None, None).await.unwrap();
+        assert!(tokens.is_empty());
+
+        // element without name fails alone, the first of duplicate names wins
+        let body = json!({ "tokens": [
+            { "value": "anonymous", "exp": 4102444800u64 },
+            { "name": "svc-a", "value": "first", "exp": 4102444800u64 },
+            { "name": "svc-a", "value": "second", "exp": 4102444800u64 },
+        ]})
+        .to_string();
+        let tokens = parse_tokens(HeaderMap::new(), body, config.clone(), None, None).await.unwrap();
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].id, "batch.svc-a");
+        assert_eq!(tokens[0].token.value, "first");
+
+        // no element parsed
+        let body = json!({ "tokens": [{ "name": "", "value": "token", "exp": 4102444800u64 }] }).to_string();
+        let err = parse_tokens(HeaderMap::new(), body, config.clone(), None, None).await.unwrap_err();
+        assert!(err.to_string().contains("batch: element 0: body field '/name' is not a token name"), "{}", err);
+
+        let body = json!({ "tokens": { "name": "svc-a" } }).to_string();
+        let err = parse_tokens(HeaderMap::new(), body, config, None, None).await.unwrap_err();
+        assert!(err.to_string().contains("body field '/tokens' is not an array"), "{}", err);
+    }
+
+    fn sample_jwt_with_claims(claims: serde_json::Value) -> String {
+        let header = STANDARD_NO_PAD.encode(r#"{"alg":"none"}"#);
+        format!("{}.{}.", header, STANDARD_NO_PAD.encode(claims.to_string()))
+    }
+
+    /// Log output of the current thread, for sync code run with `tracing::subscriber::with_default`
+    #[derive(Clone, Default)]
+    struct LogBuffer(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for LogBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    #[serial_test::serial]
+    fn test_jwt_clock_skew() {
+        use crate::parser::parser::{get_jwt_token_expiration, set_clock_skew_seconds};
+        let now = Utc::now().timestamp() as u64;
+
+        // issuer clock slightly ahead of ours
+        set_clock_skew_seconds(Some(30));
+        assert_eq!(get_jwt_token_expiration(&sample_jwt(now - 5)).unwrap(), now - 5);
+        assert!(get_jwt_token_expiration(&sample_jwt(now - 60)).is_err());
+        set_clock_skew_seconds(Some(0));
+        assert!(get_jwt_token_expiration(&sample_jwt(now - 5)).is_err());
+        set_clock_skew_seconds(None);
+
+        let logs = LogBuffer::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt().with_ansi(false).with_writer(move || writer.clone()).finish();
+        let jwt = sample_jwt_with_claims(json!({ "exp": now + 600, "iat": now + 120 }));
+        tracing::subscriber::with_default(subscriber, || get_jwt_token_expiration(&jwt)).unwrap();
+        let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        assert!(logs.contains("jwt issued in the future"), "{}", logs);
+        assert!(logs.contains(&format!("issued_at={}", now + 120)) && logs.contains("skew_seconds="), "{}", logs);
+    }
+
+    #[tokio::test]
+    #[serial_test::serial]
+    async fn test_jwt_not_before_marks_token_not_active() {
+        use crate::config::sources::*;
+        let now = Utc::now().timestamp() as u64;
+        let jwt_field = |id: &str| TokenField {
+            id: id.into(),
+            parent: "body".into(),
+            pointer: id.into(),
+            token_type: TokenType::Jwt,
+            jwks_uri: None,
+            transforms: None,
+            extract_claims: None,
+            iterate: None,
+            max_token_bytes: None,
+            value_regex: None,
+            expiration: None,
+        };
+        let config = ParseConfig { expect: None, body_format: None, tokens: vec![jwt_field("future"), jwt_field("within_skew")] };
+        let body = json!({
+            "future": sample_jwt_with_claims(json!({ "exp": now + 600, "nbf": now + 60 })),
+            "within_skew": sample_jwt_with_claims(json!({ "exp": now + 600, "nbf": now + 10 })),
+        })
+        .to_string();
+
+        let tokens = parse_tokens(HeaderMap::new(), body, config, None, None).await.unwrap();
+
+        let future = tokens.iter().find(|t| t.id == "future").unwrap();
+        assert!(!future.is_active());
+        assert_eq!(future.not_before_unix_ts, Some(now + 60 - CLOCK_SKEW_SECONDS_DEFAULT));
+        let within_skew = tokens.iter().find(|t| t.id == "within_skew").unwrap();
+        assert!(within_skew.is_active());
+        assert_eq!(within_skew.not_before_unix_ts, None);
+    }
+
+    #[tokio::test]
+    async fn test_body_format_detected_from_content_type() {
+        use crate::config::sources::*;
+        let manual = Expiration {
+            source: ExpirationSource::Manual,
+            format: ExpirationSourceFormat::Seconds,
+            manual_ttl_seconds: Some(60),
+            pointer: None,
+            linked_token_id: None,
+        };
+        let config = ParseConfig { expect: None, body_format: None, tokens: vec![body_format_token_field("access_token", manual)] };
+
+        let headers = make_headers(&[("content-type", "text/plain; charset=utf-8")]);
+        let tokens = parse_tokens(headers, "plain-token\n".to_string(), config.clone(), None, None).await.unwrap();
+        assert_eq!(tokens[0].token.value, "plain-token");
+
+        let headers = make_headers(&[("content-type", "application/x-www-form-urlencoded")]);
+        let tokens = parse_tokens(headers, "access_token=form-token&scope=read".to_string(), config.clone(), None, None).await.unwrap();
+        assert_eq!(tokens[0].token.value, "form-token");
+
+        // JSON mislabeled as text/plain is still read as JSON
+        let headers = make_headers(&[("content-type", "text/plain")]);
+        let body = json!({ "access_token": "json-token" }).to_string();
+        let tokens = parse_tokens(headers, body, config.clone(), None, None).await.unwrap();
+        assert_eq!(tokens[0].token.value, "json-token");
+
+        // configured format wins over the header
+        let config = ParseConfig { body_format: Some(BodyFormat::Json), ..config };
+        let headers = make_headers(&[("content-type", "text/plain")]);
+        let err = parse_tokens(headers, "plain-token".to_string(), config, None, None).await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<FetchError>(), Some(FetchError::ParseBody { .. })), "{}", err);
+    }
+
+    #[test]
+    fn test_jwt_payload_base64url_padded_and_encrypted() {
+        use base64::engine::general_purpose::{URL_SAFE, URL_SAFE_NO_PAD};
+        use crate::parser::parser::get_jwt_token_expiration;
+        let exp = Utc::now().timestamp() as u64 + 3600;
+        let header = URL_SAFE_NO_PAD.encode(r#"{"typ":"JWT","alg":"RS256","kid":"nOo3ZDrODXEK1jKWhXslHR_KXEg"}"#);
+
+        // Azure AD access token claims, their base64url form has '-' and '_'
+        let claims = json!({
+            "aud": "00000003-0000-0000-c000-000000000000",
+            "iss": "https://sts.windows.net/72f988bf-86f1-41af-91ab-2d7cd011db47/",
+            "exp": exp,
+            "name": "Jürgen Müller >>>???",
+            "uti": "fqiBqXLPj0eQa82S-IYFAA",
+        })
+        .to_string();
+        let payload = URL_SAFE_NO_PAD.encode(&claims);
+        assert!(payload.contains('-') && payload.contains('_'), "{}", payload);
+        let azure_jwt = format!("{}.{}.c2lnbmF0dXJl", header, payload);
+        assert_eq!(get_jwt_token_expiration(&azure_jwt).unwrap(), exp);
+
+        let padded = URL_SAFE.encode(format!(r#"{{"exp":{},"sub":"ab"}}"#, exp));
+        assert!(padded.ends_with('='), "{}", padded);
+        assert_eq!(get_jwt_token_expiration(&format!("{}.{}.c2lnbmF0dXJl", header, padded)).unwrap(), exp);
+        // standard alphabet as before
+        assert_eq!(get_jwt_token_expiration(&sample_jwt(exp)).unwrap(), exp);
+
+        let jwe = format!("{}.ZW5jcnlwdGVkLWtleQ.aXY.Y2lwaGVydGV4dA.dGFn", header);
+        let err = get_jwt_token_expiration(&jwe).unwrap_err();
+        assert!(err.to_string().contains("encrypted token, cannot extract exp"), "{}", err);
+        assert!(get_jwt_token_expiration("not-a-jwt").unwrap_err().to_string().contains("invalid JWT format"));
+    }
+
+    #[tokio::test]
+    async fn test_token_value_guardrails() {
+        use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+        use crate::config::sources::*;
+        let manual = Expiration {
+            source: ExpirationSource::Manual,
+            format: ExpirationSourceFormat::Seconds,
+            manual_ttl_seconds: Some(60),
+            pointer: None,
+            linked_token_id: None,
+        };
+        let parse = |token_field: TokenField, body: Value| {
+            let config = ParseConfig { expect: None, body_format: None, tokens: vec![token_field] };
+            parse_tokens(HeaderMap::new(), body.to_string(), config, None, None)
+        };
+        let parse_failures = || async { get_metrics().await.parse_failures.get() };
+
+        // empty and whitespace-only values
+        for value in ["", "  \n"] {
+            let before = parse_failures().await;
+            let err = parse(body_format_token_field("/token", manual.clone()), json!({ "token": value })).await.unwrap_err();
+            assert!(err.to_string().contains("token value is empty"), "{}", err);
+            assert!(parse_failures().await > before);
+        }
+
+        // pointer at a whole document instead of the token, default limit is 64 KiB
+        let document = json!({ "token": "x".repeat(MAX_TOKEN_BYTES_DEFAULT + 1) });
+        let err = parse(body_format_token_field("/token", manual.clone()), document).await.unwrap_err();
+        assert!(err.to_string().contains(&format!("max_token_bytes is {}", MAX_TOKEN_BYTES_DEFAULT)), "{}", err);
+        let limited = TokenField { max_token_bytes: Some(8), ..body_format_token_field("/token", manual.clone()) };
+        assert!(parse(limited.clone(), json!({ "token": "12345678" })).await.is_ok());
+        let err = parse(limited, json!({ "token": "123456789" })).await.unwrap_err();
+        assert!(err.to_string().contains("token value is 9 bytes, max_token_bytes is 8"), "{}", err);
+
+        let value_regex = Some("^[A-Za-z0-9._-]+$".to_string());
+        let err = parse(
+            TokenField { value_regex: value_regex.clone(), ..body_format_token_field("/token", manual.clone()) },
+            json!({ "token": "Bearer abc" }),
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("does not match value_regex"), "{}", err);
+
+        // header tokens are checked as well
+        let header_field = TokenField { parent: HEADER_FIELD.into(), pointer: "x-token".into(), ..body_format_token_field("", manual) };
+        let config = ParseConfig { expect: None, body_format: None, tokens: vec![header_field] };
+        let headers = make_headers(&[("x-token", " ")]);
+        assert!(parse_tokens(headers, String::new(), config, None, None).await.is_err());
+
+        // signed JWT passes all of them
+        let exp = Utc::now().timestamp() as u64 + 3600;
+        let jwt = format!(
+            "{}.{}.{}",
+            URL_SAFE_NO_PAD.encode(r#"{"alg":"RS256","typ":"JWT"}"#),
+            URL_SAFE_NO_PAD.encode(json!({ "sub": "svc", "exp": exp }).to_string()),
+            URL_SAFE_NO_PAD.encode([7u8; 256]),
+        );
+        let jwt_field = TokenField {
+            token_type: TokenType::Jwt,
+            expiration: None,
+            value_regex,
+            ..body_format_token_field("/token", Expiration { source: ExpirationSource::Manual, format: ExpirationSourceFormat::Seconds, manual_ttl_seconds: None, pointer: None, linked_token_id: None })
+        };
+        let tokens = parse(jwt_field, json!({ "token": jwt })).await.unwrap();
+        assert_eq!(tokens[0].token.value, jwt);
+        assert_eq!(tokens[0].token.exp_unix_ts, exp);
+    }
+}
@@ -0,0 +1,138 @@
+use anyhow::{anyhow, Result};
+use base64::alphabet;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use base64::Engine;
+
+use crate::config::sources::TokenTransform;
+use crate::parser::parser::get_json_value;
+use serde_json::Value;
+
+const PADDING_INDIFFERENT: GeneralPurposeConfig =
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent);
+pub(crate) const BASE64: GeneralPurpose = GeneralPurpose::new(&alphabet::STANDARD, PADDING_INDIFFERENT);
+pub(crate) const BASE64_URL: GeneralPurpose = GeneralPurpose::new(&alphabet::URL_SAFE, PADDING_INDIFFERENT);
+
+/// Apply transforms in order to the raw token value,
+/// missing prefix/suffix leaves the value unchanged
+pub fn apply_token_transforms(value: String, transforms: Option<&[TokenTransform]>) -> Result<String> {
+    transforms
+        .unwrap_or_default()
+        .iter()
+        .try_fold(value, apply_token_transform)
+}
+
+fn apply_token_transform(value: String, transform: &TokenTransform) -> Result<String> {
+    match transform {
+        TokenTransform::Trim => Ok(value.trim().to_owned()),
+        TokenTransform::StripPrefix(prefix) => Ok(value.strip_prefix(prefix.as_str()).unwrap_or(&value).to_owned()),
+        TokenTransform::StripSuffix(suffix) => Ok(value.strip_suffix(suffix.as_str()).unwrap_or(&value).to_owned()),
+        TokenTransform::Base64Decode => decode_utf8(&BASE64, &value),
+        TokenTransform::Base64UrlDecode => decode_utf8(&BASE64_URL, &value),
+        TokenTransform::UpperCase => Ok(value.to_uppercase()),
+        TokenTransform::LowerCase => Ok(value.to_lowercase()),
+        TokenTransform::JsonExtract { pointer } => extract_json(&value, pointer),
+    }
+}
+
+fn extract_json(value: &str, pointer: &str) -> Result<String> {
+    let json: Value = serde_json::from_str(value).map_err(|e| anyhow!("json_extract: value is not valid JSON: {}", e))?;
+    match get_json_value(&json, pointer).map_err(|e| anyhow!("json_extract: {}", e))? {
+        Value::String(extracted) => Ok(extracted.to_owned()),
+        Value::Null => Err(anyhow!("json_extract: field '{}' is null", pointer)),
+        extracted => Ok(extracted.to_string()),
+    }
+}
+
+fn decode_utf8(engine: &GeneralPurpose, value: &str) -> Result<String> {
+    let decoded = engine.decode(value).map_err(|e| anyhow!("base64 decode failed: {}", e))?;
+    String::from_utf8(decoded).map_err(|e| anyhow!("base64 decoded value is not UTF-8: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use TokenTransform::*;
+
+    fn apply(value: &str, transforms: &[TokenTransform]) -> Result<String> {
+        apply_token_transforms(value.to_string(), Some(transforms))
+    }
+
+    #[test]
+    fn test_single_transforms() {
+        assert_eq!(apply("  token \n", &[Trim]).unwrap(), "token");
+        assert_eq!(apply("Bearer eyJ.a.b", &[StripPrefix("Bearer ".into())]).unwrap(), "eyJ.a.b");
+        assert_eq!(apply("eyJ.a.b", &[StripPrefix("Bearer ".into())]).unwrap(), "eyJ.a.b");
+        assert_eq!(apply("token;expires", &[StripSuffix(";expires".into())]).unwrap(), "token");
+        assert_eq!(apply("token", &[StripSuffix(";expires".into())]).unwrap(), "token");
+        assert_eq!(apply("c2VjcmV0Pz4+", &[Base64Decode]).unwrap(), "secret?>>");
+        assert_eq!(apply("dG9rZW4", &[Base64Decode]).unwrap(), "token");
+        assert_eq!(apply("c2VjcmV0Pz4-", &[Base64UrlDecode]).unwrap(), "secret?>>");
+        assert_eq!(apply("dG9rZW4=", &[Base64UrlDecode]).unwrap(), "token");
+        assert_eq!(apply("Token", &[UpperCase]).unwrap(), "TOKEN");
+        assert_eq!(apply("Token", &[LowerCase]).unwrap(), "token");
+        assert_eq!(apply("Token", &[]).unwrap(), "Token");
+        assert_eq!(apply_token_transforms("Token".into(), None).unwrap(), "Token");
+    }
+
+    #[test]
+    fn test_invalid_base64() {
+        assert!(apply("not base64!", &[Base64Decode]).is_err());
+        assert!(apply("c2VjcmV0Pz4-", &[Base64Decode]).is_err());
+        // valid base64 of non UTF-8 bytes
+        assert!(apply("/w==", &[Base64Decode]).is_err());
+    }
+
+    #[test]
+    fn test_chained_transforms_applied_in_order() {
+        let transforms = [Trim, StripPrefix("Basic ".into()), Base64Decode, StripSuffix(":".into()), UpperCase];
+        assert_eq!(apply(" Basic dXNlcjo= ", &transforms).unwrap(), "USER");
+        // strip before trim does not match leading whitespace
+        assert_eq!(apply(" Bearer x", &[StripPrefix("Bearer ".into()), Trim]).unwrap(), "Bearer x");
+    }
+
+    #[test]
+    fn test_json_extract() {
+        let nested = r#"{"data":{"access_key":"AKIA","lease":{"ttl":3600}},"tokens":["first"],"empty":null}"#;
+        let extract = |pointer: &str| apply(nested, &[JsonExtract { pointer: pointer.into() }]);
+        assert_eq!(extract("/data/access_key").unwrap(), "AKIA");
+        assert_eq!(extract("data.access_key").unwrap(), "AKIA");
+        assert_eq!(extract("tokens.0").unwrap(), "first");
+        assert_eq!(extract("/data/lease/ttl").unwrap(), "3600");
+        assert_eq!(extract("/data/lease").unwrap(), r#"{"ttl":3600}"#);
+        assert!(extract("/data/missing").unwrap_err().to_string().contains("body field '/data/missing' not found"));
+        assert!(extract("empty").is_err());
+        assert!(apply("not json", &[JsonExtract { pointer: "token".into() }]).is_err());
+        // nested JSON string after base64
+        assert_eq!(apply("eyJ0b2tlbiI6ImlubmVyIn0=", &[Base64Decode, JsonExtract { pointer: "token".into() }]).unwrap(), "inner");
+    }
+
+    #[test]
+    fn test_transforms_from_yaml() {
+        let transforms: Vec<TokenTransform> = serde_yaml::with::singleton_map_recursive::deserialize(serde_yaml::Deserializer::from_str(
+            r#"
+- trim
+- strip_prefix: "Bearer "
+- strip_suffix: "=="
+- base64_decode
+- base64_url_decode
+- upper_case
+- lower_case
+- json_extract: { pointer: /data/token }
+"#,
+        ))
+        .unwrap();
+        assert_eq!(
+            transforms,
+            vec![
+                Trim,
+                StripPrefix("Bearer ".into()),
+                StripSuffix("==".into()),
+                Base64Decode,
+                Base64UrlDecode,
+                UpperCase,
+                LowerCase,
+                JsonExtract { pointer: "/data/token".into() }
+            ]
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use dashmap::DashMap;
+use tokio::sync::OnceCell;
+use tracing::{info, warn};
+
+use crate::helpers::time::now_i64;
+use crate::observability::metrics::get_metrics;
+
+/// Longest wait between attempts of a failing source, unless `min_refresh_interval_seconds` is longer
+pub const FAILURE_BACKOFF_MAX_SECONDS: u64 = 300;
+
+// Declare the static OnceCell to hold failure backoff per source_id.
+static SOURCE_BACKOFFS_INSTANCE: OnceCell<DashMap<String, SourceBackoff>> = OnceCell::const_new();
+
+/// Asynchronously initializes and gets a reference to the static failure backoff map.
+async fn get_source_backoffs() -> &'static DashMap<String, SourceBackoff> {
+    SOURCE_BACKOFFS_INSTANCE.get_or_init(|| async {
+        info!("Initializing static SourceBackoffs...");
+        DashMap::new()
+    }).await
+}
+
+/// Failure backoff of a source: after each failed fetch the next attempt waits `min_refresh_interval_seconds`,
+/// doubled on every consecutive failure up to `FAILURE_BACKOFF_MAX_SECONDS`; reset on success
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceBackoff {
+    pub consecutive_failures: u32,
+    pub backoff_seconds: u64,
+    /// unix ts
+    pub next_attempt_at: i64,
+}
+
+impl SourceBackoff {
+    /// Unix ts before which the source must not be fetched, None when it is not backing off
+    pub async fn next_attempt_at(source_id: &str) -> Option<i64> {
+        get_source_backoffs()
+            .await
+            .get(source_id)
+            .map(|backoff| backoff.next_attempt_at)
+            .filter(|next_attempt_at| now_i64() < *next_attempt_at)
+    }
+
+    /// Count failed fetch and schedule the next attempt, zero interval disables backoff
+    pub async fn record_failure(source_id: &str, min_refresh_interval_seconds: u64) -> Option<SourceBackoff> {
+        if min_refresh_interval_seconds == 0 {
+            return None;
+        }
+        let consecutive_failures = get_source_backoffs()
+            .await
+            .get(source_id)
+            .map_or(1, |backoff| backoff.consecutive_failures.saturating_add(1));
+        let backoff_seconds = get_backoff_seconds(min_refresh_interval_seconds, consecutive_failures);
+        let backoff = SourceBackoff {
+            consecutive_failures,
+            backoff_seconds,
+            next_attempt_at: now_i64() + backoff_seconds as i64,
+        };
+        warn!(
+            "source '{}' failed {} time(s) in a row, next attempt in {} seconds",
+            source_id, consecutive_failures, backoff_seconds
+        );
+        get_source_backoffs().await.insert(source_id.to_owned(), backoff);
+        get_metrics().await.source_backoff_seconds.with_label_values(&[source_id]).set(backoff_seconds as i64);
+        Some(backoff)
+    }
+
+    /// Successful fetch resets backoff
+    pub async fn record_success(source_id: &str) {
+        if get_source_backoffs().await.remove(source_id).is_some() {
+            info!("source '{}' recovered, backoff reset", source_id);
+            get_metrics().await.source_backoff_seconds.with_label_values(&[source_id]).set(0);
+        }
+    }
+
+    /// Drop backoff state, e.g. for a source removed by config reload
+    pub async fn remove_by_source_id(source_id: &str) {
+        get_source_backoffs().await.remove(source_id);
+    }
+}
+
+/// `min_refresh_interval_seconds * 2^(failures - 1)`, capped
+fn get_backoff_seconds(min_refresh_interval_seconds: u64, consecutive_failures: u32) -> u64 {
+    let cap = FAILURE_BACKOFF_MAX_SECONDS.max(min_refresh_interval_seconds);
+    2u64.checked_pow(consecutive_failures.saturating_sub(1))
+        .and_then(|factor| min_refresh_interval_seconds.checked_mul(factor))
+        .map_or(cap, |backoff_seconds| backoff_seconds.min(cap))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_cap() {
+        assert_eq!(get_backoff_seconds(15, 1), 15);
+        assert_eq!(get_backoff_seconds(15, 2), 30);
+        assert_eq!(get_backoff_seconds(15, 5), 240);
+        assert_eq!(get_backoff_seconds(15, 6), FAILURE_BACKOFF_MAX_SECONDS);
+        assert_eq!(get_backoff_seconds(15, u32::MAX), FAILURE_BACKOFF_MAX_SECONDS);
+        // interval longer than cap is kept
+        assert_eq!(get_backoff_seconds(600, 3), 600);
+    }
+
+    #[tokio::test]
+    async fn failure_schedules_next_attempt_and_success_resets() {
+        let source_id = "backoff_unit";
+        assert_eq!(SourceBackoff::record_failure(source_id, 0).await, None);
+        assert_eq!(SourceBackoff::next_attempt_at(source_id).await, None);
+
+        let first = SourceBackoff::record_failure(source_id, 10).await.unwrap();
+        assert_eq!((first.consecutive_failures, first.backoff_seconds), (1, 10));
+        let second = SourceBackoff::record_failure(source_id, 10).await.unwrap();
+        assert_eq!((second.consecutive_failures, second.backoff_seconds), (2, 20));
+        let next_attempt_at = SourceBackoff::next_attempt_at(source_id).await.unwrap();
+        assert!(next_attempt_at > now_i64() + 15, "{}", next_attempt_at);
+        assert_eq!(get_metrics().await.source_backoff_seconds.with_label_values(&[source_id]).get(), 20);
+
+        SourceBackoff::record_success(source_id).await;
+        assert_eq!(SourceBackoff::next_attempt_at(source_id).await, None);
+        assert_eq!(get_metrics().await.source_backoff_seconds.with_label_values(&[source_id]).get(), 0);
+        assert_eq!(SourceBackoff::record_failure(source_id, 10).await.unwrap().consecutive_failures, 1);
+        SourceBackoff::remove_by_source_id(source_id).await;
+    }
+}
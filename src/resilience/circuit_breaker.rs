@@ -0,0 +1,295 @@
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Result};
+use dashmap::DashMap;
+use tokio::sync::OnceCell;
+use tokio::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::config::settings::CircuitBreakerConfig;
+use crate::observability::metrics::get_metrics;
+use crate::resilience::retry::RetrySettings;
+
+// Declare the static OnceCell to hold circuit breakers per source_id.
+static CIRCUIT_BREAKERS_INSTANCE: OnceCell<DashMap<String, Arc<CircuitBreaker>>> = OnceCell::const_new();
+
+/// Asynchronously initializes and gets a reference to the static circuit breakers map.
+async fn get_circuit_breakers() -> &'static DashMap<String, Arc<CircuitBreaker>> {
+    CIRCUIT_BREAKERS_INSTANCE.get_or_init(|| async {
+        info!("Initializing static CircuitBreakers...");
+        DashMap::new()
+    }).await
+}
+
+#[derive(Debug, Clone)]
+pub struct CircuitBreakerSettings {
+    pub failure_threshold: u32,
+    pub open_duration_seconds: u64,
+}
+
+impl Default for CircuitBreakerSettings {
+    fn default() -> Self {
+        Self { failure_threshold: 5, open_duration_seconds: 60 }
+    }
+}
+
+impl CircuitBreakerSettings {
+    pub fn from_config(circuit_breaker: &Option<CircuitBreakerConfig>) -> CircuitBreakerSettings {
+        CircuitBreakerSettings::default().with_override(circuit_breaker)
+    }
+
+    /// Apply source level circuit breaker config on top of these (settings level) values
+    pub fn with_override(&self, circuit_breaker: &Option<CircuitBreakerConfig>) -> CircuitBreakerSettings {
+        let Some(circuit_breaker) = circuit_breaker else {
+            return self.clone();
+        };
+        CircuitBreakerSettings {
+            failure_threshold: circuit_breaker.failure_threshold.unwrap_or(self.failure_threshold),
+            open_duration_seconds: circuit_breaker.open_duration_seconds.unwrap_or(self.open_duration_seconds),
+        }
+    }
+}
+
+/// Circuit state, exported as `source_circuit_state` gauge value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    Closed = 0,
+    Open = 1,
+    HalfOpen = 2,
+}
+
+#[derive(Debug)]
+struct CircuitBreakerInner {
+    state: CircuitState,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    last_error: Option<String>,
+    probe_in_flight: bool,
+}
+
+/// Circuit breaker around a full retry sequence:
+/// - closed: operation runs with retry policy, failed sequences are counted
+/// - open: after `failure_threshold` consecutive failures, calls short-circuit with the cached error
+/// - half-open: after `open_duration`, a single probe attempt decides between closed and open
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    open_duration: Duration,
+    inner: Mutex<CircuitBreakerInner>,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, open_duration: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            open_duration,
+            inner: Mutex::new(CircuitBreakerInner {
+                state: CircuitState::Closed,
+                consecutive_failures: 0,
+                opened_at: None,
+                last_error: None,
+                probe_in_flight: false,
+            }),
+        }
+    }
+
+    /// Get circuit breaker by source_id, create it on first use
+    pub async fn get_by_source_id(source_id: &str, settings: &CircuitBreakerSettings) -> Arc<CircuitBreaker> {
+        get_circuit_breakers()
+            .await
+            .entry(source_id.to_owned())
+            .or_insert_with(|| {
+                Arc::new(CircuitBreaker::new(
+                    settings.failure_threshold,
+                    Duration::from_secs(settings.open_duration_seconds),
+                ))
+            })
+            .clone()
+    }
+
+    /// Drop circuit breaker, next call creates it from current settings
+    pub async fn remove_by_source_id(source_id: &str) {
+        get_circuit_breakers().await.remove(source_id);
+    }
+
+    pub fn state(&self) -> CircuitState {
+        self.inner.lock().unwrap().state
+    }
+
+    /// Time left until half-open probe is allowed, None when circuit accepts calls
+    pub fn open_remaining(&self) -> Option<Duration> {
+        let inner = self.inner.lock().unwrap();
+        if inner.state != CircuitState::Open {
+            return None;
+        }
+        let elapsed = inner.opened_at.map(|at| at.elapsed()).unwrap_or(self.open_duration);
+        self.open_duration.checked_sub(elapsed).filter(|remaining| !remaining.is_zero())
+    }
+
+    /// Run operation with retry policy unless circuit is open
+    pub async fn run_with_retry<F, Fut, T>(&self, source_id: &str, retry: &RetrySettings, mut operation: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<T>>,
+    {
+        let metrics = get_metrics().await;
+        let is_probe = self.before_call(source_id);
+        metrics.source_circuit_state.with_label_values(&[source_id]).set(self.state() as i64);
+
+        let res = if is_probe? {
+            // half-open: single probe attempt
+            retry.run_attempt(operation()).await
+        } else {
+            retry.run_with_retry(operation).await
+        };
+        self.after_call(source_id, &res);
+        metrics.source_circuit_state.with_label_values(&[source_id]).set(self.state() as i64);
+        res
+    }
+
+    /// Returns Ok(true) when call is a half-open probe, Err when circuit is open
+    fn before_call(&self, source_id: &str) -> Result<bool> {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.state {
+            CircuitState::Closed => Ok(false),
+            CircuitState::Open => {
+                let elapsed = inner.opened_at.map(|at| at.elapsed()).unwrap_or(self.open_duration);
+                if elapsed < self.open_duration {
+                    return Err(self.open_error(source_id, &inner));
+                }
+                info!("circuit half-open for source '{}', probing", source_id);
+                inner.state = CircuitState::HalfOpen;
+                inner.probe_in_flight = true;
+                Ok(true)
+            }
+            CircuitState::HalfOpen => {
+                if inner.probe_in_flight {
+                    return Err(self.open_error(source_id, &inner));
+                }
+                inner.probe_in_flight = true;
+                Ok(true)
+            }
+        }
+    }
+
+    fn after_call<T>(&self, source_id: &str, res: &Result<T>) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.probe_in_flight = false;
+        match res {
+            Ok(_) => {
+                if inner.state != CircuitState::Closed {
+                    info!("circuit closed for source '{}'", source_id);
+                }
+                inner.state = CircuitState::Closed;
+                inner.consecutive_failures = 0;
+                inner.opened_at = None;
+                inner.last_error = None;
+            }
+            Err(err) => {
+                inner.consecutive_failures += 1;
+                inner.last_error = Some(err.to_string());
+                if inner.state == CircuitState::HalfOpen || inner.consecutive_failures >= self.failure_threshold {
+                    warn!(
+                        "circuit opened for source '{}' after {} consecutive failures, open for {:?}",
+                        source_id, inner.consecutive_failures, self.open_duration
+                    );
+                    inner.state = CircuitState::Open;
+                    inner.opened_at = Some(Instant::now());
+                }
+            }
+        }
+    }
+
+    fn open_error(&self, source_id: &str, inner: &CircuitBreakerInner) -> anyhow::Error {
+        anyhow!(
+            "circuit open for source '{}': {}",
+            source_id,
+            inner.last_error.as_deref().unwrap_or("unknown error")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use anyhow::anyhow;
+    use tokio::time::Duration;
+
+    use crate::resilience::circuit_breaker::{CircuitBreaker, CircuitState};
+    use crate::resilience::retry::RetrySettings;
+
+    fn retry() -> RetrySettings {
+        RetrySettings { attempts: 2, base_delay_ms: 1, max_delay_ms: 1, ..Default::default() }
+    }
+
+    #[tokio::test]
+    async fn test_circuit_opens_and_short_circuits() {
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(60));
+        let calls = AtomicU32::new(0);
+
+        for _ in 0..2 {
+            let res: anyhow::Result<()> = breaker
+                .run_with_retry("cb_open", &retry(), || async {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    Err(anyhow!("endpoint down"))
+                })
+                .await;
+            assert!(res.is_err());
+        }
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+
+        let res: anyhow::Result<()> = breaker
+            .run_with_retry("cb_open", &retry(), || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            })
+            .await;
+        let err = res.unwrap_err().to_string();
+        assert!(err.contains("circuit open"));
+        assert!(err.contains("endpoint down"));
+        assert_eq!(calls.load(Ordering::SeqCst), 4, "open circuit must not call operation");
+    }
+
+    #[tokio::test]
+    async fn test_half_open_probe_closes_on_success() {
+        let breaker = CircuitBreaker::new(1, Duration::from_millis(20));
+        let _ = breaker
+            .run_with_retry("cb_probe_ok", &retry(), || async { Err::<(), _>(anyhow!("down")) })
+            .await;
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        let calls = AtomicU32::new(0);
+        let res = breaker
+            .run_with_retry("cb_probe_ok", &retry(), || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Ok(1)
+            })
+            .await;
+        assert_eq!(res.unwrap(), 1);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+
+    #[tokio::test]
+    async fn test_half_open_probe_failure_reopens() {
+        let breaker = CircuitBreaker::new(1, Duration::from_millis(20));
+        let _ = breaker
+            .run_with_retry("cb_probe_fail", &retry(), || async { Err::<(), _>(anyhow!("down")) })
+            .await;
+
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        let calls = AtomicU32::new(0);
+        let res: anyhow::Result<()> = breaker
+            .run_with_retry("cb_probe_fail", &retry(), || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(anyhow!("still down"))
+            })
+            .await;
+        assert!(res.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1, "half-open allows a single probe");
+        assert_eq!(breaker.state(), CircuitState::Open);
+    }
+}
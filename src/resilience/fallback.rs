@@ -0,0 +1,98 @@
+use dashmap::DashMap;
+use tokio::sync::OnceCell;
+use tracing::{info, warn};
+
+use crate::cache::token_context::TokenContext;
+use crate::helpers::time::now_i64;
+
+// Declare the static OnceCell to hold unix ts of the last failed fetch per source_id.
+static FAILED_FETCHES_INSTANCE: OnceCell<DashMap<String, i64>> = OnceCell::const_new();
+
+/// Asynchronously initializes and gets a reference to the static failed fetches map.
+async fn get_failed_fetches() -> &'static DashMap<String, i64> {
+    FAILED_FETCHES_INSTANCE.get_or_init(|| async {
+        info!("Initializing static FailedFetches...");
+        DashMap::new()
+    }).await
+}
+
+/// Stale token fallback: an expired token of a source whose last fetch failed is kept for
+/// `stale_token_ttl_seconds` more instead of being removed, sinks keep serving it marked as stale
+pub struct StaleFallback;
+
+impl StaleFallback {
+    /// Fetch failed after all retries
+    pub async fn record_failure(source_id: &str) {
+        get_failed_fetches().await.insert(source_id.to_owned(), now_i64());
+    }
+
+    /// Successful fetch replaces stale tokens with fresh ones
+    pub async fn record_success(source_id: &str) {
+        get_failed_fetches().await.remove(source_id);
+    }
+
+    /// Check if the last fetch attempt of the source failed
+    pub async fn last_fetch_failed(source_id: &str) -> bool {
+        get_failed_fetches().await.contains_key(source_id)
+    }
+
+    /// Drop fetch state, e.g. for a source removed by config reload
+    pub async fn remove_by_source_id(source_id: &str) {
+        get_failed_fetches().await.remove(source_id);
+    }
+}
+
+/// Expired token extended by `stale_token_ttl_seconds` and marked stale, None when it must be removed:
+/// fallback is not configured, the token is already stale or the last fetch did not fail
+pub fn keep_as_stale(
+    source_id: &str,
+    token_context: &TokenContext,
+    stale_token_ttl_seconds: Option<u64>,
+    last_fetch_failed: bool,
+) -> Option<TokenContext> {
+    let stale_token_ttl_seconds = stale_token_ttl_seconds.filter(|ttl| *ttl > 0)?;
+    if token_context.is_stale || !last_fetch_failed {
+        return None;
+    }
+    let mut stale = token_context.clone();
+    stale.token.exp_unix_ts = stale.token.exp_unix_ts.saturating_add(stale_token_ttl_seconds);
+    stale.is_stale = true;
+    warn!(
+        "token {}.{} expired while source is failing, served as stale for {} more seconds",
+        source_id, token_context.id, stale_token_ttl_seconds
+    );
+    Some(stale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::token::Token;
+
+    #[test]
+    fn expired_token_is_kept_once_while_source_fails() {
+        let expired = TokenContext::new("token".into(), Token::new("value".into(), 1_000), 10);
+        assert!(keep_as_stale("src", &expired, None, true).is_none());
+        assert!(keep_as_stale("src", &expired, Some(0), true).is_none());
+        assert!(keep_as_stale("src", &expired, Some(60), false).is_none());
+
+        let stale = keep_as_stale("src", &expired, Some(60), true).unwrap();
+        assert!(stale.is_stale);
+        assert_eq!((stale.token.value.as_str(), stale.token.exp_unix_ts), ("value", 1_060));
+        // stale ttl elapsed: removed regardless of the source state
+        assert!(keep_as_stale("src", &stale, Some(60), true).is_none());
+    }
+
+    #[tokio::test]
+    async fn failed_fetch_is_cleared_by_success() {
+        let source_id = "fallback_unit";
+        assert!(!StaleFallback::last_fetch_failed(source_id).await);
+        StaleFallback::record_failure(source_id).await;
+        assert!(StaleFallback::last_fetch_failed(source_id).await);
+        StaleFallback::record_success(source_id).await;
+        assert!(!StaleFallback::last_fetch_failed(source_id).await);
+        StaleFallback::record_failure(source_id).await;
+        StaleFallback::remove_by_source_id(source_id).await;
+        assert!(!StaleFallback::last_fetch_failed(source_id).await);
+    }
+}
@@ -1 +1,6 @@
-pub mod retry;
\ No newline at end of file
+pub mod retry;
+pub mod circuit_breaker;
+pub mod timeout;
+pub mod rate_limit;
+pub mod backoff;
+pub mod fallback;
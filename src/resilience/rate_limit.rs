@@ -0,0 +1,77 @@
+use std::sync::Mutex;
+
+use dashmap::DashMap;
+use tokio::time::{Duration, Instant};
+
+use crate::config::sinks::RateLimitConfig;
+
+/// Clients tracked before full buckets are dropped, a full bucket is the same as a new one
+pub const RATE_LIMIT_MAX_TRACKED_CLIENTS: usize = 10_000;
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+/// Token bucket per client key: `burst` requests at once, refilled with `requests_per_second`
+#[derive(Debug)]
+pub struct RateLimiter {
+    requests_per_second: f64,
+    burst: f64,
+    buckets: DashMap<String, Mutex<Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(cfg: &RateLimitConfig) -> Self {
+        Self { requests_per_second: cfg.requests_per_second, burst: cfg.burst as f64, buckets: DashMap::new() }
+    }
+
+    /// Take one request from the client bucket, `Err` holds delay until next request is allowed
+    pub fn check(&self, key: &str) -> Result<(), Duration> {
+        let now = Instant::now();
+        if self.buckets.len() >= RATE_LIMIT_MAX_TRACKED_CLIENTS && !self.buckets.contains_key(key) {
+            self.buckets.retain(|_, bucket| self.refill(bucket.get_mut().unwrap(), now) < self.burst);
+        }
+        let entry = self
+            .buckets
+            .entry(key.to_owned())
+            .or_insert_with(|| Mutex::new(Bucket { tokens: self.burst, updated_at: now }));
+        let mut bucket = entry.lock().unwrap();
+        let tokens = self.refill(&mut bucket, now);
+        if tokens >= 1.0 {
+            bucket.tokens = tokens - 1.0;
+            return Ok(());
+        }
+        Err(Duration::from_secs_f64((1.0 - tokens) / self.requests_per_second))
+    }
+
+    /// Tokens of the bucket at `now`, capped by `burst`
+    fn refill(&self, bucket: &mut Bucket, now: Instant) -> f64 {
+        let elapsed = now.saturating_duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.requests_per_second).min(self.burst);
+        bucket.updated_at = now;
+        bucket.tokens
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn burst_is_allowed_then_refilled_per_client() {
+        let limiter = RateLimiter::new(&RateLimitConfig { requests_per_second: 2.0, burst: 3 });
+        for _ in 0..3 {
+            assert!(limiter.check("10.0.0.1").is_ok());
+        }
+        let retry_after = limiter.check("10.0.0.1").unwrap_err();
+        assert_eq!(retry_after, Duration::from_millis(500));
+        // other clients have own bucket
+        assert!(limiter.check("10.0.0.2").is_ok());
+
+        tokio::time::advance(Duration::from_millis(500)).await;
+        assert!(limiter.check("10.0.0.1").is_ok());
+        assert!(limiter.check("10.0.0.1").is_err());
+    }
+}
@@ -1,28 +1,97 @@
-use tokio::time::{sleep, Duration};
+use tokio::time::{sleep, timeout, Duration};
 use anyhow::Result;
+use rand::Rng;
 use tracing::{error, warn};
 
+use crate::config::settings::{JitterMode, RetryConfig};
+use crate::sources::error::FetchError;
+
 #[derive(Debug, Clone)]
 pub struct RetrySettings {
     pub attempts: u32,
     pub base_delay_ms: u64,
     pub max_delay_ms: u64,
+    pub jitter: JitterMode,
+    /// None: retry on any status
+    pub retry_on_status: Option<Vec<u16>>,
+    /// allow `Retry-After` delay to exceed `max_delay_ms`
+    pub respect_retry_after: bool,
+    /// time limit of every single attempt, None: no limit
+    pub attempt_timeout: Option<Duration>,
+}
+
+impl Default for RetrySettings {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            base_delay_ms: 200,
+            max_delay_ms: 1000,
+            jitter: JitterMode::None,
+            retry_on_status: None,
+            respect_retry_after: false,
+            attempt_timeout: None,
+        }
+    }
 }
 
 impl RetrySettings {
+    /// Settings level retry config on top of defaults
+    pub fn from_config(retry: &Option<RetryConfig>) -> RetrySettings {
+        RetrySettings::default().with_override(retry)
+    }
+
+    /// Apply source level retry config on top of these (settings level) values
+    pub fn with_override(&self, retry: &Option<RetryConfig>) -> RetrySettings {
+        let Some(retry) = retry else {
+            return self.clone();
+        };
+        RetrySettings {
+            attempts: retry.attempts.unwrap_or(self.attempts),
+            base_delay_ms: retry.base_delay_ms.unwrap_or(self.base_delay_ms),
+            max_delay_ms: retry.max_delay_ms.unwrap_or(self.max_delay_ms),
+            jitter: retry.jitter.unwrap_or(self.jitter),
+            retry_on_status: retry.retry_on_status.clone().or_else(|| self.retry_on_status.clone()),
+            respect_retry_after: retry.respect_retry_after.unwrap_or(self.respect_retry_after),
+            attempt_timeout: self.attempt_timeout,
+        }
+    }
+
+    /// Limit every attempt to `attempt_timeout`
+    pub fn with_attempt_timeout(mut self, attempt_timeout: Option<Duration>) -> RetrySettings {
+        self.attempt_timeout = attempt_timeout;
+        self
+    }
+
+    /// Run single attempt, fails with `FetchError::Timeout` when it exceeds `attempt_timeout`
+    pub async fn run_attempt<Fut, T>(&self, attempt: Fut) -> Result<T>
+    where
+        Fut: std::future::Future<Output = Result<T>>,
+    {
+        let Some(attempt_timeout) = self.attempt_timeout else {
+            return attempt.await;
+        };
+        timeout(attempt_timeout, attempt)
+            .await
+            .unwrap_or_else(|_| Err(FetchError::Timeout { timeout: attempt_timeout }.into()))
+    }
+
     pub async fn run_with_retry<F, Fut, T>(&self, mut operation: F) -> Result<T>
     where
         F: FnMut() -> Fut,
         Fut: std::future::Future<Output = Result<T>>,
     {
-        let mut delay = self.base_delay_ms;
+        let mut delay = self.base_delay_ms.min(self.max_delay_ms);
 
         for attempt in 1..=self.attempts {
-            match operation().await {
+            match self.run_attempt(operation()).await {
                 Ok(value) => return Ok(value),
+                Err(e) if !self.is_retryable(&e) => {
+                    error!("attempt {attempt}/{} failed, not retryable: {e}", self.attempts);
+                    return Err(e);
+                }
                 Err(e) if attempt < self.attempts => {
                     warn!("Attempt {attempt}/{} failed: {e}", self.attempts);
-                    sleep(Duration::from_millis(delay)).await;
+                    sleep(self.retry_delay(&e, delay)).await;
                     delay = (delay * 2).min(self.max_delay_ms);
                 }
                 Err(e) => {
@@ -33,4 +102,113 @@ impl RetrySettings {
         }
         unreachable!("Retry loop exhausted unexpectedly")
     }
+
+    /// HTTP statuses are retried if listed in `retry_on_status` (all if not set), other typed errors
+    /// if `FetchError::is_retryable`; untyped errors are always retried
+    pub fn is_retryable(&self, err: &anyhow::Error) -> bool {
+        match (err.downcast_ref::<FetchError>(), &self.retry_on_status) {
+            (Some(FetchError::HttpStatus { status, .. }), Some(retry_on_status)) => retry_on_status.contains(&status.as_u16()),
+            (Some(fetch_error), _) => fetch_error.is_retryable(),
+            (None, _) => true,
+        }
+    }
+
+    /// Server `Retry-After` takes precedence over backoff, capped by `max_delay_ms`
+    /// unless `respect_retry_after` is set
+    pub fn retry_delay(&self, err: &anyhow::Error, delay: u64) -> Duration {
+        match FetchError::retry_after(err) {
+            Some(retry_after) if self.respect_retry_after => retry_after,
+            Some(retry_after) => retry_after.min(Duration::from_millis(self.max_delay_ms)),
+            None => Duration::from_millis(self.backoff_delay_ms(delay)),
+        }
+    }
+
+    /// Apply jitter to exponential delay, result stays within [0, delay]
+    pub fn backoff_delay_ms(&self, delay: u64) -> u64 {
+        match self.jitter {
+            JitterMode::None => delay,
+            JitterMode::Full => rand::rng().random_range(0..=delay),
+            JitterMode::Equal => delay / 2 + rand::rng().random_range(0..=delay - delay / 2),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use reqwest::StatusCode;
+
+    use super::*;
+
+    fn retry(attempts: u32) -> RetrySettings {
+        RetrySettings {
+            attempts,
+            base_delay_ms: 1,
+            max_delay_ms: 1,
+            retry_on_status: Some(vec![429, 500, 502, 503, 504]),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn status_not_in_retry_on_status_fails_immediately() {
+        let calls = AtomicU32::new(0);
+        let res: Result<()> = retry(5)
+            .run_with_retry(|| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                async { Err(FetchError::HttpStatus { status: StatusCode::UNAUTHORIZED, retry_after: None }.into()) }
+            })
+            .await;
+        assert_eq!(FetchError::http_status(&res.unwrap_err()), Some(StatusCode::UNAUTHORIZED));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn network_errors_are_always_retried() {
+        let calls = AtomicU32::new(0);
+        let res: Result<()> = retry(3)
+            .run_with_retry(|| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                async { Err(anyhow::anyhow!("connection refused")) }
+            })
+            .await;
+        assert!(res.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn backoff_delay_with_jitter_stays_within_bounds() {
+        for jitter in [JitterMode::None, JitterMode::Full, JitterMode::Equal] {
+            let settings = RetrySettings { base_delay_ms: 100, max_delay_ms: 1000, jitter, ..Default::default() };
+            let mut delay = settings.base_delay_ms;
+            for _ in 0..50 {
+                let backoff = settings.backoff_delay_ms(delay);
+                assert!(backoff <= delay && backoff <= settings.max_delay_ms, "{:?}: {} > {}", jitter, backoff, delay);
+                if jitter == JitterMode::Equal {
+                    assert!(backoff >= delay / 2);
+                }
+                delay = (delay * 2).min(settings.max_delay_ms);
+            }
+        }
+        assert_eq!(RetrySettings::default().backoff_delay_ms(0), 0);
+        assert_eq!(RetrySettings { jitter: JitterMode::Full, ..Default::default() }.backoff_delay_ms(0), 0);
+    }
+
+    #[test]
+    fn full_jitter_is_uniform_over_delay() {
+        let settings = RetrySettings { jitter: JitterMode::Full, ..Default::default() };
+        let delay = 1000;
+        let mut buckets = [0u32; 10];
+        let mut total = 0;
+        for _ in 0..1000 {
+            let backoff = settings.backoff_delay_ms(delay);
+            assert!(backoff <= delay);
+            buckets[(backoff * 10 / (delay + 1)) as usize] += 1;
+            total += backoff;
+        }
+        // 100 expected per bucket, standard deviation ~9.5
+        assert!(buckets.iter().all(|count| (50..=150).contains(count)), "{:?}", buckets);
+        assert!((450..=550).contains(&(total / 1000)), "mean {}", total / 1000);
+    }
 }
@@ -0,0 +1,54 @@
+use std::time::Duration;
+
+use reqwest::ClientBuilder;
+
+use crate::config::settings::TimeoutConfig;
+
+/// Source request timeouts, `None` means no timeout
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimeoutSettings {
+    pub connect: Option<Duration>,
+    pub read: Option<Duration>,
+}
+
+impl TimeoutSettings {
+    pub fn from_config(timeouts: &Option<TimeoutConfig>) -> TimeoutSettings {
+        TimeoutSettings::default().with_override(timeouts)
+    }
+
+    /// Apply source level timeouts config on top of these (settings level) values
+    pub fn with_override(&self, timeouts: &Option<TimeoutConfig>) -> TimeoutSettings {
+        let Some(timeouts) = timeouts else {
+            return *self;
+        };
+        TimeoutSettings {
+            connect: timeouts.connect_seconds.map(Duration::from_secs).or(self.connect),
+            read: timeouts.read_seconds.map(Duration::from_secs).or(self.read),
+        }
+    }
+
+    /// Client builder with connect timeout applied, read timeout is applied per fetch attempt by retry
+    pub fn client_builder(&self) -> ClientBuilder {
+        let builder = reqwest::Client::builder();
+        match self.connect {
+            Some(connect) => builder.connect_timeout(connect),
+            None => builder,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn source_timeouts_fall_back_to_settings() {
+        let settings = TimeoutSettings::from_config(&Some(TimeoutConfig { connect_seconds: Some(2), read_seconds: Some(10) }));
+        assert_eq!(settings.with_override(&None), settings);
+
+        let source = settings.with_override(&Some(TimeoutConfig { connect_seconds: None, read_seconds: Some(1) }));
+        assert_eq!(source, TimeoutSettings { connect: Some(Duration::from_secs(2)), read: Some(Duration::from_secs(1)) });
+
+        assert_eq!(TimeoutSettings::from_config(&None), TimeoutSettings { connect: None, read: None });
+    }
+}
@@ -0,0 +1,289 @@
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+
+use axum::{
+    extract::{Path, State},
+    http::{header::AUTHORIZATION, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use serde::Serialize;
+use tracing::info;
+
+use crate::cache::token_cache::TokenCache;
+use crate::cache::token_event::TokenEvent;
+use crate::config::settings::AdminConfig;
+use crate::helpers::secret::secret_eq;
+use crate::observability::audit::{self, AuditEvent, AuditEventType};
+use crate::server::server::AppState;
+use crate::sources::executor::token_fetch::request_refresh;
+
+const TOKEN_PREVIEW_CHARS: usize = 4;
+
+#[derive(Clone, Default)]
+pub struct AdminState {
+    enabled: bool,
+    bearer_token: Option<Arc<String>>,
+    /// configured sources, invalidation of any other source is `404`; replaced on config reload
+    source_ids: Arc<RwLock<HashSet<String>>>,
+}
+
+impl AdminState {
+    pub fn new(admin_config: &Option<AdminConfig>) -> Self {
+        Self {
+            enabled: admin_config.as_ref().map(|c| c.enabled).unwrap_or(false),
+            bearer_token: admin_config
+                .as_ref()
+                .and_then(|c| c.bearer_token.clone())
+                .map(Arc::new),
+            source_ids: Arc::default(),
+        }
+    }
+
+    pub fn with_source_ids(mut self, source_ids: HashSet<String>) -> Self {
+        self.source_ids = Arc::new(RwLock::new(source_ids));
+        self
+    }
+
+    pub fn set_source_ids(&self, source_ids: HashSet<String>) {
+        *self.source_ids.write().unwrap() = source_ids;
+    }
+
+    pub async fn router(&self) -> Router<AppState> {
+        let mut router = Router::new();
+        if self.enabled {
+            info!("admin routes enabled");
+            router = router
+                .route("/admin/cache", get(get_cache))
+                .route("/admin/invalidate/{source_id}", post(invalidate_source));
+        }
+        router
+    }
+
+    fn is_authorized(&self, headers: &HeaderMap) -> bool {
+        let Some(expected) = &self.bearer_token else {
+            return true;
+        };
+        let provided = headers
+            .get(AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "));
+        provided.is_some_and(|provided| secret_eq(provided, expected))
+    }
+}
+
+/// Token state without token value, only `abcd...wxyz` preview
+#[derive(Debug, Serialize)]
+pub struct CacheEntry {
+    pub source_id: String,
+    pub token_id: String,
+    pub exp_unix_ts: u64,
+    pub fetched_at_unix_ts: u64,
+    pub should_update: bool,
+    pub should_remove: bool,
+    pub token_preview: String,
+}
+
+/// `202` body of `POST /admin/invalidate/{source_id}`
+#[derive(Debug, Serialize)]
+pub struct InvalidateResponse {
+    pub invalidated: bool,
+    /// number of removed tokens
+    pub tokens: usize,
+}
+
+async fn get_cache(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if !state.admin_state.is_authorized(&headers) {
+        return (StatusCode::UNAUTHORIZED, "unauthorized").into_response();
+    }
+    // sources and tokens are sorted by id
+    let mut entries: Vec<CacheEntry> = Vec::new();
+    for source_id in TokenCache::list_source_ids().await {
+        entries.extend(TokenCache::get_all_by_source_id(&source_id).await.into_iter().map(|token_context| CacheEntry {
+            should_update: token_context.should_update(),
+            should_remove: token_context.should_remove(),
+            token_preview: token_preview(&token_context.token.value),
+            source_id: source_id.clone(),
+            token_id: token_context.id,
+            exp_unix_ts: token_context.token.exp_unix_ts,
+            fetched_at_unix_ts: token_context.fetched_at_unix_ts,
+        }));
+    }
+    Json(entries).into_response()
+}
+
+async fn invalidate_source(
+    State(state): State<AppState>,
+    Path(source_id): Path<String>,
+    headers: HeaderMap,
+) -> Response {
+    if !state.admin_state.is_authorized(&headers) {
+        return (StatusCode::UNAUTHORIZED, "unauthorized").into_response();
+    }
+    // sources cached without config (library use) can be invalidated too
+    let configured = state.admin_state.source_ids.read().unwrap().contains(&source_id);
+    if !configured && !TokenCache::contains_source_id(&source_id).await {
+        return (StatusCode::NOT_FOUND, format!("unknown source '{}'", source_id)).into_response();
+    }
+    // force expire: tokens are not served anymore, sinks drop them until the source is fetched again
+    let removed = TokenCache::take_by_source_id(&source_id).await.unwrap_or_default();
+    if let Some(sink_sender) = &state.sink_sender {
+        for token_context in &removed {
+            // no active sinks is not an error
+            let _ = sink_sender.send(TokenEvent::removed(&source_id, token_context.clone()));
+        }
+    }
+    info!("admin: source '{}' invalidated, {} tokens removed, re-fetch requested", source_id, removed.len());
+    audit::emit(AuditEvent::new(AuditEventType::Invalidation, &source_id));
+    request_refresh();
+    (StatusCode::ACCEPTED, Json(InvalidateResponse { invalidated: true, tokens: removed.len() })).into_response()
+}
+
+/// First and last chars of token, short tokens are fully masked
+fn token_preview(value: &str) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    if chars.len() <= TOKEN_PREVIEW_CHARS * 3 {
+        return "*".repeat(chars.len().min(TOKEN_PREVIEW_CHARS));
+    }
+    let head: String = chars[..TOKEN_PREVIEW_CHARS].iter().collect();
+    let tail: String = chars[chars.len() - TOKEN_PREVIEW_CHARS..].iter().collect();
+    format!("{}...{}", head, tail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use serial_test::serial;
+    use std::collections::HashMap;
+
+    use tokio::sync::broadcast::Sender;
+
+    use crate::cache::token::Token;
+    use crate::cache::token_context::TokenContext;
+    use crate::cache::token_event::TokenEventKind;
+    use crate::observability::metrics::get_metrics;
+    use crate::tests::common::{build_reqwest_client, spawn_axum};
+    use crate::utils::channel;
+
+    async fn spawn_admin(bearer_token: Option<String>) -> (tokio::task::JoinHandle<()>, std::net::SocketAddr) {
+        spawn_admin_with_sinks(bearer_token, channel::run()).await
+    }
+
+    async fn spawn_admin_with_sinks(
+        bearer_token: Option<String>,
+        sink_sender: Sender<TokenEvent>,
+    ) -> (tokio::task::JoinHandle<()>, std::net::SocketAddr) {
+        let admin = Some(AdminConfig {
+            enabled: true,
+            bearer_token,
+            ..Default::default()
+        });
+        let app_state = AppState::new(get_metrics().await, &HashMap::new(), &admin)
+            .with_sources(&HashMap::new(), sink_sender);
+        let app: Router = app_state.admin_state.router().await.with_state(app_state);
+        spawn_axum(app).await
+    }
+
+    #[test]
+    fn token_preview_never_exposes_full_token() {
+        assert_eq!(token_preview("abcdefghijklmnopqrstuvwxyz"), "abcd...wxyz");
+        assert_eq!(token_preview("short"), "****");
+        assert_eq!(token_preview(""), "");
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_admin_cache_snapshot_and_invalidate() -> anyhow::Result<()> {
+        TokenCache::cleanup().await;
+        let token = Token::new("abcdefghijklmnopqrstuvwxyz".to_string(), 5_000_000_000);
+        TokenCache::set("source-1".to_string(), vec![TokenContext::new("token-1".to_string(), token, 10)]).await?;
+
+        let sink_sender = channel::run();
+        let mut sink_receiver = sink_sender.subscribe();
+        let (handle, addr) = spawn_admin_with_sinks(None, sink_sender).await;
+        let client = build_reqwest_client();
+
+        let response = client.get(format!("http://{}/admin/cache", addr)).send().await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        let json: serde_json::Value = response.json().await?;
+        assert_eq!(json[0]["source_id"], "source-1");
+        assert_eq!(json[0]["token_id"], "token-1");
+        assert_eq!(json[0]["token_preview"], "abcd...wxyz");
+        assert_eq!(json[0]["should_update"], false);
+        assert!(json[0].get("value").is_none());
+
+        let response = client.post(format!("http://{}/admin/invalidate/source-1", addr)).send().await?;
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        assert_eq!(response.json::<serde_json::Value>().await?, serde_json::json!({ "invalidated": true, "tokens": 1 }));
+        assert!(TokenCache::get("source-1", "token-1").await.is_none(), "invalidated token must not be served");
+        assert!(TokenCache::get_all_by_source_id("source-1").await.is_empty());
+        let event = tokio::time::timeout(std::time::Duration::from_secs(5), sink_receiver.recv()).await??;
+        assert_eq!((event.source_id.as_str(), event.token_id.as_str(), event.kind), ("source-1", "token-1", TokenEventKind::Removed));
+        assert_eq!(event.token_context.token.value, "abcdefghijklmnopqrstuvwxyz");
+
+        // source is known but has no tokens now
+        let response = client.post(format!("http://{}/admin/invalidate/source-1", addr)).send().await?;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = client.post(format!("http://{}/admin/invalidate/unknown", addr)).send().await?;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        handle.abort();
+        TokenCache::cleanup().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_admin_requires_bearer_token() -> anyhow::Result<()> {
+        let (handle, addr) = spawn_admin(Some("admin-secret".to_string())).await;
+        let client = build_reqwest_client();
+        let url = format!("http://{}/admin/cache", addr);
+
+        assert_eq!(client.get(&url).send().await?.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(client.get(&url).bearer_auth("wrong").send().await?.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(client.get(&url).bearer_auth("admin-secret").send().await?.status(), StatusCode::OK);
+
+        handle.abort();
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_admin_invalidate_configured_source_and_api_key() -> anyhow::Result<()> {
+        TokenCache::cleanup().await;
+        let admin: AdminConfig = serde_yaml::from_str("enabled: true\napi_key: admin-key")?;
+        let sources = serde_yaml::from_str(
+            r#"
+configured:
+  type: http
+  request:
+    url: "http://127.0.0.1:1/token"
+    method: GET
+  parse:
+    tokens:
+      - id: token
+        parent: body
+        pointer: token
+        token_type: plain_text
+"#,
+        )?;
+        let app_state = AppState::new(get_metrics().await, &HashMap::new(), &Some(admin)).with_sources(&sources, channel::run());
+        let app: Router = app_state.admin_state.router().await.with_state(app_state);
+        let (handle, addr) = spawn_axum(app).await;
+        let client = build_reqwest_client();
+
+        let url = |source_id: &str| format!("http://{}/admin/invalidate/{}", addr, source_id);
+        assert_eq!(client.post(url("configured")).send().await?.status(), StatusCode::UNAUTHORIZED);
+        // configured source without cached tokens is known
+        let response = client.post(url("configured")).bearer_auth("admin-key").send().await?;
+        assert_eq!(response.status(), StatusCode::ACCEPTED);
+        assert_eq!(response.json::<serde_json::Value>().await?["tokens"], 0);
+        assert_eq!(client.post(url("unknown")).bearer_auth("admin-key").send().await?.status(), StatusCode::NOT_FOUND);
+
+        handle.abort();
+        Ok(())
+    }
+}
@@ -0,0 +1,114 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use serde::Serialize;
+use serde_json::json;
+
+use crate::cache::token_cache::TokenCache;
+use crate::config::settings::{ReadinessConfig, ServerConfig};
+use crate::server::server::AppState;
+
+/// Kubernetes style aliases, served next to `settings.server.health_path` and `ready_path`
+pub const HEALTHZ_PATH: &str = "/healthz";
+pub const READYZ_PATH: &str = "/readyz";
+
+static READY_MSG: &str = "ready";
+static NOT_READY_MSG: &str = "not_ready";
+
+#[derive(Clone)]
+pub struct HealthState {
+    health_path: String,
+    ready_path: String,
+    required_sources: Arc<Vec<String>>,
+}
+
+impl Default for HealthState {
+    fn default() -> Self {
+        Self {
+            health_path: "/health".to_string(),
+            ready_path: "/ready".to_string(),
+            required_sources: Arc::new(Vec::new()),
+        }
+    }
+}
+
+/// Readiness probe response body
+#[derive(Debug, Serialize)]
+pub struct ReadinessStatus {
+    pub status: &'static str,
+    /// required sources without an unexpired token
+    pub missing: Vec<String>,
+}
+
+impl HealthState {
+    pub fn new(server_config: &ServerConfig, readiness_config: &Option<ReadinessConfig>) -> Self {
+        Self {
+            health_path: server_config.health_path.clone(),
+            ready_path: server_config.ready_path.clone(),
+            required_sources: Arc::new(
+                readiness_config
+                    .as_ref()
+                    .map(|c| c.required_sources.clone())
+                    .unwrap_or_default(),
+            ),
+        }
+    }
+
+    /// Probe routes, registered even if no HTTP sinks are configured
+    pub async fn router(&self) -> Router<AppState> {
+        let mut health_paths = vec![self.health_path.as_str()];
+        let mut ready_paths = vec![self.ready_path.as_str()];
+        if !health_paths.contains(&HEALTHZ_PATH) {
+            health_paths.push(HEALTHZ_PATH);
+        }
+        if !ready_paths.contains(&READYZ_PATH) {
+            ready_paths.push(READYZ_PATH);
+        }
+        let mut router = Router::new();
+        for path in health_paths {
+            router = router.route(path, get(health));
+        }
+        for path in ready_paths {
+            router = router.route(path, get(ready));
+        }
+        router
+    }
+
+    /// Required sources without an unexpired token in cache
+    pub async fn missing_sources(&self) -> Vec<String> {
+        let ready: HashSet<String> = TokenCache::get_all()
+            .await
+            .into_iter()
+            .filter(|(_, token_context)| !token_context.should_remove())
+            .map(|(source_id, _)| source_id)
+            .collect();
+        self.required_sources
+            .iter()
+            .filter(|source_id| !ready.contains(*source_id))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Liveness: server is up
+async fn health() -> Response {
+    (StatusCode::OK, Json(json!({ "status": "ok" }))).into_response()
+}
+
+/// Readiness: every required source holds an unexpired token, token values are never returned
+async fn ready(State(state): State<AppState>) -> Response {
+    let missing = state.health_state.missing_sources().await;
+    let (status_code, status) = if missing.is_empty() {
+        (StatusCode::OK, READY_MSG)
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, NOT_READY_MSG)
+    };
+    (status_code, Json(ReadinessStatus { status, missing })).into_response()
+}
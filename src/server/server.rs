@@ -1,54 +1,138 @@
 use std::collections::HashMap;
-use anyhow::Result;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use anyhow::{Context, Result};
 use axum::{Router};
-use crate::config::settings::{SettingsConfig};
+use crate::cache::token_event::TokenEvent;
+use crate::config::settings::{AdminConfig, ReadinessConfig, ServerConfig, SettingsConfig};
 use crate::config::sinks::SinkConfig;
+use crate::config::sources::SourceConfig;
 use crate::observability::metrics::{get_metrics, Metrics};
 use crate::observability::routes::{MetricsState};
+use crate::server::admin::AdminState;
+use crate::server::health::HealthState;
 use crate::sinks::sink_http::{SinkHttpState};
+use tokio::sync::broadcast::Sender;
+use tokio_util::sync::CancellationToken;
+use tracing::info;
 
 #[derive(Clone)]
 pub struct AppState {
     pub metrics_state: MetricsState,
-    pub sink_http_state: SinkHttpState
+    pub sink_http_state: SinkHttpState,
+    pub admin_state: AdminState,
+    pub health_state: HealthState,
+    /// channel of active sinks, admin invalidation notifies sinks through it
+    pub sink_sender: Option<Arc<Sender<TokenEvent>>>,
 }
 
 impl AppState {
     pub fn new (
         metrics: &Metrics,
-        sinks: &HashMap<String, SinkConfig>
+        sinks: &HashMap<String, SinkConfig>,
+        admin: &Option<AdminConfig>,
     ) -> Self{
         Self { 
             metrics_state: MetricsState::new(metrics.registry.clone()), 
-            sink_http_state: SinkHttpState::new(sinks).unwrap() 
+            sink_http_state: SinkHttpState::new(sinks).unwrap(),
+            admin_state: AdminState::new(admin),
+            health_state: HealthState::default(),
+            sink_sender: None,
         }
     }
+
+    /// Configured sources and sink channel, used by admin invalidation
+    pub fn with_sources(mut self, sources: &HashMap<String, SourceConfig>, sink_sender: Sender<TokenEvent>) -> Self {
+        self.admin_state = self.admin_state.with_source_ids(sources.keys().cloned().collect());
+        self.sink_sender = Some(Arc::new(sink_sender));
+        self
+    }
+
+    /// Probe paths of `server` config, readiness requires tokens of `readiness.required_sources`
+    pub fn with_health(mut self, server: &ServerConfig, readiness: &Option<ReadinessConfig>) -> Self {
+        self.health_state = HealthState::new(server, readiness);
+        self
+    }
+
+    /// Serve reloaded sources and sinks, the running server picks them up with the next request
+    pub fn reload(&self, sources: &HashMap<String, SourceConfig>, sinks: &HashMap<String, SinkConfig>) -> Result<()> {
+        self.sink_http_state.update(sinks)?;
+        self.admin_state.set_source_ids(sources.keys().cloned().collect());
+        Ok(())
+    }
+}
+
+/// State of the server for configured sources and sinks
+pub async fn app_state(
+    settings_config: &SettingsConfig,
+    sources: &HashMap<String, SourceConfig>,
+    sinks: &HashMap<String, SinkConfig>,
+    sink_sender: Sender<TokenEvent>,
+) -> AppState {
+    let metrics = get_metrics().await;
+    AppState::new(metrics, sinks, &settings_config.admin)
+        .with_health(&settings_config.server, &settings_config.readiness)
+        .with_sources(sources, sink_sender)
 }
 
 /// Start one Axum server that dynamically dispatches on the configured sink paths.
 pub async fn start(
-    settings_config: &SettingsConfig, 
-    sinks: &HashMap<String, SinkConfig>
+    settings_config: &SettingsConfig,
+    state: AppState,
+    shutdown: CancellationToken,
 ) -> Result<()> {
     let metrics = get_metrics().await;
-    let state = AppState::new(metrics, sinks);
 
-    let app = Router::new()
+    let mut app = Router::new()
+        .merge(state.health_state.router().await)
         .merge(state.metrics_state.router(&settings_config.metrics).await)
-        .merge(state.sink_http_state.router().await)
-        .with_state(state);
-
-    if app.has_routes() {
-        let bind_addr  = &settings_config.server.host;
-        let port = &settings_config.server.port;
-        println!("address: {}, port: {}", bind_addr, port);
-        let listener = tokio::net::TcpListener::bind(format!("{}:{}", bind_addr, port))
-            .await
-            .unwrap();
-        metrics.up.set(1);
-        axum::serve(listener, app).await.unwrap();    
-    }
+        .merge(state.sink_http_state.router().await);
 
+    // admin routes: separate listener if admin port is configured, main server otherwise
+    let admin_router = state.admin_state.router().await;
+    let admin_server = match settings_config.admin.as_ref().filter(|admin| admin.enabled && admin.port.is_some()) {
+        Some(admin) => {
+            let bind_addr = admin.host.clone().unwrap_or_else(|| settings_config.server.host.clone());
+            let port = admin.port.clone().unwrap_or_default();
+            info!("admin address: {}, port: {}", bind_addr, port);
+            let listener = tokio::net::TcpListener::bind(format!("{}:{}", bind_addr, port))
+                .await
+                .with_context(|| format!("failed to bind admin listener {}:{}", bind_addr, port))?;
+            let admin_app = admin_router.with_state(state.clone());
+            Some(axum::serve(listener, admin_app).with_graceful_shutdown(shutdown.clone().cancelled_owned()))
+        }
+        None => {
+            app = app.merge(admin_router);
+            None
+        }
+    };
+    let admin_server = async {
+        match admin_server {
+            Some(admin_server) => admin_server.await.context("admin server failed"),
+            None => Ok(()),
+        }
+    };
+    let app = app.with_state(state);
+
+    let server = async {
+        if app.has_routes() {
+            let bind_addr  = &settings_config.server.host;
+            let port = &settings_config.server.port;
+            println!("address: {}, port: {}", bind_addr, port);
+            let listener = tokio::net::TcpListener::bind(format!("{}:{}", bind_addr, port))
+                .await
+                .unwrap();
+            metrics.up.set(1);
+            // consumer address of sink requests goes to the audit log
+            axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+                .with_graceful_shutdown(shutdown.cancelled_owned())
+                .await
+                .unwrap();
+            metrics.up.set(0);
+        }
+        Ok(())
+    };
+    tokio::try_join!(admin_server, server)?;
 
     Ok(())
 }
\ No newline at end of file
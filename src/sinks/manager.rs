@@ -1,29 +1,72 @@
 use std::collections::HashSet;
+use std::sync::Mutex;
+use crate::cache::token_cache::TokenCache;
 use std::{collections::HashMap, sync::Arc};
 use crate::config::sinks::SinkConfig;
-use crate::config::sinks::SinkMessage;
+use crate::cache::token_event::TokenEvent;
 use anyhow::Result;
 use tokio::task::JoinSet;
 use crate::config::sinks::SinkType;
-use tokio::sync::broadcast::Sender;
+use tokio::select;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::{Receiver, Sender};
+use tokio_util::sync::CancellationToken;
+use reqwest::Client;
+use tracing::warn;
+use crate::resilience::retry::RetrySettings;
 
 
 #[derive(Clone)]
 pub struct SinkManager {
     pub(crate) sinks: Arc<HashMap<String, SinkConfig>>,
+    /// file sink paths kept on stop, e.g. sinks surviving config reload
+    pub(crate) retained_paths: Arc<Mutex<HashSet<String>>>,
+    /// client and retry policy of push HTTP sinks (method POST/PUT), retry policy is shared by NATS, Redis, Kubernetes Secret and UDS sinks
+    pub(crate) push_client: Client,
+    pub(crate) push_retry: RetrySettings,
 }
 
 impl SinkManager {
     pub fn new(sinks: HashMap<String, SinkConfig>) -> Self {
         Self {
-            sinks: Arc::new(sinks)
+            sinks: Arc::new(sinks),
+            retained_paths: Arc::new(Mutex::new(HashSet::new())),
+            push_client: Client::new(),
+            push_retry: RetrySettings::default(),
         }
     }
 
+    /// Use shared request client and settings retry policy for push HTTP sinks
+    pub fn with_http_push(mut self, client: Client, retry: RetrySettings) -> Self {
+        self.push_client = client;
+        self.push_retry = retry;
+        self
+    }
+
+    /// Do not remove token files at these paths when sinks stop
+    pub fn retain_files_on_stop(&self, paths: HashSet<String>) {
+        *self.retained_paths.lock().unwrap() = paths;
+    }
+
     /// Start all propagation backends
     pub async fn start_active_sinks(
         &self,
-        sink_sender: Sender<SinkMessage>,
+        sink_sender: Sender<TokenEvent>,
+        shutdown: CancellationToken,
+    ) -> Result<()> {
+        // token cache events are forwarded to sinks, subscribed before replay so no change is missed
+        let token_events = TokenCache::subscribe_all().await;
+        let forwarder = forward_token_events(token_events, sink_sender.clone(), shutdown.clone());
+        let (forwarded, _) = tokio::join!(forwarder, self.run_sinks(sink_sender, shutdown));
+        forwarded
+    }
+
+    /// Run sinks on events of `sink_sender` until shutdown, cached tokens are replayed to them first.
+    /// Token cache events are forwarded to `sink_sender` by the caller, see `forward_token_events`
+    pub async fn run_sinks(
+        &self,
+        sink_sender: Sender<TokenEvent>,
+        shutdown: CancellationToken,
     ) -> Result<()> {
 
         let sink_types = self.sinks.iter().map(|entry| entry.1)
@@ -32,18 +75,48 @@ impl SinkManager {
         
         let sink_receiver_file = sink_sender.clone().subscribe();
         let sink_receiver_uds = sink_sender.clone().subscribe();
+        let sink_receiver_exec = sink_sender.clone().subscribe();
+        let sink_receiver_http_push = sink_sender.clone().subscribe();
+        let sink_receiver_nats = sink_sender.clone().subscribe();
+        let sink_receiver_redis = sink_sender.clone().subscribe();
+        let sink_receiver_kubernetes_secret = sink_sender.clone().subscribe();
+
+        // propagate already cached tokens (persistent cache, config reload), unchanged ones are skipped by sinks
+        for (source_id, token_context) in TokenCache::get_all().await {
+            let event = TokenEvent::updated(&source_id, token_context);
+            if self.sinks.values().any(|sink_config| sink_config.is_subscribed(&event)) {
+                let _ = sink_sender.send(event);
+            }
+        }
 
         let mut join_set = JoinSet::new();
         if sink_types.contains(&SinkType::File) {
-            join_set.spawn(self.clone().start_file_sinks(sink_receiver_file));
+            join_set.spawn(self.clone().start_file_sinks(sink_receiver_file, shutdown.clone()));
         }
 
-        if sink_types.contains(&SinkType::Http) {
-            // will be start with with sever if outes exists
+        // GET sinks will be start with with sever if outes exists
+        if self.sinks.values().any(SinkConfig::is_http_push) {
+            join_set.spawn(self.clone().start_http_push_sinks(sink_receiver_http_push, shutdown.clone()));
         }
             
         if sink_types.contains(&SinkType::Uds) {
-            join_set.spawn(self.clone().start_uds_sinks(sink_receiver_uds));
+            join_set.spawn(self.clone().start_uds_sinks(sink_receiver_uds, shutdown.clone()));
+        }
+
+        if sink_types.contains(&SinkType::Exec) {
+            join_set.spawn(self.clone().start_exec_sinks(sink_receiver_exec, shutdown.clone()));
+        }
+
+        if sink_types.contains(&SinkType::Nats) {
+            join_set.spawn(self.clone().start_nats_sinks(sink_receiver_nats, shutdown.clone()));
+        }
+
+        if sink_types.contains(&SinkType::Redis) {
+            join_set.spawn(self.clone().start_redis_sinks(sink_receiver_redis, shutdown.clone()));
+        }
+
+        if sink_types.contains(&SinkType::KubernetesSecret) {
+            join_set.spawn(self.clone().start_kubernetes_secret_sinks(sink_receiver_kubernetes_secret, shutdown.clone()));
         }
 
         let _ = join_set.join_all().await;
@@ -52,6 +125,23 @@ impl SinkManager {
     }
 }
 
+/// Forward token cache events to sinks until shutdown
+pub async fn forward_token_events(mut token_events: Receiver<TokenEvent>, sink_sender: Sender<TokenEvent>, shutdown: CancellationToken) -> Result<()> {
+    loop {
+        let received = select! {
+            _ = shutdown.cancelled() => break,
+            received = token_events.recv() => received,
+        };
+        match received {
+            Ok(event) => {
+                let _ = sink_sender.send(event);
+            }
+            Err(RecvError::Lagged(skipped)) => warn!("sinks lagged behind token cache, {} token events skipped", skipped),
+            Err(RecvError::Closed) => break,
+        }
+    }
+    Ok(())
+}
 
 pub enum SyncType {
     ADD,
@@ -3,4 +3,11 @@ pub mod sink_file_cache;
 pub mod sink_uds;
 pub mod sink_uds_cache;
 pub mod sink_http;
+pub mod sink_http_push;
+pub mod sink_exec;
+pub mod sink_nats;
+pub mod sink_redis;
+pub mod sink_k8s_secret;
+pub mod sink_dry_run;
+pub mod sink_check;
 pub mod manager;
\ No newline at end of file
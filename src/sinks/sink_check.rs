@@ -0,0 +1,135 @@
+use std::fmt;
+
+use tracing::{info, warn};
+
+use crate::cache::token_cache::TokenCache;
+use crate::config::sinks::{SinkConfig, SinkType};
+use crate::sinks::manager::SinkManager;
+use crate::sinks::sink_file::render_file_content;
+
+/// Result of checking one sink against token cache
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SinkCheckStatus {
+    Ok,
+    /// cached token is past its expiration
+    Expired,
+    /// token is not in cache, e.g. its source failed
+    Missing,
+    /// file sink path is absent or its content differs from the rendered token
+    FileMismatch,
+}
+
+impl fmt::Display for SinkCheckStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SinkCheckStatus::Ok => "OK",
+            SinkCheckStatus::Expired => "EXPIRED",
+            SinkCheckStatus::Missing => "MISSING",
+            SinkCheckStatus::FileMismatch => "FILE_MISMATCH",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SinkCheck {
+    pub sink_id: String,
+    pub source_id: String,
+    pub token_id: String,
+    pub status: SinkCheckStatus,
+}
+
+/// Per-sink status of a check run
+#[derive(Debug, Clone)]
+pub struct CheckSummary {
+    pub sinks: Vec<SinkCheck>,
+}
+
+impl CheckSummary {
+    pub fn is_success(&self) -> bool {
+        self.sinks.iter().all(|sink| sink.status == SinkCheckStatus::Ok)
+    }
+}
+
+impl SinkManager {
+    /// Status of every sink against token cache, file sinks are compared with the file at `path`;
+    /// sinks are not started and nothing is written
+    pub async fn check_sinks_once(&self) -> Vec<SinkCheck> {
+        let mut sink_ids: Vec<&String> = self.sinks.keys().collect();
+        sink_ids.sort();
+        let mut checks = Vec::with_capacity(sink_ids.len());
+        for sink_id in sink_ids {
+            let cfg = &self.sinks[sink_id];
+            let status = check_sink(cfg).await;
+            match status {
+                SinkCheckStatus::Ok => info!("check: sink '{}' has token {}.{}", sink_id, cfg.source_id, cfg.token_id),
+                _ => warn!("check: sink '{}' token {}.{}: {}", sink_id, cfg.source_id, cfg.token_id, status),
+            }
+            checks.push(SinkCheck {
+                sink_id: sink_id.to_owned(),
+                source_id: cfg.source_id.to_owned(),
+                token_id: cfg.token_id.to_owned(),
+                status,
+            });
+        }
+        checks
+    }
+}
+
+async fn check_sink(cfg: &SinkConfig) -> SinkCheckStatus {
+    let Some(token_context) = TokenCache::get(&cfg.source_id, &cfg.token_id).await else {
+        return SinkCheckStatus::Missing;
+    };
+    if token_context.should_remove() {
+        return SinkCheckStatus::Expired;
+    }
+    if cfg.sink_type != SinkType::File {
+        return SinkCheckStatus::Ok;
+    }
+    let expected = match render_file_content(cfg, token_context).await {
+        Ok(expected) => expected,
+        Err(err) => {
+            warn!("check: sink file '{}': {}", cfg.sink_id, err);
+            return SinkCheckStatus::FileMismatch;
+        }
+    };
+    match tokio::fs::read_to_string(&cfg.path).await {
+        Ok(content) if content == expected => SinkCheckStatus::Ok,
+        Ok(_) => SinkCheckStatus::FileMismatch,
+        Err(err) => {
+            warn!("check: sink file '{}': failed to read '{}': {}", cfg.sink_id, cfg.path, err);
+            SinkCheckStatus::FileMismatch
+        }
+    }
+}
+
+impl fmt::Display for CheckSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let header = ["sink_name", "source_id", "token_id", "status"];
+        let rows: Vec<[String; 4]> = self
+            .sinks
+            .iter()
+            .map(|sink| [sink.sink_id.clone(), sink.source_id.clone(), sink.token_id.clone(), sink.status.to_string()])
+            .collect();
+        let mut widths = header.map(str::len);
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.len());
+            }
+        }
+        let header = header.map(str::to_owned);
+        for row in std::iter::once(&header).chain(&rows) {
+            writeln!(
+                f,
+                "{:<w0$} | {:<w1$} | {:<w2$} | {}",
+                row[0],
+                row[1],
+                row[2],
+                row[3],
+                w0 = widths[0],
+                w1 = widths[1],
+                w2 = widths[2]
+            )?;
+        }
+        Ok(())
+    }
+}
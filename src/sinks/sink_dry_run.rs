@@ -0,0 +1,152 @@
+use std::fmt;
+
+use chrono::{SecondsFormat, TimeZone, Utc};
+use tracing::{info, warn};
+
+use crate::cache::token_cache::TokenCache;
+use crate::config::sinks::{SinkConfig, SinkType};
+use crate::sinks::manager::SinkManager;
+use crate::sinks::sink_file::render_file_content;
+use crate::sinks::sink_redis::render_redis_key;
+use crate::sources::executor::token_fetch::SourceFetchOutcome;
+
+/// Leading characters of a token kept in dry-run previews
+pub const DRY_RUN_PREVIEW_CHARS: usize = 4;
+
+/// What a sink would write for its token, nothing is written in dry-run
+#[derive(Debug, Clone)]
+pub struct SinkPlan {
+    pub sink_id: String,
+    pub sink_type: SinkType,
+    /// path, URL, subject, key or command the sink writes to
+    pub target: String,
+    pub token_id: String,
+    pub exp_unix_ts: Option<u64>,
+    /// redacted content, e.g. `eyJh…(612 bytes)`; `None` if the sink has nothing to write
+    pub preview: Option<String>,
+    /// why the sink would not write
+    pub skipped: Option<String>,
+}
+
+/// Per-source fetch result and per-sink planned action of a dry run
+#[derive(Debug, Clone)]
+pub struct DryRunSummary {
+    pub sources: Vec<SourceFetchOutcome>,
+    pub sinks: Vec<SinkPlan>,
+}
+
+impl DryRunSummary {
+    pub fn is_success(&self) -> bool {
+        self.sources.iter().all(|source| source.error.is_none())
+    }
+}
+
+impl SinkManager {
+    /// Plan of every sink against token cache, sinks are not started and nothing is written
+    pub async fn plan_sinks_once(&self) -> Vec<SinkPlan> {
+        let mut sink_ids: Vec<&String> = self.sinks.keys().collect();
+        sink_ids.sort();
+        let mut plans = Vec::with_capacity(sink_ids.len());
+        for sink_id in sink_ids {
+            let plan = plan_sink(sink_id, &self.sinks[sink_id]).await;
+            match &plan.skipped {
+                None => info!(
+                    "dry-run: sink '{}' would write token '{}' to '{}': {}",
+                    plan.sink_id,
+                    plan.token_id,
+                    plan.target,
+                    plan.preview.as_deref().unwrap_or_default()
+                ),
+                Some(reason) => warn!("dry-run: sink '{}' would not write: {}", plan.sink_id, reason),
+            }
+            plans.push(plan);
+        }
+        plans
+    }
+}
+
+async fn plan_sink(sink_id: &str, cfg: &SinkConfig) -> SinkPlan {
+    let mut plan = SinkPlan {
+        sink_id: sink_id.to_owned(),
+        sink_type: cfg.sink_type,
+        target: sink_target(cfg),
+        token_id: cfg.token_id.to_owned(),
+        exp_unix_ts: None,
+        preview: None,
+        skipped: None,
+    };
+    let Some(token_context) = TokenCache::get(&cfg.source_id, &cfg.token_id).await else {
+        plan.skipped = Some(format!("token {}.{} is absent", cfg.source_id, cfg.token_id));
+        return plan;
+    };
+    plan.exp_unix_ts = Some(token_context.token.exp_unix_ts);
+    let content = match cfg.sink_type {
+        SinkType::File => render_file_content(cfg, token_context).await,
+        _ => Ok(token_context.token.value),
+    };
+    match content {
+        Ok(content) => plan.preview = Some(redacted_preview(&content)),
+        Err(err) => plan.skipped = Some(err.to_string()),
+    }
+    plan
+}
+
+/// Where the sink propagates the token
+fn sink_target(cfg: &SinkConfig) -> String {
+    match cfg.sink_type {
+        _ if cfg.is_http_push() => cfg.target_url.clone().unwrap_or_default(),
+        SinkType::Exec => cfg.exec.as_ref().map(|exec| exec.command.clone()).unwrap_or_default(),
+        SinkType::Nats => cfg.nats.as_ref().map(|nats| nats.subject.clone()).unwrap_or_default(),
+        SinkType::Redis => cfg
+            .redis
+            .as_ref()
+            .map(|redis| render_redis_key(&redis.key_template, &cfg.source_id, &cfg.token_id))
+            .unwrap_or_default(),
+        SinkType::KubernetesSecret => cfg
+            .kubernetes_secret
+            .as_ref()
+            .map(|secret| format!("{}/{}:{}", secret.namespace, secret.secret_name, secret.key))
+            .unwrap_or_default(),
+        _ => cfg.path.clone(),
+    }
+}
+
+/// First `DRY_RUN_PREVIEW_CHARS` characters and total length, e.g. `eyJh…(612 bytes)`
+pub fn redacted_preview(content: &str) -> String {
+    let head: String = content.chars().take(DRY_RUN_PREVIEW_CHARS).collect();
+    format!("{}…({} bytes)", head, content.len())
+}
+
+impl fmt::Display for DryRunSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "sources:")?;
+        for source in &self.sources {
+            match &source.error {
+                None => writeln!(f, "  {}: ok", source.source_id)?,
+                Some(err) => writeln!(f, "  {}: failed: {}", source.source_id, err)?,
+            }
+        }
+        writeln!(f, "sinks:")?;
+        for sink in &self.sinks {
+            write!(f, "  {} ({:?}) '{}': ", sink.sink_id, sink.sink_type, sink.target)?;
+            match (&sink.skipped, &sink.preview) {
+                (Some(reason), _) => writeln!(f, "skip, {}", reason)?,
+                (None, preview) => {
+                    let exp = sink
+                        .exp_unix_ts
+                        .and_then(|exp| Utc.timestamp_opt(exp as i64, 0).single())
+                        .map(|exp| exp.to_rfc3339_opts(SecondsFormat::Secs, true))
+                        .unwrap_or_default();
+                    writeln!(
+                        f,
+                        "would write token '{}' expiring {}: {}",
+                        sink.token_id,
+                        exp,
+                        preview.as_deref().unwrap_or_default()
+                    )?
+                }
+            }
+        }
+        Ok(())
+    }
+}
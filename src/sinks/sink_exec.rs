@@ -0,0 +1,228 @@
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::time::Instant;
+
+use anyhow::Result;
+use tokio::process::{Child, Command};
+use tokio::select;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn, Instrument};
+
+use crate::cache::token_cache::TokenCache;
+use crate::cache::token_event::TokenEvent;
+use crate::config::sinks::{ExecSinkConfig, SinkConfig, SinkType};
+use crate::observability::audit::{self, AuditEvent, AuditEventType};
+use crate::observability::metrics::{get_metrics, SINK_ENABLED_LABEL};
+use crate::observability::otel::{sink_propagate_span, sink_resync_span};
+use crate::sinks::manager::SinkManager;
+
+static EXEC_MSG: &str = "exec";
+static ERROR_MSG: &str = "error";
+
+/// Running child of exec sink and the token it was started with
+struct ExecChild {
+    child: Child,
+    token_value: String,
+}
+
+impl SinkManager {
+    /// Run exec sink commands with token env var until shutdown, then kill them.
+    /// Events missed by lagging behind the channel are recovered by syncing every command with `TokenCache`
+    pub async fn start_exec_sinks(self, mut rx: Receiver<TokenEvent>, shutdown: CancellationToken) -> Result<()> {
+        info!("start sink 'type: exec'");
+        let metrics = get_metrics().await;
+        // sink_id -> running child
+        let mut children: HashMap<String, ExecChild> = HashMap::new();
+        loop {
+            let received = select! {
+                _ = shutdown.cancelled() => {
+                    info!("sink exec: shutdown requested");
+                    break;
+                }
+                received = rx.recv() => received,
+            };
+            let event = match received {
+                Ok(event) => Some(event),
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("sink exec: lagged, {} token events skipped, syncing all commands with token cache", skipped);
+                    None
+                }
+                Err(RecvError::Closed) => break,
+            };
+            for (sink_id, cfg) in self.sinks.iter() {
+                if cfg.sink_type != SinkType::Exec || event.as_ref().is_some_and(|event| !cfg.is_subscribed(event)) {
+                    continue;
+                }
+                let source_id = &cfg.source_id;
+                let Some(exec) = &cfg.exec else {
+                    continue;
+                };
+                let start = Instant::now();
+                let propagated = sync_child(&mut children, sink_id, cfg, exec)
+                    .instrument(match &event {
+                        Some(event) => sink_propagate_span(EXEC_MSG, sink_id, event),
+                        None => sink_resync_span(EXEC_MSG, sink_id, source_id, &cfg.token_id),
+                    })
+                    .await;
+                match propagated {
+                    Ok(true) => {
+                        metrics
+                            .sink_propagations
+                            .with_label_values(&[cfg.sink_id.as_str(), EXEC_MSG, source_id.as_str(), cfg.token_id.as_str(), SINK_ENABLED_LABEL])
+                            .inc();
+                        audit::emit(AuditEvent::new(AuditEventType::SinkPropagation, source_id).token(&cfg.token_id, None).sink(&cfg.sink_id));
+                        metrics.sink_duration.with_label_values(&[cfg.sink_id.as_str()]).observe(start.elapsed().as_secs_f64());
+                    }
+                    Ok(false) => {}
+                    Err(err) => {
+                        error!("sink exec '{}': {}", sink_id, err);
+                        metrics.sink_failures.with_label_values(&[cfg.sink_id.as_str(), ERROR_MSG]).inc();
+                    }
+                }
+            }
+        }
+
+        for (sink_id, mut exec_child) in children {
+            info!("sink exec '{}': stopping command", sink_id);
+            let _ = exec_child.child.kill().await;
+        }
+        Ok(())
+    }
+}
+
+/// Start command if it is not running, restart it on token change if configured,
+/// returns true if command was (re)started
+async fn sync_child(
+    children: &mut HashMap<String, ExecChild>,
+    sink_id: &str,
+    cfg: &SinkConfig,
+    exec: &ExecSinkConfig,
+) -> Result<bool> {
+    let Some(token_context) = TokenCache::get(&cfg.source_id, &cfg.token_id).await else {
+        warn!("sink exec '{}': token '{}' is absent, command is left as is", sink_id, cfg.token_id);
+        return Ok(false);
+    };
+    let token_value = token_context.token.value;
+
+    if let Some(exec_child) = children.get_mut(sink_id) {
+        let is_running = exec_child.child.try_wait()?.is_none();
+        if is_running && (exec_child.token_value == token_value || !exec.restart_on_refresh) {
+            return Ok(false);
+        }
+        if is_running {
+            info!("sink exec '{}': token changed, restarting command", sink_id);
+            exec_child.child.kill().await?;
+        }
+    }
+
+    let child = Command::new(&exec.command)
+        .args(&exec.args)
+        .env(&exec.env_name, &token_value)
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("failed to start '{}': {}", exec.command, e))?;
+    info!("sink exec '{}': started '{}', pid {:?}", sink_id, exec.command, child.id());
+    children.insert(sink_id.to_owned(), ExecChild { child, token_value });
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use std::time::Duration;
+
+    use serial_test::serial;
+    use tempfile::tempdir;
+
+    use crate::cache::token::Token;
+    use crate::cache::token_context::TokenContext;
+    use crate::tests::common::{json, sink_config};
+    use crate::utils::channel;
+
+    async fn wait_for_file(path: &std::path::Path, expected: &str) {
+        let written = tokio::time::timeout(Duration::from_secs(5), async {
+            while tokio::fs::read_to_string(path).await.ok().as_deref().map(str::trim) != Some(expected) {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await;
+        assert!(written.is_ok(), "command must write '{}'", expected);
+    }
+
+    /// Exec sink running a script that writes `EXEC_SINK_TOKEN` into `out_path` and stays alive
+    fn exec_sink_config(dir: &std::path::Path, out_path: &std::path::Path, source_id: &str) -> anyhow::Result<SinkConfig> {
+        let script_path = dir.join("print_token.sh");
+        std::fs::write(
+            &script_path,
+            format!("#!/bin/sh\necho \"$EXEC_SINK_TOKEN\" > {}\nexec sleep 30\n", out_path.display()),
+        )?;
+        std::fs::set_permissions(&script_path, std::fs::Permissions::from_mode(0o755))?;
+
+        let exec = json!({
+            "command": script_path.to_str().unwrap(),
+            "env_name": "EXEC_SINK_TOKEN",
+            "restart_on_refresh": true,
+        });
+        Ok(sink_config("exec", "exec_sink", source_id, json!({ "token_id": "token", "exec": exec })))
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_exec_sink_restarts_command_with_refreshed_token() -> anyhow::Result<()> {
+        TokenCache::cleanup().await;
+        let dir = tempdir()?;
+        let out_path = dir.path().join("env_out");
+        let source_id = "exec_source".to_string();
+        let sink_config = exec_sink_config(dir.path(), &out_path, &source_id)?;
+        let sink_manager = SinkManager::new(HashMap::from([("exec_sink".to_string(), sink_config)]));
+        let sink_sender = channel::run();
+        let shutdown = CancellationToken::new();
+        let worker = tokio::spawn(sink_manager.start_exec_sinks(sink_sender.subscribe(), shutdown.clone()));
+
+        TokenCache::set(source_id.clone(), vec![TokenContext::new("token".to_string(), Token::new("first".to_string(), 5_000_000_000), 10)]).await?;
+        sink_sender.send(TokenEvent::updated(&source_id, TokenCache::get(&source_id, "token").await.unwrap()))?;
+        wait_for_file(&out_path, "first").await;
+
+        TokenCache::set(source_id.clone(), vec![TokenContext::new("token".to_string(), Token::new("second".to_string(), 5_000_000_100), 10)]).await?;
+        sink_sender.send(TokenEvent::updated(&source_id, TokenCache::get(&source_id, "token").await.unwrap()))?;
+        wait_for_file(&out_path, "second").await;
+
+        shutdown.cancel();
+        tokio::time::timeout(Duration::from_secs(5), worker).await???;
+        TokenCache::cleanup().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_exec_sink_resyncs_with_token_cache_after_lagging() -> anyhow::Result<()> {
+        TokenCache::cleanup().await;
+        let dir = tempdir()?;
+        let out_path = dir.path().join("env_out");
+        let source_id = "exec_source".to_string();
+        let sink_config = exec_sink_config(dir.path(), &out_path, &source_id)?;
+        let sink_manager = SinkManager::new(HashMap::from([("exec_sink".to_string(), sink_config)]));
+        let sink_sender = channel::run();
+        let sink_receiver = sink_sender.subscribe();
+
+        // the event of the sink token is pushed out of the channel by events of another source before the sink reads it
+        TokenCache::set(source_id.clone(), vec![TokenContext::new("token".to_string(), Token::new("cached".to_string(), 5_000_000_000), 10)]).await?;
+        sink_sender.send(TokenEvent::updated(&source_id, TokenCache::get(&source_id, "token").await.unwrap()))?;
+        for _ in 0..300 {
+            sink_sender.send(TokenEvent::updated("other_source", TokenContext::new("token".to_string(), Token::new("other".to_string(), 5_000_000_000), 10)))?;
+        }
+
+        let shutdown = CancellationToken::new();
+        let worker = tokio::spawn(sink_manager.start_exec_sinks(sink_receiver, shutdown.clone()));
+        wait_for_file(&out_path, "cached").await;
+
+        shutdown.cancel();
+        tokio::time::timeout(Duration::from_secs(5), worker).await???;
+        TokenCache::cleanup().await;
+        Ok(())
+    }
+}
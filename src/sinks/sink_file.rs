@@ -1,117 +1,455 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fs::Permissions;
 use std::io::ErrorKind;
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Instant;
 
 use crate::cache::token::TOKEN_VALUE_STUB;
 use crate::cache::token_cache::TokenCache;
 use crate::cache::token_context::TokenContext;
-use crate::config::sinks::{SinkConfig, SinkMessage, SinkType};
-use crate::observability::metrics::get_metrics;
+use crate::cache::token_event::TokenEvent;
+use crate::config::sinks::{FileSinkFormat, SinkConfig, SinkType};
+use crate::observability::audit::{self, AuditEvent, AuditEventType};
+use crate::observability::metrics::{get_metrics, SINK_ENABLED_LABEL};
+use crate::observability::otel::{sink_propagate_span, sink_resync_span};
 use crate::sinks::manager::{SinkManager, SyncType};
 use crate::sinks::sink_file_cache::{SinkFileTokenMeta, SinkFileCache};
-use anyhow::Result;
-use tokio::signal::unix::{signal, SignalKind};
-use tokio::{fs, join, select};
+use anyhow::{anyhow, Result};
+use chrono::{SecondsFormat, TimeZone, Utc};
+use regex::Regex;
+use serde::Serialize;
+use serde_json::json;
+use tokio::io::AsyncWriteExt;
+use tokio::{fs, select};
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::broadcast::Receiver;
-use tracing::{error, info};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn, Instrument};
+
+pub const SINK_FILE_MODE_DEFAULT: u32 = 0o600;
+/// Symlink to the current payload dir for `atomic: true` sinks, as kubelet does for projected volumes
+pub const SINK_FILE_DATA_DIR: &str = "..data";
+pub(crate) const ETC_PASSWD: &str = "/etc/passwd";
+pub(crate) const ETC_GROUP: &str = "/etc/group";
+/// `{{source.token_id}}` or `{{expiration:unix}}`, surrounding spaces allowed
+pub(crate) const FILE_TEMPLATE_PLACEHOLDER: &str = r"\{\{\s*([a-zA-Z0-9_\.:-]+)\s*\}\}";
+/// `..<%Y_%m_%d_%H_%M_%S>_<pid>_<counter>` payload dir name generated by `write_file_data_dir_swap`
+pub(crate) const PAYLOAD_DIR_NAME: &str = r"^\.\.\d{4}(_\d{2}){5}_\d+_\d+$";
+
+// Makes payload dir names unique within one process
+static DATA_DIR_COUNTER: AtomicU64 = AtomicU64::new(0);
 
 static FILE_MSG: &'static str = "file";
 static  ERROR_MSG: &'static str =  "error";
 
 impl SinkManager {
     // Token cache: source_id -> token_id -> expiration_at
-    pub async fn start_file_sinks(self, rx: Receiver<SinkMessage>) -> Result<()> {
+    /// Propagate tokens to files until shutdown, then remove token files
+    pub async fn start_file_sinks(self, rx: Receiver<TokenEvent>, shutdown: CancellationToken) -> Result<()> {
         info!("start sink 'type: file'");
-        let cleanup = cleanup_resourses(self.sinks.clone());
-        let worker = sink_http_worker(self.sinks.clone(), rx);
-        
-        let _ = join!(cleanup, worker);
+        select! {
+            _ = shutdown.cancelled() => {
+                info!("sink file: shutdown requested, cleaning up token files");
+            }
+            _ = sink_http_worker(self.sinks.clone(), rx) => {}
+        }
+        let retained_paths = self.retained_paths.lock().unwrap().clone();
+        cleanup_stored_tokens_after_cancelling(self.sinks.clone(), &retained_paths).await
+    }
+
+    /// Write every file sink once from token cache, files are kept; error lists sinks not written
+    pub async fn write_file_sinks_once(&self) -> Result<()> {
+        let mut failed: Vec<String> = Vec::new();
+        for (sink_id, cfg) in self.sinks.iter().filter(|(_, cfg)| cfg.sink_type == SinkType::File) {
+            let written = async {
+                let token_context = TokenCache::get(&cfg.source_id, &cfg.token_id)
+                    .await
+                    .ok_or_else(|| anyhow!("token {}.{} is absent", cfg.source_id, cfg.token_id))?;
+                let content = render_file_content(cfg, token_context).await?;
+                write_token_file(cfg, content.as_bytes()).await?;
+                Ok::<_, anyhow::Error>(())
+            }
+            .await;
+            match written {
+                Ok(_) => info!("oneshot: sink file '{}' written to '{}'", sink_id, cfg.path),
+                Err(err) => {
+                    error!("oneshot: sink file '{}': {}", sink_id, err);
+                    failed.push(sink_id.to_owned());
+                }
+            }
+        }
+        if !failed.is_empty() {
+            return Err(anyhow!("file sinks failed: {}", failed.join(", ")));
+        }
         Ok(())
     }
 }
 
-async fn sink_http_worker(sinks: Arc<HashMap<String, SinkConfig>>, mut rx: Receiver<SinkMessage>) {
+async fn sink_http_worker(sinks: Arc<HashMap<String, SinkConfig>>, mut rx: Receiver<TokenEvent>) {
     let metrics = get_metrics().await;
     loop {
-        if let Ok(message) = rx.recv().await {
-            let start = Instant::now();
-            let source_id = message.0;
-                
-            for (_, cfg) in sinks.iter() {
-                if cfg.sink_type != SinkType::File || cfg.source_id != source_id {
+        let event = match rx.recv().await {
+            Ok(event) => Some(event),
+            Err(RecvError::Lagged(skipped)) => {
+                warn!("sink file: lagged, {} token events skipped, syncing all files with token cache", skipped);
+                None
+            }
+            Err(RecvError::Closed) => return,
+        };
+        let start = Instant::now();
+            
+        for (_, cfg) in sinks.iter() {
+            if cfg.sink_type != SinkType::File || event.as_ref().is_some_and(|event| !cfg.is_subscribed(event)) {
+                continue;
+            }
+            let source_id = cfg.source_id.to_owned();
+            info!("sink file:: sink file for source_id: {}", source_id);
+            let token_context_opt = TokenCache::get(&cfg.source_id, &cfg.token_id).await;
+
+            let content_opt = if let Some(token_context)= token_context_opt {
+                let mut token_contexts = vec![token_context.clone()];
+                for token_id in cfg.token_ids().into_iter().skip(1) {
+                    token_contexts.extend(TokenCache::get(&cfg.source_id, token_id).await);
+                }
+                // skip storing if all sink tokens with the same exp already exist in cache
+                if check_if_tokens_should_be_skipped(&source_id, &token_contexts).await {
                     continue;
                 }
-                info!("sink file:: sink file for source_id: {}", source_id);
-                let token_context_opt = TokenCache::get(&cfg.source_id, &cfg.token_id).await;
-
-                let token_opt = if let Some(token_context)= token_context_opt {
-                    // skip storing if token iwth the same exp already exists in cache
-                    if check_if_token_should_be_skipped(&source_id, &token_context).await {
+                if token_contexts.iter().any(|token_context| token_context.is_stale) {
+                    warn!("sink file '{}': token {}.{} is stale, source is failing", cfg.sink_id, cfg.source_id, cfg.token_id);
+                }
+                // render before local cache sync: on failure previous file is kept and next message retries
+                let content = match render_file_content(cfg, token_context).await {
+                    Ok(content) => content,
+                    Err(err) => {
+                        error!("sink file '{}': {}", cfg.sink_id, err);
+                        metrics.sink_failures.with_label_values(&[&cfg.sink_id.as_str(), &ERROR_MSG]).inc();
                         continue;
                     }
+                };
+                for token_context in &token_contexts {
                     sync_token_with_local_cache(&source_id, &cfg.path, &token_context.id, token_context
                         .token.exp_unix_ts, SyncType::ADD).await;
-                    Some(token_context.token)
-
-                // removed tokes
-                } else {
-                    info!("sink file: token  writes to {}", &cfg.path);
-                    // remove from local cache
-                    sync_token_with_local_cache(&source_id, &cfg.path, &&cfg.token_id, 0, SyncType::REMOVE).await;
-                    // cleanup token
-                    None
-                };
+                }
+                Some(content)
+
+            // removed tokes
+            } else {
+                info!("sink file: token  writes to {}", &cfg.path);
+                // remove from local cache
+                sync_token_with_local_cache(&source_id, &cfg.path, &&cfg.token_id, 0, SyncType::REMOVE).await;
+                // cleanup token
+                None
+            };
+
+            match content_opt {
+                Some(content) => {
+                    // store new token
+                    info!("token id '{}' writes, path '{}'", &cfg.token_id, &cfg.path);
+                    let _ = write_token_file(cfg, content.as_bytes())
+                    .instrument(match &event {
+                        Some(event) => sink_propagate_span(FILE_MSG, &cfg.sink_id, event),
+                        None => sink_resync_span(FILE_MSG, &cfg.sink_id, &source_id, &cfg.token_id),
+                    })
+                    .await
+                    .inspect(|_| {
+                            metrics
+                                .sink_propagations
+                                .with_label_values(&[
+                                    &cfg.sink_id.as_str(),
+                                    &FILE_MSG,
+                                    &source_id.as_str(),
+                                    &cfg.token_id.as_str(),
+                                    &SINK_ENABLED_LABEL,
+                                ])
+                                .inc();
+                            audit::emit(AuditEvent::new(AuditEventType::SinkPropagation, &source_id).token(&cfg.token_id, None).sink(&cfg.sink_id));
+                            metrics
+                                .sink_duration
+                                .with_label_values(&[&cfg.sink_id.as_str()])
+                                .observe(start.elapsed().as_secs_f64());
+                    })
+                        .inspect_err(|err| {
+                            error!("{}", err);
+                            metrics.sink_failures.with_label_values(&[&cfg.sink_id.as_str(), &ERROR_MSG]).inc();
+                        });    
+                },
+                None => {
+                    // cleanup content
+                    info!("token id '{}' cleanup, path '{}'", &cfg.token_id, &cfg.path);
+                    let _ = write_token_file(cfg, file_content_stub(cfg).as_bytes()).await
+                        .inspect_err(|err| {
+                            error!("{}", err);
+                            metrics.sink_failures.with_label_values(&[&cfg.sink_id.as_str(), &ERROR_MSG]).inc();
+                        });   
+                },
+            }
+        }
+    }
+}
+
+/// File content of sink token: rendered template, JSON envelope or raw token value
+pub(crate) async fn render_file_content(cfg: &SinkConfig, token_context: TokenContext) -> Result<String> {
+    if let Some(template) = &cfg.template {
+        return render_file_template(template, cfg).await;
+    }
+    match cfg.format.unwrap_or_default() {
+        FileSinkFormat::Raw => Ok(token_context.token.value),
+        FileSinkFormat::JsonEnvelope => Ok(serde_json::to_string(&FileTokenEnvelope {
+            token: &token_context.token.value,
+            exp_unix_ts: token_context.token.exp_unix_ts,
+            fetched_at_unix_ts: token_context.received_at_unix_ts,
+            type_hint: cfg.type_hint.as_deref(),
+        })?),
+    }
+}
+
+/// File content written when sink token is removed, `stub_value` of the sink if set
+pub(crate) fn file_content_stub(cfg: &SinkConfig) -> String {
+    if let Some(stub_value) = &cfg.stub_value {
+        return stub_value.to_owned();
+    }
+    match cfg.format.unwrap_or_default() {
+        FileSinkFormat::Raw => TOKEN_VALUE_STUB.to_string(),
+        FileSinkFormat::JsonEnvelope => json!({ "token": TOKEN_VALUE_STUB }).to_string(),
+    }
+}
+
+#[derive(Serialize)]
+struct FileTokenEnvelope<'a> {
+    token: &'a str,
+    exp_unix_ts: u64,
+    fetched_at_unix_ts: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    type_hint: Option<&'a str>,
+}
+
+/// Render file sink template against token cache: `{{source.token_id}}` gives token value,
+/// `{{expiration:<format>}}` the expiration of sink token; any unresolved placeholder is an error
+pub(crate) async fn render_file_template(template: &str, cfg: &SinkConfig) -> Result<String> {
+    let regex = Regex::new(FILE_TEMPLATE_PLACEHOLDER)?;
+    let mut result = String::with_capacity(template.len());
+    let mut last = 0;
+    for caps in regex.captures_iter(template) {
+        let placeholder = caps.get(0).unwrap();
+        let key = caps.get(1).unwrap().as_str();
+        let value = match key.split_once(':') {
+            Some(("expiration", format)) => {
+                let token_context = TokenCache::get(&cfg.source_id, &cfg.token_id)
+                    .await
+                    .ok_or_else(|| anyhow!("token {}.{} is absent", cfg.source_id, cfg.token_id))?;
+                format_expiration(token_context.token.exp_unix_ts, format)?
+            }
+            Some(_) => return Err(anyhow!("unknown template helper '{{{{{}}}}}'", key)),
+            None => {
+                let (source, id) = key
+                    .split_once('.')
+                    .ok_or_else(|| anyhow!("placeholder '{{{{{}}}}}' must be 'source.token_id'", key))?;
+                TokenCache::get(source, id)
+                    .await
+                    .ok_or_else(|| anyhow!("token {}.{} is absent", source, id))?
+                    .token
+                    .value
+            }
+        };
+        result.push_str(&template[last..placeholder.start()]);
+        result.push_str(&value);
+        last = placeholder.end();
+    }
+    result.push_str(&template[last..]);
+    Ok(result)
+}
+
+/// `unix`, `rfc3339` or `seconds` (remaining) representation of expiration
+pub(crate) fn format_expiration(exp_unix_ts: u64, format: &str) -> Result<String> {
+    match format {
+        "unix" => Ok(exp_unix_ts.to_string()),
+        "rfc3339" => Utc
+            .timestamp_opt(exp_unix_ts as i64, 0)
+            .single()
+            .map(|exp| exp.to_rfc3339_opts(SecondsFormat::Secs, true))
+            .ok_or_else(|| anyhow!("invalid expiration {}", exp_unix_ts)),
+        "seconds" => Ok((exp_unix_ts as i64 - Utc::now().timestamp()).max(0).to_string()),
+        _ => Err(anyhow!("unknown expiration format '{}', expected unix, rfc3339 or seconds", format)),
+    }
+}
+
+/// Write token file according to sink options: parent dirs creation, rename or `..data` symlink swap
+pub(crate) async fn write_token_file(cfg: &SinkConfig, content: &[u8]) -> std::io::Result<()> {
+    let mode = cfg.mode.unwrap_or(SINK_FILE_MODE_DEFAULT);
+    if cfg.create_dirs.unwrap_or(false) {
+        if let Some(parent) = Path::new(&cfg.path).parent() {
+            fs::create_dir_all(parent).await?;
+        }
+    }
+    if cfg.atomic.unwrap_or(false) {
+        write_file_data_dir_swap(&cfg.path, content, mode).await?;
+    } else {
+        write_file_atomic(&cfg.path, content, mode).await?;
+    }
+    if cfg.owner.is_some() || cfg.group.is_some() {
+        apply_file_ownership(&cfg.path, cfg.owner.as_deref(), cfg.group.as_deref())?;
+    }
+    Ok(())
+}
+
+/// chown token file to configured owner/group, skipped with a warning unless running as root
+fn apply_file_ownership(path: &str, owner: Option<&str>, group: Option<&str>) -> std::io::Result<()> {
+    if !is_root() {
+        warn!("sink file: owner/group of '{}' not applied, agent is not running as root", path);
+        return Ok(());
+    }
+    let to_io_err = |e: String| std::io::Error::new(ErrorKind::InvalidInput, e);
+    let uid = owner.map(|owner| resolve_id(ETC_PASSWD, owner)).transpose().map_err(to_io_err)?;
+    let gid = group.map(|group| resolve_id(ETC_GROUP, group)).transpose().map_err(to_io_err)?;
+    // follows the `..data` symlink for atomic sinks
+    std::os::unix::fs::chown(path, uid, gid)
+}
+
+/// Numeric id or user/group name: up to 32 chars of `[A-Za-z0-9._-]` not starting with `-`, optional trailing `$`
+pub(crate) fn is_valid_id_or_name(value: &str) -> bool {
+    if value.parse::<u32>().is_ok() {
+        return true;
+    }
+    let name = value.strip_suffix('$').unwrap_or(value);
+    (1..=32).contains(&value.len())
+        && !name.is_empty()
+        && !name.starts_with('-')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+}
+
+/// Numeric id as is, otherwise name is looked up in `/etc/passwd` or `/etc/group` format file
+fn resolve_id(db_path: &str, name: &str) -> Result<u32, String> {
+    if let Ok(id) = name.parse::<u32>() {
+        return Ok(id);
+    }
+    let db = std::fs::read_to_string(db_path).map_err(|e| format!("failed to read '{}': {}", db_path, e))?;
+    db.lines()
+        .map(|line| line.split(':').collect::<Vec<_>>())
+        .find(|fields| fields.len() > 2 && fields[0] == name)
+        .and_then(|fields| fields[2].parse::<u32>().ok())
+        .ok_or_else(|| format!("'{}' not found in '{}'", name, db_path))
+}
 
-                match token_opt {
-                    Some(token) => {
-                        // store new token
-                        info!("token id '{}' writes, path '{}'", &cfg.token_id, &cfg.path);
-                        let _ = tokio::fs::write(&cfg.path, token.value.as_bytes()).await
-                        .inspect(|_| {
-                                metrics
-                                    .sink_propagations
-                                    .with_label_values(&[
-                                        &cfg.sink_id.as_str(),
-                                        &FILE_MSG,
-                                        &source_id.as_str(),
-                                        &cfg.token_id.as_str(),
-                                    ])
-                                    .inc();
-                                metrics
-                                    .sink_duration
-                                    .with_label_values(&[&cfg.sink_id.as_str()])
-                                    .observe(start.elapsed().as_secs_f64());
-                        })
-                            .inspect_err(|err| {
-                                error!("{}", err);
-                                metrics.sink_failures.with_label_values(&[&cfg.sink_id.as_str(), &ERROR_MSG]).inc();
-                            });    
-                    },
-                    None => {
-                        // cleanup content
-                        info!("token id '{}' cleanup, path '{}'", &cfg.token_id, &cfg.path);
-                        let _ = tokio::fs::write(&cfg.path, TOKEN_VALUE_STUB.as_bytes()).await
-                            .inspect_err(|err| {
-                                error!("{}", err);
-                                metrics.sink_failures.with_label_values(&[&cfg.sink_id.as_str(), &ERROR_MSG]).inc();
-                            });   
-                    },
+/// Effective uid owns `/proc/self`
+fn is_root() -> bool {
+    std::fs::metadata("/proc/self").map(|meta| meta.uid() == 0).unwrap_or(false)
+}
+
+/// Kubelet style update: content is written to a new `..<timestamp>` payload dir next to `path`,
+/// `..data` symlink is renamed to point to it and `path` is a symlink to `..data/<file name>`.
+/// Other files of the previous payload dir are carried over, older payload dirs are removed
+pub(crate) async fn write_file_data_dir_swap(path: &str, content: &[u8], mode: u32) -> std::io::Result<()> {
+    let target = Path::new(path);
+    let (Some(dir), Some(name)) = (target.parent(), target.file_name()) else {
+        return Err(std::io::Error::new(ErrorKind::InvalidInput, format!("invalid token file path '{}'", path)));
+    };
+    let data_link = dir.join(SINK_FILE_DATA_DIR);
+    let previous_dir = fs::read_link(&data_link).await.ok();
+    let payload_dir_name = format!(
+        "..{}_{}_{}",
+        Utc::now().format("%Y_%m_%d_%H_%M_%S"),
+        std::process::id(),
+        DATA_DIR_COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+    let payload_dir = dir.join(&payload_dir_name);
+    fs::create_dir(&payload_dir).await?;
+
+    let res = async {
+        if let Some(previous_dir) = &previous_dir {
+            let mut entries = fs::read_dir(dir.join(previous_dir)).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                if entry.file_name() != name {
+                    fs::copy(entry.path(), payload_dir.join(entry.file_name())).await?;
                 }
             }
         }
+        write_file_atomic(&payload_dir.join(name).to_string_lossy(), content, mode).await?;
+
+        // swap `..data` in one rename
+        let data_link_tmp = dir.join(format!("{}_tmp", SINK_FILE_DATA_DIR));
+        let _ = fs::remove_file(&data_link_tmp).await;
+        fs::symlink(&payload_dir_name, &data_link_tmp).await?;
+        fs::rename(&data_link_tmp, &data_link).await?;
+
+        // `path` -> `..data/<name>` is created once, it follows the swaps
+        let link_target = Path::new(SINK_FILE_DATA_DIR).join(name);
+        if fs::read_link(target).await.ok().as_deref() != Some(link_target.as_path()) {
+            let link_tmp = format!("{}.tmp.{}", path, std::process::id());
+            let _ = fs::remove_file(&link_tmp).await;
+            fs::symlink(&link_target, &link_tmp).await?;
+            fs::rename(&link_tmp, target).await?;
+        }
+        Ok(())
     }
+    .await;
+
+    if res.is_ok() {
+        remove_stale_payload_dirs(dir, &payload_dir_name, previous_dir.as_deref()).await;
+    } else {
+        let _ = fs::remove_dir_all(&payload_dir).await;
+    }
+    res
 }
 
-async fn check_if_token_should_be_skipped(source_id: &str, token_context: &TokenContext) -> bool {
-    // store token in local cache
-    let token_already_exists: bool = SinkFileCache::get_by_source_id_and_token_id(&source_id, token_context.id.as_str()).await
-    .filter(|sink_file_token_meta| sink_file_token_meta.exp == token_context.token.exp_unix_ts)
-    .is_some();
+/// Remove payload dirs except the current and the previous one,
+/// the previous one is still read by readers that resolved `..data` just before the swap.
+/// Only dirs named as generated by the sink are touched, other `..` dirs of the user are kept
+async fn remove_stale_payload_dirs(dir: &Path, current: &str, previous: Option<&Path>) {
+    let Ok(payload_dir_name) = Regex::new(PAYLOAD_DIR_NAME) else {
+        return;
+    };
+    let Ok(mut entries) = fs::read_dir(dir).await else {
+        return;
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let name = entry.file_name();
+        let is_payload_dir = payload_dir_name.is_match(&name.to_string_lossy())
+            && entry.file_type().await.map(|file_type| file_type.is_dir()).unwrap_or(false);
+        if is_payload_dir && name != current && Some(Path::new(&name)) != previous {
+            let _ = fs::remove_dir_all(entry.path()).await;
+        }
+    }
+}
 
-    info!("sink file: does token exists in cache: {}", token_already_exists);
-    token_already_exists
+/// Write content to `<path>.tmp.<pid>` in the same directory and rename it over `path`,
+/// readers see either the previous or the new content, never a partial write
+pub(crate) async fn write_file_atomic(path: &str, content: &[u8], mode: u32) -> std::io::Result<()> {
+    let tmp_path = format!("{}.tmp.{}", path, std::process::id());
+    let res = async {
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(mode)
+            .open(&tmp_path)
+            .await?;
+        file.write_all(content).await?;
+        file.sync_all().await?;
+        // mode on create is masked by umask
+        fs::set_permissions(&tmp_path, Permissions::from_mode(mode)).await?;
+        fs::rename(&tmp_path, path).await
+    }
+    .await;
+    if res.is_err() {
+        let _ = fs::remove_file(&tmp_path).await;
+    }
+    res
+}
+
+async fn check_if_tokens_should_be_skipped(source_id: &str, token_contexts: &[TokenContext]) -> bool {
+    let mut tokens_already_exist = true;
+    for token_context in token_contexts {
+        tokens_already_exist &= SinkFileCache::get_by_source_id_and_token_id(&source_id, token_context.id.as_str()).await
+        .filter(|sink_file_token_meta| sink_file_token_meta.exp == token_context.token.exp_unix_ts)
+        .is_some();
+    }
+
+    info!("sink file: do tokens exist in cache: {}", tokens_already_exist);
+    tokens_already_exist
 }
 
 async fn sync_token_with_local_cache(source_id: &str, path: &str, token_id: &str, exp: u64, sync_type: SyncType) -> () {
@@ -128,29 +466,22 @@ async fn sync_token_with_local_cache(source_id: &str, path: &str, token_id: &str
     
 }
 
-async fn cleanup_resourses(sinks: Arc<HashMap<String, SinkConfig>>) -> Result<()>{
-        let mut sigint = signal(SignalKind::interrupt()).unwrap();
-        let mut sigterm = signal(SignalKind::terminate()).unwrap();
-        select! {
-            _ = sigint.recv() => {
-                info!("\nReceived SIGINT (Ctrl+C). Initiating graceful shutdown...");
-                let _ = cleanup_stored_tokens_after_cancelling(sinks.clone()).await;
-            }
-            _ = sigterm.recv() => {
-                info!("Received SIGTERM. Initiating graceful shutdown...");
-                let _ = cleanup_stored_tokens_after_cancelling(sinks.clone()).await;
-            }
-            // Add other async tasks or application logic here
-            // _ = my_application_task() => { /* handle task completion */ }
-        }
-        Ok(())
-}
-
-async fn  cleanup_stored_tokens_after_cancelling(sinks: Arc<HashMap<String, SinkConfig>>) -> Result<()> {
+async fn cleanup_stored_tokens_after_cancelling(sinks: Arc<HashMap<String, SinkConfig>>, retained_paths: &HashSet<String>) -> Result<()> {
     for (_, cfg) in sinks.iter() {
         let path = cfg.path.as_str();
-        if cfg.sink_type == SinkType::File {
+        if cfg.sink_type == SinkType::File && !retained_paths.contains(path) {
+            for token_id in cfg.token_ids() {
+                SinkFileCache::remove(&cfg.source_id, token_id).await;
+            }
             info!("remove token file at path path : {}", path);
+            if cfg.atomic.unwrap_or(false) {
+                // token content lives in the payload dir behind `..data`
+                if let Some(dir) = Path::new(path).parent() {
+                    let data_file = dir.join(SINK_FILE_DATA_DIR).join(Path::new(path).file_name().unwrap_or_default());
+                    let _ = fs::remove_file(data_file).await;
+                }
+                let _ = fs::remove_file(path).await;
+            }
             if Path::new(path).exists() {
                 match fs::remove_file(path).await {
                     Ok(_) => info!("Deleted file: {}", path),
@@ -165,7 +496,66 @@ async fn  cleanup_stored_tokens_after_cancelling(sinks: Arc<HashMap<String, Sink
         }
     }
 
-    println!("Exiting application.");
-    std::process::exit(0);
+    Ok(())
 }
 
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::cache::token::Token;
+    use crate::cache::token_context::TokenContext;
+    use crate::config::sinks::{FileSinkFormat, SinkConfig};
+    use crate::sinks::sink_file::{file_content_stub, render_file_content};
+    use crate::tests::common::sink_config;
+
+    fn file_sink(format: Value) -> SinkConfig {
+        let type_hint = if format == "json_envelope" { json!("Bearer") } else { Value::Null };
+        sink_config("file", "file_sink", "s1", json!({ "path": "/tmp/token", "format": format, "type_hint": type_hint }))
+    }
+
+    fn token_context() -> TokenContext {
+        TokenContext::new("access_token".into(), Token::new("abc".into(), 4_102_444_800), 100)
+    }
+
+    #[tokio::test]
+    async fn raw_format_writes_token_value() {
+        for cfg in [file_sink(Value::Null), file_sink(json!("raw"))] {
+            assert_eq!(cfg.format.unwrap_or_default(), FileSinkFormat::Raw);
+            assert_eq!(render_file_content(&cfg, token_context()).await.unwrap(), "abc");
+            assert_eq!(file_content_stub(&cfg), "");
+        }
+    }
+
+    #[tokio::test]
+    async fn json_envelope_format_writes_token_metadata() {
+        let cfg = file_sink(json!("json_envelope"));
+
+        let mut received = token_context();
+        received.received_at_unix_ts = 1_735_689_300;
+        let content: Value = serde_json::from_str(&render_file_content(&cfg, received).await.unwrap()).unwrap();
+
+        assert_eq!(
+            content,
+            json!({ "token": "abc", "exp_unix_ts": 4_102_444_800u64, "fetched_at_unix_ts": 1_735_689_300u64, "type_hint": "Bearer" })
+        );
+        assert_eq!(file_content_stub(&cfg), r#"{"token":""}"#);
+
+        let mut cfg = cfg;
+        cfg.type_hint = None;
+        let content: Value = serde_json::from_str(&render_file_content(&cfg, token_context()).await.unwrap()).unwrap();
+        assert!(content.get("type_hint").is_none(), "{}", content);
+    }
+
+    #[test]
+    fn stub_value_replaces_default_stub() {
+        for format in [Value::Null, json!("json_envelope")] {
+            let mut cfg = file_sink(format);
+            cfg.stub_value = Some("invalid".into());
+            assert_eq!(file_content_stub(&cfg), "invalid");
+            cfg.stub_value = Some(String::new());
+            assert_eq!(file_content_stub(&cfg), "");
+        }
+    }
+}
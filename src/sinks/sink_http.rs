@@ -1,36 +1,59 @@
 use anyhow::{anyhow, Result};
 use axum::{
-    extract::State,
-    http::{HeaderMap, HeaderName, HeaderValue, StatusCode},
+    extract::{ConnectInfo, State},
+    http::header::{AUTHORIZATION, CACHE_CONTROL, ETAG, IF_NONE_MATCH, RETRY_AFTER},
+    http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode},
     response::{IntoResponse, Response},
-    routing::get,
     Json, Router,
 };
 
 use chrono::{TimeZone, Utc};
+use ring::digest;
 use serde_json::Value;
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, net::SocketAddr, sync::{Arc, RwLock}};
 use tokio::time::Instant;
-use tracing::info;
-
-use crate::config::sinks::{ExpirationSinkFormat, ResponseField, SinkConfig, SinkType};
+use tracing::{info, info_span, warn, Instrument};
+
+use crate::cache::token_context::TokenContext;
+use crate::config::proc_reload::differs;
+use crate::config::sinks::{ExpirationSinkFormat, ResponseField, SinkAuthConfig, SinkConfig, SinkType};
+use crate::helpers::secret::secret_eq;
+use crate::observability::audit::{self, AuditEvent, AuditEventType};
+use crate::observability::otel::{set_remote_parent, TRACEPARENT_FIELD};
+use crate::resilience::rate_limit::RateLimiter;
 use crate::server::server::AppState;
-use crate::{cache::token_cache::TokenCache, observability::metrics::get_metrics};
+use crate::{cache::token_cache::TokenCache, observability::metrics::{get_metrics, SINK_ENABLED_LABEL}};
 
 static ERROR_MSG: &'static str = "error";
 static HTTP_MSG: &'static str = "http";
+static AUTH_MISSING_MSG: &str = "missing";
+static AUTH_INVALID_MSG: &str = "invalid";
+static UNKNOWN_CLIENT_MSG: &str = "unknown";
+/// Response header set when any sink token is served as stale, see `stale_token_ttl_seconds`
+pub const TOKEN_STALE_HEADER: &str = "x-token-stale";
 
+/// Sink routes are swapped on config reload while the server keeps running
 #[derive(Clone)]
 pub struct SinkHttpState {
-    sink_routes: Arc<HashMap<String, SinkConfig>>,
+    routes: Arc<RwLock<Arc<SinkRoutes>>>,
 }
 
-impl SinkHttpState {
-    pub fn new(all_sinks: &HashMap<String, SinkConfig>) -> Result<Self> {
+#[derive(Default)]
+struct SinkRoutes {
+    sink_routes: HashMap<String, SinkConfig>,
+    /// path -> limiter of sinks with `rate_limit`
+    rate_limiters: HashMap<String, Arc<RateLimiter>>,
+}
+
+impl SinkRoutes {
+    /// Limiters of unchanged sinks are taken over from `previous`, their clients keep the used budget
+    fn new(all_sinks: &HashMap<String, SinkConfig>, previous: &SinkRoutes) -> Result<Self> {
         let mut routes = HashMap::new();
+        let mut rate_limiters = HashMap::new();
 
         for (sink_name, cfg) in all_sinks {
-            if let SinkType::Http = cfg.sink_type {
+            // push sinks (POST/PUT) have no route
+            if cfg.sink_type == SinkType::Http && !cfg.is_http_push() {
                 let path = if cfg.path.starts_with('/') {
                     cfg.path.clone()
                 } else {
@@ -44,48 +67,166 @@ impl SinkHttpState {
                         sink_name
                     ));
                 }
+                if let Some(rate_limit) = &cfg.rate_limit {
+                    let unchanged = previous.sink_routes.get(&path).is_some_and(|previous_cfg| !differs(previous_cfg, cfg));
+                    let rate_limiter = match previous.rate_limiters.get(&path) {
+                        Some(rate_limiter) if unchanged => rate_limiter.clone(),
+                        _ => Arc::new(RateLimiter::new(rate_limit)),
+                    };
+                    rate_limiters.insert(path.clone(), rate_limiter);
+                }
                 routes.insert(path, cfg.clone());
             }
         }
 
         Ok(Self {
-            sink_routes: Arc::new(routes),
+            sink_routes: routes,
+            rate_limiters,
         })
     }
 }
 
 impl SinkHttpState {
-    pub async fn router(&self) -> Router<AppState> {
-        let mut router = Router::new();
+    pub fn new(all_sinks: &HashMap<String, SinkConfig>) -> Result<Self> {
+        let routes = SinkRoutes::new(all_sinks, &SinkRoutes::default())?;
+        Ok(Self {
+            routes: Arc::new(RwLock::new(Arc::new(routes))),
+        })
+    }
+
+    /// Serve `all_sinks` from now on, requests in flight finish with the previous routes
+    pub fn update(&self, all_sinks: &HashMap<String, SinkConfig>) -> Result<()> {
+        let routes = SinkRoutes::new(all_sinks, &self.routes())?;
+        for path in routes.sink_routes.keys() {
+            info!("served path: {}", path);
+        }
+        *self.routes.write().unwrap() = Arc::new(routes);
+        Ok(())
+    }
 
-        for (path, _) in self.sink_routes.iter() {
+    fn routes(&self) -> Arc<SinkRoutes> {
+        self.routes.read().unwrap().clone()
+    }
+}
+
+impl SinkHttpState {
+    /// Sink paths are dispatched by the fallback, so they can change without restarting the server
+    pub async fn router(&self) -> Router<AppState> {
+        for path in self.routes().sink_routes.keys() {
             info!("served path: {}", &path);
-            router = router.route(path, get(handle_request_axum));
         }
-        router
+        Router::new().fallback(handle_request_axum)
     }
 }
 
 /// Unified request handler — dynamic dispatch by path.
+/// `traceparent` of the incoming request makes `sink.http` span a child of the consumer trace
 async fn handle_request_axum(
     State(state): State<AppState>,
     req: axum::http::Request<axum::body::Body>,
 ) -> Response {
+    let path = req.uri().path().to_string();
+    let traceparent = req
+        .headers()
+        .get(TRACEPARENT_FIELD)
+        .and_then(|v| v.to_str().ok());
+    let span = info_span!("sink.http", http.path = %path);
+    set_remote_parent(&span, traceparent);
+    let consumer_ip = req.extensions().get::<ConnectInfo<SocketAddr>>().map(|ConnectInfo(addr)| addr.ip().to_string());
+    let routes = state.sink_http_state.routes();
+    if req.method() != Method::GET && req.method() != Method::HEAD {
+        return match routes.sink_routes.contains_key(&path) {
+            true => StatusCode::METHOD_NOT_ALLOWED.into_response(),
+            false => StatusCode::NOT_FOUND.into_response(),
+        };
+    }
+    if let Some(sink) = routes.sink_routes.get(&path) {
+        // limited before auth, so secrets can not be guessed at full speed either
+        if let Some(rate_limiter) = routes.rate_limiters.get(&path) {
+            if let Err(retry_after) = rate_limiter.check(consumer_ip.as_deref().unwrap_or(UNKNOWN_CLIENT_MSG)) {
+                warn!("sink http '{}': rate limit exceeded by {}", sink.sink_id, consumer_ip.as_deref().unwrap_or(UNKNOWN_CLIENT_MSG));
+                get_metrics().await.sink_rate_limited_requests.with_label_values(&[sink.sink_id.as_str()]).inc();
+                let retry_after_secs = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+                return (StatusCode::TOO_MANY_REQUESTS, [(RETRY_AFTER, retry_after_secs.to_string())], "too many requests").into_response();
+            }
+        }
+        if let Some(auth) = &sink.auth {
+            if let Err(reason) = check_sink_auth(auth, req.headers()) {
+                warn!("sink http '{}': unauthorized request ({})", sink.sink_id, reason);
+                get_metrics().await.sink_auth_failures.with_label_values(&[sink.sink_id.as_str(), reason]).inc();
+                return (StatusCode::UNAUTHORIZED, "unauthorized").into_response();
+            }
+        }
+    }
+    let if_none_match = req.headers().get(IF_NONE_MATCH).and_then(|v| v.to_str().ok()).map(str::to_owned);
+    serve_sink(routes, path, if_none_match, consumer_ip).instrument(span).await
+}
+
+/// Check request headers against sink `auth`, `Err` holds the rejection reason.
+/// Unresolvable or empty expected secret rejects every request
+fn check_sink_auth(auth: &SinkAuthConfig, headers: &HeaderMap) -> Result<(), &'static str> {
+    let provided = match auth {
+        SinkAuthConfig::Bearer { .. } => headers
+            .get(AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer ")),
+        SinkAuthConfig::MtlsHeader { header, .. } => headers.get(header.as_str()).and_then(|v| v.to_str().ok()),
+    };
+    let Some(provided) = provided else {
+        return Err(AUTH_MISSING_MSG);
+    };
+    match auth.secret().resolve() {
+        Some(expected) if !expected.is_empty() && secret_eq(provided, &expected) => Ok(()),
+        _ => Err(AUTH_INVALID_MSG),
+    }
+}
+
+async fn serve_sink(routes: Arc<SinkRoutes>, path: String, if_none_match: Option<String>, consumer_ip: Option<String>) -> Response {
     let metrics = get_metrics().await;
     let start = Instant::now();
 
-    let sink_routes = &state.sink_http_state.sink_routes;
-    let path = req.uri().path().to_string();
     info!("path: {}", path);
-    let sink = match sink_routes.get(&path) {
+    let sink = match routes.sink_routes.get(&path) {
         Some(s) => s,
         None => return (StatusCode::NOT_FOUND, "not found").into_response(),
     };
     info!("{}.{:?}", path, sink.response);
+    let exp_unix_ts = TokenCache::get(&sink.source_id, &sink.token_id).await.map(|token_context| token_context.token.exp_unix_ts);
+    let audit_cache_hit = || {
+        audit::emit(
+            AuditEvent::new(AuditEventType::CacheHit, &sink.source_id)
+                .token(&sink.token_id, exp_unix_ts)
+                .sink(&sink.sink_id)
+                .consumer_ip(consumer_ip.clone()),
+        )
+    };
+
+    // conditional request: unchanged sink tokens are not rendered again
+    let validators = get_sink_cache_validators(sink).await;
+    let mut cache_header_map = HeaderMap::new();
+    if is_sink_token_stale(sink).await {
+        cache_header_map.insert(TOKEN_STALE_HEADER, HeaderValue::from_static("true"));
+    }
+    if let Some((etag, max_age)) = &validators {
+        if let Ok(etag) = HeaderValue::from_str(etag) {
+            cache_header_map.insert(ETAG, etag);
+        }
+        cache_header_map.insert(CACHE_CONTROL, HeaderValue::from_str(&format!("max-age={}", max_age)).unwrap());
+        if if_none_match.as_deref().is_some_and(|if_none_match| etag_matches(if_none_match, etag)) {
+            metrics
+                .sink_propagations
+                .with_label_values(&[sink.sink_id.as_str(), HTTP_MSG, sink.source_id.as_str(), sink.token_id.as_str(), SINK_ENABLED_LABEL])
+                .inc();
+            metrics.sink_http_304_responses.with_label_values(&[sink.sink_id.as_str()]).inc();
+            metrics.sink_duration.with_label_values(&[sink.sink_id.as_str()]).observe(start.elapsed().as_secs_f64());
+            audit_cache_hit();
+            return (StatusCode::NOT_MODIFIED, cache_header_map).into_response();
+        }
+    }
 
     match render_http_response_axum(&sink).await {
         Ok((headers, body, content_type)) => {
-            let mut header_map = HeaderMap::new();
+            let mut header_map = cache_header_map;
             header_map.insert(
                 axum::http::header::CONTENT_TYPE,
                 HeaderValue::from_str(&content_type).unwrap(),
@@ -106,12 +247,14 @@ async fn handle_request_axum(
                     &HTTP_MSG,
                     &sink.source_id.as_str(),
                     &sink.token_id.as_str(),
+                    &SINK_ENABLED_LABEL,
                 ])
                 .inc();
             metrics
                 .sink_duration
                 .with_label_values(&[&sink.sink_id.as_str()])
                 .observe(start.elapsed().as_secs_f64());
+            audit_cache_hit();
 
             (header_map, Json(body)).into_response()
         }
@@ -129,11 +272,49 @@ async fn handle_request_axum(
     }
 }
 
+/// Strong ETag over values and expirations of sink tokens and `max-age` until the earliest sink token
+/// enters its safety margin, `None` when any sink token is absent
+async fn get_sink_cache_validators(sink: &SinkConfig) -> Option<(String, u64)> {
+    let now = Utc::now().timestamp() as u64;
+    let mut context = digest::Context::new(&digest::SHA256);
+    // response definition is part of the representation
+    context.update(format!("{}:{:?}", sink.path, sink.response).as_bytes());
+    let mut max_age = u64::MAX;
+    for token_id in sink.token_ids() {
+        let token_context = TokenCache::get(&sink.source_id, token_id).await?;
+        context.update(format!("\n{}:{}:{}", token_id, token_context.token.exp_unix_ts, token_context.token.value).as_bytes());
+        max_age = max_age.min(token_context.fetched_at_unix_ts.saturating_sub(now));
+    }
+    let etag = context.finish().as_ref()[..16].iter().map(|b| format!("{:02x}", b)).collect::<String>();
+    Some((format!("\"{}\"", etag), max_age))
+}
+
+/// Check if any sink token is an expired token kept while its source fails
+async fn is_sink_token_stale(sink: &SinkConfig) -> bool {
+    for token_id in sink.token_ids() {
+        if TokenCache::get(&sink.source_id, token_id).await.is_some_and(|token_context| token_context.is_stale) {
+            return true;
+        }
+    }
+    false
+}
+
+/// `If-None-Match` list or `*` against current ETag, weak comparison per RFC 9110
+fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match
+        .split(',')
+        .map(|candidate| candidate.trim())
+        .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag)
+}
+
 /// returns header, body, content-type
-async fn render_http_response_axum(
+pub(crate) async fn render_http_response_axum(
     sink: &SinkConfig,
 ) -> Result<(HashMap<String, String>, Value, String)> {
-    let response_block = sink.response.to_owned().unwrap();
+    let response_block = sink
+        .response
+        .to_owned()
+        .ok_or_else(|| anyhow!("sink '{}': response block is required", sink.sink_id))?;
 
     if !TokenCache::contains_source_id(&sink.source_id).await {
         return Err(anyhow!("source input id {} not found", sink.source_id));
@@ -142,7 +323,7 @@ async fn render_http_response_axum(
     let mut headers = HashMap::new();
     if let Some(hmap) = &response_block.headers {
         for (k, field) in hmap {
-            let val = render_field_to_string_axum(&sink.source_id, field).await?;
+            let val = render_field_to_string_axum(sink, field).await?;
             headers.insert(k.clone(), val);
         }
     }
@@ -150,7 +331,7 @@ async fn render_http_response_axum(
     let mut body_obj = serde_json::Map::new();
     if let Some(body_map) = &response_block.body {
         for (k, field) in body_map {
-            let v = render_field_to_json_axum(&sink.source_id, field).await?;
+            let v = render_field_to_json_axum(sink, field).await?;
             body_obj.insert(k.clone(), v);
         }
     }
@@ -162,30 +343,43 @@ async fn render_http_response_axum(
     ))
 }
 
-async fn render_field_to_string_axum(input: &str, field: &ResponseField) -> Result<String> {
+/// Current sink token, or the token it replaced while `rotation_overlap_seconds` of the last rotation lasts
+async fn get_sink_token(sink: &SinkConfig, token_id: &str) -> Option<TokenContext> {
+    if let Some(token_context) = TokenCache::get(&sink.source_id, token_id).await {
+        return Some(token_context);
+    }
+    let previous = TokenCache::get_previous(&sink.source_id, token_id).await?;
+    warn!("sink '{}': token {}.{} is missing, serving previous token", sink.sink_id, sink.source_id, token_id);
+    get_metrics().await.sink_overlap_served.with_label_values(&[sink.sink_id.as_str()]).inc();
+    Some(previous)
+}
+
+async fn render_field_to_string_axum(sink: &SinkConfig, field: &ResponseField) -> Result<String> {
+    let input = &sink.source_id;
     match field {
-        ResponseField::Token { id } => TokenCache::get(&input, &id)
+        ResponseField::Token { id } => get_sink_token(sink, id)
             .await
             .ok_or_else(|| anyhow!("type: string token id {}.{} doesnt exists", input, id))
             .map(|token_context| token_context.token.value),
         ResponseField::String { value } => Ok(value.clone()),
         ResponseField::Expiration { .. } => {
-            let v = render_field_to_json_axum(input, field).await?;
+            let v = render_field_to_json_axum(sink, field).await?;
             Ok(v.to_string())
         }
     }
 }
 
-async fn render_field_to_json_axum(input: &str, field: &ResponseField) -> Result<Value> {
+async fn render_field_to_json_axum(sink: &SinkConfig, field: &ResponseField) -> Result<Value> {
+    let input = &sink.source_id;
     match field {
-        ResponseField::Token { id } => TokenCache::get(&input, &id)
+        ResponseField::Token { id } => get_sink_token(sink, id)
             .await
             .map(|token_context| Value::String(token_context.token.value))
             .ok_or_else(|| anyhow!("type:jwt token id {}.{} doesnt exists", input, id)),
         ResponseField::String { value } => Ok(Value::String(value.clone())),
         ResponseField::Expiration { format, id } => {
             let now = Utc::now().timestamp();
-            TokenCache::get(&input, &id)
+            get_sink_token(sink, id)
                 .await
                 .ok_or_else(|| anyhow!("type: jwt expiration id {}.{} doesnt exists", input, id))
                 .map(|token_context| {
@@ -220,13 +414,14 @@ mod tests {
     use axum::http::StatusCode;
     use serial_test::serial;
     use std::collections::HashMap;
+    use std::time::Duration;
 
     use crate::cache::token_context::TokenContext;
-    use crate::config::sinks::{HttpResponseBlock, ResponseField, SinkConfig, SinkType};
+    use crate::config::sinks::{HttpResponseBlock, ResponseField, SinkConfig};
     use crate::server::server::AppState;
     use crate::{
         cache::{token::Token, token_cache::TokenCache},
-        tests::common::{build_reqwest_client, spawn_axum},
+        tests::common::{build_reqwest_client, json, sink_config, spawn_axum},
     };
 
     #[tokio::test]
@@ -275,12 +470,8 @@ mod tests {
 
         let sink_id = "sink-http-1";
         let sink_config = SinkConfig {
-            sink_id: sink_id.to_string(),
-            sink_type: SinkType::Http,
-            source_id: source_id.clone(),
-            path: "/tokens/test".to_string(),
-            token_id: token_id.clone(),
             response: Some(response_block),
+            ..sink_config("http", sink_id, &source_id, json!({ "path": "/tokens/test", "token_id": token_id }))
         };
 
         // -------------------------------
@@ -293,7 +484,7 @@ mod tests {
         let router = sink_http_state.router().await;
 
         let metrics = &get_metrics().await;
-        let app_state = AppState::new(metrics, &sinks);
+        let app_state = AppState::new(metrics, &sinks, &None);
 
         let app: Router = router.with_state(app_state);
 
@@ -367,12 +558,8 @@ mod tests {
 
         let sink_id = "sink-http-1";
         let sink_config = SinkConfig {
-            sink_id: sink_id.to_string(),
-            sink_type: SinkType::Http,
-            source_id: source_id.clone(),
-            path: "/tokens/test".to_string(),
-            token_id: token_id.clone(),
             response: Some(response_block),
+            ..sink_config("http", sink_id, &source_id, json!({ "path": "/tokens/test", "token_id": token_id }))
         };
 
         // -------------------------------
@@ -385,7 +572,7 @@ mod tests {
         let router = sink_http_state.router().await;
 
         let metrics = &get_metrics().await;
-        let app_state = AppState::new(metrics, &sinks);
+        let app_state = AppState::new(metrics, &sinks, &None);
 
         let app: Router = router.with_state(app_state);
 
@@ -408,4 +595,315 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    #[serial]
+    async fn test_http_sink_serves_multiple_tokens() -> anyhow::Result<()> {
+        TokenCache::cleanup().await;
+        let source_id = "oauth";
+        let exp_unix_ts = chrono::Utc::now().timestamp() as u64 + 3600;
+        TokenCache::set(
+            source_id.to_string(),
+            vec![
+                TokenContext::new("access_token".to_string(), Token::new("access".to_string(), exp_unix_ts), 10),
+                TokenContext::new("refresh_token".to_string(), Token::new("refresh".to_string(), 5_000_000_000), 10),
+            ],
+        )
+        .await?;
+
+        let sink_config: SinkConfig = serde_json::from_value(serde_json::json!({
+            "sink_id": "sink-http-multi",
+            "type": "http",
+            "source_id": source_id,
+            "path": "/tokens/oauth",
+            "token_id": "access_token",
+            "tokens": ["refresh_token"],
+            "response": {
+                "body": {
+                    "access_token": { "type": "token", "id": "access_token" },
+                    "refresh_token": { "type": "token", "id": "refresh_token" },
+                    "expires_in": { "type": "expiration", "format": "seconds", "id": "access_token" }
+                }
+            }
+        }))?;
+        assert_eq!(sink_config.token_ids(), vec!["access_token", "refresh_token"]);
+
+        let sinks = HashMap::from([(sink_config.sink_id.clone(), sink_config)]);
+        let router = SinkHttpState::new(&sinks)?.router().await;
+        let metrics = &get_metrics().await;
+        let app: Router = router.with_state(AppState::new(metrics, &sinks, &None));
+        let (handle, addr) = spawn_axum(app).await;
+
+        let response = build_reqwest_client().get(format!("http://{}/tokens/oauth", addr)).send().await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        let json: Value = response.json().await?;
+        assert_eq!(json["access_token"], "access");
+        assert_eq!(json["refresh_token"], "refresh");
+        let expires_in = json["expires_in"].as_u64().unwrap();
+        assert!(expires_in > 3590 && expires_in <= 3600);
+
+        handle.abort();
+        TokenCache::cleanup().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_http_sink_serves_previous_token_within_rotation_overlap() -> anyhow::Result<()> {
+        TokenCache::cleanup().await;
+        let source_id = "overlap";
+        let now = chrono::Utc::now().timestamp() as u64;
+        TokenCache::set(source_id.to_string(), vec![TokenContext::new("token".to_string(), Token::new("old-token".to_string(), now + 3600), 10)]).await?;
+
+        let sink_config: SinkConfig = serde_json::from_value(serde_json::json!({
+            "sink_id": "sink-http-overlap",
+            "type": "http",
+            "source_id": source_id,
+            "path": "/tokens/overlap",
+            "token_id": "token",
+            "response": { "body": { "access_token": { "type": "token", "id": "token" } } }
+        }))?;
+        let sinks = HashMap::from([(sink_config.sink_id.clone(), sink_config)]);
+        let router = SinkHttpState::new(&sinks)?.router().await;
+        let metrics = &get_metrics().await;
+        let app: Router = router.with_state(AppState::new(metrics, &sinks, &None));
+        let (handle, addr) = spawn_axum(app).await;
+        let url = format!("http://{}/tokens/overlap", addr);
+        let overlap_served = || metrics.sink_overlap_served.with_label_values(&["sink-http-overlap"]).get();
+        let overlap_served_before = overlap_served();
+
+        // rotated token expires right away and is invalidated before the next fetch
+        TokenCache::set_with_overlap(source_id.to_string(), vec![TokenContext::new("token".to_string(), Token::new("new-token".to_string(), now), 10)], Some(60)).await?;
+        let response = build_reqwest_client().get(&url).send().await?;
+        assert_eq!(response.json::<Value>().await?["access_token"], "new-token");
+        assert_eq!(overlap_served(), overlap_served_before);
+
+        TokenCache::invalidate_expired_tokens_by_source_id(source_id).await;
+        assert!(TokenCache::get(source_id, "token").await.is_none());
+        let response = build_reqwest_client().get(&url).send().await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.json::<Value>().await?["access_token"], "old-token");
+        assert_eq!(overlap_served(), overlap_served_before + 1);
+
+        handle.abort();
+        TokenCache::cleanup().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_http_sink_serves_invalidated_token_until_refetch() -> anyhow::Result<()> {
+        TokenCache::cleanup().await;
+        let source_id = "overlap-refetch";
+        let overlap = Some(60);
+        let now = chrono::Utc::now().timestamp() as u64;
+        // current token reaches its removal time before the refresh loop stores the next one
+        TokenCache::set_with_overlap(source_id.to_string(), vec![TokenContext::new("token".to_string(), Token::new("expiring-token".to_string(), now + 2), 0)], overlap).await?;
+
+        let sink_config: SinkConfig = serde_json::from_value(serde_json::json!({
+            "sink_id": "sink-http-overlap-refetch",
+            "type": "http",
+            "source_id": source_id,
+            "path": "/tokens/overlap-refetch",
+            "token_id": "token",
+            "response": { "body": { "access_token": { "type": "token", "id": "token" } } }
+        }))?;
+        let sinks = HashMap::from([(sink_config.sink_id.clone(), sink_config)]);
+        let router = SinkHttpState::new(&sinks)?.router().await;
+        let metrics = &get_metrics().await;
+        let app: Router = router.with_state(AppState::new(metrics, &sinks, &None));
+        let (handle, addr) = spawn_axum(app).await;
+        let url = format!("http://{}/tokens/overlap-refetch", addr);
+        let overlap_served = || metrics.sink_overlap_served.with_label_values(&["sink-http-overlap-refetch"]).get();
+        let overlap_served_before = overlap_served();
+
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        assert!(TokenCache::invalidate_expired_tokens_with_stale(source_id, None, overlap).await);
+        assert!(TokenCache::get(source_id, "token").await.is_none());
+        // refetch gap: the invalidated token is served
+        let response = build_reqwest_client().get(&url).send().await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.json::<Value>().await?["access_token"], "expiring-token");
+        assert_eq!(overlap_served(), overlap_served_before + 1);
+
+        // refetch stored the new token
+        TokenCache::set_with_overlap(source_id.to_string(), vec![TokenContext::new("token".to_string(), Token::new("next-token".to_string(), now + 3600), 0)], overlap).await?;
+        let response = build_reqwest_client().get(&url).send().await?;
+        assert_eq!(response.json::<Value>().await?["access_token"], "next-token");
+        assert_eq!(overlap_served(), overlap_served_before + 1);
+
+        handle.abort();
+        TokenCache::cleanup().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_http_sink_conditional_request_returns_not_modified() -> anyhow::Result<()> {
+        TokenCache::cleanup().await;
+        let source_id = "etag";
+        let exp_unix_ts = chrono::Utc::now().timestamp() as u64 + 100;
+        TokenCache::set(source_id.to_string(), vec![TokenContext::new("token".to_string(), Token::new("etag-token".to_string(), exp_unix_ts), 10)]).await?;
+
+        let sink_config: SinkConfig = serde_json::from_value(serde_json::json!({
+            "sink_id": "sink-http-etag",
+            "type": "http",
+            "source_id": source_id,
+            "path": "/tokens/etag",
+            "token_id": "token",
+            "response": { "body": { "access_token": { "type": "token", "id": "token" } } }
+        }))?;
+        let sinks = HashMap::from([(sink_config.sink_id.clone(), sink_config)]);
+        let router = SinkHttpState::new(&sinks)?.router().await;
+        let metrics = &get_metrics().await;
+        let app: Router = router.with_state(AppState::new(metrics, &sinks, &None));
+        let (handle, addr) = spawn_axum(app).await;
+        let client = build_reqwest_client();
+        let url = format!("http://{}/tokens/etag", addr);
+        let max_age = |response: &reqwest::Response| -> u64 {
+            let cache_control = response.headers()["cache-control"].to_str().unwrap();
+            cache_control.strip_prefix("max-age=").unwrap().parse().unwrap()
+        };
+
+        let not_modified_count = || metrics.sink_http_304_responses.with_label_values(&["sink-http-etag"]).get();
+        let not_modified_before = not_modified_count();
+
+        let response = client.get(&url).send().await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = response.headers()["etag"].to_str()?.to_owned();
+        assert!(etag.starts_with('"') && etag.ends_with('"'), "{}", etag);
+        let first_max_age = max_age(&response);
+        assert!(first_max_age <= 90 && first_max_age > 80, "{}", first_max_age);
+        assert_eq!(response.json::<Value>().await?["access_token"], "etag-token");
+
+        tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+        let response = client.get(&url).header("If-None-Match", &etag).send().await?;
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()["etag"].to_str()?, etag);
+        assert!(max_age(&response) < first_max_age);
+        assert!(response.bytes().await?.is_empty());
+        assert_eq!(not_modified_count(), not_modified_before + 1);
+
+        // refreshed token changes ETag
+        TokenCache::set(source_id.to_string(), vec![TokenContext::new("token".to_string(), Token::new("etag-token-2".to_string(), exp_unix_ts + 60), 10)]).await?;
+        let response = client.get(&url).header("If-None-Match", &etag).send().await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_ne!(response.headers()["etag"].to_str()?, etag);
+        assert_eq!(not_modified_count(), not_modified_before + 1);
+
+        handle.abort();
+        TokenCache::cleanup().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_http_sink_auth_rejects_missing_and_wrong_secret() -> anyhow::Result<()> {
+        TokenCache::cleanup().await;
+        let source_id = "auth";
+        let exp_unix_ts = chrono::Utc::now().timestamp() as u64 + 3600;
+        TokenCache::set(source_id.to_string(), vec![TokenContext::new("token".to_string(), Token::new("secret-token".to_string(), exp_unix_ts), 10)]).await?;
+        std::env::set_var("SINK_HTTP_AUTH_TEST_SECRET", "sink-secret");
+
+        let sink = |sink_id: &str, path: &str, auth: Value| -> SinkConfig {
+            sink_config("http", sink_id, source_id, json!({
+                "path": path,
+                "token_id": "token",
+                "response": { "body": { "access_token": { "type": "token", "id": "token" } } },
+                "auth": auth
+            }))
+        };
+        let sinks = HashMap::from([
+            ("sink-http-bearer".to_string(), sink("sink-http-bearer", "/tokens/bearer", serde_json::json!({ "type": "bearer", "from_env": "SINK_HTTP_AUTH_TEST_SECRET" }))),
+            ("sink-http-mtls".to_string(), sink("sink-http-mtls", "/tokens/mtls", serde_json::json!({ "type": "mtls_header", "header": "X-Client-Verify", "value": "SUCCESS" }))),
+        ]);
+        let router = SinkHttpState::new(&sinks)?.router().await;
+        let metrics = &get_metrics().await;
+        let app: Router = router.with_state(AppState::new(metrics, &sinks, &None));
+        let (handle, addr) = spawn_axum(app).await;
+        let client = build_reqwest_client();
+        let url = format!("http://{}/tokens/bearer", addr);
+        let missing = metrics.sink_auth_failures.with_label_values(&["sink-http-bearer", "missing"]);
+        let invalid = metrics.sink_auth_failures.with_label_values(&["sink-http-bearer", "invalid"]);
+        let (missing_before, invalid_before) = (missing.get(), invalid.get());
+
+        // missing header
+        let response = client.get(&url).send().await?;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert!(!response.text().await?.contains("secret-token"));
+        assert_eq!(missing.get(), missing_before + 1);
+
+        // wrong secret
+        let response = client.get(&url).bearer_auth("wrong").send().await?;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert!(!response.text().await?.contains("secret-token"));
+        assert_eq!(invalid.get(), invalid_before + 1);
+
+        // correct secret
+        let response = client.get(&url).bearer_auth("sink-secret").send().await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.json::<Value>().await?["access_token"], "secret-token");
+
+        // header injected by proxy
+        let url = format!("http://{}/tokens/mtls", addr);
+        assert_eq!(client.get(&url).send().await?.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(client.get(&url).header("X-Client-Verify", "NONE").send().await?.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(client.get(&url).header("X-Client-Verify", "SUCCESS").send().await?.status(), StatusCode::OK);
+
+        handle.abort();
+        TokenCache::cleanup().await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_http_sink_rate_limit_returns_too_many_requests() -> anyhow::Result<()> {
+        TokenCache::cleanup().await;
+        let source_id = "rate-limit";
+        let exp_unix_ts = chrono::Utc::now().timestamp() as u64 + 3600;
+        TokenCache::set(source_id.to_string(), vec![TokenContext::new("token".to_string(), Token::new("limited-token".to_string(), exp_unix_ts), 10)]).await?;
+
+        let sink = |sink_id: &str, path: &str, rate_limit: Value| -> SinkConfig {
+            sink_config("http", sink_id, source_id, json!({
+                "path": path,
+                "token_id": "token",
+                "response": { "body": { "access_token": { "type": "token", "id": "token" } } },
+                "rate_limit": rate_limit
+            }))
+        };
+        let sinks = HashMap::from([
+            ("sink-http-limited".to_string(), sink("sink-http-limited", "/tokens/limited", serde_json::json!({ "requests_per_second": 0.5, "burst": 5 }))),
+            ("sink-http-unlimited".to_string(), sink("sink-http-unlimited", "/tokens/unlimited", Value::Null)),
+        ]);
+        let router = SinkHttpState::new(&sinks)?.router().await;
+        let metrics = &get_metrics().await;
+        let app: Router = router.with_state(AppState::new(metrics, &sinks, &None));
+        let (handle, addr) = spawn_axum(app).await;
+        let client = build_reqwest_client();
+        let limited = metrics.sink_rate_limited_requests.with_label_values(&["sink-http-limited"]);
+        let limited_before = limited.get();
+
+        let mut statuses = Vec::new();
+        for _ in 0..20 {
+            let response = client.get(format!("http://{}/tokens/limited", addr)).send().await?;
+            statuses.push(response.status());
+            if response.status() == StatusCode::TOO_MANY_REQUESTS {
+                let retry_after: u64 = response.headers()["retry-after"].to_str()?.parse()?;
+                assert!(retry_after >= 1 && retry_after <= 2, "{}", retry_after);
+                assert!(!response.text().await?.contains("limited-token"));
+            }
+        }
+        assert_eq!(statuses[..5], [StatusCode::OK; 5]);
+        assert_eq!(statuses[5..], [StatusCode::TOO_MANY_REQUESTS; 15]);
+        assert_eq!(limited.get(), limited_before + 15);
+
+        // no rate_limit, no limit
+        for _ in 0..20 {
+            let response = client.get(format!("http://{}/tokens/unlimited", addr)).send().await?;
+            assert_eq!(response.status(), StatusCode::OK);
+        }
+
+        handle.abort();
+        TokenCache::cleanup().await;
+        Ok(())
+    }
 }
@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use anyhow::{anyhow, Result};
+use http::header::CONTENT_TYPE;
+use http::{HeaderName, HeaderValue, Method};
+use reqwest::Client;
+use tokio::select;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn, Instrument};
+
+use crate::cache::token_cache::TokenCache;
+use crate::cache::token_event::TokenEvent;
+use crate::config::sinks::{default_content_type, HttpSinkMethod, SinkConfig};
+use crate::observability::audit::{self, AuditEvent, AuditEventType};
+use crate::observability::metrics::{get_metrics, SINK_ENABLED_LABEL};
+use crate::observability::otel::{sink_propagate_span, sink_resync_span};
+use crate::resilience::retry::RetrySettings;
+use crate::sinks::manager::SinkManager;
+use crate::sinks::sink_file::render_file_template;
+use crate::sinks::sink_http::render_http_response_axum;
+use crate::sources::error::FetchError;
+
+static HTTP_PUSH_MSG: &str = "http_push";
+static ERROR_MSG: &str = "error";
+
+impl SinkManager {
+    /// Push rendered HTTP sink response to `target_url` of POST/PUT sinks whenever their token changes.
+    /// Events missed by lagging behind the channel are recovered by syncing every target with `TokenCache`
+    pub async fn start_http_push_sinks(self, mut rx: Receiver<TokenEvent>, shutdown: CancellationToken) -> Result<()> {
+        info!("start sink 'type: http' push");
+        let metrics = get_metrics().await;
+        // sink_id -> last pushed token value
+        let mut pushed: HashMap<String, String> = HashMap::new();
+        loop {
+            let received = select! {
+                _ = shutdown.cancelled() => {
+                    info!("sink http push: shutdown requested");
+                    break;
+                }
+                received = rx.recv() => received,
+            };
+            let event = match received {
+                Ok(event) => Some(event),
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("sink http push: lagged, {} token events skipped, syncing all targets with token cache", skipped);
+                    None
+                }
+                Err(RecvError::Closed) => break,
+            };
+            for (sink_id, cfg) in self.sinks.iter() {
+                if !cfg.is_http_push() || event.as_ref().is_some_and(|event| !cfg.is_subscribed(event)) {
+                    continue;
+                }
+                let source_id = &cfg.source_id;
+                let Some(token_context) = TokenCache::get(&cfg.source_id, &cfg.token_id).await else {
+                    pushed.remove(sink_id);
+                    continue;
+                };
+                if pushed.get(sink_id) == Some(&token_context.token.value) {
+                    debug!("sink http push '{}': token unchanged, skip", sink_id);
+                    continue;
+                }
+                let start = Instant::now();
+                let propagated = push_sink_response(&self.push_client, &self.push_retry, cfg)
+                    .instrument(match &event {
+                        Some(event) => sink_propagate_span(HTTP_PUSH_MSG, sink_id, event),
+                        None => sink_resync_span(HTTP_PUSH_MSG, sink_id, source_id, &cfg.token_id),
+                    })
+                    .await;
+                match propagated {
+                    Ok(_) => {
+                        pushed.insert(sink_id.to_owned(), token_context.token.value);
+                        metrics
+                            .sink_propagations
+                            .with_label_values(&[cfg.sink_id.as_str(), HTTP_PUSH_MSG, source_id.as_str(), cfg.token_id.as_str(), SINK_ENABLED_LABEL])
+                            .inc();
+                        audit::emit(AuditEvent::new(AuditEventType::SinkPropagation, source_id).token(&cfg.token_id, Some(token_context.token.exp_unix_ts)).sink(&cfg.sink_id));
+                        metrics.sink_duration.with_label_values(&[cfg.sink_id.as_str()]).observe(start.elapsed().as_secs_f64());
+                    }
+                    Err(err) => {
+                        error!("sink http push '{}': {}", sink_id, err);
+                        metrics.sink_push_failures.with_label_values(&[cfg.sink_id.as_str(), ERROR_MSG]).inc();
+                        metrics.sink_failures.with_label_values(&[cfg.sink_id.as_str(), ERROR_MSG]).inc();
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Send rendered headers and body to `target_url`, non-success statuses are retried per retry policy
+async fn push_sink_response(client: &Client, retry: &RetrySettings, cfg: &SinkConfig) -> Result<()> {
+    let target_url = cfg
+        .target_url
+        .as_deref()
+        .ok_or_else(|| anyhow!("target_url is required for method {:?}", cfg.method))?;
+    let method = match cfg.push_method() {
+        HttpSinkMethod::Put => Method::PUT,
+        _ => Method::POST,
+    };
+    let metrics = get_metrics().await;
+    retry
+        .run_with_retry(|| {
+            let method = method.clone();
+            async move {
+                let (headers, body, content_type) = render_push_request(cfg).await?;
+                metrics.sink_push_requests.with_label_values(&[cfg.sink_id.as_str(), method.as_str()]).inc();
+                let mut request = client
+                    .request(method, target_url)
+                    .header(CONTENT_TYPE, HeaderValue::from_str(&content_type)?)
+                    .body(body);
+                for (k, v) in headers {
+                    request = request.header(HeaderName::from_bytes(k.as_bytes())?, HeaderValue::from_str(&v)?);
+                }
+                let response = request.send().await?;
+                if !response.status().is_success() {
+                    return Err(FetchError::from_response(response.status(), response.headers()).into());
+                }
+                info!("sink http push '{}': token pushed to {}", cfg.sink_id, target_url);
+                Ok(())
+            }
+        })
+        .await
+}
+
+/// Render headers, body and content type of a push request,
+/// `template` replaces `response.body` when set
+async fn render_push_request(cfg: &SinkConfig) -> Result<(HashMap<String, String>, Vec<u8>, String)> {
+    let Some(template) = &cfg.template else {
+        let (headers, body, content_type) = render_http_response_axum(cfg).await?;
+        return Ok((headers, serde_json::to_vec(&body)?, content_type));
+    };
+    let (headers, content_type) = match &cfg.response {
+        Some(_) => {
+            let (headers, _, content_type) = render_http_response_axum(cfg).await?;
+            (headers, content_type)
+        }
+        None => (HashMap::new(), default_content_type()),
+    };
+    Ok((headers, render_file_template(template, cfg).await?.into_bytes(), content_type))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    use httpmock::prelude::*;
+    use serde_json::json;
+    use serial_test::serial;
+    use tokio_util::sync::CancellationToken;
+
+    use crate::cache::token::Token;
+    use crate::cache::token_cache::TokenCache;
+    use crate::cache::token_context::TokenContext;
+    use crate::cache::token_event::TokenEvent;
+    use crate::config::sinks::{HttpSinkMethod, SinkConfig};
+    use crate::resilience::retry::RetrySettings;
+    use crate::sinks::manager::SinkManager;
+    use crate::tests::common::sink_config;
+    use crate::utils::channel;
+
+    fn push_sink(source_id: &str, target_url: String) -> SinkConfig {
+        sink_config("http", "push_sink", source_id, json!({
+            "token_id": "token",
+            "method": "PUT",
+            "target_url": target_url,
+            "response": {
+                "headers": { "X-Token": { "type": "token", "id": "token" } },
+                "body": { "access_token": { "type": "token", "id": "token" }, "kind": { "type": "string", "value": "bearer" } }
+            }
+        }))
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_http_push_sink_retries_and_skips_unchanged_token() -> anyhow::Result<()> {
+        let server = MockServer::start_async().await;
+        let source_id = "http_push_source";
+        let cfg = push_sink(source_id, server.url("/webhook"));
+        assert_eq!(cfg.method, Some(HttpSinkMethod::Put));
+        assert!(cfg.is_http_push());
+
+        let failing = server.mock(|when, then| {
+            when.method(PUT).path("/webhook");
+            then.status(503);
+        });
+
+        let retry = RetrySettings { attempts: 3, base_delay_ms: 10, max_delay_ms: 10, ..Default::default() };
+        let sink_manager = SinkManager::new(HashMap::from([("push_sink".to_string(), cfg)]))
+            .with_http_push(reqwest::Client::new(), retry);
+        let sink_sender = channel::run();
+        let shutdown = CancellationToken::new();
+        let worker = tokio::spawn(sink_manager.start_http_push_sinks(sink_sender.subscribe(), shutdown.clone()));
+
+        TokenCache::set(source_id.to_string(), vec![TokenContext::new("token".to_string(), Token::new("first".to_string(), 5_000_000_000), 10)]).await?;
+        sink_sender.send(TokenEvent::updated(source_id, TokenCache::get(source_id, "token").await.unwrap()))?;
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while failing.calls_async().await < 3 {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await?;
+        failing.delete_async().await;
+
+        let pushed = server.mock(|when, then| {
+            when.method(PUT)
+                .path("/webhook")
+                .header("content-type", "application/json")
+                .header("X-Token", "first")
+                .json_body(json!({ "access_token": "first", "kind": "bearer" }));
+            then.status(204);
+        });
+        // failed push is not recorded, same token is pushed again
+        sink_sender.send(TokenEvent::updated(source_id, TokenCache::get(source_id, "token").await.unwrap()))?;
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while pushed.calls_async().await < 1 {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await?;
+
+        // unchanged token is not pushed twice
+        sink_sender.send(TokenEvent::updated(source_id, TokenCache::get(source_id, "token").await.unwrap()))?;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        pushed.assert_calls_async(1).await;
+
+        shutdown.cancel();
+        tokio::time::timeout(Duration::from_secs(5), worker).await???;
+        TokenCache::remove_by_source_id(source_id).await;
+        Ok(())
+    }
+}
@@ -0,0 +1,389 @@
+//! Kubernetes Secret sink
+//!
+//! Talks to the API server with a small reqwest client instead of `kube`: the sink only needs a strategic
+//! merge patch of one Secret key, authenticated from the in-cluster service account or the current
+//! kubeconfig context, and `kube` with `k8s-openapi` would pull a large dependency tree for that.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use std::{env, fs};
+
+use anyhow::{anyhow, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use http::header::{AUTHORIZATION, CONTENT_TYPE};
+use reqwest::{Certificate, Client, Identity};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::select;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn, Instrument};
+
+use crate::cache::token_cache::TokenCache;
+use crate::cache::token_event::TokenEvent;
+use crate::config::sinks::{KubernetesSecretSinkConfig, SinkType};
+use crate::observability::audit::{self, AuditEvent, AuditEventType};
+use crate::observability::metrics::{get_metrics, SINK_ENABLED_LABEL};
+use crate::observability::otel::{sink_propagate_span, sink_resync_span};
+use crate::resilience::retry::RetrySettings;
+use crate::sinks::manager::SinkManager;
+use crate::sources::error::FetchError;
+
+static KUBERNETES_SECRET_MSG: &str = "kubernetes_secret";
+static ERROR_MSG: &str = "error";
+static OK_MSG: &str = "ok";
+static SET_MSG: &str = "set";
+static REMOVE_MSG: &str = "remove";
+pub const STRATEGIC_MERGE_PATCH_CONTENT_TYPE: &str = "application/strategic-merge-patch+json";
+pub const IN_CLUSTER_TOKEN_PATH: &str = "/var/run/secrets/kubernetes.io/serviceaccount/token";
+pub const IN_CLUSTER_CA_PATH: &str = "/var/run/secrets/kubernetes.io/serviceaccount/ca.crt";
+
+impl SinkManager {
+    /// Patch sink tokens into their Secret data keys whenever the token changes, remove the key when the token is removed;
+    /// cluster config is loaded on first patch and loaded again after a failed one.
+    /// Events missed by lagging behind the channel are recovered by syncing every sink with `TokenCache`
+    pub async fn start_kubernetes_secret_sinks(self, mut rx: Receiver<TokenEvent>, shutdown: CancellationToken) -> Result<()> {
+        info!("start sink 'type: kubernetes_secret'");
+        let metrics = get_metrics().await;
+        // sink_id -> cluster API
+        let mut apis: HashMap<String, KubeApi> = HashMap::new();
+        // sink_id -> last stored token value
+        let mut stored: HashMap<String, String> = HashMap::new();
+        loop {
+            let received = select! {
+                _ = shutdown.cancelled() => {
+                    info!("sink kubernetes_secret: shutdown requested");
+                    break;
+                }
+                received = rx.recv() => received,
+            };
+            let event = match received {
+                Ok(event) => Some(event),
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("sink kubernetes_secret: lagged, {} token events skipped, syncing all secrets with token cache", skipped);
+                    None
+                }
+                Err(RecvError::Closed) => break,
+            };
+            for (sink_id, cfg) in self.sinks.iter() {
+                if cfg.sink_type != SinkType::KubernetesSecret || event.as_ref().is_some_and(|event| !cfg.is_subscribed(event)) {
+                    continue;
+                }
+                let source_id = &cfg.source_id;
+                let Some(secret) = &cfg.kubernetes_secret else {
+                    continue;
+                };
+                let token_context = TokenCache::get(&cfg.source_id, &cfg.token_id).await;
+                let operation = match &token_context {
+                    Some(token_context) if stored.get(sink_id) == Some(&token_context.token.value) => {
+                        debug!("sink kubernetes_secret '{}': token unchanged, skip", sink_id);
+                        continue;
+                    }
+                    Some(_) => SET_MSG,
+                    None => REMOVE_MSG,
+                };
+                let value = token_context.as_ref().map(|token_context| token_context.token.value.as_str());
+                let start = Instant::now();
+                let patched = match apis.remove(sink_id).map_or_else(|| KubeApi::load(secret.kubeconfig.as_deref()), Ok) {
+                    Ok(api) => {
+                        let patched = api
+                            .patch_secret_key(&self.push_retry, secret, value)
+                            .instrument(match &event {
+                                Some(event) => sink_propagate_span(KUBERNETES_SECRET_MSG, sink_id, event),
+                                None => sink_resync_span(KUBERNETES_SECRET_MSG, sink_id, source_id, &cfg.token_id),
+                            })
+                            .await;
+                        if patched.is_ok() {
+                            apis.insert(sink_id.to_owned(), api);
+                        }
+                        patched
+                    }
+                    Err(err) => Err(err),
+                };
+                let target = format!("{}/{}:{}", secret.namespace, secret.secret_name, secret.key);
+                match patched {
+                    Ok(_) => {
+                        let exp_unix_ts = token_context.as_ref().map(|token_context| token_context.token.exp_unix_ts);
+                        match token_context {
+                            Some(token_context) => stored.insert(sink_id.to_owned(), token_context.token.value),
+                            None => stored.remove(sink_id),
+                        };
+                        info!("sink kubernetes_secret '{}': {} key '{}'", sink_id, operation, target);
+                        metrics.sink_kubernetes_secret_patches.with_label_values(&[cfg.sink_id.as_str(), operation, OK_MSG]).inc();
+                        metrics
+                            .sink_propagations
+                            .with_label_values(&[cfg.sink_id.as_str(), KUBERNETES_SECRET_MSG, source_id.as_str(), cfg.token_id.as_str(), SINK_ENABLED_LABEL])
+                            .inc();
+                        audit::emit(AuditEvent::new(AuditEventType::SinkPropagation, source_id).token(&cfg.token_id, exp_unix_ts).sink(&cfg.sink_id));
+                        metrics.sink_duration.with_label_values(&[cfg.sink_id.as_str()]).observe(start.elapsed().as_secs_f64());
+                    }
+                    Err(err) => {
+                        error!("sink kubernetes_secret '{}': {} key '{}': {}", sink_id, operation, target, err);
+                        metrics.sink_kubernetes_secret_patches.with_label_values(&[cfg.sink_id.as_str(), operation, ERROR_MSG]).inc();
+                        metrics.sink_failures.with_label_values(&[cfg.sink_id.as_str(), ERROR_MSG]).inc();
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Strategic merge patch of a single Secret data key, base64 encoded value; `None` removes the key
+pub fn secret_key_patch(key: &str, value: Option<&str>) -> Value {
+    json!({ "data": { key: value.map(|value| STANDARD.encode(value)) } })
+}
+
+/// Kubernetes API server and credentials
+#[derive(Clone)]
+pub struct KubeApi {
+    server: String,
+    client: Client,
+    auth: KubeAuth,
+}
+
+#[derive(Clone)]
+enum KubeAuth {
+    /// client certificate or no authentication
+    None,
+    Token(String),
+    /// read on every request, projected service account tokens are rotated by kubelet
+    TokenFile(PathBuf),
+}
+
+impl KubeApi {
+    /// Current context of `kubeconfig`, in-cluster config if not set
+    pub fn load(kubeconfig: Option<&str>) -> Result<Self> {
+        match kubeconfig {
+            Some(path) => KubeApi::from_kubeconfig(Path::new(path)),
+            None => KubeApi::in_cluster(),
+        }
+    }
+
+    /// API server of `KUBERNETES_SERVICE_HOST`/`KUBERNETES_SERVICE_PORT` with the pod service account token and CA
+    pub fn in_cluster() -> Result<Self> {
+        let host = env::var("KUBERNETES_SERVICE_HOST")
+            .map_err(|_| anyhow!("KUBERNETES_SERVICE_HOST is not set, kubeconfig is required outside of a cluster"))?;
+        let port = env::var("KUBERNETES_SERVICE_PORT").unwrap_or_else(|_| "443".to_string());
+        let host = if host.contains(':') { format!("[{}]", host) } else { host };
+        let ca = fs::read(IN_CLUSTER_CA_PATH).map_err(|e| anyhow!("read '{}': {}", IN_CLUSTER_CA_PATH, e))?;
+        let client = Client::builder()
+            .add_root_certificate(Certificate::from_pem(&ca).map_err(|e| anyhow!("invalid '{}': {}", IN_CLUSTER_CA_PATH, e))?)
+            .build()?;
+        Ok(KubeApi {
+            server: format!("https://{}:{}", host, port),
+            client,
+            auth: KubeAuth::TokenFile(PathBuf::from(IN_CLUSTER_TOKEN_PATH)),
+        })
+    }
+
+    /// Cluster and user of `current-context`, relative file paths are resolved against the kubeconfig directory
+    pub fn from_kubeconfig(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path).map_err(|e| anyhow!("read kubeconfig '{}': {}", path.display(), e))?;
+        let kubeconfig: Kubeconfig =
+            serde_yaml::from_str(&content).map_err(|e| anyhow!("invalid kubeconfig '{}': {}", path.display(), e))?;
+        let base_dir = path.parent().unwrap_or(Path::new("."));
+        let resolve = |file: &str| base_dir.join(file);
+
+        let context_name = kubeconfig.current_context.as_deref().ok_or_else(|| anyhow!("kubeconfig has no current-context"))?;
+        let context = kubeconfig
+            .contexts
+            .iter()
+            .find(|named| named.name == context_name)
+            .map(|named| &named.context)
+            .ok_or_else(|| anyhow!("kubeconfig context '{}' not found", context_name))?;
+        let cluster = kubeconfig
+            .clusters
+            .iter()
+            .find(|named| named.name == context.cluster)
+            .map(|named| &named.cluster)
+            .ok_or_else(|| anyhow!("kubeconfig cluster '{}' not found", context.cluster))?;
+        let user = match &context.user {
+            Some(user_name) => Some(
+                kubeconfig
+                    .users
+                    .iter()
+                    .find(|named| &named.name == user_name)
+                    .map(|named| &named.user)
+                    .ok_or_else(|| anyhow!("kubeconfig user '{}' not found", user_name))?,
+            ),
+            None => None,
+        };
+
+        let mut builder = Client::builder();
+        let ca = match (&cluster.certificate_authority_data, &cluster.certificate_authority) {
+            (Some(data), _) => Some(STANDARD.decode(data).map_err(|e| anyhow!("invalid certificate-authority-data: {}", e))?),
+            (None, Some(file)) => Some(fs::read(resolve(file)).map_err(|e| anyhow!("read certificate-authority '{}': {}", file, e))?),
+            (None, None) => None,
+        };
+        for cert in ca.map(|pem| Certificate::from_pem_bundle(&pem)).transpose()?.into_iter().flatten() {
+            builder = builder.add_root_certificate(cert);
+        }
+        if cluster.insecure_skip_tls_verify {
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+
+        let mut auth = KubeAuth::None;
+        if let Some(user) = user {
+            let read = |data: &Option<String>, file: &Option<String>, name: &str| -> Result<Option<Vec<u8>>> {
+                match (data, file) {
+                    (Some(data), _) => Ok(Some(STANDARD.decode(data).map_err(|e| anyhow!("invalid {}-data: {}", name, e))?)),
+                    (None, Some(file)) => Ok(Some(fs::read(resolve(file)).map_err(|e| anyhow!("read {} '{}': {}", name, file, e))?)),
+                    (None, None) => Ok(None),
+                }
+            };
+            let cert = read(&user.client_certificate_data, &user.client_certificate, "client-certificate")?;
+            let key = read(&user.client_key_data, &user.client_key, "client-key")?;
+            if let (Some(mut pem), Some(key)) = (cert, key) {
+                pem.push(b'\n');
+                pem.extend_from_slice(&key);
+                builder = builder.identity(Identity::from_pem(&pem).map_err(|e| anyhow!("invalid client certificate/key: {}", e))?);
+            }
+            auth = match (&user.token, &user.token_file) {
+                (Some(token), _) => KubeAuth::Token(token.to_owned()),
+                (None, Some(file)) => KubeAuth::TokenFile(resolve(file)),
+                (None, None) => KubeAuth::None,
+            };
+        }
+        Ok(KubeApi { server: cluster.server.trim_end_matches('/').to_owned(), client: builder.build()?, auth })
+    }
+
+    fn bearer_token(&self) -> Result<Option<String>> {
+        match &self.auth {
+            KubeAuth::None => Ok(None),
+            KubeAuth::Token(token) => Ok(Some(token.to_owned())),
+            KubeAuth::TokenFile(path) => fs::read_to_string(path)
+                .map(|token| Some(token.trim().to_owned()))
+                .map_err(|e| anyhow!("read token file '{}': {}", path.display(), e)),
+        }
+    }
+
+    /// `PATCH` a single data key of an existing Secret, non-success statuses are retried per retry policy
+    pub async fn patch_secret_key(&self, retry: &RetrySettings, secret: &KubernetesSecretSinkConfig, value: Option<&str>) -> Result<()> {
+        let url = format!("{}/api/v1/namespaces/{}/secrets/{}", self.server, secret.namespace, secret.secret_name);
+        let body = serde_json::to_vec(&secret_key_patch(&secret.key, value))?;
+        retry
+            .run_with_retry(|| async {
+                let mut request = self
+                    .client
+                    .patch(&url)
+                    .header(CONTENT_TYPE, STRATEGIC_MERGE_PATCH_CONTENT_TYPE)
+                    .body(body.clone());
+                if let Some(token) = self.bearer_token()? {
+                    request = request.header(AUTHORIZATION, format!("Bearer {}", token));
+                }
+                let response = request.send().await?;
+                if !response.status().is_success() {
+                    return Err(FetchError::from_response(response.status(), response.headers()).into());
+                }
+                Ok(())
+            })
+            .await
+    }
+}
+
+/// Subset of kubeconfig needed to reach the API server
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Kubeconfig {
+    current_context: Option<String>,
+    #[serde(default)]
+    contexts: Vec<NamedContext>,
+    #[serde(default)]
+    clusters: Vec<NamedCluster>,
+    #[serde(default)]
+    users: Vec<NamedUser>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NamedContext {
+    name: String,
+    context: KubeContext,
+}
+
+#[derive(Debug, Deserialize)]
+struct KubeContext {
+    cluster: String,
+    user: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct NamedCluster {
+    name: String,
+    cluster: KubeCluster,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct KubeCluster {
+    server: String,
+    certificate_authority: Option<String>,
+    certificate_authority_data: Option<String>,
+    #[serde(default)]
+    insecure_skip_tls_verify: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct NamedUser {
+    name: String,
+    user: KubeUser,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct KubeUser {
+    token: Option<String>,
+    #[serde(rename = "tokenFile")]
+    token_file: Option<String>,
+    client_certificate: Option<String>,
+    client_certificate_data: Option<String>,
+    client_key: Option<String>,
+    client_key_data: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patch_encodes_value_and_removes_key_with_null() {
+        assert_eq!(secret_key_patch("token", Some("abc")), json!({ "data": { "token": "YWJj" } }));
+        assert_eq!(secret_key_patch("token", None), json!({ "data": { "token": null } }));
+    }
+
+    #[test]
+    fn kubeconfig_current_context_and_relative_token_file() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("token"), "file-token\n").unwrap();
+        let kubeconfig = dir.path().join("config");
+        fs::write(
+            &kubeconfig,
+            r#"
+apiVersion: v1
+kind: Config
+current-context: dev
+contexts:
+  - { name: prod, context: { cluster: prod, user: admin } }
+  - { name: dev, context: { cluster: dev, user: agent } }
+clusters:
+  - { name: prod, cluster: { server: "https://prod.example:6443" } }
+  - { name: dev, cluster: { server: "http://127.0.0.1:8001/" } }
+users:
+  - { name: admin, user: { token: admin-token } }
+  - { name: agent, user: { tokenFile: token } }
+"#,
+        )
+        .unwrap();
+
+        let api = KubeApi::from_kubeconfig(&kubeconfig).unwrap();
+        assert_eq!(api.server, "http://127.0.0.1:8001");
+        assert_eq!(api.bearer_token().unwrap().as_deref(), Some("file-token"));
+
+        fs::write(&kubeconfig, "current-context: missing\ncontexts: []\nclusters: []\n").unwrap();
+        let err = KubeApi::from_kubeconfig(&kubeconfig).err().unwrap();
+        assert_eq!(err.to_string(), "kubeconfig context 'missing' not found");
+    }
+}
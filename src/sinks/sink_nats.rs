@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use async_nats::{Client, ConnectOptions, HeaderMap, HeaderValue};
+use tokio::select;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn, Instrument};
+
+use crate::cache::token_cache::TokenCache;
+use crate::cache::token_event::TokenEvent;
+use crate::config::sinks::{NatsSinkConfig, SinkConfig, SinkType};
+use crate::observability::audit::{self, AuditEvent, AuditEventType};
+use crate::observability::metrics::{get_metrics, SINK_ENABLED_LABEL};
+use crate::observability::otel::{sink_propagate_span, sink_resync_span};
+use crate::resilience::retry::RetrySettings;
+use crate::sinks::manager::SinkManager;
+use crate::sinks::sink_http::render_http_response_axum;
+use crate::sources::fetch::prepare_generic_source_value;
+
+static NATS_MSG: &str = "nats";
+static ERROR_MSG: &str = "error";
+/// publish is acknowledged by server flush within this time, otherwise counted as failure
+const NATS_FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+impl SinkManager {
+    /// Publish sink tokens to their NATS subjects whenever the token changes,
+    /// connections are opened on first publish and reconnect with settings retry backoff.
+    /// Events missed by lagging behind the channel are recovered by syncing every subject with `TokenCache`
+    pub async fn start_nats_sinks(self, mut rx: Receiver<TokenEvent>, shutdown: CancellationToken) -> Result<()> {
+        info!("start sink 'type: nats'");
+        let metrics = get_metrics().await;
+        // sink_id -> connected client
+        let mut clients: HashMap<String, Client> = HashMap::new();
+        // sink_id -> last published token value
+        let mut published: HashMap<String, String> = HashMap::new();
+        loop {
+            let received = select! {
+                _ = shutdown.cancelled() => {
+                    info!("sink nats: shutdown requested");
+                    break;
+                }
+                received = rx.recv() => received,
+            };
+            let event = match received {
+                Ok(event) => Some(event),
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("sink nats: lagged, {} token events skipped, syncing all subjects with token cache", skipped);
+                    None
+                }
+                Err(RecvError::Closed) => break,
+            };
+            for (sink_id, cfg) in self.sinks.iter() {
+                if cfg.sink_type != SinkType::Nats || event.as_ref().is_some_and(|event| !cfg.is_subscribed(event)) {
+                    continue;
+                }
+                let source_id = &cfg.source_id;
+                let Some(nats) = &cfg.nats else {
+                    continue;
+                };
+                let Some(token_context) = TokenCache::get(&cfg.source_id, &cfg.token_id).await else {
+                    published.remove(sink_id);
+                    continue;
+                };
+                if published.get(sink_id) == Some(&token_context.token.value) {
+                    debug!("sink nats '{}': token unchanged, skip", sink_id);
+                    continue;
+                }
+                let start = Instant::now();
+                let propagated = publish_sink_token(&mut clients, &self.push_retry, cfg, nats, &token_context.token.value)
+                    .instrument(match &event {
+                        Some(event) => sink_propagate_span(NATS_MSG, sink_id, event),
+                        None => sink_resync_span(NATS_MSG, sink_id, source_id, &cfg.token_id),
+                    })
+                    .await;
+                match propagated {
+                    Ok(_) => {
+                        published.insert(sink_id.to_owned(), token_context.token.value);
+                        metrics.sink_nats_published.with_label_values(&[cfg.sink_id.as_str()]).inc();
+                        metrics
+                            .sink_propagations
+                            .with_label_values(&[cfg.sink_id.as_str(), NATS_MSG, source_id.as_str(), cfg.token_id.as_str(), SINK_ENABLED_LABEL])
+                            .inc();
+                        audit::emit(AuditEvent::new(AuditEventType::SinkPropagation, source_id).token(&cfg.token_id, Some(token_context.token.exp_unix_ts)).sink(&cfg.sink_id));
+                        metrics.sink_duration.with_label_values(&[cfg.sink_id.as_str()]).observe(start.elapsed().as_secs_f64());
+                    }
+                    Err(err) => {
+                        error!("sink nats '{}': {}", sink_id, err);
+                        metrics.sink_nats_failures.with_label_values(&[cfg.sink_id.as_str(), ERROR_MSG]).inc();
+                        metrics.sink_failures.with_label_values(&[cfg.sink_id.as_str(), ERROR_MSG]).inc();
+                    }
+                }
+            }
+        }
+
+        for (sink_id, client) in clients {
+            debug!("sink nats '{}': draining connection", sink_id);
+            let _ = client.drain().await;
+        }
+        Ok(())
+    }
+}
+
+/// Publish rendered message to sink subject and wait for server flush
+async fn publish_sink_token(
+    clients: &mut HashMap<String, Client>,
+    retry: &RetrySettings,
+    cfg: &SinkConfig,
+    nats: &NatsSinkConfig,
+    token_value: &str,
+) -> Result<()> {
+    let client = match clients.get(&cfg.sink_id) {
+        Some(client) => client.clone(),
+        None => {
+            let client = connect(nats, retry).await?;
+            clients.insert(cfg.sink_id.to_owned(), client.clone());
+            client
+        }
+    };
+    let (headers, payload) = render_nats_message(cfg, token_value).await?;
+    client.publish_with_headers(nats.subject.to_owned(), headers, payload.into()).await?;
+    tokio::time::timeout(NATS_FLUSH_TIMEOUT, client.flush())
+        .await
+        .map_err(|_| anyhow!("flush to '{}' timed out, connection state: {}", nats.nats_url, client.connection_state()))??;
+    info!("sink nats '{}': token published to '{}'", cfg.sink_id, nats.subject);
+    Ok(())
+}
+
+/// Connect to NATS server, dropped connections reconnect with exponential backoff of settings retry policy
+async fn connect(nats: &NatsSinkConfig, retry: &RetrySettings) -> Result<Client> {
+    let mut options = ConnectOptions::new();
+    if let Some(credentials_path) = &nats.credentials_path {
+        let credentials_path = prepare_generic_source_value(credentials_path).await?;
+        options = options.credentials_file(credentials_path).await?;
+    }
+    let retry = retry.clone();
+    let client = options
+        .reconnect_delay_callback(move |attempts| nats_reconnect_delay(&retry, attempts))
+        .connect(nats.nats_url.as_str())
+        .await?;
+    info!("sink nats: connected to '{}'", nats.nats_url);
+    Ok(client)
+}
+
+/// `base_delay_ms * 2^(attempts - 1)` capped by `max_delay_ms`, with retry jitter
+fn nats_reconnect_delay(retry: &RetrySettings, attempts: usize) -> Duration {
+    let exponent = attempts.saturating_sub(1).min(32) as u32;
+    let delay = retry.base_delay_ms.saturating_mul(2u64.saturating_pow(exponent)).min(retry.max_delay_ms);
+    Duration::from_millis(retry.backoff_delay_ms(delay))
+}
+
+/// `response` rendered as for HTTP sinks: headers become message headers, body the JSON payload;
+/// raw token value is published without `response`
+async fn render_nats_message(cfg: &SinkConfig, token_value: &str) -> Result<(HeaderMap, Vec<u8>)> {
+    let mut headers = HeaderMap::new();
+    if cfg.response.is_none() {
+        return Ok((headers, token_value.as_bytes().to_vec()));
+    }
+    let (response_headers, body, content_type) = render_http_response_axum(cfg).await?;
+    headers.insert("Content-Type", HeaderValue::from_str(&content_type)?);
+    for (k, v) in response_headers {
+        headers.insert(k.as_str(), HeaderValue::from_str(&v)?);
+    }
+    Ok((headers, serde_json::to_vec(&body)?))
+}
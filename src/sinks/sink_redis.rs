@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use anyhow::Result;
+use chrono::Utc;
+use redis::aio::MultiplexedConnection;
+use redis::Cmd;
+use serde_json::json;
+use tokio::select;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn, Instrument};
+
+use crate::cache::token_cache::TokenCache;
+use crate::cache::token_context::TokenContext;
+use crate::cache::token_event::TokenEvent;
+use crate::config::sinks::{RedisSinkConfig, RedisTtlMode, RedisValueFormat, SinkConfig, SinkType};
+use crate::observability::audit::{self, AuditEvent, AuditEventType};
+use crate::observability::metrics::{get_metrics, SINK_ENABLED_LABEL};
+use crate::observability::otel::{sink_propagate_span, sink_resync_span};
+use crate::resilience::retry::RetrySettings;
+use crate::sinks::manager::SinkManager;
+use crate::sources::fetch::prepare_generic_source_value;
+
+static REDIS_MSG: &str = "redis";
+static ERROR_MSG: &str = "error";
+static OK_MSG: &str = "ok";
+/// TCP, TLS and unix socket connections
+pub const REDIS_URL_SCHEMES: [&str; 4] = ["redis://", "rediss://", "unix://", "redis+unix://"];
+
+impl SinkManager {
+    /// SET sink tokens in their Redis keys whenever the token changes, DEL the key when the token is removed;
+    /// connections are opened on first command and re-opened after a failed attempt.
+    /// Events missed by lagging behind the channel are recovered by syncing every key with `TokenCache`
+    pub async fn start_redis_sinks(self, mut rx: Receiver<TokenEvent>, shutdown: CancellationToken) -> Result<()> {
+        info!("start sink 'type: redis'");
+        let metrics = get_metrics().await;
+        // sink_id -> connection
+        let mut connections: HashMap<String, MultiplexedConnection> = HashMap::new();
+        // sink_id -> last stored token value
+        let mut stored: HashMap<String, String> = HashMap::new();
+        loop {
+            let received = select! {
+                _ = shutdown.cancelled() => {
+                    info!("sink redis: shutdown requested");
+                    break;
+                }
+                received = rx.recv() => received,
+            };
+            let event = match received {
+                Ok(event) => Some(event),
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("sink redis: lagged, {} token events skipped, syncing all keys with token cache", skipped);
+                    None
+                }
+                Err(RecvError::Closed) => break,
+            };
+            for (sink_id, cfg) in self.sinks.iter() {
+                if cfg.sink_type != SinkType::Redis || event.as_ref().is_some_and(|event| !cfg.is_subscribed(event)) {
+                    continue;
+                }
+                let source_id = &cfg.source_id;
+                let Some(redis) = &cfg.redis else {
+                    continue;
+                };
+                let key = render_redis_key(&redis.key_template, &cfg.source_id, &cfg.token_id);
+                let token_context = TokenCache::get(&cfg.source_id, &cfg.token_id).await;
+                let (command_name, command) = match &token_context {
+                    Some(token_context) if stored.get(sink_id) == Some(&token_context.token.value) => {
+                        debug!("sink redis '{}': token unchanged, skip", sink_id);
+                        continue;
+                    }
+                    Some(token_context) => ("set", set_command(cfg, redis, &key, token_context)?),
+                    None => ("del", redis::cmd("DEL").arg(&key).to_owned()),
+                };
+                let start = Instant::now();
+                let propagated = run_redis_command(&mut connections, &self.push_retry, cfg, redis, &command)
+                    .instrument(match &event {
+                        Some(event) => sink_propagate_span(REDIS_MSG, sink_id, event),
+                        None => sink_resync_span(REDIS_MSG, sink_id, source_id, &cfg.token_id),
+                    })
+                    .await;
+                match propagated {
+                    Ok(_) => {
+                        let exp_unix_ts = token_context.as_ref().map(|token_context| token_context.token.exp_unix_ts);
+                        match token_context {
+                            Some(token_context) => stored.insert(sink_id.to_owned(), token_context.token.value),
+                            None => stored.remove(sink_id),
+                        };
+                        info!("sink redis '{}': {} key '{}'", sink_id, command_name, key);
+                        metrics.sink_redis_commands.with_label_values(&[cfg.sink_id.as_str(), command_name, OK_MSG]).inc();
+                        metrics
+                            .sink_propagations
+                            .with_label_values(&[cfg.sink_id.as_str(), REDIS_MSG, source_id.as_str(), cfg.token_id.as_str(), SINK_ENABLED_LABEL])
+                            .inc();
+                        audit::emit(AuditEvent::new(AuditEventType::SinkPropagation, source_id).token(&cfg.token_id, exp_unix_ts).sink(&cfg.sink_id));
+                        metrics.sink_duration.with_label_values(&[cfg.sink_id.as_str()]).observe(start.elapsed().as_secs_f64());
+                    }
+                    Err(err) => {
+                        error!("sink redis '{}': {} key '{}': {}", sink_id, command_name, key, err);
+                        metrics.sink_redis_commands.with_label_values(&[cfg.sink_id.as_str(), command_name, ERROR_MSG]).inc();
+                        metrics.sink_failures.with_label_values(&[cfg.sink_id.as_str(), ERROR_MSG]).inc();
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `{source_id}` and `{token_id}` placeholders of key template replaced
+pub fn render_redis_key(key_template: &str, source_id: &str, token_id: &str) -> String {
+    key_template.replace("{source_id}", source_id).replace("{token_id}", token_id)
+}
+
+/// SET with expiration of `ttl_mode`, expired token still gets 1 second so the key is not left without TTL
+fn set_command(cfg: &SinkConfig, redis: &RedisSinkConfig, key: &str, token_context: &TokenContext) -> Result<Cmd> {
+    let value = match redis.value_format {
+        RedisValueFormat::RawString => token_context.token.value.to_owned(),
+        RedisValueFormat::JsonEnvelope => serde_json::to_string(&json!({
+            "token": token_context.token.value,
+            "exp_unix_ts": token_context.token.exp_unix_ts,
+            "source_id": cfg.source_id,
+            "token_id": cfg.token_id,
+        }))?,
+    };
+    let mut command = redis::cmd("SET");
+    command.arg(key).arg(value);
+    match redis.ttl_mode {
+        Some(RedisTtlMode::MatchTokenExp) => {
+            let ttl = token_context.token.exp_unix_ts.saturating_sub(Utc::now().timestamp() as u64).max(1);
+            command.arg("EX").arg(ttl);
+        }
+        Some(RedisTtlMode::FixedSeconds(seconds)) => {
+            command.arg("EX").arg(seconds);
+        }
+        None => {}
+    }
+    Ok(command)
+}
+
+/// Run command with settings retry policy, failed attempt drops the connection and next attempt re-opens it
+async fn run_redis_command(
+    connections: &mut HashMap<String, MultiplexedConnection>,
+    retry: &RetrySettings,
+    cfg: &SinkConfig,
+    redis: &RedisSinkConfig,
+    command: &Cmd,
+) -> Result<()> {
+    let slot = Mutex::new(connections.remove(&cfg.sink_id));
+    let result = retry
+        .run_with_retry(|| async {
+            let existing = slot.lock().unwrap().take();
+            let mut connection = match existing {
+                Some(connection) => connection,
+                None => connect(redis).await?,
+            };
+            command.query_async::<()>(&mut connection).await?;
+            *slot.lock().unwrap() = Some(connection);
+            Ok(())
+        })
+        .await;
+    if let Some(connection) = slot.into_inner().unwrap() {
+        connections.insert(cfg.sink_id.to_owned(), connection);
+    }
+    result
+}
+
+async fn connect(redis: &RedisSinkConfig) -> Result<MultiplexedConnection> {
+    let redis_url = prepare_generic_source_value(&redis.redis_url).await?;
+    let connection = redis::Client::open(redis_url)?.get_multiplexed_async_connection().await?;
+    debug!("sink redis: connected");
+    Ok(connection)
+}
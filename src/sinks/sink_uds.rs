@@ -1,102 +1,229 @@
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::os::unix::fs::FileTypeExt;
+use std::path::Path;
 use std::time::Instant;
 
-use tokio::net::UnixStream;
+use tokio::net::{UnixListener, UnixStream};
 use tokio::io::AsyncWriteExt;
-use anyhow::Result;
-use tracing::{error, info};
+use anyhow::{anyhow, Result};
+use tracing::{debug, error, info, warn};
 
-use crate::cache::token::TOKEN_VALUE_STUB;
 use crate::cache::token_cache::TokenCache;
+use crate::cache::token_event::TokenEvent;
 use crate::cache::token_context::TokenContext;
-use crate::config::sinks::{SinkMessage, SinkType};
-use crate::observability::metrics::get_metrics;
+use crate::config::sinks::{SinkConfig, SinkType};
+use crate::observability::audit::{self, AuditEvent, AuditEventType};
+use crate::observability::metrics::{get_metrics, SINK_ENABLED_LABEL};
+use crate::resilience::retry::RetrySettings;
 use crate::sinks::manager::{SinkManager, SyncType};
 use crate::sinks::sink_uds_cache::{SinkUdsCache, SinkUdsTokenMeta};
+use tokio::select;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::broadcast::Receiver;
+use tokio::sync::watch;
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
 
-static UDS_MSG: &'static str = "uds";
-static  ERROR_MSG: &'static str =  "error";
+static UDS_MSG: &str = "uds";
+static ERROR_MSG: &str = "error";
 
 impl SinkManager {
-    pub async fn start_uds_sinks(self, mut rx: Receiver<SinkMessage>) -> Result<()> {
+    /// Serve tokens on unix sockets: every uds sink listens on its `path`,
+    /// each client gets the current token, refresh messages update the served value.
+    /// Events missed by lagging behind the channel are recovered by syncing every socket with `TokenCache`
+    pub async fn start_uds_sinks(self, mut rx: Receiver<TokenEvent>, shutdown: CancellationToken) -> Result<()> {
+        info!("start sink 'type: uds'");
         let metrics = get_metrics().await;
-        loop {
-            if let Ok(message) = rx.recv().await {
-                let start = Instant::now();
-                let source_id = message.0;
-                info!("start sink 'type: uds'");
-                for (name, cfg) in self.sinks.iter() {
-                    if cfg.sink_type != SinkType::Uds {
-                        continue;
-                    }
+        let mut servers = JoinSet::new();
+        // sink name -> served token value
+        let mut served: HashMap<String, watch::Sender<Option<String>>> = HashMap::new();
+        // sinks which socket could not be bound, retried on every next token event
+        let mut pending: Vec<String> = Vec::new();
+        for (name, cfg) in self.sinks.iter().filter(|(_, cfg)| cfg.sink_type == SinkType::Uds) {
+            let listener = match bind_uds_listener_with_retry(&self.push_retry, cfg).await {
+                Ok(listener) => listener,
+                Err(err) => {
+                    error!("sink uds '{}': {}, retry on next token event", name, err);
+                    metrics.sink_failures.with_label_values(&[cfg.sink_id.as_str(), ERROR_MSG]).inc();
+                    pending.push(name.to_owned());
+                    continue;
+                }
+            };
+            info!("UDS sink '{}' listens on '{}'", name, cfg.path);
+            let (tx, token_rx) = watch::channel(None);
+            servers.spawn(serve_uds_sink(cfg.clone(), listener, token_rx, shutdown.clone()));
+            served.insert(name.to_owned(), tx);
+        }
 
-                    info!("sink file:: sink file for source_id: {}", source_id);
-                    let token_context_opt = TokenCache::get(&cfg.source_id, &cfg.token_id).await;
+        loop {
+            let received = select! {
+                _ = shutdown.cancelled() => {
+                    info!("sink uds: shutdown requested");
+                    break;
+                }
+                received = rx.recv() => received,
+            };
+            let event = match received {
+                Ok(event) => Some(event),
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("sink uds: lagged, {} token events skipped, syncing all sockets with token cache", skipped);
+                    None
+                }
+                Err(RecvError::Closed) => break,
+            };
+            if !pending.is_empty() {
+                pending = self.drain_pending_uds_sinks(pending, &mut served, &mut servers, &shutdown).await;
+            }
+            let start = Instant::now();
+            for (name, cfg) in self.sinks.iter() {
+                if cfg.sink_type != SinkType::Uds || event.as_ref().is_some_and(|event| !cfg.is_subscribed(event)) {
+                    continue;
+                }
+                let source_id = &cfg.source_id;
+                let Some(tx) = served.get(name) else {
+                    continue;
+                };
 
-                    let token_opt = if let Some(token_context)= token_context_opt {
-                        // skip storing if token iwth the same exp already exists in cache
-                        if check_if_token_should_be_skipped(&source_id, &token_context).await {
+                match TokenCache::get(&cfg.source_id, &cfg.token_id).await {
+                    Some(token_context) => {
+                        // skip if token with the same exp is already served
+                        if check_if_token_should_be_skipped(source_id, &token_context).await && tx.borrow().is_some() {
                             continue;
                         }
-                        sync_token_with_local_cache(&source_id, &cfg.path, &token_context.id, token_context.token.exp_unix_ts, SyncType::ADD).await;
-                        Some(token_context.token)
-
-                            // removed tokes
-                    } else {
-                        info!("sink file: token  writes to {}", &cfg.path);
-                        // remove from local cache
-                        sync_token_with_local_cache(&source_id, &cfg.path, &&cfg.token_id, 0, SyncType::REMOVE).await;
-                        // cleanup token
-                        None
-                    };
-
-
-                    match token_opt {
-                        Some(token) => {
-                            // store new token
-                            if let Err(err) = async {
-                                let mut stream = UnixStream::connect(&cfg.path).await?;
-                                stream.write_all(token.value.as_bytes()).await?;
-                                stream.shutdown().await?;
-
-                                metrics
-                                    .sink_propagations
-                                    .with_label_values(&[
-                                        &cfg.sink_id.as_str(),
-                                        &UDS_MSG,
-                                        &source_id.as_str(),
-                                        &cfg.token_id.as_str(),
-                                    ])
-                                    .inc();
-                                metrics
-                                    .sink_duration
-                                    .with_label_values(&[&cfg.sink_id.as_str()])
-                                    .observe(start.elapsed().as_secs_f64());
-                                Ok::<(), std::io::Error>(())
-                            }.await {
-                                error!("{}", err);
-                                metrics
-                                    .sink_failures
-                                    .with_label_values(&[cfg.sink_id.as_str(), &ERROR_MSG])
-                                    .inc();
-                            }
-                    
-                    info!("UDS sink '{}' sent token to '{}'", name, cfg.path);
-                        },
-                        None => {
-                            // cleanup content
-                            info!("token id '{}' cleanup, path '{}'", &cfg.token_id, &cfg.path);
-                            let _ = tokio::fs::write(&cfg.path, TOKEN_VALUE_STUB.as_bytes()).await
-                                .inspect_err(|err| {
-                                    error!("{}", err);
-                                    metrics.sink_failures.with_label_values(&[&cfg.sink_id.as_str(), &ERROR_MSG]).inc();
-                                });   
-                        },
+                        if token_context.is_stale {
+                            warn!("UDS sink '{}': token {}.{} is stale, source is failing", name, cfg.source_id, cfg.token_id);
+                        }
+                        sync_token_with_local_cache(source_id, &cfg.path, &token_context.id, token_context.token.exp_unix_ts, SyncType::ADD).await;
+                        tx.send_replace(Some(token_context.token.value));
+                        metrics
+                            .sink_propagations
+                            .with_label_values(&[cfg.sink_id.as_str(), UDS_MSG, source_id.as_str(), cfg.token_id.as_str(), SINK_ENABLED_LABEL])
+                            .inc();
+                        audit::emit(AuditEvent::new(AuditEventType::SinkPropagation, source_id).token(&cfg.token_id, Some(token_context.token.exp_unix_ts)).sink(&cfg.sink_id));
+                        metrics.sink_duration.with_label_values(&[cfg.sink_id.as_str()]).observe(start.elapsed().as_secs_f64());
+                        info!("UDS sink '{}' serves new token on '{}'", name, cfg.path);
+                    }
+                    // removed token: clients get `stub_value` or nothing until next refresh
+                    None => {
+                        info!("token id '{}' cleanup, path '{}'", &cfg.token_id, &cfg.path);
+                        sync_token_with_local_cache(source_id, &cfg.path, &cfg.token_id, 0, SyncType::REMOVE).await;
+                        tx.send_replace(cfg.stub_value.clone());
                     }
                 }
             }
         }
-        
+        let _ = servers.join_all().await;
+        Ok(())
+    }
+
+    /// Single bind attempt for every pending sink, bound sinks start serving the cached token,
+    /// sinks still failing are returned to be retried on the next event
+    async fn drain_pending_uds_sinks(
+        &self,
+        pending: Vec<String>,
+        served: &mut HashMap<String, watch::Sender<Option<String>>>,
+        servers: &mut JoinSet<()>,
+        shutdown: &CancellationToken,
+    ) -> Vec<String> {
+        let metrics = get_metrics().await;
+        let mut still_pending = Vec::new();
+        for name in pending {
+            let Some(cfg) = self.sinks.get(&name) else {
+                continue;
+            };
+            metrics.sink_uds_connect_retries.with_label_values(&[cfg.sink_id.as_str()]).inc();
+            let listener = match bind_uds_listener(&cfg.path).await {
+                Ok(listener) => listener,
+                Err(err) => {
+                    debug!("sink uds '{}': still not bound: {}", name, err);
+                    still_pending.push(name);
+                    continue;
+                }
+            };
+            info!("UDS sink '{}' listens on '{}' after retry", name, cfg.path);
+            let token = TokenCache::get(&cfg.source_id, &cfg.token_id).await;
+            if let Some(token_context) = &token {
+                sync_token_with_local_cache(&cfg.source_id, &cfg.path, &token_context.id, token_context.token.exp_unix_ts, SyncType::ADD).await;
+            }
+            let (tx, token_rx) = watch::channel(token.map(|token_context| token_context.token.value));
+            servers.spawn(serve_uds_sink(cfg.clone(), listener, token_rx, shutdown.clone()));
+            served.insert(name, tx);
+        }
+        still_pending
+    }
+}
+
+/// Bind listener with `settings.retry` backoff, e.g. parent directory is not mounted yet
+/// or the previous agent instance still listens on the socket
+async fn bind_uds_listener_with_retry(retry: &RetrySettings, cfg: &SinkConfig) -> Result<UnixListener> {
+    let metrics = get_metrics().await;
+    let mut attempt = 0;
+    retry
+        .run_with_retry(|| {
+            attempt += 1;
+            if attempt > 1 {
+                metrics.sink_uds_connect_retries.with_label_values(&[cfg.sink_id.as_str()]).inc();
+            }
+            bind_uds_listener(&cfg.path)
+        })
+        .await
+}
+
+/// Bind listener at `path`, stale socket left by unclean shutdown is removed,
+/// socket served by another process and non-socket files are not touched
+async fn bind_uds_listener(path: &str) -> Result<UnixListener> {
+    match tokio::fs::symlink_metadata(path).await {
+        Ok(meta) if meta.file_type().is_socket() => {
+            if UnixStream::connect(path).await.is_ok() {
+                return Err(anyhow!("socket '{}' is in use by another process", path));
+            }
+            info!("sink uds: removing stale socket '{}'", path);
+            tokio::fs::remove_file(path).await?;
+        }
+        Ok(_) => return Err(anyhow!("path '{}' exists and is not a socket", path)),
+        Err(err) if err.kind() == ErrorKind::NotFound => {}
+        Err(err) => return Err(err.into()),
+    }
+    UnixListener::bind(path).map_err(|err| anyhow!("bind '{}': {}", path, err))
+}
+
+/// Accept clients until shutdown, then remove the socket file
+async fn serve_uds_sink(cfg: SinkConfig, listener: UnixListener, token_rx: watch::Receiver<Option<String>>, shutdown: CancellationToken) {
+    let metrics = get_metrics().await;
+    loop {
+        let accepted = select! {
+            _ = shutdown.cancelled() => break,
+            accepted = listener.accept() => accepted,
+        };
+        let mut stream = match accepted {
+            Ok((stream, _)) => stream,
+            Err(err) => {
+                error!("sink uds '{}': accept failed: {}", cfg.sink_id, err);
+                metrics.sink_failures.with_label_values(&[cfg.sink_id.as_str(), ERROR_MSG]).inc();
+                continue;
+            }
+        };
+        let token = token_rx.borrow().clone();
+        let sink_id = cfg.sink_id.clone();
+        tokio::spawn(async move {
+            let res = async {
+                if let Some(token) = token {
+                    stream.write_all(token.as_bytes()).await?;
+                }
+                stream.shutdown().await
+            }
+            .await;
+            if let Err(err) = res {
+                debug!("sink uds '{}': client write failed: {}", sink_id, err);
+            }
+        });
+    }
+    if Path::new(&cfg.path).exists() {
+        info!("remove uds socket at path: {}", cfg.path);
+        if let Err(err) = tokio::fs::remove_file(&cfg.path).await {
+            error!("sink uds '{}': remove socket '{}' failed: {}", cfg.sink_id, cfg.path, err);
+        }
     }
 }
 
@@ -128,101 +255,239 @@ async fn sync_token_with_local_cache(source_id: &str, path: &str, token_id: &str
 mod tests {
     use super::*;
     use tokio::{
-        io::{AsyncReadExt},
-        net::{UnixListener},
+        io::AsyncReadExt,
         time::{timeout, Duration},
     };
     use tempfile::tempdir;
+    use serial_test::serial;
     use std::collections::HashMap;
 
     use crate::{cache::{token::Token, token_cache::TokenCache}, config::sinks::SinkConfig, utils::channel};
     use crate::cache::token_context::TokenContext;
+    use crate::tests::common::{json, overflow_token_events, sink_config};
+
+    fn uds_sink(source_id: &str, path: &Path) -> SinkConfig {
+        sink_config("uds", "sink-1", source_id, json!({ "token_id": "tkn-1", "path": path.to_string_lossy() }))
+    }
+
+    async fn set_token(source_id: &str, value: &str, exp_unix_ts: u64) -> anyhow::Result<()> {
+        let token_ctx = TokenContext::new("tkn-1".to_string(), Token { value: value.to_string(), exp_unix_ts }, 10);
+        TokenCache::set(source_id.to_string(), vec![token_ctx]).await?;
+        Ok(())
+    }
+
+    async fn read_token(path: &Path) -> anyhow::Result<String> {
+        let mut stream = timeout(Duration::from_secs(2), UnixStream::connect(path)).await??;
+        let mut received = String::new();
+        timeout(Duration::from_secs(2), stream.read_to_string(&mut received)).await??;
+        Ok(received)
+    }
+
+    /// Send refresh message and wait until the socket serves `expected`
+    async fn refresh_and_wait(sink_sender: &tokio::sync::broadcast::Sender<TokenEvent>, source_id: &str, path: &Path, expected: &str) -> anyhow::Result<String> {
+        sink_sender.send(TokenEvent::updated(source_id, TokenCache::get(source_id, "tkn-1").await.unwrap()))?;
+        timeout(Duration::from_secs(5), async {
+            loop {
+                match read_token(path).await {
+                    Ok(received) if received == expected => return received,
+                    _ => tokio::time::sleep(Duration::from_millis(20)).await,
+                }
+            }
+        })
+        .await
+        .map_err(Into::into)
+    }
 
     #[tokio::test]
-    async fn test_start_uds_sinks_sends_token() -> anyhow::Result<()> {
-        // -------------------------------
-        // 1. Setup temporary UDS path
-        // -------------------------------
-        // Setup temporary UDS path
+    #[serial]
+    async fn test_uds_sink_serves_current_token_across_refresh() -> anyhow::Result<()> {
         let dir = tempdir()?;
         let socket_path = dir.path().join("test.sock");
-        let socket_path_str = socket_path.to_str().unwrap().to_string();
+        let source_id = "uds-src-1";
+        set_token(source_id, "old-token", 5_000_000_000).await?;
 
-        // Prepare a Unix listener to simulate the consumer
-        let listener = UnixListener::bind(&socket_path)?;
+        let sink_manager = SinkManager::new(HashMap::from([("uds_sink".to_string(), uds_sink(source_id, &socket_path))]));
+        let sink_sender = channel::run();
+        let shutdown = CancellationToken::new();
+        let manager_task = tokio::spawn(sink_manager.start_uds_sinks(sink_sender.subscribe(), shutdown.clone()));
 
-        // -------------------------------
-        // 2. Prepare TokenCache
-        // -------------------------------
+        assert_eq!(refresh_and_wait(&sink_sender, source_id, &socket_path, "old-token").await?, "old-token");
+        // every client gets the token, not only the first one
+        assert_eq!(read_token(&socket_path).await?, "old-token");
 
-        let token_value = "my-secret-token";
-        let source_id = "src-1".to_string();
-        let token_id = "tkn-1".to_string();
-        let token = Token {
-                value: token_value.to_string(),
-                exp_unix_ts: 999999,
-            };
-        let safety_margin_seconds = 10;
-        let token_ctx = TokenContext::new(token_id.to_owned(), token, safety_margin_seconds);
-        TokenCache::set(source_id.clone(), vec![token_ctx.clone()]).await?;
-        
-        // -------------------------------
-        // 2. Prepare SinkManager config
-        // -------------------------------
-        
-        let sink_config = SinkConfig {
-            sink_id: "sink-1".to_string(),
-            sink_type: SinkType::Uds,
-            source_id: source_id.clone(),
-            token_id: token_id.clone(),
-            path: socket_path_str.clone(),
-            response: None,
-        };
+        set_token(source_id, "new-token", 5_000_000_100).await?;
+        assert_eq!(refresh_and_wait(&sink_sender, source_id, &socket_path, "new-token").await?, "new-token");
 
-        let mut sinks = HashMap::new();
-        sinks.insert("uds_sink".to_string(), sink_config);
-        let sink_manager = SinkManager::new(sinks);
+        shutdown.cancel();
+        timeout(Duration::from_secs(5), manager_task).await???;
+        assert!(!socket_path.exists(), "socket must be removed on shutdown");
+        TokenCache::remove_by_source_id(source_id).await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_uds_sink_recovers_stale_socket() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let socket_path = dir.path().join("stale.sock");
+        // unclean shutdown: socket file stays, nobody listens
+        drop(std::os::unix::net::UnixListener::bind(&socket_path)?);
+        assert!(socket_path.exists());
 
-        // -------------------------------
-        // 3. Channel setup to trigger UDS sink event
-        // -------------------------------
-        
+        let source_id = "uds-src-2";
+        set_token(source_id, "fresh-token", 5_000_000_000).await?;
+        let sink_manager = SinkManager::new(HashMap::from([("uds_sink".to_string(), uds_sink(source_id, &socket_path))]));
         let sink_sender = channel::run();
-        let rx = sink_sender.clone().subscribe();
-        // Spawn the sink manager task
-        let manager_task = tokio::spawn(async move {
-            let _ = sink_manager.start_uds_sinks(rx).await;
-        });
+        let shutdown = CancellationToken::new();
+        let manager_task = tokio::spawn(sink_manager.start_uds_sinks(sink_sender.subscribe(), shutdown.clone()));
+
+        assert_eq!(refresh_and_wait(&sink_sender, source_id, &socket_path, "fresh-token").await?, "fresh-token");
 
-        // -------------------------------
-        // 4. Send a message to trigger sink
-        // -------------------------------
+        // live socket is not taken over
+        assert!(bind_uds_listener(socket_path.to_str().unwrap()).await.is_err());
 
-        sink_sender.send(SinkMessage(source_id.clone()))?;
+        shutdown.cancel();
+        timeout(Duration::from_secs(5), manager_task).await???;
+        TokenCache::remove_by_source_id(source_id).await;
+        Ok(())
+    }
 
-        // -------------------------------
-        // 5. Accept the incoming Unix connection and read token
-        // -------------------------------
-        
-        let (mut stream, _) = timeout(Duration::from_secs(5), listener.accept()).await??;
-        let mut buf = vec![0u8; 1024];
-        let n = timeout(Duration::from_secs(2), stream.read(&mut buf)).await??;
-        let received = String::from_utf8_lossy(&buf[..n]);
+    #[tokio::test]
+    #[serial]
+    async fn test_uds_sink_serves_stub_value_after_token_removed() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let socket_path = dir.path().join("stub.sock");
+        let source_id = "uds-src-3";
+        set_token(source_id, "live-token", 5_000_000_000).await?;
+        let mut sink = uds_sink(source_id, &socket_path);
+        sink.stub_value = Some("invalid".to_string());
+        let sink_manager = SinkManager::new(HashMap::from([("uds_sink".to_string(), sink)]));
+        let sink_sender = channel::run();
+        let shutdown = CancellationToken::new();
+        let manager_task = tokio::spawn(sink_manager.start_uds_sinks(sink_sender.subscribe(), shutdown.clone()));
+        assert_eq!(refresh_and_wait(&sink_sender, source_id, &socket_path, "live-token").await?, "live-token");
 
-        println!(">> received: {}", received);
+        let token_context = TokenCache::get(source_id, "tkn-1").await.unwrap();
+        TokenCache::remove_by_source_id(source_id).await;
+        sink_sender.send(TokenEvent::removed(source_id, token_context))?;
+        timeout(Duration::from_secs(5), async {
+            while read_token(&socket_path).await.ok().as_deref() != Some("invalid") {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await?;
 
+        shutdown.cancel();
+        timeout(Duration::from_secs(5), manager_task).await???;
+        Ok(())
+    }
+
+    fn uds_retry(attempts: u32) -> RetrySettings {
+        RetrySettings { attempts, base_delay_ms: 50, max_delay_ms: 200, ..RetrySettings::default() }
+    }
 
-        // -------------------------------
-        // 6. Assert that the token was sent correctly
-        // -------------------------------
-        
-        assert_eq!(received, token_value);
+    #[tokio::test]
+    #[serial]
+    async fn test_uds_sink_retries_bind_until_socket_dir_exists() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let socket_dir = dir.path().join("not-mounted-yet");
+        let socket_path = socket_dir.join("retry.sock");
+        let source_id = "uds-src-4";
+        set_token(source_id, "late-token", 5_000_000_000).await?;
 
-        // Cleanup
-        drop(listener);
-        manager_task.abort();
+        let sink_manager = SinkManager::new(HashMap::from([("uds_sink".to_string(), uds_sink(source_id, &socket_path))]))
+            .with_http_push(reqwest::Client::new(), uds_retry(10));
+        let sink_sender = channel::run();
+        let shutdown = CancellationToken::new();
+        let retries = get_metrics().await.sink_uds_connect_retries.with_label_values(&["sink-1"]).get();
+        let manager_task = tokio::spawn(sink_manager.start_uds_sinks(sink_sender.subscribe(), shutdown.clone()));
+        sink_sender.send(TokenEvent::updated(source_id, TokenCache::get(source_id, "tkn-1").await.unwrap()))?;
 
+        // socket directory appears after the token was sent
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        std::fs::create_dir(&socket_dir)?;
+
+        assert_eq!(refresh_and_wait(&sink_sender, source_id, &socket_path, "late-token").await?, "late-token");
+        assert!(get_metrics().await.sink_uds_connect_retries.with_label_values(&["sink-1"]).get() > retries);
+
+        shutdown.cancel();
+        timeout(Duration::from_secs(5), manager_task).await???;
+        TokenCache::remove_by_source_id(source_id).await;
         Ok(())
     }
-}
 
+    #[tokio::test]
+    #[serial]
+    async fn test_uds_sink_pending_bind_drained_on_event_of_other_source() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let socket_dir = dir.path().join("not-mounted-yet");
+        let socket_path = socket_dir.join("pending.sock");
+        let source_id = "uds-src-5";
+        let other_source_id = "uds-src-6";
+        set_token(source_id, "pending-token", 5_000_000_000).await?;
+        set_token(other_source_id, "other-token", 5_000_000_000).await?;
+
+        // single attempt: sink stays pending after startup
+        let sink_manager = SinkManager::new(HashMap::from([("uds_sink".to_string(), uds_sink(source_id, &socket_path))]))
+            .with_http_push(reqwest::Client::new(), uds_retry(1));
+        let sink_sender = channel::run();
+        let shutdown = CancellationToken::new();
+        let manager_task = tokio::spawn(sink_manager.start_uds_sinks(sink_sender.subscribe(), shutdown.clone()));
+        sink_sender.send(TokenEvent::updated(source_id, TokenCache::get(source_id, "tkn-1").await.unwrap()))?;
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert!(!socket_path.exists());
+
+        std::fs::create_dir(&socket_dir)?;
+        sink_sender.send(TokenEvent::updated(other_source_id, TokenCache::get(other_source_id, "tkn-1").await.unwrap()))?;
+        let received = timeout(Duration::from_secs(5), async {
+            loop {
+                match read_token(&socket_path).await {
+                    Ok(received) => return received,
+                    Err(_) => tokio::time::sleep(Duration::from_millis(20)).await,
+                }
+            }
+        })
+        .await?;
+        assert_eq!(received, "pending-token");
+
+        shutdown.cancel();
+        timeout(Duration::from_secs(5), manager_task).await???;
+        TokenCache::remove_by_source_id(source_id).await;
+        TokenCache::remove_by_source_id(other_source_id).await;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_uds_sink_serves_cached_token_after_lagging() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let socket_path = dir.path().join("lagged.sock");
+        let source_id = "uds-src-7";
+        let sink_manager = SinkManager::new(HashMap::from([("uds_sink".to_string(), uds_sink(source_id, &socket_path))]));
+        let sink_sender = channel::run();
+        let sink_receiver = sink_sender.subscribe();
+
+        // the sink token event is pushed out of the channel by events of another source before the sink reads it
+        set_token(source_id, "cached-token", 5_000_000_000).await?;
+        sink_sender.send(TokenEvent::updated(source_id, TokenCache::get(source_id, "tkn-1").await.unwrap()))?;
+        overflow_token_events(&sink_sender);
+
+        let shutdown = CancellationToken::new();
+        let manager_task = tokio::spawn(sink_manager.start_uds_sinks(sink_receiver, shutdown.clone()));
+        let received = timeout(Duration::from_secs(5), async {
+            loop {
+                match read_token(&socket_path).await {
+                    Ok(received) if !received.is_empty() => return received,
+                    _ => tokio::time::sleep(Duration::from_millis(20)).await,
+                }
+            }
+        })
+        .await?;
+        assert_eq!(received, "cached-token");
+
+        shutdown.cancel();
+        timeout(Duration::from_secs(5), manager_task).await???;
+        TokenCache::remove_by_source_id(source_id).await;
+        Ok(())
+    }
+}
@@ -0,0 +1,308 @@
+//! AWS STS `AssumeRoleWithWebIdentity` source
+//!
+//! Exchanges a web identity token (OIDC JWT, e.g. EKS projected service account token) for
+//! temporary credentials with `POST https://sts.<region>.amazonaws.com/`. The call is not signed,
+//! the web identity token is the only credential. The XML response is parsed into three tokens:
+//! `access_key_id`, `secret_access_key` and `session_token`, all expiring at `Credentials/Expiration`.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Error, Result};
+use http::{HeaderMap, Method};
+use reqwest::Client;
+
+use crate::cache::token_context::TokenContext;
+use crate::config::sources::{
+    AwsStsWebIdentityConfig, BodyFormat, Expiration, ExpirationSource, ExpirationSourceFormat, ParseConfig, RequestConfig,
+    SourceConfig, TokenField, TokenType,
+};
+use crate::parser::parser;
+use crate::sources::error::FetchError;
+use crate::sources::fetch::{get_max_response_bytes, prepare_generic_source_value, read_body_limited, FetchTokens};
+
+pub const AWS_STS_API_VERSION: &str = "2011-06-15";
+pub const AWS_STS_ACTION: &str = "AssumeRoleWithWebIdentity";
+pub const AWS_STS_DURATION_SECONDS_DEFAULT: u64 = 3600;
+/// STS accepts from 15 minutes up to the role maximum session duration, at most 12h
+pub const AWS_STS_DURATION_SECONDS_MIN: u64 = 900;
+pub const AWS_STS_DURATION_SECONDS_MAX: u64 = 43200;
+
+pub const AWS_ACCESS_KEY_ID_TOKEN_ID: &str = "access_key_id";
+pub const AWS_SECRET_ACCESS_KEY_TOKEN_ID: &str = "secret_access_key";
+pub const AWS_SESSION_TOKEN_TOKEN_ID: &str = "session_token";
+
+/// Path of the `Credentials` element in the response
+const AWS_STS_CREDENTIALS_PATH: &str = "/AssumeRoleWithWebIdentityResponse/AssumeRoleWithWebIdentityResult/Credentials";
+
+/// Emitted token ids with their `Credentials` element names
+const AWS_CREDENTIAL_TOKENS: [(&str, &str); 3] = [
+    (AWS_ACCESS_KEY_ID_TOKEN_ID, "AccessKeyId"),
+    (AWS_SECRET_ACCESS_KEY_TOKEN_ID, "SecretAccessKey"),
+    (AWS_SESSION_TOKEN_TOKEN_ID, "SessionToken"),
+];
+
+#[derive(Debug, Clone)]
+pub struct AwsStsWebIdentitySource {
+    pub source_id: String,
+    pub config: Arc<SourceConfig>,
+}
+
+impl FetchTokens for AwsStsWebIdentitySource {
+    async fn fetch_tokens(&self, client: &Client, safety_margin_seconds_settings: Option<u64>) -> Result<Vec<TokenContext>, Error> {
+        let aws_cfg = self.config.aws_sts_web_identity.as_ref().ok_or_else(|| {
+            anyhow!("source '{}': aws_sts_web_identity block is required for type=aws_sts_web_identity", self.source_id)
+        })?;
+        let form = [
+            ("Action", AWS_STS_ACTION.to_owned()),
+            ("Version", AWS_STS_API_VERSION.to_owned()),
+            ("RoleArn", prepare_generic_source_value(&aws_cfg.role_arn).await?),
+            ("RoleSessionName", prepare_generic_source_value(&aws_cfg.role_session_name).await?),
+            ("WebIdentityToken", prepare_generic_source_value(&aws_cfg.web_identity_token).await?),
+            ("DurationSeconds", aws_cfg.duration_seconds.unwrap_or(AWS_STS_DURATION_SECONDS_DEFAULT).to_string()),
+        ];
+        let response = client
+            .post(get_aws_sts_endpoint(aws_cfg))
+            .form(&form)
+            .send()
+            .await
+            .map_err(FetchError::from)?;
+        let status = response.status();
+        let fetch_error = (!status.is_success()).then(|| FetchError::from_response(status, response.headers()));
+        let body = read_body_limited(response, get_max_response_bytes(&self.config.request)).await?;
+        if let Some(fetch_error) = fetch_error {
+            // typed error keeps the status for retry policy, STS error code goes to the context
+            let code = get_xml_element_text(&body, "Code").unwrap_or_default();
+            let message = get_xml_element_text(&body, "Message").unwrap_or_default();
+            return Err(Error::from(fetch_error)
+                .context(format!("source '{}': aws sts {} failed: {} {}", self.source_id, AWS_STS_ACTION, code, message)));
+        }
+
+        parse_credentials(body, safety_margin_seconds_settings, self.config.safety_margin_seconds).await
+    }
+}
+
+/// Token contexts of `AssumeRoleWithWebIdentityResult/Credentials`, all three credentials are required
+pub(crate) async fn parse_credentials(
+    body: String,
+    safety_margin_settings: Option<u64>,
+    safety_margin_source: Option<u64>,
+) -> Result<Vec<TokenContext>> {
+    let tokens = parser::parse_tokens(HeaderMap::new(), body, get_aws_sts_parse_config(), safety_margin_settings, safety_margin_source).await?;
+    if let Some((_, name)) = AWS_CREDENTIAL_TOKENS.iter().find(|(token_id, _)| tokens.iter().all(|ctx| ctx.id != *token_id)) {
+        return Err(FetchError::ParseToken { message: format!("Credentials/{} is missing", name) }.into());
+    }
+    Ok(tokens)
+}
+
+/// Unescaped text of the first `<name>` element of an STS `ErrorResponse`
+fn get_xml_element_text(xml: &str, name: &str) -> Option<String> {
+    let open = format!("<{}>", name);
+    let close = format!("</{}>", name);
+    let start = xml.find(&open)? + open.len();
+    let end = start + xml[start..].find(&close)?;
+    let text = xml[start..end]
+        .trim()
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&");
+    Some(text)
+}
+
+pub fn get_aws_sts_endpoint(aws_cfg: &AwsStsWebIdentityConfig) -> String {
+    aws_cfg
+        .sts_endpoint
+        .clone()
+        .unwrap_or_else(|| format!("https://sts.{}.amazonaws.com/", aws_cfg.region))
+}
+
+/// Request and parse blocks describing the STS call, used by validation, metrics and sinks
+pub fn get_aws_sts_request_and_parse(aws_cfg: &AwsStsWebIdentityConfig) -> (RequestConfig, ParseConfig) {
+    let request = RequestConfig {
+        url: get_aws_sts_endpoint(aws_cfg),
+        method: Method::POST,
+        body: Some(HashMap::from([
+            ("RoleArn".to_owned(), aws_cfg.role_arn.clone()),
+            ("RoleSessionName".to_owned(), aws_cfg.role_session_name.clone()),
+            ("WebIdentityToken".to_owned(), aws_cfg.web_identity_token.clone()),
+        ])),
+        ..Default::default()
+    };
+    (request, get_aws_sts_parse_config())
+}
+
+/// Parse block of the `Credentials` tokens, all expiring at `Credentials/Expiration`
+fn get_aws_sts_parse_config() -> ParseConfig {
+    ParseConfig {
+        expect: None,
+        body_format: Some(BodyFormat::Xml),
+        tokens: AWS_CREDENTIAL_TOKENS
+            .iter()
+            .map(|(token_id, name)| TokenField {
+                id: token_id.to_string(),
+                parent: "body".to_owned(),
+                pointer: format!("{}/{}", AWS_STS_CREDENTIALS_PATH, name),
+                token_type: TokenType::PlainText,
+                jwks_uri: None,
+                transforms: None,
+                extract_claims: None,
+                iterate: None,
+                max_token_bytes: None,
+                value_regex: None,
+                expiration: Some(Expiration {
+                    source: ExpirationSource::JsonBodyField,
+                    pointer: Some(format!("{}/Expiration", AWS_STS_CREDENTIALS_PATH)),
+                    linked_token_id: None,
+                    manual_ttl_seconds: None,
+                    format: ExpirationSourceFormat::Rfc3339,
+                }),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::{Duration, Utc};
+    use httpmock::Method::POST;
+    use httpmock::MockServer;
+    use reqwest::Client;
+
+    use super::*;
+    use crate::config::sources::{GenericSourceValue, SourceTypes};
+
+    const ROLE_ARN: &str = "arn:aws:iam::123456789012:role/token-agent";
+
+    fn make_source(server: &MockServer, token_path: &str) -> Arc<SourceConfig> {
+        let aws = AwsStsWebIdentityConfig {
+            role_arn: GenericSourceValue::Literal { value: ROLE_ARN.into() },
+            role_session_name: GenericSourceValue::Literal { value: "token-agent".into() },
+            web_identity_token: GenericSourceValue::FromFile { path: token_path.into() },
+            region: "eu-west-1".into(),
+            duration_seconds: Some(900),
+            sts_endpoint: Some(server.url("/")),
+        };
+        let (request, parse) = get_aws_sts_request_and_parse(&aws);
+        Arc::new(SourceConfig {
+            source_type: SourceTypes::AwsStsWebIdentity,
+            request,
+            parse,
+            safety_margin_seconds: Some(10),
+            aws_sts_web_identity: Some(aws),
+            ..Default::default()
+        })
+    }
+
+    fn token_file(dir: &tempfile::TempDir) -> String {
+        let path = dir.path().join("token");
+        std::fs::write(&path, "oidc.jwt.token\n").unwrap();
+        path.to_str().unwrap().to_owned()
+    }
+
+    fn response_xml(credentials: &str) -> String {
+        format!(
+            r#"<AssumeRoleWithWebIdentityResponse xmlns="https://sts.amazonaws.com/doc/2011-06-15/">
+  <AssumeRoleWithWebIdentityResult>
+    <SubjectFromWebIdentityToken>system:serviceaccount:default:token-agent</SubjectFromWebIdentityToken>
+    <Credentials>{}</Credentials>
+  </AssumeRoleWithWebIdentityResult>
+</AssumeRoleWithWebIdentityResponse>"#,
+            credentials
+        )
+    }
+
+    #[tokio::test]
+    async fn credentials_emitted_as_three_tokens() {
+        let server = MockServer::start_async().await;
+        let expiration = (Utc::now() + Duration::seconds(900)).to_rfc3339();
+        let credentials = format!(
+            "<AccessKeyId>ASIAEXAMPLE</AccessKeyId><SecretAccessKey>secret/key+value</SecretAccessKey>\
+             <SessionToken>FwoGZXIvYXdzE&amp;token</SessionToken><Expiration>{}</Expiration>",
+            expiration
+        );
+        let sts = server.mock(|when, then| {
+            when.method(POST)
+                .path("/")
+                .form_urlencoded_tuple("Action", AWS_STS_ACTION)
+                .form_urlencoded_tuple("Version", AWS_STS_API_VERSION)
+                .form_urlencoded_tuple("RoleArn", ROLE_ARN)
+                .form_urlencoded_tuple("RoleSessionName", "token-agent")
+                .form_urlencoded_tuple("WebIdentityToken", "oidc.jwt.token")
+                .form_urlencoded_tuple("DurationSeconds", "900");
+            then.status(200).header("Content-Type", "text/xml").body(response_xml(&credentials));
+        });
+        let dir = tempfile::tempdir().unwrap();
+
+        let source = AwsStsWebIdentitySource { source_id: "aws_sts".into(), config: make_source(&server, &token_file(&dir)) };
+        let tokens = source.fetch_tokens(&Client::new(), None).await.unwrap();
+
+        sts.assert();
+        let values: Vec<(&str, &str)> = tokens.iter().map(|ctx| (ctx.id.as_str(), ctx.token.value.as_str())).collect();
+        assert_eq!(
+            values,
+            [
+                (AWS_ACCESS_KEY_ID_TOKEN_ID, "ASIAEXAMPLE"),
+                (AWS_SECRET_ACCESS_KEY_TOKEN_ID, "secret/key+value"),
+                (AWS_SESSION_TOKEN_TOKEN_ID, "FwoGZXIvYXdzE&token"),
+            ]
+        );
+        let exp = chrono::DateTime::parse_from_rfc3339(&expiration).unwrap().timestamp() as u64;
+        assert!(tokens.iter().all(|ctx| ctx.token.exp_unix_ts == exp));
+    }
+
+    #[tokio::test]
+    async fn sts_error_response_is_typed_with_error_code() {
+        let server = MockServer::start_async().await;
+        server.mock(|when, then| {
+            when.method(POST).path("/");
+            then.status(400).header("Content-Type", "text/xml").body(
+                r#"<ErrorResponse xmlns="https://sts.amazonaws.com/doc/2011-06-15/">
+  <Error><Type>Sender</Type><Code>InvalidIdentityToken</Code><Message>Couldn't retrieve verification key</Message></Error>
+  <RequestId>c6104cbe-af31-11e0-8154-cbc7ccf896c7</RequestId>
+</ErrorResponse>"#,
+            );
+        });
+        let dir = tempfile::tempdir().unwrap();
+
+        let source = AwsStsWebIdentitySource { source_id: "aws_sts_error".into(), config: make_source(&server, &token_file(&dir)) };
+        let err = source.fetch_tokens(&Client::new(), None).await.unwrap_err();
+
+        assert_eq!(FetchError::http_status(&err), Some(http::StatusCode::BAD_REQUEST));
+        assert!(err.to_string().contains("InvalidIdentityToken Couldn't retrieve verification key"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn incomplete_credentials_are_parse_errors() {
+        let server = MockServer::start_async().await;
+        let credentials = "<AccessKeyId>ASIAEXAMPLE</AccessKeyId><SecretAccessKey>secret</SecretAccessKey>\
+                           <Expiration>2030-01-01T00:00:00Z</Expiration>";
+        server.mock(|when, then| {
+            when.method(POST).path("/");
+            then.status(200).body(response_xml(credentials));
+        });
+        let dir = tempfile::tempdir().unwrap();
+
+        let source = AwsStsWebIdentitySource { source_id: "aws_sts_incomplete".into(), config: make_source(&server, &token_file(&dir)) };
+        let err = source.fetch_tokens(&Client::new(), None).await.unwrap_err();
+
+        assert_eq!(FetchError::reason(&err), "parse_token");
+        assert!(err.to_string().contains("Credentials/SessionToken is missing"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn missing_web_identity_token_file_fails_before_request() {
+        let server = MockServer::start_async().await;
+        let sts = server.mock(|when, then| {
+            when.method(POST).path("/");
+            then.status(200);
+        });
+
+        let source = AwsStsWebIdentitySource { source_id: "aws_sts_no_token".into(), config: make_source(&server, "/nonexistent/token") };
+        assert!(source.fetch_tokens(&Client::new(), None).await.is_err());
+        sts.assert_calls(0);
+    }
+}
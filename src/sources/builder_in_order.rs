@@ -1,11 +1,16 @@
 use crate::{
     cache::{token_cache::TokenCache, token_context::TokenContext},
-    config::sources::SourceConfig
+    config::sources::SourceConfig,
+    observability::metrics::get_metrics,
 };
 use anyhow::{anyhow, Result};
+use chrono::Utc;
 use std::{collections::{HashMap, HashSet}, sync::Arc};
 use tracing::info;
 
+const DOT_NODE_FILL_COLOR: &str = "white";
+const DOT_LEAF_FILL_COLOR: &str = "lightgreen";
+
 /// Represents a single node in the DAG — one `SourceConfig` and its dependencies
 #[derive(Debug, Clone)]
 pub struct DagNode {
@@ -44,8 +49,12 @@ impl SourceDag {
             if let Some(src) = sources.get(key) {
                 if let Some(inputs) = &src.inputs {
                     for dep in inputs {
-                        if !sources.contains_key(dep) {
-                            return Err(anyhow!("Unknown dependency '{}' for '{}'", dep, key));
+                        match sources.get(dep) {
+                            None => return Err(anyhow!("Unknown dependency '{}' for '{}'", dep, key)),
+                            Some(dep_source) if !dep_source.is_enabled() => {
+                                return Err(anyhow!("Dependency '{}' of '{}' is disabled", dep, key))
+                            }
+                            Some(_) => {}
                         }
                         visit(dep, sources, visited, temp, order)?;
                     }
@@ -58,7 +67,8 @@ impl SourceDag {
             Ok(())
         }
 
-        for k in sources.keys() {
+        // disabled sources get no node
+        for (k, _) in sources.iter().filter(|(_, src)| src.is_enabled()) {
             visit(k, sources, &mut visited, &mut temp, &mut order)?;
         }
 
@@ -79,13 +89,82 @@ impl SourceDag {
         Ok(SourceDag { ordered: Arc::new(ordered) })
     }
 
+    /// Group nodes into dependency layers: every node depends only on nodes from previous layers,
+    /// so nodes inside one layer can be fetched concurrently
+    pub fn layers(&self) -> Vec<Vec<&DagNode>> {
+        let mut depth_by_id: HashMap<&str, usize> = HashMap::new();
+        let mut layers: Vec<Vec<&DagNode>> = Vec::new();
+
+        // `ordered` is topologically sorted, dependencies always have depth already
+        for node in self.ordered.iter() {
+            let depth = node
+                .deps
+                .iter()
+                .filter_map(|dep| depth_by_id.get(dep.as_str()))
+                .map(|dep_depth| dep_depth + 1)
+                .max()
+                .unwrap_or(0);
+            depth_by_id.insert(node.id.as_str(), depth);
+            if layers.len() <= depth {
+                layers.resize_with(depth + 1, Vec::new);
+            }
+            layers[depth].push(node);
+        }
+
+        info!("Execution layers: {:?}", layers.iter().map(|layer| layer.iter().map(|node| &node.id).collect::<Vec<&String>>()).collect::<Vec<_>>());
+        layers
+    }
+
+    /// Graphviz DOT digraph of the sources: nodes are labeled with source id and type,
+    /// edges point from a source to the sources it depends on, leaf sources (no dependencies) are highlighted
+    pub fn to_dot(&self) -> String {
+        let mut nodes: Vec<&DagNode> = self.ordered.iter().collect();
+        nodes.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let mut dot = String::from("digraph sources {\n");
+        dot.push_str("    rankdir=LR;\n");
+        dot.push_str(&format!("    node [shape=box, style=filled, fillcolor={}];\n", DOT_NODE_FILL_COLOR));
+        for node in &nodes {
+            let fill_color = match node.deps.is_empty() {
+                true => format!(", fillcolor={}", DOT_LEAF_FILL_COLOR),
+                false => String::new(),
+            };
+            dot.push_str(&format!(
+                "    \"{}\" [label=\"{}\\n{}\"{}];\n",
+                escape_dot(&node.id),
+                escape_dot(&node.id),
+                node.config.source_type.as_str(),
+                fill_color
+            ));
+        }
+        for node in &nodes {
+            let mut deps: Vec<&String> = node.deps.iter().collect();
+            deps.sort();
+            for dep in deps {
+                dot.push_str(&format!(
+                    "    \"{}\" -> \"{}\" [label=\"depends on\"];\n",
+                    escape_dot(&node.id),
+                    escape_dot(dep)
+                ));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
     pub async fn store_tokens_by_source_id(
         source_id: &str,
         source_token_contexts: Vec<TokenContext>,
+        rotation_overlap_seconds: Option<u64>,
     ) -> Result<Vec<String>> {
         let source_token_contexts_len = source_token_contexts.len();
-        TokenCache::set(source_id.to_owned(), source_token_contexts).await
+        let metrics = get_metrics().await;
+        TokenCache::set_with_overlap(source_id.to_owned(), source_token_contexts, rotation_overlap_seconds).await
         .map(|updated_token_contexts| {
+            let stored_at = Utc::now().timestamp();
+            for token_id in &updated_token_contexts {
+                metrics.token_last_refresh_unix.with_label_values(&[source_id, token_id.as_str()]).set(stored_at);
+            }
             match updated_token_contexts.len() == source_token_contexts_len {
                 true => info!("all the tokens fetched and updated successfully"),
                 false => info!("not all the tokens fetched and updated successfully total tokens {}, updated tokens {}", source_token_contexts_len, updated_token_contexts.len()),
@@ -94,9 +173,100 @@ impl SourceDag {
         })
     }
 
-    pub async fn invalidate_tokens_by_source_id(source_id: &str) -> Result<()> {
-        let _ = TokenCache::invalidate_expired_tokens_by_source_id(source_id).await;
+    pub async fn invalidate_tokens_by_source_id(
+        source_id: &str,
+        stale_token_ttl_seconds: Option<u64>,
+        rotation_overlap_seconds: Option<u64>,
+    ) -> Result<()> {
+        let _ = TokenCache::invalidate_expired_tokens_with_stale(source_id, stale_token_ttl_seconds, rotation_overlap_seconds).await;
         info!("tokens invalidated for source_id: {}", source_id);
         Ok(())
     }
 }
+
+/// Escape quotes and backslashes inside a DOT quoted string
+fn escape_dot(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::config::sources::{ParseConfig, RequestConfig, SourceTypes};
+
+    fn make_source(source_type: SourceTypes, inputs: &[&str]) -> SourceConfig {
+        SourceConfig {
+            source_type,
+            request: RequestConfig::default(),
+            parse: ParseConfig::default(),
+            inputs: (!inputs.is_empty()).then(|| inputs.iter().map(|input| input.to_string()).collect()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn to_dot_renders_nodes_edges_and_leaves() {
+        // metadata <- sts <- api, vault <- api
+        let sources = HashMap::from([
+            ("metadata".to_string(), make_source(SourceTypes::METADATA, &[])),
+            ("vault".to_string(), make_source(SourceTypes::VAULT, &[])),
+            ("sts".to_string(), make_source(SourceTypes::HTTP, &["metadata"])),
+            ("api".to_string(), make_source(SourceTypes::OAUTH2, &["vault", "sts"])),
+        ]);
+
+        let dot = SourceDag::build(&sources).unwrap().to_dot();
+
+        let expected = [
+            "digraph sources {",
+            "    rankdir=LR;",
+            "    node [shape=box, style=filled, fillcolor=white];",
+            r#"    "api" [label="api\noauth2"];"#,
+            r#"    "metadata" [label="metadata\nmetadata", fillcolor=lightgreen];"#,
+            r#"    "sts" [label="sts\nhttp"];"#,
+            r#"    "vault" [label="vault\nvault", fillcolor=lightgreen];"#,
+            r#"    "api" -> "sts" [label="depends on"];"#,
+            r#"    "api" -> "vault" [label="depends on"];"#,
+            r#"    "sts" -> "metadata" [label="depends on"];"#,
+            "}",
+        ];
+        let lines: Vec<&str> = dot.lines().collect();
+        assert_eq!(lines.len(), expected.len(), "{}", dot);
+        for (line, expected_line) in lines.iter().zip(expected) {
+            assert_eq!(*line, expected_line);
+        }
+    }
+
+    #[test]
+    fn to_dot_escapes_quotes_in_source_id() {
+        let sources = HashMap::from([(r#"my "quoted" source"#.to_string(), make_source(SourceTypes::HTTP, &[]))]);
+
+        let dot = SourceDag::build(&sources).unwrap().to_dot();
+
+        assert!(dot.contains(r#"    "my \"quoted\" source" [label="my \"quoted\" source\nhttp", fillcolor=lightgreen];"#), "{}", dot);
+    }
+
+    #[test]
+    fn disabled_source_has_no_node() {
+        // metadata (disabled) <- sts, vault <- api
+        let disabled = SourceConfig { enabled: Some(false), ..make_source(SourceTypes::METADATA, &[]) };
+        let sources = HashMap::from([
+            ("metadata".to_string(), disabled),
+            ("vault".to_string(), make_source(SourceTypes::VAULT, &[])),
+            ("api".to_string(), SourceConfig { enabled: Some(true), ..make_source(SourceTypes::OAUTH2, &["vault"]) }),
+        ]);
+
+        let dag = SourceDag::build(&sources).unwrap();
+        let ids: Vec<&str> = dag.ordered.iter().map(|node| node.id.as_str()).collect();
+        assert_eq!(ids, vec!["vault", "api"]);
+        let layers: Vec<Vec<&str>> = dag.layers().iter().map(|layer| layer.iter().map(|node| node.id.as_str()).collect()).collect();
+        assert_eq!(layers, vec![vec!["vault"], vec!["api"]]);
+
+        // enabled source can not depend on a disabled one
+        let mut sources = sources;
+        sources.insert("sts".to_string(), make_source(SourceTypes::HTTP, &["metadata"]));
+        let err = SourceDag::build(&sources).err().unwrap();
+        assert_eq!(err.to_string(), "Dependency 'metadata' of 'sts' is disabled");
+    }
+}
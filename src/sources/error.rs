@@ -0,0 +1,206 @@
+use std::fmt;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use http::HeaderMap;
+use reqwest::StatusCode;
+
+/// Typed source fetch errors, carried inside `anyhow::Error` so that
+/// retry policy can inspect them with `downcast_ref`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FetchError {
+    /// Response with non-success HTTP status and optional `Retry-After` delay
+    HttpStatus {
+        status: StatusCode,
+        retry_after: Option<Duration>,
+    },
+    /// Fetch attempt exceeded `read_seconds` timeout
+    Timeout {
+        timeout: Duration,
+    },
+    /// Connection, TLS or body transfer failure
+    Network {
+        message: String,
+    },
+    /// Response body does not match `parse.body_format` (JSON or XML) while body tokens are configured
+    ParseBody {
+        message: String,
+    },
+    /// None of the configured tokens could be extracted from the response
+    ParseToken {
+        message: String,
+    },
+    /// Token of an input source referenced by request values is not in cache
+    MissingDependency {
+        source: String,
+        id: String,
+    },
+    /// Response body is longer than `max_response_bytes`
+    ResponseTooLarge {
+        limit: usize,
+    },
+    /// Response status or content type violates `parse.expect`, e.g. HTML page of an intercepting proxy
+    UnexpectedResponse {
+        status: StatusCode,
+        content_type: Option<String>,
+        /// first characters of the body, control characters replaced
+        body_preview: String,
+    },
+    /// Credential helper of exec source exited with non-zero code, `None` when killed by a signal
+    ExecExit {
+        code: Option<i32>,
+        stderr: String,
+    },
+}
+
+/// Characters of the response body kept in `UnexpectedResponse` errors
+pub const BODY_PREVIEW_CHARS: usize = 120;
+
+/// `reason` label of untyped fetch errors
+pub const FETCH_ERROR_REASON_DEFAULT: &str = "error";
+
+impl FetchError {
+    pub fn from_response(status: StatusCode, headers: &HeaderMap) -> Self {
+        let retry_after = headers
+            .get(http::header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| parse_retry_after(v, Utc::now()));
+        FetchError::HttpStatus { status, retry_after }
+    }
+
+    /// HTTP status of the error, if it was caused by a non-success response
+    pub fn http_status(err: &anyhow::Error) -> Option<StatusCode> {
+        err.downcast_ref::<FetchError>().and_then(|fetch_error| match fetch_error {
+            FetchError::HttpStatus { status, .. } => Some(*status),
+            _ => None,
+        })
+    }
+
+    /// Delay requested by server with `Retry-After` header
+    pub fn retry_after(err: &anyhow::Error) -> Option<Duration> {
+        err.downcast_ref::<FetchError>().and_then(|fetch_error| match fetch_error {
+            FetchError::HttpStatus { retry_after, .. } => *retry_after,
+            _ => None,
+        })
+    }
+
+    /// `reason` label of `source_fetch_failures_total`: snake case variant name, `error` for untyped errors
+    pub fn reason(err: &anyhow::Error) -> &'static str {
+        match err.downcast_ref::<FetchError>() {
+            Some(FetchError::HttpStatus { .. }) => "http_status",
+            Some(FetchError::Timeout { .. }) => "timeout",
+            Some(FetchError::Network { .. }) => "network",
+            Some(FetchError::ParseBody { .. }) => "parse_body",
+            Some(FetchError::ParseToken { .. }) => "parse_token",
+            Some(FetchError::MissingDependency { .. }) => "missing_dependency",
+            Some(FetchError::ResponseTooLarge { .. }) => "response_too_large",
+            Some(FetchError::UnexpectedResponse { .. }) => "unexpected_response",
+            Some(FetchError::ExecExit { .. }) => "exec_exit",
+            None => FETCH_ERROR_REASON_DEFAULT,
+        }
+    }
+
+    /// Same response is expected on the next attempt for parse errors, unexpected responses and missing input tokens,
+    /// HTTP statuses are filtered further by `retry_on_status`
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            FetchError::HttpStatus { .. } | FetchError::Timeout { .. } | FetchError::Network { .. } | FetchError::ExecExit { .. } => true,
+            FetchError::ParseBody { .. }
+            | FetchError::ParseToken { .. }
+            | FetchError::MissingDependency { .. }
+            | FetchError::ResponseTooLarge { .. }
+            | FetchError::UnexpectedResponse { .. } => false,
+        }
+    }
+}
+
+impl From<reqwest::Error> for FetchError {
+    fn from(err: reqwest::Error) -> Self {
+        FetchError::Network { message: err.to_string() }
+    }
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FetchError::HttpStatus { status, retry_after: None } => write!(f, "HTTP request failed: {}", status),
+            FetchError::HttpStatus { status, retry_after: Some(retry_after) } => write!(
+                f,
+                "HTTP request failed: {}, retry after {}s",
+                status,
+                retry_after.as_secs()
+            ),
+            FetchError::Timeout { timeout } => write!(f, "request timed out after {}s", timeout.as_secs_f64()),
+            FetchError::Network { message } => write!(f, "request failed: {}", message),
+            FetchError::ParseBody { message } => write!(f, "response body can not be decoded: {}", message),
+            FetchError::ParseToken { message } => write!(f, "no token parsed: {}", message),
+            FetchError::MissingDependency { source, id } => write!(f, "token {}.{} is absent", source, id),
+            FetchError::ResponseTooLarge { limit } => write!(f, "response body exceeds {} bytes", limit),
+            FetchError::UnexpectedResponse { status, content_type, body_preview } => write!(
+                f,
+                "unexpected response: status {}, content type '{}', body: {}",
+                status,
+                content_type.as_deref().unwrap_or_default(),
+                body_preview
+            ),
+            FetchError::ExecExit { code: Some(code), stderr } => write!(f, "command exited with code {}: {}", code, stderr),
+            FetchError::ExecExit { code: None, stderr } => write!(f, "command killed by signal: {}", stderr),
+        }
+    }
+}
+
+impl std::error::Error for FetchError {}
+
+/// Parse `Retry-After` value: delta-seconds (`30`) or HTTP-date (`Wed, 21 Oct 2015 07:28:00 GMT`),
+/// dates in the past give zero delay
+pub fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    DateTime::parse_from_rfc2822(value)
+        .ok()
+        .map(|date| (date.with_timezone(&Utc) - now).to_std().unwrap_or(Duration::ZERO))
+}
+
+/// First `BODY_PREVIEW_CHARS` characters of a response body for logs, whitespace and control characters
+/// collapse to single spaces, e.g. an HTML error page becomes one line
+pub fn body_preview(body: &str) -> String {
+    let sanitized = body
+        .split(|c: char| c.is_whitespace() || c.is_control())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    match sanitized.char_indices().nth(BODY_PREVIEW_CHARS) {
+        Some((end, _)) => format!("{}…", &sanitized[..end]),
+        None => sanitized,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn parse_retry_after_delta_seconds_and_http_date() {
+        let now = Utc.with_ymd_and_hms(2015, 10, 21, 7, 27, 0).unwrap();
+        assert_eq!(parse_retry_after("30", now), Some(Duration::from_secs(30)));
+        assert_eq!(parse_retry_after(" 0 ", now), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT", now), Some(Duration::from_secs(60)));
+        // date in the past
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("-1", now), None);
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn body_preview_is_single_line_and_truncated() {
+        assert_eq!(body_preview("<html>\r\n  <body>\tAccess\u{0}denied</body>\n</html>"), "<html> <body> Access denied</body> </html>");
+        let preview = body_preview(&"é".repeat(BODY_PREVIEW_CHARS + 10));
+        assert_eq!(preview.chars().count(), BODY_PREVIEW_CHARS + 1);
+        assert!(preview.ends_with('…'));
+        assert_eq!(body_preview(""), "");
+    }
+}
@@ -0,0 +1,221 @@
+//! Exec source: external credential helper
+//!
+//! Runs `command` with `args` directly, without a shell, and parses its stdout with `parse.tokens`
+//! as if it were a response body without headers, e.g. `gcloud auth print-access-token` or
+//! `vault token lookup -format=json`. A non-zero exit code fails the attempt with reason `exec_exit`,
+//! a command running longer than `timeout_ms` is killed and fails it with reason `timeout`,
+//! a command printing more than `max_response_bytes` is killed and fails it with reason `response_too_large`.
+
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Error, Result};
+use http::{HeaderMap, Method};
+use reqwest::Client;
+use tokio::io::AsyncReadExt;
+use tokio::process::{ChildStderr, ChildStdout, Command};
+use tracing::debug;
+
+use crate::cache::token_context::TokenContext;
+use crate::config::sources::{ExecSourceConfig, RequestConfig, SourceConfig};
+use crate::parser::parser;
+use crate::sources::error::FetchError;
+use crate::sources::fetch::{get_max_response_bytes, prepare_generic_source_value, FetchTokens};
+
+pub const EXEC_SOURCE_TIMEOUT_MS_DEFAULT: u64 = 30_000;
+/// stderr kept for `exec_exit` errors
+pub const EXEC_SOURCE_STDERR_MAX_BYTES: usize = 4096;
+/// characters a shell would interpret, `command` is a single executable and arguments go to `args`
+pub const EXEC_SOURCE_COMMAND_FORBIDDEN_CHARS: &[char] = &[' ', '\t', '\n', ';', '|', '&', '$', '`', '<', '>', '(', ')', '*', '?', '"', '\''];
+
+#[derive(Debug, Clone)]
+pub struct ExecSource {
+    pub source_id: String,
+    pub config: Arc<SourceConfig>,
+}
+
+impl FetchTokens for ExecSource {
+    async fn fetch_tokens(&self, _client: &Client, safety_margin_seconds_settings: Option<u64>) -> Result<Vec<TokenContext>, Error> {
+        let exec_cfg = self.config.exec.as_ref().ok_or_else(|| {
+            anyhow!("source '{}': exec block is required for type=exec", self.source_id)
+        })?;
+        let stdout = run_command(exec_cfg, get_max_response_bytes(&self.config.request)).await?;
+        parser::parse_tokens(HeaderMap::new(), stdout, self.config.parse.to_owned(), safety_margin_seconds_settings, self.config.safety_margin_seconds).await
+    }
+}
+
+/// Run command with resolved env values, stdout of a successful run is returned
+async fn run_command(exec_cfg: &ExecSourceConfig, max_output_bytes: usize) -> Result<String> {
+    let mut command = Command::new(&exec_cfg.command);
+    command
+        .args(&exec_cfg.args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    for (name, value) in exec_cfg.env.iter().flatten() {
+        command.env(name, prepare_generic_source_value(value).await?);
+    }
+    let mut child = command
+        .spawn()
+        .map_err(|e| anyhow!("failed to start '{}': {}", exec_cfg.command, e))?;
+    let (Some(stdout), Some(stderr)) = (child.stdout.take(), child.stderr.take()) else {
+        return Err(anyhow!("failed to capture output of '{}'", exec_cfg.command));
+    };
+
+    // child is killed on drop when the timeout elapses or output is too large
+    let timeout = Duration::from_millis(exec_cfg.timeout_ms.unwrap_or(EXEC_SOURCE_TIMEOUT_MS_DEFAULT));
+    let run = async {
+        let (stdout, stderr) = tokio::try_join!(read_stdout(stdout, max_output_bytes), read_stderr(stderr))?;
+        let status = child.wait().await.map_err(|e| anyhow!("failed to run '{}': {}", exec_cfg.command, e))?;
+        Ok::<_, Error>((status, stdout, stderr))
+    };
+    let (status, stdout, stderr) = tokio::time::timeout(timeout, run)
+        .await
+        .map_err(|_| FetchError::Timeout { timeout })??;
+    if !status.success() {
+        let stderr = String::from_utf8_lossy(&stderr).trim().to_owned();
+        return Err(FetchError::ExecExit { code: status.code(), stderr }.into());
+    }
+    debug!("'{}' printed {} bytes", exec_cfg.command, stdout.len());
+    Ok(String::from_utf8_lossy(&stdout).into_owned())
+}
+
+/// Stdout up to `max_output_bytes`, reading stops as soon as the command prints more
+async fn read_stdout(stdout: ChildStdout, max_output_bytes: usize) -> Result<Vec<u8>> {
+    let mut output = Vec::new();
+    stdout
+        .take(max_output_bytes as u64 + 1)
+        .read_to_end(&mut output)
+        .await
+        .map_err(|e| anyhow!("failed to read stdout: {}", e))?;
+    if output.len() > max_output_bytes {
+        return Err(FetchError::ResponseTooLarge { limit: max_output_bytes }.into());
+    }
+    Ok(output)
+}
+
+/// First `EXEC_SOURCE_STDERR_MAX_BYTES` of stderr for the error message, the rest is drained so the command does not block
+async fn read_stderr(mut stderr: ChildStderr) -> Result<Vec<u8>> {
+    let mut output = Vec::new();
+    (&mut stderr)
+        .take(EXEC_SOURCE_STDERR_MAX_BYTES as u64)
+        .read_to_end(&mut output)
+        .await
+        .and(tokio::io::copy(&mut stderr, &mut tokio::io::sink()).await)
+        .map_err(|e| anyhow!("failed to read stderr: {}", e))?;
+    Ok(output)
+}
+
+/// Request block describing the command, used by validation, metrics and sinks;
+/// `max_response_bytes` of the configured request limits stdout
+pub fn get_exec_source_request(exec_cfg: &ExecSourceConfig, request: &RequestConfig) -> RequestConfig {
+    RequestConfig {
+        url: format!("exec://{}", exec_cfg.command),
+        method: Method::GET,
+        max_response_bytes: request.max_response_bytes,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::Path;
+
+    use chrono::Utc;
+
+    use super::*;
+    use crate::config::sources::{GenericSourceValue, ParseConfig};
+
+    /// Run the script with `/bin/sh`, executing a freshly written file may fail with `ETXTBSY`
+    fn make_source(source_id: &str, script: &Path, timeout_ms: Option<u64>, env: Option<HashMap<String, GenericSourceValue>>) -> ExecSource {
+        let exec = ExecSourceConfig {
+            command: "/bin/sh".to_string(),
+            args: vec![script.to_string_lossy().into_owned()],
+            env,
+            timeout_ms,
+        };
+        let parse: ParseConfig = serde_yaml::from_str(
+            r#"
+tokens:
+  - id: access_token
+    parent: body
+    pointer: /access_token
+    token_type: plain_text
+    expiration: { source: json_body_field, pointer: /expires_in, format: seconds }
+"#,
+        )
+        .unwrap();
+        ExecSource {
+            source_id: source_id.to_owned(),
+            config: Arc::new(SourceConfig {
+                source_type: crate::config::sources::SourceTypes::EXEC,
+                request: get_exec_source_request(&exec, &RequestConfig::default()),
+                parse,
+                safety_margin_seconds: Some(10),
+                exec: Some(exec),
+                ..Default::default()
+            }),
+        }
+    }
+
+    fn write_script(dir: &Path, body: &str) -> std::path::PathBuf {
+        let path = dir.join("helper.sh");
+        std::fs::write(&path, body).unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn exec_stdout_is_parsed_as_response_body() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = write_script(dir.path(), "printf '{\"access_token\":\"%s\",\"expires_in\":3600}' \"$HELPER_SCOPE-token\"\n");
+        let env = HashMap::from([("HELPER_SCOPE".to_string(), GenericSourceValue::Literal { value: "read".to_string() })]);
+
+        let source = make_source("exec_ok", &script, None, Some(env));
+        let now = Utc::now().timestamp() as u64;
+        let tokens = source.fetch_tokens(&Client::new(), None).await.unwrap();
+        assert_eq!(tokens[0].id, "access_token");
+        assert_eq!(tokens[0].token.value, "read-token");
+        assert!((now + 3600..=now + 3601).contains(&tokens[0].token.exp_unix_ts));
+    }
+
+    #[tokio::test]
+    async fn exec_non_zero_exit_is_retryable_exec_exit() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = write_script(dir.path(), "echo 'not logged in' >&2\nexit 3\n");
+
+        let err = make_source("exec_exit", &script, None, None).fetch_tokens(&Client::new(), None).await.unwrap_err();
+        assert_eq!(FetchError::reason(&err), "exec_exit");
+        assert_eq!(err.to_string(), "command exited with code 3: not logged in");
+        assert!(err.downcast_ref::<FetchError>().unwrap().is_retryable());
+    }
+
+    #[tokio::test]
+    async fn exec_timeout_kills_command() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = write_script(dir.path(), "sleep 5\necho '{}'\n");
+
+        let start = std::time::Instant::now();
+        let err = make_source("exec_timeout", &script, Some(100), None).fetch_tokens(&Client::new(), None).await.unwrap_err();
+        assert_eq!(FetchError::reason(&err), "timeout");
+        assert!(err.downcast_ref::<FetchError>().unwrap().is_retryable());
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn exec_endless_stdout_is_cut_at_limit_and_killed() {
+        let dir = tempfile::tempdir().unwrap();
+        let script = write_script(dir.path(), "yes '{}'\n");
+        let mut source = make_source("exec_too_large", &script, Some(5_000), None);
+        let mut config = (*source.config).clone();
+        config.request.max_response_bytes = Some(1024);
+        source.config = Arc::new(config);
+
+        let start = std::time::Instant::now();
+        let err = source.fetch_tokens(&Client::new(), None).await.unwrap_err();
+        assert_eq!(FetchError::reason(&err), "response_too_large");
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+}
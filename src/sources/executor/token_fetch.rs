@@ -1,146 +1,540 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 
 use crate::cache::token_cache::TokenCache;
 use crate::cache::token_context::TokenContext;
-use crate::config::settings::RetryConfig;
-use crate::config::sinks::SinkMessage;
-use crate::config::sources::SourceConfig;
-use crate::helpers::time::{get_instant, now_i64};
-use crate::observability::metrics::get_metrics;
+use crate::cache::token_event::{TokenEvent, TokenEventKind};
+use crate::config::settings::SettingsConfig;
+use crate::config::sources::{SourceConfig, SourceTypes, MIN_REFRESH_INTERVAL_SECONDS_DEFAULT};
+use crate::helpers::time::{get_instant, get_safety_margin_from_context, get_token_prefetch_margin_seconds, now_i64};
+use crate::observability::audit::{self, AuditEvent, AuditEventType};
+use crate::observability::metrics::{get_metrics, Metrics};
+use crate::resilience::backoff::SourceBackoff;
+use crate::resilience::fallback::StaleFallback;
+use crate::resilience::circuit_breaker::{CircuitBreaker, CircuitBreakerSettings};
 use crate::resilience::retry::RetrySettings;
-use crate::sources::builder_in_order::SourceDag;
+use crate::resilience::timeout::TimeoutSettings;
+use crate::sources::builder_in_order::{DagNode, SourceDag};
+use crate::sources::error::FetchError;
 use crate::sources::fetch::{FetchTokens, Source};
+use crate::sources::aws_sts::AwsStsWebIdentitySource;
+use crate::sources::exec::ExecSource;
+use crate::sources::file::{watch_file_sources, FileSource};
+use crate::sources::gcp_workload_identity::GcpWorkloadIdentitySource;
+use crate::sources::kube_service_account::{watch_kube_service_account_files, KubeServiceAccountSource};
+use crate::sources::tls::SourceClient;
+use crate::sources::vault::VaultSource;
 
-use anyhow::{Result};
+use anyhow::{anyhow, Result};
+use dashmap::DashMap;
 use chrono::{DateTime, Utc};
 use reqwest::Client;
-use tokio::sync::broadcast::Sender;
-use tracing::{debug, info};
+use tokio::select;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+use tokio::sync::{Notify, OnceCell, Semaphore};
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn, Instrument, Span};
 
-static  ERROR_MSG: &'static str =  "error";
-static  HTTP_MSG: &'static str =  "http";
+
+// Wakes refresh loop on demand
+static REFRESH_NOTIFY: Notify = Notify::const_new();
+
+// Declare the static OnceCell to hold Retry-After deadlines (unix ts) per source_id.
+static RETRY_AFTER_UNTIL_INSTANCE: OnceCell<DashMap<String, i64>> = OnceCell::const_new();
+
+/// Asynchronously initializes and gets a reference to the static Retry-After deadlines map.
+async fn get_retry_after_until() -> &'static DashMap<String, i64> {
+    RETRY_AFTER_UNTIL_INSTANCE.get_or_init(|| async { DashMap::new() }).await
+}
+static INITIAL_CYCLE_MSG: &str = "initial";
+static REFRESH_CYCLE_MSG: &str = "refresh";
+static SUCCESS_RESULT_MSG: &str = "success";
+static FAILURE_RESULT_MSG: &str = "failure";
+
+/// Shared state for one refresh cycle, cloned into every spawned node task
+#[derive(Clone)]
+pub(crate) struct RefreshContext {
+    pub client: Client,
+    pub retry: RetrySettings,
+    pub circuit_breaker: CircuitBreakerSettings,
+    pub timeouts: TimeoutSettings,
+    pub safety_margin_seconds_settings: Option<u64>,
+    pub prefetch_margin_seconds_settings: Option<u64>,
+    /// caps concurrent source fetches within a layer, `settings.max_concurrent_fetches`
+    pub fetch_permits: Option<Arc<Semaphore>>,
+}
+
+/// Refresh loop settings resolved from `settings`, unset values fall back to defaults
+#[derive(Debug, Clone, Default)]
+pub struct RefreshSettings {
+    pub retry: RetrySettings,
+    pub circuit_breaker: CircuitBreakerSettings,
+    pub timeouts: TimeoutSettings,
+    pub safety_margin_seconds: Option<u64>,
+    pub prefetch_margin_seconds: Option<u64>,
+    /// caps concurrent source fetches within a layer, unlimited if not set
+    pub max_concurrent_fetches: Option<usize>,
+}
+
+impl RefreshSettings {
+    pub fn from_config(settings: &SettingsConfig) -> Self {
+        Self {
+            retry: RetrySettings::from_config(&settings.retry),
+            circuit_breaker: CircuitBreakerSettings::from_config(&settings.circuit_breaker),
+            timeouts: TimeoutSettings::from_config(&settings.timeouts),
+            safety_margin_seconds: settings.safety_margin_seconds,
+            prefetch_margin_seconds: settings.prefetch_margin_seconds,
+            max_concurrent_fetches: settings.max_concurrent_fetches,
+        }
+    }
+}
+
+/// Result of fetching one source once, `error` is set if the source failed after retries
+#[derive(Debug, Clone)]
+pub struct SourceFetchOutcome {
+    pub source_id: String,
+    pub error: Option<String>,
+}
 
 impl SourceDag {
     /// Execute all sources in DAG order, respecting dependencies and retry policies.
-    pub async fn loop_refrech_tokens(
-        &self,
-        client: &Client,
-        retry: &Option<RetryConfig>,
-        safety_margin_seconds_settings: Option<u64>,
-        tx: Sender<SinkMessage>,
-    ) -> Result<()> {
-        // prepare retry policies
-        let retry = RetrySettings {
-            attempts: retry.as_ref().and_then(|r| r.attempts).unwrap_or(3),
-            base_delay_ms: retry.as_ref().and_then(|r| r.base_delay_ms).unwrap_or(200),
-            max_delay_ms: retry.as_ref().and_then(|r| r.max_delay_ms).unwrap_or(1000),
+    pub async fn loop_refrech_tokens(&self, client: &Client, settings: &RefreshSettings, shutdown: CancellationToken) -> Result<()> {
+        // dependency layers are computed once, nodes inside a layer are fetched concurrently
+        let layers: Vec<Vec<DagNode>> = self
+            .layers()
+            .into_iter()
+            .map(|layer| layer.into_iter().cloned().collect())
+            .collect();
+        let refresh_context = RefreshContext {
+            client: client.clone(),
+            retry: settings.retry.clone(),
+            circuit_breaker: settings.circuit_breaker.clone(),
+            timeouts: settings.timeouts,
+            safety_margin_seconds_settings: settings.safety_margin_seconds,
+            prefetch_margin_seconds_settings: settings.prefetch_margin_seconds,
+            fetch_permits: settings.max_concurrent_fetches.map(|permits| Arc::new(Semaphore::new(permits))),
         };
-
-        let sources_ordered = self.ordered.clone();
-        let client = client.clone();
-        let retry = retry.clone();
-        let _ = tokio::spawn(async move {
+        // projected token files are watched for rotation next to the refresh loop
+        let kube_sa_files: Vec<(String, String)> = layers
+            .iter()
+            .flatten()
+            .filter_map(|node| node.config.kube_service_account.as_ref().map(|kube| (node.id.clone(), kube.path.clone())))
+            .collect();
+        tokio::spawn(watch_kube_service_account_files(kube_sa_files, shutdown.clone()));
+        // token files of file sources are watched for modification the same way
+        let token_files: Vec<(String, String)> = layers
+            .iter()
+            .flatten()
+            .filter_map(|node| node.config.file.as_ref().map(|file| (node.id.clone(), file.path.clone())))
+            .collect();
+        tokio::spawn(watch_file_sources(token_files, shutdown.clone()));
+        let mut token_events = TokenCache::subscribe_all().await;
+        tokio::spawn(async move {
             loop {
-                let mut sleep_until = i64::MAX;
-                info!("refetch token cycle start");
-                for node in sources_ordered.iter() {
-                    let source_id = node.id.as_str();
-
-                    info!("fetching source '{}', deps: '{:?}'", source_id, node.deps);
-
-                    // define should fetch
-                    let mut should_fetch: bool = false;
-                    for source_token in &node.config.parse.tokens {
-                        let token_context_opt = 
-                        TokenCache::get(source_id, &source_token.id).await;
-
-                        if token_context_opt
-                            .map(|token_context|{
-                                if (token_context.fetched_at_unix_ts as i64) < sleep_until {
-                                    sleep_until = token_context.fetched_at_unix_ts as i64;
-                                }
-                                token_context
-                            })
-                            .filter(|token_config| !token_config.should_update())
-                            .is_none()
-                        {
-                            info!("fetching source '{}' now", source_id);
-                            sleep_until = now_i64();
-                            should_fetch = true;
-                            break;
-                        }
-                    }
-                    if !should_fetch {
-                        continue;
+                select! {
+                    _ = shutdown.cancelled() => {
+                        info!("refetch token loop stopped");
+                        break;
                     }
+                    _ = async {
+                        info!("refetch token cycle start");
+                        let sleep_until = SourceDag::refresh_layers(&layers, &refresh_context).await;
+                        debug!("sleep until {}", sleep_until);
+                        sleep_until_next_token_fetch_check(sleep_until, &mut token_events).await;
+                    } => {}
+                }
+            }
+        })
+        .await?;
+        Ok(())
+    }
 
-                    // fetch tokens for source
+    /// Fetch every source exactly once in dependency order and store its tokens, without refresh loop;
+    /// all sources are tried, error lists sources failed after retries
+    pub async fn fetch_tokens_once(&self, client: &Client, settings: &RefreshSettings) -> Result<()> {
+        let outcomes = self.fetch_sources_once(client, settings).await?;
+        let failed: Vec<&str> = outcomes
+            .iter()
+            .filter(|outcome| outcome.error.is_some())
+            .map(|outcome| outcome.source_id.as_str())
+            .collect();
+        if !failed.is_empty() {
+            return Err(anyhow!("sources failed: {}", failed.join(", ")));
+        }
+        Ok(())
+    }
 
-                    if let Ok(token_contexts) = SourceDag::fetch_tokens_by_source_id(source_id,node.config.clone(),safety_margin_seconds_settings,&client,&retry).await {
-                        info!("fetched total tokens {} for source_id {}",token_contexts.len(),source_id);
+    /// Same as `fetch_tokens_once`, returns outcome of every source in dependency order
+    pub async fn fetch_sources_once(&self, client: &Client, settings: &RefreshSettings) -> Result<Vec<SourceFetchOutcome>> {
+        let mut outcomes: Vec<SourceFetchOutcome> = Vec::new();
+        for node in self.layers().into_iter().flatten() {
+            let source_id = node.id.as_str();
+            let timeouts = settings.timeouts.with_override(&node.config.timeouts);
+            let retry = settings.retry.with_override(&node.config.retry).with_attempt_timeout(timeouts.read);
+            let circuit_breaker = settings.circuit_breaker.with_override(&node.config.circuit_breaker);
+            let prefetch_margin = get_token_prefetch_margin_seconds(settings.prefetch_margin_seconds, node.config.prefetch_margin_seconds);
+            let fetched = SourceDag::fetch_tokens_by_source_id(
+                node,
+                settings.safety_margin_seconds,
+                prefetch_margin,
+                client,
+                &retry,
+                &circuit_breaker,
+                &timeouts,
+            )
+            .await;
+            let error = match fetched {
+                Ok(token_contexts) => {
+                    let stored_tokens = SourceDag::store_tokens_by_source_id(source_id, token_contexts, node.config.rotation_overlap_seconds).await?;
+                    info!("oneshot: stored total tokens {} for source_id {}", stored_tokens.len(), source_id);
+                    None
+                }
+                Err(err) => {
+                    error!("oneshot: fetching source '{}' failed: {}", source_id, err);
+                    Some(err.to_string())
+                }
+            };
+            outcomes.push(SourceFetchOutcome { source_id: source_id.to_owned(), error });
+        }
+        Ok(outcomes)
+    }
 
-                        let stored_tokens = match SourceDag::store_tokens_by_source_id(source_id, token_contexts).await {
-                            Ok(v) => v,
-                            Err(err) => {
-                                info!("storing tokens for source_id {} failed, {}", source_id, err);
-                            Vec::with_capacity(0)
-                            },
+    /// Run one refresh cycle layer by layer, returns unix ts of the next check
+    #[tracing::instrument(name = "refresh.cycle", skip_all, fields(sources = layers.iter().map(Vec::len).sum::<usize>()))]
+    pub(crate) async fn refresh_layers(layers: &[Vec<DagNode>], refresh_context: &RefreshContext) -> i64 {
+        let mut sleep_until = i64::MAX;
+        // inputs of `refresh_on_input_change` sources with their tokens before the cycle
+        let mut input_tokens: HashMap<&str, Vec<(String, String, u64)>> = HashMap::new();
+        for node in layers.iter().flatten().filter(|node| node.config.refresh_on_input_change == Some(true)) {
+            for dep in &node.deps {
+                input_tokens.insert(dep.as_str(), get_token_versions(dep).await);
+            }
+        }
+        for layer in layers {
+            let mut join_set = JoinSet::new();
+            for node in layer {
+                let fetch_permits = refresh_context.fetch_permits.clone();
+                let refresh = SourceDag::refresh_node(node.clone(), refresh_context.clone());
+                // node spans are children of the cycle span
+                join_set.spawn(
+                    async move {
+                        let _permit = match fetch_permits {
+                            Some(fetch_permits) => fetch_permits.acquire_owned().await.ok(),
+                            None => None,
                         };
-                        info!("stored total tokens {} for source_id {}",stored_tokens.len(),source_id);
-                    };
-
-                    // sink active propogation
-                    let _ = tx.send(SinkMessage(source_id.to_owned()))
-                    .map_err(|err|{
-                        debug!("message for source_id {} was sent {}", source_id, err);
-                    });
+                        refresh.await
+                    }
+                    .instrument(Span::current()),
+                );
+            }
+            // next layer starts only when all nodes of current layer are done
+            while let Some(res) = join_set.join_next().await {
+                match res {
+                    Ok(node_sleep_until) => sleep_until = sleep_until.min(node_sleep_until),
+                    Err(err) => {
+                        info!("refresh task failed, {}", err);
+                        sleep_until = now_i64();
+                    }
                 }
-                debug!("sleep until {}", sleep_until);
-                sleep_until_next_token_fetch_check(sleep_until).await;
             }
-        });
-        Ok(())
+            // dependents of inputs rotated in this layer are fetched again later in this cycle
+            for node in layer {
+                let Some(tokens_before) = input_tokens.get(node.id.as_str()) else { continue };
+                if &get_token_versions(&node.id).await == tokens_before {
+                    continue;
+                }
+                let dependents = layers
+                    .iter()
+                    .flatten()
+                    .filter(|dependent| dependent.config.refresh_on_input_change == Some(true) && dependent.deps.contains(&node.id));
+                for dependent in dependents {
+                    if TokenCache::force_refresh_by_source_id(&dependent.id).await {
+                        info!("source '{}' input '{}' tokens changed, fetching source again", dependent.id, node.id);
+                    }
+                }
+            }
+        }
+        sleep_until
     }
 
+    /// Fetch source tokens if any of them is absent or should be updated,
+    /// returns unix ts of the next check for this source
+    async fn refresh_node(node: DagNode, refresh_context: RefreshContext) -> i64 {
+        let source_id = node.id.as_str();
+        let mut sleep_until = i64::MAX;
+
+        info!("fetching source '{}', deps: '{:?}'", source_id, node.deps);
+        if let Some(not_before) = get_retry_after_until().await.get(source_id).map(|v| *v) {
+            if now_i64() < not_before {
+                info!("source '{}' is backing off until {} (Retry-After)", source_id, not_before);
+                return not_before;
+            }
+            get_retry_after_until().await.remove(source_id);
+        }
+        if let Some(next_attempt_at) = SourceBackoff::next_attempt_at(source_id).await {
+            info!("source '{}' is backing off until {} (failed fetch)", source_id, next_attempt_at);
+            return next_attempt_at;
+        }
+        let prefetch_margin = get_token_prefetch_margin_seconds(
+            refresh_context.prefetch_margin_seconds_settings,
+            node.config.prefetch_margin_seconds,
+        );
+
+        // define should fetch
+        let mut should_fetch: bool = false;
+        let mut should_prefetch: bool = false;
+        for source_token in &node.config.parse.tokens {
+            let token_context_opt = 
+            TokenCache::get(source_id, &source_token.id).await;
+
+            if token_context_opt
+                .map(|token_context|{
+                    let should_prefetch_at = token_context.should_prefetch_at(prefetch_margin) as i64;
+                    if should_prefetch_at < sleep_until {
+                        sleep_until = should_prefetch_at;
+                    }
+                    if token_context.should_prefetch(prefetch_margin) {
+                        should_prefetch = true;
+                    }
+                    token_context
+                })
+                .filter(|token_config| token_config.is_valid_for(Duration::ZERO))
+                .is_none()
+            {
+                info!("fetching source '{}' now", source_id);
+                sleep_until = now_i64();
+                should_fetch = true;
+                should_prefetch = false;
+                break;
+            }
+        }
+        if should_prefetch {
+            // current tokens stay in cache until new ones are parsed and stored
+            info!("pre-fetching source '{}' now", source_id);
+            get_metrics().await.source_prefetch_requests.with_label_values(&[source_id]).inc();
+            sleep_until = now_i64();
+            should_fetch = true;
+        }
+        if !should_fetch {
+            return sleep_until;
+        }
+
+        // fetch tokens for source, source level retry, circuit breaker and timeouts override settings level
+        let timeouts = refresh_context.timeouts.with_override(&node.config.timeouts);
+        let retry = refresh_context.retry.with_override(&node.config.retry).with_attempt_timeout(timeouts.read);
+        let circuit_breaker = refresh_context.circuit_breaker.with_override(&node.config.circuit_breaker);
+
+        // open circuit: skip source until cooldown elapses, then half-open probe
+        if let Some(remaining) = CircuitBreaker::get_by_source_id(source_id, &circuit_breaker).await.open_remaining() {
+            let probe_at = now_i64() + remaining.as_secs_f64().ceil() as i64;
+            info!("source '{}' skipped, circuit open until {}", source_id, probe_at);
+            return probe_at;
+        }
+
+        match SourceDag::fetch_tokens_by_source_id(&node,refresh_context.safety_margin_seconds_settings,prefetch_margin,&refresh_context.client,&retry,&circuit_breaker,&timeouts).await {
+            Ok(token_contexts) => {
+                info!("fetched total tokens {} for source_id {}",token_contexts.len(),source_id);
+                SourceBackoff::record_success(source_id).await;
+                StaleFallback::record_success(source_id).await;
+                // next check when the earliest fetched token enters its (pre-fetch) refresh window
+                sleep_until = get_next_refresh_at(&token_contexts, prefetch_margin);
+                if sleep_until <= now_i64() {
+                    warn!("source '{}': fetched token lifetime is within safety margin, it is refreshed on every check", source_id);
+                }
+
+                let stored_tokens = match SourceDag::store_tokens_by_source_id(source_id, token_contexts, node.config.rotation_overlap_seconds).await {
+                    Ok(v) => v,
+                    Err(err) => {
+                        info!("storing tokens for source_id {} failed, {}", source_id, err);
+                    Vec::with_capacity(0)
+                    },
+                };
+                info!("stored total tokens {} for source_id {}",stored_tokens.len(),source_id);
+            },
+            Err(err) => {
+                // expired tokens of the source may be kept as stale until the next successful fetch
+                StaleFallback::record_failure(source_id).await;
+                // server asked to back off: next fetch of this source not earlier than Retry-After
+                if let Some(retry_after) = FetchError::retry_after(&err) {
+                    let not_before = now_i64() + retry_after.as_secs_f64().ceil() as i64;
+                    info!("source '{}' next fetch not before {} (Retry-After)", source_id, not_before);
+                    get_retry_after_until().await.insert(source_id.to_owned(), not_before);
+                    sleep_until = not_before;
+                }
+                // negative result: do not hammer a failing source on every check
+                let min_refresh_interval = node.config.min_refresh_interval_seconds.unwrap_or(MIN_REFRESH_INTERVAL_SECONDS_DEFAULT);
+                if let Some(backoff) = SourceBackoff::record_failure(source_id, min_refresh_interval).await {
+                    sleep_until = sleep_until.max(backoff.next_attempt_at);
+                }
+            },
+        }
+
+        // sinks are notified by token cache events of changed tokens
+        sleep_until
+    }
+
+    #[tracing::instrument(
+        name = "token.fetch",
+        skip_all,
+        fields(
+            source.id = %node.id,
+            source.type = node.config.source_type.as_str(),
+            http.method = tracing::field::Empty,
+            attempt = tracing::field::Empty,
+            status = tracing::field::Empty
+        )
+    )]
     async fn fetch_tokens_by_source_id(
-        source_id: &str,
-        config: Arc<SourceConfig>,
+        node: &DagNode,
         safety_margin_seconds_settings: Option<u64>,
+        prefetch_margin_seconds: u64,
         client: &Client,
         retry: &RetrySettings,
+        circuit_breaker: &CircuitBreakerSettings,
+        timeouts: &TimeoutSettings,
     ) -> Result<Vec<TokenContext>> {
+        let (source_id, config) = (node.id.as_str(), &node.config);
         let metrics = get_metrics().await;
         let start = get_instant();
-        metrics.source_fetch_requests.with_label_values(&[&source_id, &HTTP_MSG, &&config.request.method.as_str()]).inc();
-        retry
-            .run_with_retry(|| {
-                let source = Source(config.clone());
+        // method label stays empty for sources that do not send the `request` block
+        let method = if config.source_type.is_http() { config.request.method.as_str() } else { "" };
+        if config.source_type.is_http() {
+            Span::current().record("http.method", method);
+        }
+        metrics.source_fetch_requests.with_label_values(&[source_id, config.source_type.as_str(), method]).inc();
+        // tokens already cached before the fetch are refreshed, others are fetched initially
+        let cached_token_exps: HashMap<String, u64> = TokenCache::get_all_by_source_id(source_id)
+            .await
+            .into_iter()
+            .map(|token_context| (token_context.id, token_context.token.exp_unix_ts))
+            .collect();
+        let client = &SourceClient::get_by_source_id(source_id, config, client, timeouts)
+            .await
+            .inspect_err(|err| {
+                Span::current().record("status", FetchError::reason(err));
+                record_refresh_failure(metrics, source_id, config);
+                metrics.source_fetch_failures.with_label_values(&[source_id, FetchError::reason(err)]).inc();
+                audit::emit(AuditEvent::new(AuditEventType::FetchFailure, source_id).reason(FetchError::reason(err)));
+            })?;
+        let mut attempts: u32 = 0;
+        let fetched = CircuitBreaker::get_by_source_id(source_id, circuit_breaker)
+            .await
+            .run_with_retry(source_id, retry, || {
+                attempts += 1;
+                let config = config.clone();
                 async move {
-                    source
-                        .fetch_tokens(client, safety_margin_seconds_settings)
-                        .await
+                    match config.source_type {
+                        SourceTypes::VAULT => VaultSource { source_id: source_id.to_owned(), config }
+                            .fetch_tokens(client, safety_margin_seconds_settings)
+                            .await,
+                        SourceTypes::KubeServiceAccount => KubeServiceAccountSource { source_id: source_id.to_owned(), config }
+                            .fetch_tokens(client, safety_margin_seconds_settings)
+                            .await,
+                        SourceTypes::FILE => FileSource { source_id: source_id.to_owned(), config }
+                            .fetch_tokens(client, safety_margin_seconds_settings)
+                            .await,
+                        SourceTypes::EXEC => ExecSource { source_id: source_id.to_owned(), config }
+                            .fetch_tokens(client, safety_margin_seconds_settings)
+                            .await,
+                        SourceTypes::GcpWorkloadIdentity => GcpWorkloadIdentitySource { source_id: source_id.to_owned(), config }
+                            .fetch_tokens(client, safety_margin_seconds_settings)
+                            .await,
+                        SourceTypes::AwsStsWebIdentity => AwsStsWebIdentitySource { source_id: source_id.to_owned(), config }
+                            .fetch_tokens(client, safety_margin_seconds_settings)
+                            .await,
+                        _ => Source(config)
+                            .fetch_tokens_conditional(source_id, client, safety_margin_seconds_settings, prefetch_margin_seconds)
+                            .await,
+                    }
                 }
             })
-            .await
-            .map(|source_token_contexts| {
-                metrics.source_fetch_duration.with_label_values(&[source_id]).observe(start.elapsed().as_secs_f64());
+            .await;
+        let span = Span::current();
+        span.record("attempt", attempts);
+        span.record("status", fetched.as_ref().map_or_else(FetchError::reason, |_| SUCCESS_RESULT_MSG));
+        fetched
+            .map(|mut source_token_contexts| {
+                let elapsed = start.elapsed().as_secs_f64();
+                metrics.source_fetch_duration.with_label_values(&[source_id]).observe(elapsed);
+                let now = now_i64();
+                for token_context in &source_token_contexts {
+                    let token_id = token_context.id.as_str();
+                    let cached_exp = cached_token_exps.get(token_id);
+                    let cycle = if cached_exp.is_some() { REFRESH_CYCLE_MSG } else { INITIAL_CYCLE_MSG };
+                    metrics.token_refresh_latency.with_label_values(&[source_id, token_id, cycle]).observe(elapsed);
+                    metrics.token_refresh.with_label_values(&[source_id, token_id, SUCCESS_RESULT_MSG]).inc();
+                    // unchanged expiration, e.g. cached tokens on 304, is not a newly issued token
+                    if cached_exp != Some(&token_context.token.exp_unix_ts) {
+                        let issued_ttl = (token_context.token.exp_unix_ts as i64 - now).max(0);
+                        metrics.token_issued_ttl.with_label_values(&[source_id, token_id]).observe(issued_ttl as f64);
+                    }
+                }
+                if config.safety_margin_percent.is_some() {
+                    source_token_contexts = source_token_contexts
+                        .into_iter()
+                        .map(|token_context| {
+                            let safety_margin = get_safety_margin_from_context(&token_context, safety_margin_seconds_settings, config);
+                            token_context.with_safety_margin(safety_margin)
+                        })
+                        .collect();
+                }
                 info!(
                     "source '{}' successfully fetched tokens, total tokens received: {:?}",
                     source_id,
                     source_token_contexts.len()
                 );
+                for token_context in &source_token_contexts {
+                    audit::emit(
+                        AuditEvent::new(AuditEventType::FetchSuccess, source_id)
+                            .token(&token_context.id, Some(token_context.token.exp_unix_ts)),
+                    );
+                }
                 source_token_contexts
             })
             .map_err(|e| {
+                record_refresh_failure(metrics, source_id, config);
                 metrics.source_fetch_duration.with_label_values(&[source_id]).observe(start.elapsed().as_secs_f64());
-                metrics.source_fetch_failures.with_label_values(&[source_id, ERROR_MSG]).inc();
+                metrics.source_fetch_failures.with_label_values(&[source_id, FetchError::reason(&e)]).inc();
+                audit::emit(AuditEvent::new(AuditEventType::FetchFailure, source_id).reason(FetchError::reason(&e)));
                 e
             })
     }
 }
 
-async fn sleep_until_next_token_fetch_check(sleep_until: i64) {
+/// Cached tokens of source as `(id, value, exp)`, sorted by id
+async fn get_token_versions(source_id: &str) -> Vec<(String, String, u64)> {
+    TokenCache::get_all_by_source_id(source_id)
+        .await
+        .into_iter()
+        .map(|token_context| (token_context.id, token_context.token.value, token_context.token.exp_unix_ts))
+        .collect()
+}
+
+/// Failed fetch is a failed refresh of every configured token of the source
+fn record_refresh_failure(metrics: &Metrics, source_id: &str, config: &SourceConfig) {
+    for token_field in &config.parse.tokens {
+        metrics.token_refresh.with_label_values(&[source_id, token_field.id.as_str(), FAILURE_RESULT_MSG]).inc();
+    }
+}
+
+/// Earliest unix ts any of the tokens should be (pre-)fetched again, now if there are no tokens
+pub(crate) fn get_next_refresh_at(token_contexts: &[TokenContext], prefetch_margin_seconds: u64) -> i64 {
+    token_contexts
+        .iter()
+        .map(|token_context| token_context.should_prefetch_at(prefetch_margin_seconds) as i64)
+        .min()
+        .unwrap_or_else(now_i64)
+}
+
+/// Wake refresh loop before its scheduled check, e.g. after forced invalidation
+pub fn request_refresh() {
+    REFRESH_NOTIFY.notify_one();
+}
+
+async fn sleep_until_next_token_fetch_check(sleep_until: i64, token_events: &mut Receiver<TokenEvent>) {
     info!(
         "now: {}",
         DateTime::from_timestamp_secs(Utc::now().timestamp() as i64).unwrap()
@@ -153,7 +547,26 @@ async fn sleep_until_next_token_fetch_check(sleep_until: i64) {
     }
 
     if sleep_interval > 0 {
-        info!("sleep interval {} seconds, next check start at {}", sleep_interval, DateTime::from_timestamp_secs(sleep_until).unwrap());
-        tokio::time::sleep(Duration::from_secs(sleep_interval as u64)).await;
+        match DateTime::from_timestamp_secs(sleep_until) {
+            Some(next_check) => info!("sleep interval {} seconds, next check start at {}", sleep_interval, next_check),
+            None => info!("no token to refresh, next check starts on request"),
+        }
+        select! {
+            _ = tokio::time::sleep(Duration::from_secs(sleep_interval as u64)) => {}
+            _ = REFRESH_NOTIFY.notified() => info!("refresh requested, next check starts now"),
+            _ = next_removed_token_event(token_events) => info!("token removed from cache, next check starts now"),
+        }
+    }
+}
+
+/// Resolves on the next token removal, e.g. expiration or forced invalidation; missed events count as removal
+async fn next_removed_token_event(token_events: &mut Receiver<TokenEvent>) {
+    loop {
+        match token_events.recv().await {
+            Ok(event) if event.kind == TokenEventKind::Removed => return,
+            Ok(_) => continue,
+            Err(RecvError::Lagged(_)) => return,
+            Err(RecvError::Closed) => std::future::pending::<()>().await,
+        }
     }
 }
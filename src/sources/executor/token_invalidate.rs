@@ -1,14 +1,14 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::time::{Duration};
 
 use crate::cache::token_cache::TokenCache;
-use crate::config::sinks::SinkMessage;
 use crate::config::sources::SourceConfig;
 use crate::helpers::time::{get_token_safety_margin_seconds, now_i64};
 use crate::sources::builder_in_order::SourceDag;
 use anyhow::Result;
 use chrono::{DateTime, Utc};
-use tokio::sync::broadcast::Sender;
+use tokio::select;
+use tokio_util::sync::CancellationToken;
 use tracing::{debug, info};
 
 impl SourceDag {
@@ -17,18 +17,28 @@ impl SourceDag {
         &self,
         sources: &HashMap<String, SourceConfig>,
         safety_margin_seconds_settings: &Option<u64>,
-        tx: Sender<SinkMessage>,
+        shutdown: CancellationToken,
     ) -> Result<()> {
         let sources_ordered = self.ordered.clone();
         let sources = sources.clone();
         let safety_margin_seconds_settings = safety_margin_seconds_settings.to_owned();
-        let _ = tokio::spawn(async move {
-            loop {
+        tokio::spawn(async move {
+            while !shutdown.is_cancelled() {
                 let mut sleep_until = i64::MAX;
                 info!("remove outdated tokens cycle start");
+                let cached_source_ids: HashSet<String> = TokenCache::list_source_ids().await.into_iter().collect();
                 for node in sources_ordered.iter() {
                     let source_id = node.id.as_str();
 
+                    // nothing to invalidate, check again after safety margin
+                    if !cached_source_ids.contains(source_id) {
+                        let safety_margin_source = sources.get(source_id).and_then(|source_config| source_config.safety_margin_seconds);
+                        sleep_until = now_i64()
+                            + get_token_safety_margin_seconds(safety_margin_seconds_settings, safety_margin_source) as i64;
+                        info!("source '{}' has no cached tokens: sleep_until: {}", source_id, sleep_until);
+                        continue;
+                    }
+
                     info!("check source tokens exp '{}'", source_id);
 
                     // define should fetch
@@ -65,23 +75,30 @@ impl SourceDag {
                         continue;
                     }
 
-                    let _ = match SourceDag::invalidate_tokens_by_source_id(source_id).await {
+                    // sinks are notified by `Removed` token cache events
+                    let _ = match SourceDag::invalidate_tokens_by_source_id(
+                        source_id,
+                        node.config.stale_token_ttl_seconds,
+                        node.config.rotation_overlap_seconds,
+                    )
+                    .await
+                    {
                         Ok(v) => v,
                         Err(err) => {
                             info!("removing tokens by source_id {} failed, {}", source_id, err);
                         }
                     };
-
-                    // sink active propogation
-                    let _ = tx.send(SinkMessage(source_id.to_owned())).map_err(|err| {
-                        debug!("message for source_id {} was sent {}", source_id, err);
-                    });
                 }
 
-                sleep_until_next_token_exp_check(sleep_until).await;
+                select! {
+                    _ = shutdown.cancelled() => break,
+                    _ = sleep_until_next_token_exp_check(sleep_until) => {}
+                }
                 process_metrics().await;
             }
-        });
+            info!("remove outdated tokens loop stopped");
+        })
+        .await?;
         Ok(())
     }
 }
@@ -3,16 +3,28 @@
 /// Defines all supported token sources and provides a factory to build them from config.
 
 use anyhow::{anyhow, Error, Result};
-use http::HeaderMap;
-use reqwest::Client;
+use http::{HeaderMap, Method, StatusCode};
+use reqwest::{Client, RequestBuilder, Response};
+use serde_json::Value;
 use std::collections::HashMap;
 use std::sync::Arc;
 use std::{env, fs};
+use tracing::debug;
 
+use crate::cache::etag_cache::{EtagCache, EtagEntry};
+use crate::cache::secret_cache::SecretCache;
+use crate::cache::ssm_cache::SsmCache;
 use crate::cache::token_cache::TokenCache;
 use crate::cache::token_context::TokenContext;
-use crate::config::sources::{GenericSourceValue, SourceConfig};
+use crate::config::sources::{GenericSourceValue, RequestConfig, SourceConfig, SourceTypes, OAUTH2_CLIENT_ASSERTION_TYPE_JWT, OAUTH2_GRANT_TYPE_DEFAULT};
+use crate::observability::metrics::get_metrics;
 use crate::parser::parser;
+use crate::sources::error::{body_preview, FetchError};
+use crate::sources::signed_jwt::sign_jwt;
+use crate::sources::metadata::{fetch_imdsv2_session_token, IMDSV2_SESSION_TOKEN_HEADER};
+
+/// Response body limit of sources without `max_response_bytes` and `settings.max_response_bytes`, 1 MiB
+pub const SOURCE_MAX_RESPONSE_BYTES_DEFAULT: usize = 1024 * 1024;
 
 pub trait FetchTokens {
     fn fetch_tokens(
@@ -28,40 +40,167 @@ pub struct Source(pub Arc<SourceConfig>);
 
 impl FetchTokens for Source {
     async fn fetch_tokens(&self, client: &Client, safety_margin_seconds_settings: Option<u64>) -> Result<Vec<TokenContext>, Error> {
+        let response = self.build_request(client).await?.send().await.map_err(FetchError::from)?;
+        self.parse_response(response, safety_margin_seconds_settings).await
+    }
+}
+
+impl Source {
+    /// Conditional GET: `If-None-Match`/`If-Modified-Since` are sent with validators of the last parsed response,
+    /// on `304 Not Modified` cached tokens of the source are returned without parsing.
+    /// Validators are only sent while no cached token is due for prefetch, a `304` for due tokens is a failed fetch
+    pub async fn fetch_tokens_conditional(
+        &self,
+        source_id: &str,
+        client: &Client,
+        safety_margin_seconds_settings: Option<u64>,
+        prefetch_margin_seconds: u64,
+    ) -> Result<Vec<TokenContext>, Error> {
+        if self.0.request.method != Method::GET {
+            return self.fetch_tokens(client, safety_margin_seconds_settings).await;
+        }
+        let cached_tokens = TokenCache::get_all_by_source_id(source_id).await;
+        // reusing tokens already due for prefetch would refetch them on every loop tick
+        let is_due = cached_tokens.is_empty()
+            || cached_tokens.iter().any(|token_context| token_context.should_prefetch(prefetch_margin_seconds));
+        let etag_entry = match is_due {
+            true => None,
+            false => EtagCache::get_by_source_id(source_id).await,
+        };
+
+        let mut request = self.build_request(client).await?;
+        for (name, value) in etag_entry.iter().flat_map(EtagEntry::conditional_headers) {
+            request = request.header(name, value);
+        }
+        let response = request.send().await.map_err(FetchError::from)?;
+        if etag_entry.is_some() && response.status() == StatusCode::NOT_MODIFIED {
+            debug!("source '{}': not modified, reusing {} cached tokens", source_id, cached_tokens.len());
+            get_metrics().await.source_304_responses.with_label_values(&[source_id]).inc();
+            return Ok(cached_tokens);
+        }
+
+        let etag_entry = EtagEntry::from_headers(response.headers());
+        let token_contexts = self.parse_response(response, safety_margin_seconds_settings).await?;
+        match etag_entry {
+            Some(entry) => EtagCache::set(source_id, entry).await,
+            None => EtagCache::remove(source_id).await,
+        }
+        Ok(token_contexts)
+    }
+
+    async fn build_request(&self, client: &Client) -> Result<RequestBuilder, Error> {
         let source_config = &self.0;
         let req_cfg = &source_config.request.clone();
 
         let mut request = client.request(req_cfg.method.clone(), &req_cfg.url);
 
-        // Build headers dynamically
-        if let Some(headers) = &req_cfg.headers {
-            for (key, v) in headers {
-                let value = prepare_generic_source_value(v).await?;
-                request = request.header(key, value)
-            }
-        }
-        // Build body dynamically
-        if let Some(source_body) = &req_cfg.body {
-            let mut body = HashMap::new();
-            for (k, v) in source_body {
-                let value = prepare_generic_source_value(v).await?;
-                body.insert(k.to_owned(), value);
-            }
-            request = request.json(&body);
+        // IMDSv2: obtain session token first, it is used for this request only
+        if let SourceTypes::IMDSV2 = source_config.source_type {
+            let session_token = fetch_imdsv2_session_token(client, req_cfg).await?;
+            request = request.header(IMDSV2_SESSION_TOKEN_HEADER, session_token);
         }
 
-        let response = request.send().await?;
-        if !response.status().is_success() {
-            return Err(anyhow!("HTTP request failed: {}", response.status()));
+        with_request_values(request, req_cfg).await
+    }
+
+    async fn parse_response(&self, response: Response, safety_margin_seconds_settings: Option<u64>) -> Result<Vec<TokenContext>, Error> {
+        let source_config = &self.0;
+        let expect = source_config.parse.expect.as_ref();
+        let status = response.status();
+        let status_expected = match expect.and_then(|expect| expect.expected_status.as_ref()) {
+            Some(expected_status) => expected_status.contains(&status.as_u16()),
+            None => status.is_success(),
+        };
+        // unlisted error statuses keep `Retry-After` and `retry_on_status` handling
+        if !status_expected && !status.is_success() {
+            return Err(FetchError::from_response(status, response.headers()).into());
         }
         let headers: HeaderMap = response.headers().clone();
-        let body = response.text().await?;
+        let content_type = headers
+            .get(http::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned);
+        let body = read_body_limited(response, get_max_response_bytes(&source_config.request)).await?;
+        let content_type_expected = match expect.and_then(|expect| expect.expected_content_type.as_deref()) {
+            Some(expected) => content_type
+                .as_deref()
+                .is_some_and(|content_type| content_type.to_ascii_lowercase().contains(&expected.to_ascii_lowercase())),
+            None => true,
+        };
+        if !status_expected || !content_type_expected {
+            return Err(FetchError::UnexpectedResponse { status, content_type, body_preview: body_preview(&body) }.into());
+        }
         parser::parse_tokens(headers, body, source_config.parse.to_owned(), safety_margin_seconds_settings, source_config.safety_margin_seconds).await
     }
 }
 
+/// Response body as text, read chunk by chunk and aborted as soon as it exceeds `limit` bytes
+pub(crate) async fn read_body_limited(mut response: Response, limit: usize) -> Result<String, Error> {
+    if response.content_length().is_some_and(|length| length > limit as u64) {
+        return Err(FetchError::ResponseTooLarge { limit }.into());
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(FetchError::from)? {
+        if body.len() + chunk.len() > limit {
+            return Err(FetchError::ResponseTooLarge { limit }.into());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+pub fn get_max_response_bytes(req_cfg: &RequestConfig) -> usize {
+    req_cfg.max_response_bytes.unwrap_or(SOURCE_MAX_RESPONSE_BYTES_DEFAULT)
+}
+
+/// Add configured headers, query and body or form to request, values are resolved on every call
+pub(crate) async fn with_request_values(mut request: RequestBuilder, req_cfg: &RequestConfig) -> Result<RequestBuilder, Error> {
+    // Build headers dynamically
+    if let Some(headers) = &req_cfg.headers {
+        for (key, v) in headers {
+            let value = prepare_generic_source_value(v).await?;
+            request = request.header(key, value)
+        }
+    }
+    // Build query dynamically
+    if let Some(source_query) = &req_cfg.query {
+        let mut query = Vec::with_capacity(source_query.len());
+        for (k, v) in source_query {
+            let value = prepare_generic_source_value(v).await?;
+            query.push((k.to_owned(), value));
+        }
+        request = request.query(&query);
+    }
+    // Build body dynamically
+    if let Some(source_body) = req_cfg.body.as_ref().filter(|_| req_cfg.allows_body()) {
+        let mut body = HashMap::new();
+        for (k, v) in source_body {
+            let value = prepare_generic_source_value(v).await?;
+            body.insert(k.to_owned(), value);
+        }
+        request = request.json(&body);
+    }
+    // Build OAuth2 form dynamically
+    if let Some(source_form) = req_cfg.form.as_ref().filter(|_| req_cfg.allows_body()) {
+        let mut form = vec![
+            ("grant_type", source_form.grant_type.clone().unwrap_or_else(|| OAUTH2_GRANT_TYPE_DEFAULT.to_owned())),
+            ("client_id", prepare_generic_source_value(&source_form.client_id).await?),
+            ("scope", prepare_generic_source_value(&source_form.scope).await?),
+        ];
+        if let Some(client_secret) = &source_form.client_secret {
+            form.push(("client_secret", prepare_generic_source_value(client_secret).await?));
+        }
+        if let Some(client_assertion) = &source_form.client_assertion {
+            form.push(("client_assertion_type", OAUTH2_CLIENT_ASSERTION_TYPE_JWT.to_owned()));
+            form.push(("client_assertion", prepare_generic_source_value(client_assertion).await?));
+        }
+        request = request.form(&form);
+    }
+    Ok(request)
+}
+
 
-async fn prepare_generic_source_value(value: &GenericSourceValue) -> Result<String, anyhow::Error> {
+pub(crate) async fn prepare_generic_source_value(value: &GenericSourceValue) -> Result<String, anyhow::Error> {
     match value {
     GenericSourceValue::Literal { value } => Ok(value.to_owned()),
     GenericSourceValue::FromEnv { from_env } => env::var(from_env).map_err(|err| anyhow!(err)),
@@ -71,6 +210,8 @@ async fn prepare_generic_source_value(value: &GenericSourceValue) -> Result<Stri
         .map(|res| res.trim().to_string())
         
     }
+    GenericSourceValue::FromAwsSsm { parameter_name, with_decryption } => SsmCache::get(parameter_name, *with_decryption).await,
+    GenericSourceValue::FromGcpSecret { project, secret, version } => SecretCache::get(project, secret, version.as_deref()).await,
     GenericSourceValue::Ref {
         source,
         id,
@@ -82,7 +223,10 @@ async fn prepare_generic_source_value(value: &GenericSourceValue) -> Result<Stri
                 .as_ref()
                 .map(|prefix| format!("{}{}", prefix, token_context.id))
                 .unwrap_or(token_context.token.value)
-        }).ok_or(anyhow!("token {}.{} is absent", source, id)),
+        }).ok_or_else(|| FetchError::MissingDependency { source: source.to_owned(), id: id.to_owned() }.into()),
+    GenericSourceValue::SignedJwt { key_file, algorithm, claims, ttl_seconds } => {
+        sign_jwt(key_file, algorithm.as_deref(), claims, *ttl_seconds).await
+    }
     GenericSourceValue::Template { template, required } => {
         render_template(template.as_str(), required.to_owned()).await
     }
@@ -90,25 +234,276 @@ async fn prepare_generic_source_value(value: &GenericSourceValue) -> Result<Stri
 }
 
 
-async fn render_template(template: &str, _: bool) -> Result<String, Error> {
+/// Request value template placeholder `{{source.token_id}}` or JWT claim placeholder `{{source.token_id.claim}}`
+pub(crate) const SOURCE_TEMPLATE_PLACEHOLDER: &str = r"\{\{([a-zA-Z0-9_]+\.[a-zA-Z0-9_]+(?:\.[a-zA-Z0-9_]+)?)\}\}";
+
+pub(crate) async fn render_template(template: &str, _: bool) -> Result<String, Error> {
     let mut result = template.to_string();
-    let regex = regex::Regex::new(r"\{\{([a-zA-Z0-9_]+\.[a-zA-Z0-9_]+)\}\}")?;
+    let regex = regex::Regex::new(SOURCE_TEMPLATE_PLACEHOLDER)?;
 
     for caps in regex.captures_iter(template) {
         // e.g. metadata.metadata_token
         let key = caps.get(1).unwrap().as_str();
         let parts: Vec<&str> = key.split('.').collect();
 
-        if parts.len() != 2 {
-            continue;
-        }
         let source = parts[0];
         let id = parts[1];
         let token_context = 
         TokenCache::get(source, id).await
-        .ok_or_else(|| anyhow!("token for {}.{} is absent", source, id))?;
-        result = result.replace(&format!("{{{{{}}}}}", key), &token_context.token.value);
+        .ok_or_else(|| FetchError::MissingDependency { source: source.to_owned(), id: id.to_owned() })?;
+        // e.g. idp.access_token.scope
+        let value = match parts.get(2) {
+            None => token_context.token.value,
+            Some(claim) => match token_context.claims.as_ref().and_then(|claims| claims.get(*claim)) {
+                Some(Value::String(value)) => value.clone(),
+                Some(value) => value.to_string(),
+                None => return Err(anyhow!("claim {} of token {}.{} is absent", claim, source, id)),
+            },
+        };
+        result = result.replace(&format!("{{{{{}}}}}", key), &value);
     }
 
     Ok(result)
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use httpmock::Method::{DELETE, GET, POST, PUT};
+    use httpmock::MockServer;
+    use reqwest::Client;
+    use serde_json::json;
+    use serial_test::serial;
+
+    use crate::cache::token::Token;
+    use crate::cache::token_cache::TokenCache;
+    use crate::cache::token_context::TokenContext;
+    use crate::observability::metrics::get_metrics;
+
+    use crate::config::sources::{
+        Expiration, ExpirationSource, ExpirationSourceFormat, GenericSourceValue, ParseConfig,
+        RequestConfig, ResponseExpect, SourceConfig, SourceTypes, TokenField, TokenType,
+    };
+    use crate::sources::error::FetchError;
+    use crate::sources::fetch::{FetchTokens, Source};
+
+    fn make_source(url: String, method: http::Method, body: Option<HashMap<String, GenericSourceValue>>) -> Source {
+        Source(Arc::new(SourceConfig {
+            source_type: SourceTypes::HTTP,
+            request: RequestConfig {
+                url,
+                method,
+                headers: None,
+                query: None,
+                body,
+                form: None,
+                session_ttl_seconds: None,
+                max_response_bytes: None,
+            },
+            parse: ParseConfig {
+                expect: None,
+                body_format: None,
+                tokens: vec![TokenField {
+                    id: "client_token".into(),
+                    parent: "body".into(),
+                    pointer: "client_token".into(),
+                    token_type: TokenType::PlainText,
+                    jwks_uri: None,
+                    transforms: None,
+                    extract_claims: None,
+                    iterate: None,
+                    max_token_bytes: None,
+                    value_regex: None,
+                    expiration: Some(Expiration {
+                        source: ExpirationSource::JsonBodyField,
+                        format: ExpirationSourceFormat::Seconds,
+                        manual_ttl_seconds: None,
+                        pointer: Some("lease_duration".into()),
+                        linked_token_id: None,
+                    }),
+                }],
+            },
+            ..Default::default()
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_put_source_sends_body_and_parses_tokens() {
+        let server = MockServer::start_async().await;
+        let mock = server.mock(|when, then| {
+            when.method(PUT)
+                .path("/v1/auth/token/renew-self")
+                .json_body(json!({ "increment": "1h" }));
+            then.status(200)
+                .header("Content-Type", "application/json")
+                .json_body(json!({ "client_token": "hvs.renewed", "lease_duration": 3600 }));
+        });
+
+        let body = HashMap::from([(
+            "increment".to_string(),
+            GenericSourceValue::Literal { value: "1h".into() },
+        )]);
+        let source = make_source(server.url("/v1/auth/token/renew-self"), http::Method::PUT, Some(body));
+        let tokens = source.fetch_tokens(&Client::new(), None).await.unwrap();
+
+        mock.assert();
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].token.value, "hvs.renewed");
+    }
+
+    #[tokio::test]
+    async fn test_delete_source_without_body() {
+        let server = MockServer::start_async().await;
+        let mock = server.mock(|when, then| {
+            when.method(DELETE).path("/v1/auth/token/revoke-self");
+            then.status(200)
+                .header("Content-Type", "application/json")
+                .json_body(json!({ "client_token": "revoked", "lease_duration": 1 }));
+        });
+
+        let source = make_source(server.url("/v1/auth/token/revoke-self"), http::Method::DELETE, None);
+        let tokens = source.fetch_tokens(&Client::new(), None).await.unwrap();
+
+        mock.assert();
+        assert_eq!(tokens[0].token.value, "revoked");
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_chained_source_renders_query_from_cached_token() {
+        let token = Token::new("upstream-abc".into(), 5_000_000_000);
+        TokenCache::set("upstream".into(), vec![TokenContext::new("upstream_token".into(), token, 10)])
+            .await
+            .unwrap();
+
+        let server = MockServer::start_async().await;
+        let mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/issueToken")
+                .query_param("subject_token", "upstream-abc")
+                .query_param("auth", "Bearer upstream-abc")
+                .query_param("audience", "api://x y");
+            then.status(200)
+                .header("Content-Type", "application/json")
+                .json_body(json!({ "client_token": "chained", "lease_duration": 60 }));
+        });
+
+        let mut source = make_source(server.url("/issueToken"), http::Method::GET, None);
+        Arc::get_mut(&mut source.0).unwrap().request.query = Some(HashMap::from([
+            (
+                "subject_token".to_string(),
+                GenericSourceValue::Ref { source: "upstream".into(), id: "upstream_token".into(), prefix: None },
+            ),
+            (
+                "auth".to_string(),
+                GenericSourceValue::Template { template: "Bearer {{upstream.upstream_token}}".into(), required: true },
+            ),
+            (
+                "audience".to_string(),
+                GenericSourceValue::Literal { value: "api://x y".into() },
+            ),
+        ]));
+        let tokens = source.fetch_tokens(&Client::new(), None).await.unwrap();
+
+        mock.assert();
+        assert_eq!(tokens[0].token.value, "chained");
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_not_modified_returns_cached_tokens_without_parsing() {
+        let source_id = "etag_source";
+        let server = MockServer::start_async().await;
+        let mut modified = server.mock(|when, then| {
+            when.method(GET).path("/token");
+            then.status(200)
+                .header("Content-Type", "application/json")
+                .header("ETag", "\"v1\"")
+                .header("Last-Modified", "Wed, 21 Oct 2015 07:28:00 GMT")
+                .json_body(json!({ "client_token": "etag-token", "lease_duration": 3600 }));
+        });
+        let source = make_source(server.url("/token"), http::Method::GET, None);
+        let tokens = source.fetch_tokens_conditional(source_id, &Client::new(), None, 0).await.unwrap();
+        modified.assert();
+        modified.delete();
+        TokenCache::set(source_id.into(), tokens.clone()).await.unwrap();
+
+        // empty 304 body would fail parsing
+        let not_modified = server.mock(|when, then| {
+            when.method(GET)
+                .path("/token")
+                .header("If-None-Match", "\"v1\"")
+                .header("If-Modified-Since", "Wed, 21 Oct 2015 07:28:00 GMT");
+            then.status(304);
+        });
+        let metrics = get_metrics().await;
+        let parse_failures = metrics.parse_failures.get();
+        let not_modified_before = metrics.source_304_responses.with_label_values(&[source_id]).get();
+
+        let cached = source.fetch_tokens_conditional(source_id, &Client::new(), None, 0).await.unwrap();
+
+        not_modified.assert();
+        assert_eq!(cached.len(), 1);
+        assert_eq!(cached[0].token.value, "etag-token");
+        assert_eq!(cached[0].fetched_at_unix_ts, tokens[0].fetched_at_unix_ts);
+        assert_eq!(metrics.parse_failures.get(), parse_failures);
+        assert_eq!(metrics.source_304_responses.with_label_values(&[source_id]).get(), not_modified_before + 1);
+        TokenCache::remove_by_source_id(source_id).await;
+    }
+
+    #[tokio::test]
+    async fn test_html_page_with_200_is_unexpected_response() {
+        let server = MockServer::start_async().await;
+        server.mock(|when, then| {
+            when.method(GET).path("/token");
+            then.status(200)
+                .header("Content-Type", "text/html; charset=utf-8")
+                .body("<html>\n  <body>Proxy authentication required</body>\n</html>");
+        });
+
+        let mut source = make_source(server.url("/token"), http::Method::GET, None);
+        Arc::get_mut(&mut source.0).unwrap().parse.expect =
+            Some(ResponseExpect { expected_status: None, expected_content_type: Some("application/json".into()) });
+        let err = source.fetch_tokens(&Client::new(), None).await.unwrap_err();
+
+        assert_eq!(FetchError::reason(&err), "unexpected_response");
+        assert!(!err.downcast_ref::<FetchError>().unwrap().is_retryable());
+        assert_eq!(
+            err.to_string(),
+            "unexpected response: status 200 OK, content type 'text/html; charset=utf-8', \
+             body: <html> <body>Proxy authentication required</body> </html>"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_listed_no_content_status_is_accepted() {
+        let server = MockServer::start_async().await;
+        server.mock(|when, then| {
+            when.method(POST).path("/session");
+            then.status(204).header("X-Session-Token", "session-abc");
+        });
+
+        let mut source = make_source(server.url("/session"), http::Method::POST, None);
+        let config = Arc::get_mut(&mut source.0).unwrap();
+        config.parse.expect = Some(ResponseExpect { expected_status: Some(vec![201, 204]), expected_content_type: None });
+        config.parse.tokens[0].parent = "header".into();
+        config.parse.tokens[0].pointer = "x-session-token".into();
+        config.parse.tokens[0].expiration = Some(Expiration {
+            source: ExpirationSource::Manual,
+            format: ExpirationSourceFormat::Seconds,
+            manual_ttl_seconds: Some(60),
+            pointer: None,
+            linked_token_id: None,
+        });
+        let tokens = source.fetch_tokens(&Client::new(), None).await.unwrap();
+        assert_eq!(tokens[0].token.value, "session-abc");
+
+        // 2xx status not listed
+        Arc::get_mut(&mut source.0).unwrap().parse.expect =
+            Some(ResponseExpect { expected_status: Some(vec![200]), expected_content_type: None });
+        let err = source.fetch_tokens(&Client::new(), None).await.unwrap_err();
+        assert_eq!(FetchError::reason(&err), "unexpected_response");
+    }
+}
@@ -0,0 +1,268 @@
+//! Token file source
+//!
+//! Reads a token from a local file instead of making an HTTP request, e.g. a token written by
+//! another agent or a mounted secret, so a chain can start from it without a sidecar serving the file.
+//! The file is re-read on every scheduled refresh; a watcher polls the modification time
+//! and triggers a refresh as soon as the file is rewritten.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{anyhow, Error, Result};
+use chrono::Utc;
+use http::Method;
+use reqwest::Client;
+use tokio::select;
+use tokio::sync::{OnceCell, RwLock};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::cache::token::Token;
+use crate::cache::token_cache::TokenCache;
+use crate::cache::token_context::TokenContext;
+use crate::config::sources::{
+    Expiration, ExpirationSource, ExpirationSourceFormat, FileSourceConfig, ParseConfig, RequestConfig, SourceConfig, TokenField, TokenType,
+};
+use crate::helpers::time::get_token_safety_margin_seconds;
+use crate::parser::parser::get_jwt_token_expiration;
+use crate::sources::executor::token_fetch::request_refresh;
+use crate::sources::fetch::FetchTokens;
+
+pub const FILE_SOURCE_TOKEN_ID_DEFAULT: &str = "token";
+pub const FILE_SOURCE_WATCH_INTERVAL_SECONDS: u64 = 5;
+
+// Declare the static OnceCell to hold the token file modification times.
+static FILE_SOURCE_CACHE_INSTANCE: OnceCell<FileSourceCache> = OnceCell::const_new();
+
+/// Asynchronously initializes and gets a reference to the static `FileSourceCache`.
+async fn get_file_source_cache() -> &'static FileSourceCache {
+    FILE_SOURCE_CACHE_INSTANCE.get_or_init(|| async {
+        info!("Initializing static file source cache...");
+        FileSourceCache::new()
+    }).await
+}
+
+/// path -> modification time of the last read
+#[derive(Clone, Default)]
+pub struct FileSourceCache {
+    inner: Arc<RwLock<HashMap<String, SystemTime>>>,
+}
+
+impl FileSourceCache {
+    pub fn new() -> Self {
+        Self { inner: Arc::new(RwLock::new(HashMap::new())) }
+    }
+
+    pub async fn get_by_path(path: &str) -> Option<SystemTime> {
+        get_file_source_cache().await.inner.read().await.get(path).cloned()
+    }
+
+    pub async fn set(path: &str, modified: SystemTime) {
+        get_file_source_cache().await.inner.write().await.insert(path.to_owned(), modified);
+    }
+
+    /// True when file was modified after the last read
+    pub async fn is_modified(path: &str) -> bool {
+        let Some(cached) = Self::get_by_path(path).await else {
+            return false;
+        };
+        match get_modified(path).await {
+            Ok(modified) => modified != cached,
+            Err(_) => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FileSource {
+    pub source_id: String,
+    pub config: Arc<SourceConfig>,
+}
+
+impl FetchTokens for FileSource {
+    async fn fetch_tokens(&self, _client: &Client, safety_margin_seconds_settings: Option<u64>) -> Result<Vec<TokenContext>, Error> {
+        let file_cfg = self.config.file.as_ref().ok_or_else(|| {
+            anyhow!("source '{}': file block is required for type=file", self.source_id)
+        })?;
+        let safety_margin = get_token_safety_margin_seconds(safety_margin_seconds_settings, self.config.safety_margin_seconds);
+        let token = read_token_file(file_cfg).await?;
+        Ok(vec![TokenContext::new(get_file_source_token_id(file_cfg), token, safety_margin)])
+    }
+}
+
+/// Read and validate token file, modification time is recorded for the watcher
+async fn read_token_file(file_cfg: &FileSourceConfig) -> Result<Token> {
+    let path = file_cfg.path.as_str();
+    let modified = get_modified(path).await?;
+    let value = tokio::fs::read_to_string(path)
+        .await
+        .map_err(|e| anyhow!("failed to read token file '{}': {}", path, e))?
+        .trim()
+        .to_owned();
+    if value.is_empty() {
+        return Err(anyhow!("token file '{}' is empty", path));
+    }
+    let exp = match file_cfg.token_type.clone().unwrap_or(TokenType::PlainText) {
+        TokenType::Jwt => get_jwt_token_expiration(&value).map_err(|e| anyhow!("token file '{}': {}", path, e))?,
+        TokenType::PlainText => {
+            let ttl = file_cfg
+                .ttl_seconds
+                .ok_or_else(|| anyhow!("token file '{}': ttl_seconds is required for token_type=plain_text", path))?;
+            Utc::now().timestamp() as u64 + ttl
+        }
+    };
+    if exp <= Utc::now().timestamp() as u64 {
+        return Err(anyhow!("token file '{}' expired at {}", path, exp));
+    }
+    info!("token file '{}' read, expires at {}", path, exp);
+    FileSourceCache::set(path, modified).await;
+    Ok(Token::new(value, exp))
+}
+
+/// Modification time of the file the path resolves to, symlinks are followed
+async fn get_modified(path: &str) -> Result<SystemTime> {
+    tokio::fs::metadata(path)
+        .await
+        .and_then(|metadata| metadata.modified())
+        .map_err(|e| anyhow!("failed to stat token file '{}': {}", path, e))
+}
+
+/// Invalidate cached tokens of `(source_id, path)` pairs which file was modified since the last read,
+/// true when any source has to be fetched again
+pub async fn invalidate_modified_file_sources(sources: &[(String, String)]) -> bool {
+    let mut modified = false;
+    for (source_id, path) in sources {
+        if FileSourceCache::is_modified(path).await {
+            info!("source '{}': token file '{}' modified", source_id, path);
+            if !TokenCache::force_refresh_by_source_id(source_id).await {
+                warn!("source '{}': no cached tokens to invalidate", source_id);
+            }
+            modified = true;
+        }
+    }
+    modified
+}
+
+/// Poll token files of `(source_id, path)` pairs until shutdown,
+/// a modified file invalidates cached tokens of its source and wakes the refresh loop
+pub async fn watch_file_sources(sources: Vec<(String, String)>, shutdown: CancellationToken) {
+    if sources.is_empty() {
+        return;
+    }
+    info!("watching {} token file(s)", sources.len());
+    loop {
+        select! {
+            _ = shutdown.cancelled() => {
+                info!("token file watcher stopped");
+                break;
+            }
+            _ = tokio::time::sleep(Duration::from_secs(FILE_SOURCE_WATCH_INTERVAL_SECONDS)) => {}
+        }
+        if invalidate_modified_file_sources(&sources).await {
+            request_refresh();
+        }
+    }
+}
+
+pub fn get_file_source_token_id(file_cfg: &FileSourceConfig) -> String {
+    file_cfg
+        .token_id
+        .clone()
+        .unwrap_or_else(|| FILE_SOURCE_TOKEN_ID_DEFAULT.to_owned())
+}
+
+/// Request and parse blocks describing the token file, used by validation, metrics and sinks
+pub fn get_file_source_request_and_parse(file_cfg: &FileSourceConfig) -> (RequestConfig, ParseConfig) {
+    let request = RequestConfig {
+        url: format!("file://{}", file_cfg.path),
+        method: Method::GET,
+        ..Default::default()
+    };
+    let token_type = file_cfg.token_type.clone().unwrap_or(TokenType::PlainText);
+    let expiration = match token_type {
+        TokenType::Jwt => None,
+        TokenType::PlainText => Some(Expiration {
+            source: ExpirationSource::Manual,
+            pointer: None,
+            linked_token_id: None,
+            manual_ttl_seconds: file_cfg.ttl_seconds,
+            format: ExpirationSourceFormat::Seconds,
+        }),
+    };
+    let parse = ParseConfig {
+        expect: None,
+        body_format: None,
+        tokens: vec![TokenField {
+            id: get_file_source_token_id(file_cfg),
+            parent: "body".to_owned(),
+            pointer: "/token".to_owned(),
+            token_type,
+            jwks_uri: None,
+            transforms: None,
+            extract_claims: None,
+            iterate: None,
+            max_token_bytes: None,
+            value_regex: None,
+            expiration,
+        }],
+    };
+    (request, parse)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::config::sources::SourceTypes;
+
+    fn make_source(source_id: &str, path: &Path, ttl_seconds: Option<u64>) -> FileSource {
+        let file = FileSourceConfig {
+            path: path.to_string_lossy().into_owned(),
+            token_type: None,
+            ttl_seconds,
+            token_id: None,
+        };
+        let (request, parse) = get_file_source_request_and_parse(&file);
+        FileSource {
+            source_id: source_id.to_owned(),
+            config: Arc::new(SourceConfig {
+                source_type: SourceTypes::FILE,
+                request,
+                parse,
+                safety_margin_seconds: Some(10),
+                file: Some(file),
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[tokio::test]
+    async fn plain_text_token_file_expires_after_ttl() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("token");
+        std::fs::write(&path, "  static-secret\n").unwrap();
+
+        let source = make_source("file_plain_text", &path, Some(600));
+        let now = Utc::now().timestamp() as u64;
+        let tokens = source.fetch_tokens(&Client::new(), None).await.unwrap();
+        assert_eq!(tokens[0].id, FILE_SOURCE_TOKEN_ID_DEFAULT);
+        assert_eq!(tokens[0].token.value, "static-secret");
+        assert!((now + 600..=now + 601).contains(&tokens[0].token.exp_unix_ts));
+        assert!(!FileSourceCache::is_modified(&path.to_string_lossy()).await);
+    }
+
+    #[tokio::test]
+    async fn missing_or_empty_token_file_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("token");
+
+        let source = make_source("file_missing", &path, Some(600));
+        assert!(source.fetch_tokens(&Client::new(), None).await.is_err());
+
+        std::fs::write(&path, "\n").unwrap();
+        let err = source.fetch_tokens(&Client::new(), None).await.unwrap_err();
+        assert!(err.to_string().contains("is empty"), "{}", err);
+    }
+}
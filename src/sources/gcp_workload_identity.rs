@@ -0,0 +1,350 @@
+//! GCP Workload Identity Federation source
+//!
+//! Runs the whole federation flow as one source:
+//! 1. fetches the subject token (OIDC JWT or SAML assertion) with the source `request` block,
+//! 2. exchanges it at `https://sts.googleapis.com/v1/token` for a federated access token,
+//! 3. with `service_account` set, exchanges the federated token for a service account access token
+//!    with `generateAccessToken` of the IAM Credentials API.
+//!
+//! All steps run inside one fetch attempt, so they share the source retry policy, circuit breaker
+//! and timeouts. Every step is counted in `source_fetch_requests_total` and `source_fetch_failures_total`
+//! with the step name as `source_type` and `reason` label.
+
+use std::future::Future;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Error, Result};
+use http::HeaderMap;
+use reqwest::{Client, Response};
+use serde_json::{json, Value};
+use tracing::debug;
+
+use crate::cache::token_context::TokenContext;
+use crate::config::sources::{
+    Expiration, ExpirationSource, ExpirationSourceFormat, GcpWorkloadIdentityConfig, ParseConfig, RequestConfig,
+    SourceConfig, TokenField, TokenType,
+};
+use crate::observability::metrics::get_metrics;
+use crate::parser::parser::{get_json_value, parse_tokens};
+use crate::sources::error::FetchError;
+use crate::sources::fetch::{with_request_values, FetchTokens};
+
+pub const GCP_STS_URL_DEFAULT: &str = "https://sts.googleapis.com/v1/token";
+pub const GCP_IAM_CREDENTIALS_URL_DEFAULT: &str = "https://iamcredentials.googleapis.com";
+pub const GCP_SCOPE_DEFAULT: &str = "https://www.googleapis.com/auth/cloud-platform";
+pub const GCP_SUBJECT_TOKEN_TYPE_DEFAULT: &str = "urn:ietf:params:oauth:token-type:jwt";
+pub const GCP_SUBJECT_TOKEN_TYPES: [&str; 4] = [
+    "urn:ietf:params:oauth:token-type:jwt",
+    "urn:ietf:params:oauth:token-type:id_token",
+    "urn:ietf:params:oauth:token-type:saml2",
+    "urn:ietf:params:oauth:token-type:access_token",
+];
+pub const GCP_LIFETIME_SECONDS_DEFAULT: u64 = 3600;
+/// `generateAccessToken` accepts up to 12h with `constraints/iam.allowServiceAccountCredentialLifetimeExtension`
+pub const GCP_LIFETIME_SECONDS_MAX: u64 = 43200;
+pub const GCP_TOKEN_ID_DEFAULT: &str = "access_token";
+
+const TOKEN_EXCHANGE_GRANT_TYPE: &str = "urn:ietf:params:oauth:grant-type:token-exchange";
+const ACCESS_TOKEN_TYPE: &str = "urn:ietf:params:oauth:token-type:access_token";
+
+/// Flow steps, used as `source_type` label of fetch requests and `reason` label of fetch failures
+pub const GCP_STEP_SUBJECT_TOKEN: &str = "gcp_subject_token";
+pub const GCP_STEP_STS: &str = "gcp_sts";
+pub const GCP_STEP_IAM_CREDENTIALS: &str = "gcp_iam_credentials";
+
+#[derive(Debug, Clone)]
+pub struct GcpWorkloadIdentitySource {
+    pub source_id: String,
+    pub config: Arc<SourceConfig>,
+}
+
+impl FetchTokens for GcpWorkloadIdentitySource {
+    async fn fetch_tokens(&self, client: &Client, safety_margin_seconds_settings: Option<u64>) -> Result<Vec<TokenContext>, Error> {
+        let gcp_cfg = self.config.gcp_workload_identity.as_ref().ok_or_else(|| {
+            anyhow!("source '{}': gcp_workload_identity block is required for type=gcp_workload_identity", self.source_id)
+        })?;
+        let request = &self.config.request;
+
+        let subject_token = self
+            .observe_step(GCP_STEP_SUBJECT_TOKEN, request.method.as_str(), fetch_subject_token(client, request, gcp_cfg))
+            .await?;
+        let mut body = self
+            .observe_step(GCP_STEP_STS, "POST", exchange_subject_token(client, gcp_cfg, &subject_token))
+            .await?;
+        if let Some(service_account) = &gcp_cfg.service_account {
+            let federated_token = get_string_field(&body, "access_token", GCP_STEP_STS)?;
+            body = self
+                .observe_step(
+                    GCP_STEP_IAM_CREDENTIALS,
+                    "POST",
+                    generate_access_token(client, gcp_cfg, service_account, &federated_token),
+                )
+                .await?;
+        }
+        parse_tokens(HeaderMap::new(), body, self.config.parse.to_owned(), safety_margin_seconds_settings, self.config.safety_margin_seconds).await
+    }
+}
+
+impl GcpWorkloadIdentitySource {
+    async fn observe_step<T>(&self, step: &str, method: &str, request: impl Future<Output = Result<T>>) -> Result<T> {
+        let metrics = get_metrics().await;
+        metrics.source_fetch_requests.with_label_values(&[&self.source_id, step, method]).inc();
+        request.await.map_err(|err| {
+            metrics.source_fetch_failures.with_label_values(&[&self.source_id, step]).inc();
+            err.context(format!("source '{}': {} request failed", self.source_id, step))
+        })
+    }
+}
+
+/// Subject token from provider response: field at `subject_token_pointer` or the whole trimmed body
+async fn fetch_subject_token(client: &Client, request: &RequestConfig, gcp_cfg: &GcpWorkloadIdentityConfig) -> Result<String> {
+    let response = with_request_values(client.request(request.method.clone(), &request.url), request)
+        .await?
+        .send()
+        .await?;
+    let body = success_body(response).await?;
+    let subject_token = match &gcp_cfg.subject_token_pointer {
+        Some(pointer) => get_string_field(&body, pointer, GCP_STEP_SUBJECT_TOKEN)?,
+        None => body.trim().to_owned(),
+    };
+    if subject_token.is_empty() {
+        return Err(anyhow!("{}: subject token is empty", GCP_STEP_SUBJECT_TOKEN));
+    }
+    Ok(subject_token)
+}
+
+async fn exchange_subject_token(client: &Client, gcp_cfg: &GcpWorkloadIdentityConfig, subject_token: &str) -> Result<String> {
+    let body = json!({
+        "grantType": TOKEN_EXCHANGE_GRANT_TYPE,
+        "audience": gcp_cfg.audience,
+        "scope": get_gcp_scope(gcp_cfg),
+        "requestedTokenType": ACCESS_TOKEN_TYPE,
+        "subjectToken": subject_token,
+        "subjectTokenType": gcp_cfg.subject_token_type.as_deref().unwrap_or(GCP_SUBJECT_TOKEN_TYPE_DEFAULT),
+    });
+    let response = client.post(get_gcp_sts_url(gcp_cfg)).json(&body).send().await.map_err(FetchError::from)?;
+    success_body(response).await
+}
+
+async fn generate_access_token(
+    client: &Client,
+    gcp_cfg: &GcpWorkloadIdentityConfig,
+    service_account: &str,
+    federated_token: &str,
+) -> Result<String> {
+    let body = json!({
+        "scope": [get_gcp_scope(gcp_cfg)],
+        "lifetime": format!("{}s", gcp_cfg.lifetime_seconds.unwrap_or(GCP_LIFETIME_SECONDS_DEFAULT)),
+    });
+    let response = client
+        .post(get_gcp_generate_access_token_url(gcp_cfg, service_account))
+        .bearer_auth(federated_token)
+        .json(&body)
+        .send()
+        .await?;
+    success_body(response).await
+}
+
+async fn success_body(response: Response) -> Result<String> {
+    if !response.status().is_success() {
+        return Err(FetchError::from_response(response.status(), response.headers()).into());
+    }
+    Ok(response.text().await?)
+}
+
+fn get_string_field(body: &str, pointer: &str, step: &str) -> Result<String> {
+    let json: Value = serde_json::from_str(body).map_err(|e| anyhow!("{}: response is not valid JSON: {}", step, e))?;
+    debug!("{}: reading '{}' from response", step, pointer);
+    get_json_value(&json, pointer)?
+        .as_str()
+        .map(str::to_owned)
+        .ok_or_else(|| anyhow!("{}: body field '{}' is not a string", step, pointer))
+}
+
+fn get_gcp_scope(gcp_cfg: &GcpWorkloadIdentityConfig) -> &str {
+    gcp_cfg.scope.as_deref().unwrap_or(GCP_SCOPE_DEFAULT)
+}
+
+pub fn get_gcp_sts_url(gcp_cfg: &GcpWorkloadIdentityConfig) -> &str {
+    gcp_cfg.sts_url.as_deref().unwrap_or(GCP_STS_URL_DEFAULT)
+}
+
+pub fn get_gcp_generate_access_token_url(gcp_cfg: &GcpWorkloadIdentityConfig, service_account: &str) -> String {
+    format!(
+        "{}/v1/projects/-/serviceAccounts/{}:generateAccessToken",
+        gcp_cfg.iam_credentials_url.as_deref().unwrap_or(GCP_IAM_CREDENTIALS_URL_DEFAULT).trim_end_matches('/'),
+        service_account
+    )
+}
+
+pub fn get_gcp_workload_identity_token_id(gcp_cfg: &GcpWorkloadIdentityConfig) -> String {
+    gcp_cfg
+        .token_id
+        .clone()
+        .unwrap_or_else(|| GCP_TOKEN_ID_DEFAULT.to_owned())
+}
+
+/// Parse block of the last response: STS token, or `generateAccessToken` token with `service_account`
+pub fn get_gcp_workload_identity_parse(gcp_cfg: &GcpWorkloadIdentityConfig) -> ParseConfig {
+    let (pointer, expiration_pointer, format) = match gcp_cfg.service_account {
+        Some(_) => ("/accessToken", "/expireTime", ExpirationSourceFormat::Rfc3339),
+        None => ("/access_token", "/expires_in", ExpirationSourceFormat::Seconds),
+    };
+    ParseConfig {
+        expect: None,
+        body_format: None,
+        tokens: vec![TokenField {
+            id: get_gcp_workload_identity_token_id(gcp_cfg),
+            parent: "body".to_owned(),
+            pointer: pointer.to_owned(),
+            token_type: TokenType::PlainText,
+            jwks_uri: None,
+            transforms: None,
+            extract_claims: None,
+            iterate: None,
+            max_token_bytes: None,
+            value_regex: None,
+            expiration: Some(Expiration {
+                source: ExpirationSource::JsonBodyField,
+                pointer: Some(expiration_pointer.to_owned()),
+                linked_token_id: None,
+                manual_ttl_seconds: None,
+                format,
+            }),
+        }],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::{Duration, Utc};
+    use httpmock::Method::{GET, POST};
+    use httpmock::MockServer;
+    use reqwest::Client;
+    use serde_json::json;
+
+    use super::*;
+    use crate::config::sources::{GenericSourceValue, SourceTypes};
+
+    const AUDIENCE: &str = "//iam.googleapis.com/projects/123/locations/global/workloadIdentityPools/pool/providers/oidc";
+
+    fn make_source(server: &MockServer, subject_token_pointer: Option<&str>, service_account: Option<&str>) -> Arc<SourceConfig> {
+        let gcp = GcpWorkloadIdentityConfig {
+            audience: AUDIENCE.into(),
+            subject_token_type: None,
+            subject_token_pointer: subject_token_pointer.map(str::to_owned),
+            scope: None,
+            service_account: service_account.map(str::to_owned),
+            lifetime_seconds: Some(600),
+            sts_url: Some(server.url("/v1/token")),
+            iam_credentials_url: Some(server.base_url()),
+            token_id: None,
+        };
+        Arc::new(SourceConfig {
+            source_type: SourceTypes::GcpWorkloadIdentity,
+            request: RequestConfig {
+                url: server.url("/oidc/token"),
+                method: http::Method::GET,
+                headers: Some([("Metadata".to_owned(), GenericSourceValue::Literal { value: "true".into() })].into()),
+                ..Default::default()
+            },
+            parse: get_gcp_workload_identity_parse(&gcp),
+            safety_margin_seconds: Some(10),
+            gcp_workload_identity: Some(gcp),
+            ..Default::default()
+        })
+    }
+
+    async fn step_requests(source_id: &str, step: &str, method: &str) -> u64 {
+        get_metrics().await.source_fetch_requests.with_label_values(&[source_id, step, method]).get()
+    }
+
+    #[tokio::test]
+    async fn federated_token_from_oidc_subject_token() {
+        let server = MockServer::start_async().await;
+        let subject = server.mock(|when, then| {
+            when.method(GET).path("/oidc/token").header("Metadata", "true");
+            then.status(200).json_body(json!({ "id_token": "oidc-jwt" }));
+        });
+        let sts = server.mock(|when, then| {
+            when.method(POST).path("/v1/token").json_body(json!({
+                "grantType": TOKEN_EXCHANGE_GRANT_TYPE,
+                "audience": AUDIENCE,
+                "scope": GCP_SCOPE_DEFAULT,
+                "requestedTokenType": ACCESS_TOKEN_TYPE,
+                "subjectToken": "oidc-jwt",
+                "subjectTokenType": GCP_SUBJECT_TOKEN_TYPE_DEFAULT,
+            }));
+            then.status(200).json_body(json!({ "access_token": "federated", "token_type": "Bearer", "expires_in": 3599 }));
+        });
+
+        let source = GcpWorkloadIdentitySource { source_id: "gcp_wif_federated".into(), config: make_source(&server, Some("/id_token"), None) };
+        let tokens = source.fetch_tokens(&Client::new(), None).await.unwrap();
+
+        subject.assert();
+        sts.assert();
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].id, GCP_TOKEN_ID_DEFAULT);
+        assert_eq!(tokens[0].token.value, "federated");
+        let now = Utc::now().timestamp() as u64;
+        assert!(tokens[0].token.exp_unix_ts >= now + 3590 && tokens[0].token.exp_unix_ts <= now + 3599);
+        assert_eq!(step_requests("gcp_wif_federated", GCP_STEP_SUBJECT_TOKEN, "GET").await, 1);
+        assert_eq!(step_requests("gcp_wif_federated", GCP_STEP_STS, "POST").await, 1);
+        assert_eq!(step_requests("gcp_wif_federated", GCP_STEP_IAM_CREDENTIALS, "POST").await, 0);
+    }
+
+    #[tokio::test]
+    async fn service_account_token_from_saml_subject_token() {
+        let server = MockServer::start_async().await;
+        server.mock(|when, then| {
+            when.method(GET).path("/oidc/token");
+            then.status(200).body("PHNhbWxwOlJlc3BvbnNlPg==\n");
+        });
+        server.mock(|when, then| {
+            when.method(POST).path("/v1/token").json_body_includes(r#"{ "subjectToken": "PHNhbWxwOlJlc3BvbnNlPg==" }"#);
+            then.status(200).json_body(json!({ "access_token": "federated", "expires_in": 3599 }));
+        });
+        let expire_time = (Utc::now() + Duration::seconds(600)).to_rfc3339();
+        let impersonation = server.mock(|when, then| {
+            when.method(POST)
+                .path("/v1/projects/-/serviceAccounts/agent@project.iam.gserviceaccount.com:generateAccessToken")
+                .header("Authorization", "Bearer federated")
+                .json_body(json!({ "scope": [GCP_SCOPE_DEFAULT], "lifetime": "600s" }));
+            then.status(200).json_body(json!({ "accessToken": "ya29.service-account", "expireTime": expire_time }));
+        });
+
+        let config = make_source(&server, None, Some("agent@project.iam.gserviceaccount.com"));
+        let source = GcpWorkloadIdentitySource { source_id: "gcp_wif_impersonation".into(), config };
+        let tokens = source.fetch_tokens(&Client::new(), None).await.unwrap();
+
+        impersonation.assert();
+        assert_eq!(tokens[0].token.value, "ya29.service-account");
+        let now = Utc::now().timestamp() as u64;
+        assert!(tokens[0].token.exp_unix_ts >= now + 590 && tokens[0].token.exp_unix_ts <= now + 600);
+        assert_eq!(step_requests("gcp_wif_impersonation", GCP_STEP_IAM_CREDENTIALS, "POST").await, 1);
+    }
+
+    #[tokio::test]
+    async fn sts_failure_is_typed_and_counted_by_step() {
+        let server = MockServer::start_async().await;
+        server.mock(|when, then| {
+            when.method(GET).path("/oidc/token");
+            then.status(200).json_body(json!({ "id_token": "oidc-jwt" }));
+        });
+        server.mock(|when, then| {
+            when.method(POST).path("/v1/token");
+            then.status(400).json_body(json!({ "error": "invalid_grant" }));
+        });
+
+        let source = GcpWorkloadIdentitySource { source_id: "gcp_wif_sts_failed".into(), config: make_source(&server, Some("id_token"), None) };
+        let err = source.fetch_tokens(&Client::new(), None).await.unwrap_err();
+
+        // typed error survives the step context, so retry policy still sees the HTTP status
+        assert_eq!(FetchError::http_status(&err), Some(http::StatusCode::BAD_REQUEST));
+        assert!(err.to_string().contains("gcp_sts request failed"), "{}", err);
+        let failures = get_metrics().await.source_fetch_failures.with_label_values(&["gcp_wif_sts_failed", GCP_STEP_STS]).get();
+        assert_eq!(failures, 1);
+    }
+}
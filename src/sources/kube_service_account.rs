@@ -0,0 +1,314 @@
+//! Kubernetes projected service account token source
+//!
+//! Reads the JWT kubelet writes to a `serviceAccountToken` volume projection, no HTTP request is made.
+//! Expiration comes from the `exp` claim, optional `audience` is checked against the `aud` claim.
+//! Kubelet rotates the token by swapping the `..data` symlink, so the file is re-read only when
+//! the inode behind the path changes; a watcher polls the inode and triggers a refresh on rotation.
+
+use std::collections::HashMap;
+use std::os::unix::fs::MetadataExt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Error, Result};
+use chrono::Utc;
+use http::Method;
+use reqwest::Client;
+use serde_json::Value;
+use tokio::select;
+use tokio::sync::{OnceCell, RwLock};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+use crate::cache::token::Token;
+use crate::cache::token_cache::TokenCache;
+use crate::cache::token_context::TokenContext;
+use crate::config::sources::{KubeServiceAccountConfig, ParseConfig, RequestConfig, SourceConfig, TokenField, TokenType};
+use crate::helpers::time::get_token_safety_margin_seconds;
+use crate::parser::parser::{decode_jwt_from_string, get_jwt_token_expiration};
+use crate::sources::executor::token_fetch::request_refresh;
+use crate::sources::fetch::FetchTokens;
+
+pub const KUBE_SA_TOKEN_ID_DEFAULT: &str = "token";
+pub const KUBE_SA_WATCH_INTERVAL_SECONDS: u64 = 5;
+
+// Declare the static OnceCell to hold the projected token file cache.
+static KUBE_SA_FILE_CACHE_INSTANCE: OnceCell<KubeServiceAccountCache> = OnceCell::const_new();
+
+/// Asynchronously initializes and gets a reference to the static `KubeServiceAccountCache`.
+async fn get_kube_sa_file_cache() -> &'static KubeServiceAccountCache {
+    KUBE_SA_FILE_CACHE_INSTANCE.get_or_init(|| async {
+        info!("Initializing static kube service account file cache...");
+        KubeServiceAccountCache::new()
+    }).await
+}
+
+/// Token read from file and inode it was read from
+#[derive(Clone)]
+pub struct KubeServiceAccountFile {
+    pub inode: u64,
+    pub token: Token,
+}
+
+/// path -> last read token file
+#[derive(Clone, Default)]
+pub struct KubeServiceAccountCache {
+    inner: Arc<RwLock<HashMap<String, KubeServiceAccountFile>>>,
+}
+
+impl KubeServiceAccountCache {
+    pub fn new() -> Self {
+        Self { inner: Arc::new(RwLock::new(HashMap::new())) }
+    }
+
+    pub async fn get_by_path(path: &str) -> Option<KubeServiceAccountFile> {
+        get_kube_sa_file_cache().await.inner.read().await.get(path).cloned()
+    }
+
+    pub async fn set(path: &str, file: KubeServiceAccountFile) {
+        get_kube_sa_file_cache().await.inner.write().await.insert(path.to_owned(), file);
+    }
+
+    /// True when file behind path has another inode than the cached one
+    pub async fn is_rotated(path: &str) -> bool {
+        let Some(cached) = Self::get_by_path(path).await else {
+            return false;
+        };
+        match get_inode(path).await {
+            Ok(inode) => inode != cached.inode,
+            Err(_) => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct KubeServiceAccountSource {
+    pub source_id: String,
+    pub config: Arc<SourceConfig>,
+}
+
+impl FetchTokens for KubeServiceAccountSource {
+    async fn fetch_tokens(&self, _client: &Client, safety_margin_seconds_settings: Option<u64>) -> Result<Vec<TokenContext>, Error> {
+        let kube_cfg = self.config.kube_service_account.as_ref().ok_or_else(|| {
+            anyhow!("source '{}': kube_service_account block is required for type=kube_service_account", self.source_id)
+        })?;
+        let safety_margin = get_token_safety_margin_seconds(safety_margin_seconds_settings, self.config.safety_margin_seconds);
+        let token = read_token_file(kube_cfg).await?;
+        Ok(vec![TokenContext::new(get_kube_service_account_token_id(kube_cfg), token, safety_margin)])
+    }
+}
+
+/// Token from cache while inode is unchanged, otherwise file is read and validated
+async fn read_token_file(kube_cfg: &KubeServiceAccountConfig) -> Result<Token> {
+    let path = kube_cfg.path.as_str();
+    let inode = get_inode(path).await?;
+    if let Some(cached) = KubeServiceAccountCache::get_by_path(path).await.filter(|file| file.inode == inode) {
+        if cached.token.exp_unix_ts <= Utc::now().timestamp() as u64 {
+            return Err(anyhow!("projected token '{}' expired at {} and was not rotated", path, cached.token.exp_unix_ts));
+        }
+        debug!("projected token '{}' unchanged, inode {}", path, inode);
+        return Ok(cached.token);
+    }
+
+    let value = tokio::fs::read_to_string(path)
+        .await
+        .map_err(|e| anyhow!("failed to read projected token '{}': {}", path, e))?
+        .trim()
+        .to_owned();
+    let exp = get_jwt_token_expiration(&value).map_err(|e| anyhow!("projected token '{}': {}", path, e))?;
+    if let Some(audience) = &kube_cfg.audience {
+        let claims = decode_jwt_from_string(&value)?;
+        if !has_audience(&claims.aud, audience) {
+            return Err(anyhow!("projected token '{}': audience '{}' not found in aud claim", path, audience));
+        }
+    }
+    info!("projected token '{}' read, inode {}, expires at {}", path, inode, exp);
+    let token = Token::new(value, exp);
+    KubeServiceAccountCache::set(path, KubeServiceAccountFile { inode, token: token.clone() }).await;
+    Ok(token)
+}
+
+/// `aud` claim is a string or an array of strings
+fn has_audience(aud: &Option<Value>, audience: &str) -> bool {
+    match aud {
+        Some(Value::String(aud)) => aud == audience,
+        Some(Value::Array(auds)) => auds.iter().any(|aud| aud.as_str() == Some(audience)),
+        _ => false,
+    }
+}
+
+/// Inode of the file the path resolves to, symlinks are followed
+async fn get_inode(path: &str) -> Result<u64> {
+    tokio::fs::metadata(path)
+        .await
+        .map(|metadata| metadata.ino())
+        .map_err(|e| anyhow!("failed to stat projected token '{}': {}", path, e))
+}
+
+/// Poll projected token files of `(source_id, path)` pairs until shutdown,
+/// a rotated file invalidates cached tokens of its source and wakes the refresh loop
+pub async fn watch_kube_service_account_files(sources: Vec<(String, String)>, shutdown: CancellationToken) {
+    if sources.is_empty() {
+        return;
+    }
+    info!("watching {} projected service account token file(s)", sources.len());
+    loop {
+        select! {
+            _ = shutdown.cancelled() => {
+                info!("projected token watcher stopped");
+                break;
+            }
+            _ = tokio::time::sleep(Duration::from_secs(KUBE_SA_WATCH_INTERVAL_SECONDS)) => {}
+        }
+        let mut rotated = false;
+        for (source_id, path) in &sources {
+            if KubeServiceAccountCache::is_rotated(path).await {
+                info!("source '{}': projected token '{}' rotated", source_id, path);
+                if !TokenCache::force_refresh_by_source_id(source_id).await {
+                    warn!("source '{}': no cached tokens to invalidate", source_id);
+                }
+                rotated = true;
+            }
+        }
+        if rotated {
+            request_refresh();
+        }
+    }
+}
+
+pub fn get_kube_service_account_token_id(kube_cfg: &KubeServiceAccountConfig) -> String {
+    kube_cfg
+        .token_id
+        .clone()
+        .unwrap_or_else(|| KUBE_SA_TOKEN_ID_DEFAULT.to_owned())
+}
+
+/// Request and parse blocks describing the token file, used by validation, metrics and sinks
+pub fn get_kube_service_account_request_and_parse(kube_cfg: &KubeServiceAccountConfig) -> (RequestConfig, ParseConfig) {
+    let request = RequestConfig {
+        url: format!("file://{}", kube_cfg.path),
+        method: Method::GET,
+        ..Default::default()
+    };
+    let parse = ParseConfig {
+        expect: None,
+        body_format: None,
+        tokens: vec![TokenField {
+            id: get_kube_service_account_token_id(kube_cfg),
+            parent: "body".to_owned(),
+            pointer: "/token".to_owned(),
+            token_type: TokenType::Jwt,
+            jwks_uri: None,
+            transforms: None,
+            extract_claims: None,
+            iterate: None,
+            max_token_bytes: None,
+            value_regex: None,
+            expiration: None,
+        }],
+    };
+    (request, parse)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::sync::Arc;
+
+    use base64::{engine::general_purpose::STANDARD_NO_PAD, Engine};
+    use chrono::Utc;
+    use reqwest::Client;
+    use serde_json::json;
+
+    use super::*;
+    use crate::config::sources::SourceTypes;
+
+    fn sample_jwt(exp: u64, aud: Value) -> String {
+        let header = STANDARD_NO_PAD.encode(r#"{"alg":"none"}"#);
+        let payload = STANDARD_NO_PAD.encode(json!({ "exp": exp, "aud": aud }).to_string());
+        format!("{}.{}.", header, payload)
+    }
+
+    /// kubelet style rotation: new file renamed over the old one, inode changes
+    fn rotate(path: &Path, token: &str) {
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, token).unwrap();
+        std::fs::rename(&tmp, path).unwrap();
+    }
+
+    fn make_source(source_id: &str, path: &Path, audience: Option<&str>) -> KubeServiceAccountSource {
+        let kube = KubeServiceAccountConfig {
+            path: path.to_string_lossy().into_owned(),
+            audience: audience.map(str::to_owned),
+            token_id: None,
+        };
+        let (request, parse) = get_kube_service_account_request_and_parse(&kube);
+        KubeServiceAccountSource {
+            source_id: source_id.to_owned(),
+            config: Arc::new(SourceConfig {
+                source_type: SourceTypes::KubeServiceAccount,
+                request,
+                parse,
+                safety_margin_seconds: Some(10),
+                kube_service_account: Some(kube),
+                ..Default::default()
+            }),
+        }
+    }
+
+    #[tokio::test]
+    async fn kube_sa_token_reread_on_rotation_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("token");
+        let exp = Utc::now().timestamp() as u64 + 3600;
+        let first = sample_jwt(exp, json!("vault"));
+        rotate(&path, &format!("{}\n", first));
+
+        let source = make_source("kube_sa_rotation", &path, None);
+        let tokens = source.fetch_tokens(&Client::new(), None).await.unwrap();
+        assert_eq!(tokens[0].id, KUBE_SA_TOKEN_ID_DEFAULT);
+        assert_eq!(tokens[0].token.value, first);
+        assert_eq!(tokens[0].token.exp_unix_ts, exp);
+
+        // same inode: file is not re-read
+        std::fs::write(&path, sample_jwt(exp + 1, json!("vault"))).unwrap();
+        let path_str = path.to_string_lossy();
+        assert!(!KubeServiceAccountCache::is_rotated(&path_str).await);
+        let tokens = source.fetch_tokens(&Client::new(), None).await.unwrap();
+        assert_eq!(tokens[0].token.value, first);
+
+        // rotated: new inode, new token
+        let second = sample_jwt(exp + 600, json!("vault"));
+        rotate(&path, &second);
+        assert!(KubeServiceAccountCache::is_rotated(&path_str).await);
+        let tokens = source.fetch_tokens(&Client::new(), None).await.unwrap();
+        assert_eq!(tokens[0].token.value, second);
+        assert_eq!(tokens[0].token.exp_unix_ts, exp + 600);
+        assert!(!KubeServiceAccountCache::is_rotated(&path_str).await);
+    }
+
+    #[tokio::test]
+    async fn kube_sa_token_audience_validation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("token");
+        let exp = Utc::now().timestamp() as u64 + 3600;
+        rotate(&path, &sample_jwt(exp, json!(["https://kubernetes.default.svc", "vault"])));
+
+        let source = make_source("kube_sa_audience", &path, Some("vault"));
+        assert!(source.fetch_tokens(&Client::new(), None).await.is_ok());
+
+        rotate(&path, &sample_jwt(exp, json!("sts.amazonaws.com")));
+        let err = source.fetch_tokens(&Client::new(), None).await.unwrap_err();
+        assert!(err.to_string().contains("audience 'vault'"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn kube_sa_expired_token_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("token");
+        rotate(&path, &sample_jwt(Utc::now().timestamp() as u64 - 60, json!("vault")));
+
+        let source = make_source("kube_sa_expired", &path, None);
+        assert!(source.fetch_tokens(&Client::new(), None).await.is_err());
+    }
+}
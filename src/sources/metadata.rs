@@ -0,0 +1,161 @@
+//! AWS EC2 instance metadata (IMDSv2)
+//!
+//! IMDSv2 requires a session token obtained with `PUT /latest/api/token`
+//! before any metadata path can be read. The session token is only attached
+//! to the follow-up request and is never cached or propagated to sinks.
+
+use anyhow::{anyhow, Result};
+use reqwest::{Client, Url};
+
+use crate::config::sources::RequestConfig;
+
+pub const IMDSV2_SESSION_TOKEN_PATH: &str = "/latest/api/token";
+pub const IMDSV2_SESSION_TTL_HEADER: &str = "X-aws-ec2-metadata-token-ttl-seconds";
+pub const IMDSV2_SESSION_TOKEN_HEADER: &str = "X-aws-ec2-metadata-token";
+pub const IMDSV2_SESSION_TTL_SECONDS_DEFAULT: u64 = 21600;
+pub const IMDSV2_SESSION_TTL_SECONDS_MAX: u64 = 21600;
+
+/// Request a short-lived IMDSv2 session token from the same host as `request.url`
+pub async fn fetch_imdsv2_session_token(client: &Client, req_cfg: &RequestConfig) -> Result<String> {
+    let session_url = get_imdsv2_session_url(&req_cfg.url)?;
+    let ttl = req_cfg
+        .session_ttl_seconds
+        .unwrap_or(IMDSV2_SESSION_TTL_SECONDS_DEFAULT);
+
+    let response = client
+        .put(session_url)
+        .header(IMDSV2_SESSION_TTL_HEADER, ttl.to_string())
+        .send()
+        .await?;
+    if !response.status().is_success() {
+        return Err(anyhow!("IMDSv2 session token request failed: {}", response.status()));
+    }
+
+    let session_token = response.text().await?.trim().to_string();
+    if session_token.is_empty() {
+        return Err(anyhow!("IMDSv2 session token is empty"));
+    }
+    Ok(session_token)
+}
+
+fn get_imdsv2_session_url(metadata_url: &str) -> Result<Url> {
+    let url = Url::parse(metadata_url)
+        .map_err(|e| anyhow!("invalid metadata url '{}': {}", metadata_url, e))?;
+    url.join(IMDSV2_SESSION_TOKEN_PATH)
+        .map_err(|e| anyhow!("invalid IMDSv2 session url for '{}': {}", metadata_url, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::Utc;
+    use httpmock::Method::{GET, PUT};
+    use httpmock::MockServer;
+    use reqwest::Client;
+    use serde_json::json;
+
+    use crate::config::sources::{
+        Expiration, ExpirationSource, ExpirationSourceFormat, ParseConfig, RequestConfig,
+        SourceConfig, SourceTypes, TokenField, TokenType,
+    };
+    use crate::sources::fetch::{FetchTokens, Source};
+    use crate::sources::metadata::{
+        get_imdsv2_session_url, IMDSV2_SESSION_TOKEN_HEADER, IMDSV2_SESSION_TOKEN_PATH,
+        IMDSV2_SESSION_TTL_HEADER,
+    };
+
+    fn make_imdsv2_source(url: String) -> SourceConfig {
+        SourceConfig {
+            source_type: SourceTypes::IMDSV2,
+            request: RequestConfig {
+                url,
+                method: http::Method::GET,
+                headers: None,
+                query: None,
+                body: None,
+                form: None,
+                session_ttl_seconds: Some(300),
+                max_response_bytes: None,
+            },
+            parse: ParseConfig {
+                expect: None,
+                body_format: None,
+                tokens: vec![TokenField {
+                    id: "session_token".into(),
+                    parent: "body".into(),
+                    pointer: "Token".into(),
+                    token_type: TokenType::PlainText,
+                    jwks_uri: None,
+                    transforms: None,
+                    extract_claims: None,
+                    iterate: None,
+                    max_token_bytes: None,
+                    value_regex: None,
+                    expiration: Some(Expiration {
+                        source: ExpirationSource::Manual,
+                        format: ExpirationSourceFormat::Seconds,
+                        manual_ttl_seconds: Some(600),
+                        pointer: None,
+                        linked_token_id: None,
+                    }),
+                }],
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_session_url_uses_metadata_host() {
+        let url = get_imdsv2_session_url(
+            "http://169.254.169.254/latest/meta-data/iam/security-credentials/role",
+        )
+        .unwrap();
+        assert_eq!(url.as_str(), "http://169.254.169.254/latest/api/token");
+    }
+
+    #[tokio::test]
+    async fn test_imdsv2_two_step_fetch() {
+        let server = MockServer::start_async().await;
+        let session_mock = server.mock(|when, then| {
+            when.method(PUT)
+                .path(IMDSV2_SESSION_TOKEN_PATH)
+                .header(IMDSV2_SESSION_TTL_HEADER, "300");
+            then.status(200).body("imds-session-abc");
+        });
+        let metadata_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/latest/meta-data/iam/security-credentials/role")
+                .header(IMDSV2_SESSION_TOKEN_HEADER, "imds-session-abc");
+            then.status(200)
+                .header("Content-Type", "application/json")
+                .json_body(json!({ "Token": "aws-session-token" }));
+        });
+
+        let source = Source(Arc::new(make_imdsv2_source(
+            server.url("/latest/meta-data/iam/security-credentials/role"),
+        )));
+        let tokens = source.fetch_tokens(&Client::new(), None).await.unwrap();
+
+        session_mock.assert();
+        metadata_mock.assert();
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].token.value, "aws-session-token");
+        assert!(tokens[0].token.exp_unix_ts > Utc::now().timestamp() as u64);
+    }
+
+    #[tokio::test]
+    async fn test_imdsv2_session_failure_is_error() {
+        let server = MockServer::start_async().await;
+        server.mock(|when, then| {
+            when.method(PUT).path(IMDSV2_SESSION_TOKEN_PATH);
+            then.status(403);
+        });
+
+        let source = Source(Arc::new(make_imdsv2_source(
+            server.url("/latest/meta-data/iam/security-credentials/role"),
+        )));
+        let res = source.fetch_tokens(&Client::new(), None).await;
+        assert!(res.is_err());
+    }
+}
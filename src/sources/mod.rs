@@ -1,3 +1,13 @@
+pub mod aws_sts;
 pub mod builder_in_order;
+pub mod error;
+pub mod exec;
 pub mod executor;
-pub mod fetch;
\ No newline at end of file
+pub mod fetch;
+pub mod file;
+pub mod gcp_workload_identity;
+pub mod kube_service_account;
+pub mod metadata;
+pub mod signed_jwt;
+pub mod tls;
+pub mod vault;
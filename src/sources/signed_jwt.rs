@@ -0,0 +1,163 @@
+//! Signed JWT assertions (`private_key_jwt`, JWT bearer grant) for request values
+//!
+//! The assertion is built and signed at request time: configured claims (templates rendered),
+//! then `iat`, `exp` (`iat + ttl_seconds`) and a random `jti` unless set. The key file is a PEM
+//! private key or a service account JSON key file (`private_key`, `private_key_id` used as `kid`).
+//! Parsed keys are cached by key file and algorithm, changed key files are picked up on restart.
+
+use std::collections::HashMap;
+use std::fs;
+use std::str::FromStr;
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use serde_json::{Map, Value};
+use tokio::sync::{OnceCell, RwLock};
+use tracing::debug;
+
+use crate::sources::fetch::render_template;
+
+pub const SIGNED_JWT_ALGORITHM_DEFAULT: &str = "RS256";
+pub const SIGNED_JWT_TTL_SECONDS_DEFAULT: u64 = 300;
+/// Claims always set from `ttl_seconds` at signing time
+pub const SIGNED_JWT_RESERVED_CLAIMS: [&str; 2] = ["iat", "exp"];
+
+// Declare the static OnceCell to hold the parsed signing keys.
+static SIGNING_KEY_CACHE_INSTANCE: OnceCell<RwLock<HashMap<(String, Algorithm), SigningKey>>> = OnceCell::const_new();
+
+/// Asynchronously initializes and gets a reference to the static signing key cache.
+async fn get_signing_key_cache() -> &'static RwLock<HashMap<(String, Algorithm), SigningKey>> {
+    SIGNING_KEY_CACHE_INSTANCE.get_or_init(|| async {
+        debug!("Initializing static signing key cache...");
+        RwLock::new(HashMap::new())
+    }).await
+}
+
+/// Parsed private key with optional key id from JSON key file
+#[derive(Clone)]
+pub struct SigningKey {
+    key: EncodingKey,
+    kid: Option<String>,
+}
+
+/// Algorithm by name, `RS256` when not set; HMAC algorithms are rejected as they don't use a key file
+pub fn get_signing_algorithm(algorithm: Option<&str>) -> Result<Algorithm> {
+    let name = algorithm.unwrap_or(SIGNED_JWT_ALGORITHM_DEFAULT);
+    match Algorithm::from_str(name) {
+        Ok(Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512) | Err(_) => Err(anyhow!(
+            "unsupported algorithm '{}', expected one of RS256, RS384, RS512, PS256, PS384, PS512, ES256, ES384, EdDSA",
+            name
+        )),
+        Ok(algorithm) => Ok(algorithm),
+    }
+}
+
+/// Read and parse private key, used by validation to fail fast and by signing on cache miss
+pub fn load_signing_key(key_file: &str, algorithm: Algorithm) -> Result<SigningKey> {
+    let content = fs::read_to_string(key_file).map_err(|err| anyhow!("key_file '{}' is not readable: {}", key_file, err))?;
+    let (pem, kid) = match serde_json::from_str::<Value>(&content) {
+        Ok(json) => (
+            json.get("private_key")
+                .and_then(Value::as_str)
+                .ok_or_else(|| anyhow!("key_file '{}': JSON key file has no private_key", key_file))?
+                .to_owned(),
+            json.get("private_key_id").and_then(Value::as_str).map(str::to_owned),
+        ),
+        Err(_) => (content, None),
+    };
+    let key = match algorithm {
+        Algorithm::ES256 | Algorithm::ES384 => EncodingKey::from_ec_pem(pem.as_bytes()),
+        Algorithm::EdDSA => EncodingKey::from_ed_pem(pem.as_bytes()),
+        _ => EncodingKey::from_rsa_pem(pem.as_bytes()),
+    }
+    .map_err(|err| anyhow!("key_file '{}' is not a valid {:?} private key: {}", key_file, algorithm, err))?;
+    Ok(SigningKey { key, kid })
+}
+
+/// Signed compact JWT with configured claims, `iat`, `exp` and `jti`
+pub async fn sign_jwt(
+    key_file: &str,
+    algorithm: Option<&str>,
+    claims: &HashMap<String, String>,
+    ttl_seconds: Option<u64>,
+) -> Result<String> {
+    let algorithm = get_signing_algorithm(algorithm)?;
+    let signing_key = get_signing_key(key_file, algorithm).await?;
+
+    let mut payload = Map::new();
+    for (name, value) in claims {
+        payload.insert(name.to_owned(), Value::String(render_template(value, true).await?));
+    }
+    let iat = Utc::now().timestamp();
+    let ttl_seconds = ttl_seconds.unwrap_or(SIGNED_JWT_TTL_SECONDS_DEFAULT) as i64;
+    payload.insert("iat".to_owned(), Value::from(iat));
+    payload.insert("exp".to_owned(), Value::from(iat + ttl_seconds));
+    payload
+        .entry("jti")
+        .or_insert_with(|| Value::String(format!("{:032x}", rand::random::<u128>())));
+
+    let mut header = Header::new(algorithm);
+    header.kid = signing_key.kid.clone();
+    encode(&header, &payload, &signing_key.key).map_err(|err| anyhow!("key_file '{}': JWT signing failed: {}", key_file, err))
+}
+
+async fn get_signing_key(key_file: &str, algorithm: Algorithm) -> Result<SigningKey> {
+    let cache = get_signing_key_cache().await;
+    let cache_key = (key_file.to_owned(), algorithm);
+    if let Some(signing_key) = cache.read().await.get(&cache_key) {
+        return Ok(signing_key.clone());
+    }
+    let signing_key = load_signing_key(key_file, algorithm)?;
+    cache.write().await.insert(cache_key, signing_key.clone());
+    Ok(signing_key)
+}
+
+#[cfg(test)]
+mod tests {
+    use jsonwebtoken::{decode, decode_header, DecodingKey, Validation};
+
+    use super::*;
+
+    const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/tests/fixtures/jwt");
+
+    #[test]
+    fn hmac_and_unknown_algorithms_are_rejected() {
+        assert_eq!(get_signing_algorithm(None).unwrap(), Algorithm::RS256);
+        assert_eq!(get_signing_algorithm(Some("ES256")).unwrap(), Algorithm::ES256);
+        assert!(get_signing_algorithm(Some("HS256")).is_err());
+        assert!(get_signing_algorithm(Some("none")).is_err());
+    }
+
+    #[test]
+    fn unreadable_or_mismatched_key_is_rejected() {
+        let err = load_signing_key("/nonexistent/key.pem", Algorithm::RS256).err().unwrap();
+        assert!(err.to_string().contains("is not readable"), "{}", err);
+        let dir = tempfile::tempdir().unwrap();
+        let not_a_key = dir.path().join("key.pem");
+        fs::write(&not_a_key, "not a key").unwrap();
+        assert!(load_signing_key(not_a_key.to_str().unwrap(), Algorithm::RS256).is_err());
+        let private_key = format!("{}/private.pem", FIXTURES);
+        assert!(load_signing_key(&private_key, Algorithm::ES256).is_err());
+    }
+
+    #[tokio::test]
+    async fn json_key_file_sets_kid() {
+        let pem = fs::read_to_string(format!("{}/private.pem", FIXTURES)).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let key_file = dir.path().join("service-account.json");
+        let json = serde_json::json!({ "type": "service_account", "private_key_id": "key-1", "private_key": pem });
+        fs::write(&key_file, json.to_string()).unwrap();
+
+        let claims = HashMap::from([("iss".to_string(), "sa@project.iam.gserviceaccount.com".to_string())]);
+        let jwt = sign_jwt(key_file.to_str().unwrap(), None, &claims, Some(60)).await.unwrap();
+
+        assert_eq!(decode_header(&jwt).unwrap().kid.as_deref(), Some("key-1"));
+        let public_key = fs::read(format!("{}/public.pem", FIXTURES)).unwrap();
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.validate_aud = false;
+        let payload = decode::<Value>(&jwt, &DecodingKey::from_rsa_pem(&public_key).unwrap(), &validation).unwrap().claims;
+        assert_eq!(payload["iss"], "sa@project.iam.gserviceaccount.com");
+        assert_eq!(payload["exp"].as_i64().unwrap() - payload["iat"].as_i64().unwrap(), 60);
+    }
+}
@@ -0,0 +1,154 @@
+//! Per-source HTTP clients with TLS options (custom CA, client certificate, insecure skip verify).
+//!
+//! Sources without `tls` block and own connect timeout share the default client.
+
+use std::fs;
+
+use anyhow::{anyhow, Result};
+use dashmap::DashMap;
+use reqwest::{Certificate, Client, Identity};
+use tokio::sync::OnceCell;
+use tracing::info;
+
+use crate::config::sources::{SourceConfig, TlsConfig};
+use crate::resilience::timeout::TimeoutSettings;
+
+// Declare the static OnceCell to hold tls clients per source_id.
+static SOURCE_CLIENTS_INSTANCE: OnceCell<DashMap<String, Client>> = OnceCell::const_new();
+
+/// Asynchronously initializes and gets a reference to the static source clients map.
+async fn get_source_clients() -> &'static DashMap<String, Client> {
+    SOURCE_CLIENTS_INSTANCE.get_or_init(|| async {
+        info!("Initializing static SourceClients...");
+        DashMap::new()
+    }).await
+}
+
+pub struct SourceClient;
+
+impl SourceClient {
+    /// Return client for source: default one if neither `tls` nor source connect timeout is configured,
+    /// otherwise per-source client, built once and reused
+    pub async fn get_by_source_id(source_id: &str, config: &SourceConfig, default: &Client, timeouts: &TimeoutSettings) -> Result<Client> {
+        let own_connect_timeout = config.timeouts.as_ref().is_some_and(|t| t.connect_seconds.is_some());
+        if config.tls.is_none() && !own_connect_timeout {
+            return Ok(default.clone());
+        }
+        let clients = get_source_clients().await;
+        if let Some(client) = clients.get(source_id) {
+            return Ok(client.clone());
+        }
+        let client = build_source_client(config.tls.as_ref(), timeouts)
+            .map_err(|e| anyhow!("source '{}': client build failed: {}", source_id, e))?;
+        info!("source '{}': client created", source_id);
+        clients.insert(source_id.to_owned(), client.clone());
+        Ok(client)
+    }
+
+    /// Drop cached client, next call builds it from current source config
+    pub async fn remove_by_source_id(source_id: &str) {
+        get_source_clients().await.remove(source_id);
+    }
+}
+
+/// Build reqwest client with TLS options applied
+pub fn build_tls_client(tls: &TlsConfig) -> Result<Client> {
+    build_source_client(Some(tls), &TimeoutSettings::default())
+}
+
+/// Build reqwest client with connect timeout and optional TLS options applied
+pub fn build_source_client(tls: Option<&TlsConfig>, timeouts: &TimeoutSettings) -> Result<Client> {
+    let mut builder = timeouts.client_builder();
+    let Some(tls) = tls else {
+        return builder.build().map_err(|e| anyhow!("{}", e));
+    };
+
+    if let Some(ca_file) = &tls.ca_file {
+        let pem = fs::read(ca_file).map_err(|e| anyhow!("read ca_file '{}': {}", ca_file, e))?;
+        let certs = Certificate::from_pem_bundle(&pem)
+            .map_err(|e| anyhow!("invalid ca_file '{}': {}", ca_file, e))?;
+        if certs.is_empty() {
+            return Err(anyhow!("invalid ca_file '{}': no certificates found", ca_file));
+        }
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+
+    match (&tls.client_cert_file, &tls.client_key_file) {
+        (Some(cert_file), Some(key_file)) => {
+            let mut pem = fs::read(cert_file)
+                .map_err(|e| anyhow!("read client_cert_file '{}': {}", cert_file, e))?;
+            let key = fs::read(key_file)
+                .map_err(|e| anyhow!("read client_key_file '{}': {}", key_file, e))?;
+            pem.push(b'\n');
+            pem.extend_from_slice(&key);
+            let identity = Identity::from_pem(&pem)
+                .map_err(|e| anyhow!("invalid client certificate/key: {}", e))?;
+            builder = builder.identity(identity);
+        }
+        (None, None) => {}
+        _ => return Err(anyhow!("client_cert_file and client_key_file must be set together")),
+    }
+
+    if tls.insecure_skip_verify.unwrap_or(false) {
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+
+    builder.build().map_err(|e| anyhow!("{}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    fn tmp_file(content: &str) -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        file
+    }
+
+    #[test]
+    fn build_tls_client_insecure_skip_verify() {
+        let tls = TlsConfig { insecure_skip_verify: Some(true), ..Default::default() };
+        assert!(build_tls_client(&tls).is_ok());
+    }
+
+    #[test]
+    fn build_tls_client_missing_ca_file() {
+        let tls = TlsConfig { ca_file: Some("/nonexistent/ca.pem".into()), ..Default::default() };
+        let err = build_tls_client(&tls).unwrap_err().to_string();
+        assert!(err.contains("read ca_file"), "{}", err);
+    }
+
+    #[test]
+    fn build_tls_client_invalid_ca_file() {
+        let ca = tmp_file("not a certificate");
+        let tls = TlsConfig { ca_file: Some(ca.path().to_string_lossy().into()), ..Default::default() };
+        let err = build_tls_client(&tls).unwrap_err().to_string();
+        assert!(err.contains("invalid ca_file"), "{}", err);
+    }
+
+    #[test]
+    fn build_tls_client_cert_without_key() {
+        let cert = tmp_file("-----BEGIN CERTIFICATE-----\n-----END CERTIFICATE-----\n");
+        let tls = TlsConfig { client_cert_file: Some(cert.path().to_string_lossy().into()), ..Default::default() };
+        let err = build_tls_client(&tls).unwrap_err().to_string();
+        assert!(err.contains("must be set together"), "{}", err);
+    }
+
+    #[test]
+    fn build_tls_client_invalid_identity() {
+        let cert = tmp_file("garbage");
+        let key = tmp_file("garbage");
+        let tls = TlsConfig {
+            client_cert_file: Some(cert.path().to_string_lossy().into()),
+            client_key_file: Some(key.path().to_string_lossy().into()),
+            ..Default::default()
+        };
+        let err = build_tls_client(&tls).unwrap_err().to_string();
+        assert!(err.contains("invalid client certificate/key"), "{}", err);
+    }
+}
@@ -0,0 +1,320 @@
+//! HashiCorp Vault AppRole source
+//!
+//! Logs in with `POST <vault_addr>/v1/auth/<mount>/login` (`role_id`, `secret_id`) and emits
+//! `auth.client_token` with expiration derived from `auth.lease_duration`.
+//! When the token nears expiry it is renewed with `POST /v1/auth/token/renew-self`,
+//! a failed renewal falls back to a new login.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::{anyhow, Error, Result};
+use chrono::Utc;
+use http::Method;
+use reqwest::Client;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::cache::token::Token;
+use crate::cache::token_cache::TokenCache;
+use crate::cache::token_context::TokenContext;
+use crate::config::sources::{
+    Expiration, ExpirationSource, ExpirationSourceFormat, ParseConfig, RequestConfig, SourceConfig,
+    TokenField, TokenType, VaultConfig,
+};
+use crate::helpers::time::get_token_safety_margin_seconds;
+use crate::sources::fetch::{prepare_generic_source_value, FetchTokens};
+use crate::sources::error::FetchError;
+
+pub const VAULT_TOKEN_HEADER: &str = "X-Vault-Token";
+pub const VAULT_RENEW_SELF_PATH: &str = "/v1/auth/token/renew-self";
+pub const VAULT_APPROLE_MOUNT_DEFAULT: &str = "approle";
+pub const VAULT_TOKEN_ID_DEFAULT: &str = "client_token";
+
+#[derive(Deserialize)]
+struct VaultAuthResponse {
+    auth: VaultAuth,
+}
+
+/// No Debug: holds the Vault client token
+#[derive(Deserialize)]
+struct VaultAuth {
+    client_token: String,
+    lease_duration: u64,
+    #[serde(default)]
+    renewable: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct VaultSource {
+    pub source_id: String,
+    pub config: Arc<SourceConfig>,
+}
+
+impl FetchTokens for VaultSource {
+    async fn fetch_tokens(&self, client: &Client, safety_margin_seconds_settings: Option<u64>) -> Result<Vec<TokenContext>, Error> {
+        let vault_cfg = self
+            .config
+            .vault
+            .as_ref()
+            .ok_or_else(|| anyhow!("source '{}': vault block is required for type=vault", self.source_id))?;
+        let token_id = get_vault_token_id(vault_cfg);
+        let safety_margin = get_token_safety_margin_seconds(safety_margin_seconds_settings, self.config.safety_margin_seconds);
+
+        // renew current token while it is still valid
+        if let Some(current) = TokenCache::get(&self.source_id, &token_id).await.filter(|ctx| !ctx.should_remove()) {
+            match renew_self(client, vault_cfg, &current.token.value).await {
+                Ok(auth) if auth.lease_duration > safety_margin => {
+                    info!("source '{}': vault token renewed, lease {}s", self.source_id, auth.lease_duration);
+                    return Ok(vec![to_token_context(token_id, auth, safety_margin)]);
+                }
+                Ok(auth) => warn!(
+                    "source '{}': vault token renewed with lease {}s within safety margin, login again",
+                    self.source_id, auth.lease_duration
+                ),
+                Err(e) => warn!("source '{}': vault token renewal failed, login again: {}", self.source_id, e),
+            }
+        }
+
+        let auth = login(client, vault_cfg).await?;
+        if !auth.renewable {
+            info!("source '{}': vault token is not renewable", self.source_id);
+        }
+        Ok(vec![to_token_context(token_id, auth, safety_margin)])
+    }
+}
+
+async fn login(client: &Client, vault_cfg: &VaultConfig) -> Result<VaultAuth> {
+    let body = HashMap::from([
+        ("role_id", prepare_generic_source_value(&vault_cfg.role_id).await?),
+        ("secret_id", prepare_generic_source_value(&vault_cfg.secret_id).await?),
+    ]);
+    let response = client.post(get_vault_login_url(vault_cfg)).json(&body).send().await.map_err(FetchError::from)?;
+    parse_auth_response(response, "login").await
+}
+
+async fn renew_self(client: &Client, vault_cfg: &VaultConfig, token: &str) -> Result<VaultAuth> {
+    let url = format!("{}{}", vault_cfg.vault_addr.trim_end_matches('/'), VAULT_RENEW_SELF_PATH);
+    let response = client
+        .post(url)
+        .header(VAULT_TOKEN_HEADER, token)
+        .json(&serde_json::json!({}))
+        .send()
+        .await
+        .map_err(FetchError::from)?;
+    parse_auth_response(response, "renew-self").await
+}
+
+/// Typed errors keep `retry_on_status`, `Retry-After` and failure reasons working for vault sources
+async fn parse_auth_response(response: reqwest::Response, operation: &str) -> Result<VaultAuth> {
+    if !response.status().is_success() {
+        let err = FetchError::from_response(response.status(), response.headers());
+        return Err(Error::from(err).context(format!("vault {} failed", operation)));
+    }
+    let auth = response
+        .json::<VaultAuthResponse>()
+        .await
+        .map_err(|e| FetchError::ParseBody { message: format!("vault {} invalid response: {}", operation, e) })?
+        .auth;
+    if auth.client_token.is_empty() {
+        return Err(FetchError::ParseToken { message: format!("vault {}: auth.client_token is empty", operation) }.into());
+    }
+    Ok(auth)
+}
+
+fn to_token_context(token_id: String, auth: VaultAuth, safety_margin: u64) -> TokenContext {
+    let exp = Utc::now().timestamp() as u64 + auth.lease_duration;
+    TokenContext::new(token_id, Token::new(auth.client_token, exp), safety_margin)
+}
+
+pub fn get_vault_token_id(vault_cfg: &VaultConfig) -> String {
+    vault_cfg
+        .token_id
+        .clone()
+        .unwrap_or_else(|| VAULT_TOKEN_ID_DEFAULT.to_owned())
+}
+
+pub fn get_vault_login_url(vault_cfg: &VaultConfig) -> String {
+    format!(
+        "{}/v1/auth/{}/login",
+        vault_cfg.vault_addr.trim_end_matches('/'),
+        vault_cfg.mount.as_deref().unwrap_or(VAULT_APPROLE_MOUNT_DEFAULT).trim_matches('/')
+    )
+}
+
+/// Request and parse blocks describing the login call, used by validation, metrics and sinks
+pub fn get_vault_request_and_parse(vault_cfg: &VaultConfig) -> (RequestConfig, ParseConfig) {
+    let request = RequestConfig {
+        url: get_vault_login_url(vault_cfg),
+        method: Method::POST,
+        body: Some(HashMap::from([
+            ("role_id".to_owned(), vault_cfg.role_id.clone()),
+            ("secret_id".to_owned(), vault_cfg.secret_id.clone()),
+        ])),
+        ..Default::default()
+    };
+    let parse = ParseConfig {
+        expect: None,
+        body_format: None,
+        tokens: vec![TokenField {
+            id: get_vault_token_id(vault_cfg),
+            parent: "body".to_owned(),
+            pointer: "/auth/client_token".to_owned(),
+            token_type: TokenType::PlainText,
+            jwks_uri: None,
+            transforms: None,
+            extract_claims: None,
+            iterate: None,
+            max_token_bytes: None,
+            value_regex: None,
+            expiration: Some(Expiration {
+                source: ExpirationSource::JsonBodyField,
+                pointer: Some("/auth/lease_duration".to_owned()),
+                linked_token_id: None,
+                manual_ttl_seconds: None,
+                format: ExpirationSourceFormat::Seconds,
+            }),
+        }],
+    };
+    (request, parse)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::Utc;
+    use httpmock::Method::POST;
+    use httpmock::MockServer;
+    use reqwest::Client;
+    use serde_json::json;
+    use serial_test::serial;
+
+    use super::*;
+    use crate::config::sources::{GenericSourceValue, SourceTypes};
+
+    fn make_source(vault_addr: String) -> Arc<SourceConfig> {
+        let vault = VaultConfig {
+            vault_addr,
+            role_id: GenericSourceValue::Literal { value: "role".into() },
+            secret_id: GenericSourceValue::Literal { value: "secret".into() },
+            mount: None,
+            token_id: None,
+        };
+        let (request, parse) = get_vault_request_and_parse(&vault);
+        Arc::new(SourceConfig {
+            source_type: SourceTypes::VAULT,
+            request,
+            parse,
+            safety_margin_seconds: Some(10),
+            vault: Some(vault),
+            ..Default::default()
+        })
+    }
+
+    fn auth_body(token: &str, lease_duration: u64) -> serde_json::Value {
+        json!({ "auth": { "client_token": token, "lease_duration": lease_duration, "renewable": true } })
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn vault_approle_login() {
+        let server = MockServer::start_async().await;
+        let login = server.mock(|when, then| {
+            when.method(POST)
+                .path("/v1/auth/approle/login")
+                .json_body(json!({ "role_id": "role", "secret_id": "secret" }));
+            then.status(200).json_body(auth_body("s.login", 3600));
+        });
+
+        let source = VaultSource { source_id: "vault_login".into(), config: make_source(server.base_url()) };
+        let tokens = source.fetch_tokens(&Client::new(), None).await.unwrap();
+
+        login.assert();
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].id, VAULT_TOKEN_ID_DEFAULT);
+        assert_eq!(tokens[0].token.value, "s.login");
+        let now = Utc::now().timestamp() as u64;
+        assert!(tokens[0].token.exp_unix_ts >= now + 3590 && tokens[0].token.exp_unix_ts <= now + 3600);
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn vault_renews_cached_token() {
+        let server = MockServer::start_async().await;
+        let login = server.mock(|when, then| {
+            when.method(POST).path("/v1/auth/approle/login");
+            then.status(200).json_body(auth_body("s.login", 3600));
+        });
+        let renew = server.mock(|when, then| {
+            when.method(POST).path(VAULT_RENEW_SELF_PATH).header(VAULT_TOKEN_HEADER, "s.current");
+            then.status(200).json_body(auth_body("s.current", 7200));
+        });
+
+        let exp = Utc::now().timestamp() as u64 + 5;
+        TokenCache::set("vault_renew".into(), vec![TokenContext::new(VAULT_TOKEN_ID_DEFAULT.into(), Token::new("s.current".into(), exp), 10)]).await.unwrap();
+
+        let source = VaultSource { source_id: "vault_renew".into(), config: make_source(server.base_url()) };
+        let tokens = source.fetch_tokens(&Client::new(), None).await.unwrap();
+
+        renew.assert();
+        login.assert_calls(0);
+        assert_eq!(tokens[0].token.value, "s.current");
+        assert!(tokens[0].token.exp_unix_ts > exp + 7000);
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn vault_login_when_renew_fails() {
+        let server = MockServer::start_async().await;
+        let login = server.mock(|when, then| {
+            when.method(POST).path("/v1/auth/approle/login");
+            then.status(200).json_body(auth_body("s.new", 3600));
+        });
+        let renew = server.mock(|when, then| {
+            when.method(POST).path(VAULT_RENEW_SELF_PATH);
+            then.status(403).json_body(json!({ "errors": ["permission denied"] }));
+        });
+
+        let exp = Utc::now().timestamp() as u64 + 5;
+        TokenCache::set("vault_renew_failed".into(), vec![TokenContext::new(VAULT_TOKEN_ID_DEFAULT.into(), Token::new("s.revoked".into(), exp), 10)]).await.unwrap();
+
+        let source = VaultSource { source_id: "vault_renew_failed".into(), config: make_source(server.base_url()) };
+        let tokens = source.fetch_tokens(&Client::new(), None).await.unwrap();
+
+        renew.assert();
+        login.assert();
+        assert_eq!(tokens[0].token.value, "s.new");
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn vault_login_failure() {
+        let server = MockServer::start_async().await;
+        server.mock(|when, then| {
+            when.method(POST).path("/v1/auth/approle/login");
+            then.status(400).json_body(json!({ "errors": ["invalid role or secret ID"] }));
+        });
+
+        let source = VaultSource { source_id: "vault_login_failed".into(), config: make_source(server.base_url()) };
+        let err = source.fetch_tokens(&Client::new(), None).await.unwrap_err();
+        assert!(err.to_string().contains("vault login failed"), "{}", err);
+        assert_eq!(FetchError::http_status(&err), Some(http::StatusCode::BAD_REQUEST));
+        assert_eq!(FetchError::reason(&err), "http_status");
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn vault_login_without_client_token_is_parse_error() {
+        let server = MockServer::start_async().await;
+        server.mock(|when, then| {
+            when.method(POST).path("/v1/auth/approle/login");
+            then.status(200).json_body(auth_body("", 3600));
+        });
+
+        let source = VaultSource { source_id: "vault_login_empty".into(), config: make_source(server.base_url()) };
+        let err = source.fetch_tokens(&Client::new(), None).await.unwrap_err();
+        assert_eq!(FetchError::reason(&err), "parse_token");
+        assert!(!err.downcast_ref::<FetchError>().unwrap().is_retryable());
+    }
+}
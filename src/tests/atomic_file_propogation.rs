@@ -3,6 +3,7 @@ mod tests {
     use std::fs;
     use std::os::unix::fs::PermissionsExt;
     use tokio::time::sleep;
+    use crate::sinks::sink_file::{write_file_atomic, SINK_FILE_MODE_DEFAULT};
 
     #[tokio::test]
     async fn atomic_write_and_permissions() {
@@ -14,17 +15,10 @@ mod tests {
         // ensure cleanup
         let _ = fs::remove_file(&path);
 
-        // Simulate atomic write (tmp -> rename) code (should match your implementation)
-        let tmp = path.with_extension("tmp");
-        if let Some(parent) = path.parent() {
-            let _ = fs::create_dir_all(parent);
-        }
         let content = "token-value-123";
-        fs::write(&tmp, content).expect("write tmp");
-        // set 0o600 perms
-        let perms = fs::Permissions::from_mode(0o600);
-        fs::set_permissions(&tmp, perms).expect("set perms");
-        fs::rename(&tmp, &path).expect("rename");
+        write_file_atomic(path.to_str().unwrap(), content.as_bytes(), SINK_FILE_MODE_DEFAULT)
+            .await
+            .expect("atomic write");
 
         // small delay to stabilize FS
         sleep(std::time::Duration::from_millis(20)).await;
@@ -41,4 +35,270 @@ mod tests {
         // cleanup
         let _ = fs::remove_file(&path);
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn concurrent_reader_never_sees_empty_file() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("token").to_string_lossy().to_string();
+        write_file_atomic(&path, b"token-initial", 0o640).await.unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode, 0o640);
+
+        let done = Arc::new(AtomicBool::new(false));
+        let reader = {
+            let done = done.clone();
+            let path = path.clone();
+            std::thread::spawn(move || {
+                let mut reads = 0;
+                while !done.load(Ordering::Relaxed) {
+                    let got = fs::read(&path).expect("file must always exist");
+                    assert!(!got.is_empty(), "reader observed empty file");
+                    reads += 1;
+                }
+                reads
+            })
+        };
+
+        for i in 0..200 {
+            let value = format!("token-value-{}-{}", i, "x".repeat(4096));
+            write_file_atomic(&path, value.as_bytes(), SINK_FILE_MODE_DEFAULT).await.unwrap();
+        }
+        done.store(true, Ordering::Relaxed);
+        let reads = reader.join().expect("reader panicked");
+        assert!(reads > 0);
+
+        let mode = fs::metadata(&path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode, 0o600);
+        assert!(fs::read_to_string(&path).unwrap().starts_with("token-value-199-"));
+        // no temp files left behind
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    fn file_sink(path: &str, atomic: bool) -> crate::config::sinks::SinkConfig {
+        crate::config::sinks::SinkConfig {
+            sink_id: "file_sink".to_string(),
+            sink_type: crate::config::sinks::SinkType::File,
+            source_id: "source".to_string(),
+            path: path.to_string(),
+            token_id: "token".to_string(),
+            response: None,
+            tokens: None,
+            method: None,
+            target_url: None,
+            mode: None,
+            owner: None,
+            group: None,
+            exec: None,
+            template: None,
+            atomic: Some(atomic),
+            create_dirs: Some(true),
+            auth: None,
+            nats: None,
+            redis: None,
+            kubernetes_secret: None,
+            rate_limit: None,
+            format: None,
+            type_hint: None,
+            stub_value: None,
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn data_dir_swap_reader_never_sees_partial_token() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+        use crate::sinks::sink_file::{write_token_file, SINK_FILE_DATA_DIR};
+
+        let dir = tempfile::tempdir().unwrap();
+        // parent dirs are created by the sink
+        let token_dir = dir.path().join("projected/tokens");
+        let path = token_dir.join("token").to_string_lossy().to_string();
+        let cfg = file_sink(&path, true);
+        let padding = "x".repeat(4096);
+        write_token_file(&cfg, format!("token-value-initial-{}", padding).as_bytes()).await.unwrap();
+        assert_eq!(fs::read_link(&path).unwrap(), std::path::Path::new(SINK_FILE_DATA_DIR).join("token"));
+
+        let done = Arc::new(AtomicBool::new(false));
+        let reader = {
+            let done = done.clone();
+            let path = path.clone();
+            let padding = padding.clone();
+            std::thread::spawn(move || {
+                let mut reads = 0;
+                while !done.load(Ordering::Relaxed) {
+                    let got = fs::read_to_string(&path).expect("token must always be readable");
+                    assert!(got.starts_with("token-value-") && got.ends_with(&padding), "reader observed partial token");
+                    reads += 1;
+                }
+                reads
+            })
+        };
+
+        for i in 0..200 {
+            write_token_file(&cfg, format!("token-value-{}-{}", i, padding).as_bytes()).await.unwrap();
+        }
+        done.store(true, Ordering::Relaxed);
+        let reads = reader.join().expect("reader panicked");
+        assert!(reads > 0);
+
+        assert!(fs::read_to_string(&path).unwrap().starts_with("token-value-199-"));
+        let mode = fs::metadata(&path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode, SINK_FILE_MODE_DEFAULT);
+        // `token` symlink, `..data` symlink, the current and the previous payload dirs
+        assert_eq!(fs::read_dir(&token_dir).unwrap().count(), 4);
+    }
+
+    #[tokio::test]
+    async fn data_dir_swap_keeps_other_files_of_dir() {
+        use crate::sinks::sink_file::write_token_file;
+
+        let dir = tempfile::tempdir().unwrap();
+        let access = dir.path().join("access").to_string_lossy().to_string();
+        let refresh = dir.path().join("refresh").to_string_lossy().to_string();
+
+        write_token_file(&file_sink(&access, true), b"access-1").await.unwrap();
+        write_token_file(&file_sink(&refresh, true), b"refresh-1").await.unwrap();
+        write_token_file(&file_sink(&access, true), b"access-2").await.unwrap();
+
+        assert_eq!(fs::read_to_string(&access).unwrap(), "access-2");
+        assert_eq!(fs::read_to_string(&refresh).unwrap(), "refresh-1");
+    }
+
+    #[tokio::test]
+    async fn data_dir_swap_keeps_unrelated_dot_dot_dirs() {
+        use crate::sinks::sink_file::write_token_file;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("token").to_string_lossy().to_string();
+        for user_dir in ["..config", "..2024_01_01_00_00_00", "..backup_1"] {
+            fs::create_dir(dir.path().join(user_dir)).unwrap();
+        }
+
+        for i in 0..3 {
+            write_token_file(&file_sink(&path, true), format!("token-{}", i).as_bytes()).await.unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "token-2");
+        for user_dir in ["..config", "..2024_01_01_00_00_00", "..backup_1"] {
+            assert!(dir.path().join(user_dir).is_dir(), "{} must be kept", user_dir);
+        }
+        // user dirs, `token` symlink, `..data` symlink, the current and the previous payload dirs
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 7);
+    }
+
+    #[tokio::test]
+    async fn sink_mode_string_and_ownership_applied() {
+        use std::os::unix::fs::MetadataExt;
+        use crate::config::sinks::parse_file_mode;
+        use crate::sinks::sink_file::{is_valid_id_or_name, write_token_file};
+
+        assert_eq!(parse_file_mode("0600"), Ok(0o600));
+        assert_eq!(parse_file_mode("0o640"), Ok(0o640));
+        assert!(parse_file_mode("0990").is_err());
+        // names are not looked up on the validating host
+        assert!(["1000", "nginx", "app-user", "svc.account", "machine$"].iter().all(|v| is_valid_id_or_name(v)));
+        assert!(["", "-user", "user name", "a:b", "$", &"u".repeat(33)].iter().all(|v| !is_valid_id_or_name(v)));
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("token").to_string_lossy().to_string();
+        // current uid/gid: chown is a no-op when not running as root
+        let meta = fs::metadata(dir.path()).unwrap();
+        let yaml = format!(
+            "type: file\nsource_id: source\ntoken_id: token\npath: {}\nmode: \"0640\"\nowner: \"{}\"\ngroup: \"{}\"\n",
+            path, meta.uid(), meta.gid()
+        );
+        let cfg: crate::config::sinks::SinkConfig = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(cfg.mode, Some(0o640));
+
+        write_token_file(&cfg, b"token-value").await.unwrap();
+        let written = fs::metadata(&path).unwrap();
+        assert_eq!(written.permissions().mode() & 0o777, 0o640);
+        assert_eq!((written.uid(), written.gid()), (meta.uid(), meta.gid()));
+    }
+
+    #[tokio::test]
+    #[serial_test::serial]
+    async fn file_sink_cleanup_on_shutdown_keeps_directory() {
+        use std::collections::HashMap;
+        use std::time::Duration;
+        use tokio_util::sync::CancellationToken;
+        use crate::cache::{token::Token, token_cache::TokenCache, token_context::TokenContext};
+        use crate::cache::token_event::TokenEvent;
+        use crate::sinks::manager::SinkManager;
+        use crate::utils::channel;
+
+        let dir = tempfile::tempdir().unwrap();
+        let token_dir = dir.path().join("tokens");
+        let path = token_dir.join("token");
+        let source_id = "file_sink_cleanup".to_string();
+        let mut cfg = file_sink(&path.to_string_lossy(), false);
+        cfg.source_id = source_id.clone();
+        cfg.mode = Some(0o600);
+
+        let sink_manager = SinkManager::new(HashMap::from([("file_sink".to_string(), cfg)]));
+        let sink_sender = channel::run();
+        let shutdown = CancellationToken::new();
+        let worker = tokio::spawn(sink_manager.start_file_sinks(sink_sender.subscribe(), shutdown.clone()));
+
+        TokenCache::set(source_id.clone(), vec![TokenContext::new("token".to_string(), Token::new("value".to_string(), 5_000_000_000), 10)]).await.unwrap();
+        sink_sender.send(TokenEvent::updated(&source_id, TokenCache::get(&source_id, "token").await.unwrap())).unwrap();
+        let written = tokio::time::timeout(Duration::from_secs(5), async {
+            while fs::read_to_string(&path).ok().as_deref() != Some("value") {
+                sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await;
+        assert!(written.is_ok(), "token must be written");
+        assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+
+        shutdown.cancel();
+        tokio::time::timeout(Duration::from_secs(5), worker).await.unwrap().unwrap().unwrap();
+        assert!(!path.exists(), "token file must be removed on shutdown");
+        assert!(token_dir.is_dir(), "configured directory must be preserved");
+        TokenCache::remove_by_source_id(&source_id).await;
+    }
+
+    #[tokio::test]
+    #[serial_test::serial]
+    async fn lagging_file_sink_writes_cached_token() {
+        use std::collections::HashMap;
+        use std::time::Duration;
+        use tokio_util::sync::CancellationToken;
+        use crate::cache::{token::Token, token_cache::TokenCache, token_context::TokenContext};
+        use crate::cache::token_event::TokenEvent;
+        use crate::sinks::manager::SinkManager;
+        use crate::tests::common::overflow_token_events;
+        use crate::utils::channel;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("token");
+        let source_id = "file_sink_lagged".to_string();
+        let mut cfg = file_sink(&path.to_string_lossy(), false);
+        cfg.source_id = source_id.clone();
+
+        let sink_manager = SinkManager::new(HashMap::from([("file_sink".to_string(), cfg)]));
+        let sink_sender = channel::run();
+        let sink_receiver = sink_sender.subscribe();
+        // the sink token event is pushed out of the channel by events of another source before the sink reads it
+        TokenCache::set(source_id.clone(), vec![TokenContext::new("token".to_string(), Token::new("cached".to_string(), 5_000_000_000), 10)]).await.unwrap();
+        sink_sender.send(TokenEvent::updated(&source_id, TokenCache::get(&source_id, "token").await.unwrap())).unwrap();
+        overflow_token_events(&sink_sender);
+
+        let shutdown = CancellationToken::new();
+        let worker = tokio::spawn(sink_manager.start_file_sinks(sink_receiver, shutdown.clone()));
+        let written = tokio::time::timeout(Duration::from_secs(5), async {
+            while fs::read_to_string(&path).ok().as_deref() != Some("cached") {
+                sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await;
+        assert!(written.is_ok(), "cached token must be written");
+
+        shutdown.cancel();
+        tokio::time::timeout(Duration::from_secs(5), worker).await.unwrap().unwrap().unwrap();
+        TokenCache::remove_by_source_id(&source_id).await;
+    }
 }
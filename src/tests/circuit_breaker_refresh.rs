@@ -0,0 +1,84 @@
+// Circuit breaker in refresh loop:
+//  - consecutive failed fetches open the circuit, source is skipped while open
+//  - after cooldown a half-open probe is sent, recovered source closes the circuit
+//  - source level circuit_breaker overrides settings
+
+#[cfg(test)]
+mod test {
+
+use std::{collections::HashMap, time::Duration};
+
+use httpmock::prelude::*;
+use serde_json::json;
+use serial_test::serial;
+
+use crate::cache::token_cache::TokenCache;
+use crate::config::settings::CircuitBreakerConfig;
+use crate::config::sources::SourceConfig;
+use crate::observability::metrics::get_metrics;
+use crate::resilience::circuit_breaker::{CircuitBreaker, CircuitBreakerSettings, CircuitState};
+use crate::sources::builder_in_order::{DagNode, SourceDag};
+use crate::tests::common::{http_source, refresh_context};
+
+const SOURCE_ID: &str = "circuit_breaker_refresh";
+
+async fn circuit_state() -> i64 {
+    get_metrics().await.source_circuit_state.with_label_values(&[SOURCE_ID]).get()
+}
+
+#[tokio::test]
+#[serial]
+async fn circuit_opens_skips_source_and_recovers_after_probe() {
+    TokenCache::cleanup().await;
+    let server = MockServer::start_async().await;
+    let mut failing = server.mock(|when, then| {
+        when.method(GET).path("/flaky");
+        then.status(500).json_body(json!({ "error": "down" }));
+    });
+
+    let source = SourceConfig {
+        min_refresh_interval_seconds: Some(0), // refresh_layers is called back to back, no failure backoff
+        // source level override, settings level threshold is 5
+        circuit_breaker: Some(CircuitBreakerConfig { failure_threshold: Some(2), open_duration_seconds: Some(1) }),
+        ..http_source(server.url("/flaky"), 3600)
+    };
+    let sources = HashMap::from([(SOURCE_ID.to_string(), source)]);
+    let dag = SourceDag::build(&sources).unwrap();
+    let layers: Vec<Vec<DagNode>> = dag
+        .layers()
+        .into_iter()
+        .map(|layer| layer.into_iter().cloned().collect())
+        .collect();
+    let refresh_context = refresh_context();
+
+    // closed: failures are counted until source level threshold
+    SourceDag::refresh_layers(&layers, &refresh_context).await;
+    // breaker is created on first fetch with source level settings
+    let breaker = CircuitBreaker::get_by_source_id(SOURCE_ID, &CircuitBreakerSettings::default()).await;
+    assert_eq!(breaker.state(), CircuitState::Closed);
+    SourceDag::refresh_layers(&layers, &refresh_context).await;
+    assert_eq!(breaker.state(), CircuitState::Open);
+    assert_eq!(circuit_state().await, CircuitState::Open as i64);
+    failing.assert_calls(2);
+
+    // open: source is skipped
+    SourceDag::refresh_layers(&layers, &refresh_context).await;
+    failing.assert_calls(2);
+    failing.delete();
+
+    // half-open: after cooldown a single probe hits the recovered source and closes the circuit
+    let recovered = server.mock(|when, then| {
+        when.method(GET).path("/flaky");
+        then.status(200).json_body(json!({ "token": "recovered" }));
+    });
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    SourceDag::refresh_layers(&layers, &refresh_context).await;
+    recovered.assert_calls(1);
+    assert_eq!(breaker.state(), CircuitState::Closed);
+    assert_eq!(circuit_state().await, CircuitState::Closed as i64);
+    assert_eq!(TokenCache::get(SOURCE_ID, "token").await.unwrap().token.value, "recovered");
+
+    TokenCache::cleanup().await;
+}
+
+}
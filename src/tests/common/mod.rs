@@ -6,7 +6,23 @@ pub use tokio::task::JoinHandle;
 use std::net::SocketAddr;
 use reqwest::Client;
 use regex::Regex;
+use serde_json::Value;
 use std::collections::HashMap;
+use tokio::sync::broadcast::Sender;
+use tokio_util::sync::CancellationToken;
+
+use crate::cache::token::Token;
+use crate::cache::token_cache::TokenCache;
+use crate::cache::token_context::TokenContext;
+use crate::cache::token_event::TokenEvent;
+use crate::config::sinks::SinkConfig;
+use crate::resilience::retry::RetrySettings;
+use crate::sinks::manager::SinkManager;
+use crate::utils::channel;
+
+use crate::config::sources::{
+    Expiration, ExpirationSource, ExpirationSourceFormat, FormValue, ParseConfig, RequestConfig, SourceConfig, TokenField, TokenType,
+};
 
 /// Spawn an Axum router on an ephemeral port and return (JoinHandle, SocketAddr)
 pub async fn spawn_axum(router: Router) -> (JoinHandle<()>, SocketAddr) {
@@ -35,9 +51,125 @@ pub fn render_template(template: &str, ctx: &HashMap<String, String>) -> Option<
     Some(out)
 }
 
+/// Refresh context of tests: one attempt with 1ms retry delays, default timeouts and margins,
+/// fields are overridden with `RefreshContext { retry, ..refresh_context() }`
+#[cfg(test)]
+pub(crate) fn refresh_context() -> crate::sources::executor::token_fetch::RefreshContext {
+    use crate::resilience::circuit_breaker::CircuitBreakerSettings;
+    use crate::resilience::timeout::TimeoutSettings;
+    use crate::sources::executor::token_fetch::RefreshContext;
+
+    RefreshContext {
+        client: Client::new(),
+        retry: RetrySettings { attempts: 1, base_delay_ms: 1, max_delay_ms: 1, ..Default::default() },
+        circuit_breaker: CircuitBreakerSettings { failure_threshold: 5, open_duration_seconds: 60 },
+        timeouts: TimeoutSettings::default(),
+        safety_margin_seconds_settings: None,
+        prefetch_margin_seconds_settings: None,
+        fetch_permits: None,
+    }
+}
+
 pub fn build_reqwest_client() -> Client {
     Client::builder()
         .timeout(std::time::Duration::from_secs(5))
         .build()
         .expect("reqwest client")
 }
+
+/// HTTP GET source with one plain text `token` at body pointer `token`, valid for `manual_ttl_seconds`.
+/// Other fields are set with struct update syntax: `SourceConfig { inputs, ..http_source(url, 3600) }`
+pub fn http_source(url: impl Into<String>, manual_ttl_seconds: u64) -> SourceConfig {
+    SourceConfig {
+        request: RequestConfig {
+            url: url.into(),
+            method: http::Method::GET,
+            ..Default::default()
+        },
+        parse: ParseConfig {
+            tokens: vec![TokenField {
+                id: "token".into(),
+                parent: "body".into(),
+                pointer: "token".into(),
+                token_type: TokenType::PlainText,
+                expiration: Some(Expiration {
+                    source: ExpirationSource::Manual,
+                    format: ExpirationSourceFormat::Seconds,
+                    manual_ttl_seconds: Some(manual_ttl_seconds),
+                    pointer: None,
+                    linked_token_id: None,
+                }),
+                jwks_uri: None,
+                transforms: None,
+                extract_claims: None,
+                iterate: None,
+                max_token_bytes: None,
+                value_regex: None,
+            }],
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+/// OAuth2 token endpoint source: `form` is POSTed, `access_token` expires after `expires_in` of the response
+pub fn oauth2_source(url: impl Into<String>, form: FormValue) -> SourceConfig {
+    let mut source = http_source(url, 0);
+    source.request.method = http::Method::POST;
+    source.request.form = Some(form);
+    source.parse.tokens[0] = TokenField {
+        id: "access_token".into(),
+        pointer: "access_token".into(),
+        expiration: Some(Expiration {
+            source: ExpirationSource::JsonBodyField,
+            format: ExpirationSourceFormat::Seconds,
+            pointer: Some("expires_in".into()),
+            linked_token_id: None,
+            manual_ttl_seconds: None,
+        }),
+        ..source.parse.tokens[0].clone()
+    };
+    source
+}
+
+/// Sink of `sink_type` for token `access_token` of `source_id`, `options` are merged into the sink:
+/// `sink_config("nats", "nats_raw", SOURCE_ID, json!({ "nats": { "nats_url": url, "subject": "tokens" } }))`
+pub fn sink_config(sink_type: &str, sink_id: &str, source_id: &str, options: Value) -> SinkConfig {
+    let mut sink = json!({ "type": sink_type, "source_id": source_id, "token_id": "access_token" });
+    if let (Some(sink), Value::Object(options)) = (sink.as_object_mut(), options) {
+        sink.extend(options);
+    }
+    let mut cfg: SinkConfig = serde_json::from_value(sink).unwrap();
+    cfg.sink_id = sink_id.into();
+    cfg
+}
+
+/// Store `access_token` of `source_id`, token events are emitted as by a refresh
+pub async fn set_access_token(source_id: &str, value: &str, exp_unix_ts: u64) {
+    TokenCache::set(source_id.into(), vec![TokenContext::new("access_token".into(), Token::new(value.into(), exp_unix_ts), 10)])
+        .await
+        .unwrap();
+}
+
+/// Run active sinks with fast `attempts` retry until the returned token is cancelled;
+/// the returned sender delivers token events to the sinks
+pub fn start_active_sinks(sinks: HashMap<String, SinkConfig>, attempts: u32) -> (JoinHandle<anyhow::Result<()>>, Sender<TokenEvent>, CancellationToken) {
+    let retry = RetrySettings { attempts, base_delay_ms: 10, max_delay_ms: 50, ..Default::default() };
+    let sink_manager = SinkManager::new(sinks).with_http_push(Client::new(), retry);
+    let sink_sender = channel::run();
+    let shutdown = CancellationToken::new();
+    let worker = tokio::spawn({
+        let (sink_sender, shutdown) = (sink_sender.clone(), shutdown.clone());
+        async move { sink_manager.start_active_sinks(sink_sender, shutdown).await }
+    });
+    (worker, sink_sender, shutdown)
+}
+
+/// Fill the token event channel with events of another source,
+/// so a subscriber that did not read yet lags behind and loses its older events
+pub fn overflow_token_events(sink_sender: &Sender<TokenEvent>) {
+    for _ in 0..channel::BUFFER_SIZE {
+        let token_context = TokenContext::new("access_token".into(), Token::new("other".into(), 4_102_444_800), 10);
+        sink_sender.send(TokenEvent::updated("other_source", token_context)).unwrap();
+    }
+}
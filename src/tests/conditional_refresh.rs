@@ -0,0 +1,73 @@
+// Conditional GET in refresh loop:
+//  - validators are not sent for cached tokens already due for refresh, the source answers with fresh tokens
+//  - a due source is not re-requested every loop tick on `304 Not Modified`
+
+#[cfg(test)]
+mod test {
+
+use std::{collections::HashMap, time::Duration};
+
+use httpmock::prelude::*;
+use reqwest::Client;
+use serde_json::json;
+use serial_test::serial;
+use tokio_util::sync::CancellationToken;
+
+use crate::cache::etag_cache::{EtagCache, EtagEntry};
+use crate::cache::token::Token;
+use crate::cache::token_cache::TokenCache;
+use crate::cache::token_context::TokenContext;
+use crate::helpers::time::now_i64;
+use crate::resilience::backoff::SourceBackoff;
+use crate::sources::builder_in_order::SourceDag;
+use crate::sources::executor::token_fetch::RefreshSettings;
+use crate::tests::common::http_source;
+
+const SOURCE_ID: &str = "conditional_refresh";
+
+#[tokio::test]
+#[serial]
+async fn due_tokens_are_fetched_without_validators() {
+    TokenCache::cleanup().await;
+    SourceBackoff::remove_by_source_id(SOURCE_ID).await;
+    let server = MockServer::start_async().await;
+    let not_modified = server.mock(|when, then| {
+        when.method(GET).path("/token").header("If-None-Match", "\"v1\"");
+        then.status(304);
+    });
+    let modified = server.mock(|when, then| {
+        when.method(GET).path("/token").header_missing("If-None-Match");
+        then.status(200)
+            .header("ETag", "\"v2\"")
+            .json_body(json!({ "token": "fresh" }));
+    });
+
+    // cached token expires in 5s, already due for refresh
+    let exp = (now_i64() + 5) as u64;
+    TokenCache::set(SOURCE_ID.into(), vec![TokenContext::new("token".into(), Token::new("stale".into(), exp), 10)]).await.unwrap();
+    EtagCache::set(SOURCE_ID, EtagEntry { etag: Some("\"v1\"".into()), last_modified: None }).await;
+
+    let sources = HashMap::from([(SOURCE_ID.to_string(), http_source(server.url("/token"), 3600))]);
+    let dag = SourceDag::build(&sources).unwrap();
+    let shutdown = CancellationToken::new();
+    let refresh = tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move { dag.loop_refrech_tokens(&Client::new(), &RefreshSettings::default(), shutdown).await }
+    });
+
+    // reusing the due token on 304 would request the source every second
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    shutdown.cancel();
+    refresh.await.unwrap().unwrap();
+
+    modified.assert_calls_async(1).await;
+    not_modified.assert_calls_async(0).await;
+    let tokens = TokenCache::get_all_by_source_id(SOURCE_ID).await;
+    assert_eq!(tokens[0].token.value, "fresh");
+
+    EtagCache::remove(SOURCE_ID).await;
+    SourceBackoff::remove_by_source_id(SOURCE_ID).await;
+    TokenCache::cleanup().await;
+}
+
+}
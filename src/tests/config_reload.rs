@@ -0,0 +1,385 @@
+// Config hot reload:
+//  - diff reports added, removed and changed sources/sinks and settings
+//  - invalid config is rejected without aborting
+//  - surviving sources keep cached tokens, surviving file sinks keep token files
+//  - watched config file change adds HTTP route without re-fetching unchanged source
+//  - watched config file change stops removed sinks only, server keeps serving
+
+#[cfg(test)]
+mod test {
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::Duration;
+
+use serial_test::serial;
+use tempfile::tempdir;
+use tokio_util::sync::CancellationToken;
+
+use crate::cache::token::Token;
+use crate::cache::token_cache::TokenCache;
+use crate::cache::token_context::TokenContext;
+use crate::config::proc_reload::{retained_file_sink_paths, ConfigDiff};
+use crate::observability::metrics::get_metrics;
+use crate::sinks::manager::SinkManager;
+use crate::utils::app::run_with_reload;
+use crate::utils::{channel, config_loader};
+use crate::ServiceConfig;
+
+fn config_yaml(sources: &str, sinks: &str, safety_margin_seconds: u64) -> String {
+    format!(
+        r#"
+settings:
+  safety_margin_seconds: {safety_margin_seconds}
+  server:
+    host: 127.0.0.1
+    port: 8080
+  metrics:
+    path: "/metrics"
+    is_enabled: false
+sources:
+{sources}
+sinks:
+{sinks}
+"#
+    )
+}
+
+fn source_yaml(id: &str, url: &str) -> String {
+    format!(
+        r#"  {id}:
+    type: http
+    request:
+      url: "{url}"
+      method: GET
+    parse:
+      tokens:
+        - id: token
+          parent: body
+          pointer: token
+          token_type: plain_text
+          expiration:
+            source: manual
+            format: seconds
+            manual_ttl_seconds: 3600
+"#
+    )
+}
+
+fn file_sink_yaml(id: &str, source_id: &str, path: &str) -> String {
+    format!(
+        r#"  {id}:
+    type: file
+    source_id: {source_id}
+    path: "{path}"
+    token_id: token
+"#
+    )
+}
+
+fn parse(yaml: &str) -> ServiceConfig {
+    serde_yaml::from_str(yaml).expect("test config must parse")
+}
+
+#[test]
+fn diff_reports_added_removed_and_changed() {
+    let current = parse(&config_yaml(
+        &(source_yaml("kept", "http://localhost/kept") + &source_yaml("changed", "http://localhost/a") + &source_yaml("removed", "http://localhost/r")),
+        &(file_sink_yaml("kept_sink", "kept", "/tmp/kept") + &file_sink_yaml("removed_sink", "removed", "/tmp/removed")),
+        10,
+    ));
+    let reloaded = parse(&config_yaml(
+        &(source_yaml("kept", "http://localhost/kept") + &source_yaml("changed", "http://localhost/b") + &source_yaml("added", "http://localhost/n")),
+        &(file_sink_yaml("kept_sink", "kept", "/tmp/kept") + &file_sink_yaml("added_sink", "added", "/tmp/added")),
+        20,
+    ));
+
+    let diff = ConfigDiff::new(&current, &reloaded);
+    assert_eq!(diff.added_sources, vec!["added"]);
+    assert_eq!(diff.removed_sources, vec!["removed"]);
+    assert_eq!(diff.changed_sources, vec!["changed"]);
+    assert_eq!(diff.added_sinks, vec!["added_sink"]);
+    assert_eq!(diff.removed_sinks, vec!["removed_sink"]);
+    assert!(diff.changed_sinks.is_empty());
+    assert!(diff.settings_changed);
+    assert!(ConfigDiff::new(&current, &current).is_empty());
+    assert_eq!(retained_file_sink_paths(&current, &reloaded), HashSet::from(["/tmp/kept".to_string()]));
+}
+
+#[test]
+fn identical_reload_with_several_headers_is_empty_diff() {
+    let source = r#"  with_headers:
+    type: http
+    request:
+      url: "http://localhost/token"
+      method: POST
+      headers:
+        Accept: { value: "application/json" }
+        X-Client-Id: { value: "client" }
+        X-Request-Source: { value: "token-agent" }
+        X-Tenant: { value: "tenant-a" }
+        X-Trace: { value: "on" }
+      query:
+        audience: { value: "api" }
+        scope: { value: "read" }
+        region: { value: "eu" }
+    parse:
+      tokens:
+        - id: token
+          parent: body
+          pointer: token
+          token_type: plain_text
+"#;
+    let sink = r#"  http_sink:
+    type: http
+    source_id: with_headers
+    path: /token
+    token_id: token
+    response:
+      headers:
+        Cache-Control: { type: string, value: "no-store" }
+        X-Token-Source: { type: string, value: "with_headers" }
+        X-Served-By: { type: string, value: "token-agent" }
+        X-Env: { type: string, value: "test" }
+      body:
+        access_token: { type: token, id: token }
+        token_type: { type: string, value: Bearer }
+        expires_in: { type: expiration, id: token }
+"#;
+    let yaml = config_yaml(source, sink, 10);
+    let current = parse(&yaml);
+    // every parse gets its own hash map seeds, iteration order of the maps differs between them
+    for _ in 0..20 {
+        let diff = ConfigDiff::new(&current, &parse(&yaml));
+        assert!(diff.is_empty(), "identical config reported as changed: {}", diff);
+    }
+}
+
+#[tokio::test]
+async fn reload_rejects_invalid_config_without_panic() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("token-agent.yaml");
+    // sink references unknown source
+    let yaml = config_yaml(&source_yaml("s1", "http://localhost/s1"), &file_sink_yaml("sink", "unknown", "/tmp/token"), 10);
+    std::fs::write(&path, yaml).unwrap();
+
+    let res = config_loader::reload(path.to_str().unwrap()).await;
+    assert!(res.unwrap_err().to_string().contains("config is not valid"));
+}
+
+#[tokio::test]
+#[serial]
+async fn reload_keeps_tokens_and_files_of_surviving_sinks() {
+    TokenCache::cleanup().await;
+    let dir = tempdir().unwrap();
+    let kept_path = dir.path().join("kept").to_str().unwrap().to_string();
+    let removed_path = dir.path().join("removed").to_str().unwrap().to_string();
+    let added_path = dir.path().join("added").to_str().unwrap().to_string();
+
+    for source_id in ["reload_kept", "reload_removed"] {
+        let token = Token::new(format!("{}-token", source_id), 5_000_000_000);
+        TokenCache::set(source_id.to_string(), vec![TokenContext::new("token".to_string(), token, 10)]).await.unwrap();
+    }
+    let current = parse(&config_yaml(
+        &(source_yaml("reload_kept", "http://localhost/kept") + &source_yaml("reload_removed", "http://localhost/r")),
+        &(file_sink_yaml("kept_sink", "reload_kept", &kept_path) + &file_sink_yaml("removed_sink", "reload_removed", &removed_path)),
+        10,
+    ));
+    let reloaded = parse(&config_yaml(
+        &source_yaml("reload_kept", "http://localhost/kept"),
+        &(file_sink_yaml("kept_sink", "reload_kept", &kept_path) + &file_sink_yaml("added_sink", "reload_kept", &added_path)),
+        10,
+    ));
+
+    // running generation writes cached tokens to its file sinks
+    let generation = CancellationToken::new();
+    let sink_manager = SinkManager::new(current.sinks.clone());
+    let running = tokio::spawn({
+        let (sink_manager, generation) = (sink_manager.clone(), generation.clone());
+        async move { sink_manager.start_active_sinks(channel::run(), generation).await }
+    });
+    wait_for_file(&kept_path, "reload_kept-token").await;
+    wait_for_file(&removed_path, "reload_removed-token").await;
+
+    // reload: stop running generation, surviving file sink keeps its file
+    let diff = ConfigDiff::new(&current, &reloaded);
+    sink_manager.retain_files_on_stop(retained_file_sink_paths(&current, &reloaded));
+    generation.cancel();
+    running.await.unwrap().unwrap();
+    diff.apply().await;
+    assert_eq!(std::fs::read_to_string(&kept_path).unwrap(), "reload_kept-token");
+    assert!(!Path::new(&removed_path).exists(), "removed sink file must be cleaned up");
+    assert!(TokenCache::get("reload_removed", "token").await.is_none());
+
+    // new generation propagates cached token of surviving source to added sink without re-fetch
+    let generation = CancellationToken::new();
+    let running = tokio::spawn({
+        let (sink_manager, generation) = (SinkManager::new(reloaded.sinks.clone()), generation.clone());
+        async move { sink_manager.start_active_sinks(channel::run(), generation).await }
+    });
+    wait_for_file(&added_path, "reload_kept-token").await;
+    assert_eq!(TokenCache::get("reload_kept", "token").await.unwrap().token.value, "reload_kept-token");
+
+    generation.cancel();
+    running.await.unwrap().unwrap();
+    TokenCache::cleanup().await;
+}
+
+fn http_sink_yaml(id: &str, source_id: &str, path: &str) -> String {
+    format!(
+        r#"  {id}:
+    type: http
+    source_id: {source_id}
+    path: "{path}"
+    token_id: token
+    response:
+      body:
+        token: {{ type: token, id: token }}
+"#
+    )
+}
+
+fn watched_config_yaml(port: u16, sources: &str, sinks: &str) -> String {
+    format!(
+        r#"
+settings:
+  reload:
+    watch_interval_seconds: 1
+  server:
+    host: 127.0.0.1
+    port: "{port}"
+  metrics:
+    path: "/metrics"
+    is_enabled: false
+sources:
+{sources}
+sinks:
+{sinks}
+"#
+    )
+}
+
+#[tokio::test]
+#[serial]
+async fn watched_config_change_adds_http_route_without_refetch() {
+    TokenCache::cleanup().await;
+    let token_server = httpmock::MockServer::start_async().await;
+    let token_mock = token_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/token");
+        then.status(200).json_body(serde_json::json!({ "token": "live-token" }));
+    });
+    let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let source = source_yaml("reload_live", &token_server.url("/token"));
+    let dir = tempdir().unwrap();
+    let config_path = dir.path().join("token-agent.yaml");
+    let config_path = config_path.to_str().unwrap().to_string();
+    std::fs::write(&config_path, watched_config_yaml(port, &source, &http_sink_yaml("first", "reload_live", "/first"))).unwrap();
+
+    let reloads = get_metrics().await.config_reloads.with_label_values(&["success"]);
+    let reloads_before = reloads.get();
+    let service_config = config_loader::run(&config_path).await.unwrap();
+    let shutdown = CancellationToken::new();
+    let app = tokio::spawn({
+        let (config_path, shutdown) = (config_path.clone(), shutdown.clone());
+        async move { run_with_reload(&config_path, service_config, shutdown).await }
+    });
+
+    let client = reqwest::Client::new();
+    let wait_for_route = |path: &'static str| {
+        let client = client.clone();
+        async move {
+            tokio::time::timeout(Duration::from_secs(10), async {
+                loop {
+                    if let Ok(response) = client.get(format!("http://127.0.0.1:{}{}", port, path)).send().await {
+                        if response.status().is_success() {
+                            return response.json::<serde_json::Value>().await.unwrap();
+                        }
+                    }
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                }
+            })
+            .await
+            .unwrap_or_else(|_| panic!("route '{}' must serve token", path))
+        }
+    };
+    assert_eq!(wait_for_route("/first").await["token"], "live-token");
+
+    // add sink, file watch picks up the change
+    let sinks = http_sink_yaml("first", "reload_live", "/first") + &http_sink_yaml("second", "reload_live", "/second");
+    std::fs::write(&config_path, watched_config_yaml(port, &source, &sinks)).unwrap();
+    assert_eq!(wait_for_route("/second").await["token"], "live-token");
+    assert_eq!(wait_for_route("/first").await["token"], "live-token");
+    assert_eq!(reloads.get(), reloads_before + 1);
+    // unchanged source keeps its cached token
+    token_mock.assert_calls_async(1).await;
+
+    shutdown.cancel();
+    tokio::time::timeout(Duration::from_secs(10), app).await.unwrap().unwrap().unwrap();
+    TokenCache::cleanup().await;
+}
+
+#[tokio::test]
+#[serial]
+async fn watched_config_change_stops_removed_sinks_only() {
+    TokenCache::cleanup().await;
+    let token_server = httpmock::MockServer::start_async().await;
+    let token_mock = token_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/token");
+        then.status(200).json_body(serde_json::json!({ "token": "live-token" }));
+    });
+    let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let source = source_yaml("reload_sinks", &token_server.url("/token"));
+    let dir = tempdir().unwrap();
+    let kept_path = dir.path().join("kept").to_str().unwrap().to_string();
+    let removed_path = dir.path().join("removed").to_str().unwrap().to_string();
+    let config_path = dir.path().join("token-agent.yaml").to_str().unwrap().to_string();
+    let kept_sinks = file_sink_yaml("kept_file", "reload_sinks", &kept_path) + &http_sink_yaml("kept_http", "reload_sinks", "/kept");
+    let sinks = kept_sinks.clone() + &file_sink_yaml("removed_file", "reload_sinks", &removed_path) + &http_sink_yaml("removed_http", "reload_sinks", "/removed");
+    std::fs::write(&config_path, watched_config_yaml(port, &source, &sinks)).unwrap();
+
+    let service_config = config_loader::run(&config_path).await.unwrap();
+    let shutdown = CancellationToken::new();
+    let app = tokio::spawn({
+        let (config_path, shutdown) = (config_path.clone(), shutdown.clone());
+        async move { run_with_reload(&config_path, service_config, shutdown).await }
+    });
+    wait_for_file(&kept_path, "live-token").await;
+    wait_for_file(&removed_path, "live-token").await;
+
+    let client = reqwest::Client::new();
+    let status = |path: &'static str| {
+        let client = client.clone();
+        async move { client.get(format!("http://127.0.0.1:{}{}", port, path)).send().await.unwrap().status() }
+    };
+    assert!(status("/removed").await.is_success());
+
+    std::fs::write(&config_path, watched_config_yaml(port, &source, &kept_sinks)).unwrap();
+    let stopped = tokio::time::timeout(Duration::from_secs(10), async {
+        while Path::new(&removed_path).exists() {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await;
+    assert!(stopped.is_ok(), "removed sink file must be cleaned up");
+    assert_eq!(status("/removed").await, reqwest::StatusCode::NOT_FOUND);
+    assert!(status("/kept").await.is_success());
+    assert_eq!(std::fs::read_to_string(&kept_path).unwrap(), "live-token");
+    token_mock.assert_calls_async(1).await;
+
+    shutdown.cancel();
+    tokio::time::timeout(Duration::from_secs(10), app).await.unwrap().unwrap().unwrap();
+    assert!(!Path::new(&kept_path).exists(), "kept sink file is cleaned up on shutdown");
+    TokenCache::cleanup().await;
+}
+
+async fn wait_for_file(path: &str, expected: &str) {
+    let written = tokio::time::timeout(Duration::from_secs(5), async {
+        while tokio::fs::read_to_string(path).await.ok().as_deref() != Some(expected) {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await;
+    assert!(written.is_ok(), "file '{}' must contain '{}'", path, expected);
+}
+
+}
@@ -0,0 +1,108 @@
+// Dry run:
+//  - sources are fetched, file sink paths are not created, summary reports planned writes with redacted previews
+//  - failed source is reported, its sinks are reported as skipped
+
+#[cfg(test)]
+mod test {
+
+use std::time::Duration;
+
+use httpmock::prelude::*;
+use serde_json::json;
+use serial_test::serial;
+
+use crate::cache::token_cache::TokenCache;
+use crate::config::sinks::SinkType;
+use crate::{ServiceConfig, TokenAgent};
+
+fn config_yaml(base_url: &str, dir: &str) -> String {
+    format!(
+        r#"
+settings:
+  server:
+    host: 127.0.0.1
+    port: "8080"
+  metrics:
+    path: "/metrics"
+    is_enabled: false
+  retry: {{ attempts: 1, base_delay_ms: 1, max_delay_ms: 1 }}
+sources:
+  first:
+    type: http
+    request: {{ url: "{base_url}/first", method: GET }}
+    parse:
+      tokens:
+        - {{ id: token, parent: body, pointer: token, token_type: plain_text, expiration: {{ source: manual, format: seconds, manual_ttl_seconds: 3600 }} }}
+  broken:
+    type: http
+    request: {{ url: "{base_url}/broken", method: GET }}
+    parse:
+      tokens:
+        - {{ id: token, parent: body, pointer: token, token_type: plain_text, expiration: {{ source: manual, format: seconds, manual_ttl_seconds: 3600 }} }}
+sinks:
+  first_file:
+    type: file
+    source_id: first
+    token_id: token
+    path: "{dir}/first.token"
+  first_http:
+    type: http
+    source_id: first
+    token_id: token
+    path: "/token/first"
+  broken_file:
+    type: file
+    source_id: broken
+    token_id: token
+    path: "{dir}/broken.token"
+"#
+    )
+}
+
+#[tokio::test]
+#[serial]
+async fn dry_run_fetches_sources_and_reports_planned_writes_without_writing() {
+    TokenCache::cleanup().await;
+    let server = MockServer::start_async().await;
+    let first = server.mock(|when, then| {
+        when.method(GET).path("/first");
+        then.status(200).json_body(json!({ "token": "first-token" }));
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/broken");
+        then.status(500);
+    });
+    let dir = tempfile::tempdir().unwrap();
+    let service_config: ServiceConfig =
+        serde_yaml::from_str(&config_yaml(&server.base_url(), dir.path().to_str().unwrap())).unwrap();
+
+    let agent = TokenAgent::from_config(service_config).await.unwrap();
+    let summary = tokio::time::timeout(Duration::from_secs(10), agent.dry_run()).await.unwrap().unwrap();
+
+    first.assert_calls_async(1).await;
+    assert!(!dir.path().join("first.token").exists());
+    assert!(!dir.path().join("broken.token").exists());
+    assert!(!summary.is_success());
+    let sources: Vec<(&str, bool)> =
+        summary.sources.iter().map(|source| (source.source_id.as_str(), source.error.is_none())).collect();
+    assert!(sources.contains(&("first", true)) && sources.contains(&("broken", false)), "{:?}", sources);
+
+    let sink_ids: Vec<&str> = summary.sinks.iter().map(|sink| sink.sink_id.as_str()).collect();
+    assert_eq!(sink_ids, ["broken_file", "first_file", "first_http"]);
+    let first_file = &summary.sinks[1];
+    assert_eq!(first_file.sink_type, SinkType::File);
+    assert_eq!(first_file.target, dir.path().join("first.token").to_str().unwrap());
+    assert_eq!(first_file.token_id, "token");
+    assert_eq!(first_file.preview.as_deref(), Some("firs…(11 bytes)"));
+    assert!(first_file.exp_unix_ts.is_some() && first_file.skipped.is_none());
+    assert_eq!(summary.sinks[2].target, "/token/first");
+    assert_eq!(summary.sinks[0].skipped.as_deref(), Some("token broken.token is absent"));
+
+    let printed = summary.to_string();
+    assert!(printed.contains("  first: ok\n"), "{}", printed);
+    assert!(printed.contains("  broken: failed: "), "{}", printed);
+    assert!(printed.contains("would write token 'token' expiring "), "{}", printed);
+    assert!(!printed.contains("first-token"), "{}", printed);
+    TokenCache::cleanup().await;
+}
+}
@@ -4,24 +4,19 @@
 mod tests {
     use serde::Deserialize;
     use crate::cache::token_cache::TokenCache;
-    use crate::observability::service_resources_metrics::collect_process_metrics;
-    use crate::sinks::manager::SinkManager;
-    use crate::sources::builder_in_order::SourceDag;
     use crate::tests::common::{build_reqwest_client};
     use crate::utils::config_loader;
-    use crate::utils::{channel, logging};
-    use crate::{server, ServiceConfig};
+    use crate::utils::logging;
+    use crate::{ServiceConfig, TokenAgent, TokenAgentHandle};
     use anyhow::Context;
     use anyhow::{anyhow, Result};
     use chrono::{DateTime, Utc};
     use httpmock::Method::GET;
     use httpmock::MockServer;
-    use reqwest::Client;
     use serde_json::json;
     use serial_test::serial;
     use std::sync::Arc;
 
-    use tokio::sync::oneshot::{self, Receiver, Sender};
     use tokio::task;
     use tokio::time::{sleep, Duration};
 
@@ -56,19 +51,8 @@ mod tests {
         // load config
         let service_config = prepare_service_configs("examples/google_metadata_token.yaml").await?;
 
-        
-
-        // Create graceful shutdown signal
-        let (shutdown_tx, shutdown_rx): (Sender<()>, Receiver<()>) = oneshot::channel();
         // Run app
-        let app_task: task::JoinHandle<Result<()>> = task::spawn({
-            let service_config = service_config.clone();
-            let client = Client::builder().build()?;
-            async move {
-                run_app(service_config, client, shutdown_rx).await.unwrap();
-                Ok(())
-            }
-        });
+        let agent = TokenAgent::from_config((*service_config).clone()).await?.start()?;
 
         // Spawn test thread
         let test_task = task::spawn({
@@ -146,7 +130,7 @@ mod tests {
             }
         });
 
-        graceful_shutdown(shutdown_tx, app_task, test_task).await
+        graceful_shutdown(agent, test_task).await
     }
 
 
@@ -185,56 +169,13 @@ mod tests {
         Ok(service_config)
     }
 
-    pub async fn run_app(
-        service_config: Arc<ServiceConfig>,
-        client: Client,
-        mut shutdown: oneshot::Receiver<()>,
-    ) -> Result<()> {
-        let dag = SourceDag::build(&service_config.sources)?;
-        let sink_sender = channel::run();
-        let safety_margin_seconds = service_config.settings.safety_margin_seconds;
-        let retry = &service_config.settings.retry;
-
-        let receiver =
-            dag.loop_refrech_tokens(&client, retry, safety_margin_seconds, sink_sender.clone());
-        let cleaner = dag.loop_check_token_exp(
-            &service_config.sources,
-            &safety_margin_seconds,
-            sink_sender.clone(),
-        );
-        let sink_manager = SinkManager::new(service_config.sinks.clone());
-        let http_server = server::server::start(&service_config.settings, &service_config.sinks);
-        let service_metrics = collect_process_metrics(service_config.settings.metrics.is_enabled);
-        let active_sinks = sink_manager.start_active_sinks(sink_sender.clone());
-
-        tokio::select! {
-            res = async {
-                tokio::try_join!(
-                    receiver,
-                    cleaner,
-                    active_sinks,
-                    http_server,
-                    service_metrics
-                )
-            } => {
-                res?;
-            },
-            _ = &mut shutdown => {
-                tracing::info!("🧹 shutting down app gracefully");
-            }
-        }
-        Ok(())
-    }
-
-    async fn graceful_shutdown(shutdown_tx: Sender<()>, app_task: task::JoinHandle<Result<()>>, test_task: task::JoinHandle<Result<()>>) -> Result<()> {
+    async fn graceful_shutdown(agent: TokenAgentHandle, test_task: task::JoinHandle<Result<()>>) -> Result<()> {
         let test_result = tokio::time::timeout(Duration::from_secs(15), test_task).await;
         assert!(test_result.is_ok(), "Test timed out!");
 
-        // Signal graceful shutdown
-        let _ = shutdown_tx.send(());
-
-        // Wait for the app task to finish
-        let _ = app_task.await?;
+        // Signal graceful shutdown and wait for the app to finish
+        let app_result = tokio::time::timeout(Duration::from_secs(5), agent.shutdown()).await;
+        assert!(app_result.is_ok(), "App did not stop after shutdown!");
 
         match test_result.map_err(|_| anyhow!("test timed out"))? {
             Ok(_) => Ok(()),
@@ -25,6 +25,8 @@ mod test {
 
 
         tokio::time::sleep(Duration::from_secs(ttl)).await;
+        // expired tokens are dropped by the refresh loop
+        TokenCache::invalidate_expired_tokens_by_source_id("src_short").await;
         let got2 = TokenCache::get("src_short", token_context.id.as_str()).await;
 
         assert!(got2.is_none() == true);
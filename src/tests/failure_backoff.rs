@@ -0,0 +1,69 @@
+// Failure backoff in refresh loop:
+//  - a failed fetch is not repeated before `min_refresh_interval_seconds`
+//  - the wait doubles on consecutive failures, current wait is exported as `source_backoff_seconds`
+//  - a permanently failing source is attempted a bounded number of times
+
+#[cfg(test)]
+mod test {
+
+use std::{collections::HashMap, time::Duration};
+
+use httpmock::prelude::*;
+use reqwest::Client;
+use serde_json::json;
+use serial_test::serial;
+use tokio_util::sync::CancellationToken;
+
+use crate::cache::token_cache::TokenCache;
+use crate::config::settings::{CircuitBreakerConfig, RetryConfig};
+use crate::config::sources::SourceConfig;
+use crate::observability::metrics::get_metrics;
+use crate::resilience::backoff::SourceBackoff;
+use crate::sources::builder_in_order::SourceDag;
+use crate::sources::executor::token_fetch::RefreshSettings;
+use crate::tests::common::http_source;
+
+const SOURCE_ID: &str = "failure_backoff";
+
+#[tokio::test]
+#[serial]
+async fn permanently_failing_source_is_attempted_bounded_times() {
+    TokenCache::cleanup().await;
+    SourceBackoff::remove_by_source_id(SOURCE_ID).await;
+    let server = MockServer::start_async().await;
+    let not_found = server.mock(|when, then| {
+        when.method(GET).path("/missing");
+        then.status(404).json_body(json!({ "error": "not found" }));
+    });
+
+    let source = SourceConfig {
+        min_refresh_interval_seconds: Some(2),
+        // one request per refresh cycle, circuit stays closed: only failure backoff limits attempts
+        retry: Some(RetryConfig { attempts: Some(1), ..Default::default() }),
+        circuit_breaker: Some(CircuitBreakerConfig { failure_threshold: Some(1000), open_duration_seconds: None }),
+        ..http_source(server.url("/missing"), 3600)
+    };
+    let sources = HashMap::from([(SOURCE_ID.to_string(), source)]);
+    let dag = SourceDag::build(&sources).unwrap();
+    let shutdown = CancellationToken::new();
+    let refresh = tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move { dag.loop_refrech_tokens(&Client::new(), &RefreshSettings::default(), shutdown).await }
+    });
+
+    // attempts at ~0s, ~2s and ~6s, the next one is not before ~14s; without backoff the loop retries every second
+    tokio::time::sleep(Duration::from_secs(10)).await;
+    shutdown.cancel();
+    refresh.await.unwrap().unwrap();
+
+    let hits = not_found.calls_async().await;
+    assert!((2..=4).contains(&hits), "failing source fetched {} times in 10s", hits);
+    let backoff_seconds = get_metrics().await.source_backoff_seconds.with_label_values(&[SOURCE_ID]).get();
+    assert!(backoff_seconds >= 4, "backoff must grow on consecutive failures, got {}", backoff_seconds);
+    assert!(SourceBackoff::next_attempt_at(SOURCE_ID).await.is_some());
+
+    SourceBackoff::remove_by_source_id(SOURCE_ID).await;
+    TokenCache::cleanup().await;
+}
+
+}
@@ -0,0 +1,141 @@
+// Source fetch failure reasons:
+//  - 500 response is counted as `http_status` and retried
+//  - unparseable body is counted as `parse_body` and not retried
+//  - absent input token is counted as `missing_dependency`, no request is sent
+//  - 2 MiB body is counted as `response_too_large` with the default 1 MiB limit, a raised limit accepts it
+
+#[cfg(test)]
+mod test {
+
+use std::time::Duration;
+
+use httpmock::prelude::*;
+use serial_test::serial;
+
+use crate::cache::token_cache::TokenCache;
+use crate::observability::metrics::get_metrics;
+use crate::{ServiceConfig, TokenAgent};
+
+const TOKEN: &str = "{ id: token, parent: body, pointer: token, token_type: plain_text, expiration: { source: manual, format: seconds, manual_ttl_seconds: 3600 } }";
+
+fn config_yaml(base_url: &str) -> String {
+    format!(
+        r#"
+settings:
+  server:
+    host: 127.0.0.1
+    port: "8080"
+  metrics:
+    path: "/metrics"
+    is_enabled: false
+  retry: {{ attempts: 3, base_delay_ms: 1, max_delay_ms: 1 }}
+sources:
+  reason_server_error:
+    type: http
+    request: {{ url: "{base_url}/server_error", method: GET }}
+    parse: {{ tokens: [{TOKEN}] }}
+  reason_html_body:
+    type: http
+    request: {{ url: "{base_url}/html_body", method: GET }}
+    parse: {{ tokens: [{TOKEN}] }}
+  reason_missing_input:
+    type: http
+    inputs: [reason_html_body]
+    request:
+      url: "{base_url}/missing_input"
+      method: GET
+      headers:
+        Authorization: {{ template: "Bearer {{{{reason_html_body.token}}}}", required: true }}
+    parse: {{ tokens: [{TOKEN}] }}
+sinks: {{}}
+"#
+    )
+}
+
+#[tokio::test]
+#[serial]
+async fn failures_counted_by_typed_reason() {
+    TokenCache::cleanup().await;
+    let server = MockServer::start_async().await;
+    let server_error = server.mock(|when, then| {
+        when.method(GET).path("/server_error");
+        then.status(500);
+    });
+    let html_body = server.mock(|when, then| {
+        when.method(GET).path("/html_body");
+        then.status(200).body("<html>maintenance</html>");
+    });
+    let missing_input = server.mock(|when, then| {
+        when.method(GET).path("/missing_input");
+        then.status(200);
+    });
+    let failures = get_metrics().await.source_fetch_failures.clone();
+    let count = |source: &str, reason: &str| failures.with_label_values(&[source, reason]).get();
+    let before = [
+        count("reason_server_error", "http_status"),
+        count("reason_html_body", "parse_body"),
+        count("reason_missing_input", "missing_dependency"),
+    ];
+
+    let service_config: ServiceConfig = serde_yaml::from_str(&config_yaml(&server.base_url())).unwrap();
+    let agent = TokenAgent::from_config(service_config).await.unwrap();
+    let err = tokio::time::timeout(Duration::from_secs(10), agent.run_once()).await.unwrap().unwrap_err();
+
+    assert!(err.to_string().starts_with("sources failed: "), "{}", err);
+    server_error.assert_calls_async(3).await;
+    html_body.assert_calls_async(1).await;
+    missing_input.assert_calls_async(0).await;
+    assert_eq!(count("reason_server_error", "http_status"), before[0] + 1);
+    assert_eq!(count("reason_html_body", "parse_body"), before[1] + 1);
+    assert_eq!(count("reason_missing_input", "missing_dependency"), before[2] + 1);
+    assert_eq!(count("reason_server_error", "error") + count("reason_html_body", "error"), 0);
+    TokenCache::cleanup().await;
+}
+
+#[tokio::test]
+#[serial]
+async fn oversized_response_counted_as_response_too_large() {
+    TokenCache::cleanup().await;
+    let server = MockServer::start_async().await;
+    let body = serde_json::json!({ "token": "large", "padding": "a".repeat(2 * 1024 * 1024) }).to_string();
+    let large = server.mock(|when, then| {
+        when.method(GET).path("/large");
+        then.status(200).body(body);
+    });
+    let yaml = format!(
+        r#"
+settings:
+  server:
+    host: 127.0.0.1
+    port: "8080"
+  metrics:
+    path: "/metrics"
+    is_enabled: false
+  retry: {{ attempts: 3, base_delay_ms: 1, max_delay_ms: 1 }}
+sources:
+  reason_large_default:
+    type: http
+    request: {{ url: "{url}", method: GET }}
+    parse: {{ tokens: [{TOKEN}] }}
+  reason_large_allowed:
+    type: http
+    request: {{ url: "{url}", method: GET, max_response_bytes: 4194304 }}
+    parse: {{ tokens: [{TOKEN}] }}
+sinks: {{}}
+"#,
+        url = server.url("/large")
+    );
+    let failures = get_metrics().await.source_fetch_failures.clone();
+    let before = failures.with_label_values(&["reason_large_default", "response_too_large"]).get();
+
+    let agent = TokenAgent::from_config(serde_yaml::from_str(&yaml).unwrap()).await.unwrap();
+    let err = tokio::time::timeout(Duration::from_secs(10), agent.run_once()).await.unwrap().unwrap_err();
+
+    assert_eq!(err.to_string(), "sources failed: reason_large_default");
+    // not retried: the same body is expected on the next attempt
+    large.assert_calls_async(2).await;
+    assert_eq!(failures.with_label_values(&["reason_large_default", "response_too_large"]).get(), before + 1);
+    assert_eq!(TokenCache::get("reason_large_allowed", "token").await.unwrap().token.value, "large");
+    TokenCache::cleanup().await;
+}
+}
@@ -0,0 +1,153 @@
+// File sink template:
+//  - placeholders are rendered against token cache at write time
+//  - unresolvable placeholder counts a sink failure and keeps previous file content
+//  - sink with several tokens is rewritten when any of them changes
+
+#[cfg(test)]
+mod test {
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+use serial_test::serial;
+use tempfile::tempdir;
+use tokio_util::sync::CancellationToken;
+
+use crate::cache::token::Token;
+use crate::cache::token_cache::TokenCache;
+use crate::cache::token_context::TokenContext;
+use crate::cache::token_event::TokenEvent;
+use crate::config::sinks::{SinkConfig, SinkType};
+use crate::observability::metrics::get_metrics;
+use crate::sinks::manager::SinkManager;
+use crate::utils::channel;
+
+const SOURCE_ID: &str = "file_template";
+
+fn make_sink(path: &Path, template: &str) -> SinkConfig {
+    SinkConfig {
+        sink_id: "templated".into(),
+        sink_type: SinkType::File,
+        source_id: SOURCE_ID.into(),
+        path: path.to_string_lossy().into_owned(),
+        token_id: "access_token".into(),
+        response: None,
+        tokens: None,
+        method: None,
+        target_url: None,
+        mode: None,
+        owner: None,
+        group: None,
+        exec: None,
+        template: Some(template.into()),
+        atomic: None,
+        create_dirs: None,
+        auth: None,
+        nats: None,
+        redis: None,
+        kubernetes_secret: None,
+        rate_limit: None,
+        format: None,
+        type_hint: None,
+        stub_value: None,
+    }
+}
+
+async fn run_sink_once(sink: SinkConfig) {
+    let sink_manager = SinkManager::new(HashMap::from([("templated".to_string(), sink)]));
+    let sink_sender = channel::run();
+    let shutdown = CancellationToken::new();
+    // keep token files: `retained` paths are not removed on stop
+    sink_manager.retain_files_on_stop(sink_manager.sinks.values().map(|sink| sink.path.clone()).collect());
+    let worker = tokio::spawn(sink_manager.start_file_sinks(sink_sender.subscribe(), shutdown.clone()));
+    sink_sender.send(TokenEvent::updated(SOURCE_ID, TokenCache::get(SOURCE_ID, "access_token").await.unwrap())).unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    shutdown.cancel();
+    tokio::time::timeout(Duration::from_secs(5), worker).await.unwrap().unwrap().unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn json_template_rendered_with_token_and_expiration() {
+    TokenCache::cleanup().await;
+    let exp = 4_102_444_800; // 2100-01-01T00:00:00Z
+    TokenCache::set(
+        SOURCE_ID.into(),
+        vec![
+            TokenContext::new("access_token".into(), Token::new("abc.def".into(), exp), 10),
+            TokenContext::new("id_token".into(), Token::new("id-123".into(), exp), 10),
+        ],
+    )
+    .await
+    .unwrap();
+
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("config.json");
+    let template = r#"{"auths":{"registry":{"auth":"{{file_template.access_token}}","identitytoken":"{{ file_template.id_token }}"}},"expires_at":"{{expiration:rfc3339}}","exp":{{expiration:unix}}}"#;
+    run_sink_once(make_sink(&path, template)).await;
+
+    let written: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).expect("valid JSON");
+    assert_eq!(written["auths"]["registry"]["auth"], "abc.def");
+    assert_eq!(written["auths"]["registry"]["identitytoken"], "id-123");
+    assert_eq!(written["expires_at"], "2100-01-01T00:00:00Z");
+    assert_eq!(written["exp"], exp);
+    TokenCache::cleanup().await;
+}
+
+#[tokio::test]
+#[serial]
+async fn multi_token_sink_rewritten_when_any_token_changes() {
+    TokenCache::cleanup().await;
+    let set_tokens = |refresh: &'static str| async move {
+        TokenCache::set(
+            SOURCE_ID.into(),
+            vec![
+                TokenContext::new("access_token".into(), Token::new("access".into(), 4_102_444_800), 10),
+                TokenContext::new("refresh_token".into(), Token::new(refresh.into(), 4_102_444_800 + refresh.len() as u64), 10),
+            ],
+        )
+        .await
+        .unwrap();
+    };
+    set_tokens("refresh-1").await;
+
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("oauth.json");
+    let mut sink = make_sink(&path, r#"{"access_token":"{{file_template.access_token}}","refresh_token":"{{file_template.refresh_token}}"}"#);
+    sink.tokens = Some(vec!["refresh_token".into()]);
+    run_sink_once(sink.clone()).await;
+
+    let written: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).expect("valid JSON");
+    assert_eq!(written["access_token"], "access");
+    assert_eq!(written["refresh_token"], "refresh-1");
+
+    // only the additional token is refreshed
+    set_tokens("refresh-two").await;
+    run_sink_once(sink).await;
+    let written: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).expect("valid JSON");
+    assert_eq!(written["refresh_token"], "refresh-two");
+    TokenCache::cleanup().await;
+}
+
+#[tokio::test]
+#[serial]
+async fn unresolved_placeholder_keeps_previous_file() {
+    TokenCache::cleanup().await;
+    TokenCache::set(SOURCE_ID.into(), vec![TokenContext::new("access_token".into(), Token::new("abc".into(), 4_102_444_800), 10)])
+        .await
+        .unwrap();
+
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("netrc");
+    std::fs::write(&path, "previous").unwrap();
+    let failures = get_metrics().await.sink_failures.with_label_values(&["templated", "error"]);
+    let failures_before = failures.get();
+
+    run_sink_once(make_sink(&path, "machine registry login agent password {{file_template.missing}}\n")).await;
+
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "previous");
+    assert_eq!(failures.get(), failures_before + 1);
+    TokenCache::cleanup().await;
+}
+}
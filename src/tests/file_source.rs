@@ -0,0 +1,80 @@
+// File source:
+//  - token file with a JWT is read into the token cache
+//  - the file is rewritten with a new exp, the modification is detected
+//    and the next refresh cycle stores the new token
+//  - fetches are counted with source_type "file" and no method
+
+#[cfg(test)]
+mod test {
+
+use std::collections::HashMap;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Utc;
+use serde_json::json;
+use serial_test::serial;
+
+use crate::cache::token_cache::TokenCache;
+use crate::config::sources::{FileSourceConfig, SourceConfig, SourceTypes, TokenType};
+use crate::observability::metrics::get_metrics;
+use crate::sources::builder_in_order::{DagNode, SourceDag};
+use crate::sources::executor::token_fetch::RefreshContext;
+use crate::sources::file::{get_file_source_request_and_parse, invalidate_modified_file_sources};
+use crate::tests::common;
+
+fn sample_jwt(exp: u64) -> String {
+    let header = URL_SAFE_NO_PAD.encode(r#"{"alg":"none"}"#);
+    let payload = URL_SAFE_NO_PAD.encode(json!({ "exp": exp }).to_string());
+    format!("{}.{}.", header, payload)
+}
+
+fn refresh_context() -> RefreshContext {
+    RefreshContext { safety_margin_seconds_settings: Some(10), ..common::refresh_context() }
+}
+
+#[tokio::test]
+#[serial]
+async fn rewritten_token_file_is_picked_up_by_refresh() {
+    TokenCache::cleanup().await;
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sa-token");
+    let path_str = path.to_string_lossy().into_owned();
+    let exp = Utc::now().timestamp() as u64 + 3600;
+    std::fs::write(&path, format!("{}\n", sample_jwt(exp))).unwrap();
+
+    let file = FileSourceConfig { path: path_str.clone(), token_type: Some(TokenType::Jwt), ttl_seconds: None, token_id: None };
+    let (request, parse) = get_file_source_request_and_parse(&file);
+    let source = SourceConfig { source_type: SourceTypes::FILE, request, parse, file: Some(file), ..Default::default() };
+    let sources = HashMap::from([("file_sa".to_string(), source)]);
+    let layers: Vec<Vec<DagNode>> = SourceDag::build(&sources)
+        .unwrap()
+        .layers()
+        .into_iter()
+        .map(|layer| layer.into_iter().cloned().collect())
+        .collect();
+
+    SourceDag::refresh_layers(&layers, &refresh_context()).await;
+    let token_context = TokenCache::get("file_sa", "token").await.unwrap();
+    assert_eq!(token_context.token.value, sample_jwt(exp));
+    assert_eq!(token_context.token.exp_unix_ts, exp);
+    // counted as a file source, there is no HTTP method
+    let requests = get_metrics().await.source_fetch_requests.with_label_values(&["file_sa", "file", ""]).get();
+    assert_eq!(requests, 1);
+
+    // unmodified file: token is still valid and kept
+    let watched = vec![("file_sa".to_string(), path_str.clone())];
+    assert!(!invalidate_modified_file_sources(&watched).await);
+
+    // rewritten with a later exp, modification time is moved forward to be independent of fs granularity
+    std::fs::write(&path, sample_jwt(exp + 600)).unwrap();
+    let file = std::fs::File::options().write(true).open(&path).unwrap();
+    file.set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(5)).unwrap();
+    assert!(invalidate_modified_file_sources(&watched).await);
+
+    SourceDag::refresh_layers(&layers, &refresh_context()).await;
+    let token_context = TokenCache::get("file_sa", "token").await.unwrap();
+    assert_eq!(token_context.token.value, sample_jwt(exp + 600));
+    assert_eq!(token_context.token.exp_unix_ts, exp + 600);
+    TokenCache::cleanup().await;
+}
+}
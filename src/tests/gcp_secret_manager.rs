@@ -0,0 +1,85 @@
+// This test runs a single refresh cycle for an OAuth2 source with the client secret in GCP Secret Manager:
+//  - the secret version is accessed with an access token of an `authorized_user` credentials file
+//  - the decoded secret is sent as client_secret
+//  - parsed access_token is stored in TokenCache
+
+#[cfg(test)]
+mod test {
+
+use std::collections::HashMap;
+
+use base64::Engine;
+use httpmock::prelude::*;
+use serde_json::json;
+use serial_test::serial;
+
+use crate::cache::token_cache::TokenCache;
+use crate::config::sources::{FormValue, GenericSourceValue};
+use crate::sources::builder_in_order::{DagNode, SourceDag};
+use crate::tests::common::{oauth2_source, refresh_context};
+
+#[tokio::test]
+#[serial]
+async fn client_secret_is_read_from_secret_manager() {
+    let server = MockServer::start_async().await;
+    let adc_mock = server.mock(|when, then| {
+        when.method(POST)
+            .path("/adc/token")
+            .form_urlencoded_tuple("grant_type", "refresh_token")
+            .form_urlencoded_tuple("refresh_token", "user-refresh-token");
+        then.status(200).json_body(json!({ "access_token": "user-access-token", "expires_in": 3599, "token_type": "Bearer" }));
+    });
+    let secret_mock = server.mock(|when, then| {
+        when.method(GET)
+            .path("/v1/projects/demo/secrets/oauth-client-secret/versions/latest:access")
+            .header("authorization", "Bearer user-access-token");
+        then.status(200).json_body(json!({
+            "name": "projects/123/secrets/oauth-client-secret/versions/2",
+            "payload": { "data": base64::engine::general_purpose::STANDARD.encode("s3cr3t&=") }
+        }));
+    });
+    let token_mock = server.mock(|when, then| {
+        when.method(POST)
+            .path("/oauth2/token")
+            .form_urlencoded_tuple("client_id", "agent")
+            .form_urlencoded_tuple("client_secret", "s3cr3t&=");
+        then.status(200).json_body(json!({ "access_token": "idp-access-token", "token_type": "Bearer", "expires_in": 3600 }));
+    });
+    let dir = tempfile::tempdir().unwrap();
+    let credentials = dir.path().join("application_default_credentials.json");
+    let key = json!({
+        "type": "authorized_user",
+        "client_id": "gcloud-client",
+        "client_secret": "gcloud-secret",
+        "refresh_token": "user-refresh-token",
+        "token_uri": server.url("/adc/token"),
+    });
+    std::fs::write(&credentials, key.to_string()).unwrap();
+    std::env::set_var("GOOGLE_APPLICATION_CREDENTIALS", &credentials);
+    std::env::set_var("GCP_SECRET_MANAGER_ENDPOINT", server.base_url());
+
+    let form = FormValue {
+        client_id: GenericSourceValue::Literal { value: "agent".into() },
+        client_secret: Some(GenericSourceValue::FromGcpSecret { project: "demo".into(), secret: "oauth-client-secret".into(), version: None }),
+        client_assertion: None,
+        scope: GenericSourceValue::Literal { value: "api.read".into() },
+        grant_type: None,
+    };
+    let sources = HashMap::from([("gcp_idp".to_string(), oauth2_source(server.url("/oauth2/token"), form))]);
+    let dag = SourceDag::build(&sources).unwrap();
+    let layers: Vec<Vec<DagNode>> = dag
+        .layers()
+        .into_iter()
+        .map(|layer| layer.into_iter().cloned().collect())
+        .collect();
+    let refresh_context = refresh_context();
+    SourceDag::refresh_layers(&layers, &refresh_context).await;
+    std::env::remove_var("GOOGLE_APPLICATION_CREDENTIALS");
+
+    token_mock.assert_calls(1);
+    adc_mock.assert_calls(1);
+    secret_mock.assert_calls(1);
+    assert_eq!(TokenCache::get("gcp_idp", "access_token").await.unwrap().token.value, "idp-access-token");
+}
+
+}
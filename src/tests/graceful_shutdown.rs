@@ -0,0 +1,118 @@
+// Graceful shutdown:
+//  - cancelling shutdown token stops refresh, cleanup and sink loops
+//  - file sink tokens are removed as part of normal teardown
+
+#[cfg(test)]
+mod test {
+
+use std::{collections::HashMap, path::Path, time::Duration};
+
+use httpmock::prelude::*;
+use reqwest::Client;
+use serde_json::json;
+use serial_test::serial;
+use tempfile::tempdir;
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
+
+use crate::cache::token_cache::TokenCache;
+use crate::config::sinks::{SinkConfig, SinkType};
+use crate::observability::service_resources_metrics::collect_process_metrics;
+use crate::sinks::manager::SinkManager;
+use crate::sources::builder_in_order::SourceDag;
+use crate::sources::executor::token_fetch::RefreshSettings;
+use crate::utils::channel;
+use crate::tests::common::http_source;
+
+const SOURCE_ID: &str = "graceful_shutdown";
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+#[serial]
+async fn shutdown_stops_all_loops_and_cleans_file_sinks() {
+    TokenCache::cleanup().await;
+    let server = MockServer::start_async().await;
+    server.mock(|when, then| {
+        when.method(GET).path("/token");
+        then.status(200).json_body(json!({ "token": "shutdown-token" }));
+    });
+
+    let dir = tempdir().unwrap();
+    let token_path = dir.path().join("token").to_str().unwrap().to_string();
+    let sources = HashMap::from([(SOURCE_ID.to_string(), http_source(server.url("/token"), 3600))]);
+    let sinks = HashMap::from([(
+        "file_sink".to_string(),
+        SinkConfig {
+            sink_id: "file_sink".to_string(),
+            sink_type: SinkType::File,
+            source_id: SOURCE_ID.to_string(),
+            path: token_path.clone(),
+            token_id: "token".to_string(),
+            response: None,
+            tokens: None,
+            method: None,
+            target_url: None,
+            mode: None,
+            owner: None,
+            group: None,
+            exec: None,
+            template: None,
+            atomic: None,
+            create_dirs: None,
+            auth: None,
+            nats: None,
+            redis: None,
+            kubernetes_secret: None,
+            rate_limit: None,
+            format: None,
+            type_hint: None,
+            stub_value: None,
+        },
+    )]);
+
+    let shutdown = CancellationToken::new();
+    let sink_sender = channel::run();
+    let mut tasks = JoinSet::new();
+    tasks.spawn({
+        let sink_manager = SinkManager::new(sinks);
+        let (sink_sender, shutdown) = (sink_sender.clone(), shutdown.clone());
+        async move { sink_manager.start_active_sinks(sink_sender, shutdown).await }
+    });
+    tasks.spawn({
+        let (sources, shutdown) = (sources.clone(), shutdown.clone());
+        async move {
+            let dag = SourceDag::build(&sources)?;
+            dag.loop_refrech_tokens(&Client::new(), &RefreshSettings::default(), shutdown).await
+        }
+    });
+    tasks.spawn({
+        let shutdown = shutdown.clone();
+        async move {
+            let dag = SourceDag::build(&sources)?;
+            dag.loop_check_token_exp(&sources, &None, shutdown).await
+        }
+    });
+    tasks.spawn(collect_process_metrics(true, shutdown.clone()));
+
+    // wait for file sink propagation
+    let written = tokio::time::timeout(Duration::from_secs(5), async {
+        while tokio::fs::read_to_string(&token_path).await.ok().as_deref() != Some("shutdown-token") {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await;
+    assert!(written.is_ok(), "file sink must receive token");
+
+    shutdown.cancel();
+    let stopped = tokio::time::timeout(Duration::from_secs(5), async {
+        while let Some(res) = tasks.join_next().await {
+            res.expect("task panicked").expect("task failed");
+        }
+    })
+    .await;
+    assert!(stopped.is_ok(), "all tasks must finish after shutdown");
+    assert!(!Path::new(&token_path).exists(), "file sink must be cleaned up on shutdown");
+
+    TokenCache::cleanup().await;
+}
+
+}
@@ -0,0 +1,133 @@
+// Health and readiness probes:
+//  - liveness answers `{"status":"ok"}` on configured `health_path` and `/healthz`
+//  - readiness flips with unexpired tokens of required sources in cache
+//  - readiness defaults to all configured sources
+
+#[cfg(test)]
+mod test {
+
+use std::collections::HashMap;
+
+use axum::http::StatusCode;
+use serde_json::Value;
+use serial_test::serial;
+
+use crate::cache::token::Token;
+use crate::cache::token_cache::TokenCache;
+use crate::cache::token_context::TokenContext;
+use crate::config::proc_initiateor::initiate_default_values;
+use crate::config::settings::{ReadinessConfig, ServerConfig};
+use crate::config::sources::ServiceConfig;
+use crate::observability::metrics::get_metrics;
+use crate::server::health::{HEALTHZ_PATH, READYZ_PATH};
+use crate::server::server::AppState;
+use crate::tests::common::{build_reqwest_client, json, spawn_axum, JoinHandle, Router};
+
+fn server_config(health_path: &str, ready_path: &str) -> ServerConfig {
+    serde_json::from_value(json!({ "host": "127.0.0.1", "port": "0", "health_path": health_path, "ready_path": ready_path })).unwrap()
+}
+
+async fn spawn_probes(server: &ServerConfig, readiness: &Option<ReadinessConfig>) -> (JoinHandle<()>, String) {
+    let app_state = AppState::new(get_metrics().await, &HashMap::new(), &None).with_health(server, readiness);
+    let app: Router = app_state.health_state.router().await.with_state(app_state);
+    let (handle, addr) = spawn_axum(app).await;
+    (handle, format!("http://{}", addr))
+}
+
+async fn set_token(source_id: &str, exp_unix_ts: u64) {
+    TokenCache::set(source_id.to_string(), vec![TokenContext::new("token".to_string(), Token::new(format!("{}-token", source_id), exp_unix_ts), 10)])
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+#[serial]
+async fn health_is_ok_on_configured_path_and_alias() {
+    let (handle, base) = spawn_probes(&server_config("/live", "/ready"), &None).await;
+    let client = build_reqwest_client();
+    for path in ["/live", HEALTHZ_PATH] {
+        let response = client.get(format!("{}{}", base, path)).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.json::<Value>().await.unwrap(), json!({ "status": "ok" }));
+    }
+    assert_eq!(client.get(format!("{}/health", base)).send().await.unwrap().status(), StatusCode::NOT_FOUND);
+    handle.abort();
+}
+
+#[tokio::test]
+#[serial]
+async fn ready_follows_required_source_tokens() {
+    TokenCache::cleanup().await;
+    let readiness = Some(ReadinessConfig { required_sources: vec!["ready_a".to_string(), "ready_b".to_string()] });
+    let (handle, base) = spawn_probes(&server_config("/health", "/ready"), &readiness).await;
+    let client = build_reqwest_client();
+    let ready = |path: &str| client.get(format!("{}{}", base, path)).send();
+
+    let response = ready("/ready").await.unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.json::<Value>().await.unwrap(), json!({ "status": "not_ready", "missing": ["ready_a", "ready_b"] }));
+
+    let exp_unix_ts = chrono::Utc::now().timestamp() as u64 + 3600;
+    set_token("ready_a", exp_unix_ts).await;
+    // expired token does not count
+    set_token("ready_b", 1).await;
+    let response = ready(READYZ_PATH).await.unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.json::<Value>().await.unwrap()["missing"], json!(["ready_b"]));
+
+    set_token("ready_b", exp_unix_ts).await;
+    let response = ready("/ready").await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.text().await.unwrap();
+    assert!(!body.contains("-token"), "{}", body);
+    assert_eq!(serde_json::from_str::<Value>(&body).unwrap(), json!({ "status": "ready", "missing": [] }));
+
+    // removed source makes agent unready again
+    TokenCache::remove_by_source_id("ready_a").await;
+    let response = ready("/ready").await.unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.json::<Value>().await.unwrap()["missing"], json!(["ready_a"]));
+
+    handle.abort();
+    TokenCache::cleanup().await;
+}
+
+#[tokio::test]
+#[serial]
+async fn ready_requires_all_sources_by_default() {
+    TokenCache::cleanup().await;
+    let yaml = r#"
+settings:
+  server:
+    host: 127.0.0.1
+    port: 8080
+  metrics:
+    path: "/metrics"
+sources:
+  second:
+    type: http
+    request: { url: "http://localhost/second", method: GET }
+    parse: { tokens: [] }
+  first:
+    type: http
+    request: { url: "http://localhost/first", method: GET }
+    parse: { tokens: [] }
+sinks: {}
+"#;
+    let cfg = initiate_default_values(serde_yaml::from_str::<ServiceConfig>(yaml).unwrap());
+    assert_eq!(cfg.settings.server.health_path, "/health");
+    assert_eq!(cfg.settings.server.ready_path, "/ready");
+    let (handle, base) = spawn_probes(&cfg.settings.server, &cfg.settings.readiness).await;
+
+    let response = build_reqwest_client().get(format!("{}/ready", base)).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.json::<Value>().await.unwrap()["missing"], json!(["first", "second"]));
+
+    set_token("first", chrono::Utc::now().timestamp() as u64 + 3600).await;
+    set_token("second", chrono::Utc::now().timestamp() as u64 + 3600).await;
+    assert_eq!(build_reqwest_client().get(format!("{}/ready", base)).send().await.unwrap().status(), StatusCode::OK);
+
+    handle.abort();
+    TokenCache::cleanup().await;
+}
+}
@@ -0,0 +1,160 @@
+// HTTP push sink:
+//  - every refreshed token is pushed to the webhook with templated body and rendered headers
+//  - failing webhook is retried and counted as sink failure
+//  - sink lagging behind token events pushes the cached token
+
+#[cfg(test)]
+mod test {
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use httpmock::prelude::*;
+use serde_json::json;
+use serial_test::serial;
+use tokio_util::sync::CancellationToken;
+
+use crate::cache::token_cache::TokenCache;
+use crate::cache::token_event::TokenEvent;
+use crate::config::sinks::{HttpSinkMethod, SinkConfig, SinkType};
+use crate::observability::metrics::get_metrics;
+use crate::resilience::retry::RetrySettings;
+use crate::sinks::manager::SinkManager;
+use crate::tests::common::{overflow_token_events, set_access_token, sink_config, start_active_sinks};
+use crate::utils::channel;
+
+const SOURCE_ID: &str = "http_push";
+
+fn make_sink(url: String) -> SinkConfig {
+    sink_config(
+        "http_push",
+        "webhook",
+        SOURCE_ID,
+        json!({
+            "url": url,
+            "response": {
+                "headers": { "Authorization": { "type": "string", "value": "Bearer webhook-secret" } }
+            },
+            "template": r#"{"secret":"{{http_push.access_token}}","expires_at":"{{expiration:rfc3339}}"}"#
+        }),
+    )
+}
+
+async fn wait_calls(mock: &httpmock::Mock<'_>, calls: usize) {
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while mock.calls_async().await < calls {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("webhook called");
+}
+
+#[tokio::test]
+#[serial]
+async fn refreshed_token_pushed_with_templated_body() {
+    TokenCache::cleanup().await;
+    let server = MockServer::start_async().await;
+    let cfg = make_sink(server.url("/secrets/api"));
+    assert_eq!(cfg.sink_type, SinkType::HttpPush);
+    assert_eq!(cfg.push_method(), HttpSinkMethod::Post);
+
+    let (worker, sink_sender, shutdown) = start_active_sinks(HashMap::from([("webhook".to_string(), cfg)]), 2);
+    // let sink workers subscribe
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let first = server.mock(|when, then| {
+        when.method(POST)
+            .path("/secrets/api")
+            .header("authorization", "Bearer webhook-secret")
+            .header("content-type", "application/json")
+            .json_body(json!({ "secret": "first", "expires_at": "2100-01-01T00:00:00Z" }));
+        then.status(200);
+    });
+    set_access_token(SOURCE_ID, "first", 4_102_444_800).await;
+    sink_sender.send(TokenEvent::updated(SOURCE_ID, TokenCache::get(SOURCE_ID, "access_token").await.unwrap())).unwrap();
+    wait_calls(&first, 1).await;
+
+    // refresh
+    let second = server.mock(|when, then| {
+        when.method(POST)
+            .path("/secrets/api")
+            .json_body(json!({ "secret": "second", "expires_at": "2100-01-01T01:00:00Z" }));
+        then.status(200);
+    });
+    set_access_token(SOURCE_ID, "second", 4_102_448_400).await;
+    sink_sender.send(TokenEvent::updated(SOURCE_ID, TokenCache::get(SOURCE_ID, "access_token").await.unwrap())).unwrap();
+    wait_calls(&second, 1).await;
+    first.assert_calls_async(1).await;
+
+    shutdown.cancel();
+    tokio::time::timeout(Duration::from_secs(5), worker).await.unwrap().unwrap().unwrap();
+    TokenCache::cleanup().await;
+}
+
+#[tokio::test]
+#[serial]
+async fn failing_webhook_retried_and_counted() {
+    TokenCache::cleanup().await;
+    let server = MockServer::start_async().await;
+    let failing = server.mock(|when, then| {
+        when.method(POST).path("/secrets/api");
+        then.status(500);
+    });
+    let failures = get_metrics().await.sink_failures.with_label_values(&["webhook", "error"]);
+    let failures_before = failures.get();
+
+    let retry = RetrySettings { attempts: 3, base_delay_ms: 10, max_delay_ms: 10, ..Default::default() };
+    let sink_manager = SinkManager::new(HashMap::from([("webhook".to_string(), make_sink(server.url("/secrets/api")))]))
+        .with_http_push(reqwest::Client::new(), retry);
+    let sink_sender = channel::run();
+    let shutdown = CancellationToken::new();
+    let worker = tokio::spawn(sink_manager.start_http_push_sinks(sink_sender.subscribe(), shutdown.clone()));
+
+    set_access_token(SOURCE_ID, "token", 4_102_444_800).await;
+    sink_sender.send(TokenEvent::updated(SOURCE_ID, TokenCache::get(SOURCE_ID, "access_token").await.unwrap())).unwrap();
+    wait_calls(&failing, 3).await;
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while failures.get() == failures_before {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("sink failure counted");
+    assert_eq!(failures.get(), failures_before + 1);
+
+    shutdown.cancel();
+    tokio::time::timeout(Duration::from_secs(5), worker).await.unwrap().unwrap().unwrap();
+    TokenCache::cleanup().await;
+}
+
+#[tokio::test]
+#[serial]
+async fn lagging_sink_pushes_cached_token() {
+    TokenCache::cleanup().await;
+    let server = MockServer::start_async().await;
+    let pushed = server.mock(|when, then| {
+        when.method(POST)
+            .path("/secrets/api")
+            .json_body(json!({ "secret": "cached", "expires_at": "2100-01-01T00:00:00Z" }));
+        then.status(200);
+    });
+    let sink_manager = SinkManager::new(HashMap::from([("webhook".to_string(), make_sink(server.url("/secrets/api")))]))
+        .with_http_push(reqwest::Client::new(), RetrySettings::default());
+    let sink_sender = channel::run();
+    let sink_receiver = sink_sender.subscribe();
+
+    // the sink token event is pushed out of the channel by events of another source before the sink reads it
+    set_access_token(SOURCE_ID, "cached", 4_102_444_800).await;
+    sink_sender.send(TokenEvent::updated(SOURCE_ID, TokenCache::get(SOURCE_ID, "access_token").await.unwrap())).unwrap();
+    overflow_token_events(&sink_sender);
+
+    let shutdown = CancellationToken::new();
+    let worker = tokio::spawn(sink_manager.start_http_push_sinks(sink_receiver, shutdown.clone()));
+    wait_calls(&pushed, 1).await;
+
+    shutdown.cancel();
+    tokio::time::timeout(Duration::from_secs(5), worker).await.unwrap().unwrap().unwrap();
+    TokenCache::cleanup().await;
+}
+}
@@ -0,0 +1,89 @@
+// Refresh on input change:
+//  - parent token with short TTL is rotated on the second cycle
+//  - child with `refresh_on_input_change` is fetched again in the same cycle with the new parent token
+//  - child without the option keeps its token until its own expiration
+
+#[cfg(test)]
+mod test {
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use httpmock::prelude::*;
+use serde_json::json;
+use serial_test::serial;
+
+use crate::cache::token_cache::TokenCache;
+use crate::config::sources::{GenericSourceValue, SourceConfig};
+use crate::sources::builder_in_order::{DagNode, SourceDag};
+use crate::sources::executor::token_fetch::RefreshContext;
+use crate::tests::common::{self, http_source};
+
+fn refresh_context() -> RefreshContext {
+    RefreshContext { safety_margin_seconds_settings: Some(0), ..common::refresh_context() }
+}
+
+async fn token_value(source_id: &str) -> String {
+    TokenCache::get(source_id, "token").await.unwrap().token.value
+}
+
+#[tokio::test]
+#[serial]
+async fn child_is_fetched_again_when_parent_token_rotates() {
+    TokenCache::cleanup().await;
+    let server = MockServer::start_async().await;
+    let parent = server.mock(|when, then| {
+        when.method(GET).path("/parent");
+        then.status(200).json_body(json!({ "token": "parent-1" }));
+    });
+    for (path, child) in [("/child", "child"), ("/static_child", "static")] {
+        for generation in ["1", "2"] {
+            server.mock(|when, then| {
+                when.method(GET).path(path).header("Authorization", format!("Bearer parent-{}", generation));
+                then.status(200).json_body(json!({ "token": format!("{}-{}", child, generation) }));
+            });
+        }
+    }
+    let parent_header = HashMap::from([(
+        "Authorization".to_string(),
+        GenericSourceValue::Template { template: "Bearer {{rotating_parent.token}}".into(), required: false },
+    )]);
+    let mut child = SourceConfig {
+        inputs: Some(vec!["rotating_parent".into()]),
+        refresh_on_input_change: Some(true),
+        ..http_source(server.url("/child"), 3600)
+    };
+    child.request.headers = Some(parent_header.clone());
+    let mut static_child = SourceConfig { inputs: Some(vec!["rotating_parent".into()]), ..http_source(server.url("/static_child"), 3600) };
+    static_child.request.headers = Some(parent_header);
+    let sources = HashMap::from([
+        ("rotating_parent".to_string(), http_source(server.url("/parent"), 1)),
+        ("child".to_string(), child),
+        ("static_child".to_string(), static_child),
+    ]);
+    let layers: Vec<Vec<DagNode>> = SourceDag::build(&sources)
+        .unwrap()
+        .layers()
+        .into_iter()
+        .map(|layer| layer.into_iter().cloned().collect())
+        .collect();
+
+    SourceDag::refresh_layers(&layers, &refresh_context()).await;
+    assert_eq!(token_value("child").await, "child-1");
+    assert_eq!(token_value("static_child").await, "static-1");
+
+    // parent token expires, next cycle fetches the rotated one
+    parent.delete_async().await;
+    server.mock(|when, then| {
+        when.method(GET).path("/parent");
+        then.status(200).json_body(json!({ "token": "parent-2" }));
+    });
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    SourceDag::refresh_layers(&layers, &refresh_context()).await;
+
+    assert_eq!(token_value("rotating_parent").await, "parent-2");
+    assert_eq!(token_value("child").await, "child-2");
+    assert_eq!(token_value("static_child").await, "static-1");
+    TokenCache::cleanup().await;
+}
+}
@@ -0,0 +1,117 @@
+// JWT claims:
+//  - claims listed in `extract_claims` are rendered into a dependent source request by `{{source.token_id.claim}}`
+//  - claim absent in the token fails the dependent source
+
+#[cfg(test)]
+mod test {
+
+use std::time::Duration;
+
+use base64::{engine::general_purpose::STANDARD_NO_PAD, Engine};
+use chrono::Utc;
+use httpmock::prelude::*;
+use serde_json::json;
+use serial_test::serial;
+
+use crate::cache::token_cache::TokenCache;
+use crate::{ServiceConfig, TokenAgent};
+
+fn make_jwt(claims: serde_json::Value) -> String {
+    let header = STANDARD_NO_PAD.encode(r#"{"alg":"none"}"#);
+    format!("{}.{}.", header, STANDARD_NO_PAD.encode(claims.to_string()))
+}
+
+fn config_yaml(base_url: &str, dir: &str) -> String {
+    format!(
+        r#"
+settings:
+  server:
+    host: 127.0.0.1
+    port: "8080"
+  metrics:
+    path: "/metrics"
+    is_enabled: false
+  retry: {{ attempts: 1, base_delay_ms: 1, max_delay_ms: 1 }}
+sources:
+  idp:
+    type: http
+    request: {{ url: "{base_url}/idp", method: GET }}
+    parse:
+      tokens:
+        - {{ id: access_token, parent: body, pointer: access_token, token_type: jwt, extract_claims: [scope, tenant] }}
+  api:
+    type: http
+    inputs: [idp]
+    request:
+      url: "{base_url}/api"
+      method: GET
+      headers:
+        X-Scope: {{ template: "{{{{idp.access_token.scope}}}}", required: true }}
+        X-Tenant: {{ template: "tenant-{{{{idp.access_token.tenant}}}}", required: true }}
+    parse:
+      tokens:
+        - {{ id: token, parent: body, pointer: token, token_type: plain_text, expiration: {{ source: manual, format: seconds, manual_ttl_seconds: 3600 }} }}
+sinks:
+  api_file:
+    type: file
+    source_id: api
+    token_id: token
+    path: "{dir}/api.token"
+"#
+    )
+}
+
+async fn run_once(base_url: &str, dir: &str) -> anyhow::Result<()> {
+    let service_config: ServiceConfig = serde_yaml::from_str(&config_yaml(base_url, dir)).unwrap();
+    let agent = TokenAgent::from_config(service_config).await.unwrap();
+    tokio::time::timeout(Duration::from_secs(10), agent.run_once()).await.expect("oneshot must return")
+}
+
+#[tokio::test]
+#[serial]
+async fn claims_rendered_into_dependent_source_headers() {
+    TokenCache::cleanup().await;
+    let exp = Utc::now().timestamp() as u64 + 3600;
+    let server = MockServer::start_async().await;
+    server.mock(|when, then| {
+        when.method(GET).path("/idp");
+        then.status(200).json_body(json!({ "access_token": make_jwt(json!({ "exp": exp, "scope": "read write", "tenant": 7 })) }));
+    });
+    let api = server.mock(|when, then| {
+        when.method(GET).path("/api").header("X-Scope", "read write").header("X-Tenant", "tenant-7");
+        then.status(200).json_body(json!({ "token": "api-token" }));
+    });
+    let dir = tempfile::tempdir().unwrap();
+
+    run_once(&server.base_url(), dir.path().to_str().unwrap()).await.unwrap();
+
+    api.assert_calls_async(1).await;
+    let idp_token = TokenCache::get("idp", "access_token").await.unwrap();
+    assert_eq!(idp_token.claims.unwrap()["scope"], json!("read write"));
+    assert_eq!(std::fs::read_to_string(dir.path().join("api.token")).unwrap(), "api-token");
+    TokenCache::cleanup().await;
+}
+
+#[tokio::test]
+#[serial]
+async fn absent_claim_fails_dependent_source() {
+    TokenCache::cleanup().await;
+    let exp = Utc::now().timestamp() as u64 + 3600;
+    let server = MockServer::start_async().await;
+    server.mock(|when, then| {
+        when.method(GET).path("/idp");
+        then.status(200).json_body(json!({ "access_token": make_jwt(json!({ "exp": exp, "scope": "read" })) }));
+    });
+    let api = server.mock(|when, then| {
+        when.method(GET).path("/api");
+        then.status(200).json_body(json!({ "token": "api-token" }));
+    });
+    let dir = tempfile::tempdir().unwrap();
+
+    let err = run_once(&server.base_url(), dir.path().to_str().unwrap()).await.unwrap_err();
+
+    assert_eq!(err.to_string(), "sources failed: api");
+    api.assert_calls_async(0).await;
+    TokenCache::cleanup().await;
+}
+}
@@ -0,0 +1,117 @@
+// Kubernetes Secret sink against a mock API server:
+//  - changed token is patched into the Secret data key base64 encoded, with strategic merge patch and kubeconfig token
+//  - unchanged token is not patched again
+//  - removed token removes the data key
+
+#[cfg(test)]
+mod test {
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::Utc;
+use httpmock::prelude::*;
+use serde_json::json;
+use serial_test::serial;
+
+use crate::cache::token_cache::TokenCache;
+use crate::config::sinks::{SinkConfig, SinkType};
+use crate::observability::metrics::get_metrics;
+use crate::sinks::sink_k8s_secret::STRATEGIC_MERGE_PATCH_CONTENT_TYPE;
+use crate::tests::common::{set_access_token, sink_config, start_active_sinks};
+
+const SOURCE_ID: &str = "k8s_secret_source";
+const SECRET_PATH: &str = "/api/v1/namespaces/apps/secrets/api-credentials";
+
+fn write_kubeconfig(dir: &tempfile::TempDir, server: &str) -> String {
+    let path = dir.path().join("kubeconfig");
+    std::fs::write(
+        &path,
+        format!(
+            r#"
+apiVersion: v1
+kind: Config
+current-context: test
+contexts:
+  - name: test
+    context: {{ cluster: mock, user: agent }}
+clusters:
+  - name: mock
+    cluster: {{ server: "{}" }}
+users:
+  - name: agent
+    user: {{ token: agent-token }}
+"#,
+            server
+        ),
+    )
+    .unwrap();
+    path.to_string_lossy().into_owned()
+}
+
+fn make_sink(kubeconfig: &str) -> SinkConfig {
+    sink_config(
+        "kubernetes_secret",
+        "k8s_secret",
+        SOURCE_ID,
+        json!({ "kubernetes_secret": { "namespace": "apps", "secret_name": "api-credentials", "key": "token", "kubeconfig": kubeconfig } }),
+    )
+}
+
+async fn wait_for_calls(mock: &httpmock::Mock<'_>, calls: usize) {
+    let reached = tokio::time::timeout(Duration::from_secs(5), async {
+        while mock.calls_async().await < calls {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await;
+    assert!(reached.is_ok(), "expected {} patches, got {}", calls, mock.calls_async().await);
+}
+
+#[tokio::test]
+#[serial]
+async fn token_patched_into_secret_key_and_removed() {
+    TokenCache::cleanup().await;
+    let server = MockServer::start_async().await;
+    let patch = |value: serde_json::Value| {
+        server.mock(move |when, then| {
+            when.method(PATCH)
+                .path(SECRET_PATH)
+                .header("content-type", STRATEGIC_MERGE_PATCH_CONTENT_TYPE)
+                .header("authorization", "Bearer agent-token")
+                .json_body(json!({ "data": { "token": value } }));
+            then.status(200).json_body(json!({ "kind": "Secret" }));
+        })
+    };
+    let first = patch(json!("Zmlyc3Q="));
+    let second = patch(json!("c2Vjb25k"));
+    let removed = patch(json!(null));
+
+    let dir = tempfile::tempdir().unwrap();
+    let sink = make_sink(&write_kubeconfig(&dir, &server.base_url()));
+    assert_eq!(sink.sink_type, SinkType::KubernetesSecret);
+    let patches = get_metrics().await.sink_kubernetes_secret_patches.clone();
+    let set_before = patches.with_label_values(&["k8s_secret", "set", "ok"]).get();
+    let (worker, _, shutdown) = start_active_sinks(HashMap::from([("k8s_secret".to_string(), sink)]), 2);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let exp = Utc::now().timestamp() as u64 + 3600;
+
+    set_access_token(SOURCE_ID, "first", exp).await;
+    wait_for_calls(&first, 1).await;
+    // same value stored again is not patched
+    set_access_token(SOURCE_ID, "first", exp).await;
+    set_access_token(SOURCE_ID, "second", exp).await;
+    wait_for_calls(&second, 1).await;
+    first.assert_calls_async(1).await;
+    assert_eq!(patches.with_label_values(&["k8s_secret", "set", "ok"]).get(), set_before + 2);
+
+    TokenCache::remove_by_source_id(SOURCE_ID).await;
+    wait_for_calls(&removed, 1).await;
+
+    shutdown.cancel();
+    worker.await.unwrap().unwrap();
+    TokenCache::cleanup().await;
+}
+
+}
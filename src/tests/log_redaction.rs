@@ -0,0 +1,84 @@
+// Token values never reach logs:
+//  - a fetch cycle logged at trace level contains the token fingerprint at most, never the value
+//  - debug print of the whole token cache is redacted as well
+
+#[cfg(test)]
+mod test {
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use httpmock::prelude::*;
+use serial_test::serial;
+
+use crate::cache::token_cache::TokenCache;
+use crate::TokenAgent;
+
+#[derive(Clone, Default)]
+struct LogBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl std::io::Write for LogBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+// current thread runtime: spawned tasks log through the thread local subscriber
+#[tokio::test]
+#[serial]
+async fn fetch_cycle_logs_do_not_contain_token_value() {
+    const SECRET: &str = "redaction-secret-4f1c9a";
+    TokenCache::cleanup().await;
+    let server = MockServer::start_async().await;
+    server.mock(|when, then| {
+        when.method(GET).path("/token");
+        then.status(200).json_body(serde_json::json!({ "token": SECRET }));
+    });
+    let yaml = format!(
+        r#"
+settings:
+  server:
+    host: 127.0.0.1
+    port: "8080"
+  metrics:
+    path: "/metrics"
+    is_enabled: false
+sources:
+  redacted_source:
+    type: http
+    request: {{ url: "{url}", method: GET }}
+    parse:
+      tokens:
+        - {{ id: token, parent: body, pointer: token, token_type: plain_text, expiration: {{ source: manual, format: seconds, manual_ttl_seconds: 3600 }} }}
+sinks: {{}}
+"#,
+        url = server.url("/token")
+    );
+
+    let logs = LogBuffer::default();
+    let writer = logs.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_ansi(false)
+        .with_max_level(tracing::Level::TRACE)
+        .with_writer(move || writer.clone())
+        .finish();
+    let guard = tracing::subscriber::set_default(subscriber);
+    let agent = TokenAgent::from_config(serde_yaml::from_str(&yaml).unwrap()).await.unwrap();
+    tokio::time::timeout(Duration::from_secs(10), agent.run_once()).await.unwrap().unwrap();
+    TokenCache::println().await;
+    drop(guard);
+
+    let token_context = TokenCache::get("redacted_source", "token").await.unwrap();
+    assert_eq!(token_context.token.value, SECRET);
+    let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+    assert!(logs.contains("token cache:"), "{}", logs);
+    assert!(logs.contains(&token_context.token.fingerprint()), "{}", logs);
+    assert!(!logs.contains(SECRET), "{}", logs);
+    TokenCache::cleanup().await;
+}
+}
@@ -2,6 +2,42 @@ pub mod common;
 pub mod atomic_file_propogation;
 pub mod expiration_and_cache;
 pub mod chained_fetch_and_retry;
+pub mod parallel_dag_refresh;
+pub mod source_tls_client;
+pub mod source_mtls;
+pub mod per_source_retry;
+pub mod retry_after;
+pub mod circuit_breaker_refresh;
+pub mod graceful_shutdown;
+pub mod config_reload;
+pub mod file_sink_template;
+pub mod http_push_sink;
+pub mod health_endpoints;
+pub mod nats_sink;
+pub mod redis_sink;
+pub mod source_timeouts;
+pub mod token_agent;
+pub mod token_events;
+pub mod oneshot;
+pub mod jwt_claims;
+pub mod dry_run;
+pub mod sink_check;
+pub mod fetch_failure_reasons;
+pub mod refresh_scheduling;
+pub mod input_change_refresh;
+pub mod oauth2_client_credentials;
+pub mod private_key_jwt;
+pub mod file_source;
+pub mod refresh_latency_metrics;
+pub mod gcp_secret_manager;
+pub mod log_redaction;
+pub mod failure_backoff;
+pub mod conditional_refresh;
+pub mod token_lifetime_metrics;
+pub mod k8s_secret_sink;
+#[cfg(feature = "otel")]
+pub mod refresh_tracing;
+pub mod stale_token_fallback;
 
 // examples configs tests
 pub mod examples;
\ No newline at end of file
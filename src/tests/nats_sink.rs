@@ -0,0 +1,207 @@
+// NATS sink:
+//  - changed token is published to the subject as raw value or rendered `response` with headers
+//  - unchanged token is not published again
+//  - dropped connection is re-established and the next token is delivered
+//  - sink lagging behind token events publishes the cached token
+
+#[cfg(test)]
+mod test {
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+use serial_test::serial;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use crate::cache::token_cache::TokenCache;
+use crate::cache::token_event::TokenEvent;
+use crate::config::sinks::SinkType;
+use crate::observability::metrics::get_metrics;
+use crate::sinks::manager::SinkManager;
+use crate::tests::common::{overflow_token_events, set_access_token, sink_config, start_active_sinks, JoinHandle};
+use crate::utils::channel;
+
+const SOURCE_ID: &str = "nats_source";
+const INFO: &str = "INFO {\"server_id\":\"fake\",\"server_name\":\"fake\",\"version\":\"2.10.0\",\"proto\":1,\"headers\":true,\"max_payload\":1048576}\r\n";
+
+/// Message received by fake server: subject, raw header block, payload
+#[derive(Debug)]
+struct Published {
+    subject: String,
+    headers: String,
+    payload: String,
+}
+
+/// Minimal NATS server: answers PING, reports PUB/HPUB messages,
+/// first connection is closed after its first message when `drop_after_publish` is set
+async fn spawn_fake_nats(drop_after_publish: bool) -> (JoinHandle<()>, String, mpsc::UnboundedReceiver<Published>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("nats://{}", listener.local_addr().unwrap());
+    let (tx, rx) = mpsc::unbounded_channel();
+    let handle = tokio::spawn(async move {
+        let mut drop_connection = drop_after_publish;
+        loop {
+            let Ok((stream, _)) = listener.accept().await else { return };
+            tokio::spawn(serve_connection(stream, tx.clone(), drop_connection));
+            drop_connection = false;
+        }
+    });
+    (handle, url, rx)
+}
+
+async fn serve_connection(stream: TcpStream, tx: mpsc::UnboundedSender<Published>, drop_after_publish: bool) {
+    let (read, mut write) = stream.into_split();
+    let mut reader = BufReader::new(read);
+    if write.write_all(INFO.as_bytes()).await.is_err() {
+        return;
+    }
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).await.unwrap_or(0) == 0 {
+            return;
+        }
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        match parts.first().copied() {
+            Some("PING") => {
+                if write.write_all(b"PONG\r\n").await.is_err() {
+                    return;
+                }
+            }
+            Some(op @ ("PUB" | "HPUB")) => {
+                let total: usize = parts.last().unwrap().parse().unwrap();
+                let header_len: usize = if op == "HPUB" { parts[parts.len() - 2].parse().unwrap() } else { 0 };
+                let mut message = vec![0u8; total + 2];
+                if reader.read_exact(&mut message).await.is_err() {
+                    return;
+                }
+                let message = String::from_utf8(message).unwrap();
+                let _ = tx.send(Published {
+                    subject: parts[1].to_string(),
+                    headers: message[..header_len].to_string(),
+                    payload: message[header_len..total].to_string(),
+                });
+                if drop_after_publish {
+                    return;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+async fn next_published(rx: &mut mpsc::UnboundedReceiver<Published>) -> Published {
+    tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.expect("message published").unwrap()
+}
+
+#[tokio::test]
+#[serial]
+async fn changed_token_published_raw_and_rendered() {
+    TokenCache::cleanup().await;
+    let (server, url, mut rx) = spawn_fake_nats(false).await;
+    let raw = sink_config("nats", "nats_raw", SOURCE_ID, json!({ "nats": { "nats_url": url, "subject": "tokens.raw" } }));
+    assert_eq!(raw.sink_type, SinkType::Nats);
+    let rendered = sink_config(
+        "nats",
+        "nats_rendered",
+        SOURCE_ID,
+        json!({
+            "nats": { "nats_url": url, "subject": "tokens.rendered" },
+            "response": {
+                "content_type": "application/json",
+                "headers": { "X-Token-Source": { "type": "string", "value": "agent" } },
+                "body": {
+                    "access_token": { "type": "token", "id": "access_token" },
+                    "expires_at": { "type": "expiration", "id": "access_token", "format": "rfc3339" }
+                }
+            }
+        }),
+    );
+    let published_before = get_metrics().await.sink_nats_published.with_label_values(&["nats_raw"]).get();
+    let (worker, sink_sender, shutdown) =
+        start_active_sinks(HashMap::from([("nats_raw".to_string(), raw), ("nats_rendered".to_string(), rendered)]), 1);
+    // let sink workers subscribe
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    set_access_token(SOURCE_ID, "first", 4_102_444_800).await;
+    sink_sender.send(TokenEvent::updated(SOURCE_ID, TokenCache::get(SOURCE_ID, "access_token").await.unwrap())).unwrap();
+    let mut messages = HashMap::new();
+    for _ in 0..2 {
+        let message = next_published(&mut rx).await;
+        messages.insert(message.subject.clone(), message);
+    }
+    assert_eq!(messages["tokens.raw"].payload, "first");
+    let rendered = &messages["tokens.rendered"];
+    assert_eq!(
+        serde_json::from_str::<Value>(&rendered.payload).unwrap(),
+        json!({ "access_token": "first", "expires_at": "2100-01-01T00:00:00+00:00" })
+    );
+    assert!(rendered.headers.contains("Content-Type: application/json"), "{}", rendered.headers);
+    assert!(rendered.headers.contains("X-Token-Source: agent"), "{}", rendered.headers);
+    assert_eq!(get_metrics().await.sink_nats_published.with_label_values(&["nats_raw"]).get(), published_before + 1);
+
+    // unchanged token is skipped
+    sink_sender.send(TokenEvent::updated(SOURCE_ID, TokenCache::get(SOURCE_ID, "access_token").await.unwrap())).unwrap();
+    assert!(tokio::time::timeout(Duration::from_millis(300), rx.recv()).await.is_err());
+
+    shutdown.cancel();
+    tokio::time::timeout(Duration::from_secs(5), worker).await.unwrap().unwrap().unwrap();
+    server.abort();
+    TokenCache::cleanup().await;
+}
+
+#[tokio::test]
+#[serial]
+async fn dropped_connection_reconnects_and_publishes_next_token() {
+    TokenCache::cleanup().await;
+    let (server, url, mut rx) = spawn_fake_nats(true).await;
+    let sink = sink_config("nats", "nats_reconnect", SOURCE_ID, json!({ "nats": { "nats_url": url, "subject": "tokens.reconnect" } }));
+    let (worker, sink_sender, shutdown) = start_active_sinks(HashMap::from([("nats_reconnect".to_string(), sink)]), 1);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    set_access_token(SOURCE_ID, "first", 4_102_444_800).await;
+    sink_sender.send(TokenEvent::updated(SOURCE_ID, TokenCache::get(SOURCE_ID, "access_token").await.unwrap())).unwrap();
+    assert_eq!(next_published(&mut rx).await.payload, "first");
+
+    // server closed the connection after first message
+    set_access_token(SOURCE_ID, "second", 4_102_448_400).await;
+    sink_sender.send(TokenEvent::updated(SOURCE_ID, TokenCache::get(SOURCE_ID, "access_token").await.unwrap())).unwrap();
+    let message = next_published(&mut rx).await;
+    assert_eq!((message.subject.as_str(), message.payload.as_str()), ("tokens.reconnect", "second"));
+
+    shutdown.cancel();
+    tokio::time::timeout(Duration::from_secs(5), worker).await.unwrap().unwrap().unwrap();
+    server.abort();
+    TokenCache::cleanup().await;
+}
+
+#[tokio::test]
+#[serial]
+async fn lagging_sink_publishes_cached_token() {
+    TokenCache::cleanup().await;
+    let (server, url, mut rx) = spawn_fake_nats(false).await;
+    let sink = sink_config("nats", "nats_lagged", SOURCE_ID, json!({ "nats": { "nats_url": url, "subject": "tokens.lagged" } }));
+    let sink_manager = SinkManager::new(HashMap::from([("nats_lagged".to_string(), sink)]));
+    let sink_sender = channel::run();
+    let sink_receiver = sink_sender.subscribe();
+
+    // the sink token event is pushed out of the channel by events of another source before the sink reads it
+    set_access_token(SOURCE_ID, "cached", 4_102_444_800).await;
+    sink_sender.send(TokenEvent::updated(SOURCE_ID, TokenCache::get(SOURCE_ID, "access_token").await.unwrap())).unwrap();
+    overflow_token_events(&sink_sender);
+
+    let shutdown = CancellationToken::new();
+    let worker = tokio::spawn(sink_manager.start_nats_sinks(sink_receiver, shutdown.clone()));
+    let message = next_published(&mut rx).await;
+    assert_eq!((message.subject.as_str(), message.payload.as_str()), ("tokens.lagged", "cached"));
+
+    shutdown.cancel();
+    tokio::time::timeout(Duration::from_secs(5), worker).await.unwrap().unwrap().unwrap();
+    server.abort();
+    TokenCache::cleanup().await;
+}
+}
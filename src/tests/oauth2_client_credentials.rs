@@ -0,0 +1,57 @@
+// This test runs a single refresh cycle for an OAuth2 client_credentials source:
+//  - request.form is sent url-encoded with grant_type=client_credentials added
+//  - client_secret is resolved from env like any other request value
+//  - parsed access_token is stored in TokenCache
+
+#[cfg(test)]
+mod test {
+
+use std::collections::HashMap;
+
+use httpmock::prelude::*;
+use serde_json::json;
+use serial_test::serial;
+
+use crate::cache::token_cache::TokenCache;
+use crate::config::sources::{FormValue, GenericSourceValue};
+use crate::sources::builder_in_order::{DagNode, SourceDag};
+use crate::tests::common::{oauth2_source, refresh_context};
+
+#[tokio::test]
+#[serial]
+async fn client_credentials_form_is_sent_url_encoded() {
+    std::env::set_var("OAUTH2_CLIENT_CREDENTIALS_TEST_SECRET", "s3cr3t&=");
+    let server = MockServer::start_async().await;
+    let mock = server.mock(|when, then| {
+        when.method(POST)
+            .path("/oauth2/token")
+            .header("content-type", "application/x-www-form-urlencoded")
+            .form_urlencoded_tuple("grant_type", "client_credentials")
+            .form_urlencoded_tuple("client_id", "agent")
+            .form_urlencoded_tuple("client_secret", "s3cr3t&=")
+            .form_urlencoded_tuple("scope", "api.read api.write");
+        then.status(200).json_body(json!({ "access_token": "idp-access-token", "token_type": "Bearer", "expires_in": 3600 }));
+    });
+
+    let form = FormValue {
+        client_id: GenericSourceValue::Literal { value: "agent".into() },
+        client_secret: Some(GenericSourceValue::FromEnv { from_env: "OAUTH2_CLIENT_CREDENTIALS_TEST_SECRET".into() }),
+        client_assertion: None,
+        scope: GenericSourceValue::Literal { value: "api.read api.write".into() },
+        grant_type: None,
+    };
+    let sources = HashMap::from([("idp".to_string(), oauth2_source(server.url("/oauth2/token"), form))]);
+    let dag = SourceDag::build(&sources).unwrap();
+    let layers: Vec<Vec<DagNode>> = dag
+        .layers()
+        .into_iter()
+        .map(|layer| layer.into_iter().cloned().collect())
+        .collect();
+    let refresh_context = refresh_context();
+    SourceDag::refresh_layers(&layers, &refresh_context).await;
+
+    mock.assert_calls(1);
+    assert_eq!(TokenCache::get("idp", "access_token").await.unwrap().token.value, "idp-access-token");
+}
+
+}
@@ -0,0 +1,116 @@
+// Oneshot run:
+//  - every source is fetched once in dependency order, file sinks are written and the run returns
+//  - failed source is reported in the error, file sinks of other sources are still written
+
+#[cfg(test)]
+mod test {
+
+use std::time::Duration;
+
+use httpmock::prelude::*;
+use serde_json::json;
+use serial_test::serial;
+
+use crate::cache::token_cache::TokenCache;
+use crate::{ServiceConfig, TokenAgent};
+
+fn config_yaml(base_url: &str, dir: &str) -> String {
+    format!(
+        r#"
+settings:
+  server:
+    host: 127.0.0.1
+    port: "8080"
+  metrics:
+    path: "/metrics"
+    is_enabled: false
+  retry: {{ attempts: 2, base_delay_ms: 1, max_delay_ms: 1 }}
+sources:
+  first:
+    type: http
+    request: {{ url: "{base_url}/first", method: GET }}
+    parse:
+      tokens:
+        - {{ id: token, parent: body, pointer: token, token_type: plain_text, expiration: {{ source: manual, format: seconds, manual_ttl_seconds: 3600 }} }}
+  chained:
+    type: http
+    inputs: [first]
+    request:
+      url: "{base_url}/chained"
+      method: GET
+      headers:
+        Authorization: {{ template: "Bearer {{{{first.token}}}}", required: true }}
+    parse:
+      tokens:
+        - {{ id: token, parent: body, pointer: token, token_type: plain_text, expiration: {{ source: manual, format: seconds, manual_ttl_seconds: 3600 }} }}
+sinks:
+  first_file:
+    type: file
+    source_id: first
+    token_id: token
+    path: "{dir}/first.token"
+  chained_file:
+    type: file
+    source_id: chained
+    token_id: token
+    path: "{dir}/chained.token"
+"#
+    )
+}
+
+async fn run_once(base_url: &str, dir: &str) -> anyhow::Result<()> {
+    let service_config: ServiceConfig = serde_yaml::from_str(&config_yaml(base_url, dir)).unwrap();
+    let agent = TokenAgent::from_config(service_config).await.unwrap();
+    tokio::time::timeout(Duration::from_secs(10), agent.run_once()).await.expect("oneshot must return")
+}
+
+#[tokio::test]
+#[serial]
+async fn oneshot_fetches_sources_in_order_and_writes_file_sinks() {
+    TokenCache::cleanup().await;
+    let server = MockServer::start_async().await;
+    let first = server.mock(|when, then| {
+        when.method(GET).path("/first");
+        then.status(200).json_body(json!({ "token": "first-token" }));
+    });
+    let chained = server.mock(|when, then| {
+        when.method(GET).path("/chained").header("Authorization", "Bearer first-token");
+        then.status(200).json_body(json!({ "token": "chained-token" }));
+    });
+    let dir = tempfile::tempdir().unwrap();
+    let dir_path = dir.path().to_str().unwrap();
+
+    run_once(&server.base_url(), dir_path).await.unwrap();
+
+    first.assert_calls_async(1).await;
+    chained.assert_calls_async(1).await;
+    assert_eq!(std::fs::read_to_string(dir.path().join("first.token")).unwrap(), "first-token");
+    assert_eq!(std::fs::read_to_string(dir.path().join("chained.token")).unwrap(), "chained-token");
+    TokenCache::cleanup().await;
+}
+
+#[tokio::test]
+#[serial]
+async fn oneshot_reports_failed_source_after_retries() {
+    TokenCache::cleanup().await;
+    let server = MockServer::start_async().await;
+    server.mock(|when, then| {
+        when.method(GET).path("/first");
+        then.status(200).json_body(json!({ "token": "first-token" }));
+    });
+    let chained = server.mock(|when, then| {
+        when.method(GET).path("/chained");
+        then.status(500);
+    });
+    let dir = tempfile::tempdir().unwrap();
+    let dir_path = dir.path().to_str().unwrap();
+
+    let err = run_once(&server.base_url(), dir_path).await.unwrap_err().to_string();
+
+    chained.assert_calls_async(2).await;
+    assert_eq!(err, "sources failed: chained");
+    assert_eq!(std::fs::read_to_string(dir.path().join("first.token")).unwrap(), "first-token");
+    assert!(!dir.path().join("chained.token").exists());
+    TokenCache::cleanup().await;
+}
+}
@@ -0,0 +1,125 @@
+// This test builds a diamond DAG:
+//  - a -> b, a -> c, b + c -> d
+// Then it runs a single refresh cycle and asserts:
+//  - b and c are fetched concurrently (their requests overlap)
+//  - d starts only after both b and c are completed
+// With `max_concurrent_fetches: 1` independent nodes of one layer are fetched one after another
+
+#[cfg(test)]
+mod test {
+
+use std::{collections::HashMap, sync::{Arc, Mutex}, time::Duration};
+
+use axum::{extract::{Path, State}, routing::get, Json, Router};
+use serde_json::json;
+use serial_test::serial;
+use tokio::sync::Semaphore;
+use tokio::time::{sleep, Instant};
+
+use crate::cache::token_cache::TokenCache;
+use crate::config::sources::SourceConfig;
+use crate::sources::builder_in_order::{DagNode, SourceDag};
+use crate::sources::executor::token_fetch::RefreshContext;
+use crate::tests::common::{self, http_source, spawn_axum};
+
+type Timeline = Arc<Mutex<HashMap<String, (Instant, Instant)>>>;
+
+fn refresh_context(max_concurrent_fetches: Option<usize>) -> RefreshContext {
+    RefreshContext {
+        fetch_permits: max_concurrent_fetches.map(|permits| Arc::new(Semaphore::new(permits))),
+        ..common::refresh_context()
+    }
+}
+
+async fn handle(State(timeline): State<Timeline>, Path(name): Path<String>) -> Json<serde_json::Value> {
+    let started_at = Instant::now();
+    sleep(Duration::from_millis(200)).await;
+    timeline.lock().unwrap().insert(name.clone(), (started_at, Instant::now()));
+    Json(json!({ "token": format!("token-{}", name) }))
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+#[serial]
+async fn diamond_dag_fetches_independent_nodes_concurrently() {
+    let timeline: Timeline = Arc::new(Mutex::new(HashMap::new()));
+    let router = Router::new().route("/{name}", get(handle)).with_state(timeline.clone());
+    let (handle, addr) = spawn_axum(router).await;
+
+    let url = |name: &str| format!("http://{}/{}", addr, name);
+    let sources = HashMap::from([
+        ("dag_a".to_string(), http_source(url("dag_a"), 3600)),
+        ("dag_b".to_string(), SourceConfig { inputs: Some(vec!["dag_a".into()]), ..http_source(url("dag_b"), 3600) }),
+        ("dag_c".to_string(), SourceConfig { inputs: Some(vec!["dag_a".into()]), ..http_source(url("dag_c"), 3600) }),
+        ("dag_d".to_string(), SourceConfig { inputs: Some(vec!["dag_b".into(), "dag_c".into()]), ..http_source(url("dag_d"), 3600) }),
+    ]);
+    let dag = SourceDag::build(&sources).unwrap();
+
+    // layers: [a], [b, c], [d]
+    let layers: Vec<Vec<DagNode>> = dag
+        .layers()
+        .into_iter()
+        .map(|layer| layer.into_iter().cloned().collect())
+        .collect();
+    let mut layer_ids: Vec<Vec<String>> = layers
+        .iter()
+        .map(|layer| layer.iter().map(|node| node.id.clone()).collect())
+        .collect();
+    layer_ids.iter_mut().for_each(|layer| layer.sort());
+    assert_eq!(layer_ids, vec![vec!["dag_a"], vec!["dag_b", "dag_c"], vec!["dag_d"]]);
+
+    SourceDag::refresh_layers(&layers, &refresh_context(None)).await;
+
+    let timeline = timeline.lock().unwrap().clone();
+    let (a_start, a_end) = timeline["dag_a"];
+    let (b_start, b_end) = timeline["dag_b"];
+    let (c_start, c_end) = timeline["dag_c"];
+    let (d_start, _) = timeline["dag_d"];
+
+    assert!(a_start < a_end && a_end <= b_start && a_end <= c_start, "b and c must start after a");
+    assert!(b_start < c_end && c_start < b_end, "b and c must be fetched concurrently");
+    assert!(d_start >= b_end && d_start >= c_end, "d must start after b and c are completed");
+
+    for source_id in ["dag_a", "dag_b", "dag_c", "dag_d"] {
+        let token = TokenCache::get(source_id, "token").await.unwrap();
+        assert_eq!(token.token.value, format!("token-{}", source_id));
+    }
+
+    handle.abort();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+#[serial]
+async fn max_concurrent_fetches_serializes_independent_nodes() {
+    let timeline: Timeline = Arc::new(Mutex::new(HashMap::new()));
+    let router = Router::new().route("/{name}", get(handle)).with_state(timeline.clone());
+    let (handle, addr) = spawn_axum(router).await;
+
+    let url = |name: &str| format!("http://{}/{}", addr, name);
+    let sources = HashMap::from([
+        ("capped_x".to_string(), http_source(url("capped_x"), 3600)),
+        ("capped_y".to_string(), http_source(url("capped_y"), 3600)),
+    ]);
+    let layers: Vec<Vec<DagNode>> = SourceDag::build(&sources)
+        .unwrap()
+        .layers()
+        .into_iter()
+        .map(|layer| layer.into_iter().cloned().collect())
+        .collect();
+    assert_eq!(layers.len(), 1);
+
+    let started_at = Instant::now();
+    SourceDag::refresh_layers(&layers, &refresh_context(Some(1))).await;
+    assert!(started_at.elapsed() >= Duration::from_millis(400), "{:?}", started_at.elapsed());
+
+    let timeline = timeline.lock().unwrap().clone();
+    let (x_start, x_end) = timeline["capped_x"];
+    let (y_start, y_end) = timeline["capped_y"];
+    assert!(x_end <= y_start || y_end <= x_start, "x and y must not overlap");
+
+    for source_id in ["capped_x", "capped_y"] {
+        TokenCache::remove_by_source_id(source_id).await;
+    }
+    handle.abort();
+}
+
+}
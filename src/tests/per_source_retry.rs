@@ -0,0 +1,139 @@
+// This test runs a single refresh cycle for two sources, each failing twice before succeeding:
+//  - retry_one: source retry attempts = 1, gives up after the first failure
+//  - retry_five: source retry attempts = 5, succeeds on the third attempt
+// Settings level retry (attempts = 2) would make both sources fail.
+// Retry on status: 401 fails immediately, 429 is retried until success.
+
+#[cfg(test)]
+mod test {
+
+use std::{collections::HashMap, sync::{Arc, Mutex}};
+
+use axum::{extract::{Path, State}, http::StatusCode, routing::get, Json, Router};
+use axum::response::{IntoResponse, Response};
+use serde_json::json;
+use serial_test::serial;
+
+use crate::cache::token_cache::TokenCache;
+use crate::config::settings::{JitterMode, RetryConfig};
+use crate::config::sources::SourceConfig;
+use crate::resilience::retry::RetrySettings;
+use crate::sources::builder_in_order::{DagNode, SourceDag};
+use crate::sources::executor::token_fetch::RefreshContext;
+use crate::tests::common::{http_source, refresh_context, spawn_axum};
+
+type Hits = Arc<Mutex<HashMap<String, u32>>>;
+
+const FAILURES_BEFORE_SUCCESS: u32 = 2;
+
+async fn handle(State(hits): State<Hits>, Path(name): Path<String>) -> Result<Json<serde_json::Value>, StatusCode> {
+    let mut hits = hits.lock().unwrap();
+    let count = hits.entry(name.clone()).or_default();
+    *count += 1;
+    if *count <= FAILURES_BEFORE_SUCCESS {
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+    Ok(Json(json!({ "token": format!("token-{}", name) })))
+}
+
+#[tokio::test]
+#[serial]
+async fn source_retry_overrides_settings_retry() {
+    let hits: Hits = Arc::new(Mutex::new(HashMap::new()));
+    let router = Router::new().route("/{name}", get(handle)).with_state(hits.clone());
+    let (_handle, addr) = spawn_axum(router).await;
+
+    let url = |name: &str| format!("http://{}/{}", addr, name);
+    let sources = HashMap::from([
+        (
+            "retry_one".to_string(),
+            SourceConfig { retry: Some(RetryConfig { attempts: Some(1), ..Default::default() }), ..http_source(url("retry_one"), 3600) },
+        ),
+        (
+            "retry_five".to_string(),
+            SourceConfig { retry: Some(RetryConfig { attempts: Some(5), ..Default::default() }), ..http_source(url("retry_five"), 3600) },
+        ),
+    ]);
+    let dag = SourceDag::build(&sources).unwrap();
+    let layers: Vec<Vec<DagNode>> = dag
+        .layers()
+        .into_iter()
+        .map(|layer| layer.into_iter().cloned().collect())
+        .collect();
+
+    let refresh_context = RefreshContext {
+        retry: RetrySettings { attempts: 2, base_delay_ms: 1, max_delay_ms: 1, ..Default::default() },
+        ..refresh_context()
+    };
+    SourceDag::refresh_layers(&layers, &refresh_context).await;
+
+    let hits = hits.lock().unwrap().clone();
+    assert_eq!(hits["retry_one"], 1, "attempts=1 must give up after one try");
+    assert_eq!(hits["retry_five"], FAILURES_BEFORE_SUCCESS + 1, "attempts=5 must succeed after transient failures");
+    assert!(TokenCache::get("retry_one", "token").await.is_none());
+    assert_eq!(TokenCache::get("retry_five", "token").await.unwrap().token.value, "token-retry_five");
+}
+
+#[test]
+fn source_retry_falls_back_to_settings_retry() {
+    let settings = RetrySettings { attempts: 3, base_delay_ms: 200, max_delay_ms: 1000, ..Default::default() };
+
+    let retry = settings.with_override(&None);
+    assert_eq!((retry.attempts, retry.base_delay_ms, retry.max_delay_ms), (3, 200, 1000));
+
+    let retry = settings.with_override(&Some(RetryConfig { attempts: Some(7), max_delay_ms: Some(5000), ..Default::default() }));
+    assert_eq!((retry.attempts, retry.base_delay_ms, retry.max_delay_ms), (7, 200, 5000));
+}
+
+async fn handle_status(State(hits): State<Hits>, Path((name, status)): Path<(String, u16)>) -> Response {
+    let mut hits = hits.lock().unwrap();
+    let count = hits.entry(name.clone()).or_default();
+    *count += 1;
+    if *count <= FAILURES_BEFORE_SUCCESS {
+        return StatusCode::from_u16(status).unwrap().into_response();
+    }
+    Json(json!({ "token": format!("token-{}", name) })).into_response()
+}
+
+#[tokio::test]
+#[serial]
+async fn retry_only_on_configured_statuses() {
+    let hits: Hits = Arc::new(Mutex::new(HashMap::new()));
+    let router = Router::new().route("/{name}/{status}", get(handle_status)).with_state(hits.clone());
+    let (_handle, addr) = spawn_axum(router).await;
+
+    let unauthorized = http_source(format!("http://{}/status_401/401", addr), 3600);
+    let too_many_requests = http_source(format!("http://{}/status_429/429", addr), 3600);
+    let sources = HashMap::from([
+        ("status_401".to_string(), unauthorized),
+        ("status_429".to_string(), too_many_requests),
+    ]);
+    let dag = SourceDag::build(&sources).unwrap();
+    let layers: Vec<Vec<DagNode>> = dag
+        .layers()
+        .into_iter()
+        .map(|layer| layer.into_iter().cloned().collect())
+        .collect();
+
+    let refresh_context = RefreshContext {
+        retry: RetrySettings {
+            attempts: 5,
+            base_delay_ms: 1,
+            max_delay_ms: 5,
+            jitter: JitterMode::Full,
+            retry_on_status: Some(vec![429, 500, 502, 503, 504]),
+            respect_retry_after: false,
+            attempt_timeout: None,
+        },
+        ..refresh_context()
+    };
+    SourceDag::refresh_layers(&layers, &refresh_context).await;
+
+    let hits = hits.lock().unwrap().clone();
+    assert_eq!(hits["status_401"], 1, "401 must not be retried");
+    assert_eq!(hits["status_429"], FAILURES_BEFORE_SUCCESS + 1, "429 must be retried");
+    assert!(TokenCache::get("status_401", "token").await.is_none());
+    assert_eq!(TokenCache::get("status_429", "token").await.unwrap().token.value, "token-status_429");
+}
+
+}
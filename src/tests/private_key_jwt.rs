@@ -0,0 +1,106 @@
+// This test runs a single refresh cycle for an OAuth2 source with private_key_jwt client authentication:
+//  - form.client_assertion is a JWT signed with the RSA fixture key (src/tests/fixtures/jwt)
+//  - the mock verifies the assertion signature with the public key and records its claims
+//  - aud claim is rendered from a template referencing another source
+
+#[cfg(test)]
+mod test {
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use chrono::Utc;
+use httpmock::prelude::*;
+use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
+use serde_json::{json, Value};
+use serial_test::serial;
+
+use crate::cache::token::Token;
+use crate::cache::token_cache::TokenCache;
+use crate::cache::token_context::TokenContext;
+use crate::config::sources::{FormValue, GenericSourceValue, OAUTH2_CLIENT_ASSERTION_TYPE_JWT};
+use crate::sources::builder_in_order::{DagNode, SourceDag};
+use crate::tests::common::{oauth2_source, refresh_context};
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/tests/fixtures/jwt");
+
+/// Claims of a `client_assertion` signed by the fixture key, None for any other request
+fn verified_assertion_claims(body: &str) -> Option<Value> {
+    let fields: HashMap<&str, &str> = body.split('&').filter_map(|pair| pair.split_once('=')).collect();
+    let assertion_type = OAUTH2_CLIENT_ASSERTION_TYPE_JWT.replace(':', "%3A");
+    if fields.get("client_assertion_type") != Some(&assertion_type.as_str()) {
+        return None;
+    }
+    let public_key = std::fs::read(format!("{}/public.pem", FIXTURES)).unwrap();
+    let mut validation = Validation::new(Algorithm::RS256);
+    validation.set_audience(&["https://idp.example.com/oauth2/token"]);
+    decode::<Value>(fields.get("client_assertion")?, &DecodingKey::from_rsa_pem(&public_key).unwrap(), &validation)
+        .ok()
+        .map(|data| data.claims)
+}
+
+#[tokio::test]
+#[serial]
+async fn client_assertion_is_signed_with_key_file() {
+    let token = Token::new("https://idp.example.com/oauth2/token".into(), 5_000_000_000);
+    TokenCache::set("idp_config".into(), vec![TokenContext::new("token_endpoint".into(), token, 10)]).await.unwrap();
+
+    let received_claims = Arc::new(Mutex::new(None));
+    let server = MockServer::start_async().await;
+    let mock = {
+        let received_claims = received_claims.clone();
+        server.mock(move |when, then| {
+            when.method(POST)
+                .path("/oauth2/token")
+                .form_urlencoded_tuple("grant_type", "client_credentials")
+                .form_urlencoded_tuple("client_id", "agent")
+                .is_true(move |request| {
+                    let claims = verified_assertion_claims(&String::from_utf8_lossy(request.body_ref()));
+                    let verified = claims.is_some();
+                    *received_claims.lock().unwrap() = claims;
+                    verified
+                });
+            then.status(200).json_body(json!({ "access_token": "pkjwt-access-token", "expires_in": 3600 }));
+        })
+    };
+
+    let client_assertion = GenericSourceValue::SignedJwt {
+        key_file: format!("{FIXTURES}/private.pem"),
+        algorithm: Some("RS256".into()),
+        claims: HashMap::from([
+            ("iss".to_string(), "agent".to_string()),
+            ("sub".to_string(), "agent".to_string()),
+            ("aud".to_string(), "{{idp_config.token_endpoint}}".to_string()),
+        ]),
+        ttl_seconds: Some(120),
+    };
+    let form = FormValue {
+        client_id: GenericSourceValue::Literal { value: "agent".into() },
+        client_secret: None,
+        client_assertion: Some(client_assertion),
+        scope: GenericSourceValue::Literal { value: "api.read".into() },
+        grant_type: None,
+    };
+    let sources = HashMap::from([("pkjwt_idp".to_string(), oauth2_source(server.url("/oauth2/token"), form))]);
+    let dag = SourceDag::build(&sources).unwrap();
+    let layers: Vec<Vec<DagNode>> = dag
+        .layers()
+        .into_iter()
+        .map(|layer| layer.into_iter().cloned().collect())
+        .collect();
+    let refresh_context = refresh_context();
+    SourceDag::refresh_layers(&layers, &refresh_context).await;
+
+    mock.assert_calls(1);
+    assert_eq!(TokenCache::get("pkjwt_idp", "access_token").await.unwrap().token.value, "pkjwt-access-token");
+    let claims = received_claims.lock().unwrap().clone().unwrap();
+    assert_eq!(claims["iss"], "agent");
+    assert_eq!(claims["sub"], "agent");
+    assert_eq!(claims["aud"], "https://idp.example.com/oauth2/token");
+    assert!(claims["jti"].as_str().is_some_and(|jti| !jti.is_empty()));
+    let (iat, exp) = (claims["iat"].as_i64().unwrap(), claims["exp"].as_i64().unwrap());
+    assert_eq!(exp - iat, 120);
+    assert!((iat - Utc::now().timestamp()).abs() < 60);
+}
+
+}
@@ -0,0 +1,187 @@
+// Redis sink:
+//  - changed token is SET with raw value and TTL matching token expiration, unchanged token is not SET again
+//  - removed token DELetes the key
+//  - JSON envelope is stored with fixed TTL, dropped connection is re-opened by the retry
+//  - sink lagging behind token events SETs the cached token
+
+#[cfg(test)]
+mod test {
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::Utc;
+use serde_json::{json, Value};
+use serial_test::serial;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use crate::cache::token_cache::TokenCache;
+use crate::cache::token_event::TokenEvent;
+use crate::config::sinks::{SinkConfig, SinkType};
+use crate::observability::metrics::get_metrics;
+use crate::sinks::manager::SinkManager;
+use crate::tests::common::{overflow_token_events, set_access_token, sink_config, start_active_sinks, JoinHandle};
+use crate::utils::channel;
+
+const SOURCE_ID: &str = "redis_source";
+
+/// Minimal Redis server: answers every command with OK (DEL with 1), reports SET/DEL arguments,
+/// first connection is closed after its first SET when `drop_after_set` is set
+async fn spawn_fake_redis(drop_after_set: bool) -> (JoinHandle<()>, String, mpsc::UnboundedReceiver<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("redis://{}", listener.local_addr().unwrap());
+    let (tx, rx) = mpsc::unbounded_channel();
+    let handle = tokio::spawn(async move {
+        let mut drop_connection = drop_after_set;
+        loop {
+            let Ok((stream, _)) = listener.accept().await else { return };
+            tokio::spawn(serve_connection(stream, tx.clone(), drop_connection));
+            drop_connection = false;
+        }
+    });
+    (handle, url, rx)
+}
+
+async fn serve_connection(stream: TcpStream, tx: mpsc::UnboundedSender<Vec<String>>, drop_after_set: bool) {
+    let (read, mut write) = stream.into_split();
+    let mut reader = BufReader::new(read);
+    loop {
+        let Some(command) = read_command(&mut reader).await else { return };
+        let reply: &[u8] = if command[0].eq_ignore_ascii_case("DEL") { b":1\r\n" } else { b"+OK\r\n" };
+        let is_set = command[0].eq_ignore_ascii_case("SET");
+        if matches!(command[0].to_uppercase().as_str(), "SET" | "DEL") {
+            let _ = tx.send(command);
+        }
+        if is_set && drop_after_set {
+            return;
+        }
+        if write.write_all(reply).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// RESP array of bulk strings
+async fn read_command(reader: &mut BufReader<tokio::net::tcp::OwnedReadHalf>) -> Option<Vec<String>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line).await.ok()? == 0 {
+        return None;
+    }
+    let count: usize = line.trim().strip_prefix('*')?.parse().ok()?;
+    let mut command = Vec::with_capacity(count);
+    for _ in 0..count {
+        line.clear();
+        reader.read_line(&mut line).await.ok()?;
+        let len: usize = line.trim().strip_prefix('$')?.parse().ok()?;
+        let mut arg = vec![0u8; len + 2];
+        reader.read_exact(&mut arg).await.ok()?;
+        command.push(String::from_utf8_lossy(&arg[..len]).into_owned());
+    }
+    Some(command)
+}
+
+/// `redis_url` of the fake server is set into `redis` options
+fn redis_sink(sink_id: &str, url: &str, mut redis: Value) -> SinkConfig {
+    redis["redis_url"] = json!({ "value": url });
+    sink_config("redis", sink_id, SOURCE_ID, json!({ "redis": redis }))
+}
+
+async fn next_command(rx: &mut mpsc::UnboundedReceiver<Vec<String>>) -> Vec<String> {
+    tokio::time::timeout(Duration::from_secs(5), rx.recv()).await.expect("command sent").unwrap()
+}
+
+#[tokio::test]
+#[serial]
+async fn changed_token_set_with_token_ttl_and_removed_token_deleted() {
+    TokenCache::cleanup().await;
+    let (server, url, mut rx) = spawn_fake_redis(false).await;
+    let sink = redis_sink("redis_raw", &url, json!({ "key_template": "tokens:{source_id}:{token_id}", "ttl_mode": "match_token_exp" }));
+    assert_eq!(sink.sink_type, SinkType::Redis);
+    let commands = get_metrics().await.sink_redis_commands.clone();
+    let set_before = commands.with_label_values(&["redis_raw", "set", "ok"]).get();
+    let (worker, _, shutdown) = start_active_sinks(HashMap::from([("redis_raw".to_string(), sink)]), 2);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let exp = Utc::now().timestamp() as u64 + 3600;
+    set_access_token(SOURCE_ID, "first", exp).await;
+    let command = next_command(&mut rx).await;
+    assert_eq!(command[..3], ["SET", "tokens:redis_source:access_token", "first"]);
+    assert_eq!(command[3], "EX");
+    let ttl: u64 = command[4].parse().unwrap();
+    assert!((3590..=3600).contains(&ttl), "{}", ttl);
+
+    // unchanged token is skipped
+    set_access_token(SOURCE_ID, "first", exp).await;
+    set_access_token(SOURCE_ID, "second", exp + 60).await;
+    assert_eq!(next_command(&mut rx).await[..3], ["SET", "tokens:redis_source:access_token", "second"]);
+
+    TokenCache::remove_by_source_id(SOURCE_ID).await;
+    assert_eq!(next_command(&mut rx).await, ["DEL", "tokens:redis_source:access_token"]);
+    assert_eq!(commands.with_label_values(&["redis_raw", "set", "ok"]).get(), set_before + 2);
+
+    shutdown.cancel();
+    tokio::time::timeout(Duration::from_secs(5), worker).await.unwrap().unwrap().unwrap();
+    server.abort();
+    TokenCache::cleanup().await;
+}
+
+#[tokio::test]
+#[serial]
+async fn json_envelope_set_with_fixed_ttl_after_reconnect() {
+    TokenCache::cleanup().await;
+    let (server, url, mut rx) = spawn_fake_redis(true).await;
+    let sink = redis_sink(
+        "redis_envelope",
+        &url,
+        json!({ "key_template": "tokens:{token_id}", "ttl_mode": { "fixed_seconds": 300 }, "value_format": "json_envelope" }),
+    );
+    let (worker, _, shutdown) = start_active_sinks(HashMap::from([("redis_envelope".to_string(), sink)]), 2);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    // server closes the connection instead of answering, the retry opens a new one
+    set_access_token(SOURCE_ID, "first", 4_102_444_800).await;
+    let dropped = next_command(&mut rx).await;
+    let command = next_command(&mut rx).await;
+    assert_eq!(dropped, command);
+    assert_eq!(command[..2], ["SET", "tokens:access_token"]);
+    assert_eq!(command[3..], ["EX", "300"]);
+    let envelope: Value = serde_json::from_str(&command[2]).unwrap();
+    assert_eq!(
+        envelope,
+        json!({ "token": "first", "exp_unix_ts": 4_102_444_800u64, "source_id": SOURCE_ID, "token_id": "access_token" })
+    );
+
+    shutdown.cancel();
+    tokio::time::timeout(Duration::from_secs(5), worker).await.unwrap().unwrap().unwrap();
+    server.abort();
+    TokenCache::cleanup().await;
+}
+
+#[tokio::test]
+#[serial]
+async fn lagging_sink_sets_cached_token() {
+    TokenCache::cleanup().await;
+    let (server, url, mut rx) = spawn_fake_redis(false).await;
+    let sink = redis_sink("redis_lagged", &url, json!({ "key_template": "tokens:{token_id}", "ttl_mode": { "fixed_seconds": 300 } }));
+    let sink_manager = SinkManager::new(HashMap::from([("redis_lagged".to_string(), sink)]));
+    let sink_sender = channel::run();
+    let sink_receiver = sink_sender.subscribe();
+
+    // the sink token event is pushed out of the channel by events of another source before the sink reads it
+    set_access_token(SOURCE_ID, "cached", 4_102_444_800).await;
+    sink_sender.send(TokenEvent::updated(SOURCE_ID, TokenCache::get(SOURCE_ID, "access_token").await.unwrap())).unwrap();
+    overflow_token_events(&sink_sender);
+
+    let shutdown = CancellationToken::new();
+    let worker = tokio::spawn(sink_manager.start_redis_sinks(sink_receiver, shutdown.clone()));
+    assert_eq!(next_command(&mut rx).await, ["SET", "tokens:access_token", "cached", "EX", "300"]);
+
+    shutdown.cancel();
+    tokio::time::timeout(Duration::from_secs(5), worker).await.unwrap().unwrap().unwrap();
+    server.abort();
+    TokenCache::cleanup().await;
+}
+}
@@ -0,0 +1,68 @@
+// Per-token refresh latency metrics:
+//  - first fetch of a token is observed with cycle `initial`, fetches of cached tokens with cycle `refresh`
+//  - `token_last_refresh_unix` is set when the token is stored
+
+#[cfg(test)]
+mod test {
+
+use std::time::Duration;
+
+use chrono::Utc;
+use httpmock::prelude::*;
+use serial_test::serial;
+
+use crate::cache::token_cache::TokenCache;
+use crate::observability::metrics::get_metrics;
+use crate::TokenAgent;
+
+#[tokio::test]
+#[serial]
+async fn refresh_latency_labeled_by_cycle() {
+    TokenCache::cleanup().await;
+    let server = MockServer::start_async().await;
+    let token = server.mock(|when, then| {
+        when.method(GET).path("/token");
+        then.status(200).json_body(serde_json::json!({ "token": "latency" }));
+    });
+    let yaml = format!(
+        r#"
+settings:
+  server:
+    host: 127.0.0.1
+    port: "8080"
+  metrics:
+    path: "/metrics"
+    is_enabled: false
+sources:
+  latency_source:
+    type: http
+    request: {{ url: "{url}", method: GET }}
+    parse:
+      tokens:
+        - {{ id: token, parent: body, pointer: token, token_type: plain_text, expiration: {{ source: manual, format: seconds, manual_ttl_seconds: 3600 }} }}
+sinks: {{}}
+"#,
+        url = server.url("/token")
+    );
+    let metrics = get_metrics().await;
+    let samples = |cycle: &str| metrics.token_refresh_latency.with_label_values(&["latency_source", "token", cycle]).get_sample_count();
+    let (initial_before, refresh_before) = (samples("initial"), samples("refresh"));
+    let started_at = Utc::now().timestamp();
+
+    let agent = TokenAgent::from_config(serde_yaml::from_str(&yaml).unwrap()).await.unwrap();
+    tokio::time::timeout(Duration::from_secs(10), agent.run_once()).await.unwrap().unwrap();
+    assert_eq!(samples("initial"), initial_before + 1);
+    assert_eq!(samples("refresh"), refresh_before);
+
+    // token is cached now
+    let agent = TokenAgent::from_config(serde_yaml::from_str(&yaml).unwrap()).await.unwrap();
+    tokio::time::timeout(Duration::from_secs(10), agent.run_once()).await.unwrap().unwrap();
+    assert_eq!(samples("initial"), initial_before + 1);
+    assert_eq!(samples("refresh"), refresh_before + 1);
+
+    token.assert_calls_async(2).await;
+    let last_refresh = metrics.token_last_refresh_unix.with_label_values(&["latency_source", "token"]).get();
+    assert!(last_refresh >= started_at && last_refresh <= Utc::now().timestamp(), "{}", last_refresh);
+    TokenCache::cleanup().await;
+}
+}
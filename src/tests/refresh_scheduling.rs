@@ -0,0 +1,88 @@
+// Refresh scheduling:
+//  - after fetching a 3600s token the next check is at expiration minus safety margin, not in 1 second
+//  - the following cycle does not fetch again and keeps the schedule
+//  - pre-fetch margin moves the next check earlier
+
+#[cfg(test)]
+mod test {
+
+use std::collections::HashMap;
+
+use chrono::Utc;
+use httpmock::prelude::*;
+use serde_json::json;
+use serial_test::serial;
+
+use crate::cache::token_cache::TokenCache;
+use crate::config::sources::SourceConfig;
+use crate::sources::builder_in_order::{DagNode, SourceDag};
+use crate::sources::executor::token_fetch::RefreshContext;
+use crate::tests::common;
+
+const SOURCE_ID: &str = "scheduled_source";
+
+fn layers(url: String) -> Vec<Vec<DagNode>> {
+    let source: SourceConfig = serde_yaml::from_str(&format!(
+        "type: http\nrequest: {{ url: \"{}\", method: GET }}\nparse:\n  tokens:\n    - {{ id: token, parent: body, pointer: token, token_type: plain_text, expiration: {{ source: manual, format: seconds, manual_ttl_seconds: 3600 }} }}\n",
+        url
+    ))
+    .unwrap();
+    SourceDag::build(&HashMap::from([(SOURCE_ID.to_string(), source)]))
+        .unwrap()
+        .layers()
+        .into_iter()
+        .map(|layer| layer.into_iter().cloned().collect())
+        .collect()
+}
+
+fn refresh_context(prefetch_margin_seconds: Option<u64>) -> RefreshContext {
+    RefreshContext {
+        safety_margin_seconds_settings: Some(300),
+        prefetch_margin_seconds_settings: prefetch_margin_seconds,
+        ..common::refresh_context()
+    }
+}
+
+fn now() -> i64 {
+    Utc::now().timestamp()
+}
+
+#[tokio::test]
+#[serial]
+async fn next_check_is_at_expiration_minus_safety_margin() {
+    TokenCache::cleanup().await;
+    let server = MockServer::start_async().await;
+    let token = server.mock(|when, then| {
+        when.method(GET).path("/token");
+        then.status(200).json_body(json!({ "token": "scheduled" }));
+    });
+    let layers = layers(server.url("/token"));
+
+    let fetched_at = now();
+    let sleep_until = SourceDag::refresh_layers(&layers, &refresh_context(None)).await;
+    let interval = sleep_until - fetched_at;
+    assert!((3600 - 300 - 2..=3600 - 300).contains(&interval), "sleep interval {}", interval);
+
+    // valid token: no fetch, same schedule
+    assert_eq!(SourceDag::refresh_layers(&layers, &refresh_context(None)).await, sleep_until);
+    token.assert_calls_async(1).await;
+    TokenCache::cleanup().await;
+}
+
+#[tokio::test]
+#[serial]
+async fn prefetch_margin_moves_next_check_earlier() {
+    TokenCache::cleanup().await;
+    let server = MockServer::start_async().await;
+    server.mock(|when, then| {
+        when.method(GET).path("/token");
+        then.status(200).json_body(json!({ "token": "scheduled" }));
+    });
+
+    let fetched_at = now();
+    let sleep_until = SourceDag::refresh_layers(&layers(server.url("/token")), &refresh_context(Some(600))).await;
+    let interval = sleep_until - fetched_at;
+    assert!((3600 - 300 - 600 - 2..=3600 - 300 - 600).contains(&interval), "sleep interval {}", interval);
+    TokenCache::cleanup().await;
+}
+}
@@ -0,0 +1,103 @@
+// Refresh cycle tracing, spans collected by in-memory span exporter:
+//  - one refresh cycle of a two-source chain is one trace with `refresh.cycle` root span
+//  - `token.fetch` of every source is a child of the cycle, with attempt and status attributes
+//  - `token.parse` is a child of its fetch, `cache.set` is a child of the cycle
+//  - token events carry `cache.set` context, so sink propagation joins the same trace
+
+#[cfg(test)]
+mod test {
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use httpmock::prelude::*;
+use serde_json::json;
+use opentelemetry::trace::SpanId;
+use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider, SpanData};
+use serial_test::serial;
+use tracing_subscriber::layer::SubscriberExt;
+
+use crate::cache::token_cache::TokenCache;
+use crate::config::settings::OtelConfig;
+use crate::config::sources::SourceConfig;
+use crate::observability::otel::{provider_layer, sink_propagate_span, tracer_provider};
+use crate::sources::builder_in_order::{DagNode, SourceDag};
+use crate::tests::common::{http_source, refresh_context};
+
+fn layers(sources: &HashMap<String, SourceConfig>) -> Vec<Vec<DagNode>> {
+    SourceDag::build(sources)
+        .unwrap()
+        .layers()
+        .into_iter()
+        .map(|layer| layer.into_iter().cloned().collect())
+        .collect()
+}
+
+fn attribute(span: &SpanData, key: &str) -> Option<String> {
+    span.attributes.iter().find(|kv| kv.key.as_str() == key).map(|kv| kv.value.as_str().into_owned())
+}
+
+/// Span of `name` with `source.id` attribute
+fn find<'a>(spans: &'a [SpanData], name: &str, source_id: &str) -> &'a SpanData {
+    spans
+        .iter()
+        .find(|span| span.name == name && attribute(span, "source.id").as_deref() == Some(source_id))
+        .unwrap_or_else(|| panic!("span {} of {} not found in {:?}", name, source_id, spans))
+}
+
+#[tokio::test]
+#[serial]
+async fn refresh_cycle_of_source_chain_is_one_trace() {
+    TokenCache::cleanup().await;
+    let server = MockServer::start_async().await;
+    server.mock(|when, then| {
+        when.method(GET).path("/first");
+        then.status(200).json_body(json!({ "token": "first" }));
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/second");
+        then.status(200).json_body(json!({ "token": "second" }));
+    });
+    let sources = HashMap::from([
+        ("trace_first".to_string(), http_source(server.url("/first"), 3600)),
+        ("trace_second".to_string(), SourceConfig { inputs: Some(vec!["trace_first".to_string()]), ..http_source(server.url("/second"), 3600) }),
+    ]);
+    let mut subscription = TokenCache::subscribe("trace_first", "token").await;
+
+    let exporter = InMemorySpanExporter::default();
+    let provider = tracer_provider(SdkTracerProvider::builder().with_simple_exporter(exporter.clone()), &OtelConfig::default());
+    // current thread runtime: spawned node tasks see the default subscriber
+    let _guard = tracing::subscriber::set_default(tracing_subscriber::registry().with(provider_layer(&provider)));
+    SourceDag::refresh_layers(&layers(&sources), &refresh_context()).await;
+    let event = tokio::time::timeout(Duration::from_secs(5), subscription.recv()).await.unwrap().unwrap();
+    drop(sink_propagate_span("file", "trace_sink", &event));
+    let spans = exporter.get_finished_spans().unwrap();
+
+    let cycle = spans.iter().find(|span| span.name == "refresh.cycle").expect("refresh.cycle span");
+    assert_eq!(cycle.parent_span_id, SpanId::INVALID);
+    assert_eq!(attribute(cycle, "sources").as_deref(), Some("2"));
+    let trace_id = cycle.span_context.trace_id();
+    assert!(spans.iter().all(|span| span.span_context.trace_id() == trace_id), "{:?}", spans);
+
+    for source_id in ["trace_first", "trace_second"] {
+        let fetch = find(&spans, "token.fetch", source_id);
+        assert_eq!(fetch.parent_span_id, cycle.span_context.span_id());
+        assert_eq!(attribute(fetch, "attempt").as_deref(), Some("1"));
+        assert_eq!(attribute(fetch, "status").as_deref(), Some("success"));
+        let parse = spans
+            .iter()
+            .find(|span| span.name == "token.parse" && span.parent_span_id == fetch.span_context.span_id())
+            .unwrap_or_else(|| panic!("token.parse of {} not found", source_id));
+        assert_eq!(attribute(parse, "tokens").as_deref(), Some("1"));
+        assert_eq!(find(&spans, "cache.set", source_id).parent_span_id, cycle.span_context.span_id());
+    }
+    // dependent source is fetched after its input is stored
+    assert!(find(&spans, "cache.set", "trace_first").end_time <= find(&spans, "token.fetch", "trace_second").start_time);
+
+    let propagate = find(&spans, "sink.propagate", "trace_first");
+    assert_eq!(propagate.parent_span_id, find(&spans, "cache.set", "trace_first").span_context.span_id());
+    assert_eq!(attribute(propagate, "sink.id").as_deref(), Some("trace_sink"));
+    assert!(attribute(propagate, "traceparent").is_none());
+}
+
+}
@@ -0,0 +1,96 @@
+// Retry-After handling:
+//  - retry delay follows Retry-After when `respect_retry_after` is set
+//  - without it Retry-After is capped by max_delay_ms
+//  - refresh loop does not fetch the source again before Retry-After elapses
+
+#[cfg(test)]
+mod test {
+
+use std::{collections::HashMap, sync::Arc, time::{Duration, Instant}};
+
+use httpmock::prelude::*;
+use reqwest::Client;
+use serde_json::json;
+use serial_test::serial;
+
+use crate::helpers::time::now_i64;
+use crate::resilience::retry::RetrySettings;
+use crate::sources::builder_in_order::{DagNode, SourceDag};
+use crate::sources::fetch::{FetchTokens, Source};
+use crate::tests::common::{http_source, refresh_context};
+
+async fn fetch_with_retry(url: String, retry: RetrySettings) -> Duration {
+    let source = Source(Arc::new(http_source(url, 3600)));
+    let client = Client::new();
+    let start = Instant::now();
+    let res = retry
+        .run_with_retry(|| {
+            let source = source.clone();
+            let client = client.clone();
+            async move { source.fetch_tokens(&client, None).await }
+        })
+        .await;
+    assert!(res.is_err());
+    start.elapsed()
+}
+
+#[tokio::test]
+async fn retry_delay_respects_retry_after() {
+    let server = MockServer::start_async().await;
+    let mock = server.mock(|when, then| {
+        when.method(GET).path("/throttled");
+        then.status(429).header("Retry-After", "1");
+    });
+
+    let retry = RetrySettings { attempts: 2, base_delay_ms: 1, max_delay_ms: 10, respect_retry_after: true, ..Default::default() };
+    let elapsed = fetch_with_retry(server.url("/throttled"), retry).await;
+
+    mock.assert_calls(2);
+    assert!(elapsed >= Duration::from_secs(1), "inter-request delay {:?} must respect Retry-After", elapsed);
+}
+
+#[tokio::test]
+async fn retry_after_is_capped_by_max_delay() {
+    let server = MockServer::start_async().await;
+    let mock = server.mock(|when, then| {
+        when.method(GET).path("/throttled");
+        then.status(503).header("Retry-After", "30");
+    });
+
+    let retry = RetrySettings { attempts: 3, base_delay_ms: 1, max_delay_ms: 50, ..Default::default() };
+    let elapsed = fetch_with_retry(server.url("/throttled"), retry).await;
+
+    mock.assert_calls(3);
+    assert!(elapsed >= Duration::from_millis(100), "delay {:?} must be max_delay_ms per retry", elapsed);
+    assert!(elapsed < Duration::from_secs(5), "delay {:?} must be capped by max_delay_ms", elapsed);
+}
+
+#[tokio::test]
+#[serial]
+async fn refresh_loop_waits_for_retry_after() {
+    let server = MockServer::start_async().await;
+    let mock = server.mock(|when, then| {
+        when.method(GET).path("/retry_after_loop");
+        then.status(429).header("Retry-After", "30").json_body(json!({ "error": "slow down" }));
+    });
+
+    let sources = HashMap::from([("retry_after_loop".to_string(), http_source(server.url("/retry_after_loop"), 3600))]);
+    let dag = SourceDag::build(&sources).unwrap();
+    let layers: Vec<Vec<DagNode>> = dag
+        .layers()
+        .into_iter()
+        .map(|layer| layer.into_iter().cloned().collect())
+        .collect();
+    let refresh_context = refresh_context();
+
+    let now = now_i64();
+    let sleep_until = SourceDag::refresh_layers(&layers, &refresh_context).await;
+    assert!(sleep_until >= now + 30, "next refresh must be pushed out by Retry-After");
+    mock.assert_calls(1);
+
+    // next cycle skips the source until Retry-After elapses
+    assert_eq!(SourceDag::refresh_layers(&layers, &refresh_context).await, sleep_until);
+    mock.assert_calls(1);
+}
+
+}
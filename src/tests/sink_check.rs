@@ -0,0 +1,116 @@
+// Check:
+//  - sources are fetched, every sink is reported with its status, nothing is written
+//  - file sink holding the token is OK, stale or absent file is FILE_MISMATCH, sink of a failed source is MISSING
+
+#[cfg(test)]
+mod test {
+
+use std::time::Duration;
+
+use httpmock::prelude::*;
+use serde_json::json;
+use serial_test::serial;
+
+use crate::cache::token_cache::TokenCache;
+use crate::sinks::sink_check::SinkCheckStatus;
+use crate::{ServiceConfig, TokenAgent};
+
+fn config_yaml(base_url: &str, dir: &str) -> String {
+    format!(
+        r#"
+settings:
+  server:
+    host: 127.0.0.1
+    port: "8080"
+  metrics:
+    path: "/metrics"
+    is_enabled: false
+  retry: {{ attempts: 1, base_delay_ms: 1, max_delay_ms: 5000 }}
+sources:
+  first:
+    type: http
+    request: {{ url: "{base_url}/first", method: GET }}
+    parse:
+      tokens:
+        - {{ id: token, parent: body, pointer: token, token_type: plain_text, expiration: {{ source: manual, format: seconds, manual_ttl_seconds: 3600 }} }}
+  broken:
+    type: http
+    request: {{ url: "{base_url}/broken", method: GET }}
+    parse:
+      tokens:
+        - {{ id: token, parent: body, pointer: token, token_type: plain_text, expiration: {{ source: manual, format: seconds, manual_ttl_seconds: 3600 }} }}
+sinks:
+  first_file:
+    type: file
+    source_id: first
+    token_id: token
+    path: "{dir}/first.token"
+  stale_file:
+    type: file
+    source_id: first
+    token_id: token
+    path: "{dir}/stale.token"
+  absent_file:
+    type: file
+    source_id: first
+    token_id: token
+    path: "{dir}/absent.token"
+  first_http:
+    type: http
+    source_id: first
+    token_id: token
+    path: "/token/first"
+  broken_http:
+    type: http
+    source_id: broken
+    token_id: token
+    path: "/token/broken"
+"#
+    )
+}
+
+#[tokio::test]
+#[serial]
+async fn check_reports_status_of_every_sink() {
+    TokenCache::cleanup().await;
+    let server = MockServer::start_async().await;
+    server.mock(|when, then| {
+        when.method(GET).path("/first");
+        then.status(200).json_body(json!({ "token": "first-token" }));
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/broken");
+        then.status(500);
+    });
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("first.token"), "first-token").unwrap();
+    std::fs::write(dir.path().join("stale.token"), "old-token").unwrap();
+    let service_config: ServiceConfig =
+        serde_yaml::from_str(&config_yaml(&server.base_url(), dir.path().to_str().unwrap())).unwrap();
+
+    let agent = TokenAgent::from_config(service_config).await.unwrap();
+    let summary = tokio::time::timeout(Duration::from_secs(10), agent.check()).await.unwrap().unwrap();
+
+    assert!(!summary.is_success());
+    assert!(!dir.path().join("absent.token").exists());
+    assert_eq!(std::fs::read_to_string(dir.path().join("stale.token")).unwrap(), "old-token");
+    let statuses: Vec<(&str, SinkCheckStatus)> =
+        summary.sinks.iter().map(|sink| (sink.sink_id.as_str(), sink.status)).collect();
+    assert_eq!(
+        statuses,
+        [
+            ("absent_file", SinkCheckStatus::FileMismatch),
+            ("broken_http", SinkCheckStatus::Missing),
+            ("first_file", SinkCheckStatus::Ok),
+            ("first_http", SinkCheckStatus::Ok),
+            ("stale_file", SinkCheckStatus::FileMismatch),
+        ]
+    );
+
+    let printed = summary.to_string();
+    assert!(printed.starts_with("sink_name   | source_id | token_id | status\n"), "{}", printed);
+    assert!(printed.contains("broken_http | broken    | token    | MISSING\n"), "{}", printed);
+    assert!(!printed.contains("first-token"), "{}", printed);
+    TokenCache::cleanup().await;
+}
+}
@@ -0,0 +1,116 @@
+// mTLS source: axum server over rustls requiring client certificates signed by test CA
+// (src/tests/fixtures/mtls, generated with openssl, valid for 100 years)
+//  - source with client certificate, key and CA bundle fetches token over cached per-source client
+//  - source without client certificate is rejected during handshake
+
+#[cfg(test)]
+mod test {
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::routing::get;
+use axum::{Json, Router};
+use reqwest::Client;
+use serde_json::json;
+use serial_test::serial;
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::server::WebPkiClientVerifier;
+use tokio_rustls::rustls::{RootCertStore, ServerConfig};
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+
+use crate::config::sources::{SourceConfig, TlsConfig};
+use crate::sources::fetch::{FetchTokens, Source};
+use crate::resilience::timeout::TimeoutSettings;
+use crate::sources::tls::SourceClient;
+use crate::tests::common::http_source;
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/tests/fixtures/mtls");
+
+fn fixture(name: &str) -> String {
+    format!("{}/{}", FIXTURES, name)
+}
+
+/// TLS listener for `axum::serve`, failed handshakes are dropped
+struct MtlsListener {
+    listener: TcpListener,
+    acceptor: TlsAcceptor,
+}
+
+impl axum::serve::Listener for MtlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        loop {
+            let Ok((stream, addr)) = self.listener.accept().await else {
+                continue;
+            };
+            if let Ok(stream) = self.acceptor.accept(stream).await {
+                return (stream, addr);
+            }
+        }
+    }
+
+    fn local_addr(&self) -> std::io::Result<Self::Addr> {
+        self.listener.local_addr()
+    }
+}
+
+async fn spawn_mtls_server() -> SocketAddr {
+    let mut roots = RootCertStore::empty();
+    roots.add(CertificateDer::from_pem_file(fixture("ca.pem")).unwrap()).unwrap();
+    let verifier = WebPkiClientVerifier::builder(Arc::new(roots)).build().unwrap();
+    let certs = CertificateDer::pem_file_iter(fixture("server.pem")).unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+    let key = PrivateKeyDer::from_pem_file(fixture("server.key")).unwrap();
+    let config = ServerConfig::builder().with_client_cert_verifier(verifier).with_single_cert(certs, key).unwrap();
+
+    let listener = MtlsListener {
+        listener: TcpListener::bind("127.0.0.1:0").await.unwrap(),
+        acceptor: TlsAcceptor::from(Arc::new(config)),
+    };
+    let addr = axum::serve::Listener::local_addr(&listener).unwrap();
+    let app = Router::new().route("/token", get(|| async { Json(json!({ "token": "mtls-token" })) }));
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    addr
+}
+
+#[tokio::test]
+#[serial]
+async fn source_with_client_certificate_fetches_token() {
+    let addr = spawn_mtls_server().await;
+    let tls = TlsConfig {
+        ca_file: Some(fixture("ca.pem")),
+        client_cert_file: Some(fixture("client.pem")),
+        client_key_file: Some(fixture("client.key")),
+        ..Default::default()
+    };
+    let config = SourceConfig { tls: Some(tls), ..http_source(format!("https://localhost:{}/token", addr.port()), 3600) };
+    let tls: &TlsConfig = config.tls.as_ref().unwrap();
+    assert_eq!(tls.client_cert_file.as_deref(), Some(fixture("client.pem").as_str()));
+
+    let client = SourceClient::get_by_source_id("mtls_source", &config, &Client::new(), &TimeoutSettings::default()).await.unwrap();
+    let tokens = Source(Arc::new(config.clone())).fetch_tokens(&client, None).await.unwrap();
+    assert_eq!(tokens[0].token.value, "mtls-token");
+
+    // cached client is reused for next fetch
+    let cached = SourceClient::get_by_source_id("mtls_source", &config, &Client::new(), &TimeoutSettings::default()).await.unwrap();
+    let tokens = Source(Arc::new(config)).fetch_tokens(&cached, None).await.unwrap();
+    assert_eq!(tokens[0].token.value, "mtls-token");
+    SourceClient::remove_by_source_id("mtls_source").await;
+}
+
+#[tokio::test]
+#[serial]
+async fn source_without_client_certificate_is_rejected() {
+    let addr = spawn_mtls_server().await;
+    let tls = TlsConfig { ca_file: Some(fixture("ca.pem")), ..Default::default() };
+    let config = SourceConfig { tls: Some(tls), ..http_source(format!("https://localhost:{}/token", addr.port()), 3600) };
+    let client = SourceClient::get_by_source_id("mtls_source_no_cert", &config, &Client::new(), &TimeoutSettings::default()).await.unwrap();
+    assert!(Source(Arc::new(config)).fetch_tokens(&client, None).await.is_err());
+    SourceClient::remove_by_source_id("mtls_source_no_cert").await;
+}
+}
@@ -0,0 +1,109 @@
+// Source request timeouts:
+//  - slow source fails with timeout error after source `read_seconds`, not after the server delay
+//  - every retry attempt gets a fresh timeout
+//  - source without own timeouts uses settings level timeouts
+
+#[cfg(test)]
+mod test {
+
+use std::{collections::HashMap, time::{Duration, Instant}};
+
+use httpmock::prelude::*;
+use reqwest::Client;
+use serde_json::json;
+use serial_test::serial;
+
+use crate::cache::token_cache::TokenCache;
+use crate::config::settings::TimeoutConfig;
+use crate::config::sources::SourceConfig;
+use crate::resilience::retry::RetrySettings;
+use crate::resilience::timeout::TimeoutSettings;
+use crate::sources::builder_in_order::{DagNode, SourceDag};
+use crate::sources::error::FetchError;
+use crate::sources::executor::token_fetch::RefreshContext;
+use crate::sources::fetch::{FetchTokens, Source};
+use crate::tests::common::{self, http_source};
+
+const SERVER_DELAY: Duration = Duration::from_secs(3);
+
+fn layers(sources: &HashMap<String, SourceConfig>) -> Vec<Vec<DagNode>> {
+    SourceDag::build(sources)
+        .unwrap()
+        .layers()
+        .into_iter()
+        .map(|layer| layer.into_iter().cloned().collect())
+        .collect()
+}
+
+fn refresh_context(attempts: u32, timeouts: TimeoutSettings) -> RefreshContext {
+    RefreshContext {
+        retry: RetrySettings { attempts, base_delay_ms: 1, max_delay_ms: 1, ..Default::default() },
+        timeouts,
+        ..common::refresh_context()
+    }
+}
+
+#[tokio::test]
+async fn slow_source_attempt_fails_with_timeout_error() {
+    let server = MockServer::start_async().await;
+    server.mock(|when, then| {
+        when.method(GET).path("/slow");
+        then.status(200).delay(SERVER_DELAY).json_body(json!({ "token": "late" }));
+    });
+    let source = Source(http_source(server.url("/slow"), 3600).into());
+    let retry = RetrySettings { attempts: 1, ..Default::default() }.with_attempt_timeout(Some(Duration::from_secs(1)));
+    let client = Client::new();
+
+    let start = Instant::now();
+    let err = retry.run_with_retry(|| source.fetch_tokens(&client, None)).await.unwrap_err();
+    let elapsed = start.elapsed();
+
+    assert_eq!(err.downcast_ref::<FetchError>(), Some(&FetchError::Timeout { timeout: Duration::from_secs(1) }));
+    assert_eq!(err.to_string(), "request timed out after 1s");
+    assert!(elapsed >= Duration::from_secs(1) && elapsed < SERVER_DELAY, "{:?}", elapsed);
+}
+
+#[tokio::test]
+#[serial]
+async fn source_read_timeout_applies_to_every_attempt() {
+    TokenCache::cleanup().await;
+    let server = MockServer::start_async().await;
+    let slow = server.mock(|when, then| {
+        when.method(GET).path("/slow");
+        then.status(200).delay(SERVER_DELAY).json_body(json!({ "token": "late" }));
+    });
+    // source level read timeout overrides settings level, which would let the response through
+    let timeouts = Some(TimeoutConfig { connect_seconds: Some(1), read_seconds: Some(1) });
+    let sources = HashMap::from([("slow_source".to_string(), SourceConfig { timeouts, ..http_source(server.url("/slow"), 3600) })]);
+    let settings_timeouts = TimeoutSettings { connect: None, read: Some(Duration::from_secs(10)) };
+
+    let start = Instant::now();
+    SourceDag::refresh_layers(&layers(&sources), &refresh_context(2, settings_timeouts)).await;
+    let elapsed = start.elapsed();
+
+    slow.assert_calls_async(2).await;
+    assert!(elapsed >= Duration::from_secs(2) && elapsed < 2 * SERVER_DELAY, "{:?}", elapsed);
+    assert!(TokenCache::get("slow_source", "token").await.is_none());
+    TokenCache::cleanup().await;
+}
+
+#[tokio::test]
+#[serial]
+async fn settings_read_timeout_used_without_source_timeouts() {
+    TokenCache::cleanup().await;
+    let server = MockServer::start_async().await;
+    server.mock(|when, then| {
+        when.method(GET).path("/slow");
+        then.status(200).delay(SERVER_DELAY).json_body(json!({ "token": "late" }));
+    });
+    let sources = HashMap::from([("settings_timeout".to_string(), http_source(server.url("/slow"), 3600))]);
+    let settings_timeouts = TimeoutSettings::from_config(&Some(TimeoutConfig { connect_seconds: None, read_seconds: Some(1) }));
+
+    let start = Instant::now();
+    SourceDag::refresh_layers(&layers(&sources), &refresh_context(1, settings_timeouts)).await;
+
+    assert!(start.elapsed() < SERVER_DELAY, "{:?}", start.elapsed());
+    assert!(TokenCache::get("settings_timeout", "token").await.is_none());
+    TokenCache::cleanup().await;
+}
+}
@@ -0,0 +1,65 @@
+// This test runs a single refresh cycle over plain HTTP for three sources:
+//  - tls_default: no tls block, shared default client
+//  - tls_insecure: tls block, dedicated client is built and used
+//  - tls_broken: tls block with missing ca_file, client build fails and no request is sent
+
+#[cfg(test)]
+mod test {
+
+use std::collections::HashMap;
+
+use httpmock::prelude::*;
+use serde_json::json;
+use serial_test::serial;
+
+use crate::cache::token_cache::TokenCache;
+use crate::config::sources::{SourceConfig, TlsConfig};
+use crate::resilience::timeout::TimeoutSettings;
+use crate::sources::builder_in_order::{DagNode, SourceDag};
+use crate::sources::tls::SourceClient;
+use crate::tests::common::{http_source, refresh_context};
+
+#[tokio::test]
+#[serial]
+async fn per_source_tls_clients_are_selected() {
+    let server = MockServer::start_async().await;
+    let mock = |name: &'static str| {
+        server.mock(|when, then| {
+            when.method(GET).path(format!("/{}", name));
+            then.status(200).json_body(json!({ "token": format!("token-{}", name) }));
+        })
+    };
+    let default_mock = mock("tls_default");
+    let insecure_mock = mock("tls_insecure");
+    let broken_mock = mock("tls_broken");
+
+    let insecure_tls = TlsConfig { insecure_skip_verify: Some(true), ..Default::default() };
+    let broken_tls = TlsConfig { ca_file: Some("/nonexistent/ca.pem".into()), ..Default::default() };
+    let sources = HashMap::from([
+        ("tls_default".to_string(), http_source(server.url("/tls_default"), 3600)),
+        ("tls_insecure".to_string(), SourceConfig { tls: Some(insecure_tls), ..http_source(server.url("/tls_insecure"), 3600) }),
+        ("tls_broken".to_string(), SourceConfig { tls: Some(broken_tls), ..http_source(server.url("/tls_broken"), 3600) }),
+    ]);
+    let dag = SourceDag::build(&sources).unwrap();
+    let layers: Vec<Vec<DagNode>> = dag
+        .layers()
+        .into_iter()
+        .map(|layer| layer.into_iter().cloned().collect())
+        .collect();
+
+    let refresh_context = refresh_context();
+    SourceDag::refresh_layers(&layers, &refresh_context).await;
+
+    default_mock.assert_calls(1);
+    insecure_mock.assert_calls(1);
+    broken_mock.assert_calls(0);
+    assert_eq!(TokenCache::get("tls_default", "token").await.unwrap().token.value, "token-tls_default");
+    assert_eq!(TokenCache::get("tls_insecure", "token").await.unwrap().token.value, "token-tls_insecure");
+    assert!(TokenCache::get("tls_broken", "token").await.is_none());
+
+    // build errors are returned on each call, valid tls client is reused
+    assert!(SourceClient::get_by_source_id("tls_broken", &sources["tls_broken"], &refresh_context.client, &TimeoutSettings::default()).await.is_err());
+    assert!(SourceClient::get_by_source_id("tls_insecure", &sources["tls_insecure"], &refresh_context.client, &TimeoutSettings::default()).await.is_ok());
+}
+
+}
@@ -0,0 +1,122 @@
+// Stale token fallback with `stale_token_ttl_seconds`:
+//  - expired token of a source whose last fetch failed is kept as stale, with extended expiration
+//  - HTTP sink serves it with `X-Token-Stale: true`, `cached_stale_tokens_total` counts it
+//  - after `stale_token_ttl_seconds` the token is removed regardless
+//  - expired token of a source whose last fetch succeeded is removed right away
+
+#[cfg(test)]
+mod test {
+
+use std::{collections::HashMap, time::Duration};
+
+use axum::Router;
+use httpmock::prelude::*;
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use serial_test::serial;
+
+use crate::cache::token_cache::TokenCache;
+use crate::cache::token_event::TokenEventKind;
+use crate::config::sinks::SinkConfig;
+use crate::config::sources::SourceConfig;
+use crate::observability::metrics::get_metrics;
+use crate::resilience::fallback::StaleFallback;
+use crate::server::server::AppState;
+use crate::sinks::sink_http::{SinkHttpState, TOKEN_STALE_HEADER};
+use crate::sources::builder_in_order::{DagNode, SourceDag};
+use crate::tests::common::{build_reqwest_client, http_source, refresh_context, spawn_axum};
+
+const SOURCE_ID: &str = "stale_source";
+const STALE_TOKEN_TTL_SECONDS: u64 = 3;
+
+fn layers(sources: &HashMap<String, SourceConfig>) -> Vec<Vec<DagNode>> {
+    SourceDag::build(sources)
+        .unwrap()
+        .layers()
+        .into_iter()
+        .map(|layer| layer.into_iter().cloned().collect())
+        .collect()
+}
+
+#[tokio::test]
+#[serial]
+async fn expired_token_is_served_stale_while_source_fails() -> anyhow::Result<()> {
+    TokenCache::cleanup().await;
+    StaleFallback::remove_by_source_id(SOURCE_ID).await;
+    let server = MockServer::start_async().await;
+    let ok = server.mock(|when, then| {
+        when.method(GET).path("/token");
+        then.status(200).json_body(json!({ "token": "last-good" }));
+    });
+    // token expires one second after fetch, so it is invalidated right away
+    let source = SourceConfig {
+        // refresh_layers is called back to back, no failure backoff
+        min_refresh_interval_seconds: Some(0),
+        stale_token_ttl_seconds: Some(STALE_TOKEN_TTL_SECONDS),
+        ..http_source(server.url("/token"), 1)
+    };
+    let sources = HashMap::from([(SOURCE_ID.to_string(), source)]);
+    let layers = layers(&sources);
+
+    SourceDag::refresh_layers(&layers, &refresh_context()).await;
+    let fetched = TokenCache::get(SOURCE_ID, "token").await.unwrap();
+    assert!(!fetched.is_stale);
+
+    // last fetch succeeded: expired token is removed
+    SourceDag::invalidate_tokens_by_source_id(SOURCE_ID, Some(STALE_TOKEN_TTL_SECONDS), None).await?;
+    assert!(TokenCache::get(SOURCE_ID, "token").await.is_none());
+
+    // fetched again, then the source goes down
+    SourceDag::refresh_layers(&layers, &refresh_context()).await;
+    ok.delete_async().await;
+    let outage = server.mock(|when, then| {
+        when.method(GET).path("/token");
+        then.status(500);
+    });
+    let fetched = TokenCache::get(SOURCE_ID, "token").await.unwrap();
+    SourceDag::refresh_layers(&layers, &refresh_context()).await;
+    assert_eq!(outage.calls_async().await, 1);
+    assert!(StaleFallback::last_fetch_failed(SOURCE_ID).await);
+
+    let mut subscription = TokenCache::subscribe(SOURCE_ID, "token").await;
+    SourceDag::invalidate_tokens_by_source_id(SOURCE_ID, Some(STALE_TOKEN_TTL_SECONDS), None).await?;
+    let stale = TokenCache::get(SOURCE_ID, "token").await.expect("stale token is kept");
+    assert!(stale.is_stale);
+    assert_eq!(stale.token.value, "last-good");
+    assert_eq!(stale.token.exp_unix_ts, fetched.token.exp_unix_ts + STALE_TOKEN_TTL_SECONDS);
+    let event = tokio::time::timeout(Duration::from_secs(5), subscription.recv()).await?.unwrap();
+    assert_eq!(event.kind, TokenEventKind::Updated);
+    assert!(event.token_context.is_stale);
+    let metrics = get_metrics().await;
+    assert_eq!(metrics.cached_stale_tokens.with_label_values(&[SOURCE_ID]).get(), 1);
+
+    let sink_config: SinkConfig = serde_json::from_value(json!({
+        "sink_id": "sink-http-stale",
+        "type": "http",
+        "source_id": SOURCE_ID,
+        "path": "/tokens/stale",
+        "token_id": "token",
+        "response": { "body": { "access_token": { "type": "token", "id": "token" } } }
+    }))?;
+    let sinks = HashMap::from([(sink_config.sink_id.clone(), sink_config)]);
+    let router = SinkHttpState::new(&sinks)?.router().await;
+    let app: Router = router.with_state(AppState::new(metrics, &sinks, &None));
+    let (handle, addr) = spawn_axum(app).await;
+    let response = build_reqwest_client().get(format!("http://{}/tokens/stale", addr)).send().await?;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers().get(TOKEN_STALE_HEADER).and_then(|v| v.to_str().ok()), Some("true"));
+    assert_eq!(response.json::<Value>().await?["access_token"], "last-good");
+    handle.abort();
+
+    // source still failing, stale ttl elapsed: removed regardless
+    tokio::time::sleep(Duration::from_secs(STALE_TOKEN_TTL_SECONDS)).await;
+    SourceDag::invalidate_tokens_by_source_id(SOURCE_ID, Some(STALE_TOKEN_TTL_SECONDS), None).await?;
+    assert!(TokenCache::get(SOURCE_ID, "token").await.is_none());
+    assert_eq!(metrics.cached_stale_tokens.with_label_values(&[SOURCE_ID]).get(), 0);
+
+    StaleFallback::remove_by_source_id(SOURCE_ID).await;
+    TokenCache::cleanup().await;
+    Ok(())
+}
+
+}
@@ -0,0 +1,104 @@
+// Embedded token agent:
+//  - agent started from config fetches tokens, handle serves them and stops on shutdown
+//  - invalid config is rejected by `from_config` with all validation errors
+
+#[cfg(test)]
+mod test {
+
+use std::time::Duration;
+
+use serial_test::serial;
+
+use crate::cache::token_cache::TokenCache;
+use crate::{ServiceConfig, TokenAgent};
+
+fn config_yaml(port: u16, url: &str, sink_source_id: &str) -> String {
+    format!(
+        r#"
+settings:
+  server:
+    host: 127.0.0.1
+    port: "{port}"
+  metrics:
+    path: "/metrics"
+    is_enabled: false
+sources:
+  embedded:
+    type: http
+    request:
+      url: "{url}"
+      method: GET
+    parse:
+      tokens:
+        - id: token
+          parent: body
+          pointer: token
+          token_type: plain_text
+          expiration:
+            source: manual
+            format: seconds
+            manual_ttl_seconds: 3600
+sinks:
+  embedded_http:
+    type: http
+    source_id: {sink_source_id}
+    path: "/embedded"
+    token_id: token
+    response:
+      body:
+        token: {{ type: token, id: token }}
+"#
+    )
+}
+
+fn parse(yaml: &str) -> ServiceConfig {
+    serde_yaml::from_str(yaml).expect("test config must parse")
+}
+
+#[tokio::test]
+#[serial]
+async fn embedded_agent_serves_tokens_until_shutdown() {
+    TokenCache::cleanup().await;
+    let token_server = httpmock::MockServer::start_async().await;
+    token_server.mock(|when, then| {
+        when.method(httpmock::Method::GET).path("/token");
+        then.status(200).json_body(serde_json::json!({ "token": "embedded-token" }));
+    });
+    let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let service_config = parse(&config_yaml(port, &token_server.url("/token"), "embedded"));
+
+    let handle = TokenAgent::from_config(service_config).await.unwrap().start().unwrap();
+    let token = tokio::time::timeout(Duration::from_secs(5), async {
+        loop {
+            if let Some(token) = handle.get_token("embedded", "token").await {
+                return token;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("agent must fetch token");
+    assert_eq!(token.value, "embedded-token");
+    assert!(handle.get_token("embedded", "unknown").await.is_none());
+
+    // embedded agent serves the same HTTP sinks as the binary
+    let response = reqwest::get(format!("http://127.0.0.1:{}/embedded", port)).await.unwrap();
+    assert!(response.status().is_success(), "unexpected status: {}", response.status());
+    assert_eq!(response.json::<serde_json::Value>().await.unwrap()["token"], "embedded-token");
+
+    let shutdown_token = handle.shutdown_token();
+    tokio::time::timeout(Duration::from_secs(10), handle.shutdown()).await.unwrap().unwrap();
+    assert!(shutdown_token.is_cancelled());
+    TokenCache::cleanup().await;
+}
+
+#[tokio::test]
+async fn invalid_config_is_rejected_by_from_config() {
+    let service_config = parse(&config_yaml(8080, "http://localhost/token", "missing_source"));
+
+    let err = TokenAgent::from_config(service_config).await.err().expect("config must be rejected");
+
+    assert!(err.to_string().starts_with("config is not valid"), "{}", err);
+    assert!(err.to_string().contains("missing_source"), "{}", err);
+}
+}
@@ -0,0 +1,90 @@
+// Token events:
+//  - subscriber observes exactly one `Updated` event per refresh, with the new expiration
+//  - expired token invalidation produces `Removed` event
+//  - unchanged token stored again emits nothing, other tokens of the source are not observed
+
+#[cfg(test)]
+mod test {
+
+use std::{collections::HashMap, time::Duration};
+
+use chrono::Utc;
+use httpmock::prelude::*;
+use serde_json::json;
+use serial_test::serial;
+
+use crate::cache::token::Token;
+use crate::cache::token_cache::TokenCache;
+use crate::cache::token_context::TokenContext;
+use crate::cache::token_event::{TokenEventKind, TokenSubscription};
+use crate::config::sources::SourceConfig;
+use crate::sources::builder_in_order::{DagNode, SourceDag};
+use crate::tests::common::{http_source, refresh_context};
+
+const SOURCE_ID: &str = "events_source";
+
+fn layers(sources: &HashMap<String, SourceConfig>) -> Vec<Vec<DagNode>> {
+    SourceDag::build(sources)
+        .unwrap()
+        .layers()
+        .into_iter()
+        .map(|layer| layer.into_iter().cloned().collect())
+        .collect()
+}
+
+async fn assert_no_event(subscription: &mut TokenSubscription) {
+    let event = tokio::time::timeout(Duration::from_millis(200), subscription.recv()).await;
+    assert!(event.is_err(), "unexpected event: {:?}", event);
+}
+
+#[tokio::test]
+#[serial]
+async fn refresh_emits_updated_and_invalidation_emits_removed() {
+    TokenCache::cleanup().await;
+    let server = MockServer::start_async().await;
+    server.mock(|when, then| {
+        when.method(GET).path("/token");
+        then.status(200).json_body(json!({ "token": "fresh" }));
+    });
+    // token is invalidated 1 second before expiration, so it is expired right away
+    let sources = HashMap::from([(SOURCE_ID.to_string(), http_source(server.url("/token"), 1))]);
+    let mut subscription = TokenCache::subscribe(SOURCE_ID, "token").await;
+
+    let refreshed_at = Utc::now().timestamp() as u64;
+    SourceDag::refresh_layers(&layers(&sources), &refresh_context()).await;
+
+    let updated = tokio::time::timeout(Duration::from_secs(5), subscription.recv()).await.unwrap().unwrap();
+    assert_eq!(updated.kind, TokenEventKind::Updated);
+    assert_eq!((updated.source_id.as_str(), updated.token_id.as_str()), (SOURCE_ID, "token"));
+    assert_eq!(updated.token_context.token.value, "fresh");
+    let exp = updated.token_context.token.exp_unix_ts;
+    assert!(exp >= refreshed_at + 1 && exp <= Utc::now().timestamp() as u64 + 1, "{}", exp);
+    assert_no_event(&mut subscription).await;
+
+    assert!(TokenCache::invalidate_expired_tokens_by_source_id(SOURCE_ID).await);
+    let removed = tokio::time::timeout(Duration::from_secs(5), subscription.recv()).await.unwrap().unwrap();
+    assert_eq!(removed.kind, TokenEventKind::Removed);
+    assert_eq!(removed.token_context.token.exp_unix_ts, exp);
+    assert!(TokenCache::get(SOURCE_ID, "token").await.is_none());
+    TokenCache::cleanup().await;
+}
+
+#[tokio::test]
+#[serial]
+async fn unchanged_token_emits_nothing() {
+    TokenCache::cleanup().await;
+    let token = |id: &str, value: &str| TokenContext::new(id.into(), Token::new(value.into(), 4_102_444_800), 10);
+    TokenCache::set(SOURCE_ID.into(), vec![token("a", "first"), token("b", "first")]).await.unwrap();
+    let mut subscription_a = TokenCache::subscribe(SOURCE_ID, "a").await;
+    let mut subscription_b = TokenCache::subscribe(SOURCE_ID, "b").await;
+
+    TokenCache::set(SOURCE_ID.into(), vec![token("a", "first"), token("b", "second")]).await.unwrap();
+
+    let updated = tokio::time::timeout(Duration::from_secs(5), subscription_b.recv()).await.unwrap().unwrap();
+    assert_eq!(updated.kind, TokenEventKind::Updated);
+    assert_eq!(updated.token_context.token.value, "second");
+    assert_no_event(&mut subscription_a).await;
+    assert_no_event(&mut subscription_b).await;
+    TokenCache::cleanup().await;
+}
+}
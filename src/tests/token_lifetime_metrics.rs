@@ -0,0 +1,97 @@
+// Token lifetime metrics, scraped from the registry:
+//  - `token_issued_ttl_seconds` observes the lifetime of every newly fetched token
+//  - `token_seconds_until_expiry` is refreshed by cache metrics processing
+//  - `token_refresh_total` counts refresh results per token
+
+#[cfg(test)]
+mod test {
+
+use std::time::Duration;
+
+use httpmock::prelude::*;
+use prometheus::{Encoder, TextEncoder};
+use serial_test::serial;
+
+use crate::cache::token_cache::TokenCache;
+use crate::observability::metrics::get_metrics;
+use crate::TokenAgent;
+
+fn config(source_id: &str, url: String) -> String {
+    format!(
+        r#"
+settings:
+  server:
+    host: 127.0.0.1
+    port: "8080"
+  metrics:
+    path: "/metrics"
+    is_enabled: false
+  retry:
+    attempts: 1
+sources:
+  {source_id}:
+    type: http
+    request: {{ url: "{url}", method: GET }}
+    parse:
+      tokens:
+        - {{ id: token, parent: body, pointer: token, token_type: plain_text, expiration: {{ source: manual, format: seconds, manual_ttl_seconds: 3600 }} }}
+sinks: {{}}
+"#
+    )
+}
+
+async fn scrape() -> String {
+    let mut buffer = Vec::new();
+    TextEncoder::new().encode(&get_metrics().await.registry.gather(), &mut buffer).unwrap();
+    String::from_utf8(buffer).unwrap()
+}
+
+/// Value of the exposition line starting with `series`
+fn sample(scraped: &str, series: &str) -> Option<f64> {
+    scraped
+        .lines()
+        .find_map(|line| line.strip_prefix(series))
+        .and_then(|value| value.trim().parse().ok())
+}
+
+#[tokio::test]
+#[serial]
+async fn token_lifetime_metrics_are_exported() {
+    TokenCache::cleanup().await;
+    let server = MockServer::start_async().await;
+    server.mock(|when, then| {
+        when.method(GET).path("/token");
+        then.status(200).json_body(serde_json::json!({ "token": "lifetime" }));
+    });
+    server.mock(|when, then| {
+        when.method(GET).path("/broken");
+        then.status(500);
+    });
+
+    let agent = TokenAgent::from_config(serde_yaml::from_str(&config("ttl_source", server.url("/token"))).unwrap()).await.unwrap();
+    tokio::time::timeout(Duration::from_secs(10), agent.run_once()).await.unwrap().unwrap();
+    let agent = TokenAgent::from_config(serde_yaml::from_str(&config("ttl_broken", server.url("/broken"))).unwrap()).await.unwrap();
+    assert!(tokio::time::timeout(Duration::from_secs(10), agent.run_once()).await.unwrap().is_err());
+    TokenCache::process_metrics().await;
+
+    let scraped = scrape().await;
+    let labels = r#"{source="ttl_source",token_id="token"}"#;
+    assert_eq!(sample(&scraped, &format!("tokenagent_token_issued_ttl_seconds_count{}", labels)), Some(1.0), "{}", scraped);
+    let issued_ttl = sample(&scraped, &format!("tokenagent_token_issued_ttl_seconds_sum{}", labels)).unwrap();
+    assert!((3590.0..=3600.0).contains(&issued_ttl), "{}", issued_ttl);
+    let until_expiry = sample(&scraped, &format!("tokenagent_token_seconds_until_expiry{}", labels)).unwrap();
+    assert!((3590.0..=3600.0).contains(&until_expiry), "{}", until_expiry);
+    assert_eq!(
+        sample(&scraped, r#"tokenagent_token_refresh_total{result="success",source="ttl_source",token_id="token"}"#),
+        Some(1.0)
+    );
+    assert_eq!(
+        sample(&scraped, r#"tokenagent_token_refresh_total{result="failure",source="ttl_broken",token_id="token"}"#),
+        Some(1.0)
+    );
+    assert_eq!(sample(&scraped, r#"tokenagent_token_issued_ttl_seconds_count{source="ttl_broken",token_id="token"}"#), None);
+
+    TokenCache::cleanup().await;
+}
+
+}
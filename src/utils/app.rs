@@ -0,0 +1,384 @@
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::time::{Duration, SystemTime};
+
+use anyhow::Result;
+use reqwest::Client;
+use tokio::select;
+use tokio::signal::unix::{signal, Signal, SignalKind};
+use tokio::sync::broadcast::Sender;
+use tokio::task::{self, JoinSet};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+use crate::cache::secret_cache::SecretCache;
+use crate::cache::ssm_cache::SsmCache;
+use crate::cache::token_cache::TokenCache;
+use crate::cache::token_event::TokenEvent;
+use crate::parser::parser::set_clock_skew_seconds;
+use crate::config::proc_reload::{differs, retained_file_sink_paths, ConfigDiff};
+use crate::config::sinks::{SinkConfig, SinkType};
+use crate::config::sources::ServiceConfig;
+use crate::observability::metrics::{get_metrics, SINK_DISABLED_LABEL};
+use crate::observability::service_resources_metrics::collect_process_metrics;
+use crate::resilience::retry::RetrySettings;
+use crate::resilience::timeout::TimeoutSettings;
+use crate::server;
+use crate::server::server::AppState;
+use crate::sinks::manager::{forward_token_events, SinkManager};
+use crate::sinks::sink_check::CheckSummary;
+use crate::sinks::sink_dry_run::DryRunSummary;
+use crate::sources::builder_in_order::SourceDag;
+use crate::sources::executor::token_fetch::RefreshSettings;
+use crate::sources::tls::build_source_client;
+use crate::utils::{channel, config_loader};
+
+static RELOAD_SUCCESS_MSG: &str = "success";
+static RELOAD_FAILURE_MSG: &str = "failure";
+
+/// Run app, on SIGHUP or config file change load and validate config again and apply the difference:
+/// the server and unchanged sources and sinks keep running, changed settings restart the app.
+/// Cached tokens of surviving sources and their file sink files are kept
+pub async fn run_with_reload(config_path: &str, mut service_config: ServiceConfig, shutdown: CancellationToken) -> Result<()> {
+    let mut sighup = signal(SignalKind::hangup())?;
+    let mut last_modified = config_modified_at(config_path);
+    loop {
+        let generation = shutdown.child_token();
+        let watch_interval = service_config
+            .settings
+            .reload
+            .as_ref()
+            .and_then(|reload| reload.watch_interval_seconds)
+            .map(Duration::from_secs);
+        let mut app = RunningApp::start(&service_config, generation.clone()).await?;
+
+        service_config = loop {
+            select! {
+                res = app.wait() => return res,
+                _ = reload_requested(&mut sighup, config_path, watch_interval, &mut last_modified) => {
+                    info!("Reloading config from '{}'...", config_path);
+                    let metrics = get_metrics().await;
+                    let reloaded = match config_loader::reload(config_path).await {
+                        Ok(reloaded) => {
+                            metrics.config_reloads.with_label_values(&[RELOAD_SUCCESS_MSG]).inc();
+                            reloaded
+                        }
+                        Err(err) => {
+                            error!("config reload aborted, running config is kept: {}", err);
+                            metrics.config_reloads.with_label_values(&[RELOAD_FAILURE_MSG]).inc();
+                            metrics.config_reload_failures.inc();
+                            continue;
+                        }
+                    };
+                    let diff = ConfigDiff::new(&service_config, &reloaded);
+                    if diff.is_empty() {
+                        info!("config reload: no changes");
+                        continue;
+                    }
+                    info!("config reload: {}", diff);
+                    if diff.settings_changed {
+                        // server, request client and retry policy are built from settings, everything restarts
+                        app.retain_files_on_stop(retained_file_sink_paths(&service_config, &reloaded));
+                        generation.cancel();
+                        app.wait().await?;
+                        diff.apply().await;
+                        break reloaded;
+                    }
+                    app.reload(&service_config, &reloaded, &diff).await?;
+                    service_config = reloaded;
+                }
+            }
+        };
+    }
+}
+
+pub async fn run_app(service_config: &ServiceConfig, shutdown: CancellationToken) -> Result<()> {
+    RunningApp::start(service_config, shutdown).await?.wait().await
+}
+
+/// Workers and server of the running config.
+/// Sources share refresh loops, every active sink runs on its own so it can be stopped alone
+struct RunningApp {
+    shutdown: CancellationToken,
+    /// shared by sources without own tls or connect timeout, and by push sinks
+    client: Client,
+    retry: RetrySettings,
+    sink_sender: Sender<TokenEvent>,
+    server_state: AppState,
+    sources: RunningTask,
+    /// sink id -> running active sink, HTTP GET sinks are served by the server
+    sinks: HashMap<String, RunningSink>,
+    tasks: JoinSet<Result<()>>,
+    /// tasks not joined yet
+    running: HashSet<task::Id>,
+}
+
+struct RunningTask {
+    shutdown: CancellationToken,
+    id: task::Id,
+}
+
+struct RunningSink {
+    config: SinkConfig,
+    manager: SinkManager,
+    task: RunningTask,
+}
+
+impl RunningApp {
+    async fn start(service_config: &ServiceConfig, shutdown: CancellationToken) -> Result<Self> {
+        let settings = &service_config.settings;
+        let (dag, client) = prepare_sources(service_config).await?;
+
+        let sink_sender = channel::run();
+        let enabled_sinks = service_config.enabled_sinks();
+        let server_state = server::server::app_state(settings, &service_config.sources, &enabled_sinks, sink_sender.clone()).await;
+        let mut tasks = JoinSet::new();
+        let sources = spawn_sources(&mut tasks, dag, service_config, client.clone(), shutdown.child_token());
+        let mut app = Self {
+            shutdown: shutdown.clone(),
+            client,
+            retry: RetrySettings::from_config(&settings.retry),
+            sink_sender: sink_sender.clone(),
+            server_state: server_state.clone(),
+            running: HashSet::from([sources.id]),
+            sources,
+            sinks: HashMap::new(),
+            tasks,
+        };
+
+        // token cache events are forwarded to sinks, subscribed before sinks replay cached tokens so no change is missed
+        let token_events = TokenCache::subscribe_all().await;
+        app.spawn(forward_token_events(token_events, sink_sender, shutdown.clone()));
+        record_disabled_sinks(service_config).await;
+        for (sink_id, sink_config) in enabled_sinks {
+            app.start_sink(sink_id, sink_config);
+        }
+
+        let server_settings = settings.clone();
+        let server_shutdown = shutdown.clone();
+        app.spawn(async move { server::server::start(&server_settings, server_state, server_shutdown).await });
+        app.spawn(collect_process_metrics(settings.metrics.is_enabled.to_owned(), shutdown));
+        info!("Service starting...");
+        Ok(app)
+    }
+
+    /// Apply config without settings changes: removed and changed sinks stop, source loops restart if any source changed,
+    /// then added and changed sinks start and the server serves reloaded HTTP sinks
+    async fn reload(&mut self, current: &ServiceConfig, reloaded: &ServiceConfig, diff: &ConfigDiff) -> Result<()> {
+        let enabled_sinks = reloaded.enabled_sinks();
+        let stopped_sink_ids: Vec<String> = self
+            .sinks
+            .iter()
+            .filter(|(sink_id, sink)| enabled_sinks.get(*sink_id).is_none_or(|sink_config| differs(&sink.config, sink_config)))
+            .map(|(sink_id, _)| sink_id.to_owned())
+            .collect();
+        let retained_paths = retained_file_sink_paths(current, reloaded);
+        let mut stopped = Vec::new();
+        for sink_id in stopped_sink_ids {
+            let sink = self.sinks.remove(&sink_id).expect("stopped sink is running");
+            info!("config reload: stopping sink '{}'", sink_id);
+            sink.manager.retain_files_on_stop(retained_paths.clone());
+            sink.task.shutdown.cancel();
+            stopped.push(sink.task.id);
+        }
+        let sources_changed = !(diff.added_sources.is_empty() && diff.removed_sources.is_empty() && diff.changed_sources.is_empty());
+        if sources_changed {
+            self.sources.shutdown.cancel();
+            stopped.push(self.sources.id);
+        }
+        self.wait_stopped(stopped).await?;
+
+        diff.apply().await;
+        if sources_changed {
+            info!("config reload: restarting source refresh");
+            let dag = SourceDag::build(&reloaded.sources)?;
+            self.sources = spawn_sources(&mut self.tasks, dag, reloaded, self.client.clone(), self.shutdown.child_token());
+            self.running.insert(self.sources.id);
+        }
+        record_disabled_sinks(reloaded).await;
+        for (sink_id, sink_config) in enabled_sinks {
+            if !self.sinks.contains_key(&sink_id) {
+                self.start_sink(sink_id, sink_config);
+            }
+        }
+        self.server_state.reload(&reloaded.sources, &reloaded.enabled_sinks())
+    }
+
+    /// Start active sink, HTTP GET sinks are served by the server
+    fn start_sink(&mut self, sink_id: String, sink_config: SinkConfig) {
+        if sink_config.sink_type == SinkType::Http && !sink_config.is_http_push() {
+            return;
+        }
+        // push http sinks share request client and settings retry policy
+        let manager = SinkManager::new(HashMap::from([(sink_id.clone(), sink_config.clone())]))
+            .with_http_push(self.client.clone(), self.retry.clone());
+        let shutdown = self.shutdown.child_token();
+        let id = self.spawn({
+            let (manager, sink_sender, shutdown) = (manager.clone(), self.sink_sender.clone(), shutdown.clone());
+            async move { manager.run_sinks(sink_sender, shutdown).await }
+        });
+        self.sinks.insert(sink_id, RunningSink { config: sink_config, manager, task: RunningTask { shutdown, id } });
+    }
+
+    fn spawn(&mut self, task: impl Future<Output = Result<()>> + Send + 'static) -> task::Id {
+        let id = self.tasks.spawn(task).id();
+        self.running.insert(id);
+        id
+    }
+
+    /// Do not remove token files at these paths when the app stops
+    fn retain_files_on_stop(&self, paths: HashSet<String>) {
+        for sink in self.sinks.values() {
+            sink.manager.retain_files_on_stop(paths.clone());
+        }
+    }
+
+    /// Wait until all tasks finish, the first failed task fails the app
+    async fn wait(&mut self) -> Result<()> {
+        while self.join_next().await?.is_some() {}
+        Ok(())
+    }
+
+    /// Wait until tasks `ids` finish, failure of any task fails the app
+    async fn wait_stopped(&mut self, ids: Vec<task::Id>) -> Result<()> {
+        while ids.iter().any(|id| self.running.contains(id)) {
+            if self.join_next().await?.is_none() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    async fn join_next(&mut self) -> Result<Option<task::Id>> {
+        let Some(joined) = self.tasks.join_next_with_id().await else {
+            return Ok(None);
+        };
+        let (id, res) = joined?;
+        self.running.remove(&id);
+        res?;
+        Ok(Some(id))
+    }
+}
+
+/// Spawn token refresh and expiration loops of `dag`
+fn spawn_sources(
+    tasks: &mut JoinSet<Result<()>>,
+    dag: SourceDag,
+    service_config: &ServiceConfig,
+    client: Client,
+    shutdown: CancellationToken,
+) -> RunningTask {
+    let sources = service_config.sources.clone();
+    let safety_margin_seconds = service_config.settings.safety_margin_seconds;
+    let refresh_settings = RefreshSettings::from_config(&service_config.settings);
+    let id = tasks
+        .spawn({
+            let shutdown = shutdown.clone();
+            async move {
+                let receiver = dag.loop_refrech_tokens(&client, &refresh_settings, shutdown.clone());
+                let cleaner = dag.loop_check_token_exp(&sources, &safety_margin_seconds, shutdown);
+                tokio::try_join!(receiver, cleaner)?;
+                Ok(())
+            }
+        })
+        .id();
+    RunningTask { shutdown, id }
+}
+
+/// Sinks of disabled sources are not started, their `sink_propagations_total` series stay at 0 with `enabled=false`
+async fn record_disabled_sinks(service_config: &ServiceConfig) {
+    let metrics = get_metrics().await;
+    let enabled_sinks = service_config.enabled_sinks();
+    for (sink_id, sink_config) in service_config.sinks.iter().filter(|(sink_id, _)| !enabled_sinks.contains_key(*sink_id)) {
+        info!("sink '{}' skipped, source '{}' is disabled", sink_id, sink_config.source_id);
+        metrics.sink_propagations.with_label_values(&[
+            sink_config.sink_id.as_str(),
+            sink_config.metric_sink_type(),
+            sink_config.source_id.as_str(),
+            sink_config.token_id.as_str(),
+            SINK_DISABLED_LABEL,
+        ]);
+    }
+}
+
+/// Resolves on SIGHUP or, if `watch_interval` is set, when config file modification time changes
+async fn reload_requested(
+    sighup: &mut Signal,
+    config_path: &str,
+    watch_interval: Option<Duration>,
+    last_modified: &mut Option<SystemTime>,
+) {
+    let watch = async {
+        let Some(watch_interval) = watch_interval else {
+            return std::future::pending().await;
+        };
+        loop {
+            tokio::time::sleep(watch_interval).await;
+            let modified = config_modified_at(config_path);
+            if modified != *last_modified {
+                info!("config file '{}' changed", config_path);
+                *last_modified = modified;
+                return;
+            }
+        }
+    };
+    select! {
+        _ = sighup.recv() => info!("Received SIGHUP"),
+        _ = watch => {}
+    }
+}
+
+/// Source DAG and shared source client of `service_config`, caches and parser are set up from `settings`
+async fn prepare_sources(service_config: &ServiceConfig) -> Result<(SourceDag, Client)> {
+    let settings = &service_config.settings;
+    let dag = SourceDag::build(&service_config.sources)?;
+    SsmCache::set_ttl_seconds(settings.ssm_cache_ttl_seconds).await;
+    SecretCache::set_ttl_seconds(settings.gcp_secret_cache_ttl_seconds).await;
+    set_clock_skew_seconds(settings.clock_skew_seconds);
+    let client = build_source_client(None, &TimeoutSettings::from_config(&settings.timeouts))?;
+    Ok((dag, client))
+}
+
+fn config_modified_at(config_path: &str) -> Option<SystemTime> {
+    std::fs::metadata(config_path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// Fetch every source once in dependency order and write file sinks, without HTTP server, active sinks and loops;
+/// file sinks are written even if some sources failed, then the first error is returned
+pub async fn run_oneshot(service_config: &ServiceConfig) -> Result<()> {
+    let (dag, client) = prepare_sources(service_config).await?;
+    let fetched = dag.fetch_tokens_once(&client, &RefreshSettings::from_config(&service_config.settings)).await;
+    let written = SinkManager::new(service_config.enabled_sinks()).write_file_sinks_once().await;
+    fetched.and(written)
+}
+
+/// Fetch every source once like `run_oneshot`, sinks only report what they would write
+pub async fn run_dry_run(service_config: &ServiceConfig) -> Result<DryRunSummary> {
+    let (dag, client) = prepare_sources(service_config).await?;
+    let sources = dag.fetch_sources_once(&client, &RefreshSettings::from_config(&service_config.settings)).await?;
+    let sinks = SinkManager::new(service_config.enabled_sinks()).plan_sinks_once().await;
+    Ok(DryRunSummary { sources, sinks })
+}
+
+/// Fetch every source once like `run_oneshot` within `settings.retry.max_delay_ms * attempts`,
+/// then check that every sink has a live token; file sinks must hold it at `path`. Nothing is written
+pub async fn run_check(service_config: &ServiceConfig) -> Result<CheckSummary> {
+    let (dag, client) = prepare_sources(service_config).await?;
+    let refresh_settings = RefreshSettings::from_config(&service_config.settings);
+    let retry = &refresh_settings.retry;
+    let timeout = Duration::from_millis(retry.max_delay_ms.saturating_mul(retry.attempts as u64));
+    let fetched = dag.fetch_sources_once(&client, &refresh_settings);
+    // sources not fetched in time leave their sinks MISSING
+    match tokio::time::timeout(timeout, fetched).await {
+        Ok(outcomes) => {
+            for outcome in outcomes? {
+                if let Some(err) = outcome.error {
+                    warn!("check: source '{}' failed: {}", outcome.source_id, err);
+                }
+            }
+        }
+        Err(_) => warn!("check: sources were not fetched within {:?}", timeout),
+    }
+    let sinks = SinkManager::new(service_config.enabled_sinks()).check_sinks_once().await;
+    Ok(CheckSummary { sinks })
+}
@@ -1,10 +1,10 @@
 use tokio::sync::broadcast::{self, Sender};
 
-use crate::config::sinks::SinkMessage;
+use crate::cache::token_event::TokenEvent;
 
 
-const BUFFER_SIZE: usize = 50;
-pub fn run() -> Sender<SinkMessage> {
+pub(crate) const BUFFER_SIZE: usize = 256;
+pub fn run() -> Sender<TokenEvent> {
     let (sink_sender, _) = broadcast::channel(BUFFER_SIZE);
     sink_sender.clone()
 }
\ No newline at end of file
@@ -2,9 +2,28 @@ use std::{path::Path};
 use anyhow::{anyhow, Result};
 
 use crate::ServiceConfig;
-use crate::config::proc_loader::file_to_config;
+use crate::config::proc_dump::{dump_config, DumpFormat};
+use crate::config::proc_loader::{file_to_checked_config, file_to_config, file_to_validation_errors};
+use crate::config::proc_validator::ValidationIssue;
 
 pub async  fn run(config_path: &str) -> Result<ServiceConfig> {    
     let path = Path::new(config_path);
     file_to_config(path).await.map_err(|e| anyhow!(format!("Invalid config format: {}", e)))
-}
\ No newline at end of file
+}
+
+/// Load config for hot reload: invalid config is an error, running config stays untouched
+pub async fn reload(config_path: &str) -> Result<ServiceConfig> {
+    let path = Path::new(config_path);
+    file_to_checked_config(path).await
+}
+
+/// Validate config without running the service, returns all validation errors
+pub async fn validate(config_path: &str, env_file: Option<&str>) -> Result<Vec<ValidationIssue>> {
+    file_to_validation_errors(Path::new(config_path), env_file.map(Path::new)).await
+}
+
+/// Load config as the service sees it (env vars expanded, defaults injected) and serialize it with secrets redacted
+pub async fn dump(config_path: &str, format: DumpFormat) -> Result<String> {
+    let service_config = file_to_checked_config(Path::new(config_path)).await?;
+    dump_config(&service_config, format)
+}
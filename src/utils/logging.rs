@@ -5,6 +5,9 @@ use tracing_subscriber::{fmt, EnvFilter, layer::SubscriberExt, util::SubscriberI
 use anyhow::Result;
 use crate::ServiceConfig;
 use crate::config::settings::{LogFormat, LoggingConfig};
+use crate::observability::audit::AuditLog;
+use crate::config::settings::OtelConfig;
+use crate::observability::otel;
 
 
 #[derive(Debug, Clone, Copy, ValueEnum)]
@@ -50,18 +53,22 @@ pub async fn run(service_config: &ServiceConfig, arg_log_level: Option<LogLevel>
         }))
         .unwrap();
 
-    init_logging(&logging_config);
+    init_logging(&logging_config, service_config.settings.otel.as_ref());
+    if let Some(audit) = &service_config.settings.audit {
+        AuditLog::init(audit)?;
+    }
     Ok(())
 }
 
 
-/// Initialize tracing with the desired config.
-pub fn init_logging(cfg: &LoggingConfig) {
+/// Initialize tracing with the desired config, spans are exported when `otel` export is configured.
+pub fn init_logging(cfg: &LoggingConfig, otel: Option<&OtelConfig>) {
     let env_filter = EnvFilter::try_new(&cfg.level)
         .unwrap_or_else(|_| EnvFilter::new("debug"));
 
     // Base layer: filter + writer
     let registry = tracing_subscriber::registry().with(env_filter);
+    let registry = registry.with(otel::layer(otel));
 
     // Choose format layer
     match cfg.format {
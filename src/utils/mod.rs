@@ -1,3 +1,4 @@
+pub mod app;
 pub mod channel;
 pub mod config_loader;
-pub mod logging;
\ No newline at end of file
+pub mod logging;pub mod shutdown;
@@ -0,0 +1,40 @@
+use std::future::Future;
+use std::time::Duration;
+
+use anyhow::Result;
+use tokio::select;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+pub const SHUTDOWN_GRACE_PERIOD_SECONDS_DEFAULT: u64 = 10;
+
+/// Cancel shutdown token on SIGINT or SIGTERM
+pub async fn cancel_on_signal(shutdown: CancellationToken) -> Result<()> {
+    let mut sigint = signal(SignalKind::interrupt())?;
+    let mut sigterm = signal(SignalKind::terminate())?;
+    select! {
+        _ = sigint.recv() => info!("Received SIGINT (Ctrl+C). Initiating graceful shutdown..."),
+        _ = sigterm.recv() => info!("Received SIGTERM. Initiating graceful shutdown..."),
+        _ = shutdown.cancelled() => return Ok(()),
+    }
+    shutdown.cancel();
+    Ok(())
+}
+
+/// Run app until it completes; once shutdown is requested it gets `grace_period` to finish
+pub async fn run_with_grace_period<F>(app: F, shutdown: CancellationToken, grace_period: Duration) -> Result<()>
+where
+    F: Future<Output = Result<()>>,
+{
+    select! {
+        res = app => res,
+        _ = async {
+            shutdown.cancelled().await;
+            tokio::time::sleep(grace_period).await;
+        } => {
+            warn!("tasks did not finish within grace period {:?}, exiting", grace_period);
+            Ok(())
+        }
+    }
+}